@@ -0,0 +1,448 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use greatwestern::grammar::{owning_portal, DateRange, Instruction, ProjectMapping};
+use greatwestern::usagereport::Usage;
+use greatwestern::{Hpc, NotificationEvent};
+use templemeads::destination::Destination;
+use templemeads::notification;
+use templemeads::portal_identifier::PortalIdentifier;
+use templemeads::portalroutes;
+use templemeads::Error;
+
+use crate::{get_project_limit, get_projects, get_usage_report};
+
+struct State {
+    /// The path back to each portal, taken from the last job it sent
+    paths: BTreeMap<String, (PortalIdentifier, Destination)>,
+
+    /// The highest threshold (in percent) already notified for each
+    /// project, so that each is only sent once
+    notified: BTreeMap<String, u8>,
+
+    /// Where the paths and notified thresholds are kept between restarts
+    file: Option<PathBuf>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    paths: BTreeMap::new(),
+    notified: BTreeMap::new(),
+    file: None,
+});
+
+///
+/// What is kept in the state file
+///
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct SavedState {
+    #[serde(default)]
+    paths: BTreeMap<String, Destination>,
+
+    #[serde(default)]
+    notified: BTreeMap<String, u8>,
+}
+
+fn load_state(path: &Path) -> Result<SavedState, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| {
+            Error::Parse(format!(
+                "Could not read the limit alerts in {}: {}",
+                path.display(),
+                e
+            ))
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SavedState::default()),
+        Err(e) => Err(Error::IO(e)),
+    }
+}
+
+async fn save_state(path: &Path, saved: &SavedState) -> Result<(), Error> {
+    let json = serde_json::to_string_pretty(saved)
+        .map_err(|e| Error::Parse(format!("Could not serialise the limit alerts: {}", e)))?;
+
+    // write then rename, so a crash never leaves a half-written file
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, json).await?;
+    tokio::fs::rename(&tmp, path).await?;
+
+    Ok(())
+}
+
+///
+/// Write the paths and notified thresholds to the state file, if there is one
+///
+async fn persist() {
+    let (file, saved) = match STATE.lock() {
+        Ok(state) => match &state.file {
+            Some(file) => (
+                file.clone(),
+                SavedState {
+                    paths: state
+                        .paths
+                        .iter()
+                        .map(|(portal, (_, path))| (portal.clone(), path.clone()))
+                        .collect(),
+                    notified: state.notified.clone(),
+                },
+            ),
+            None => return,
+        },
+        Err(e) => {
+            tracing::error!("Could not read the limit alerts: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = save_state(&file, &saved).await {
+        tracing::error!(
+            "Could not save the limit alerts to {}: {}",
+            file.display(),
+            e
+        );
+    }
+}
+
+///
+/// Return the path back to every portal whose projects should be checked -
+/// each portal that this agent has a route from, as configured on the agents
+/// between them, and any other portal that has sent this agent a job
+///
+async fn portal_paths() -> Vec<(PortalIdentifier, Destination)> {
+    let mut paths: BTreeMap<String, (PortalIdentifier, Destination)> = match STATE.lock() {
+        Ok(state) => state.paths.clone(),
+        Err(e) => {
+            tracing::error!("Could not read the paths to the portals: {}", e);
+            BTreeMap::new()
+        }
+    };
+
+    for route in portalroutes::all_routes().await {
+        paths.insert(
+            route.portal().to_string(),
+            (route.portal().clone(), route.route().reverse()),
+        );
+    }
+
+    paths.into_values().collect()
+}
+
+///
+/// Remember the path back to the portal that owns the project or user
+/// in `instruction`, so that alerts for its projects can be sent the
+/// same way. Call this for every job that this agent receives.
+///
+pub fn record_path(instruction: &Instruction, destination: &Destination) {
+    let Some(portal) = owning_portal(instruction) else {
+        return;
+    };
+
+    match STATE.lock() {
+        Ok(mut state) => {
+            state
+                .paths
+                .insert(portal.to_string(), (portal, destination.reverse()));
+        }
+        Err(e) => tracing::error!("Could not record the path to {}: {}", portal, e),
+    }
+}
+
+///
+/// Return the highest of the thresholds (in percent) that `usage` has
+/// reached of `limit`, 100 if the limit is used up, or 0 if none have
+/// been reached or there is no limit
+///
+pub fn reached_threshold(thresholds: &[u8], usage: &Usage, limit: &Usage) -> u8 {
+    if limit.is_zero() {
+        return 0;
+    }
+
+    if usage.seconds() >= limit.seconds() {
+        return 100;
+    }
+
+    let percent = 100.0 * usage.seconds() as f64 / limit.seconds() as f64;
+
+    thresholds
+        .iter()
+        .filter(|t| percent >= **t as f64)
+        .max()
+        .cloned()
+        .unwrap_or(0)
+}
+
+///
+/// Parse the comma-separated list of thresholds, each a percentage
+/// from 1 to 99 (100% is always alerted, as LimitExhausted)
+///
+pub fn parse_thresholds(thresholds: &str) -> Result<Vec<u8>, Error> {
+    let mut parsed = Vec::new();
+
+    for threshold in thresholds.split(',').filter(|t| !t.trim().is_empty()) {
+        match threshold.trim().parse::<u8>() {
+            Ok(t) if (1..100).contains(&t) => parsed.push(t),
+            _ => {
+                return Err(Error::Parse(format!(
+                    "Invalid limit threshold '{}' - this should be a percentage from 1 to 99",
+                    threshold
+                )))
+            }
+        }
+    }
+
+    parsed.sort();
+    parsed.dedup();
+
+    Ok(parsed)
+}
+
+///
+/// Return the threshold to alert for the project, if it has passed a
+/// higher one than was last alerted. A project that drops back below
+/// (e.g. because its limit was raised) can be alerted again.
+///
+fn update_notified(project: &str, reached: u8) -> Option<u8> {
+    let mut state = match STATE.lock() {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::error!("Could not read the limit alerts: {}", e);
+            return None;
+        }
+    };
+
+    let previous = state.notified.get(project).cloned().unwrap_or(0);
+
+    match reached {
+        0 => {
+            state.notified.remove(project);
+            None
+        }
+        reached => {
+            state.notified.insert(project.to_string(), reached);
+
+            match reached > previous {
+                true => Some(reached),
+                false => None,
+            }
+        }
+    }
+}
+
+async fn check_project(
+    me: &str,
+    project: &ProjectMapping,
+    thresholds: &[u8],
+    dates: &DateRange,
+    path: &Destination,
+) -> Result<(), Error> {
+    let limit = get_project_limit(me, project.project()).await?;
+
+    if limit.is_zero() {
+        return Ok(());
+    }
+
+    let usage = get_usage_report(me, project, dates).await?.total_usage();
+
+    let reached = reached_threshold(thresholds, &usage, &limit);
+
+    if let Some(reached) = update_notified(&project.project().to_string(), reached) {
+        let event = match reached {
+            100 => NotificationEvent::LimitExhausted(project.project().clone()),
+            percent => NotificationEvent::LimitThresholdReached(project.project().clone(), percent),
+        };
+
+        tracing::info!(
+            "Project {} has used {} of its limit of {}",
+            project.project(),
+            usage,
+            limit
+        );
+
+        notification::send::<Hpc>(path, event).await;
+    }
+
+    Ok(())
+}
+
+async fn check_all(me: &str, thresholds: &[u8], dates: &str) {
+    let dates = match DateRange::parse(dates) {
+        Ok(dates) => dates,
+        Err(e) => {
+            tracing::error!("Could not parse the limit alert dates '{}': {}", dates, e);
+            return;
+        }
+    };
+
+    for (portal, path) in portal_paths().await {
+        let projects = match get_projects(me, &portal).await {
+            Ok(projects) => projects,
+            Err(e) => {
+                tracing::warn!("Could not get the projects of {}: {}", portal, e);
+                continue;
+            }
+        };
+
+        for project in projects {
+            if let Err(e) = check_project(me, &project, thresholds, &dates, &path).await {
+                tracing::warn!("Could not check the limit of {}: {}", project, e);
+            }
+        }
+    }
+
+    persist().await;
+}
+
+///
+/// Start the task that, every `interval`, compares the usage of every
+/// project in `dates` against its limit, and notifies the project's
+/// portal when it passes one of the `thresholds` or uses up its limit.
+/// Every portal with a route to this agent is checked, as is any other
+/// portal that sends it a job. The thresholds already notified, and the
+/// paths back to the portals, are kept in `state_file` (if given), so that
+/// a restart does not repeat alerts. `me` is this agent's name.
+///
+pub fn enable(
+    me: &str,
+    thresholds: Vec<u8>,
+    dates: &str,
+    interval: Duration,
+    state_file: Option<&Path>,
+) -> Result<(), Error> {
+    if let Some(file) = state_file {
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let saved = load_state(file)?;
+
+        let mut state = STATE
+            .lock()
+            .map_err(|e| Error::Bug(format!("Could not read the limit alerts: {}", e)))?;
+
+        for (portal, path) in saved.paths {
+            match PortalIdentifier::parse(&portal) {
+                Ok(identifier) => {
+                    state.paths.insert(portal, (identifier, path));
+                }
+                Err(e) => tracing::warn!("Ignoring the saved path to '{}': {}", portal, e),
+            }
+        }
+
+        state.notified = saved.notified;
+        state.file = Some(file.to_path_buf());
+
+        tracing::info!("Keeping the limit alerts sent in {}", file.display());
+    }
+
+    let me = me.to_string();
+    let dates = dates.to_string();
+
+    tracing::info!(
+        "Checking project limits every {} seconds, alerting at {:?}% and 100%",
+        interval.as_secs(),
+        thresholds
+    );
+
+    tokio::spawn(async move {
+        // give the account and scheduler agents time to connect first
+        let mut interval =
+            tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+
+        loop {
+            interval.tick().await;
+            check_all(&me, &thresholds, &dates).await;
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reached_threshold() {
+        let thresholds = parse_thresholds("90, 75,75").unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(thresholds, vec![75, 90]);
+
+        let limit = Usage::from_hours(100.0);
+
+        assert_eq!(
+            reached_threshold(&thresholds, &Usage::from_hours(50.0), &limit),
+            0
+        );
+        assert_eq!(
+            reached_threshold(&thresholds, &Usage::from_hours(75.0), &limit),
+            75
+        );
+        assert_eq!(
+            reached_threshold(&thresholds, &Usage::from_hours(95.0), &limit),
+            90
+        );
+        assert_eq!(
+            reached_threshold(&thresholds, &Usage::from_hours(120.0), &limit),
+            100
+        );
+
+        // a project without a limit is never alerted
+        assert_eq!(
+            reached_threshold(&thresholds, &Usage::from_hours(1.0), &Usage::new(0)),
+            0
+        );
+
+        assert!(parse_thresholds("100").is_err());
+        assert!(parse_thresholds("0").is_err());
+        assert!(parse_thresholds("lots").is_err());
+    }
+
+    #[test]
+    fn test_each_threshold_is_notified_once() {
+        let project = "alerted.brics";
+
+        assert_eq!(update_notified(project, 75), Some(75));
+        assert_eq!(update_notified(project, 75), None);
+        assert_eq!(update_notified(project, 100), Some(100));
+
+        // the limit was raised, so the thresholds can be alerted again
+        assert_eq!(update_notified(project, 0), None);
+        assert_eq!(update_notified(project, 75), Some(75));
+    }
+
+    #[tokio::test]
+    async fn test_saved_state_round_trips() {
+        let file =
+            std::env::temp_dir().join(format!("op-limit-alerts-{}.json", std::process::id()));
+
+        // nothing saved yet
+        assert_eq!(
+            load_state(&file).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            SavedState::default()
+        );
+
+        let saved = SavedState {
+            paths: BTreeMap::from([(
+                "brics".to_string(),
+                Destination::parse("cluster.platform.provider.brics")
+                    .unwrap_or_else(|e| unreachable!("{:?}", e)),
+            )]),
+            notified: BTreeMap::from([("proj.brics".to_string(), 90)]),
+        };
+
+        save_state(&file, &saved)
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            load_state(&file).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            saved
+        );
+
+        let _ = std::fs::remove_file(&file);
+    }
+}
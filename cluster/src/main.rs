@@ -5,17 +5,28 @@ use anyhow::Result;
 use std::collections::HashMap;
 
 use greatwestern::grammar::Instruction::{
-    AddProject, AddUser, BlockProject, BlockUser, ClearProjectQuota, ClearUserQuota, GetHomeDir,
-    GetLimit, GetLocalHomeDir, GetLocalProjectDirs, GetLocalUserDirs, GetProjectDirs,
-    GetProjectMapping, GetProjectQuota, GetProjectQuotas, GetProjects, GetStorageReport,
-    GetStorageReports, GetUsageReport, GetUsageReports, GetUserDirs, GetUserMapping, GetUserQuota,
-    GetUserQuotas, GetUsers, IsBlockedProject, IsBlockedUser, IsProtectedUser, RemoveProject,
-    RemoveUser, SetLimit, SetProjectQuota, SetUserQuota, UnblockProject, UnblockUser,
+    AddProject, AddUser, BlockProject, BlockUser, ClearProjectQuota, ClearUserQuota,
+    CreateReservation, DeleteReservation, GetCostReport, GetHomeDir, GetJobRecords, GetLimit,
+    GetLocalHomeDir, GetLocalProjectDirs, GetLocalRecycledProjectDirs, GetLocalRecycledUserDirs,
+    GetLocalUserDirs, GetMemberLimits, GetProjectDirs, GetProjectMapping, GetProjectQuota,
+    GetProjectQuotas, GetProjects, GetRecycledProjectDirs, GetRecycledUserDirs, GetReservations,
+    GetSchedulingPolicy, GetStorageReport, GetStorageReports, GetUsageReport, GetUsageReports,
+    GetUserDirs, GetUserLimit, GetUserMapping, GetUserQuota, GetUserQuotas, GetUsers,
+    IsBlockedProject, IsBlockedUser, IsProtectedUser, PurgeLocalRecycledProjectDirs,
+    PurgeLocalRecycledUserDirs, PurgeRecycledProjectDirs, PurgeRecycledUserDirs, RemoveProject,
+    RemoveUser, RestoreLocalProjectDir, RestoreLocalUserDir, RestoreProjectDir, RestoreUserDir,
+    SetLimit, SetProjectQuota, SetProjectTemplate, SetSchedulingPolicy, SetUserLimit,
+    SetUserProfile, SetUserQuota, SetUserRole, UnblockProject, UnblockUser, UpdateReservation,
+    UpdateUserProfile,
 };
 use greatwestern::grammar::{
-    DateRange, ProjectIdentifier, ProjectMapping, UserIdentifier, UserMapping,
+    DateRange, Instruction, ProjectIdentifier, ProjectMapping, UserIdentifier, UserMapping,
+    UserProfile,
 };
-use greatwestern::storage::{Quota, Volume};
+use greatwestern::jobrecords::{JobRecords, Page};
+use greatwestern::pricing::{ProjectCostReport, RateCard};
+use greatwestern::scheduling::{ProjectLimit, Reservation, SchedulingPolicy, UserLimit};
+use greatwestern::storage::{Quota, RecycledDir, Volume};
 use greatwestern::storagereport::{ProjectStorageReport, StorageReport};
 use greatwestern::usagereport::{ProjectUsageReport, Usage, UsageReport};
 use greatwestern::{Hpc, NotificationEvent};
@@ -31,8 +42,16 @@ use templemeads::Error;
 type Envelope = templemeads::job::Envelope<Hpc>;
 type Job = templemeads::job::Job<Hpc>;
 
+mod limitalerts;
+
 const AGENT_WAIT_TIME: u64 = 10;
 
+///
+/// The rates used to cost usage, and the node type that usage not broken
+/// down by partition is costed as. This is None if no rates file is configured.
+///
+static RATES: std::sync::RwLock<Option<(RateCard, Option<String>)>> = std::sync::RwLock::new(None);
+
 ///
 /// Main function for the cluster instance agent
 ///
@@ -77,6 +96,76 @@ async fn main() -> Result<()> {
         }
     };
 
+    // get the (optional) rates file used to turn usage into costs
+    let rates_file = config.option("rates-file", "");
+
+    if !rates_file.is_empty() {
+        let rates = RateCard::load(std::path::Path::new(&rates_file))?;
+        let rates_node = config.option("rates-node", "");
+
+        let rates_node = match rates_node.trim().is_empty() {
+            true => None,
+            false => Some(rates_node.trim().to_string()),
+        };
+
+        match RATES.write() {
+            Ok(mut guard) => *guard = Some((rates, rates_node)),
+            Err(e) => {
+                return Err(anyhow::anyhow!(format!(
+                    "Could not store the rates from {}: {}",
+                    rates_file, e
+                )));
+            }
+        }
+
+        tracing::info!("Costing usage with the rates in {}", rates_file);
+    }
+
+    // get how often (in seconds) to check each project's usage against its
+    // limit, alerting its portal at each of the limit-thresholds (0 disables)
+    let limit_check_interval: u64 = match config.option("limit-check-interval", "3600").parse() {
+        Ok(interval) => interval,
+        Err(_) => {
+            return Err(anyhow::anyhow!(
+                "Invalid limit check interval provided. This should be a number of seconds."
+                    .to_owned(),
+            ));
+        }
+    };
+
+    if limit_check_interval > 0 {
+        let thresholds =
+            limitalerts::parse_thresholds(&config.option("limit-thresholds", "75,90"))?;
+        let limit_dates = config.option("limit-dates", "this_year");
+
+        // check now, rather than on the first check, that the dates parse
+        DateRange::parse(&limit_dates)?;
+
+        // where the limit alerts already sent are kept, so that a restart
+        // does not send them again (an empty string keeps them in memory)
+        let limit_state_file = config.option(
+            "limit-state-file",
+            &dirs::data_local_dir()
+                .unwrap_or(".".into())
+                .join("openportal")
+                .join("cluster-limit-alerts.json")
+                .to_string_lossy(),
+        );
+
+        let limit_state_file = match limit_state_file.trim().is_empty() {
+            true => None,
+            false => Some(std::path::PathBuf::from(limit_state_file.trim())),
+        };
+
+        limitalerts::enable(
+            &config.service().name(),
+            thresholds,
+            &limit_dates,
+            std::time::Duration::from_secs(limit_check_interval),
+            limit_state_file.as_deref(),
+        )?;
+    }
+
     async_runnable! {
         ///
         /// Runnable function that will be called when a job is received
@@ -87,6 +176,9 @@ async fn main() -> Result<()> {
             let me = envelope.recipient();
             let job = envelope.job();
 
+            // remember the way back to the portal, to send it limit alerts
+            limitalerts::record_path(&job.instruction(), &job.destination());
+
             match job.instruction() {
                 GetProjects(portal) => {
                     // get the list of projects from the cluster
@@ -293,6 +385,16 @@ async fn main() -> Result<()> {
                     let report = get_usage_reports(me.name(), &portal, &dates).await?;
                     job.completed(report)
                 }
+                GetCostReport(project, dates) => {
+                    let mapping = get_project_mapping(me.name(), &project).await?;
+                    let report = get_cost_report(me.name(), &mapping, &dates).await?;
+                    job.completed(report)
+                }
+                GetJobRecords(project, dates, page) => {
+                    let mapping = get_project_mapping(me.name(), &project).await?;
+                    let records = get_job_records(me.name(), &mapping, &dates, &page).await?;
+                    job.completed(records)
+                }
                 GetStorageReport(project, dates) => {
                     let report = get_storage_report(me.name(), &project, &dates).await?;
                     job.completed(report)
@@ -305,10 +407,54 @@ async fn main() -> Result<()> {
                     let limit = get_project_limit(me.name(), &project).await?;
                     job.completed(limit)
                 }
+                GetMemberLimits(project) => {
+                    let mut limits = get_member_limits(me.name(), &project).await?;
+
+                    // show the users with their own limits by their identifiers
+                    limits.add_mappings(&get_accounts(me.name(), &project).await?);
+
+                    job.completed(limits)
+                }
+                GetUserLimit(user) => {
+                    let limit = user_limit(me.name(), &user, "get_local_user_limit", None).await?;
+                    job.completed(limit)
+                }
+                SetUserLimit(user, limit) => {
+                    let limit = user_limit(me.name(), &user, "set_local_user_limit", Some(limit.to_string())).await?;
+                    job.completed(limit)
+                }
                 SetLimit(project, limit) => {
                     let limit = set_project_limit(me.name(), &project, limit).await?;
                     job.completed(limit)
                 }
+                GetSchedulingPolicy(project) => {
+                    let policy = scheduling_policy(me.name(), &project, "get_local_scheduling_policy", None).await?;
+                    job.completed(policy)
+                }
+                SetSchedulingPolicy(project, policy) => {
+                    let policy = scheduling_policy(me.name(), &project, "set_local_scheduling_policy", Some(policy.to_string())).await?;
+                    job.completed(policy)
+                }
+                SetProjectTemplate(project, template) => {
+                    let policy = scheduling_policy(me.name(), &project, "set_local_project_template", Some(template.to_string())).await?;
+                    job.completed(policy)
+                }
+                GetReservations(project) => {
+                    let reservations = get_reservations(me.name(), &project).await?;
+                    job.completed(reservations)
+                }
+                CreateReservation(project, details) => {
+                    let details = reservation(me.name(), &project, "create_local_reservation", &details.to_string()).await?;
+                    job.completed(details)
+                }
+                UpdateReservation(project, details) => {
+                    let details = reservation(me.name(), &project, "update_local_reservation", &details.to_string()).await?;
+                    job.completed(details)
+                }
+                DeleteReservation(project, name) => {
+                    let details = reservation(me.name(), &project, "delete_local_reservation", &name).await?;
+                    job.completed(details)
+                }
                 GetProjectQuota(project, volume) => {
                     let quota = get_project_quota(me.name(), &project, &volume).await?;
                     job.completed(quota)
@@ -368,6 +514,58 @@ async fn main() -> Result<()> {
                     let dirs = get_user_dirs(me.name(), &mapping).await?;
                     job.completed(dirs)
                 }
+                GetRecycledUserDirs(user) => {
+                    let mapping = get_user_mapping(me.name(), &user).await?;
+                    let recycled = get_recycled_dirs(me.name(), &GetLocalRecycledUserDirs(mapping)).await?;
+                    job.completed(recycled)
+                }
+                GetRecycledProjectDirs(project) => {
+                    let mapping = get_project_mapping(me.name(), &project).await?;
+                    let recycled = get_recycled_dirs(me.name(), &GetLocalRecycledProjectDirs(mapping)).await?;
+                    job.completed(recycled)
+                }
+                RestoreUserDir(user, volume, version) => {
+                    let mapping = get_user_mapping(me.name(), &user).await?;
+                    let restored = restore_recycled_dirs(me.name(), &RestoreLocalUserDir(mapping, volume, version)).await?;
+                    job.completed(restored)
+                }
+                RestoreProjectDir(project, volume, version) => {
+                    let mapping = get_project_mapping(me.name(), &project).await?;
+                    let restored = restore_recycled_dirs(me.name(), &RestoreLocalProjectDir(mapping, volume, version)).await?;
+                    job.completed(restored)
+                }
+                PurgeRecycledUserDirs(user) => {
+                    let mapping = get_user_mapping(me.name(), &user).await?;
+                    let purged = get_recycled_dirs(me.name(), &PurgeLocalRecycledUserDirs(mapping)).await?;
+                    job.completed(purged)
+                }
+                PurgeRecycledProjectDirs(project) => {
+                    let mapping = get_project_mapping(me.name(), &project).await?;
+                    let purged = get_recycled_dirs(me.name(), &PurgeLocalRecycledProjectDirs(mapping)).await?;
+                    job.completed(purged)
+                }
+                GetLocalRecycledUserDirs(_) | GetLocalRecycledProjectDirs(_)
+                | PurgeLocalRecycledUserDirs(_) | PurgeLocalRecycledProjectDirs(_) => {
+                    let recycled = get_recycled_dirs(me.name(), &job.instruction()).await?;
+                    job.completed(recycled)
+                }
+                RestoreLocalUserDir(_, _, _) | RestoreLocalProjectDir(_, _, _) => {
+                    let restored = restore_recycled_dirs(me.name(), &job.instruction()).await?;
+                    job.completed(restored)
+                }
+                SetUserProfile(user, profile) => {
+                    let profile = set_user_profile(me.name(), &user, &profile, "set_user_profile").await?;
+                    job.completed(profile)
+                }
+                UpdateUserProfile(user, profile) => {
+                    let profile = set_user_profile(me.name(), &user, &profile, "update_user_profile").await?;
+                    job.completed(profile)
+                }
+                SetUserRole(user, role) => {
+                    assert_agents_connected().await?;
+                    let mapping = set_user_role(me.name(), &user, &role).await?;
+                    job.completed(mapping)
+                }
                 _ => {
                     tracing::error!("Unknown instruction: {:?}", job.instruction());
                     Err(Error::UnknownInstruction(
@@ -982,6 +1180,125 @@ async fn delete_user_directories(me: &str, mapping: &UserMapping) -> Result<(),
     }
 }
 
+///
+/// Pass a `set_user_profile` or `update_user_profile` (the `command`) for
+/// the user on to the account agent, which writes the profile into the
+/// user's account and returns it
+///
+async fn set_user_profile(
+    me: &str,
+    user: &UserIdentifier,
+    profile: &UserProfile,
+    command: &str,
+) -> Result<UserProfile, Error> {
+    match agent::account(AGENT_WAIT_TIME).await {
+        Some(account) => {
+            let job = Job::parse(
+                &format!("{}.{} {} {} {}", me, account.name(), command, user, profile),
+                false,
+            )?
+            .put(&account)
+            .await?;
+
+            let result = job.wait().await?.result::<UserProfile>()?;
+
+            match result {
+                Some(profile) => {
+                    tracing::info!("User {} profile updated", user);
+                    Ok(profile)
+                }
+                None => {
+                    tracing::error!("Error updating the user's profile: {:?}", job);
+                    Err(Error::Call(format!(
+                        "Error updating the user's profile: {:?}",
+                        job
+                    )))
+                }
+            }
+        }
+        None => {
+            tracing::error!("No account agent found");
+            Err(Error::MissingAgent(
+                "Cannot run the job because there is no account agent".to_string(),
+            ))
+        }
+    }
+}
+
+///
+/// Set the role of the user within their project. The account agent
+/// puts the user into (or takes them out of) their project's managers
+/// group, and the scheduler makes them a coordinator of (or removes
+/// them as a coordinator from) their project's account
+///
+async fn set_user_role(me: &str, user: &UserIdentifier, role: &str) -> Result<UserMapping, Error> {
+    let mapping = match agent::account(AGENT_WAIT_TIME).await {
+        Some(account) => {
+            let job = Job::parse(
+                &format!("{}.{} set_user_role {} {}", me, account.name(), user, role),
+                false,
+            )?
+            .put(&account)
+            .await?;
+
+            let result = job.wait().await?.result::<UserMapping>()?;
+
+            match result {
+                Some(mapping) => mapping,
+                None => {
+                    tracing::error!("Error setting the user's role: {:?}", job);
+                    return Err(Error::Call(format!(
+                        "Error setting the user's role: {:?}",
+                        job
+                    )));
+                }
+            }
+        }
+        None => {
+            tracing::error!("No account agent found");
+            return Err(Error::MissingAgent(
+                "Cannot run the job because there is no account agent".to_string(),
+            ));
+        }
+    };
+
+    match agent::scheduler(AGENT_WAIT_TIME).await {
+        Some(scheduler) => {
+            let job = Job::parse(
+                &format!(
+                    "{}.{} set_local_user_role {} {}",
+                    me,
+                    scheduler.name(),
+                    mapping,
+                    role
+                ),
+                false,
+            )?
+            .put(&scheduler)
+            .await?;
+
+            job.wait().await?;
+
+            if job.is_error() {
+                tracing::error!("Error setting the user's role in the scheduler: {:?}", job);
+                Err(Error::Call(format!(
+                    "Error setting the user's role in the scheduler: {:?}",
+                    job
+                )))
+            } else {
+                tracing::info!("User {} role set to '{}'", user, role);
+                Ok(mapping)
+            }
+        }
+        None => {
+            tracing::error!("No scheduler agent found");
+            Err(Error::MissingAgent(
+                "Cannot run the job because there is no scheduler agent".to_string(),
+            ))
+        }
+    }
+}
+
 async fn update_homedir(me: &str, user: &UserIdentifier, homedir: &str) -> Result<String, Error> {
     // find the Account agent
     match agent::account(AGENT_WAIT_TIME).await {
@@ -1224,6 +1541,76 @@ async fn get_usage_report(
     Ok(report)
 }
 
+///
+/// Get the cost of the usage of the project, using the configured rates.
+/// Usage on each partition is charged at the rates for that partition.
+///
+async fn get_cost_report(
+    me: &str,
+    project: &ProjectMapping,
+    dates: &DateRange,
+) -> Result<ProjectCostReport, Error> {
+    // copy the rates so that the lock is not held while waiting for the report
+    let rates = match RATES.read() {
+        Ok(guard) => guard.clone(),
+        Err(e) => return Err(Error::Bug(format!("Could not read the rates: {}", e))),
+    };
+
+    let (rates, node) = rates.ok_or_else(|| {
+        Error::Misconfigured(
+            "Cannot cost usage because no rates-file has been configured".to_string(),
+        )
+    })?;
+
+    let report = get_usage_report(me, project, dates).await?;
+
+    Ok(rates.cost(&report, node.as_deref()))
+}
+
+///
+/// Get a page of the records of the jobs of the project from the
+/// scheduler, with the users of the jobs mapped to their identifiers
+///
+async fn get_job_records(
+    me: &str,
+    project: &ProjectMapping,
+    dates: &DateRange,
+    page: &Page,
+) -> Result<JobRecords, Error> {
+    let scheduler = match agent::scheduler(AGENT_WAIT_TIME).await {
+        Some(scheduler) => scheduler,
+        None => {
+            tracing::error!("No scheduler agent found");
+            return Err(Error::MissingAgent(
+                "Cannot run the job because there is no scheduler agent".to_string(),
+            ));
+        }
+    };
+
+    let job = Job::parse(
+        &format!(
+            "{}.{} get_local_job_records {} {} {}",
+            me,
+            scheduler.name(),
+            project,
+            dates,
+            page
+        ),
+        false,
+    )?
+    .put(&scheduler)
+    .await?;
+
+    let mut records = match job.wait().await?.result::<JobRecords>()? {
+        Some(records) => records,
+        None => JobRecords::paginate(project.project(), dates, page, Vec::new()),
+    };
+
+    records.add_mappings(&get_accounts(me, project.project()).await?);
+
+    Ok(records)
+}
+
 async fn get_usage_reports(
     me: &str,
     portal: &PortalIdentifier,
@@ -1328,6 +1715,91 @@ async fn get_project_limit(me: &str, project: &ProjectIdentifier) -> Result<Usag
     Ok(limit)
 }
 
+///
+/// Return the limit of the project, together with the limits of those
+/// of its members that have their own, keyed by their local usernames
+///
+async fn get_member_limits(me: &str, project: &ProjectIdentifier) -> Result<ProjectLimit, Error> {
+    // get the mapping for this project
+    let mapping = get_project_mapping(me, project).await?;
+
+    // find the scheduler agent
+    let scheduler = match agent::scheduler(AGENT_WAIT_TIME).await {
+        Some(scheduler) => scheduler,
+        None => {
+            tracing::error!("No scheduler agent found");
+            return Err(Error::MissingAgent(
+                "Cannot run the job because there is no scheduler agent".to_string(),
+            ));
+        }
+    };
+
+    let job = Job::parse(
+        &format!(
+            "{}.{} get_local_member_limits {}",
+            me,
+            scheduler.name(),
+            mapping
+        ),
+        false,
+    )?;
+
+    let job = job.put(&scheduler).await?;
+
+    let limits = match job.wait().await?.result::<ProjectLimit>()? {
+        Some(limits) => limits,
+        None => ProjectLimit::new(&Usage::new(0)),
+    };
+
+    Ok(limits)
+}
+
+///
+/// Send the passed `command` (either `get_local_user_limit` or
+/// `set_local_user_limit`), with its optional `argument`, for the user
+/// on to the scheduler, returning the limits that the user now has
+///
+async fn user_limit(
+    me: &str,
+    user: &UserIdentifier,
+    command: &str,
+    argument: Option<String>,
+) -> Result<UserLimit, Error> {
+    // get the mapping for this user
+    let mapping = get_user_mapping(me, user).await?;
+
+    // find the scheduler agent
+    let scheduler = match agent::scheduler(AGENT_WAIT_TIME).await {
+        Some(scheduler) => scheduler,
+        None => {
+            tracing::error!("No scheduler agent found");
+            return Err(Error::MissingAgent(
+                "Cannot run the job because there is no scheduler agent".to_string(),
+            ));
+        }
+    };
+
+    let instruction = match argument {
+        Some(argument) => format!("{} {} {}", command, mapping, argument),
+        None => format!("{} {}", command, mapping),
+    };
+
+    let job = Job::parse(
+        &format!("{}.{} {}", me, scheduler.name(), instruction),
+        false,
+    )?;
+
+    let job = job.put(&scheduler).await?;
+
+    match job.wait().await?.result::<UserLimit>()? {
+        Some(limit) => Ok(limit),
+        None => {
+            tracing::error!("No limit returned for user {}", user);
+            Err(Error::Call(format!("No limit returned for user {}", user)))
+        }
+    }
+}
+
 pub async fn set_project_limit(
     me: &str,
     project: &ProjectIdentifier,
@@ -1370,6 +1842,145 @@ pub async fn set_project_limit(
     Ok(limit)
 }
 
+///
+/// Send the passed `command` (one of `get_local_scheduling_policy`,
+/// `set_local_scheduling_policy` or `set_local_project_template`), with
+/// its optional `argument`, for the project on to the scheduler, returning
+/// the scheduling policy that the project's account now has
+///
+async fn scheduling_policy(
+    me: &str,
+    project: &ProjectIdentifier,
+    command: &str,
+    argument: Option<String>,
+) -> Result<SchedulingPolicy, Error> {
+    // get the mapping for this project
+    let mapping = get_project_mapping(me, project).await?;
+
+    // find the scheduler agent
+    let scheduler = match agent::scheduler(AGENT_WAIT_TIME).await {
+        Some(scheduler) => scheduler,
+        None => {
+            tracing::error!("No scheduler agent found");
+            return Err(Error::MissingAgent(
+                "Cannot run the job because there is no scheduler agent".to_string(),
+            ));
+        }
+    };
+
+    let instruction = match argument {
+        Some(argument) => format!("{} {} {}", command, mapping, argument),
+        None => format!("{} {}", command, mapping),
+    };
+
+    let job = Job::parse(
+        &format!("{}.{} {}", me, scheduler.name(), instruction),
+        false,
+    )?;
+
+    let job = job.put(&scheduler).await?;
+
+    match job.wait().await?.result::<SchedulingPolicy>()? {
+        Some(policy) => Ok(policy),
+        None => {
+            tracing::error!("No scheduling policy returned for project {}", project);
+            Err(Error::Call(format!(
+                "No scheduling policy returned for project {}",
+                project
+            )))
+        }
+    }
+}
+
+///
+/// Send the passed reservation `command` (one of `create_local_reservation`,
+/// `update_local_reservation` or `delete_local_reservation`), with its
+/// `argument`, for the project on to the scheduler, returning the
+/// reservation that was created, updated or deleted
+///
+async fn reservation(
+    me: &str,
+    project: &ProjectIdentifier,
+    command: &str,
+    argument: &str,
+) -> Result<Reservation, Error> {
+    // get the mapping for this project
+    let mapping = get_project_mapping(me, project).await?;
+
+    // find the scheduler agent
+    let scheduler = match agent::scheduler(AGENT_WAIT_TIME).await {
+        Some(scheduler) => scheduler,
+        None => {
+            tracing::error!("No scheduler agent found");
+            return Err(Error::MissingAgent(
+                "Cannot run the job because there is no scheduler agent".to_string(),
+            ));
+        }
+    };
+
+    let job = Job::parse(
+        &format!(
+            "{}.{} {} {} {}",
+            me,
+            scheduler.name(),
+            command,
+            mapping,
+            argument
+        ),
+        false,
+    )?;
+
+    let job = job.put(&scheduler).await?;
+
+    match job.wait().await?.result::<Reservation>()? {
+        Some(reservation) => Ok(reservation),
+        None => {
+            tracing::error!("No reservation returned for project {}", project);
+            Err(Error::Call(format!(
+                "No reservation returned for project {}",
+                project
+            )))
+        }
+    }
+}
+
+async fn get_reservations(
+    me: &str,
+    project: &ProjectIdentifier,
+) -> Result<Vec<Reservation>, Error> {
+    // get the mapping for this project
+    let mapping = get_project_mapping(me, project).await?;
+
+    // find the scheduler agent
+    let scheduler = match agent::scheduler(AGENT_WAIT_TIME).await {
+        Some(scheduler) => scheduler,
+        None => {
+            tracing::error!("No scheduler agent found");
+            return Err(Error::MissingAgent(
+                "Cannot run the job because there is no scheduler agent".to_string(),
+            ));
+        }
+    };
+
+    let job = Job::parse(
+        &format!(
+            "{}.{} get_local_reservations {}",
+            me,
+            scheduler.name(),
+            mapping
+        ),
+        false,
+    )?;
+
+    let job = job.put(&scheduler).await?;
+
+    Ok(job
+        .wait()
+        .await?
+        .result::<Vec<Reservation>>()?
+        .unwrap_or_default())
+}
+
 async fn clear_project_quota(
     me: &str,
     project: &ProjectIdentifier,
@@ -2121,6 +2732,90 @@ async fn get_project_dirs(me: &str, mapping: &ProjectMapping) -> Result<Vec<Stri
     }
 }
 
+///
+/// Send an instruction that lists or purges recycled directories to the
+/// filesystem agent, returning the recycled directories it listed or purged
+///
+async fn get_recycled_dirs(me: &str, instruction: &Instruction) -> Result<Vec<RecycledDir>, Error> {
+    // find the Filesystem agent
+    match agent::filesystem(AGENT_WAIT_TIME).await {
+        Some(filesystem) => {
+            // send the job to the filesystem agent
+            let job = Job::parse(
+                &format!("{}.{} {}", me, filesystem.name(), instruction),
+                false,
+            )?
+            .put(&filesystem)
+            .await?;
+
+            // Wait for the job to complete
+            let result = job.wait().await?.result::<Vec<RecycledDir>>()?;
+
+            match result {
+                Some(recycled) => {
+                    tracing::debug!("Recycled directories: {:?}", recycled);
+                    Ok(recycled)
+                }
+                None => {
+                    tracing::error!("No recycled directories returned?");
+                    Err(Error::Call(format!(
+                        "No recycled directories were returned for '{}'",
+                        instruction
+                    )))
+                }
+            }
+        }
+        None => {
+            tracing::error!("No filesystem agent found");
+            Err(Error::MissingAgent(
+                "Cannot run the job because there is no filesystem agent".to_string(),
+            ))
+        }
+    }
+}
+
+///
+/// Send an instruction that restores recycled directories to the
+/// filesystem agent, returning the paths of the restored directories
+///
+async fn restore_recycled_dirs(me: &str, instruction: &Instruction) -> Result<Vec<String>, Error> {
+    // find the Filesystem agent
+    match agent::filesystem(AGENT_WAIT_TIME).await {
+        Some(filesystem) => {
+            // send the job to the filesystem agent
+            let job = Job::parse(
+                &format!("{}.{} {}", me, filesystem.name(), instruction),
+                false,
+            )?
+            .put(&filesystem)
+            .await?;
+
+            // Wait for the job to complete
+            let result = job.wait().await?.result::<Vec<String>>()?;
+
+            match result {
+                Some(restored) => {
+                    tracing::info!("Restored directories: {:?}", restored);
+                    Ok(restored)
+                }
+                None => {
+                    tracing::error!("No restored directories returned?");
+                    Err(Error::Call(format!(
+                        "No restored directories were returned for '{}'",
+                        instruction
+                    )))
+                }
+            }
+        }
+        None => {
+            tracing::error!("No filesystem agent found");
+            Err(Error::MissingAgent(
+                "Cannot run the job because there is no filesystem agent".to_string(),
+            ))
+        }
+    }
+}
+
 async fn get_user_dirs(me: &str, mapping: &UserMapping) -> Result<Vec<String>, Error> {
     // find the Filesystem agent
     match agent::filesystem(AGENT_WAIT_TIME).await {
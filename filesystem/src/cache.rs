@@ -77,13 +77,13 @@ pub async fn set_filesystem_config(mut config: FilesystemConfig) -> Result<(), E
             tracing::info!("    - Quota engine: {}", engine_name);
             tracing::info!(
                 "    - Max quota: {}",
-                volume_config.max_quota().unwrap_or(&QuotaLimit::Unlimited)
+                volume_config.max_quota().unwrap_or(&QuotaLimit::UNLIMITED)
             );
             tracing::info!(
                 "    - Default quota: {}",
                 volume_config
                     .default_quota()
-                    .unwrap_or(&QuotaLimit::Unlimited)
+                    .unwrap_or(&QuotaLimit::UNLIMITED)
             )
         };
     }
@@ -106,13 +106,13 @@ pub async fn set_filesystem_config(mut config: FilesystemConfig) -> Result<(), E
             tracing::info!("    - Quota engine: {}", engine_name);
             tracing::info!(
                 "    - Max quota: {}",
-                volume_config.max_quota().unwrap_or(&QuotaLimit::Unlimited)
+                volume_config.max_quota().unwrap_or(&QuotaLimit::UNLIMITED)
             );
             tracing::info!(
                 "    - Default quota: {}",
                 volume_config
                     .default_quota()
-                    .unwrap_or(&QuotaLimit::Unlimited)
+                    .unwrap_or(&QuotaLimit::UNLIMITED)
             );
         };
     }
@@ -0,0 +1,423 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! Concrete implementation of the CephFS directory quota engine.
+//!
+//! CephFS quotas are set on directories rather than on users or groups,
+//! by writing the `ceph.quota.max_bytes` and `ceph.quota.max_files`
+//! extended attributes, and the recursive usage of a directory is read
+//! from its `ceph.dir.rbytes` and `ceph.dir.rfiles` attributes. This
+//! engine applies a project's (or user's) quota to the directory that
+//! the volume's first `PathConfig` creates for it.
+//!
+//! The attributes are read and written through `filesystem::get_xattr`
+//! and `filesystem::set_xattr`, so the paths go through the same
+//! `clean_and_check_path` checks as every other operation of the agent,
+//! and the exec prefix (if set) is honoured.
+//!
+//! # TOML configuration example
+//!
+//! ```toml
+//! [quota_engines.cephfs]
+//! type = "cephfs"
+//! ```
+
+use anyhow::Result;
+use chrono::Utc;
+use greatwestern::grammar::{ProjectMapping, UserMapping, UserOrProjectMapping};
+use greatwestern::storage::{Quota, QuotaLimit, StorageSize, StorageUsage, Volume};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use templemeads::job::assert_not_expired;
+use templemeads::Error;
+
+use crate::filesystem;
+use crate::volumeconfig::{PathConfig, ProjectVolumeConfig, UserVolumeConfig};
+
+const MAX_BYTES: &str = "ceph.quota.max_bytes";
+const MAX_FILES: &str = "ceph.quota.max_files";
+const RBYTES: &str = "ceph.dir.rbytes";
+const RFILES: &str = "ceph.dir.rfiles";
+
+/// Configuration for the CephFS quota engine. The quotas live on the
+/// volume directories themselves, so nothing else is needed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CephfsEngineConfig {}
+
+/// Parse the value of a numeric CephFS attribute. An attribute that is
+/// not set counts as 0, which for the quota attributes means unlimited
+fn parse_xattr(name: &str, value: Option<&str>) -> Result<u64, Error> {
+    match value.map(|v| v.trim()) {
+        None | Some("") => Ok(0),
+        Some(value) => value.parse::<u64>().map_err(|e| {
+            Error::Parse(format!(
+                "Failed to parse the '{}' attribute ('{}'): {}",
+                name, value, e
+            ))
+        }),
+    }
+}
+
+/// Build a quota from the values of the CephFS attributes. A
+/// `max_bytes` of 0 is unlimited, as is a `max_files` of 0 (as it is
+/// for `QuotaLimit`)
+fn quota_from_xattrs(max_bytes: u64, max_files: u64, rbytes: u64, rfiles: u64) -> Quota {
+    let limit = if max_bytes == 0 {
+        QuotaLimit::unlimited()
+    } else {
+        QuotaLimit::limited(StorageSize::from_bytes(max_bytes))
+    };
+
+    let mut quota = Quota::with_usage(
+        limit.with_inodes(max_files),
+        StorageUsage::new(StorageSize::from_bytes(rbytes)),
+    );
+
+    quota.set_inode_usage(rfiles);
+
+    quota
+}
+
+/// Return the values of `ceph.quota.max_bytes` and `ceph.quota.max_files`
+/// for a limit. The inode limit is the one requested, else
+/// `default_inode_limit`, and 0 means unlimited
+fn limit_to_xattrs(limit: &QuotaLimit, default_inode_limit: Option<u64>) -> (u64, u64) {
+    (
+        limit.size().map(|size| size.as_bytes()).unwrap_or(0),
+        limit.inodes().or(default_inode_limit).unwrap_or(0),
+    )
+}
+
+/// CephFS quota engine implementation.
+pub struct CephfsEngine {
+    _config: CephfsEngineConfig,
+}
+
+impl CephfsEngine {
+    /// Create a new CephFS quota engine with the given configuration
+    pub fn new(config: CephfsEngineConfig) -> Result<Self, Error> {
+        Ok(Self { _config: config })
+    }
+
+    /// Initialise the engine
+    pub async fn initialize(&self) -> Result<(), Error> {
+        tracing::info!("CephfsEngine initialized");
+        Ok(())
+    }
+
+    /// Return the directory that holds the quota for `mapping`, which is
+    /// the one created by the volume's first `PathConfig`, together with
+    /// the root that it must lie within
+    fn quota_dir(
+        path_configs: &[PathConfig],
+        mapping: UserOrProjectMapping,
+        volume: &Volume,
+    ) -> Result<(PathBuf, Vec<PathBuf>), Error> {
+        let path_config = path_configs.first().ok_or_else(|| {
+            Error::Misconfigured(format!("Volume '{}' has no paths configured", volume))
+        })?;
+
+        Ok((
+            path_config.path(mapping)?,
+            vec![PathBuf::from(path_config.root())],
+        ))
+    }
+
+    /// Write the quota attributes of a directory
+    async fn write_quota(
+        &self,
+        dir: &Path,
+        roots: &[PathBuf],
+        limit: &QuotaLimit,
+        default_inode_limit: Option<u64>,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let (max_bytes, max_files) = limit_to_xattrs(limit, default_inode_limit);
+
+        assert_not_expired(expires)?;
+        filesystem::set_xattr(dir, roots, MAX_BYTES, &max_bytes.to_string()).await?;
+
+        assert_not_expired(expires)?;
+        filesystem::set_xattr(dir, roots, MAX_FILES, &max_files.to_string()).await
+    }
+
+    /// Read a numeric attribute of a directory
+    async fn read_xattr(
+        &self,
+        dir: &Path,
+        roots: &[PathBuf],
+        name: &str,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<u64, Error> {
+        assert_not_expired(expires)?;
+        let value = filesystem::get_xattr(dir, roots, name).await?;
+        parse_xattr(name, value.as_deref())
+    }
+
+    /// Read the quota and usage of a directory from its attributes
+    async fn read_quota(
+        &self,
+        dir: &Path,
+        roots: &[PathBuf],
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        Ok(quota_from_xattrs(
+            self.read_xattr(dir, roots, MAX_BYTES, expires).await?,
+            self.read_xattr(dir, roots, MAX_FILES, expires).await?,
+            self.read_xattr(dir, roots, RBYTES, expires).await?,
+            self.read_xattr(dir, roots, RFILES, expires).await?,
+        ))
+    }
+
+    // -----------------------------------------------------------------------
+    // User quota methods
+    // -----------------------------------------------------------------------
+
+    pub async fn set_user_quota(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        volume_config: &UserVolumeConfig,
+        limit: &QuotaLimit,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let user = mapping.local_user().unix()?;
+
+        // Validate against any configured maximum.
+        if let Some(max_quota) = volume_config.max_quota() {
+            if limit.exceeds(max_quota) {
+                return Err(Error::Failed(format!(
+                    "Requested quota limit ({}) exceeds maximum allowed quota ({}) for user {} on volume {}",
+                    limit, max_quota, user, volume
+                )));
+            }
+        }
+
+        let (dir, roots) = Self::quota_dir(
+            &volume_config.path_configs(),
+            mapping.clone().into(),
+            volume,
+        )?;
+
+        tracing::info!(
+            "CephfsEngine::set_user_quota: user={}, volume={}, limit={}, dir={}",
+            user,
+            volume,
+            limit,
+            dir.to_string_lossy()
+        );
+
+        self.write_quota(
+            &dir,
+            &roots,
+            limit,
+            volume_config.default_inode_limit(),
+            expires,
+        )
+        .await?;
+
+        self.read_quota(&dir, &roots, expires).await
+    }
+
+    pub async fn get_user_quota(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        volume_config: &UserVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let (dir, roots) = Self::quota_dir(
+            &volume_config.path_configs(),
+            mapping.clone().into(),
+            volume,
+        )?;
+
+        tracing::info!(
+            "CephfsEngine::get_user_quota: user={}, volume={}, dir={}",
+            mapping.local_user(),
+            volume,
+            dir.to_string_lossy()
+        );
+
+        self.read_quota(&dir, &roots, expires).await
+    }
+
+    pub async fn clear_user_quota(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        volume_config: &UserVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let (dir, roots) = Self::quota_dir(
+            &volume_config.path_configs(),
+            mapping.clone().into(),
+            volume,
+        )?;
+
+        tracing::info!(
+            "CephfsEngine::clear_user_quota: user={}, volume={}, dir={}",
+            mapping.local_user(),
+            volume,
+            dir.to_string_lossy()
+        );
+
+        self.write_quota(&dir, &roots, &QuotaLimit::unlimited(), Some(0), expires)
+            .await
+    }
+
+    // -----------------------------------------------------------------------
+    // Project quota methods
+    // -----------------------------------------------------------------------
+
+    pub async fn set_project_quota(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        volume_config: &ProjectVolumeConfig,
+        limit: &QuotaLimit,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        // Validate against any configured maximum.
+        if let Some(max_quota) = volume_config.max_quota() {
+            if limit.exceeds(max_quota) {
+                return Err(Error::Failed(format!(
+                    "Requested quota limit ({}) exceeds maximum allowed quota ({}) for project {} on volume {}",
+                    limit, max_quota, mapping.project(), volume
+                )));
+            }
+        }
+
+        let (dir, roots) = Self::quota_dir(
+            &volume_config.path_configs(),
+            mapping.clone().into(),
+            volume,
+        )?;
+
+        tracing::info!(
+            "CephfsEngine::set_project_quota: project={}, volume={}, limit={}, dir={}",
+            mapping.project(),
+            volume,
+            limit,
+            dir.to_string_lossy()
+        );
+
+        self.write_quota(
+            &dir,
+            &roots,
+            limit,
+            volume_config.default_inode_limit(),
+            expires,
+        )
+        .await?;
+
+        self.read_quota(&dir, &roots, expires).await
+    }
+
+    pub async fn get_project_quota(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        volume_config: &ProjectVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let (dir, roots) = Self::quota_dir(
+            &volume_config.path_configs(),
+            mapping.clone().into(),
+            volume,
+        )?;
+
+        tracing::info!(
+            "CephfsEngine::get_project_quota: project={}, volume={}, dir={}",
+            mapping.project(),
+            volume,
+            dir.to_string_lossy()
+        );
+
+        self.read_quota(&dir, &roots, expires).await
+    }
+
+    pub async fn clear_project_quota(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        volume_config: &ProjectVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let (dir, roots) = Self::quota_dir(
+            &volume_config.path_configs(),
+            mapping.clone().into(),
+            volume,
+        )?;
+
+        tracing::info!(
+            "CephfsEngine::clear_project_quota: project={}, volume={}, dir={}",
+            mapping.project(),
+            volume,
+            dir.to_string_lossy()
+        );
+
+        self.write_quota(&dir, &roots, &QuotaLimit::unlimited(), Some(0), expires)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ceph_xattrs() {
+        assert_eq!(
+            parse_xattr(RBYTES, Some("1073741824\n")).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            1_073_741_824
+        );
+
+        // an unset quota attribute is unlimited
+        assert_eq!(
+            parse_xattr(MAX_BYTES, None).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            0
+        );
+        assert_eq!(
+            parse_xattr(MAX_FILES, Some("")).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            0
+        );
+
+        assert!(parse_xattr(RFILES, Some("lots")).is_err());
+        assert!(parse_xattr(RFILES, Some("-1")).is_err());
+    }
+
+    #[test]
+    fn test_quota_from_ceph_xattrs() {
+        let quota = quota_from_xattrs(1_099_511_627_776, 500_000, 549_755_813_888, 1234);
+
+        assert_eq!(
+            quota.limit().size(),
+            Some(StorageSize::from_bytes(1_099_511_627_776))
+        );
+        assert_eq!(quota.limit().inodes(), Some(500_000));
+        assert_eq!(
+            quota.usage(),
+            Some(StorageUsage::new(StorageSize::from_bytes(549_755_813_888)))
+        );
+        assert_eq!(quota.inode_usage(), Some(1234));
+        assert!(!quota.is_over_quota());
+
+        let quota = quota_from_xattrs(0, 0, 4096, 1);
+        assert!(quota.is_unlimited());
+        assert_eq!(quota.limit().inodes(), Some(0));
+    }
+
+    #[test]
+    fn test_limit_to_ceph_xattrs() {
+        let limit = QuotaLimit::limited(StorageSize::from_gigabytes(1.0));
+
+        assert_eq!(limit_to_xattrs(&limit, None), (1_073_741_824, 0));
+        assert_eq!(limit_to_xattrs(&limit, Some(1000)), (1_073_741_824, 1000));
+        assert_eq!(
+            limit_to_xattrs(&limit.with_inodes(500), Some(1000)),
+            (1_073_741_824, 500)
+        );
+        assert_eq!(limit_to_xattrs(&QuotaLimit::unlimited(), None), (0, 0));
+    }
+}
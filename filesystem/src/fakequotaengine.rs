@@ -5,7 +5,8 @@
 //!
 //! Quota limits are persisted as plain-text files in a local `quota_dir`
 //! (host-side, written directly by this process).  Actual disk usage is
-//! measured by running `du -sk` on each volume path — the `du` command is
+//! measured by running `du -sk` (and `du -s --inodes` for the number of
+//! files) on each volume path — the `du` command is
 //! configurable so it can be redirected into a Docker container just like
 //! the other exec-prefix commands.
 //!
@@ -91,7 +92,7 @@ impl FakeEngine {
         Path::new(&self.config.quota_dir).join(format!("group_{}", local_group))
     }
 
-    /// Read a quota limit from a file.  Returns `Unlimited` if the file does
+    /// Read a quota limit from a file.  Returns an unlimited quota if the file does
     /// not exist (i.e. no quota has been set yet).
     async fn read_limit(&self, path: &Path) -> Result<QuotaLimit, Error> {
         match tokio::fs::read_to_string(path).await {
@@ -102,7 +103,7 @@ impl FakeEngine {
                     e
                 ))
             }),
-            Err(_) => Ok(QuotaLimit::Unlimited),
+            Err(_) => Ok(QuotaLimit::unlimited()),
         }
     }
 
@@ -147,9 +148,14 @@ impl FakeEngine {
     // du helper
     // -----------------------------------------------------------------------
 
-    /// Run `du -sk <dir>` and return the result in bytes.
+    /// Run `du <args> <dir>` and return the leading number of its output.
     /// Returns 0 if the directory does not exist or `du` fails.
-    async fn du_bytes(&self, dir: &str, expires: &chrono::DateTime<Utc>) -> Result<u64, Error> {
+    async fn du_count(
+        &self,
+        args: &[&str],
+        dir: &str,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<u64, Error> {
         assert_not_expired(expires)?;
 
         let parts: Vec<&str> = self.config.du.split_whitespace().collect();
@@ -158,11 +164,17 @@ impl FakeEngine {
             None => return Ok(0),
         };
 
-        tracing::debug!("FakeQuotaEngine: {} -sk {}", self.config.du, dir);
+        tracing::debug!(
+            "FakeQuotaEngine: {} {} {}",
+            self.config.du,
+            args.join(" "),
+            dir
+        );
 
         let output = Command::new(prog)
             .args(prefix_args)
-            .args(["-sk", dir])
+            .args(args)
+            .arg(dir)
             .output()
             .await
             .map_err(|e| Error::Failed(format!("du failed on '{}': {}", dir, e)))?;
@@ -172,48 +184,75 @@ impl FakeEngine {
             return Ok(0);
         }
 
-        // `du -sk` output: "<kb>\t<path>"
-        let kb: u64 = String::from_utf8_lossy(&output.stdout)
+        // `du -s` output: "<count>\t<path>"
+        Ok(String::from_utf8_lossy(&output.stdout)
             .split_whitespace()
             .next()
             .and_then(|s| s.parse().ok())
-            .unwrap_or(0);
+            .unwrap_or(0))
+    }
 
-        Ok(kb * 1024)
+    /// Run `du -sk <dir>` and return the result in bytes.
+    /// Returns 0 if the directory does not exist or `du` fails.
+    async fn du_bytes(&self, dir: &str, expires: &chrono::DateTime<Utc>) -> Result<u64, Error> {
+        let kb = self.du_count(&["-sk"], dir, expires).await?;
+        Ok(kb.saturating_mul(1024))
     }
 
-    /// Sum `du` usage across every path in a user volume config.
+    /// Run `du -s --inodes <dir>` and return the number of files.
+    /// Returns 0 if the directory does not exist or `du` fails.
+    async fn du_inodes(&self, dir: &str, expires: &chrono::DateTime<Utc>) -> Result<u64, Error> {
+        self.du_count(&["-s", "--inodes"], dir, expires).await
+    }
+
+    /// Sum `du` usage and number of files across every path in a user
+    /// volume config.
     async fn user_usage(
         &self,
         mapping: &UserMapping,
         volume_config: &UserVolumeConfig,
         expires: &chrono::DateTime<Utc>,
-    ) -> Result<StorageUsage, Error> {
+    ) -> Result<(StorageUsage, u64), Error> {
         let mut total_bytes: u64 = 0;
+        let mut total_inodes: u64 = 0;
         for path_config in volume_config.path_configs() {
             if let Ok(path) = path_config.path(mapping.clone().into()) {
-                let bytes = self.du_bytes(&path.to_string_lossy(), expires).await?;
+                let path = path.to_string_lossy();
+                let bytes = self.du_bytes(&path, expires).await?;
                 total_bytes = total_bytes.saturating_add(bytes);
+                let inodes = self.du_inodes(&path, expires).await?;
+                total_inodes = total_inodes.saturating_add(inodes);
             }
         }
-        Ok(StorageUsage::new(StorageSize::from_bytes(total_bytes)))
+        Ok((
+            StorageUsage::new(StorageSize::from_bytes(total_bytes)),
+            total_inodes,
+        ))
     }
 
-    /// Sum `du` usage across every path in a project volume config.
+    /// Sum `du` usage and number of files across every path in a project
+    /// volume config.
     async fn project_usage(
         &self,
         mapping: &ProjectMapping,
         volume_config: &ProjectVolumeConfig,
         expires: &chrono::DateTime<Utc>,
-    ) -> Result<StorageUsage, Error> {
+    ) -> Result<(StorageUsage, u64), Error> {
         let mut total_bytes: u64 = 0;
+        let mut total_inodes: u64 = 0;
         for path_config in volume_config.path_configs() {
             if let Ok(path) = path_config.path(mapping.clone().into()) {
-                let bytes = self.du_bytes(&path.to_string_lossy(), expires).await?;
+                let path = path.to_string_lossy();
+                let bytes = self.du_bytes(&path, expires).await?;
                 total_bytes = total_bytes.saturating_add(bytes);
+                let inodes = self.du_inodes(&path, expires).await?;
+                total_inodes = total_inodes.saturating_add(inodes);
             }
         }
-        Ok(StorageUsage::new(StorageSize::from_bytes(total_bytes)))
+        Ok((
+            StorageUsage::new(StorageSize::from_bytes(total_bytes)),
+            total_inodes,
+        ))
     }
 
     // -----------------------------------------------------------------------
@@ -238,7 +277,7 @@ impl FakeEngine {
 
         // Validate against any configured maximum.
         if let Some(max_quota) = volume_config.max_quota() {
-            if limit > max_quota {
+            if limit.exceeds(max_quota) {
                 return Err(Error::Failed(format!(
                     "Requested quota ({}) exceeds maximum ({}) for user {} on volume {}",
                     limit, max_quota, user, volume
@@ -246,7 +285,15 @@ impl FakeEngine {
             }
         }
 
-        self.write_limit(&self.user_quota_path(user), limit).await?;
+        // Record the volume's default inode limit if none was requested,
+        // as the real engines would apply it
+        let limit = match (limit.inodes(), volume_config.default_inode_limit()) {
+            (None, Some(inodes)) => limit.with_inodes(inodes),
+            _ => limit.clone(),
+        };
+
+        self.write_limit(&self.user_quota_path(user), &limit)
+            .await?;
         self.get_user_quota(mapping, volume, volume_config, expires)
             .await
     }
@@ -266,8 +313,10 @@ impl FakeEngine {
         );
 
         let limit = self.read_limit(&self.user_quota_path(user)).await?;
-        let usage = self.user_usage(mapping, volume_config, expires).await?;
-        Ok(Quota::with_usage(limit, usage))
+        let (usage, inode_usage) = self.user_usage(mapping, volume_config, expires).await?;
+        let mut quota = Quota::with_usage(limit, usage);
+        quota.set_inode_usage(inode_usage);
+        Ok(quota)
     }
 
     pub async fn clear_user_quota(
@@ -308,7 +357,7 @@ impl FakeEngine {
 
         // Validate against any configured maximum.
         if let Some(max_quota) = volume_config.max_quota() {
-            if limit > max_quota {
+            if limit.exceeds(max_quota) {
                 return Err(Error::Failed(format!(
                     "Requested quota ({}) exceeds maximum ({}) for project {} on volume {}",
                     limit,
@@ -319,7 +368,14 @@ impl FakeEngine {
             }
         }
 
-        self.write_limit(&self.group_quota_path(group), limit)
+        // Record the volume's default inode limit if none was requested,
+        // as the real engines would apply it
+        let limit = match (limit.inodes(), volume_config.default_inode_limit()) {
+            (None, Some(inodes)) => limit.with_inodes(inodes),
+            _ => limit.clone(),
+        };
+
+        self.write_limit(&self.group_quota_path(group), &limit)
             .await?;
         self.get_project_quota(mapping, volume, volume_config, expires)
             .await
@@ -340,8 +396,10 @@ impl FakeEngine {
         );
 
         let limit = self.read_limit(&self.group_quota_path(group)).await?;
-        let usage = self.project_usage(mapping, volume_config, expires).await?;
-        Ok(Quota::with_usage(limit, usage))
+        let (usage, inode_usage) = self.project_usage(mapping, volume_config, expires).await?;
+        let mut quota = Quota::with_usage(limit, usage);
+        quota.set_inode_usage(inode_usage);
+        Ok(quota)
     }
 
     pub async fn clear_project_quota(
@@ -2,6 +2,8 @@
 // SPDX-License-Identifier: MIT
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use greatwestern::storage::RecycleVersion;
 use once_cell::sync::{Lazy, OnceCell};
 use templemeads::Error;
 
@@ -614,15 +616,24 @@ async fn create_dir_native(
     std::fs::create_dir(path)
         .with_context(|| format!("Could not create directory '{}'", path.to_string_lossy()))?;
 
+    // Set the ownership and permissions **without following symlinks** - see
+    // `set_ownership_native`.
+    set_ownership_native(path, uid, gid, permissions)
+}
+
+///
+/// Set the ownership and permissions of the directory at `path`.
+///
+fn set_ownership_native(path: &Path, uid: Uid, gid: Gid, permissions: u32) -> Result<(), Error> {
     // Set the ownership and permissions **without following symlinks**.
     //
     // `nix::unistd::chown` and `std::fs::set_permissions` both follow, so if anything
-    // replaced `path` between the create above and here, ownership of the symlink's
+    // replaced `path` between its creation and here, ownership of the symlink's
     // *target* would be transferred. `create_dir` succeeding means `path` was a real
     // directory a moment ago, and the nofollow variants mean that is still the thing
     // being modified - closing the race that the resolve-then-act check in
     // `assert_within_root` can only narrow. See finding R33.
-    // Open the directory we just created with `O_NOFOLLOW | O_DIRECTORY` and operate
+    // Open the directory with `O_NOFOLLOW | O_DIRECTORY` and operate
     // on the **file descriptor**, so the target cannot be swapped underneath us at
     // all - stronger than a nofollow path operation, which still resolves the path
     // once more. `O_NOFOLLOW` makes the open itself fail if `path` is a symlink.
@@ -717,6 +728,21 @@ async fn create_dir_remote(
         )));
     }
 
+    set_ownership_remote(path, username, groupname, permissions, prefix).await
+}
+
+///
+/// Set the ownership and permissions of the directory at `path` on the remote.
+///
+async fn set_ownership_remote(
+    path: &Path,
+    username: &str,
+    groupname: &str,
+    permissions: u32,
+    prefix: &[String],
+) -> Result<(), Error> {
+    let path_str = path.to_string_lossy();
+
     // chown user:group path
     let owner = format!("{}:{}", username, groupname);
     // `-h` so a symlink is never followed - the remote counterpart of the
@@ -742,6 +768,143 @@ async fn create_dir_remote(
     Ok(())
 }
 
+///
+/// Set the ownership and permissions of a directory that already exists,
+/// but which was not created by `create_dir` - e.g. the junction of a
+/// fileset that a quota engine has linked at a project's path. Unlike
+/// `create_dir`, which leaves an existing directory alone, this always
+/// applies the ownership and permissions.
+///
+pub async fn set_dir_ownership(
+    path: &Path,
+    roots: &[PathBuf],
+    username: &str,
+    groupname: &str,
+    permissions: &str,
+) -> Result<(), Error> {
+    let path = clean_and_check_path(path, roots, false).await?;
+    let permissions = clean_and_check_permissions(permissions).await?;
+
+    tracing::info!(
+        "Setting ownership of directory '{}' to '{}:{}' with permissions '{}'",
+        path.to_string_lossy(),
+        username,
+        groupname,
+        unix_mode::to_string(permissions)
+    );
+
+    match get_exec_prefix() {
+        Some(prefix) => set_ownership_remote(&path, username, groupname, permissions, prefix).await,
+        None => {
+            let uid = Uid::from_raw(nameservice::resolve_uid(username).await?);
+            let gid = Gid::from_raw(nameservice::resolve_gid(groupname).await?);
+            set_ownership_native(&path, uid, gid, permissions)
+        }
+    }
+}
+
+///
+/// Read the extended attribute `name` of the existing file or directory
+/// at `path`, returning `None` if the attribute is not set. This is how
+/// quota engines read filesystem-provided attributes such as CephFS's
+/// `ceph.dir.rbytes`. A symlink at `path` is never followed.
+///
+pub async fn get_xattr(
+    path: &Path,
+    roots: &[PathBuf],
+    name: &str,
+) -> Result<Option<String>, Error> {
+    let path = clean_and_check_path(path, roots, true).await?;
+
+    match get_exec_prefix() {
+        Some(prefix) => {
+            let path_str = path.to_string_lossy();
+            let (exit_code, stdout, stderr) = run_remote(
+                prefix,
+                &[
+                    "getfattr",
+                    "-h",
+                    "--only-values",
+                    "--absolute-names",
+                    "-n",
+                    name,
+                    &path_str,
+                ],
+            )
+            .await?;
+
+            if exit_code == 0 {
+                Ok(Some(stdout.trim().to_owned()))
+            } else if stderr.contains("No such attribute") || stderr.contains("No data available") {
+                Ok(None)
+            } else {
+                Err(Error::State(format!(
+                    "getfattr '{}' '{}' failed: exit code {}, stderr: {}",
+                    name, path_str, exit_code, stderr
+                )))
+            }
+        }
+        None => match xattr::get(&path, name) {
+            Ok(value) => Ok(value.map(|v| String::from_utf8_lossy(&v).trim().to_owned())),
+            Err(e) => Err(Error::State(format!(
+                "Could not read attribute '{}' of '{}': {}",
+                name,
+                path.to_string_lossy(),
+                e
+            ))),
+        },
+    }
+}
+
+///
+/// Set the extended attribute `name` of the existing file or directory at
+/// `path` to `value`, e.g. CephFS's `ceph.quota.max_bytes`. A symlink at
+/// `path` is never followed.
+///
+pub async fn set_xattr(
+    path: &Path,
+    roots: &[PathBuf],
+    name: &str,
+    value: &str,
+) -> Result<(), Error> {
+    let path = clean_and_check_path(path, roots, true).await?;
+
+    tracing::info!(
+        "Setting attribute '{}' of '{}' to '{}'",
+        name,
+        path.to_string_lossy(),
+        value
+    );
+
+    match get_exec_prefix() {
+        Some(prefix) => {
+            let path_str = path.to_string_lossy();
+            let (exit_code, _, stderr) = run_remote(
+                prefix,
+                &["setfattr", "-h", "-n", name, "-v", value, &path_str],
+            )
+            .await?;
+
+            if exit_code != 0 {
+                return Err(Error::State(format!(
+                    "setfattr '{}' '{}' failed: exit code {}, stderr: {}",
+                    name, path_str, exit_code, stderr
+                )));
+            }
+
+            Ok(())
+        }
+        None => xattr::set(&path, name, value.as_bytes()).map_err(|e| {
+            Error::State(format!(
+                "Could not set attribute '{}' of '{}': {}",
+                name,
+                path.to_string_lossy(),
+                e
+            ))
+        }),
+    }
+}
+
 /// Create a symlink at `link` pointing to `path`.
 ///
 /// `roots` is every configured volume root, not one - the two paths legitimately live
@@ -1016,25 +1179,12 @@ async fn clear_placeholder_dir_native(path: &Path) -> Result<bool, Error> {
 
 ///
 /// Check if a directory exists in the .recycle subdirectory of its parent.
-/// Returns Some(recycle_path) if found, None otherwise.
+/// Returns Some(recycle_path) of the newest version if found, None otherwise.
 ///
 async fn check_recycle_native(path: &Path) -> Result<Option<PathBuf>, Error> {
-    let parent = match path.parent() {
-        Some(p) => p,
-        None => return Ok(None),
-    };
-
-    let dir_name = match path.file_name() {
-        Some(n) => n.to_string_lossy(),
-        None => return Ok(None),
-    };
-
-    let recycle_path = parent.join(".recycle").join(dir_name.as_ref());
-
-    if recycle_path.exists() {
-        Ok(Some(recycle_path))
-    } else {
-        Ok(None)
+    match recycled_versions_native(path).await?.first() {
+        Some((version, _)) => recycled_path(path, version),
+        None => Ok(None),
     }
 }
 
@@ -1149,22 +1299,9 @@ async fn clear_placeholder_dir_remote(path: &Path, prefix: &[String]) -> Result<
 }
 
 async fn check_recycle_remote(path: &Path, prefix: &[String]) -> Result<Option<PathBuf>, Error> {
-    let parent = match path.parent() {
-        Some(p) => p,
-        None => return Ok(None),
-    };
-
-    let dir_name = match path.file_name() {
-        Some(n) => n.to_string_lossy(),
-        None => return Ok(None),
-    };
-
-    let recycle_path = parent.join(".recycle").join(dir_name.as_ref());
-
-    if remote_exists(prefix, &recycle_path).await? {
-        Ok(Some(recycle_path))
-    } else {
-        Ok(None)
+    match recycled_versions_remote(path, prefix).await?.first() {
+        Some((version, _)) => recycled_path(path, version),
+        None => Ok(None),
     }
 }
 
@@ -1418,6 +1555,10 @@ async fn correct_restored_ownership_remote(
 /// This is a non-destructive way to "remove" directories - they can be restored later
 /// or permanently deleted by a separate cleanup process.
 ///
+/// The directory is renamed to `<name>.<version>` in `.recycle`, where the version is
+/// the time it was recycled (see `RecycleVersion`), so every earlier recycled copy is
+/// kept alongside it rather than being overwritten.
+///
 pub async fn recycle_dir(path: &Path, roots: &[PathBuf]) -> Result<(), Error> {
     let path = clean_and_check_path(path, roots, false).await?;
 
@@ -1471,21 +1612,23 @@ async fn recycle_dir_native(path: &Path) -> Result<(), Error> {
         })?;
     }
 
-    let recycle_path = recycle_parent.join(dir_name);
+    // Each recycle is kept as a new version, so nothing recycled earlier
+    // is ever deleted to make room
+    let now = Utc::now();
+    let mut count = 0;
 
-    // If something already exists in recycle with this name, we need to handle it
-    if recycle_path.exists() {
-        tracing::warn!(
-            "Recycle path '{}' already exists. Removing old recycled directory.",
-            recycle_path.to_string_lossy()
-        );
-        std::fs::remove_dir_all(&recycle_path).with_context(|| {
-            format!(
-                "Could not remove old recycled directory '{}'",
-                recycle_path.to_string_lossy()
-            )
-        })?;
-    }
+    let recycle_path = loop {
+        let candidate = recycle_parent.join(recycled_name(
+            &dir_name.to_string_lossy(),
+            &RecycleVersion::new(&now, count),
+        ));
+
+        if std::fs::symlink_metadata(&candidate).is_err() {
+            break candidate;
+        }
+
+        count = count.saturating_add(1);
+    };
 
     tracing::info!(
         "Moving '{}' to recycle '{}'",
@@ -1564,23 +1707,23 @@ async fn recycle_dir_remote(path: &Path, prefix: &[String]) -> Result<(), Error>
         }
     }
 
-    let recycle_path = recycle_parent.join(dir_name.as_ref());
-    let recycle_path_str = recycle_path.to_string_lossy();
+    // Each recycle is kept as a new version, so nothing recycled earlier
+    // is ever deleted to make room
+    let now = Utc::now();
+    let mut count = 0;
 
-    // If something already exists in recycle with this name, remove it.
-    if remote_exists(prefix, &recycle_path).await? {
-        tracing::warn!(
-            "Recycle path '{}' already exists (remote). Removing.",
-            recycle_path_str
-        );
-        let (exit_code, _, stderr) = run_remote(prefix, &["rm", "-rf", &recycle_path_str]).await?;
-        if exit_code != 0 {
-            return Err(Error::State(format!(
-                "rm -rf '{}' failed: exit code {}, stderr: {}",
-                recycle_path_str, exit_code, stderr
-            )));
+    let recycle_path = loop {
+        let candidate =
+            recycle_parent.join(recycled_name(&dir_name, &RecycleVersion::new(&now, count)));
+
+        if !remote_exists(prefix, &candidate).await? {
+            break candidate;
         }
-    }
+
+        count = count.saturating_add(1);
+    };
+
+    let recycle_path_str = recycle_path.to_string_lossy();
 
     tracing::info!(
         "Moving (remote) '{}' to recycle '{}'",
@@ -1616,6 +1759,554 @@ async fn recycle_dir_remote(path: &Path, prefix: &[String]) -> Result<(), Error>
     Ok(())
 }
 
+///
+/// The name that a version of the directory called `dir_name` has in `.recycle`.
+/// A legacy version is one recycled before versions were kept, so has no suffix.
+///
+fn recycled_name(dir_name: &str, version: &RecycleVersion) -> String {
+    match version.is_legacy() {
+        true => dir_name.to_string(),
+        false => format!("{}.{}", dir_name, version),
+    }
+}
+
+///
+/// The path in `.recycle` of the specified version of the directory at `path`
+///
+fn recycled_path(path: &Path, version: &RecycleVersion) -> Result<Option<PathBuf>, Error> {
+    let parent = match path.parent() {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+
+    let dir_name = match path.file_name() {
+        Some(n) => n.to_string_lossy(),
+        None => return Ok(None),
+    };
+
+    Ok(Some(
+        parent
+            .join(".recycle")
+            .join(recycled_name(&dir_name, version)),
+    ))
+}
+
+///
+/// Return the version and recycled time of the entry called `entry` in `.recycle`,
+/// if it is a recycled version of the directory called `dir_name`. `modified` is
+/// the time the entry was last modified, which is when a legacy version was
+/// recycled.
+///
+fn parse_recycled_name(
+    dir_name: &str,
+    entry: &str,
+    modified: &DateTime<Utc>,
+) -> Option<(RecycleVersion, DateTime<Utc>)> {
+    if entry == dir_name {
+        return Some((RecycleVersion::legacy(), *modified));
+    }
+
+    let version = RecycleVersion::parse(entry.strip_prefix(dir_name)?.strip_prefix('.')?).ok()?;
+
+    // `.legacy` is not a suffix that `recycled_name` ever adds
+    let recycled = version.timestamp()?;
+
+    Some((version, recycled))
+}
+
+///
+/// Return when the entry called `entry` in `.recycle` was recycled, whatever
+/// the name of the directory it was
+///
+fn recycled_time(entry: &str, modified: &DateTime<Utc>) -> DateTime<Utc> {
+    entry
+        .rsplit_once('.')
+        .and_then(|(_, version)| RecycleVersion::parse(version).ok())
+        .and_then(|version| version.timestamp())
+        .unwrap_or(*modified)
+}
+
+///
+/// Sort recycled versions so that the newest is first. Versions recycled in the
+/// same second are told apart by their "-N" suffix, and a longer suffix is a
+/// later one, so they are compared by length before they are compared as text.
+///
+fn sort_newest_first(versions: &mut [(RecycleVersion, DateTime<Utc>)]) {
+    versions.sort_by_cached_key(|(version, recycled)| {
+        let version = version.to_string();
+        std::cmp::Reverse((*recycled, version.len(), version))
+    });
+}
+
+///
+/// Return the name and modification time of every entry in the `.recycle`
+/// directory at `recycle`, which may not exist
+///
+fn list_recycle_native(recycle: &Path) -> Result<Vec<(String, DateTime<Utc>)>, Error> {
+    // `symlink_metadata` so that a `.recycle` that is not a real directory is
+    // never followed
+    match std::fs::symlink_metadata(recycle) {
+        Ok(metadata) if metadata.is_dir() => {}
+        _ => return Ok(Vec::new()),
+    }
+
+    let mut entries = Vec::new();
+
+    for entry in std::fs::read_dir(recycle).with_context(|| {
+        format!(
+            "Could not read recycle directory '{}'",
+            recycle.to_string_lossy()
+        )
+    })? {
+        let entry = entry?;
+
+        // `DirEntry::metadata` does not follow a symlink
+        let modified = match entry.metadata().and_then(|m| m.modified()) {
+            Ok(modified) => DateTime::<Utc>::from(modified),
+            Err(_) => Utc::now(),
+        };
+
+        entries.push((entry.file_name().to_string_lossy().to_string(), modified));
+    }
+
+    Ok(entries)
+}
+
+///
+/// The remote counterpart of `list_recycle_native`. `find -printf` gives the name
+/// and modification time of every entry in one command, rather than a `stat` per
+/// entry.
+///
+async fn list_recycle_remote(
+    recycle: &Path,
+    prefix: &[String],
+) -> Result<Vec<(String, DateTime<Utc>)>, Error> {
+    if !remote_exists(prefix, recycle).await? {
+        return Ok(Vec::new());
+    }
+
+    let recycle_str = recycle.to_string_lossy();
+
+    let (exit_code, stdout, stderr) = run_remote(
+        prefix,
+        &[
+            "find",
+            &recycle_str,
+            "-mindepth",
+            "1",
+            "-maxdepth",
+            "1",
+            "-printf",
+            "%f\\t%T@\\n",
+        ],
+    )
+    .await?;
+
+    if exit_code != 0 {
+        return Err(Error::State(format!(
+            "find '{}' failed: exit code {}, stderr: {}",
+            recycle_str, exit_code, stderr
+        )));
+    }
+
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, modified)| {
+            let modified = modified
+                .trim()
+                .split('.')
+                .next()
+                .and_then(|secs| secs.parse::<i64>().ok())
+                .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+                .unwrap_or_else(Utc::now);
+
+            (name.to_string(), modified)
+        })
+        .collect())
+}
+
+fn recycled_versions(
+    path: &Path,
+    entries: Vec<(String, DateTime<Utc>)>,
+) -> Vec<(RecycleVersion, DateTime<Utc>)> {
+    let dir_name = match path.file_name() {
+        Some(n) => n.to_string_lossy(),
+        None => return Vec::new(),
+    };
+
+    let mut versions: Vec<(RecycleVersion, DateTime<Utc>)> = entries
+        .iter()
+        .filter_map(|(entry, modified)| parse_recycled_name(&dir_name, entry, modified))
+        .collect();
+
+    sort_newest_first(&mut versions);
+
+    versions
+}
+
+async fn recycled_versions_native(
+    path: &Path,
+) -> Result<Vec<(RecycleVersion, DateTime<Utc>)>, Error> {
+    let recycle = match path.parent() {
+        Some(p) => p.join(".recycle"),
+        None => return Ok(Vec::new()),
+    };
+
+    Ok(recycled_versions(path, list_recycle_native(&recycle)?))
+}
+
+async fn recycled_versions_remote(
+    path: &Path,
+    prefix: &[String],
+) -> Result<Vec<(RecycleVersion, DateTime<Utc>)>, Error> {
+    let recycle = match path.parent() {
+        Some(p) => p.join(".recycle"),
+        None => return Ok(Vec::new()),
+    };
+
+    Ok(recycled_versions(
+        path,
+        list_recycle_remote(&recycle, prefix).await?,
+    ))
+}
+
+///
+/// Return every version of the directory at `path` that is waiting in the
+/// .recycle subdirectory of its parent, with the time it was recycled,
+/// newest first
+///
+pub async fn get_recycled_versions(
+    path: &Path,
+    roots: &[PathBuf],
+) -> Result<Vec<(RecycleVersion, DateTime<Utc>)>, Error> {
+    let path = clean_and_check_path(path, roots, false).await?;
+
+    match get_exec_prefix() {
+        Some(prefix) => recycled_versions_remote(&path, prefix).await,
+        None => recycled_versions_native(&path).await,
+    }
+}
+
+///
+/// Restore the specified recycled version of the directory at `path`, owned by
+/// `username` and `groupname`.
+///
+/// Whatever is at `path` now is recycled first, as a new version, so that
+/// restoring the wrong version never loses anything - unless it is a placeholder
+/// holding nothing real (see `clear_placeholder_dir_native`), which is removed.
+///
+pub async fn restore_recycled_dir(
+    path: &Path,
+    roots: &[PathBuf],
+    version: &RecycleVersion,
+    username: &str,
+    groupname: &str,
+) -> Result<(), Error> {
+    let path = clean_and_check_path(path, roots, false).await?;
+
+    match get_exec_prefix() {
+        Some(prefix) => {
+            restore_recycled_dir_remote(&path, version, username, groupname, prefix).await
+        }
+        None => {
+            // see `create_dir_native` for why this goes through `nameservice`
+            let uid = Uid::from_raw(nameservice::resolve_uid(username).await?);
+            let gid = Gid::from_raw(nameservice::resolve_gid(groupname).await?);
+
+            restore_recycled_dir_native(&path, version, uid, gid).await
+        }
+    }
+}
+
+async fn restore_recycled_dir_native(
+    path: &Path,
+    version: &RecycleVersion,
+    uid: Uid,
+    gid: Gid,
+) -> Result<(), Error> {
+    let recycle_path = match recycled_path(path, version)? {
+        Some(recycle_path) if std::fs::symlink_metadata(&recycle_path).is_ok() => recycle_path,
+        _ => {
+            return Err(Error::State(format!(
+                "There is no version {} of '{}' in the recycle bin",
+                version,
+                path.to_string_lossy()
+            )))
+        }
+    };
+
+    // only a real directory can be a placeholder - anything else, including a
+    // symlink, is recycled rather than looked inside
+    let in_the_way = match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => !clear_placeholder_dir_native(path).await?,
+        Ok(_) => true,
+        Err(_) => false,
+    };
+
+    if in_the_way {
+        tracing::info!(
+            "Recycling the current '{}' before restoring version {}",
+            path.to_string_lossy(),
+            version
+        );
+        recycle_dir_native(path).await?;
+    }
+
+    restore_from_recycle_native(&recycle_path, path, uid, gid).await
+}
+
+async fn restore_recycled_dir_remote(
+    path: &Path,
+    version: &RecycleVersion,
+    username: &str,
+    groupname: &str,
+    prefix: &[String],
+) -> Result<(), Error> {
+    let recycle_path = match recycled_path(path, version)? {
+        Some(recycle_path) if remote_exists(prefix, &recycle_path).await? => recycle_path,
+        _ => {
+            return Err(Error::State(format!(
+                "There is no version {} of '{}' in the recycle bin (remote)",
+                version,
+                path.to_string_lossy()
+            )))
+        }
+    };
+
+    let in_the_way = if remote_is_symlink(prefix, path).await? {
+        true
+    } else if remote_exists(prefix, path).await? {
+        !clear_placeholder_dir_remote(path, prefix).await?
+    } else {
+        false
+    };
+
+    if in_the_way {
+        tracing::info!(
+            "Recycling the current '{}' (remote) before restoring version {}",
+            path.to_string_lossy(),
+            version
+        );
+        recycle_dir_remote(path, prefix).await?;
+    }
+
+    restore_from_recycle_remote(&recycle_path, path, username, groupname, prefix).await
+}
+
+///
+/// Permanently delete a single entry from a `.recycle` directory. A symlink is
+/// deleted as a symlink - `remove_dir_all` never follows one.
+///
+fn delete_recycled_native(entry: &Path) -> Result<(), Error> {
+    let metadata = std::fs::symlink_metadata(entry)?;
+
+    match metadata.is_dir() {
+        true => std::fs::remove_dir_all(entry),
+        false => std::fs::remove_file(entry),
+    }
+    .with_context(|| {
+        format!(
+            "Could not purge recycled directory '{}'",
+            entry.to_string_lossy()
+        )
+    })?;
+
+    Ok(())
+}
+
+async fn delete_recycled_remote(entry: &Path, prefix: &[String]) -> Result<(), Error> {
+    let entry_str = entry.to_string_lossy();
+
+    let (exit_code, _, stderr) = run_remote(prefix, &["rm", "-rf", "--", &entry_str]).await?;
+
+    if exit_code != 0 {
+        return Err(Error::State(format!(
+            "rm -rf '{}' failed: exit code {}, stderr: {}",
+            entry_str, exit_code, stderr
+        )));
+    }
+
+    Ok(())
+}
+
+///
+/// Permanently delete every recycled version of the directory at `path`,
+/// returning the versions that were deleted
+///
+pub async fn purge_recycled_dir(
+    path: &Path,
+    roots: &[PathBuf],
+) -> Result<Vec<(RecycleVersion, DateTime<Utc>)>, Error> {
+    let path = clean_and_check_path(path, roots, false).await?;
+    let prefix = get_exec_prefix();
+
+    let versions = match prefix {
+        Some(prefix) => recycled_versions_remote(&path, prefix).await?,
+        None => recycled_versions_native(&path).await?,
+    };
+
+    for (version, _) in &versions {
+        if let Some(recycle_path) = recycled_path(&path, version)? {
+            let recycle_path = clean_and_check_path(&recycle_path, roots, false).await?;
+
+            tracing::info!(
+                "Purging recycled directory '{}'",
+                recycle_path.to_string_lossy()
+            );
+
+            match prefix {
+                Some(prefix) => delete_recycled_remote(&recycle_path, prefix).await?,
+                None => delete_recycled_native(&recycle_path)?,
+            }
+        }
+    }
+
+    Ok(versions)
+}
+
+///
+/// Find every `.recycle` directory in `dir`, or in the directories below it
+/// down to `depth` levels. Symlinks and other hidden directories are not
+/// descended into.
+///
+fn find_recycle_dirs_native(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!(
+                "Could not look for recycle directories in '{}': {}",
+                dir.to_string_lossy(),
+                e
+            );
+            return;
+        }
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        // `DirEntry::file_type` does not follow a symlink
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+
+        let name = entry.file_name();
+
+        if name == ".recycle" {
+            found.push(entry.path());
+        } else if depth > 0 && !name.to_string_lossy().starts_with('.') {
+            find_recycle_dirs_native(&entry.path(), depth - 1, found);
+        }
+    }
+}
+
+async fn find_recycle_dirs_remote(
+    dir: &Path,
+    depth: usize,
+    prefix: &[String],
+) -> Result<Vec<PathBuf>, Error> {
+    let dir_str = dir.to_string_lossy();
+    let max_depth = depth.saturating_add(1).to_string();
+
+    let (exit_code, stdout, stderr) = run_remote(
+        prefix,
+        &[
+            "find",
+            &dir_str,
+            "-mindepth",
+            "1",
+            "-maxdepth",
+            &max_depth,
+            "-type",
+            "d",
+            "-name",
+            ".recycle",
+            "-print",
+            "-prune",
+        ],
+    )
+    .await?;
+
+    if exit_code != 0 {
+        // `find` still prints what it could read, so use that
+        tracing::warn!(
+            "Could not look everywhere for recycle directories in '{}' (remote): \
+             exit code {}, stderr: {}",
+            dir_str,
+            exit_code,
+            stderr.trim()
+        );
+    }
+
+    Ok(stdout
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+///
+/// Permanently delete everything in the `.recycle` directories in `root`,
+/// down to `depth` levels below it, that was recycled before `before`.
+/// Returns the number of recycled directories that were deleted.
+///
+pub async fn purge_expired_recycled(
+    root: &Path,
+    depth: usize,
+    roots: &[PathBuf],
+    before: &DateTime<Utc>,
+) -> Result<usize, Error> {
+    let root = clean_and_check_path(root, roots, false).await?;
+    let prefix = get_exec_prefix();
+
+    let recycle_dirs = match prefix {
+        Some(prefix) => find_recycle_dirs_remote(&root, depth, prefix).await?,
+        None => {
+            let mut found = Vec::new();
+            find_recycle_dirs_native(&root, depth, &mut found);
+            found
+        }
+    };
+
+    let mut purged = 0;
+
+    for recycle in recycle_dirs {
+        let entries = match prefix {
+            Some(prefix) => list_recycle_remote(&recycle, prefix).await?,
+            None => list_recycle_native(&recycle)?,
+        };
+
+        for (entry, modified) in entries {
+            if recycled_time(&entry, &modified) >= *before {
+                continue;
+            }
+
+            let entry = clean_and_check_path(&recycle.join(&entry), roots, false).await?;
+
+            tracing::info!(
+                "Purging expired recycled directory '{}'",
+                entry.to_string_lossy()
+            );
+
+            let result = match prefix {
+                Some(prefix) => delete_recycled_remote(&entry, prefix).await,
+                None => delete_recycled_native(&entry),
+            };
+
+            match result {
+                Ok(()) => purged += 1,
+                Err(e) => tracing::error!(
+                    "Could not purge expired recycled directory '{}': {}",
+                    entry.to_string_lossy(),
+                    e
+                ),
+            }
+        }
+    }
+
+    Ok(purged)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1925,4 +2616,170 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&base);
     }
+
+    #[test]
+    fn test_recycled_names_are_matched_to_their_directory() {
+        let modified = Utc::now();
+
+        // a directory recycled before versions were kept
+        assert_eq!(
+            parse_recycled_name("fred", "fred", &modified),
+            Some((RecycleVersion::legacy(), modified))
+        );
+
+        let (version, recycled) = parse_recycled_name("fred", "fred.20260314T101500Z-2", &modified)
+            .expect("a versioned name must parse");
+        assert_eq!(version.to_string(), "20260314T101500Z-2");
+        assert_eq!(Some(recycled), version.timestamp());
+        assert_eq!(recycled_name("fred", &version), "fred.20260314T101500Z-2");
+        assert_eq!(recycled_name("fred", &RecycleVersion::legacy()), "fred");
+
+        // other users' directories, and names that no version produces
+        for other in [
+            "freda",
+            "fred.bloggs",
+            "fred.bloggs.20260314T101500Z",
+            "fred.legacy",
+            "fred.",
+            "jim.20260314T101500Z",
+        ] {
+            assert_eq!(
+                parse_recycled_name("fred", other, &modified),
+                None,
+                "{}",
+                other
+            );
+        }
+
+        // when anything was recycled is known without its directory name
+        assert_eq!(
+            recycled_time("fred.bloggs.20260314T101500Z", &modified),
+            version.timestamp().expect("timestamp")
+        );
+        assert_eq!(recycled_time("fred", &modified), modified);
+    }
+
+    #[tokio::test]
+    async fn test_recycling_keeps_every_version() {
+        let base = std::env::temp_dir().join(format!("op-recycle-versions-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+
+        // a directory recycled the old way, which is the oldest
+        make_dir(&base.join(".recycle").join("fred"), &["legacy.txt"]);
+        filetime::set_file_mtime(
+            base.join(".recycle").join("fred"),
+            filetime::FileTime::from_unix_time(0, 0),
+        )
+        .expect("set mtime");
+
+        let home = make_dir(&base.join("fred"), &["first.txt"]);
+        recycle_dir_native(&home).await.expect("recycle first");
+        assert!(!home.exists());
+
+        make_dir(&home, &["second.txt"]);
+        recycle_dir_native(&home).await.expect("recycle second");
+
+        let versions = recycled_versions_native(&home).await.expect("list");
+        assert_eq!(versions.len(), 3, "nothing recycled may be deleted");
+
+        // newest first, with the second recycle in the same second as the first
+        let (newest, _) = versions.first().expect("newest");
+        let (legacy, _) = versions.last().expect("oldest");
+        assert!(legacy.is_legacy());
+
+        let newest_path = recycled_path(&home, newest).expect("path").expect("some");
+        assert!(newest_path.join("second.txt").exists());
+
+        // re-adding restores the newest version
+        assert_eq!(
+            check_recycle_native(&home).await.expect("check"),
+            Some(newest_path)
+        );
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[tokio::test]
+    async fn test_restoring_an_older_version_recycles_the_current_directory() {
+        let base = std::env::temp_dir().join(format!("op-recycle-restore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+
+        let uid = nix::unistd::getuid();
+        let gid = nix::unistd::getgid();
+
+        let home = make_dir(&base.join("fred"), &["thesis.tex"]);
+        recycle_dir_native(&home).await.expect("recycle");
+
+        let (version, _) = recycled_versions_native(&home)
+            .await
+            .expect("list")
+            .first()
+            .cloned()
+            .expect("a version");
+
+        // the user was re-added and has started again
+        make_dir(&home, &["new-work.txt"]);
+
+        restore_recycled_dir_native(&home, &version, uid, gid)
+            .await
+            .expect("restore");
+
+        assert!(
+            home.join("thesis.tex").exists(),
+            "the version must come back"
+        );
+
+        // ...and what was there is kept as a version of its own
+        let versions = recycled_versions_native(&home).await.expect("list");
+        assert_eq!(versions.len(), 1);
+        let (current, _) = versions.first().expect("a version");
+        assert!(recycled_path(&home, current)
+            .expect("path")
+            .expect("some")
+            .join("new-work.txt")
+            .exists());
+
+        // a version that is not there is an error, not a silent no-op
+        assert!(
+            restore_recycled_dir_native(&home, &RecycleVersion::legacy(), uid, gid)
+                .await
+                .is_err()
+        );
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[tokio::test]
+    async fn test_purging_finds_recycle_dirs_and_never_follows_symlinks() {
+        let base = std::env::temp_dir().join(format!("op-recycle-purge-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+
+        // root/.recycle holds projects, root/proj/.recycle holds users
+        make_dir(&base.join(".recycle"), &[]);
+        make_dir(&base.join("proj").join(".recycle"), &[]);
+        make_dir(&base.join("proj").join("fred").join(".recycle"), &[]);
+
+        let mut found = Vec::new();
+        find_recycle_dirs_native(&base, 1, &mut found);
+        found.sort();
+        assert_eq!(
+            found,
+            vec![base.join(".recycle"), base.join("proj").join(".recycle")],
+            "only the .recycle directories within the depth are found"
+        );
+
+        // a recycled symlink is deleted as a symlink
+        let elsewhere = make_dir(&base.join("elsewhere"), &["secret"]);
+        let link = base.join(".recycle").join("proj.20260314T101500Z");
+        std::os::unix::fs::symlink(&elsewhere, &link).expect("symlink");
+
+        delete_recycled_native(&link).expect("delete");
+        assert!(std::fs::symlink_metadata(&link).is_err());
+        assert!(
+            elsewhere.join("secret").exists(),
+            "the target must be untouched"
+        );
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
 }
@@ -0,0 +1,1002 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! Concrete implementation of the IBM Storage Scale (GPFS) quota engine.
+//!
+//! Each project gets its own independent fileset, created with
+//! `mmcrfileset` and linked at the project's directory with
+//! `mmlinkfileset`, so that a project quota is a fileset quota. Block
+//! and inode limits are set with `mmsetquota`, and usage is read with
+//! `mmlsquota`, using its machine-readable (`-Y`) output. The parser for
+//! that output also reads `mmrepquota -Y`, as the two share a format.
+//!
+//! Every `mm` command runs through a configurable prefix (e.g. `"sudo"`
+//! or `"ssh nsd01"`), and, as for the Lustre engine, only `max_runners`
+//! of them run at once, so that the cluster manager is not overloaded.
+//!
+//! # TOML configuration example
+//!
+//! ```toml
+//! [quota_engines.gpfs]
+//! type           = "gpfs"
+//! filesystem     = "gpfs01"
+//! command_prefix = "sudo"
+//! fileset_prefix = "proj_"
+//! ```
+
+use anyhow::Result;
+use chrono::Utc;
+use greatwestern::grammar::{ProjectMapping, UserMapping};
+use greatwestern::storage::{Quota, QuotaLimit, StorageSize, StorageUsage, Volume};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use templemeads::job::assert_not_expired;
+use templemeads::Error;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use crate::runnerpool::RunnerPool;
+use crate::volumeconfig::{ProjectVolumeConfig, UserVolumeConfig};
+
+/// The maximum number of inodes given to a new fileset if the volume
+/// does not set a `default_inode_limit`
+const DEFAULT_FILESET_INODES: u64 = 1_000_000;
+
+/// Configuration for the Storage Scale (GPFS) quota engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpfsEngineConfig {
+    /// The Storage Scale device (filesystem) name, e.g. `gpfs01`
+    filesystem: String,
+
+    /// Prefix for every `mm` command, e.g. `"sudo"`, `"ssh nsd01"` or
+    /// `"docker exec gpfs"` (default: no prefix)
+    #[serde(default)]
+    command_prefix: String,
+
+    /// Directory holding the `mm` commands (default: `/usr/lpp/mmfs/bin`).
+    /// Set this to an empty string to find them on the `PATH`
+    #[serde(default = "default_mmfs_bin")]
+    mmfs_bin: String,
+
+    /// Prefix added to a project's local group name to name its fileset
+    /// (default: no prefix, so the fileset is named after the group)
+    #[serde(default)]
+    fileset_prefix: String,
+
+    /// The fileset in which user quotas apply. If this is not set then
+    /// user quotas apply across the whole filesystem. Setting it requires
+    /// per-fileset quotas (`--perfileset-quota`) on the filesystem
+    #[serde(default)]
+    user_fileset: Option<String>,
+
+    /// Maximum number of concurrent `mm` commands to run
+    #[serde(default = "default_max_runners")]
+    max_runners: usize,
+
+    /// Timeout in seconds for `mm` commands (default: 60)
+    #[serde(default = "default_command_timeout")]
+    command_timeout_secs: u64,
+}
+
+fn default_mmfs_bin() -> String {
+    "/usr/lpp/mmfs/bin".to_string()
+}
+
+fn default_max_runners() -> usize {
+    4
+}
+
+fn default_command_timeout() -> u64 {
+    60
+}
+
+impl GpfsEngineConfig {
+    pub fn filesystem(&self) -> &str {
+        &self.filesystem
+    }
+
+    pub fn command_timeout(&self) -> Duration {
+        Duration::from_secs(self.command_timeout_secs)
+    }
+
+    pub fn max_runners(&self) -> usize {
+        self.max_runners
+    }
+
+    /// Return the full command line (prefix, then program) for the
+    /// named `mm` command
+    fn command(&self, name: &str) -> Vec<String> {
+        let mut command: Vec<String> = self
+            .command_prefix
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+
+        let mmfs_bin = self.mmfs_bin.trim().trim_end_matches('/');
+
+        if mmfs_bin.is_empty() {
+            command.push(name.to_string());
+        } else {
+            command.push(format!("{}/{}", mmfs_bin, name));
+        }
+
+        command
+    }
+}
+
+/// Whether or not to run in "dry_run" mode - in this mode, no `mm`
+/// commands will actually be run
+fn is_dry_run() -> bool {
+    match std::env::var("OPENPORTAL_GPFS_DRY_RUN") {
+        Ok(val) => val == "1" || val.to_lowercase() == "true",
+        Err(_) => false,
+    }
+}
+
+/// GPFS runners - ensures that only a limited number of `mm` commands
+/// are run at the same time
+static GPFS_RUNNERS: Lazy<RunnerPool<()>> = Lazy::new(|| RunnerPool::new("GPFS"));
+
+/// Decode a field of `-Y` output. The `mm` commands percent-encode
+/// characters that would clash with the format, such as `:` and `%`,
+/// e.g. a path is written as `%2Fgpfs01%2Fprojects`
+fn decode_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while let Some(&byte) = bytes.get(i) {
+        if byte == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+            if let Some(value) = hex {
+                decoded.push(value);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(byte);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Parse the machine-readable (`-Y`) output of an `mm` command into one
+/// map of column name to value per data line. Each line is a list of
+/// `:`-separated fields, starting with the command name. A line whose
+/// third field is `HEADER` names the columns of the data lines that
+/// follow it
+fn parse_y_output(output: &str) -> Vec<HashMap<String, String>> {
+    let mut header: Option<Vec<String>> = None;
+    let mut rows = Vec::new();
+
+    for line in output.lines() {
+        let fields: Vec<&str> = line.trim().split(':').collect();
+
+        if fields.len() < 3 {
+            continue;
+        }
+
+        if fields.get(2) == Some(&"HEADER") {
+            header = Some(fields.iter().map(|f| f.to_string()).collect());
+            continue;
+        }
+
+        let Some(header) = &header else {
+            continue;
+        };
+
+        // data lines must come from the same command as the header
+        if fields.first().copied() != header.first().map(|h| h.as_str()) {
+            continue;
+        }
+
+        rows.push(
+            header
+                .iter()
+                .zip(fields.iter())
+                .filter(|(name, _)| !name.is_empty())
+                .map(|(name, value)| (name.clone(), decode_field(value)))
+                .collect(),
+        );
+    }
+
+    rows
+}
+
+/// Read a numeric column from a row of `-Y` output
+fn get_number(row: &HashMap<String, String>, column: &str) -> Result<u64, Error> {
+    let value = row.get(column).map(|v| v.trim()).unwrap_or_default();
+
+    value.parse::<u64>().map_err(|e| {
+        Error::Parse(format!(
+            "Failed to parse the '{}' column ('{}') of quota output: {}",
+            column, value, e
+        ))
+    })
+}
+
+/// Parse the `-Y` output of `mmlsquota` or `mmrepquota`, returning the
+/// quota of type `quota_type` (`FILESET`, `USR` or `GRP`) for `name`,
+/// optionally within `fileset`. Block values are in kilobytes, and a
+/// hard limit of 0 means unlimited. Returns `None` if there is no
+/// quota for `name` in the output
+fn parse_quota_output(
+    output: &str,
+    quota_type: &str,
+    name: &str,
+    fileset: Option<&str>,
+) -> Result<Option<Quota>, Error> {
+    for row in parse_y_output(output) {
+        if row.get("name").map(|n| n.as_str()) != Some(name) {
+            continue;
+        }
+
+        if let Some(row_type) = row.get("quotaType") {
+            if !row_type.eq_ignore_ascii_case(quota_type) {
+                continue;
+            }
+        }
+
+        if let Some(fileset) = fileset {
+            match row.get("filesetname").map(|f| f.as_str()) {
+                Some("") | None => {}
+                Some(row_fileset) if row_fileset == fileset => {}
+                _ => continue,
+            }
+        }
+
+        let block_usage = get_number(&row, "blockUsage")?;
+        let block_limit = get_number(&row, "blockLimit")?;
+
+        let mut limit = if block_limit == 0 {
+            QuotaLimit::unlimited()
+        } else {
+            QuotaLimit::limited(StorageSize::from_kilobytes(block_limit as f64))
+        };
+
+        // the file columns are read if present - a files limit of 0 is
+        // unlimited, as it is for QuotaLimit
+        if let Ok(files_limit) = get_number(&row, "filesLimit") {
+            limit = limit.with_inodes(files_limit);
+        }
+
+        let mut quota = Quota::with_usage(
+            limit,
+            StorageUsage::new(StorageSize::from_kilobytes(block_usage as f64)),
+        );
+
+        if let Ok(files_usage) = get_number(&row, "filesUsage") {
+            quota.set_inode_usage(files_usage);
+        }
+
+        return Ok(Some(quota));
+    }
+
+    Ok(None)
+}
+
+/// The state of a fileset, as reported by `mmlsfileset -L -Y`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FilesetInfo {
+    /// Whether the fileset is linked into the namespace
+    linked: bool,
+    /// The junction path, if the fileset is linked
+    path: Option<String>,
+    /// The maximum number of inodes in the fileset's inode space
+    max_inodes: Option<u64>,
+}
+
+/// Parse the `-Y` output of `mmlsfileset`, returning the state of
+/// `fileset`, or `None` if it is not in the output
+fn parse_fileset_output(output: &str, fileset: &str) -> Option<FilesetInfo> {
+    parse_y_output(output)
+        .into_iter()
+        .find(|row| row.get("filesetName").map(|f| f.as_str()) == Some(fileset))
+        .map(|row| {
+            let linked = row
+                .get("status")
+                .map(|s| s.eq_ignore_ascii_case("linked"))
+                .unwrap_or(false);
+
+            let path = row
+                .get("path")
+                .filter(|p| linked && !p.is_empty() && p.as_str() != "--")
+                .cloned();
+
+            FilesetInfo {
+                linked,
+                path,
+                max_inodes: get_number(&row, "maxInodes").ok(),
+            }
+        })
+}
+
+/// Return the `--block` and `--files` arguments for `mmsetquota`. The
+/// soft limits are not used, and a hard limit of 0 means unlimited. The
+/// inode limit is the one requested, else `default_inode_limit`
+fn limit_to_args(limit: &QuotaLimit, default_inode_limit: Option<u64>) -> (String, String) {
+    let block = match limit.size() {
+        Some(size) => format!("0:{}K", size.as_bytes().div_ceil(1024)),
+        None => "0:0".to_string(),
+    };
+
+    let files = format!("0:{}", limit.inodes().or(default_inode_limit).unwrap_or(0));
+
+    (block, files)
+}
+
+/// Storage Scale (GPFS) quota engine implementation.
+///
+/// Projects are independent filesets, so project quotas are fileset
+/// quotas. User quotas are set on the filesystem, or within the
+/// configured `user_fileset`.
+pub struct GpfsEngine {
+    config: GpfsEngineConfig,
+}
+
+impl GpfsEngine {
+    /// Create a new GPFS quota engine with the given configuration
+    pub fn new(config: GpfsEngineConfig) -> Result<Self, Error> {
+        if config.filesystem.trim().is_empty() {
+            return Err(Error::Misconfigured(
+                "GpfsEngine requires a non-empty 'filesystem' setting".to_string(),
+            ));
+        }
+
+        Ok(Self { config })
+    }
+
+    /// Initialise the engine
+    pub async fn initialize(&self) -> Result<(), Error> {
+        GPFS_RUNNERS.set((), self.config.max_runners()).await;
+        tracing::info!(
+            "GpfsEngine initialized for filesystem '{}' with command prefix '{}' and max runners {}",
+            self.config.filesystem(),
+            self.config.command_prefix,
+            self.config.max_runners()
+        );
+        Ok(())
+    }
+
+    /// Return the name of the fileset for a project
+    fn fileset_name(&self, mapping: &ProjectMapping) -> String {
+        format!("{}{}", self.config.fileset_prefix, mapping.local_group())
+    }
+
+    /// Return the `mmsetquota` / `mmlsquota` target for user quotas - the
+    /// filesystem, or `filesystem:fileset` if a user fileset is configured
+    fn user_quota_target(&self) -> String {
+        match &self.config.user_fileset {
+            Some(fileset) => format!("{}:{}", self.config.filesystem(), fileset),
+            None => self.config.filesystem().to_string(),
+        }
+    }
+
+    /// Run the named `mm` command with the passed arguments, returning
+    /// its stdout
+    async fn run_mm_command(
+        &self,
+        name: &str,
+        args: &[&str],
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<String, Error> {
+        let command = self.config.command(name);
+        let command_string = format!("{} {}", command.join(" "), args.join(" "));
+
+        if is_dry_run() {
+            tracing::info!("DRY RUN: {}", command_string);
+            return Ok(String::new());
+        }
+
+        let _runner = GPFS_RUNNERS.runner(expires).await?;
+        assert_not_expired(expires)?;
+
+        let (program, initial_args) = command
+            .split_first()
+            .ok_or_else(|| Error::Misconfigured("GPFS command is empty".to_string()))?;
+
+        let mut cmd = Command::new(program);
+        cmd.args(initial_args);
+        cmd.args(args);
+
+        tracing::info!("Executing GPFS command: {}", command_string);
+
+        let timeout_duration = self.config.command_timeout();
+
+        let output = timeout(timeout_duration, cmd.output())
+            .await
+            .map_err(|_| {
+                Error::Timeout(format!(
+                    "GPFS command '{}' timed out after {} seconds",
+                    command_string,
+                    timeout_duration.as_secs()
+                ))
+            })?
+            .map_err(|e| {
+                Error::Failed(format!(
+                    "Failed to execute GPFS command '{}': {}",
+                    command_string, e
+                ))
+            })?;
+
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::Failed(format!(
+                "GPFS command '{}' failed: stdout={} stderr={}",
+                command_string,
+                stdout.trim(),
+                stderr.trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Return the state of the named fileset, or `None` if it does not
+    /// exist
+    async fn get_fileset(
+        &self,
+        fileset: &str,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Option<FilesetInfo>, Error> {
+        match self
+            .run_mm_command(
+                "mmlsfileset",
+                &[self.config.filesystem(), fileset, "-L", "-Y"],
+                expires,
+            )
+            .await
+        {
+            Ok(output) => Ok(parse_fileset_output(&output, fileset)),
+            Err(Error::Failed(message))
+                if message.contains("not found") || message.contains("does not exist") =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create (if needed) and link the project's fileset at `path`, so
+    /// that it becomes the project's directory on this volume
+    pub async fn create_project_dir(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        volume_config: &ProjectVolumeConfig,
+        path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let fileset = self.fileset_name(mapping);
+        let fs = self.config.filesystem();
+        let junction = path.to_str().ok_or_else(|| {
+            Error::Incompatible("Directory path contains invalid UTF-8".to_string())
+        })?;
+
+        tracing::info!(
+            "GpfsEngine::create_project_dir: project={}, volume={}, fileset={}, path={}",
+            mapping.project(),
+            volume,
+            fileset,
+            junction
+        );
+
+        let info = match self.get_fileset(&fileset, expires).await? {
+            Some(info) => info,
+            None => {
+                let inodes = match volume_config.default_inode_limit() {
+                    Some(inodes) if inodes > 0 => inodes,
+                    _ => DEFAULT_FILESET_INODES,
+                };
+
+                self.run_mm_command(
+                    "mmcrfileset",
+                    &[
+                        fs,
+                        &fileset,
+                        "--inode-space",
+                        "new",
+                        "--inode-limit",
+                        &inodes.to_string(),
+                    ],
+                    expires,
+                )
+                .await?;
+
+                FilesetInfo {
+                    linked: false,
+                    path: None,
+                    max_inodes: Some(inodes),
+                }
+            }
+        };
+
+        if info.linked {
+            // a fileset that was linked elsewhere is not moved, as that
+            // would take the data away from wherever it is in use
+            match &info.path {
+                Some(linked) if Path::new(linked) != path => {
+                    return Err(Error::State(format!(
+                        "Fileset '{}' is already linked at '{}', not at '{}'",
+                        fileset, linked, junction
+                    )));
+                }
+                _ => return Ok(true),
+            }
+        }
+
+        // this links a fileset that was unlinked when the project was
+        // removed, which brings back its data
+        self.run_mm_command("mmlinkfileset", &[fs, &fileset, "-J", junction], expires)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Unlink the project's fileset from the namespace. Its data is kept
+    /// in the fileset, and comes back if the project is added again
+    pub async fn remove_project_dir(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        _volume_config: &ProjectVolumeConfig,
+        _path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let fileset = self.fileset_name(mapping);
+
+        tracing::info!(
+            "GpfsEngine::remove_project_dir: project={}, volume={}, fileset={}",
+            mapping.project(),
+            volume,
+            fileset
+        );
+
+        match self.get_fileset(&fileset, expires).await? {
+            Some(info) if info.linked => {
+                self.run_mm_command(
+                    "mmunlinkfileset",
+                    &[self.config.filesystem(), &fileset],
+                    expires,
+                )
+                .await?;
+                Ok(true)
+            }
+            Some(_) => Ok(true),
+            // there is no fileset, so this is a plain directory that the
+            // caller should recycle as usual
+            None => Ok(false),
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // User quota methods
+    // -----------------------------------------------------------------------
+
+    pub async fn set_user_quota(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        volume_config: &UserVolumeConfig,
+        limit: &QuotaLimit,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let user = mapping.local_user().unix()?;
+
+        // Validate against any configured maximum.
+        if let Some(max_quota) = volume_config.max_quota() {
+            if limit.exceeds(max_quota) {
+                return Err(Error::Failed(format!(
+                    "Requested quota limit ({}) exceeds maximum allowed quota ({}) for user {} on volume {}",
+                    limit, max_quota, user, volume
+                )));
+            }
+        }
+
+        let (block, files) = limit_to_args(limit, volume_config.default_inode_limit());
+        let target = self.user_quota_target();
+
+        tracing::info!(
+            "GpfsEngine::set_user_quota: user={}, volume={}, limit={}, block={}, files={}",
+            user,
+            volume,
+            limit,
+            block,
+            files
+        );
+
+        self.run_mm_command(
+            "mmsetquota",
+            &[
+                &target, "--user", user, "--block", &block, "--files", &files,
+            ],
+            expires,
+        )
+        .await?;
+
+        self.get_user_quota(mapping, volume, volume_config, expires)
+            .await
+    }
+
+    pub async fn get_user_quota(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        _volume_config: &UserVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let user = mapping.local_user().unix()?;
+        let target = self.user_quota_target();
+
+        tracing::info!(
+            "GpfsEngine::get_user_quota: user={}, volume={}",
+            user,
+            volume
+        );
+
+        let output = self
+            .run_mm_command("mmlsquota", &["-u", user, "-Y", &target], expires)
+            .await?;
+
+        // no quota line means that no quota has been set
+        Ok(
+            parse_quota_output(&output, "USR", user, self.config.user_fileset.as_deref())?
+                .unwrap_or_else(|| {
+                    Quota::with_usage(QuotaLimit::unlimited(), StorageUsage::from(0))
+                }),
+        )
+    }
+
+    pub async fn clear_user_quota(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        _volume_config: &UserVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let user = mapping.local_user().unix()?;
+        let target = self.user_quota_target();
+
+        tracing::info!(
+            "GpfsEngine::clear_user_quota: user={}, volume={}",
+            user,
+            volume
+        );
+
+        self.run_mm_command(
+            "mmsetquota",
+            &[&target, "--user", user, "--block", "0:0", "--files", "0:0"],
+            expires,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Project (fileset) quota methods
+    // -----------------------------------------------------------------------
+
+    pub async fn set_project_quota(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        volume_config: &ProjectVolumeConfig,
+        limit: &QuotaLimit,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let fileset = self.fileset_name(mapping);
+        let fs = self.config.filesystem();
+
+        // Validate against any configured maximum.
+        if let Some(max_quota) = volume_config.max_quota() {
+            if limit.exceeds(max_quota) {
+                return Err(Error::Failed(format!(
+                    "Requested quota limit ({}) exceeds maximum allowed quota ({}) for project {} on volume {}",
+                    limit, max_quota, mapping.project(), volume
+                )));
+            }
+        }
+
+        let info = self.get_fileset(&fileset, expires).await?.ok_or_else(|| {
+            Error::NotFound(format!(
+                "Fileset '{}' for project {} does not exist on filesystem '{}'",
+                fileset,
+                mapping.project(),
+                fs
+            ))
+        })?;
+
+        let (block, files) = limit_to_args(limit, volume_config.default_inode_limit());
+        let inodes = limit
+            .inodes()
+            .or(volume_config.default_inode_limit())
+            .unwrap_or(0);
+
+        tracing::info!(
+            "GpfsEngine::set_project_quota: fileset={}, volume={}, limit={}, block={}, files={}",
+            fileset,
+            volume,
+            limit,
+            block,
+            files
+        );
+
+        // an independent fileset cannot hold more files than its inode
+        // space, so grow that if the inode limit needs it
+        if let Some(max_inodes) = info.max_inodes {
+            if inodes > max_inodes {
+                self.run_mm_command(
+                    "mmchfileset",
+                    &[fs, &fileset, "--inode-limit", &inodes.to_string()],
+                    expires,
+                )
+                .await?;
+            }
+        }
+
+        let target = format!("{}:{}", fs, fileset);
+
+        self.run_mm_command(
+            "mmsetquota",
+            &[&target, "--block", &block, "--files", &files],
+            expires,
+        )
+        .await?;
+
+        self.get_project_quota(mapping, volume, volume_config, expires)
+            .await
+    }
+
+    pub async fn get_project_quota(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        _volume_config: &ProjectVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let fileset = self.fileset_name(mapping);
+
+        tracing::info!(
+            "GpfsEngine::get_project_quota: fileset={}, volume={}",
+            fileset,
+            volume
+        );
+
+        let output = self
+            .run_mm_command(
+                "mmlsquota",
+                &["-j", &fileset, "-Y", self.config.filesystem()],
+                expires,
+            )
+            .await?;
+
+        // no quota line means that no quota has been set
+        Ok(parse_quota_output(&output, "FILESET", &fileset, None)?
+            .unwrap_or_else(|| Quota::with_usage(QuotaLimit::unlimited(), StorageUsage::from(0))))
+    }
+
+    pub async fn clear_project_quota(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        _volume_config: &ProjectVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let fileset = self.fileset_name(mapping);
+
+        tracing::info!(
+            "GpfsEngine::clear_project_quota: fileset={}, volume={}",
+            fileset,
+            volume
+        );
+
+        let target = format!("{}:{}", self.config.filesystem(), fileset);
+
+        self.run_mm_command(
+            "mmsetquota",
+            &[&target, "--block", "0:0", "--files", "0:0"],
+            expires,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // recorded from `mmlsquota -j proj_alpha -Y gpfs01`
+    const MMLSQUOTA_FILESET: &str = "\
+mmlsquota::HEADER:version:reserved:reserved:filesystemName:quotaType:id:name:blockUsage:blockQuota:blockLimit:blockInDoubt:blockGrace:filesUsage:filesQuota:filesLimit:filesInDoubt:filesGrace:remarks:quota:defQuota:fid:filesetname:
+mmlsquota::0:1:::gpfs01:FILESET:12:proj_alpha:52428800:0:104857600:0:none:12034:0:1000000:0:none:e:on:off:::
+";
+
+    // recorded from `mmlsquota -u alice -Y gpfs01:home`, with a
+    // per-fileset quota in two filesets
+    const MMLSQUOTA_USER: &str = "\
+mmlsquota::HEADER:version:reserved:reserved:filesystemName:quotaType:id:name:blockUsage:blockQuota:blockLimit:blockInDoubt:blockGrace:filesUsage:filesQuota:filesLimit:filesInDoubt:filesGrace:remarks:quota:defQuota:fid:filesetname:
+mmlsquota::0:1:::gpfs01:USR:1001:alice:1024:0:0:0:none:10:0:0:0:none:i:on:off::root:
+mmlsquota::0:1:::gpfs01:USR:1001:alice:2048:0:10485760:0:none:20:0:50000:0:none:e:on:off::home:
+";
+
+    // recorded from `mmrepquota -j -Y gpfs01`
+    const MMREPQUOTA: &str = "\
+mmrepquota::HEADER:version:reserved:reserved:filesystemName:quotaType:id:name:blockUsage:blockQuota:blockLimit:blockInDoubt:blockGrace:filesUsage:filesQuota:filesLimit:filesInDoubt:filesGrace:remarks:quota:defQuota:fid:filesetname:
+mmrepquota::0:1:::gpfs01:FILESET:0:root:8388608:0:0:0:none:4096:0:0:0:none:i:on:off:::
+mmrepquota::0:1:::gpfs01:FILESET:12:proj_alpha:52428800:0:104857600:0:none:12034:0:1000000:0:none:e:on:off:::
+mmrepquota::0:1:::gpfs01:FILESET:13:proj_beta:0:0:0:0:none:1:0:0:0:none:i:on:off:::
+";
+
+    // recorded from `mmlsfileset gpfs01 proj_alpha -L -Y`
+    const MMLSFILESET_LINKED: &str = "\
+mmlsfileset::HEADER:version:reserved:reserved:filesystemName:filesetName:id:rootInode:status:path:parentId:created:inodes:dataInKB:comment:filesetMode:afmTarget:afmState:afmMode:afmFileLookupRefreshInterval:afmFileOpenRefreshInterval:afmDirLookupRefreshInterval:afmDirOpenRefreshInterval:afmAsyncDelay:afmNeedsRecovery:afmExpirationTimeout:afmRPO:afmLastPSnapId:inodeSpace:isInodeSpaceOwner:maxInodes:allocInodes:inodeSpaceMask:afmShowHomeSnapshots:afmNumReadThreads:reserved:afmReadBufferSize:afmWriteBufferSize:afmReadSparseThreshold:afmParallelReadChunkSize:afmParallelReadThreshold:snapId:afmNumFlushThreads:afmPrefetchThreshold:afmEnableAutoEviction:permChangeFlag:afmParallelWriteThreshold:freeInodes:afmNeedsResync:afmParallelWriteChunkSize:afmNumWriteThreads:afmPrimID:afmDRState:afmAssociatedPrimaryId:afmDIO:afmGatewayNode:afmIOFlags:afmVerifyDmapi:afmSkipHomeACL:afmSkipHomeMtimeNsec:afmForceCtimeChange:afmSkipResyncRecovery:afmSkipConflictQDrop:afmRefreshAsync:afmParallelMounts:afmRefreshOnce:afmSkipHomeCtimeNsec:afmReaddirOnce:afmResyncVer2:afmSnapUncachedRead:afmFastCreate:afmObjectXattr:afmObjectACL:afmMUAutoRemove:afmNFSV4:afmObjectGCS:afmObjectFastReaddir:afmObjectVHB:afmObjectSSL:afmObjectNoPrefetch:afmObjectBlkIO:afmRefreshRecursive:
+mmlsfileset::0:1:::gpfs01:proj_alpha:12:1048579:Linked:%2Fgpfs01%2Fprojects%2Falpha:0:Mon Mar  2 10%3A14%3A07 2026:0:0::off:-:-:-:-:-:-:-:-:-:-:-:-:12:1:1000000:100352:1024:-:-:0:-:-:-:-:-:0:-:-:-:chmodAndSetacl:-:88123:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:-:
+";
+
+    // recorded from `mmlsfileset gpfs01 proj_beta -L -Y` after the
+    // project was removed
+    const MMLSFILESET_UNLINKED: &str = "\
+mmlsfileset::HEADER:version:reserved:reserved:filesystemName:filesetName:id:rootInode:status:path:parentId:created:inodes:dataInKB:comment:filesetMode:afmTarget:afmState:afmMode:afmFileLookupRefreshInterval:afmFileOpenRefreshInterval:afmDirLookupRefreshInterval:afmDirOpenRefreshInterval:afmAsyncDelay:afmNeedsRecovery:afmExpirationTimeout:afmRPO:afmLastPSnapId:inodeSpace:isInodeSpaceOwner:maxInodes:allocInodes:
+mmlsfileset::0:1:::gpfs01:proj_beta:13:2097155:Unlinked:--:0:Tue Mar  3 09%3A00%3A00 2026:0:0::off:-:-:-:-:-:-:-:-:-:-:-:-:13:1:500000:100352:
+";
+
+    #[test]
+    fn test_parse_fileset_quota() {
+        let quota = parse_quota_output(MMLSQUOTA_FILESET, "FILESET", "proj_alpha", None)
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+            .unwrap_or_else(|| unreachable!("no quota for proj_alpha"));
+
+        assert_eq!(
+            quota.limit().size(),
+            Some(StorageSize::from_kilobytes(104857600.0))
+        );
+        assert_eq!(
+            quota.usage(),
+            Some(StorageUsage::new(StorageSize::from_kilobytes(52428800.0)))
+        );
+        assert_eq!(quota.limit().inodes(), Some(1_000_000));
+        assert_eq!(quota.inode_usage(), Some(12034));
+
+        // mmrepquota has the same format, with a line per fileset
+        let from_repquota = parse_quota_output(MMREPQUOTA, "FILESET", "proj_alpha", None)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(from_repquota, Some(quota));
+
+        let beta = parse_quota_output(MMREPQUOTA, "FILESET", "proj_beta", None)
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+            .unwrap_or_else(|| unreachable!("no quota for proj_beta"));
+        assert!(beta.is_unlimited());
+        assert_eq!(beta.limit().inodes(), Some(0));
+
+        assert_eq!(
+            parse_quota_output(MMREPQUOTA, "FILESET", "proj_gamma", None)
+                .unwrap_or_else(|e| unreachable!("{:?}", e)),
+            None
+        );
+
+        // a user quota is not mistaken for a fileset quota
+        assert_eq!(
+            parse_quota_output(MMLSQUOTA_USER, "FILESET", "alice", None)
+                .unwrap_or_else(|e| unreachable!("{:?}", e)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_user_quota_in_fileset() {
+        let quota = parse_quota_output(MMLSQUOTA_USER, "USR", "alice", Some("home"))
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+            .unwrap_or_else(|| unreachable!("no quota for alice"));
+
+        assert_eq!(
+            quota.limit().size(),
+            Some(StorageSize::from_kilobytes(10485760.0))
+        );
+        assert_eq!(quota.limit().inodes(), Some(50000));
+        assert_eq!(quota.inode_usage(), Some(20));
+
+        // without a fileset, the first line for the user is used
+        let quota = parse_quota_output(MMLSQUOTA_USER, "USR", "alice", None)
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+            .unwrap_or_else(|| unreachable!("no quota for alice"));
+        assert!(quota.is_unlimited());
+    }
+
+    #[test]
+    fn test_parse_fileset_state() {
+        assert_eq!(
+            parse_fileset_output(MMLSFILESET_LINKED, "proj_alpha"),
+            Some(FilesetInfo {
+                linked: true,
+                path: Some("/gpfs01/projects/alpha".to_string()),
+                max_inodes: Some(1_000_000),
+            })
+        );
+
+        assert_eq!(
+            parse_fileset_output(MMLSFILESET_UNLINKED, "proj_beta"),
+            Some(FilesetInfo {
+                linked: false,
+                path: None,
+                max_inodes: Some(500_000),
+            })
+        );
+
+        assert_eq!(parse_fileset_output(MMLSFILESET_LINKED, "proj_beta"), None);
+        assert_eq!(parse_fileset_output("", "proj_alpha"), None);
+    }
+
+    #[test]
+    fn test_limit_to_mmsetquota_args() {
+        let limit = QuotaLimit::limited(StorageSize::from_gigabytes(1.0));
+
+        assert_eq!(
+            limit_to_args(&limit, None),
+            ("0:1048576K".to_string(), "0:0".to_string())
+        );
+        assert_eq!(
+            limit_to_args(&limit, Some(1000)),
+            ("0:1048576K".to_string(), "0:1000".to_string())
+        );
+        assert_eq!(
+            limit_to_args(&limit.with_inodes(500), Some(1000)),
+            ("0:1048576K".to_string(), "0:500".to_string())
+        );
+        assert_eq!(
+            limit_to_args(&QuotaLimit::unlimited(), None),
+            ("0:0".to_string(), "0:0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_y_fields() {
+        assert_eq!(decode_field("%2Fgpfs01%2Fprojects"), "/gpfs01/projects");
+        assert_eq!(decode_field("10%3A14%3A07"), "10:14:07");
+        assert_eq!(decode_field("100%"), "100%");
+        assert_eq!(decode_field("%zz"), "%zz");
+    }
+
+    #[test]
+    fn test_command_includes_prefix_and_bin() {
+        let config: GpfsEngineConfig = toml::from_str(
+            r#"
+            filesystem = "gpfs01"
+            command_prefix = "ssh nsd01 sudo"
+            "#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            config.command("mmlsquota"),
+            vec!["ssh", "nsd01", "sudo", "/usr/lpp/mmfs/bin/mmlsquota"]
+        );
+
+        let config: GpfsEngineConfig = toml::from_str(
+            r#"
+            filesystem = "gpfs01"
+            mmfs_bin = ""
+            "#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(config.command("mmsetquota"), vec!["mmsetquota"]);
+        assert!(GpfsEngine::new(config).is_ok());
+
+        let config: GpfsEngineConfig =
+            toml::from_str(r#"filesystem = " ""#).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(GpfsEngine::new(config).is_err());
+    }
+}
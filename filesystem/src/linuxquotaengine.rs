@@ -128,18 +128,25 @@ impl LinuxEngine {
 
     /// Convert a [`QuotaLimit`] to kilobytes for `setquota`.
     ///
-    /// Returns `0` for [`QuotaLimit::Unlimited`] (which `setquota` treats
+    /// Returns `0` for an unlimited size (which `setquota` treats
     /// as "no limit").
     fn limit_to_kb(limit: &QuotaLimit) -> u64 {
-        match limit {
-            QuotaLimit::Unlimited => 0,
-            QuotaLimit::Limited(size) => {
+        match limit.size() {
+            None => 0,
+            Some(size) => {
                 let bytes = size.as_bytes();
                 bytes.div_ceil(1024)
             }
         }
     }
 
+    /// Return the inode limit to pass to `setquota` - this is the limit
+    /// requested in the [`QuotaLimit`] if there is one, else the volume's
+    /// default. `0` means "no limit".
+    fn limit_to_inodes(limit: &QuotaLimit, default_inode_limit: Option<u64>) -> u64 {
+        limit.inodes().or(default_inode_limit).unwrap_or(0)
+    }
+
     /// Parse `repquota` output and find the quota for `name`.
     ///
     /// `repquota` output looks like (columns vary if over soft quota):
@@ -161,7 +168,9 @@ impl LinuxEngine {
     /// * `[4]`  block-hard   (KB, 0 = unlimited)
     /// * `[5]`  either block-grace (if flags[0] == '+') OR inode-used
     ///
-    /// We only need columns 2 (usage) and 4 (hard limit).
+    /// The inode columns (used, soft, hard) follow, shifted along by one
+    /// if the block grace column is present. We need the block usage and
+    /// hard limit, and the inode usage and hard limit.
     fn parse_quota_for_name(output: &str, name: &str) -> Result<Quota, Error> {
         for line in output.lines() {
             let tokens: Vec<&str> = line.split_whitespace().collect();
@@ -186,18 +195,40 @@ impl LinuxEngine {
             };
 
             let usage = StorageUsage::new(StorageSize::from_kilobytes(used_kb));
-            let limit = if hard_kb == 0.0 {
-                QuotaLimit::Unlimited
+            let mut limit = if hard_kb == 0.0 {
+                QuotaLimit::unlimited()
             } else {
-                QuotaLimit::Limited(StorageSize::from_kilobytes(hard_kb))
+                QuotaLimit::limited(StorageSize::from_kilobytes(hard_kb))
             };
 
-            return Ok(Quota::with_usage(limit, usage));
+            // the inode columns start after the block grace column, which
+            // is only present if the block soft limit has been exceeded
+            let inode_col = match tokens.get(1) {
+                Some(flags) if flags.starts_with('+') => 6,
+                _ => 5,
+            };
+
+            let inodes_used: Option<u64> = tokens.get(inode_col).and_then(|t| t.parse().ok());
+            let inodes_hard: Option<u64> = tokens.get(inode_col + 2).and_then(|t| t.parse().ok());
+
+            // inode hard limit of 0 means unlimited, which is also what
+            // QuotaLimit uses
+            if let Some(inodes_hard) = inodes_hard {
+                limit = limit.with_inodes(inodes_hard);
+            }
+
+            let mut quota = Quota::with_usage(limit, usage);
+
+            if let Some(inodes_used) = inodes_used {
+                quota.set_inode_usage(inodes_used);
+            }
+
+            return Ok(quota);
         }
 
         // Name not found in output — no quota has been set; treat as unlimited / no usage.
         Ok(Quota::with_usage(
-            QuotaLimit::Unlimited,
+            QuotaLimit::unlimited(),
             StorageUsage::from(0),
         ))
     }
@@ -218,7 +249,7 @@ impl LinuxEngine {
 
         // Validate against any configured maximum.
         if let Some(max_quota) = volume_config.max_quota() {
-            if limit > max_quota {
+            if limit.exceeds(max_quota) {
                 return Err(Error::Failed(format!(
                     "Requested quota limit ({}) exceeds maximum allowed quota ({}) for user {} on volume {}",
                     limit, max_quota, user, volume
@@ -227,7 +258,7 @@ impl LinuxEngine {
         }
 
         let kb = Self::limit_to_kb(limit);
-        let inode_limit = volume_config.default_inode_limit().unwrap_or(0);
+        let inode_limit = Self::limit_to_inodes(limit, volume_config.default_inode_limit());
         let fs = self.config.filesystem.as_str();
 
         tracing::info!(
@@ -320,7 +351,7 @@ impl LinuxEngine {
 
         // Validate against any configured maximum.
         if let Some(max_quota) = volume_config.max_quota() {
-            if limit > max_quota {
+            if limit.exceeds(max_quota) {
                 return Err(Error::Failed(format!(
                     "Requested quota limit ({}) exceeds maximum allowed quota ({}) for project {} on volume {}",
                     limit, max_quota, mapping.project(), volume
@@ -329,7 +360,7 @@ impl LinuxEngine {
         }
 
         let kb = Self::limit_to_kb(limit);
-        let inode_limit = volume_config.default_inode_limit().unwrap_or(0);
+        let inode_limit = Self::limit_to_inodes(limit, volume_config.default_inode_limit());
         let fs = self.config.filesystem.as_str();
 
         tracing::info!(
@@ -406,3 +437,62 @@ impl LinuxEngine {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repquota_block_and_inode_columns() {
+        let output = "\
+                   Block limits               File limits
+User            used    soft    hard  grace    used  soft  hard  grace
+----------------------------------------------------------------------
+root      --       0       0       0              3     0     0
+alice     --   12345   20000   25000             12   100   150
+bob       +-   25001   20000   25000  6days        5   100   150
+";
+
+        let alice = LinuxEngine::parse_quota_for_name(output, "alice")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(
+            alice.limit().size(),
+            Some(StorageSize::from_kilobytes(25000.0))
+        );
+        assert_eq!(alice.limit().inodes(), Some(150));
+        assert_eq!(alice.inode_usage(), Some(12));
+
+        // the block grace column shifts the inode columns along
+        let bob = LinuxEngine::parse_quota_for_name(output, "bob")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(bob.limit().inodes(), Some(150));
+        assert_eq!(bob.inode_usage(), Some(5));
+
+        // an inode hard limit of 0 is unlimited
+        let root = LinuxEngine::parse_quota_for_name(output, "root")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(root.is_unlimited());
+        assert_eq!(root.limit().inodes(), Some(0));
+        assert_eq!(root.inode_usage(), Some(3));
+
+        let missing = LinuxEngine::parse_quota_for_name(output, "carol")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(missing.is_unlimited());
+        assert_eq!(missing.inode_usage(), None);
+    }
+
+    #[test]
+    fn test_requested_inode_limit_overrides_the_volume_default() {
+        let limit = QuotaLimit::limited(StorageSize::from_gigabytes(1.0));
+        assert_eq!(LinuxEngine::limit_to_inodes(&limit, Some(1000)), 1000);
+        assert_eq!(LinuxEngine::limit_to_inodes(&limit, None), 0);
+        assert_eq!(
+            LinuxEngine::limit_to_inodes(&limit.with_inodes(500), Some(1000)),
+            500
+        );
+        assert_eq!(
+            LinuxEngine::limit_to_inodes(&limit.with_inodes(0), Some(1000)),
+            0
+        );
+    }
+}
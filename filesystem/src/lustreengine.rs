@@ -8,8 +8,6 @@ use chrono::Utc;
 use greatwestern::grammar::{ProjectMapping, UserMapping};
 use greatwestern::storage::{Quota, QuotaLimit, StorageSize, StorageUsage, Volume};
 use once_cell::sync::Lazy;
-use rand::seq::IteratorRandom;
-use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
@@ -21,6 +19,7 @@ use tokio::process::Command;
 use tokio::sync::Mutex;
 use tokio::time::timeout;
 
+use crate::runnerpool::RunnerPool;
 use crate::volumeconfig::{ProjectVolumeConfig, UserVolumeConfig};
 
 /// Quota ID strategy for generating unique lustre quota identifiers.
@@ -488,7 +487,7 @@ struct LfsRunner {
     lfs_command: String,
 }
 
-static LFS_RUNNERS: Lazy<Mutex<Vec<Arc<Mutex<LfsRunner>>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static LFS_RUNNERS: Lazy<RunnerPool<LfsRunner>> = Lazy::new(|| RunnerPool::new("LFS"));
 
 #[derive(Debug)]
 pub struct LockedRunner {
@@ -496,23 +495,16 @@ pub struct LockedRunner {
 }
 
 pub async fn set_command(lfs: &str, max_lfs_runners: usize) {
-    tracing::debug!(
-        "Using command line lfs commands: {}, max_lfs_runners: {}",
-        lfs,
-        max_lfs_runners
-    );
-
-    // make sure we have at least one runner
-    let max_lfs_runners = max_lfs_runners.max(1);
-    let mut runners = LFS_RUNNERS.lock().await;
-
-    runners.clear();
-
-    for _ in 0..max_lfs_runners {
-        runners.push(Arc::new(Mutex::new(LfsRunner {
-            lfs_command: lfs.to_string(),
-        })));
-    }
+    tracing::debug!("Using command line lfs commands: {}", lfs);
+
+    LFS_RUNNERS
+        .set(
+            LfsRunner {
+                lfs_command: lfs.to_string(),
+            },
+            max_lfs_runners,
+        )
+        .await;
 }
 
 impl LockedRunner {
@@ -575,36 +567,9 @@ impl LockedRunner {
 // that we can only run a small number of lfs commands at a time, thereby not
 // overloading the server
 pub async fn runner(expires: &chrono::DateTime<Utc>) -> Result<LockedRunner, Error> {
-    let runners = LFS_RUNNERS.lock().await;
-
-    if runners.is_empty() {
-        return Err(Error::Call(
-            "No LFS runners have been configured".to_string(),
-        ));
-    }
-
-    let mut rng = rand::rngs::StdRng::from_os_rng();
-
-    loop {
-        // try all the runners in a random order
-        for runner in runners.iter().choose_multiple(&mut rng, runners.len()) {
-            assert_not_expired(expires)?;
-
-            match runner.clone().try_lock_owned() {
-                Ok(guard) => {
-                    return Ok(LockedRunner { runner: guard });
-                }
-                Err(_) => {
-                    // the runner is already locked, so try the next one
-                    continue;
-                }
-            }
-        }
-
-        // wait a bit before trying again
-        assert_not_expired(expires)?;
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-    }
+    Ok(LockedRunner {
+        runner: LFS_RUNNERS.runner(expires).await?,
+    })
 }
 
 /// Command queue for slow operations (like lfs project -srp)
@@ -1115,9 +1080,9 @@ impl LustreEngine {
         mount_point: &str,
         expires: &chrono::DateTime<Utc>,
     ) -> Result<(), Error> {
-        let block_limit = match limit {
-            QuotaLimit::Unlimited => "0".to_string(), // 0 means unlimited in Lustre
-            QuotaLimit::Limited(storage) => {
+        let block_limit = match limit.size() {
+            None => "0".to_string(), // 0 means unlimited in Lustre
+            Some(storage) => {
                 // Convert to MB (Lustre uses MB units)
                 let mb = storage.as_megabytes();
                 format!("{}M", mb)
@@ -1133,11 +1098,11 @@ impl LustreEngine {
 
         if is_dry_run() || is_lustre_id_only() {
             tracing::info!(
-                "DRY RUN: {} setquota -p {} -B {} -I {}k {}",
+                "DRY RUN: {} setquota -p {} -B {} -I {} {}",
                 self.config.lfs_command(),
                 project_id,
                 block_limit,
-                inode_limit / 1000,
+                inode_limit,
                 mount_point
             );
             return Ok(());
@@ -1151,7 +1116,7 @@ impl LustreEngine {
                 "-B",
                 &block_limit,
                 "-I",
-                &format!("{}k", inode_limit / 1000), // Lustre uses k suffix for thousands
+                &inode_limit.to_string(), // 0 means unlimited in Lustre
                 mount_point,
             ],
             self.config.command_timeout(),
@@ -1179,7 +1144,7 @@ impl LustreEngine {
                 mount_point
             );
             return Ok(Quota::with_usage(
-                QuotaLimit::Unlimited,
+                QuotaLimit::unlimited(),
                 StorageUsage::new(StorageSize::from_kilobytes(0.0)),
             ));
         }
@@ -1212,7 +1177,7 @@ impl LustreEngine {
                 mount_point
             );
             return Ok(Quota::with_usage(
-                QuotaLimit::Unlimited,
+                QuotaLimit::unlimited(),
                 StorageUsage::new(StorageSize::from_kilobytes(0.0)),
             ));
         }
@@ -1249,7 +1214,7 @@ impl LustreEngine {
                 mount_point
             );
             return Ok(Quota::with_usage(
-                QuotaLimit::Unlimited,
+                QuotaLimit::unlimited(),
                 StorageUsage::new(StorageSize::from_kilobytes(0.0)),
             ));
         }
@@ -1379,14 +1344,34 @@ impl LustreEngine {
                 trimmed
             );
 
+            // parts[5] = current number of files
+            // parts[7] = hard limit on files (0 = unlimited)
+            // These are optional, as older output may not include them
+            let files_used = parts
+                .get(5)
+                .and_then(|f| f.trim_end_matches('*').parse::<u64>().ok());
+            let files_limit = parts
+                .get(7)
+                .and_then(|f| f.trim_end_matches('*').parse::<u64>().ok());
+
             let usage = StorageUsage::new(StorageSize::from_kilobytes(usage_kb as f64));
-            let limit = if limit_kb == 0 {
-                QuotaLimit::Unlimited
+            let mut limit = if limit_kb == 0 {
+                QuotaLimit::unlimited()
             } else {
-                QuotaLimit::Limited(StorageSize::from_kilobytes(limit_kb as f64))
+                QuotaLimit::limited(StorageSize::from_kilobytes(limit_kb as f64))
             };
 
-            return Ok(Quota::with_usage(limit, usage));
+            if let Some(files_limit) = files_limit {
+                limit = limit.with_inodes(files_limit);
+            }
+
+            let mut quota = Quota::with_usage(limit, usage);
+
+            if let Some(files_used) = files_used {
+                quota.set_inode_usage(files_used);
+            }
+
+            return Ok(quota);
         }
 
         // If we reach here, we did not find valid quota data
@@ -1458,7 +1443,7 @@ impl LustreEngine {
 
         // Validate that the limit does not exceed the maximum quota for this volume
         if let Some(max_quota) = volume_config.max_quota() {
-            if limit > max_quota {
+            if limit.exceeds(max_quota) {
                 return Err(Error::Failed(format!(
                     "Requested quota limit ({}) exceeds maximum allowed quota ({}) for user {} on volume {}",
                     limit, max_quota, user, volume
@@ -1474,8 +1459,12 @@ impl LustreEngine {
             ))
         })?;
 
-        // Get inode limit (use configured value or large default)
-        let inode_limit = volume_config.default_inode_limit().unwrap_or(1_000_000);
+        // Get inode limit (use the requested limit, else the configured
+        // default, else a large default)
+        let inode_limit = limit
+            .inodes()
+            .or(volume_config.default_inode_limit())
+            .unwrap_or(1_000_000);
 
         // loop over all path configs and set lustre ids
         let mut all_set = true;
@@ -1493,7 +1482,7 @@ impl LustreEngine {
                 "Skipping quota limit setting due to OPENPORTAL_LUSTRE_ID_ONLY environment variable"
             );
             return Ok(Quota::with_usage(
-                QuotaLimit::Unlimited,
+                QuotaLimit::unlimited(),
                 StorageUsage::from(0),
             ));
         }
@@ -1580,7 +1569,7 @@ impl LustreEngine {
 
         // Validate that the limit does not exceed the maximum quota for this volume
         if let Some(max_quota) = volume_config.max_quota() {
-            if limit > max_quota {
+            if limit.exceeds(max_quota) {
                 return Err(Error::Failed(format!(
                     "Requested quota limit ({}) exceeds maximum allowed quota ({}) for project {} on volume {}",
                     limit, max_quota, project, volume
@@ -1596,8 +1585,12 @@ impl LustreEngine {
             ))
         })?;
 
-        // Get inode limit (use configured value or large default)
-        let inode_limit = volume_config.default_inode_limit().unwrap_or(1_000_000);
+        // Get inode limit (use the requested limit, else the configured
+        // default, else a large default)
+        let inode_limit = limit
+            .inodes()
+            .or(volume_config.default_inode_limit())
+            .unwrap_or(1_000_000);
 
         // loop over all path configs and set lustre ids
         let mut all_set = true;
@@ -1615,7 +1608,7 @@ impl LustreEngine {
                 "Skipping quota limit setting due to OPENPORTAL_LUSTRE_ID_ONLY environment variable"
             );
             return Ok(Quota::with_usage(
-                QuotaLimit::Unlimited,
+                QuotaLimit::unlimited(),
                 StorageUsage::from(0),
             ));
         }
@@ -1709,7 +1702,7 @@ impl LustreEngine {
         if quota_id == 0 {
             // return unlimited quota as the user is uninitialized
             return Ok(Quota::with_usage(
-                QuotaLimit::Unlimited,
+                QuotaLimit::unlimited(),
                 StorageUsage::from(0),
             ));
         }
@@ -1796,7 +1789,7 @@ impl LustreEngine {
         if quota_id == 0 {
             // return unlimited quota as the project is uninitialized
             return Ok(Quota::with_usage(
-                QuotaLimit::Unlimited,
+                QuotaLimit::unlimited(),
                 StorageUsage::from(0),
             ));
         }
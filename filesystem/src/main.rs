@@ -6,8 +6,10 @@ use chrono::Utc;
 
 use greatwestern::grammar::Instruction::{
     AddLocalProject, AddLocalUser, ClearLocalProjectQuota, ClearLocalUserQuota, GetLocalHomeDir,
-    GetLocalProjectDirs, GetLocalProjectQuota, GetLocalProjectQuotas, GetLocalStorageReport,
-    GetLocalUserDirs, GetLocalUserQuota, GetLocalUserQuotas, RemoveLocalProject, RemoveLocalUser,
+    GetLocalProjectDirs, GetLocalProjectQuota, GetLocalProjectQuotas, GetLocalRecycledProjectDirs,
+    GetLocalRecycledUserDirs, GetLocalStorageReport, GetLocalUserDirs, GetLocalUserQuota,
+    GetLocalUserQuotas, PurgeLocalRecycledProjectDirs, PurgeLocalRecycledUserDirs,
+    RemoveLocalProject, RemoveLocalUser, RestoreLocalProjectDir, RestoreLocalUserDir,
     SetLocalProjectQuota, SetLocalUserQuota,
 };
 use greatwestern::grammar::{Date, ProjectMapping, UserMapping};
@@ -19,6 +21,7 @@ use templemeads::agent::filesystem::{process_args, run, Defaults};
 use templemeads::agent::Type as AgentType;
 use templemeads::async_runnable;
 use templemeads::notification::default_notify_runner;
+use templemeads::portal_identifier::PortalIdentifier;
 use templemeads::set_notify_runner;
 use templemeads::Error;
 
@@ -26,13 +29,19 @@ type Envelope = templemeads::job::Envelope<Hpc>;
 type Job = templemeads::job::Job<Hpc>;
 
 mod cache;
+mod cephfsengine;
 mod fakequotaengine;
 mod filesystem;
+mod gpfsengine;
 mod linuxquotaengine;
 mod lustreengine;
 mod nameservice;
 mod quotaengine;
+mod recyclebin;
+mod runnerpool;
+mod storagehistory;
 mod volumeconfig;
+mod zfsengine;
 
 use volumeconfig::FilesystemConfig;
 
@@ -101,6 +110,52 @@ async fn main() -> Result<()> {
     };
     filesystem::set_exec_prefix(exec_prefix)?;
 
+    // Daily storage snapshots, so that storage reports can be given for
+    // past dates. Set to an empty string to only report on today.
+    let storage_report_dir = config.option(
+        "storage-report-dir",
+        &dirs::data_local_dir()
+            .unwrap_or(".".into())
+            .join("openportal")
+            .join("filesystem-storage-reports")
+            .to_string_lossy(),
+    );
+
+    // Portals whose existing projects are looked up at startup, so that
+    // projects added before history was kept are snapshotted too.
+    let storage_report_portals = config
+        .option("storage-report-portals", "")
+        .split(',')
+        .map(|portal| portal.trim())
+        .filter(|portal| !portal.is_empty())
+        .map(PortalIdentifier::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !storage_report_dir.trim().is_empty() {
+        storagehistory::enable(
+            std::path::Path::new(storage_report_dir.trim()),
+            &config.service().name(),
+            &storage_report_portals,
+        )
+        .await?;
+    }
+
+    // Removed directories are kept in the recycle bin for this many days,
+    // and then purged. Set to 0 to keep them until they are purged explicitly.
+    let recycle_retention_days = config.option("recycle-retention-days", "30");
+
+    match recycle_retention_days.trim().parse::<u32>() {
+        Ok(days) => recyclebin::enable(days).await?,
+        Err(_) => {
+            return Err(Error::Misconfigured(format!(
+                "Invalid recycle-retention-days '{}' - this should be a whole number \
+                 of days, or 0 to keep recycled directories until they are purged",
+                recycle_retention_days
+            ))
+            .into());
+        }
+    }
+
     async_runnable! {
         ///
         /// Runnable function that will be called when a job is received
@@ -114,24 +169,45 @@ async fn main() -> Result<()> {
 
             match job.instruction() {
                 GetLocalStorageReport(mapping, dates) => {
-                    let today = Date::today().day();
-                    if dates != today {
-                        return job.errored(&format!(
-                            "Storage reports only support today's date; requested range: {}",
+                    // only today's storage can be measured - earlier days
+                    // come from the recorded snapshots
+                    let report = match storagehistory::includes_today(&dates) {
+                        true => Some(get_local_storage_report(
+                            me.name(), &sender, &mapping, job.expires()
+                        ).await?),
+                        false => None,
+                    };
+
+                    if let Err(e) = storagehistory::record(&mapping, &sender, report.clone()).await {
+                        tracing::error!("Could not record the storage report of {}: {}", mapping, e);
+                    }
+
+                    match (storagehistory::get(&mapping, &dates).await?, report) {
+                        (Some(history), _) => job.completed(history),
+                        (None, Some(report)) if dates == Date::today().day() => job.completed(report),
+                        _ => job.errored(&format!(
+                            "Storage reports for past dates need the storage-report-dir option; \
+                             requested range: {}",
                             dates
-                        ));
+                        )),
                     }
-                    let report = get_local_storage_report(
-                        me.name(), &sender, &mapping, job.expires()
-                    ).await?;
-                    job.completed(report)
                 },
                 AddLocalProject(mapping) => {
                     create_project_dirs_and_links(&mapping, job.expires()).await?;
+
+                    if let Err(e) = storagehistory::record(&mapping, &sender, None).await {
+                        tracing::error!("Could not start the storage history of {}: {}", mapping, e);
+                    }
+
                     job.completed_none()
                 },
                 RemoveLocalProject(mapping) => {
-                    remove_project_dirs_and_links(&mapping).await?;
+                    remove_project_dirs_and_links(&mapping, job.expires()).await?;
+
+                    if let Err(e) = storagehistory::retire(&mapping).await {
+                        tracing::error!("Could not retire the storage history of {}: {}", mapping, e);
+                    }
+
                     job.completed_none()
                 },
                 AddLocalUser(mapping) => {
@@ -139,7 +215,7 @@ async fn main() -> Result<()> {
                     job.completed_none()
                 },
                 RemoveLocalUser(mapping) => {
-                    remove_user_dirs(&mapping).await?;
+                    remove_user_dirs(&mapping, job.expires()).await?;
                     job.completed_none()
                 },
                 GetLocalHomeDir(mapping) => {
@@ -195,6 +271,30 @@ async fn main() -> Result<()> {
 
                     job.completed(project_dirs)
                 },
+                GetLocalRecycledUserDirs(mapping) => {
+                    let recycled = recyclebin::get_recycled_user_dirs(&mapping).await?;
+                    job.completed(recycled)
+                },
+                GetLocalRecycledProjectDirs(mapping) => {
+                    let recycled = recyclebin::get_recycled_project_dirs(&mapping).await?;
+                    job.completed(recycled)
+                },
+                RestoreLocalUserDir(mapping, volume, version) => {
+                    let restored = recyclebin::restore_user_dir(&mapping, &volume, &version).await?;
+                    job.completed(restored)
+                },
+                RestoreLocalProjectDir(mapping, volume, version) => {
+                    let restored = recyclebin::restore_project_dir(&mapping, &volume, &version).await?;
+                    job.completed(restored)
+                },
+                PurgeLocalRecycledUserDirs(mapping) => {
+                    let purged = recyclebin::purge_recycled_user_dirs(&mapping).await?;
+                    job.completed(purged)
+                },
+                PurgeLocalRecycledProjectDirs(mapping) => {
+                    let purged = recyclebin::purge_recycled_project_dirs(&mapping).await?;
+                    job.completed(purged)
+                },
                 SetLocalProjectQuota(mapping, volume, limit) => {
                     let quota = set_project_quota(&mapping, &volume, &limit, job.expires()).await?;
                     job.completed(quota)
@@ -254,18 +354,56 @@ async fn create_project_dirs_and_links(
     // create all of the project volume directories first
     for (volume, volume_config) in config.get_project_volumes() {
         tracing::info!("Creating project volume: {}", volume);
-        for path_config in volume_config.path_configs() {
+
+        let engine = match volume_config.quota_engine_name() {
+            Some(engine_name) => Some(config.get_quota_engine(engine_name)?),
+            None => None,
+        };
+
+        for (i, path_config) in volume_config.path_configs().iter().enumerate() {
             match path_config.path(mapping.clone().into()) {
                 Ok(path) => {
                     tracing::info!("    - Directory path to create: {}", path.to_string_lossy());
-                    filesystem::create_dir(
-                        &path,
-                        &config.all_roots(),
-                        "root",
-                        mapping.local_group(),
-                        path_config.permission(),
-                    )
-                    .await?;
+
+                    // the quota engine may create the volume's first directory
+                    // itself, e.g. as a GPFS fileset linked at this path
+                    let created_by_engine = match (&engine, i) {
+                        (Some(engine), 0) => {
+                            let path =
+                                filesystem::clean_and_check_path(&path, &config.all_roots(), false)
+                                    .await?;
+                            engine
+                                .create_project_dir(
+                                    mapping,
+                                    &volume,
+                                    &volume_config,
+                                    &path,
+                                    expires,
+                                )
+                                .await?
+                        }
+                        _ => false,
+                    };
+
+                    if created_by_engine {
+                        filesystem::set_dir_ownership(
+                            &path,
+                            &config.all_roots(),
+                            "root",
+                            mapping.local_group(),
+                            path_config.permission(),
+                        )
+                        .await?;
+                    } else {
+                        filesystem::create_dir(
+                            &path,
+                            &config.all_roots(),
+                            "root",
+                            mapping.local_group(),
+                            path_config.permission(),
+                        )
+                        .await?;
+                    }
                 }
                 Err(error) => {
                     tracing::warn!("Could not get path for creation: {}", error);
@@ -362,18 +500,49 @@ async fn create_user_dirs(
     for (volume, volume_config) in config.get_user_volumes() {
         tracing::info!("Creating user volume: {}", volume);
 
-        for path_config in volume_config.path_configs() {
+        let engine = match volume_config.quota_engine_name() {
+            Some(engine_name) => Some(config.get_quota_engine(engine_name)?),
+            None => None,
+        };
+
+        for (i, path_config) in volume_config.path_configs().iter().enumerate() {
             match path_config.path(mapping.clone().into()) {
                 Ok(path) => {
                     tracing::info!("    - User directory to create: {}", path.to_string_lossy());
-                    filesystem::create_dir(
-                        &path,
-                        &config.all_roots(),
-                        mapping.local_user().unix()?,
-                        mapping.local_group(),
-                        path_config.permission(),
-                    )
-                    .await?;
+
+                    // the quota engine may create the volume's first directory
+                    // itself, e.g. as a ZFS dataset mounted at this path
+                    let created_by_engine = match (&engine, i) {
+                        (Some(engine), 0) => {
+                            let path =
+                                filesystem::clean_and_check_path(&path, &config.all_roots(), false)
+                                    .await?;
+                            engine
+                                .create_user_dir(mapping, &volume, &volume_config, &path, expires)
+                                .await?
+                        }
+                        _ => false,
+                    };
+
+                    if created_by_engine {
+                        filesystem::set_dir_ownership(
+                            &path,
+                            &config.all_roots(),
+                            mapping.local_user().unix()?,
+                            mapping.local_group(),
+                            path_config.permission(),
+                        )
+                        .await?;
+                    } else {
+                        filesystem::create_dir(
+                            &path,
+                            &config.all_roots(),
+                            mapping.local_user().unix()?,
+                            mapping.local_group(),
+                            path_config.permission(),
+                        )
+                        .await?;
+                    }
                 }
                 Err(error) => {
                     tracing::warn!("Could not get path for creation: {}", error);
@@ -418,14 +587,24 @@ async fn create_user_dirs(
 
 ///
 /// Remove (recycle) the project directories, links, and home roots for a given ProjectMapping.
-/// This is non-destructive - directories are moved to .recycle subdirectories.
+/// This is non-destructive - directories are moved to .recycle subdirectories, apart from
+/// filesets or datasets of a quota engine, which the engine detaches instead.
 ///
-async fn remove_project_dirs_and_links(mapping: &ProjectMapping) -> Result<(), Error> {
+async fn remove_project_dirs_and_links(
+    mapping: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<(), Error> {
     let config = cache::get_filesystem_config().await?;
 
     for (volume, volume_config) in config.get_project_volumes() {
         tracing::info!("Removing project volume: {}", volume);
-        for path_config in volume_config.path_configs() {
+
+        let engine = match volume_config.quota_engine_name() {
+            Some(engine_name) => Some(config.get_quota_engine(engine_name)?),
+            None => None,
+        };
+
+        for (i, path_config) in volume_config.path_configs().iter().enumerate() {
             if let Ok(Some(link_path)) = path_config.link_path(mapping.clone().into()) {
                 tracing::info!("    - Link path to remove: {}", link_path.to_string_lossy());
                 filesystem::remove_link(&link_path, &config.all_roots()).await?;
@@ -434,7 +613,33 @@ async fn remove_project_dirs_and_links(mapping: &ProjectMapping) -> Result<(), E
             match path_config.path(mapping.clone().into()) {
                 Ok(path) => {
                     tracing::info!("    - Directory path to remove: {}", path.to_string_lossy());
-                    filesystem::recycle_dir(&path, &config.all_roots()).await?;
+
+                    // a directory created by the quota engine is removed by it
+                    let removed_by_engine = match (&engine, i) {
+                        (Some(engine), 0) => {
+                            let path =
+                                filesystem::clean_and_check_path(&path, &config.all_roots(), false)
+                                    .await?;
+                            engine
+                                .remove_project_dir(
+                                    mapping,
+                                    &volume,
+                                    &volume_config,
+                                    &path,
+                                    expires,
+                                )
+                                .await?
+                        }
+                        _ => false,
+                    };
+
+                    // what the engine detached stays with the engine - it
+                    // is not moved to (or purged from) the recycle bin
+                    if removed_by_engine {
+                        tracing::info!("    - Detached by the quota engine, not recycled");
+                    } else {
+                        filesystem::recycle_dir(&path, &config.all_roots()).await?;
+                    }
                 }
                 Err(error) => {
                     tracing::warn!("Could not get path for removal: {}", error);
@@ -464,22 +669,51 @@ async fn remove_project_dirs_and_links(mapping: &ProjectMapping) -> Result<(), E
 
 ///
 /// Remove (recycle) the user's home directories in all home roots.
-/// This is non-destructive - directories are moved to .recycle subdirectories.
+/// This is non-destructive - directories are moved to .recycle subdirectories, apart from
+/// datasets of a quota engine, which the engine detaches instead.
 ///
-async fn remove_user_dirs(mapping: &UserMapping) -> Result<(), Error> {
+async fn remove_user_dirs(
+    mapping: &UserMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<(), Error> {
     let config = cache::get_filesystem_config().await?;
 
     for (volume, volume_config) in config.get_user_volumes() {
         tracing::info!("Removing user volume: {}", volume);
 
-        for path_config in volume_config.path_configs() {
+        let engine = match volume_config.quota_engine_name() {
+            Some(engine_name) => Some(config.get_quota_engine(engine_name)?),
+            None => None,
+        };
+
+        for (i, path_config) in volume_config.path_configs().iter().enumerate() {
             match path_config.path(mapping.clone().into()) {
                 Ok(path) => {
                     tracing::info!(
                         "    - Home directory path to remove: {}",
                         path.to_string_lossy()
                     );
-                    filesystem::recycle_dir(&path, &config.all_roots()).await?;
+
+                    // a directory created by the quota engine is removed by it
+                    let removed_by_engine = match (&engine, i) {
+                        (Some(engine), 0) => {
+                            let path =
+                                filesystem::clean_and_check_path(&path, &config.all_roots(), false)
+                                    .await?;
+                            engine
+                                .remove_user_dir(mapping, &volume, &volume_config, &path, expires)
+                                .await?
+                        }
+                        _ => false,
+                    };
+
+                    // what the engine detached stays with the engine - it
+                    // is not moved to (or purged from) the recycle bin
+                    if removed_by_engine {
+                        tracing::info!("    - Detached by the quota engine, not recycled");
+                    } else {
+                        filesystem::recycle_dir(&path, &config.all_roots()).await?;
+                    }
                 }
                 Err(error) => {
                     tracing::warn!("Could not get path for removal: {}", error);
@@ -4,7 +4,7 @@
 //! Quota engine framework for managing filesystem quotas across different storage backends.
 //!
 //! This module provides an abstraction layer for setting and retrieving storage quotas
-//! on different filesystem types (Lustre, Ceph, VAST, etc.). Each filesystem type
+//! on different filesystem types (Lustre, Storage Scale, CephFS, ZFS, etc.). Each filesystem type
 //! implements the `QuotaEngine` trait to provide backend-specific quota management.
 
 use anyhow::Result;
@@ -12,12 +12,16 @@ use chrono::Utc;
 use greatwestern::grammar::{ProjectMapping, UserMapping};
 use greatwestern::storage::{Quota, QuotaLimit, Volume};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use templemeads::Error;
 
+use crate::cephfsengine::{CephfsEngine, CephfsEngineConfig};
 use crate::fakequotaengine::{FakeEngine, FakeQuotaEngineConfig};
+use crate::gpfsengine::{GpfsEngine, GpfsEngineConfig};
 use crate::linuxquotaengine::{LinuxEngine, LinuxQuotaEngineConfig};
 use crate::lustreengine::{LustreEngine, LustreEngineConfig};
 use crate::volumeconfig::{ProjectVolumeConfig, UserVolumeConfig};
+use crate::zfsengine::{ZfsEngine, ZfsEngineConfig};
 
 /// Configuration for creating quota engines.
 ///
@@ -30,10 +34,15 @@ pub enum QuotaEngineConfig {
     Lustre(LustreEngineConfig),
     #[serde(rename = "linux")]
     Linux(LinuxQuotaEngineConfig),
+    #[serde(rename = "gpfs")]
+    Gpfs(GpfsEngineConfig),
+    #[serde(rename = "cephfs")]
+    Cephfs(CephfsEngineConfig),
+    #[serde(rename = "zfs")]
+    Zfs(ZfsEngineConfig),
     #[serde(rename = "fake")]
     Fake(FakeQuotaEngineConfig),
     // Future backends can be added here:
-    // Ceph(CephEngineConfig),
     // Vast(VastEngineConfig),
 }
 
@@ -51,6 +60,18 @@ impl QuotaEngineConfig {
                 let engine = LinuxEngine::new(config.clone())?;
                 engine.initialize().await
             }
+            QuotaEngineConfig::Gpfs(config) => {
+                let engine = GpfsEngine::new(config.clone())?;
+                engine.initialize().await
+            }
+            QuotaEngineConfig::Cephfs(config) => {
+                let engine = CephfsEngine::new(config.clone())?;
+                engine.initialize().await
+            }
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                engine.initialize().await
+            }
             QuotaEngineConfig::Fake(config) => {
                 let engine = FakeEngine::new(config.clone())?;
                 engine.initialize().await
@@ -82,6 +103,24 @@ impl QuotaEngineConfig {
                     .set_user_quota(mapping, volume, volume_config, limit, expires)
                     .await
             }
+            QuotaEngineConfig::Gpfs(config) => {
+                let engine = GpfsEngine::new(config.clone())?;
+                engine
+                    .set_user_quota(mapping, volume, volume_config, limit, expires)
+                    .await
+            }
+            QuotaEngineConfig::Cephfs(config) => {
+                let engine = CephfsEngine::new(config.clone())?;
+                engine
+                    .set_user_quota(mapping, volume, volume_config, limit, expires)
+                    .await
+            }
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                engine
+                    .set_user_quota(mapping, volume, volume_config, limit, expires)
+                    .await
+            }
             QuotaEngineConfig::Fake(config) => {
                 let engine = FakeEngine::new(config.clone())?;
                 engine
@@ -115,6 +154,24 @@ impl QuotaEngineConfig {
                     .set_project_quota(mapping, volume, volume_config, limit, expires)
                     .await?)
             }
+            QuotaEngineConfig::Gpfs(config) => {
+                let engine = GpfsEngine::new(config.clone())?;
+                Ok(engine
+                    .set_project_quota(mapping, volume, volume_config, limit, expires)
+                    .await?)
+            }
+            QuotaEngineConfig::Cephfs(config) => {
+                let engine = CephfsEngine::new(config.clone())?;
+                Ok(engine
+                    .set_project_quota(mapping, volume, volume_config, limit, expires)
+                    .await?)
+            }
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                Ok(engine
+                    .set_project_quota(mapping, volume, volume_config, limit, expires)
+                    .await?)
+            }
             QuotaEngineConfig::Fake(config) => {
                 let engine = FakeEngine::new(config.clone())?;
                 Ok(engine
@@ -147,6 +204,24 @@ impl QuotaEngineConfig {
                     .get_user_quota(mapping, volume, volume_config, expires)
                     .await?)
             }
+            QuotaEngineConfig::Gpfs(config) => {
+                let engine = GpfsEngine::new(config.clone())?;
+                Ok(engine
+                    .get_user_quota(mapping, volume, volume_config, expires)
+                    .await?)
+            }
+            QuotaEngineConfig::Cephfs(config) => {
+                let engine = CephfsEngine::new(config.clone())?;
+                Ok(engine
+                    .get_user_quota(mapping, volume, volume_config, expires)
+                    .await?)
+            }
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                Ok(engine
+                    .get_user_quota(mapping, volume, volume_config, expires)
+                    .await?)
+            }
             QuotaEngineConfig::Fake(config) => {
                 let engine = FakeEngine::new(config.clone())?;
                 Ok(engine
@@ -179,6 +254,24 @@ impl QuotaEngineConfig {
                     .get_project_quota(mapping, volume, volume_config, expires)
                     .await?)
             }
+            QuotaEngineConfig::Gpfs(config) => {
+                let engine = GpfsEngine::new(config.clone())?;
+                Ok(engine
+                    .get_project_quota(mapping, volume, volume_config, expires)
+                    .await?)
+            }
+            QuotaEngineConfig::Cephfs(config) => {
+                let engine = CephfsEngine::new(config.clone())?;
+                Ok(engine
+                    .get_project_quota(mapping, volume, volume_config, expires)
+                    .await?)
+            }
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                Ok(engine
+                    .get_project_quota(mapping, volume, volume_config, expires)
+                    .await?)
+            }
             QuotaEngineConfig::Fake(config) => {
                 let engine = FakeEngine::new(config.clone())?;
                 Ok(engine
@@ -211,6 +304,24 @@ impl QuotaEngineConfig {
                     .clear_user_quota(mapping, volume, volume_config, expires)
                     .await
             }
+            QuotaEngineConfig::Gpfs(config) => {
+                let engine = GpfsEngine::new(config.clone())?;
+                engine
+                    .clear_user_quota(mapping, volume, volume_config, expires)
+                    .await
+            }
+            QuotaEngineConfig::Cephfs(config) => {
+                let engine = CephfsEngine::new(config.clone())?;
+                engine
+                    .clear_user_quota(mapping, volume, volume_config, expires)
+                    .await
+            }
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                engine
+                    .clear_user_quota(mapping, volume, volume_config, expires)
+                    .await
+            }
             QuotaEngineConfig::Fake(config) => {
                 let engine = FakeEngine::new(config.clone())?;
                 engine
@@ -243,6 +354,24 @@ impl QuotaEngineConfig {
                     .clear_project_quota(mapping, volume, volume_config, expires)
                     .await
             }
+            QuotaEngineConfig::Gpfs(config) => {
+                let engine = GpfsEngine::new(config.clone())?;
+                engine
+                    .clear_project_quota(mapping, volume, volume_config, expires)
+                    .await
+            }
+            QuotaEngineConfig::Cephfs(config) => {
+                let engine = CephfsEngine::new(config.clone())?;
+                engine
+                    .clear_project_quota(mapping, volume, volume_config, expires)
+                    .await
+            }
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                engine
+                    .clear_project_quota(mapping, volume, volume_config, expires)
+                    .await
+            }
             QuotaEngineConfig::Fake(config) => {
                 let engine = FakeEngine::new(config.clone())?;
                 engine
@@ -273,10 +402,174 @@ impl QuotaEngineConfig {
                 // the filesystem path already stored in the engine config.
                 Ok(())
             }
+            QuotaEngineConfig::Gpfs(_config) => {
+                // The GPFS engine names filesets after projects, so needs no
+                // per-volume configuration.
+                Ok(())
+            }
+            QuotaEngineConfig::Cephfs(_config) => {
+                // CephFS quotas live on the volume's own directories.
+                Ok(())
+            }
+            QuotaEngineConfig::Zfs(_config) => {
+                // ZFS datasets are named after projects and users under the
+                // engine's parent dataset.
+                Ok(())
+            }
             QuotaEngineConfig::Fake(_config) => {
                 // Fake quota engine requires no per-volume configuration.
                 Ok(())
             }
         }
     }
+
+    ///
+    /// Return whether this engine creates project directories itself
+    /// (as GPFS filesets or ZFS datasets). On removal it detaches them
+    /// instead of the caller recycling them, so they never enter the
+    /// recycle bin.
+    ///
+    pub fn manages_project_dirs(&self) -> bool {
+        matches!(self, QuotaEngineConfig::Gpfs(_) | QuotaEngineConfig::Zfs(_))
+    }
+
+    ///
+    /// Return whether this engine creates user directories itself (as
+    /// ZFS datasets, if `user_datasets` is set). As for projects, these
+    /// never enter the recycle bin.
+    ///
+    pub fn manages_user_dirs(&self) -> bool {
+        match self {
+            QuotaEngineConfig::Zfs(config) => config.user_datasets(),
+            _ => false,
+        }
+    }
+
+    ///
+    /// Create the directory for a project on a volume, if this engine
+    /// manages project directories itself (e.g. as GPFS filesets or ZFS
+    /// datasets).
+    /// Returns `true` if the engine created (or already had) the
+    /// directory, or `false` if the caller should create it as a plain
+    /// directory
+    ///
+    pub async fn create_project_dir(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        volume_config: &ProjectVolumeConfig,
+        path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        match self {
+            QuotaEngineConfig::Gpfs(config) => {
+                let engine = GpfsEngine::new(config.clone())?;
+                engine
+                    .create_project_dir(mapping, volume, volume_config, path, expires)
+                    .await
+            }
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                engine
+                    .create_project_dir(mapping, volume, volume_config, path, expires)
+                    .await
+            }
+            QuotaEngineConfig::Lustre(_)
+            | QuotaEngineConfig::Linux(_)
+            | QuotaEngineConfig::Cephfs(_)
+            | QuotaEngineConfig::Fake(_) => Ok(false),
+        }
+    }
+
+    ///
+    /// Remove the directory for a project on a volume, if this engine
+    /// manages project directories itself. Returns `true` if the engine
+    /// removed the directory, or `false` if the caller should recycle
+    /// it as a plain directory
+    ///
+    pub async fn remove_project_dir(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        volume_config: &ProjectVolumeConfig,
+        path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        match self {
+            QuotaEngineConfig::Gpfs(config) => {
+                let engine = GpfsEngine::new(config.clone())?;
+                engine
+                    .remove_project_dir(mapping, volume, volume_config, path, expires)
+                    .await
+            }
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                engine
+                    .remove_project_dir(mapping, volume, volume_config, path, expires)
+                    .await
+            }
+            QuotaEngineConfig::Lustre(_)
+            | QuotaEngineConfig::Linux(_)
+            | QuotaEngineConfig::Cephfs(_)
+            | QuotaEngineConfig::Fake(_) => Ok(false),
+        }
+    }
+
+    ///
+    /// Create the directory for a user on a volume, if this engine manages
+    /// user directories itself (e.g. as ZFS datasets). Returns `true` if
+    /// the engine created (or already had) the directory, or `false` if
+    /// the caller should create it as a plain directory
+    ///
+    pub async fn create_user_dir(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        volume_config: &UserVolumeConfig,
+        path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        match self {
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                engine
+                    .create_user_dir(mapping, volume, volume_config, path, expires)
+                    .await
+            }
+            QuotaEngineConfig::Lustre(_)
+            | QuotaEngineConfig::Linux(_)
+            | QuotaEngineConfig::Gpfs(_)
+            | QuotaEngineConfig::Cephfs(_)
+            | QuotaEngineConfig::Fake(_) => Ok(false),
+        }
+    }
+
+    ///
+    /// Remove the directory for a user on a volume, if this engine manages
+    /// user directories itself. Returns `true` if the engine removed the
+    /// directory, or `false` if the caller should recycle it as a plain
+    /// directory
+    ///
+    pub async fn remove_user_dir(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        volume_config: &UserVolumeConfig,
+        path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        match self {
+            QuotaEngineConfig::Zfs(config) => {
+                let engine = ZfsEngine::new(config.clone())?;
+                engine
+                    .remove_user_dir(mapping, volume, volume_config, path, expires)
+                    .await
+            }
+            QuotaEngineConfig::Lustre(_)
+            | QuotaEngineConfig::Linux(_)
+            | QuotaEngineConfig::Gpfs(_)
+            | QuotaEngineConfig::Cephfs(_)
+            | QuotaEngineConfig::Fake(_) => Ok(false),
+        }
+    }
 }
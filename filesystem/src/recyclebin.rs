@@ -0,0 +1,425 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! The recycle bin of removed user and project directories.
+//!
+//! Removing a user or project moves each of its directories into the
+//! `.recycle` directory next to it, as a new version named after the time
+//! it was recycled (see `filesystem::recycle_dir`). Re-adding the user or
+//! project restores the newest version. This module lists every version,
+//! restores a specific one on request, and purges them - either on request,
+//! or by a background task once they are older than the retention period.
+//!
+//! The recycle bin is not used for directories that a volume's quota
+//! engine creates itself - GPFS filesets and ZFS datasets. Removing the
+//! user or project detaches these (unlinks the fileset, or unmounts the
+//! dataset) and leaves them to be restored, by re-adding the user or
+//! project, or destroyed, by the storage admins. They are never listed,
+//! restored or purged here, and the retention period does not apply.
+
+use anyhow::Result;
+use chrono::Utc;
+use greatwestern::grammar::{ProjectMapping, UserMapping};
+use greatwestern::storage::{RecycleVersion, RecycledDir, Volume};
+use std::path::{Path, PathBuf};
+use templemeads::Error;
+
+use crate::cache;
+use crate::filesystem;
+
+/// How often the purge task looks for recycled directories that have expired
+const PURGE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+///
+/// Start the task that purges recycled directories once they have been in
+/// the recycle bin for more than `retention_days`. Nothing is purged
+/// automatically if this is 0.
+///
+pub async fn enable(retention_days: u32) -> Result<(), Error> {
+    let config = cache::get_filesystem_config().await?;
+
+    let engine_managed: Vec<String> = config
+        .get_engine_managed_user_volumes()
+        .into_iter()
+        .chain(config.get_engine_managed_project_volumes())
+        .map(|volume| volume.to_string())
+        .collect();
+
+    if !engine_managed.is_empty() {
+        tracing::warn!(
+            "The recycle bin is not used for the filesets or datasets that the quota \
+             engines create on volume(s) {}. These are detached, not recycled, when their \
+             user or project is removed, and are never purged by this agent.",
+            engine_managed.join(", ")
+        );
+    }
+
+    if retention_days == 0 {
+        tracing::info!("Recycled directories will be kept until they are purged");
+        return Ok(());
+    }
+
+    tracing::info!(
+        "Recycled directories will be purged after {} day(s)",
+        retention_days
+    );
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + std::time::Duration::from_secs(60),
+            PURGE_CHECK_INTERVAL,
+        );
+
+        loop {
+            interval.tick().await;
+            purge_expired(retention_days).await;
+        }
+    });
+
+    Ok(())
+}
+
+///
+/// Purge everything in every recycle bin that was recycled more than
+/// `retention_days` ago
+///
+async fn purge_expired(retention_days: u32) {
+    let config = match cache::get_filesystem_config().await {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("Could not purge expired recycled directories: {}", e);
+            return;
+        }
+    };
+
+    // a retention period too long to subtract is one that nothing has reached
+    let before = match Utc::now().checked_sub_signed(chrono::Duration::days(retention_days.into()))
+    {
+        Some(before) => before,
+        None => return,
+    };
+
+    let roots = config.all_roots();
+
+    // the deepest that a `.recycle` can be below each root
+    let mut depths: Vec<(PathBuf, usize)> = Vec::new();
+
+    let path_configs = config
+        .get_user_volumes()
+        .into_values()
+        .flat_map(|volume_config| volume_config.path_configs())
+        .chain(
+            config
+                .get_project_volumes()
+                .into_values()
+                .flat_map(|volume_config| volume_config.path_configs()),
+        );
+
+    for path_config in path_configs {
+        let root = PathBuf::from(path_config.root());
+        let depth = path_config.recycle_depth();
+
+        match depths.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, d)) => *d = (*d).max(depth),
+            None => depths.push((root, depth)),
+        }
+    }
+
+    for (root, depth) in depths {
+        match filesystem::purge_expired_recycled(&root, depth, &roots, &before).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!(
+                "Purged {} recycled director(ies) in {} that were recycled before {}",
+                purged,
+                root.to_string_lossy(),
+                before
+            ),
+            Err(e) => tracing::error!(
+                "Could not purge the expired recycled directories in {}: {}",
+                root.to_string_lossy(),
+                e
+            ),
+        }
+    }
+}
+
+///
+/// Add every recycled version of the directory at `path` on `volume`
+/// to `recycled`
+///
+async fn add_recycled_versions(
+    volume: &Volume,
+    path: &Path,
+    roots: &[PathBuf],
+    recycled: &mut Vec<RecycledDir>,
+) -> Result<(), Error> {
+    let path_str = path.to_string_lossy();
+
+    for (version, recycled_at) in filesystem::get_recycled_versions(path, roots).await? {
+        recycled.push(RecycledDir::new(volume, &path_str, &version, &recycled_at));
+    }
+
+    Ok(())
+}
+
+///
+/// The directories of a user that are recycled when the user is removed,
+/// with the volume they are on
+///
+async fn user_dirs(mapping: &UserMapping) -> Result<Vec<(Volume, PathBuf)>, Error> {
+    let config = cache::get_filesystem_config().await?;
+
+    let mut dirs = Vec::new();
+
+    for (volume, volume_config) in config.get_user_volumes() {
+        for path_config in volume_config.path_configs() {
+            match path_config.path(mapping.clone().into()) {
+                Ok(path) => dirs.push((volume.clone(), path)),
+                Err(error) => {
+                    tracing::warn!(
+                        "Could not get user directory path for volume {}: {}",
+                        volume,
+                        error
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(dirs)
+}
+
+///
+/// The directories of a project that are recycled when the project is
+/// removed - its project directories, and the roots of its users'
+/// directories - with the volume they are on
+///
+async fn project_dirs(mapping: &ProjectMapping) -> Result<Vec<(Volume, PathBuf)>, Error> {
+    let config = cache::get_filesystem_config().await?;
+
+    let mut dirs = Vec::new();
+
+    for (volume, volume_config) in config.get_project_volumes() {
+        for path_config in volume_config.path_configs() {
+            match path_config.path(mapping.clone().into()) {
+                Ok(path) => dirs.push((volume.clone(), path)),
+                Err(error) => {
+                    tracing::warn!(
+                        "Could not get project directory path for volume {}: {}",
+                        volume,
+                        error
+                    );
+                }
+            }
+        }
+    }
+
+    for (volume, volume_config) in config.get_user_volumes() {
+        for path_config in volume_config.path_configs() {
+            match path_config.project_path(mapping) {
+                Ok(path) => dirs.push((volume.clone(), path)),
+                Err(error) => {
+                    tracing::warn!(
+                        "Could not get user directory root for volume {}: {}",
+                        volume,
+                        error
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(dirs)
+}
+
+///
+/// Return every recycled version of the directories of a user
+///
+pub async fn get_recycled_user_dirs(mapping: &UserMapping) -> Result<Vec<RecycledDir>, Error> {
+    let roots = cache::get_filesystem_config().await?.all_roots();
+
+    let mut recycled = Vec::new();
+
+    for (volume, path) in user_dirs(mapping).await? {
+        add_recycled_versions(&volume, &path, &roots, &mut recycled).await?;
+    }
+
+    Ok(recycled)
+}
+
+///
+/// Return every recycled version of the directories of a project
+///
+pub async fn get_recycled_project_dirs(
+    mapping: &ProjectMapping,
+) -> Result<Vec<RecycledDir>, Error> {
+    let roots = cache::get_filesystem_config().await?.all_roots();
+
+    let mut recycled = Vec::new();
+
+    for (volume, path) in project_dirs(mapping).await? {
+        add_recycled_versions(&volume, &path, &roots, &mut recycled).await?;
+    }
+
+    Ok(recycled)
+}
+
+///
+/// Restore `version` of every directory in `dirs` that is on `volume` and
+/// has that version recycled, owned by `username` and `groupname`.
+/// `engine_managed` are the volumes whose quota engine detaches, rather
+/// than recycles, their directories. Returns the paths of the restored
+/// directories.
+///
+async fn restore_dirs(
+    dirs: Vec<(Volume, PathBuf)>,
+    engine_managed: &[Volume],
+    volume: &Volume,
+    version: &RecycleVersion,
+    username: &str,
+    groupname: &str,
+) -> Result<Vec<String>, Error> {
+    let roots = cache::get_filesystem_config().await?.all_roots();
+
+    let mut restored = Vec::new();
+
+    for (_, path) in dirs.iter().filter(|(v, _)| v == volume) {
+        let has_version = filesystem::get_recycled_versions(path, &roots)
+            .await?
+            .iter()
+            .any(|(v, _)| v == version);
+
+        if has_version {
+            filesystem::restore_recycled_dir(path, &roots, version, username, groupname).await?;
+            restored.push(path.to_string_lossy().to_string());
+        }
+    }
+
+    if restored.is_empty() && engine_managed.contains(volume) {
+        return Err(Error::NotFound(format!(
+            "There is no version {} of any directory on volume {} in the recycle bin. \
+             The directories that its quota engine creates are detached, not recycled, \
+             on removal - re-adding the user or project attaches them again.",
+            version, volume
+        )));
+    }
+
+    if restored.is_empty() {
+        return Err(Error::NotFound(format!(
+            "There is no version {} of any directory on volume {} in the recycle bin",
+            version, volume
+        )));
+    }
+
+    Ok(restored)
+}
+
+///
+/// Restore `version` of the recycled directories of a user on `volume`,
+/// returning the paths of the restored directories
+///
+pub async fn restore_user_dir(
+    mapping: &UserMapping,
+    volume: &Volume,
+    version: &RecycleVersion,
+) -> Result<Vec<String>, Error> {
+    restore_dirs(
+        user_dirs(mapping).await?,
+        &cache::get_filesystem_config()
+            .await?
+            .get_engine_managed_user_volumes(),
+        volume,
+        version,
+        mapping.local_user().unix()?,
+        mapping.local_group(),
+    )
+    .await
+}
+
+///
+/// Restore `version` of the recycled directories of a project on `volume`,
+/// returning the paths of the restored directories
+///
+pub async fn restore_project_dir(
+    mapping: &ProjectMapping,
+    volume: &Volume,
+    version: &RecycleVersion,
+) -> Result<Vec<String>, Error> {
+    restore_dirs(
+        project_dirs(mapping).await?,
+        &cache::get_filesystem_config()
+            .await?
+            .get_engine_managed_project_volumes(),
+        volume,
+        version,
+        "root",
+        mapping.local_group(),
+    )
+    .await
+}
+
+///
+/// Permanently delete every recycled version of the directories in `dirs`,
+/// returning what was deleted. `engine_managed` are the volumes whose
+/// quota engine detaches, rather than recycles, their directories - what
+/// it detached is not deleted.
+///
+async fn purge_dirs(
+    dirs: Vec<(Volume, PathBuf)>,
+    engine_managed: Vec<Volume>,
+) -> Result<Vec<RecycledDir>, Error> {
+    let roots = cache::get_filesystem_config().await?.all_roots();
+
+    if !engine_managed.is_empty() {
+        tracing::warn!(
+            "Not purging any filesets or datasets detached by the quota engines of \
+             volume(s) {} - these are not in the recycle bin, and must be destroyed \
+             by the storage admins",
+            engine_managed
+                .iter()
+                .map(|volume| volume.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let mut purged = Vec::new();
+
+    for (volume, path) in dirs {
+        let path_str = path.to_string_lossy();
+
+        for (version, recycled_at) in filesystem::purge_recycled_dir(&path, &roots).await? {
+            purged.push(RecycledDir::new(&volume, &path_str, &version, &recycled_at));
+        }
+    }
+
+    Ok(purged)
+}
+
+///
+/// Permanently delete every recycled version of the directories of a user
+///
+pub async fn purge_recycled_user_dirs(mapping: &UserMapping) -> Result<Vec<RecycledDir>, Error> {
+    purge_dirs(
+        user_dirs(mapping).await?,
+        cache::get_filesystem_config()
+            .await?
+            .get_engine_managed_user_volumes(),
+    )
+    .await
+}
+
+///
+/// Permanently delete every recycled version of the directories of a project
+///
+pub async fn purge_recycled_project_dirs(
+    mapping: &ProjectMapping,
+) -> Result<Vec<RecycledDir>, Error> {
+    purge_dirs(
+        project_dirs(mapping).await?,
+        cache::get_filesystem_config()
+            .await?
+            .get_engine_managed_project_volumes(),
+    )
+    .await
+}
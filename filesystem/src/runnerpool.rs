@@ -0,0 +1,122 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+use chrono::Utc;
+use rand::seq::IteratorRandom;
+use rand::SeedableRng;
+use std::sync::Arc;
+use templemeads::job::assert_not_expired;
+use templemeads::Error;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+///
+/// A pool of runners, used by the quota engines to make sure that only
+/// a limited number of their (slow) filesystem commands are run at the
+/// same time, so that the filesystem servers are not overloaded. Each
+/// runner holds whatever is needed to run the commands, e.g. the path
+/// to the command, and is held for as long as a command is running.
+///
+#[derive(Debug)]
+pub struct RunnerPool<T> {
+    name: &'static str,
+    runners: Mutex<Vec<Arc<Mutex<T>>>>,
+}
+
+impl<T: Clone> RunnerPool<T> {
+    ///
+    /// Create an empty pool - `name` names the commands in errors,
+    /// e.g. "LFS"
+    ///
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            runners: Mutex::new(Vec::new()),
+        }
+    }
+
+    ///
+    /// Replace the runners in the pool with `max_runners` copies of
+    /// `runner` (and at least one)
+    ///
+    pub async fn set(&self, runner: T, max_runners: usize) {
+        tracing::debug!("Using max {} runners: {}", self.name, max_runners);
+
+        let mut runners = self.runners.lock().await;
+
+        runners.clear();
+
+        for _ in 0..max_runners.max(1) {
+            runners.push(Arc::new(Mutex::new(runner.clone())));
+        }
+    }
+
+    ///
+    /// Return a runner from the pool, waiting until one is free. The
+    /// runner is returned to the pool when the guard is dropped.
+    ///
+    pub async fn runner(
+        &self,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<OwnedMutexGuard<T>, Error> {
+        let runners = self.runners.lock().await;
+
+        if runners.is_empty() {
+            return Err(Error::Call(format!(
+                "No {} runners have been configured",
+                self.name
+            )));
+        }
+
+        let mut rng = rand::rngs::StdRng::from_os_rng();
+
+        loop {
+            // try all the runners in a random order
+            for runner in runners.iter().choose_multiple(&mut rng, runners.len()) {
+                assert_not_expired(expires)?;
+
+                if let Ok(guard) = runner.clone().try_lock_owned() {
+                    return Ok(guard);
+                }
+            }
+
+            // wait a bit before trying again
+            assert_not_expired(expires)?;
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runners_are_limited_and_returned() {
+        let pool = RunnerPool::new("test");
+        let expires = Utc::now() + chrono::Duration::seconds(5);
+
+        assert!(pool.runner(&expires).await.is_err());
+
+        pool.set("cmd".to_string(), 2).await;
+
+        let first = pool
+            .runner(&expires)
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        let second = pool
+            .runner(&expires)
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(first.as_str(), "cmd");
+        assert_eq!(second.as_str(), "cmd");
+
+        // both runners are busy, so a third waits until the job expires
+        let soon = Utc::now() + chrono::Duration::milliseconds(300);
+        assert!(pool.runner(&soon).await.is_err());
+
+        // ...or until one is returned
+        drop(first);
+        assert!(pool.runner(&expires).await.is_ok());
+    }
+}
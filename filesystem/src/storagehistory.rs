@@ -0,0 +1,639 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! Daily storage report snapshots, kept so that `get_local_storage_report`
+//! can be answered for past dates.
+//!
+//! Quotas can only be read as they are now, so a day that nobody asked
+//! about is lost for good. Each project this agent manages is therefore
+//! snapshotted once a day by a background task, whether or not anyone asks,
+//! and every snapshot - timed or requested - is merged into one JSON file per
+//! project in the history directory. The newest snapshot of each day wins,
+//! exactly as when `ProjectStorageReport`s are added together.
+//!
+//! Projects are normally found as they are added, so at startup the cluster
+//! agents are also asked for the projects of each known portal, so that
+//! projects added before history was kept are snapshotted too.
+
+use anyhow::Result;
+use chrono::Utc;
+use greatwestern::grammar::{Date, DateRange, ProjectMapping};
+use greatwestern::storagereport::ProjectStorageReport;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use templemeads::agent;
+use templemeads::agent::Peer;
+use templemeads::agent::Type as AgentType;
+use templemeads::portal_identifier::PortalIdentifier;
+use templemeads::Error;
+use tokio::sync::Mutex;
+
+/// How often the snapshot task looks for projects not yet snapshotted today
+const SNAPSHOT_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// How long a timed snapshot may take, including asking the sender for the
+/// project's users
+const SNAPSHOT_TIMEOUT_MINUTES: i64 = 5;
+
+///
+/// Everything kept for one project - the history itself, plus what is
+/// needed to take the next snapshot without being asked
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProjectHistory {
+    mapping: ProjectMapping,
+
+    /// The agent that manages this project, which is asked for its users
+    peer: String,
+    zone: String,
+
+    /// False once the project has been removed - its history is kept, but
+    /// no more snapshots are taken
+    active: bool,
+
+    report: ProjectStorageReport,
+}
+
+#[derive(Debug, Default)]
+struct History {
+    dir: Option<PathBuf>,
+}
+
+static HISTORY: Lazy<Mutex<History>> = Lazy::new(|| Mutex::new(History::default()));
+
+fn today() -> Date {
+    Date::from_chrono(&Utc::now().date_naive())
+}
+
+fn history_path(dir: &Path, mapping: &ProjectMapping) -> PathBuf {
+    dir.join(format!("{}.json", mapping.project()))
+}
+
+async fn load(path: &Path) -> Result<Option<ProjectHistory>, Error> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(json) => match serde_json::from_str(&json) {
+            Ok(history) => Ok(Some(history)),
+            Err(e) => Err(Error::Parse(format!(
+                "Could not read the storage history in {}: {}",
+                path.display(),
+                e
+            ))),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::IO(e)),
+    }
+}
+
+async fn save(path: &Path, history: &ProjectHistory) -> Result<(), Error> {
+    let json = serde_json::to_string(history)
+        .map_err(|e| Error::Parse(format!("Could not serialise storage history: {}", e)))?;
+
+    // write then rename, so a crash never leaves a half-written history
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, json).await?;
+    tokio::fs::rename(&tmp, path).await?;
+
+    Ok(())
+}
+
+///
+/// Keep storage report history in `dir`, and start the task that snapshots
+/// every active project in it once a day. `me` is this agent's name, and
+/// `portals` are portals whose existing projects should be found at startup,
+/// in addition to those of the projects already in the history.
+///
+pub async fn enable(dir: &Path, me: &str, portals: &[PortalIdentifier]) -> Result<(), Error> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    HISTORY.lock().await.dir = Some(dir.to_path_buf());
+
+    tracing::info!("Storage report history will be kept in {}", dir.display());
+
+    let me = me.to_string();
+    let portals = portals.to_vec();
+
+    tokio::spawn(async move {
+        // give the agent that manages each project time to connect first
+        let mut interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + std::time::Duration::from_secs(60),
+            SNAPSHOT_CHECK_INTERVAL,
+        );
+
+        interval.tick().await;
+        seed(&me, &portals).await;
+
+        loop {
+            snapshot_all(&me).await;
+            interval.tick().await;
+        }
+    });
+
+    Ok(())
+}
+
+async fn record_in(
+    dir: &Path,
+    mapping: &ProjectMapping,
+    sender: &Peer,
+    report: Option<ProjectStorageReport>,
+) -> Result<(), Error> {
+    let path = history_path(dir, mapping);
+
+    let updated = match load(&path).await? {
+        Some(existing) => ProjectHistory {
+            mapping: mapping.clone(),
+            peer: sender.name().to_string(),
+            zone: sender.zone().to_string(),
+            active: true,
+            report: match report {
+                Some(report) => existing.report + report,
+                None => existing.report,
+            },
+        },
+        None => ProjectHistory {
+            mapping: mapping.clone(),
+            peer: sender.name().to_string(),
+            zone: sender.zone().to_string(),
+            active: true,
+            report: match report {
+                Some(report) => report,
+                // nothing yet - dated in the past, so the first timed
+                // snapshot is still taken today
+                None => ProjectStorageReport::new(mapping.project()).within(&today().prev().day()),
+            },
+        },
+    };
+
+    save(&path, &updated).await
+}
+
+///
+/// Merge `report` into the history of the project in `mapping`, which is
+/// managed by `sender`, and make sure the project is snapshotted daily.
+/// Does nothing if history is not being kept.
+///
+pub async fn record(
+    mapping: &ProjectMapping,
+    sender: &Peer,
+    report: Option<ProjectStorageReport>,
+) -> Result<(), Error> {
+    let history = HISTORY.lock().await;
+
+    match &history.dir {
+        Some(dir) => record_in(dir, mapping, sender, report).await,
+        None => Ok(()),
+    }
+}
+
+async fn retire_in(dir: &Path, mapping: &ProjectMapping) -> Result<(), Error> {
+    let path = history_path(dir, mapping);
+
+    if let Some(mut existing) = load(&path).await? {
+        existing.active = false;
+        save(&path, &existing).await?;
+    }
+
+    Ok(())
+}
+
+///
+/// Stop taking snapshots of the project in `mapping`, keeping the history
+/// already recorded
+///
+pub async fn retire(mapping: &ProjectMapping) -> Result<(), Error> {
+    let history = HISTORY.lock().await;
+
+    match &history.dir {
+        Some(dir) => retire_in(dir, mapping).await,
+        None => Ok(()),
+    }
+}
+
+async fn get_in(
+    dir: &Path,
+    mapping: &ProjectMapping,
+    dates: &DateRange,
+) -> Result<ProjectStorageReport, Error> {
+    let report = match load(&history_path(dir, mapping)).await? {
+        Some(existing) => existing.report,
+        None => ProjectStorageReport::new(mapping.project()),
+    };
+
+    Ok(report.within(dates))
+}
+
+///
+/// Return the recorded snapshots of the project in `mapping` that fall
+/// within `dates`, or `None` if history is not being kept
+///
+pub async fn get(
+    mapping: &ProjectMapping,
+    dates: &DateRange,
+) -> Result<Option<ProjectStorageReport>, Error> {
+    let history = HISTORY.lock().await;
+
+    match &history.dir {
+        Some(dir) => Ok(Some(get_in(dir, mapping, dates).await?)),
+        None => Ok(None),
+    }
+}
+
+///
+/// Return whether `dates` includes today (UTC), the only day whose storage
+/// can still be measured
+///
+pub fn includes_today(dates: &DateRange) -> bool {
+    let today = today();
+    dates.start_date() <= &today && dates.end_date() >= &today
+}
+
+///
+/// Return every project history held in `dir`, skipping (with a warning)
+/// any that cannot be read
+///
+async fn load_all(dir: &Path) -> Vec<ProjectHistory> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!(
+                "Could not read the storage history in {}: {}",
+                dir.display(),
+                e
+            );
+            return vec![];
+        }
+    };
+
+    let mut histories = vec![];
+
+    loop {
+        let path = match entries.next_entry().await {
+            Ok(Some(entry)) => entry.path(),
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!(
+                    "Could not read the storage history in {}: {}",
+                    dir.display(),
+                    e
+                );
+                break;
+            }
+        };
+
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+
+        match load(&path).await {
+            Ok(Some(history)) => histories.push(history),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Skipping storage history {}: {}", path.display(), e),
+        }
+    }
+
+    histories
+}
+
+///
+/// Start keeping history of the projects that the connected cluster agents
+/// already have for `portals`, and for the portals already in the history.
+/// Projects that already have a history are left as they are.
+///
+async fn seed(me: &str, portals: &[PortalIdentifier]) {
+    let (dir, known) = {
+        let history = HISTORY.lock().await;
+
+        let Some(dir) = history.dir.clone() else {
+            return;
+        };
+
+        let known = load_all(&dir).await;
+
+        (dir, known)
+    };
+
+    let mut portals = portals.to_vec();
+
+    for history in &known {
+        let portal = history.mapping.project().portal_identifier();
+
+        if !portals.contains(&portal) {
+            portals.push(portal);
+        }
+    }
+
+    for peer in agent::get_all(&AgentType::Instance).await {
+        for portal in &portals {
+            let projects = match get_projects(me, &peer, portal).await {
+                Ok(projects) => projects,
+                Err(e) => {
+                    tracing::warn!(
+                        "Could not ask {} for the projects of {}: {}",
+                        peer,
+                        portal,
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            for mapping in projects {
+                if known.iter().any(|h| h.mapping == mapping) {
+                    continue;
+                }
+
+                let history = HISTORY.lock().await;
+
+                if let Err(e) = record_in(&dir, &mapping, &peer, None).await {
+                    tracing::error!("Could not start the storage history of {}: {}", mapping, e);
+                } else {
+                    tracing::info!(
+                        "Started the storage history of existing project {}",
+                        mapping
+                    );
+                }
+
+                drop(history);
+            }
+        }
+    }
+}
+
+async fn get_projects(
+    me: &str,
+    peer: &Peer,
+    portal: &PortalIdentifier,
+) -> Result<Vec<ProjectMapping>, Error> {
+    let job = crate::Job::parse(
+        &format!("{}.{} get_projects {}", me, peer.name(), portal),
+        false,
+    )?
+    .put(peer)
+    .await?;
+
+    Ok(job
+        .wait()
+        .await?
+        .result::<Vec<ProjectMapping>>()?
+        .unwrap_or_default())
+}
+
+///
+/// Take today's snapshot of every active project in `dir` that does not
+/// have one, using `take` to measure each project
+///
+async fn snapshot_due<F, Fut>(dir: &Path, take: F)
+where
+    F: Fn(ProjectHistory) -> Fut,
+    Fut: Future<Output = Result<ProjectStorageReport, Error>>,
+{
+    let due: Vec<ProjectHistory> = {
+        let _history = HISTORY.lock().await;
+
+        load_all(dir)
+            .await
+            .into_iter()
+            .filter(|history| {
+                history.active
+                    && Date::from_chrono(&history.report.generated_at().date_naive()) != today()
+            })
+            .collect()
+    };
+
+    for history in due {
+        let mapping = history.mapping.clone();
+        let sender = Peer::new(&history.peer, &history.zone);
+
+        // the sender may simply not be connected yet - the next check will
+        // try again
+        match take(history).await {
+            Ok(report) => {
+                let _history = HISTORY.lock().await;
+
+                if let Err(e) = record_in(dir, &mapping, &sender, Some(report)).await {
+                    tracing::error!(
+                        "Could not record the storage snapshot of {}: {}",
+                        mapping,
+                        e
+                    );
+                } else {
+                    tracing::info!("Recorded today's storage snapshot of {}", mapping);
+                }
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Could not take today's storage snapshot of {}: {}",
+                    mapping,
+                    e
+                );
+            }
+        }
+    }
+}
+
+///
+/// Take today's snapshot of every active project that does not have one
+///
+async fn snapshot_all(me: &str) {
+    let Some(dir) = HISTORY.lock().await.dir.clone() else {
+        return;
+    };
+
+    snapshot_due(&dir, |history| async move {
+        let sender = Peer::new(&history.peer, &history.zone);
+        let expires = Utc::now() + chrono::Duration::minutes(SNAPSHOT_TIMEOUT_MINUTES);
+
+        crate::get_local_storage_report(me, &sender, &history.mapping, &expires).await
+    })
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use greatwestern::storage::{Quota, StorageSize, Volume};
+    use std::collections::HashMap;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "op-storage-history-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap_or_else(|e| unreachable!("{:?}", e));
+        dir
+    }
+
+    fn mapping(project: &str) -> ProjectMapping {
+        ProjectMapping::parse(&format!("{}.brics:{}", project, project))
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+    }
+
+    fn snapshot(mapping: &ProjectMapping, gigabytes: f64) -> ProjectStorageReport {
+        let mut report = ProjectStorageReport::new(mapping.project());
+        report.set_project_quotas(HashMap::from([(
+            Volume::new("home"),
+            Quota::limited(StorageSize::from_gigabytes(gigabytes)),
+        )]));
+        report
+    }
+
+    #[tokio::test]
+    async fn test_record_and_get() {
+        let dir = temp_dir("record");
+        let project = mapping("proj");
+        let sender = Peer::new("cluster", "brics");
+
+        let report = snapshot(&project, 10.0);
+
+        record_in(&dir, &project, &sender, Some(report.clone()))
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let current = get_in(&dir, &project, &today().day())
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(current.project_quotas(), report.project_quotas());
+
+        let yesterday = get_in(&dir, &project, &today().prev().day())
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert!(yesterday.is_empty());
+
+        // a project with no history has an empty report
+        let unknown = get_in(&dir, &mapping("other"), &today().day())
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert!(unknown.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_record_keeps_the_newest_snapshot() {
+        let dir = temp_dir("newest");
+        let project = mapping("proj");
+        let sender = Peer::new("cluster", "brics");
+
+        for gigabytes in [10.0, 20.0] {
+            record_in(&dir, &project, &sender, Some(snapshot(&project, gigabytes)))
+                .await
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+        }
+
+        // recording nothing keeps what was there
+        record_in(&dir, &project, &sender, None)
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let report = get_in(&dir, &project, &today().day())
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            report.project_quotas(),
+            snapshot(&project, 20.0).project_quotas()
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_retire_keeps_history() {
+        let dir = temp_dir("retire");
+        let project = mapping("proj");
+        let sender = Peer::new("cluster", "brics");
+
+        record_in(&dir, &project, &sender, Some(snapshot(&project, 10.0)))
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        retire_in(&dir, &project)
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let histories = load_all(&dir).await;
+        assert_eq!(histories.len(), 1);
+        assert!(histories.iter().all(|history| !history.active));
+
+        let report = get_in(&dir, &project, &today().day())
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert!(!report.is_empty());
+
+        // retiring a project with no history does nothing
+        retire_in(&dir, &mapping("other"))
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(load_all(&dir).await.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_only_takes_due_projects() {
+        let dir = temp_dir("snapshot");
+        let sender = Peer::new("cluster", "brics");
+
+        // new, so due today
+        let fresh = mapping("fresh");
+        record_in(&dir, &fresh, &sender, None)
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        // already snapshotted today
+        let done = mapping("done");
+        record_in(&dir, &done, &sender, Some(snapshot(&done, 5.0)))
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        // removed, so never snapshotted again
+        let removed = mapping("removed");
+        record_in(&dir, &removed, &sender, None)
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        retire_in(&dir, &removed)
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let taken = Mutex::new(vec![]);
+
+        snapshot_due(&dir, |history| {
+            let taken = &taken;
+            async move {
+                taken.lock().await.push(history.mapping.clone());
+                assert_eq!(history.peer, "cluster");
+                assert_eq!(history.zone, "brics");
+                Ok(snapshot(&history.mapping, 1.0))
+            }
+        })
+        .await;
+
+        assert_eq!(taken.into_inner(), vec![fresh.clone()]);
+
+        let report = get_in(&dir, &fresh, &today().day())
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            report.project_quotas(),
+            snapshot(&fresh, 1.0).project_quotas()
+        );
+
+        // nothing is due once today's snapshot is taken
+        snapshot_due(&dir, |history| async move {
+            unreachable!("{} was snapshotted twice", history.mapping)
+        })
+        .await;
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
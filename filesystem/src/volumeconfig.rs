@@ -288,6 +288,46 @@ impl FilesystemConfig {
         roots
     }
 
+    /// Return the user volumes whose directories are created by their
+    /// quota engine (e.g. as ZFS datasets). Removing a user detaches
+    /// these rather than moving them to the recycle bin.
+    pub fn get_engine_managed_user_volumes(&self) -> Vec<Volume> {
+        let mut volumes: Vec<Volume> = self
+            .user_volumes
+            .iter()
+            .filter(|(_, volume_config)| {
+                volume_config
+                    .quota_engine_name()
+                    .and_then(|name| self.quota_engines.get(name))
+                    .is_some_and(|engine| engine.manages_user_dirs())
+            })
+            .map(|(volume, _)| volume.clone())
+            .collect();
+
+        volumes.sort_by_key(|volume| volume.to_string());
+        volumes
+    }
+
+    /// Return the project volumes whose directories are created by their
+    /// quota engine (as GPFS filesets or ZFS datasets). Removing a project
+    /// detaches these rather than moving them to the recycle bin.
+    pub fn get_engine_managed_project_volumes(&self) -> Vec<Volume> {
+        let mut volumes: Vec<Volume> = self
+            .project_volumes
+            .iter()
+            .filter(|(_, volume_config)| {
+                volume_config
+                    .quota_engine_name()
+                    .and_then(|name| self.quota_engines.get(name))
+                    .is_some_and(|engine| engine.manages_project_dirs())
+            })
+            .map(|(volume, _)| volume.clone())
+            .collect();
+
+        volumes.sort_by_key(|volume| volume.to_string());
+        volumes
+    }
+
     /// Return the named quota engine configuration
     ///
     /// This returns the configuration which can then be used to create an engine
@@ -351,6 +391,16 @@ impl PathConfig {
         &self.root
     }
 
+    /// How many levels below `root` the parents of the paths this `PathConfig`
+    /// produces can be - which is where a removed directory's `.recycle` is.
+    pub fn recycle_depth(&self) -> usize {
+        Path::new(&self.subpath)
+            .components()
+            .filter(|c| matches!(c, std::path::Component::Normal(_)))
+            .count()
+            .saturating_sub(1)
+    }
+
     pub fn project_path(&self, mapping: &ProjectMapping) -> Result<PathBuf, Error> {
         let project_name = mapping.project().project();
 
@@ -555,22 +605,18 @@ impl UserVolumeConfig {
 
         // make sure that the default quota is not larger than the max quota
         if let (Some(max), Some(default)) = (&self.max_quota, &self.default_quota) {
-            match (max, default) {
-                (QuotaLimit::Unlimited, _) => {}
-                (_, QuotaLimit::Unlimited) => {
-                    return Err(Error::Misconfigured(
-                        "User volume default quota cannot be Unlimited if max quota is limited"
-                            .to_string(),
-                    ));
-                }
-                (QuotaLimit::Limited(max_size), QuotaLimit::Limited(default_size)) => {
-                    if default_size > max_size {
-                        return Err(Error::Misconfigured(format!(
-                            "User volume default quota ({}) cannot be larger than max quota ({})",
-                            default_size, max_size
-                        )));
-                    }
-                }
+            if max.is_limited() && default.is_unlimited() {
+                return Err(Error::Misconfigured(
+                    "User volume default quota cannot be Unlimited if max quota is limited"
+                        .to_string(),
+                ));
+            }
+
+            if default.exceeds(max) {
+                return Err(Error::Misconfigured(format!(
+                    "User volume default quota ({}) cannot be larger than max quota ({})",
+                    default, max
+                )));
             }
         }
 
@@ -782,7 +828,7 @@ impl ProjectVolumeConfig {
 
         // make sure that the default quota is not larger than the max quota
         if let (Some(max), Some(default)) = (&self.max_quota, &self.default_quota) {
-            if default > max {
+            if default.exceeds(max) {
                 return Err(Error::Misconfigured(format!(
                     "Project volume default quota ({}) cannot be larger than max quota ({})",
                     default, max
@@ -956,4 +1002,69 @@ mod tests {
         assert!(validate_subpath_placeholders("/static/path", false, false).is_ok());
         assert!(validate_subpath_placeholders("", false, false).is_ok());
     }
+
+    #[test]
+    fn test_recycle_depth() {
+        let path_config = |subpath: &str| {
+            PathConfig::new(
+                "/home".to_string(),
+                subpath.to_string(),
+                "0755".to_string(),
+                None,
+            )
+        };
+
+        assert_eq!(path_config("{project}").recycle_depth(), 0);
+        assert_eq!(path_config("{project}/{user}").recycle_depth(), 1);
+        assert_eq!(path_config("/{project}/data/{user}").recycle_depth(), 2);
+    }
+
+    #[test]
+    fn test_engine_managed_volumes_are_those_with_their_own_dirs() {
+        let config: FilesystemConfig = toml::from_str(
+            r#"
+            [quota_engines.gpfs]
+            type = "gpfs"
+            filesystem = "fs1"
+
+            [quota_engines.zfs]
+            type = "zfs"
+            parent = "tank/home"
+            user_datasets = true
+
+            [quota_engines.linux]
+            type = "linux"
+            filesystem = "/dev/sda1"
+
+            [user_volumes.home]
+            roots = ["/home"]
+            subpath = "{project}/{user}"
+            quota_engine = "zfs"
+
+            [user_volumes.scratch]
+            roots = ["/scratch"]
+            subpath = "{project}/{user}"
+            quota_engine = "linux"
+
+            [project_volumes.projects]
+            roots = ["/projects"]
+            subpath = "{project}"
+            quota_engine = "gpfs"
+
+            [project_volumes.shared]
+            roots = ["/shared"]
+            subpath = "{project}"
+            "#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            config.get_engine_managed_user_volumes(),
+            vec![Volume::new("home")]
+        );
+        assert_eq!(
+            config.get_engine_managed_project_volumes(),
+            vec![Volume::new("projects")]
+        );
+    }
 }
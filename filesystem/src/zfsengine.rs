@@ -0,0 +1,943 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! Concrete implementation of the ZFS quota engine.
+//!
+//! Rather than a plain directory, each project gets a child dataset of
+//! the configured `parent` dataset, mounted at the project's path, so
+//! that a project quota is the dataset's `quota` (or `refquota`). User
+//! quotas are `userquota@<user>` / `userobjquota@<user>` on the parent
+//! dataset, or, if `user_datasets` is set, each user also gets a child
+//! dataset mounted at their directory, with its own `quota`.
+//!
+//! Usage is read from `used` (or `referenced` for `refquota`), and from
+//! `userused@<user>` / `userobjused@<user>` for user quotas.
+//!
+//! # TOML configuration example
+//!
+//! ```toml
+//! [quota_engines.zfs_projects]
+//! type        = "zfs"
+//! parent      = "tank/projects"
+//! zfs_command = "sudo zfs"
+//! ```
+
+use anyhow::Result;
+use chrono::Utc;
+use greatwestern::grammar::{ProjectMapping, UserMapping};
+use greatwestern::storage::{Quota, QuotaLimit, StorageSize, StorageUsage, Volume};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use templemeads::job::assert_not_expired;
+use templemeads::Error;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use crate::volumeconfig::{ProjectVolumeConfig, UserVolumeConfig};
+
+fn default_zfs_command() -> String {
+    "zfs".to_string()
+}
+
+fn default_command_timeout() -> u64 {
+    60
+}
+
+/// Which dataset property holds the size limit of a project (or user)
+/// dataset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZfsQuotaProperty {
+    /// `quota` - limits the dataset, its children and its snapshots
+    #[default]
+    Quota,
+    /// `refquota` - limits only the data referenced by the dataset itself
+    Refquota,
+}
+
+impl ZfsQuotaProperty {
+    /// The property that holds the limit
+    fn limit(&self) -> &'static str {
+        match self {
+            ZfsQuotaProperty::Quota => "quota",
+            ZfsQuotaProperty::Refquota => "refquota",
+        }
+    }
+
+    /// The property that holds the usage counted against the limit
+    fn usage(&self) -> &'static str {
+        match self {
+            ZfsQuotaProperty::Quota => "used",
+            ZfsQuotaProperty::Refquota => "referenced",
+        }
+    }
+}
+
+/// Configuration for the ZFS quota engine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZfsEngineConfig {
+    /// The dataset under which the project (and user) datasets are
+    /// created, e.g. `tank/projects`
+    parent: String,
+
+    /// The `zfs` command (default: `"zfs"`). May include a prefix, e.g.
+    /// `"sudo zfs"` or `"ssh nas01 zfs"`
+    #[serde(default = "default_zfs_command")]
+    zfs_command: String,
+
+    /// Whether to give each user their own dataset, rather than using
+    /// `userquota@` on the parent dataset (default: false)
+    #[serde(default)]
+    user_datasets: bool,
+
+    /// The property used for dataset limits (default: `quota`)
+    #[serde(default)]
+    quota_property: ZfsQuotaProperty,
+
+    /// Timeout in seconds for `zfs` commands (default: 60)
+    #[serde(default = "default_command_timeout")]
+    command_timeout_secs: u64,
+}
+
+impl ZfsEngineConfig {
+    /// Whether each user has their own dataset
+    pub fn user_datasets(&self) -> bool {
+        self.user_datasets
+    }
+}
+
+/// Whether or not to run in "dry_run" mode - in this mode, no `zfs`
+/// commands will actually be run
+fn is_dry_run() -> bool {
+    match std::env::var("OPENPORTAL_ZFS_DRY_RUN") {
+        Ok(val) => val == "1" || val.to_lowercase() == "true",
+        Err(_) => false,
+    }
+}
+
+/// Check that `name` can be used as a single component of a dataset
+/// name, so that a project or user name can never address a different
+/// dataset (e.g. via `/`, `@` or `..`)
+fn check_component(name: &str) -> Result<&str, Error> {
+    let valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'));
+
+    match valid {
+        true => Ok(name),
+        false => Err(Error::Incompatible(format!(
+            "'{}' cannot be used as the name of a ZFS dataset",
+            name
+        ))),
+    }
+}
+
+/// Parse a value from `zfs get -Hp`. Unset limits are reported as
+/// `none`, `-` or `0`, which all mean unlimited (returned as 0)
+fn parse_value(property: &str, value: &str) -> Result<u64, Error> {
+    match value.trim() {
+        "" | "-" | "none" => Ok(0),
+        value => value.parse::<u64>().map_err(|e| {
+            Error::Parse(format!(
+                "Failed to parse the '{}' property ('{}') of zfs output: {}",
+                property, value, e
+            ))
+        }),
+    }
+}
+
+/// Parse the output of `zfs get -Hp -o property,value`, which is one
+/// tab-separated property and value per line
+fn parse_get_output(output: &str) -> Result<HashMap<String, u64>, Error> {
+    let mut values = HashMap::new();
+
+    for line in output.lines() {
+        let Some((property, value)) = line.split_once('\t') else {
+            continue;
+        };
+
+        values.insert(property.to_string(), parse_value(property, value)?);
+    }
+
+    Ok(values)
+}
+
+/// Return the value of `property` from parsed `zfs get` output
+fn get_value(values: &HashMap<String, u64>, property: &str) -> Result<u64, Error> {
+    values.get(property).copied().ok_or_else(|| {
+        Error::Parse(format!(
+            "The '{}' property is missing from the zfs output",
+            property
+        ))
+    })
+}
+
+/// Return the value to pass to `zfs set` for a limit, where 0 is
+/// unlimited
+fn limit_value(value: u64) -> String {
+    match value {
+        0 => "none".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// ZFS quota engine implementation.
+pub struct ZfsEngine {
+    config: ZfsEngineConfig,
+}
+
+impl ZfsEngine {
+    /// Create a new ZFS quota engine with the given configuration
+    pub fn new(config: ZfsEngineConfig) -> Result<Self, Error> {
+        let parent = config.parent.trim();
+
+        if parent.is_empty() || parent.starts_with('/') || parent.ends_with('/') {
+            return Err(Error::Misconfigured(format!(
+                "ZfsEngine requires 'parent' to be a dataset name such as 'tank/projects', not '{}'",
+                config.parent
+            )));
+        }
+
+        Ok(Self { config })
+    }
+
+    /// Initialise the engine
+    pub async fn initialize(&self) -> Result<(), Error> {
+        tracing::info!(
+            "ZfsEngine initialized for parent dataset '{}' using '{}'",
+            self.config.parent,
+            self.config.zfs_command
+        );
+        Ok(())
+    }
+
+    /// Return the dataset for a project
+    fn project_dataset(&self, mapping: &ProjectMapping) -> Result<String, Error> {
+        Ok(format!(
+            "{}/{}",
+            self.config.parent.trim(),
+            check_component(mapping.local_group())?
+        ))
+    }
+
+    /// Return the dataset for a user, if users have their own datasets
+    fn user_dataset(&self, mapping: &UserMapping) -> Result<Option<String>, Error> {
+        if !self.config.user_datasets {
+            return Ok(None);
+        }
+
+        Ok(Some(format!(
+            "{}/{}",
+            self.config.parent.trim(),
+            check_component(mapping.local_user().unix()?)?
+        )))
+    }
+
+    /// Run `zfs` with the passed arguments, returning its stdout
+    async fn run_zfs(
+        &self,
+        args: &[&str],
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<String, Error> {
+        assert_not_expired(expires)?;
+
+        let cmd_str = format!("{} {}", self.config.zfs_command, args.join(" "));
+
+        if is_dry_run() {
+            tracing::info!("DRY RUN: {}", cmd_str);
+            return Ok(String::new());
+        }
+
+        let parts: Vec<&str> = self.config.zfs_command.split_whitespace().collect();
+        let (prog, initial_args) = parts.split_first().ok_or_else(|| {
+            Error::Misconfigured(format!(
+                "zfs command is empty: '{}'",
+                self.config.zfs_command
+            ))
+        })?;
+
+        tracing::info!("ZfsEngine executing: {}", cmd_str);
+
+        let mut cmd = Command::new(prog);
+        cmd.args(initial_args);
+        cmd.args(args);
+
+        let timeout_duration = Duration::from_secs(self.config.command_timeout_secs);
+
+        let output = timeout(timeout_duration, cmd.output())
+            .await
+            .map_err(|_| {
+                Error::Timeout(format!(
+                    "Command '{}' timed out after {} seconds",
+                    cmd_str,
+                    timeout_duration.as_secs()
+                ))
+            })?
+            .map_err(|e| Error::Failed(format!("Failed to spawn '{}': {}", cmd_str, e)))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::Failed(format!(
+                "Command '{}' failed (exit {:?}): {}",
+                cmd_str,
+                output.status.code(),
+                stderr.trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Return the mountpoint of a dataset, or `None` if it does not exist
+    async fn mountpoint(
+        &self,
+        dataset: &str,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, Error> {
+        match self
+            .run_zfs(&["list", "-H", "-o", "mountpoint", dataset], expires)
+            .await
+        {
+            // there is no output in dry-run mode
+            Ok(output) if output.trim().is_empty() => Ok(None),
+            Ok(output) => Ok(Some(output.trim().to_string())),
+            Err(Error::Failed(message)) if message.contains("does not exist") => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create (if needed) and mount `dataset` at `path`. A dataset that
+    /// was detached when its project or user was removed is mounted
+    /// again, bringing back its data
+    async fn attach_dataset(
+        &self,
+        dataset: &str,
+        path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let path_str = path.to_str().ok_or_else(|| {
+            Error::Incompatible("Directory path contains invalid UTF-8".to_string())
+        })?;
+
+        let mountpoint = format!("mountpoint={}", path_str);
+
+        match self.mountpoint(dataset, expires).await? {
+            None => {
+                self.run_zfs(&["create", "-o", &mountpoint, dataset], expires)
+                    .await?;
+            }
+            Some(current) if current == "none" => {
+                self.run_zfs(&["set", &mountpoint, dataset], expires)
+                    .await?;
+            }
+            Some(current) if Path::new(&current) == path => {}
+            Some(current) => {
+                // a dataset that is mounted elsewhere is not moved, as that
+                // would take the data away from wherever it is in use
+                return Err(Error::State(format!(
+                    "Dataset '{}' is already mounted at '{}', not at '{}'",
+                    dataset, current, path_str
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Unmount `dataset`, keeping its data. Returns `false` if there is
+    /// no such dataset
+    async fn detach_dataset(
+        &self,
+        dataset: &str,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        match self.mountpoint(dataset, expires).await? {
+            None => Ok(false),
+            Some(current) if current == "none" => Ok(true),
+            Some(_) => {
+                self.run_zfs(&["set", "mountpoint=none", dataset], expires)
+                    .await?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Set the size limit of a dataset. OpenZFS only limits the number of
+    /// objects (files) of a user, group or project ID within a dataset, not
+    /// of the dataset itself, so the inode limit is set as the
+    /// `userobjquota@` of the `owner` of a user's own dataset, and is not
+    /// applied to project datasets
+    async fn set_dataset_quota(
+        &self,
+        dataset: &str,
+        owner: Option<&str>,
+        limit: &QuotaLimit,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let bytes = limit.size().map(|size| size.as_bytes()).unwrap_or(0);
+        let mut properties = vec![format!(
+            "{}={}",
+            self.config.quota_property.limit(),
+            limit_value(bytes)
+        )];
+
+        match owner {
+            Some(user) => properties.push(format!(
+                "userobjquota@{}={}",
+                user,
+                limit_value(limit.inodes().unwrap_or(0))
+            )),
+            None => {
+                if let Some(inodes) = limit.inodes().filter(|inodes| *inodes > 0) {
+                    tracing::warn!(
+                        "OpenZFS cannot limit the number of files in a whole dataset, only those of \
+                         a user, group or project ID within it - ignoring the limit of {} files on '{}'",
+                        inodes,
+                        dataset
+                    );
+                }
+            }
+        }
+
+        let mut args = vec!["set"];
+        args.extend(properties.iter().map(|p| p.as_str()));
+        args.push(dataset);
+
+        self.run_zfs(&args, expires).await?;
+
+        Ok(())
+    }
+
+    /// Read the size limit and usage of a dataset, together with the
+    /// object limit and usage of the `owner` of a user's own dataset
+    async fn get_dataset_quota(
+        &self,
+        dataset: &str,
+        owner: Option<&str>,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let limit_property = self.config.quota_property.limit();
+        let usage_property = self.config.quota_property.usage();
+        let mut properties = format!("{},{}", limit_property, usage_property);
+
+        if let Some(user) = owner {
+            properties.push_str(&format!(",userobjquota@{0},userobjused@{0}", user));
+        }
+
+        let output = self
+            .run_zfs(
+                &["get", "-Hp", "-o", "property,value", &properties, dataset],
+                expires,
+            )
+            .await?;
+
+        if is_dry_run() {
+            return Ok(Quota::with_usage(
+                QuotaLimit::unlimited(),
+                StorageUsage::from(0),
+            ));
+        }
+
+        let values = parse_get_output(&output)?;
+
+        let mut quota = dataset_quota(
+            get_value(&values, limit_property)?,
+            get_value(&values, usage_property)?,
+        );
+
+        if let Some(user) = owner {
+            add_object_quota(&mut quota, &values, user);
+        }
+
+        Ok(quota)
+    }
+
+    /// Create (if needed) and mount the project's dataset at `path`, so
+    /// that it becomes the project's directory on this volume
+    pub async fn create_project_dir(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        _volume_config: &ProjectVolumeConfig,
+        path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let dataset = self.project_dataset(mapping)?;
+
+        tracing::info!(
+            "ZfsEngine::create_project_dir: project={}, volume={}, dataset={}, path={}",
+            mapping.project(),
+            volume,
+            dataset,
+            path.to_string_lossy()
+        );
+
+        self.attach_dataset(&dataset, path, expires).await?;
+
+        Ok(true)
+    }
+
+    /// Unmount the project's dataset. Its data is kept, and comes back if
+    /// the project is added again
+    pub async fn remove_project_dir(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        _volume_config: &ProjectVolumeConfig,
+        _path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let dataset = self.project_dataset(mapping)?;
+
+        tracing::info!(
+            "ZfsEngine::remove_project_dir: project={}, volume={}, dataset={}",
+            mapping.project(),
+            volume,
+            dataset
+        );
+
+        // no dataset means this is a plain directory that the caller
+        // should recycle as usual
+        self.detach_dataset(&dataset, expires).await
+    }
+
+    /// Create (if needed) and mount the user's dataset at `path`, if
+    /// users have their own datasets
+    pub async fn create_user_dir(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        _volume_config: &UserVolumeConfig,
+        path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let Some(dataset) = self.user_dataset(mapping)? else {
+            return Ok(false);
+        };
+
+        tracing::info!(
+            "ZfsEngine::create_user_dir: user={}, volume={}, dataset={}, path={}",
+            mapping.local_user(),
+            volume,
+            dataset,
+            path.to_string_lossy()
+        );
+
+        self.attach_dataset(&dataset, path, expires).await?;
+
+        Ok(true)
+    }
+
+    /// Unmount the user's dataset, if users have their own datasets
+    pub async fn remove_user_dir(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        _volume_config: &UserVolumeConfig,
+        _path: &Path,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let Some(dataset) = self.user_dataset(mapping)? else {
+            return Ok(false);
+        };
+
+        tracing::info!(
+            "ZfsEngine::remove_user_dir: user={}, volume={}, dataset={}",
+            mapping.local_user(),
+            volume,
+            dataset
+        );
+
+        self.detach_dataset(&dataset, expires).await
+    }
+
+    // -----------------------------------------------------------------------
+    // User quota methods
+    // -----------------------------------------------------------------------
+
+    pub async fn set_user_quota(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        volume_config: &UserVolumeConfig,
+        limit: &QuotaLimit,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let user = mapping.local_user().unix()?;
+
+        // Validate against any configured maximum.
+        if let Some(max_quota) = volume_config.max_quota() {
+            if limit.exceeds(max_quota) {
+                return Err(Error::Failed(format!(
+                    "Requested quota limit ({}) exceeds maximum allowed quota ({}) for user {} on volume {}",
+                    limit, max_quota, user, volume
+                )));
+            }
+        }
+
+        tracing::info!(
+            "ZfsEngine::set_user_quota: user={}, volume={}, limit={}",
+            user,
+            volume,
+            limit
+        );
+
+        let inodes = limit
+            .inodes()
+            .or(volume_config.default_inode_limit())
+            .unwrap_or(0);
+
+        match self.user_dataset(mapping)? {
+            Some(dataset) => {
+                self.set_dataset_quota(&dataset, Some(user), &limit.with_inodes(inodes), expires)
+                    .await?
+            }
+            None => {
+                let bytes = limit.size().map(|size| size.as_bytes()).unwrap_or(0);
+
+                let userquota = format!("userquota@{}={}", user, limit_value(bytes));
+                let userobjquota = format!("userobjquota@{}={}", user, limit_value(inodes));
+
+                self.run_zfs(
+                    &["set", &userquota, &userobjquota, self.config.parent.trim()],
+                    expires,
+                )
+                .await?;
+            }
+        }
+
+        self.get_user_quota(mapping, volume, volume_config, expires)
+            .await
+    }
+
+    pub async fn get_user_quota(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        _volume_config: &UserVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let user = mapping.local_user().unix()?;
+
+        tracing::info!(
+            "ZfsEngine::get_user_quota: user={}, volume={}",
+            user,
+            volume
+        );
+
+        if let Some(dataset) = self.user_dataset(mapping)? {
+            return self.get_dataset_quota(&dataset, Some(user), expires).await;
+        }
+
+        let properties = format!(
+            "userquota@{0},userused@{0},userobjquota@{0},userobjused@{0}",
+            user
+        );
+
+        let output = self
+            .run_zfs(
+                &[
+                    "get",
+                    "-Hp",
+                    "-o",
+                    "property,value",
+                    &properties,
+                    self.config.parent.trim(),
+                ],
+                expires,
+            )
+            .await?;
+
+        if is_dry_run() {
+            return Ok(Quota::with_usage(
+                QuotaLimit::unlimited(),
+                StorageUsage::from(0),
+            ));
+        }
+
+        user_quota(&parse_get_output(&output)?, user)
+    }
+
+    pub async fn clear_user_quota(
+        &self,
+        mapping: &UserMapping,
+        volume: &Volume,
+        _volume_config: &UserVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let user = mapping.local_user().unix()?;
+
+        tracing::info!(
+            "ZfsEngine::clear_user_quota: user={}, volume={}",
+            user,
+            volume
+        );
+
+        match self.user_dataset(mapping)? {
+            Some(dataset) => {
+                self.set_dataset_quota(&dataset, Some(user), &QuotaLimit::unlimited(), expires)
+                    .await
+            }
+            None => {
+                let userquota = format!("userquota@{}=none", user);
+                let userobjquota = format!("userobjquota@{}=none", user);
+
+                self.run_zfs(
+                    &["set", &userquota, &userobjquota, self.config.parent.trim()],
+                    expires,
+                )
+                .await?;
+
+                Ok(())
+            }
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // Project (dataset) quota methods
+    // -----------------------------------------------------------------------
+
+    pub async fn set_project_quota(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        volume_config: &ProjectVolumeConfig,
+        limit: &QuotaLimit,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        // Validate against any configured maximum.
+        if let Some(max_quota) = volume_config.max_quota() {
+            if limit.exceeds(max_quota) {
+                return Err(Error::Failed(format!(
+                    "Requested quota limit ({}) exceeds maximum allowed quota ({}) for project {} on volume {}",
+                    limit, max_quota, mapping.project(), volume
+                )));
+            }
+        }
+
+        let dataset = self.project_dataset(mapping)?;
+
+        tracing::info!(
+            "ZfsEngine::set_project_quota: dataset={}, volume={}, limit={}",
+            dataset,
+            volume,
+            limit
+        );
+
+        self.set_dataset_quota(&dataset, None, limit, expires)
+            .await?;
+        self.get_dataset_quota(&dataset, None, expires).await
+    }
+
+    pub async fn get_project_quota(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        _volume_config: &ProjectVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Quota, Error> {
+        let dataset = self.project_dataset(mapping)?;
+
+        tracing::info!(
+            "ZfsEngine::get_project_quota: dataset={}, volume={}",
+            dataset,
+            volume
+        );
+
+        self.get_dataset_quota(&dataset, None, expires).await
+    }
+
+    pub async fn clear_project_quota(
+        &self,
+        mapping: &ProjectMapping,
+        volume: &Volume,
+        _volume_config: &ProjectVolumeConfig,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let dataset = self.project_dataset(mapping)?;
+
+        tracing::info!(
+            "ZfsEngine::clear_project_quota: dataset={}, volume={}",
+            dataset,
+            volume
+        );
+
+        self.set_dataset_quota(&dataset, None, &QuotaLimit::unlimited(), expires)
+            .await
+    }
+}
+
+/// Build the quota of a dataset from its limit and usage in bytes, where
+/// a limit of 0 is unlimited
+fn dataset_quota(limit: u64, usage: u64) -> Quota {
+    let limit = match limit {
+        0 => QuotaLimit::unlimited(),
+        limit => QuotaLimit::limited(StorageSize::from_bytes(limit)),
+    };
+
+    Quota::with_usage(limit, StorageUsage::new(StorageSize::from_bytes(usage)))
+}
+
+/// Build the quota of a user from the parsed `userquota@`, `userused@`,
+/// `userobjquota@` and `userobjused@` properties
+fn user_quota(values: &HashMap<String, u64>, user: &str) -> Result<Quota, Error> {
+    let mut quota = dataset_quota(
+        get_value(values, &format!("userquota@{}", user))?,
+        get_value(values, &format!("userused@{}", user))?,
+    );
+
+    add_object_quota(&mut quota, values, user);
+
+    Ok(quota)
+}
+
+/// Add the parsed `userobjquota@` and `userobjused@` properties of `user`
+/// to `quota`. These are missing on pools without the userobj_accounting
+/// feature, when the number of files is not known
+fn add_object_quota(quota: &mut Quota, values: &HashMap<String, u64>, user: &str) {
+    if let Ok(inodes) = get_value(values, &format!("userobjquota@{}", user)) {
+        quota.set_limit(quota.limit().with_inodes(inodes));
+    }
+
+    if let Ok(used) = get_value(values, &format!("userobjused@{}", user)) {
+        quota.set_inode_usage(used);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // recorded from `zfs get -Hp -o property,value quota,used tank/projects/alpha`
+    const DATASET_GET: &str = "quota\t1099511627776\nused\t52613349376\n";
+
+    // recorded from `zfs get -Hp -o property,value
+    // userquota@alice,userused@alice,userobjquota@alice,userobjused@alice tank/home`
+    const USER_GET: &str = "\
+userquota@alice\t107374182400
+userused@alice\t2147483648
+userobjquota@alice\tnone
+userobjused@alice\t5321
+";
+
+    #[test]
+    fn test_parse_zfs_get() {
+        let values = parse_get_output(DATASET_GET).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let quota = dataset_quota(
+            get_value(&values, "quota").unwrap_or_else(|e| unreachable!("{:?}", e)),
+            get_value(&values, "used").unwrap_or_else(|e| unreachable!("{:?}", e)),
+        );
+
+        assert_eq!(
+            quota.limit().size(),
+            Some(StorageSize::from_bytes(1_099_511_627_776))
+        );
+        assert_eq!(
+            quota.usage(),
+            Some(StorageUsage::new(StorageSize::from_bytes(52_613_349_376)))
+        );
+        assert_eq!(quota.limit().inodes(), None);
+
+        assert!(get_value(&values, "refquota").is_err());
+        assert!(parse_get_output("quota\tlots\n").is_err());
+
+        // an unset limit is unlimited
+        let values =
+            parse_get_output("quota\tnone\nused\t0\n").unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(values.get("quota"), Some(&0));
+        assert!(dataset_quota(0, 0).is_unlimited());
+    }
+
+    #[test]
+    fn test_parse_zfs_user_dataset_quota() {
+        // a user's own dataset, with the files they own in it
+        let values = parse_get_output(
+            "quota\t107374182400\nused\t1073741824\n\
+             userobjquota@alice\t1000000\nuserobjused@alice\t5321\n",
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let mut quota = dataset_quota(
+            get_value(&values, "quota").unwrap_or_else(|e| unreachable!("{:?}", e)),
+            get_value(&values, "used").unwrap_or_else(|e| unreachable!("{:?}", e)),
+        );
+        add_object_quota(&mut quota, &values, "alice");
+
+        assert_eq!(
+            quota.limit().size(),
+            Some(StorageSize::from_gigabytes(100.0))
+        );
+        assert_eq!(quota.limit().inodes(), Some(1000000));
+        assert_eq!(quota.inode_usage(), Some(5321));
+    }
+
+    #[test]
+    fn test_parse_zfs_user_quota() {
+        let values = parse_get_output(USER_GET).unwrap_or_else(|e| unreachable!("{:?}", e));
+        let quota = user_quota(&values, "alice").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            quota.limit().size(),
+            Some(StorageSize::from_gigabytes(100.0))
+        );
+        assert_eq!(
+            quota.usage(),
+            Some(StorageUsage::new(StorageSize::from_gigabytes(2.0)))
+        );
+        assert_eq!(quota.limit().inodes(), Some(0));
+        assert_eq!(quota.inode_usage(), Some(5321));
+
+        // pools without userobj_accounting have no object properties
+        let values = parse_get_output("userquota@bob\t-\nuserused@bob\t1024\n")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        let quota = user_quota(&values, "bob").unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(quota.is_unlimited());
+        assert_eq!(quota.inode_usage(), None);
+
+        assert!(user_quota(&values, "alice").is_err());
+    }
+
+    #[test]
+    fn test_dataset_names_are_single_components() {
+        assert_eq!(
+            check_component("proj-alpha_1.x").unwrap_or_else(|e| unreachable!("{:?}", e)),
+            "proj-alpha_1.x"
+        );
+
+        assert!(check_component("").is_err());
+        assert!(check_component("..").is_err());
+        assert!(check_component("alpha/beta").is_err());
+        assert!(check_component("alpha@snap").is_err());
+        assert!(check_component("alpha beta").is_err());
+    }
+
+    #[test]
+    fn test_zfs_config() {
+        let config: ZfsEngineConfig = toml::from_str(
+            r#"
+            parent = "tank/projects"
+            quota_property = "refquota"
+            "#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(config.quota_property, ZfsQuotaProperty::Refquota);
+        assert_eq!(config.quota_property.usage(), "referenced");
+        assert_eq!(config.zfs_command, "zfs");
+        assert!(!config.user_datasets);
+        assert!(ZfsEngine::new(config).is_ok());
+
+        let config: ZfsEngineConfig = toml::from_str(r#"parent = "/tank/projects""#)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(ZfsEngine::new(config).is_err());
+
+        assert_eq!(limit_value(0), "none");
+        assert_eq!(limit_value(1024), "1024");
+    }
+}
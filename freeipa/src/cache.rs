@@ -21,6 +21,7 @@ struct Database {
     groups: HashMap<ProjectIdentifier, IPAGroup>,
     system_groups: Vec<IPAGroup>,
     instance_groups: HashMap<Peer, Vec<IPAGroup>>,
+    manager_roles: Vec<String>,
     users_in_group: HashMap<ProjectIdentifier, HashSet<UserIdentifier>>,
     user_mutexes: HashMap<UserIdentifier, Arc<Mutex<()>>>,
     group_mutexes: HashMap<ProjectIdentifier, Arc<Mutex<()>>>,
@@ -357,6 +358,26 @@ pub async fn set_system_groups(groups: &[IPAGroup]) -> Result<(), Error> {
     Ok(())
 }
 
+///
+/// Return the project roles (e.g. "pi", "manager") whose members are
+/// added to their project's managers group
+///
+pub async fn get_manager_roles() -> Result<Vec<String>, Error> {
+    let cache = CACHE.read().await;
+    Ok(cache.manager_roles.clone())
+}
+
+///
+/// Set the project roles whose members are added to their project's
+/// managers group
+///
+pub async fn set_manager_roles(roles: &[String]) -> Result<(), Error> {
+    let mut cache = CACHE.write().await;
+    cache.manager_roles = roles.to_vec();
+    tracing::info!("Setting manager roles to {:?}", cache.manager_roles);
+    Ok(())
+}
+
 ///
 /// Set the list of all instance groups that should be used for each
 /// instance that connects to this agent. These groups should be added
@@ -4,7 +4,9 @@
 use anyhow::Context;
 use anyhow::Result;
 use chrono::Utc;
-use greatwestern::grammar::{ProjectIdentifier, ProjectMapping, UserIdentifier, UserMapping};
+use greatwestern::grammar::{
+    validate_role, ProjectIdentifier, ProjectMapping, UserIdentifier, UserMapping, UserProfile,
+};
 use once_cell::sync::Lazy;
 use rand::seq::IteratorRandom;
 use rand::SeedableRng;
@@ -15,6 +17,7 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use templemeads::job::assert_not_expired;
+use templemeads::metrics;
 use templemeads::portal_identifier::PortalIdentifier;
 use templemeads::Error;
 use tokio::sync::Mutex;
@@ -160,9 +163,26 @@ where
     call_post_to(&Target::Pinned, func, args, kwargs, expires).await
 }
 
+///
+/// As `call_write`, but for a change whose keyword arguments are not all
+/// strings - e.g. a multi-valued attribute such as `ipasshpubkey`, which
+/// FreeIPA only accepts as a list.
+///
+async fn call_write_values<T>(
+    func: &str,
+    args: Option<Vec<String>>,
+    kwargs: HashMap<String, serde_json::Value>,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    call_post_values_to(&Target::Pinned, func, args, kwargs, expires).await
+}
+
 ///
 /// Call a post URL on a FreeIPA server chosen according to the passed
-/// `Target`.
+/// `Target`, recording how long the call took in `/metrics`.
 ///
 async fn call_post_to<T>(
     target: &Target,
@@ -171,6 +191,52 @@ async fn call_post_to<T>(
     kwargs: Option<HashMap<String, String>>,
     expires: &chrono::DateTime<Utc>,
 ) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let kwargs = kwargs
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(k, v)| (k, serde_json::Value::String(v)))
+        .collect();
+
+    call_post_values_to(target, func, args, kwargs, expires).await
+}
+
+async fn call_post_values_to<T>(
+    target: &Target,
+    func: &str,
+    args: Option<Vec<String>>,
+    kwargs: HashMap<String, serde_json::Value>,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let start_time = std::time::Instant::now();
+
+    let result = call_post_to_server(target, func, args, kwargs, expires).await;
+
+    metrics::observe(
+        "freeipa_call_duration_seconds",
+        "How long calls to the FreeIPA JSON-RPC API took, by method and outcome",
+        &[
+            ("method", func),
+            ("outcome", if result.is_ok() { "ok" } else { "error" }),
+        ],
+        start_time.elapsed(),
+    );
+
+    result
+}
+
+async fn call_post_to_server<T>(
+    target: &Target,
+    func: &str,
+    args: Option<Vec<String>>,
+    mut kwargs: HashMap<String, serde_json::Value>,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<T, Error>
 where
     T: DeserializeOwned,
 {
@@ -209,8 +275,7 @@ where
     // make id a random integer between 1 and 1000
     let id = rand::random::<u16>() % 1000;
 
-    let mut kwargs = kwargs.unwrap_or_default();
-    kwargs.insert("version".to_string(), "2.251".to_string());
+    kwargs.insert("version".to_string(), serde_json::Value::from("2.251"));
 
     // the payload is a json object that contains the method, the parameters
     // (as an array, plus a dict of the version) and a random id. The id
@@ -2942,6 +3007,16 @@ pub async fn remove_project(
         };
     }
 
+    // the managers group is only created on demand, and is nothing
+    // without the project, so remove it rather than leave it behind
+    if let Err(e) = remove_managers_group(project, expires).await {
+        tracing::error!(
+            "Could not remove the managers group of project {}. Error: {}",
+            project,
+            e
+        );
+    }
+
     // DO NOT REMOVE THE GROUP AS WE MAY WANT TO RE-ADD IT LATER, AND
     // WILL NEED TO USE THE SAME GID!
 
@@ -3585,6 +3660,16 @@ pub async fn remove_user(
         }
     }
 
+    // the managers group is not a project group, so it isn't in the
+    // list above and has to be left separately
+    if let Err(e) = remove_from_managers_group(&user, expires).await {
+        tracing::error!(
+            "Could not remove user {} from their project's managers group. Error: {}",
+            user.identifier(),
+            e
+        );
+    }
+
     let kwargs = {
         let mut kwargs = HashMap::new();
         kwargs.insert("uid".to_string(), user.userid().to_string());
@@ -3723,6 +3808,353 @@ pub async fn update_homedir(
     Ok(user.home().to_string())
 }
 
+///
+/// Write the passed profile into the user's FreeIPA account, as
+/// `givenname`/`sn`/`displayname`, `mail`, `ipasshpubkey` and `loginshell`.
+/// If `replace` is true then a field that is missing from the profile is
+/// cleared (the name goes back to the username and project that the
+/// account was created with), except for the login shell, which is left
+/// alone as an account always needs one. Otherwise only the fields that
+/// are set are changed. This returns the profile that was written, or an
+/// error if the user doesn't exist or is not managed by OpenPortal.
+///
+pub async fn set_user_profile(
+    user: &UserIdentifier,
+    profile: &UserProfile,
+    replace: bool,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<UserProfile, Error> {
+    profile.validate()?;
+
+    let user = get_user(user, expires).await?.ok_or(Error::Call(format!(
+        "User {} does not exist in FreeIPA?",
+        user
+    )))?;
+
+    assert_not_expired(expires)?;
+
+    if !user.is_managed() {
+        tracing::warn!(
+            "Refusing to set the profile of {} as they are not managed by OpenPortal",
+            user.identifier()
+        );
+        return Err(Error::UnmanagedUser(format!(
+            "Cannot set the profile of {} as they are not managed by OpenPortal",
+            user.identifier()
+        )));
+    }
+
+    let mut kwargs: HashMap<String, serde_json::Value> = HashMap::new();
+
+    match (
+        profile.given_name(),
+        profile.family_name(),
+        profile.display_name(),
+    ) {
+        (Some(given_name), Some(family_name), Some(display_name)) => {
+            kwargs.insert("givenname".to_string(), given_name.into());
+            kwargs.insert("sn".to_string(), family_name.into());
+            kwargs.insert("displayname".to_string(), display_name.into());
+        }
+        _ if replace => {
+            // the same placeholder name that add_user creates the account with
+            let username = user.identifier().username().to_string();
+            let project = user.identifier().project().to_string();
+
+            kwargs.insert(
+                "displayname".to_string(),
+                format!("{} {}", username, project).into(),
+            );
+            kwargs.insert("givenname".to_string(), username.into());
+            kwargs.insert("sn".to_string(), project.into());
+        }
+        _ => {}
+    }
+
+    // a null value deletes the attribute
+    match profile.email() {
+        Some(email) => {
+            kwargs.insert("mail".to_string(), email.into());
+        }
+        None if replace => {
+            kwargs.insert("mail".to_string(), serde_json::Value::Null);
+        }
+        None => {}
+    }
+
+    match profile.ssh_keys() {
+        Some(keys) if !keys.is_empty() => {
+            kwargs.insert("ipasshpubkey".to_string(), keys.into());
+        }
+        Some(_) => {
+            kwargs.insert("ipasshpubkey".to_string(), serde_json::Value::Null);
+        }
+        None if replace => {
+            kwargs.insert("ipasshpubkey".to_string(), serde_json::Value::Null);
+        }
+        None => {}
+    }
+
+    if let Some(shell) = profile.shell() {
+        kwargs.insert("loginshell".to_string(), shell.into());
+    }
+
+    if kwargs.is_empty() {
+        tracing::debug!(
+            "Nothing in the profile to update for user {}",
+            user.identifier()
+        );
+        return Ok(profile.clone());
+    }
+
+    kwargs.insert("uid".to_string(), user.userid().to_string().into());
+
+    // do not check for expiry below as this has to run to completion
+
+    match call_write_values::<IPAResponse>("user_mod", None, kwargs, expires).await {
+        Ok(_) => {
+            tracing::info!(
+                "Successfully updated the profile for user: {}",
+                user.identifier()
+            );
+        }
+        Err(Error::Call(message)) if message.contains("EmptyModlist") => {
+            // FreeIPA refuses a modification that would change nothing
+            tracing::debug!(
+                "Profile for user {} is already up to date. No changes needed.",
+                user.identifier()
+            );
+        }
+        Err(Error::NotFound(_)) => {
+            tracing::info!(
+                "User {} not found in FreeIPA. Assuming it has been removed behind our back.",
+                user
+            );
+
+            // clear the cache as FreeIPA has been changed behind our back
+            cache::clear().await?;
+
+            return Err(Error::Call(format!(
+                "User {} does not exist in FreeIPA?",
+                user.identifier()
+            )));
+        }
+        Err(e) => {
+            tracing::error!(
+                "Could not update the profile for user {}. Error: {}",
+                user.identifier(),
+                e
+            );
+            return Err(e);
+        }
+    }
+
+    // refresh the cached copy of the user
+    let _ = force_get_user(user.identifier(), expires).await?;
+
+    Ok(profile.clone())
+}
+
+///
+/// Return the name of the FreeIPA group that holds the managers of
+/// the passed project, e.g. `brics.myproject.managers`
+///
+fn identifier_to_managers_groupid(project: &ProjectIdentifier) -> Result<String, Error> {
+    Ok(format!(
+        "{}.managers",
+        identifier_to_projectid(project, false)?
+    ))
+}
+
+///
+/// Ensure that the managers group for the passed project exists,
+/// creating it if necessary. Note that the description must not start
+/// with a project identifier, or else this group would be mistaken for
+/// a project group by `get_groups`.
+///
+async fn ensure_managers_group_exists(
+    project: &ProjectIdentifier,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<String, Error> {
+    let groupid = identifier_to_managers_groupid(project)?;
+
+    let kwargs = {
+        let mut kwargs = HashMap::new();
+        kwargs.insert("cn".to_string(), groupid.clone());
+        kwargs.insert(
+            "description".to_string(),
+            format!(
+                "Managers of the {} project in the {} portal (OpenPortal)",
+                project.project(),
+                project.portal()
+            ),
+        );
+        kwargs
+    };
+
+    match call_write::<IPAResponse>("group_add", None, Some(kwargs), expires).await {
+        Ok(_) => {
+            tracing::info!("Created {} group in FreeIPA", groupid);
+            Ok(groupid)
+        }
+        Err(Error::Duplicate(_)) => {
+            // group already exists - that's fine
+            Ok(groupid)
+        }
+        Err(e) => {
+            tracing::error!("Failed to ensure {} group exists: {}", groupid, e);
+            Err(e)
+        }
+    }
+}
+
+///
+/// Remove the managers group of the passed project, e.g. when the
+/// project is removed. This is not an error if the group isn't there,
+/// e.g. because the project never had any managers.
+///
+async fn remove_managers_group(
+    project: &ProjectIdentifier,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<(), Error> {
+    let groupid = identifier_to_managers_groupid(project)?;
+
+    let kwargs = {
+        let mut kwargs = HashMap::new();
+        kwargs.insert("cn".to_string(), groupid.clone());
+        kwargs
+    };
+
+    match call_write::<IPAResponse>("group_del", None, Some(kwargs), expires).await {
+        Ok(_) => {
+            tracing::info!("Removed {} group from FreeIPA", groupid);
+            Ok(())
+        }
+        Err(Error::NotFound(_)) => Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to remove {} group: {}", groupid, e);
+            Err(e)
+        }
+    }
+}
+
+///
+/// Remove the passed user from the managers group of their project.
+/// This is not an error if the user (or the group) isn't there.
+///
+async fn remove_from_managers_group(
+    user: &IPAUser,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<(), Error> {
+    let groupid = identifier_to_managers_groupid(&user.identifier().project_identifier())?;
+
+    let kwargs = {
+        let mut kwargs = HashMap::new();
+        kwargs.insert("cn".to_string(), groupid.clone());
+        kwargs.insert("user".to_string(), user.userid().to_string());
+        kwargs
+    };
+
+    match call_write::<IPAResponse>("group_remove_member", None, Some(kwargs), expires).await {
+        Ok(_) => {
+            tracing::info!("Removed user {} from group {}", user.identifier(), groupid);
+            Ok(())
+        }
+        Err(Error::NotFound(_)) => Ok(()),
+        Err(e) => {
+            tracing::error!(
+                "Could not remove user {} from group {}: {}",
+                user.identifier(),
+                groupid,
+                e
+            );
+            Err(e)
+        }
+    }
+}
+
+///
+/// Set the role of the user within their project. If the role is one
+/// of the configured manager roles (e.g. "pi", "manager") then the user
+/// is added to their project's managers group (which is created if it
+/// doesn't exist), otherwise they are removed from it. This returns
+/// the user, or an error if the user doesn't exist or is not managed
+/// by OpenPortal.
+///
+pub async fn set_user_role(
+    user: &UserIdentifier,
+    role: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<IPAUser, Error> {
+    let role = validate_role(role)?;
+
+    let user = get_user(user, expires).await?.ok_or(Error::Call(format!(
+        "User {} does not exist in FreeIPA?",
+        user
+    )))?;
+
+    assert_not_expired(expires)?;
+
+    if !user.is_managed() {
+        tracing::warn!(
+            "Refusing to set the role of {} as they are not managed by OpenPortal",
+            user.identifier()
+        );
+        return Err(Error::UnmanagedUser(format!(
+            "Cannot set the role of {} as they are not managed by OpenPortal",
+            user.identifier()
+        )));
+    }
+
+    if !cache::get_manager_roles().await?.contains(&role) {
+        tracing::info!("Setting the role of {} to '{}'", user.identifier(), role);
+        remove_from_managers_group(&user, expires).await?;
+        return Ok(user);
+    }
+
+    if user.is_disabled() {
+        return Err(Error::Call(format!(
+            "Cannot make {} a manager of their project as their account is disabled",
+            user.identifier()
+        )));
+    }
+
+    let groupid =
+        ensure_managers_group_exists(&user.identifier().project_identifier(), expires).await?;
+
+    let kwargs = {
+        let mut kwargs = HashMap::new();
+        kwargs.insert("cn".to_string(), groupid.clone());
+        kwargs.insert("user".to_string(), user.userid().to_string());
+        kwargs
+    };
+
+    match call_write::<IPAResponse>("group_add_member", None, Some(kwargs), expires).await {
+        Ok(_) => {
+            tracing::info!(
+                "Added user {} to group {} as their role is '{}'",
+                user.identifier(),
+                groupid,
+                role
+            );
+        }
+        Err(Error::Duplicate(_)) => {
+            // already a member - that's fine
+        }
+        Err(e) => {
+            tracing::error!(
+                "Could not add user {} to group {}: {}",
+                user.identifier(),
+                groupid,
+                e
+            );
+            return Err(e);
+        }
+    }
+
+    Ok(user)
+}
+
 ///
 /// Return all of the groups that are managed by OpenPortal for the
 /// passed portal
@@ -4567,6 +4999,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_managers_group_is_not_the_project_group() {
+        // removing a project deletes its managers group, but must keep
+        // the project group, so that the project gets the same GID if
+        // it is added again
+        let project = match ProjectIdentifier::parse("proj.brics") {
+            Ok(p) => p,
+            Err(e) => unreachable!("project: {:?}", e),
+        };
+
+        let managers = match identifier_to_managers_groupid(&project) {
+            Ok(id) => id,
+            Err(e) => unreachable!("managers: {:?}", e),
+        };
+
+        assert_eq!(managers, "brics.proj.managers");
+        assert_ne!(
+            Some(managers),
+            identifier_to_projectid(&project, false).ok()
+        );
+    }
+
     #[test]
     fn test_group_member_uids_come_from_member_user() {
         // FreeIPA's JSON-RPC returns group members as `member_user` (with the
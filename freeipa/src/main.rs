@@ -14,9 +14,9 @@ mod cache;
 use greatwestern::grammar::Instruction::{
     AddProject, AddUser, BlockUser, GetProjectMapping, GetProjects, GetUserMapping, GetUsers,
     IsBlockedUser, IsExistingProject, IsExistingUser, IsProtectedUser, RemoveProject, RemoveUser,
-    UnblockUser, UpdateHomeDir,
+    SetUserProfile, SetUserRole, UnblockUser, UpdateHomeDir, UpdateUserProfile,
 };
-use greatwestern::grammar::UserMapping;
+use greatwestern::grammar::{validate_role, UserMapping};
 use greatwestern::Hpc;
 use templemeads::agent::account::{process_args, run, Defaults};
 use templemeads::agent::{Peer, Type as AgentType};
@@ -123,6 +123,15 @@ async fn main() -> Result<()> {
     let instance_groups: HashMap<Peer, Vec<IPAGroup>> =
         IPAGroup::parse_instance_groups(&config.option("instance-groups", ""))?;
 
+    // The project roles whose members are added to their project's
+    // managers group, as a comma-separated list
+    let manager_roles: Vec<String> = config
+        .option("manager-roles", "pi,manager")
+        .split(',')
+        .filter(|role| !role.trim().is_empty())
+        .map(validate_role)
+        .collect::<Result<Vec<_>, _>>()?;
+
     if freeipa_server.is_empty() {
         return Err(anyhow::anyhow!(
             "No FreeIPA server specified. Please set this in the freeipa-server option."
@@ -149,6 +158,7 @@ async fn main() -> Result<()> {
 
     cache::set_system_groups(&system_groups).await?;
     cache::set_instance_groups(&instance_groups).await?;
+    cache::set_manager_roles(&manager_roles).await?;
 
     // connect the single shared FreeIPA client - this will be used in the
     // async function (we can't bind variables to async functions, or else
@@ -222,6 +232,18 @@ async fn main() -> Result<()> {
                     let _ = freeipa::update_homedir(&user, &homedir, job.expires()).await?;
                     job.completed(homedir)
                 },
+                SetUserProfile(user, profile) => {
+                    let profile = freeipa::set_user_profile(&user, &profile, true, job.expires()).await?;
+                    job.completed(profile)
+                },
+                UpdateUserProfile(user, profile) => {
+                    let profile = freeipa::set_user_profile(&user, &profile, false, job.expires()).await?;
+                    job.completed(profile)
+                },
+                SetUserRole(user, role) => {
+                    let user = freeipa::set_user_role(&user, &role, job.expires()).await?;
+                    job.completed(user.mapping()?)
+                },
                 GetProjectMapping(project) => {
                     let mapping = freeipa::get_project_mapping(&project, job.expires()).await?;
                     job.completed(mapping)
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ *
+ * The cost of a project's usage on a single day, per (local) user
+ * and per component
+ *
+ */
+export type DailyProjectCostReport = { users: { [key in string]?: number }, components: { [key in string]?: number }, };
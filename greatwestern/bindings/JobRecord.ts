@@ -0,0 +1,67 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Usage } from "./Usage";
+
+/**
+ *
+ * The accounting record of a single job that was charged to a
+ * project. The usage is only that which fell within the dates
+ * that the records were asked for.
+ *
+ */
+export type JobRecord = { 
+/**
+ * The scheduler's ID of the job
+ */
+id: bigint, 
+/**
+ * The user who ran the job, if they are known
+ */
+user?: string | null, 
+/**
+ * The local (scheduler) username of the user who ran the job
+ */
+local_user: string, 
+/**
+ * The state of the job, e.g. "COMPLETED" or "RUNNING"
+ */
+state: string, 
+/**
+ * The QoS the job ran under
+ */
+qos: string, 
+/**
+ * When the job became eligible to run
+ */
+eligible_time: string, 
+/**
+ * When the job started running
+ */
+start_time: string, 
+/**
+ * When the job ended, or the end of the dates if it ran on
+ */
+end_time: string, 
+/**
+ * The number of nodes allocated to the job
+ */
+nodes: bigint, 
+/**
+ * The number of CPUs allocated to the job
+ */
+cpus: bigint, 
+/**
+ * The number of GPUs allocated to the job
+ */
+gpus: bigint, 
+/**
+ * The memory (in MB) allocated to the job
+ */
+memory: bigint, 
+/**
+ * The billing units allocated to the job
+ */
+billing: bigint, 
+/**
+ * The usage charged to the project for the job
+ */
+usage: Usage, };
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JobRecord } from "./JobRecord";
+
+/**
+ *
+ * A page of the job records of a project over a range of dates,
+ * in the order that the jobs started
+ *
+ */
+export type JobRecords = { project: string, dates: string, 
+/**
+ * The number of this page, counting from 1
+ */
+page: number, 
+/**
+ * The most records in each page
+ */
+page_size: number, 
+/**
+ * The number of records in all of the pages
+ */
+total: bigint, records: Array<JobRecord>, };
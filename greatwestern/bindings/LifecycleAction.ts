@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ *
+ * What happens to a project when it reaches a stage of its lifecycle
+ *
+ */
+export type LifecycleAction = { "warn": number } | "block" | "remove";
@@ -0,0 +1,26 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LifecycleAction } from "./LifecycleAction";
+
+/**
+ *
+ * A change to a project on a resource that falls due on a date,
+ * because of the end date of the project's award
+ *
+ */
+export type LifecycleTransition = { 
+/**
+ * The project (award) that changes
+ */
+project: string, 
+/**
+ * The (virtual) resource the project is on
+ */
+resource: string, 
+/**
+ * The date on which the change is due
+ */
+date: string, 
+/**
+ * What happens to the project
+ */
+action: LifecycleAction, };
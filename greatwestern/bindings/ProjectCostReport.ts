@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DailyProjectCostReport } from "./DailyProjectCostReport";
+
+/**
+ *
+ * The cost of a project's usage over a range of days, as calculated
+ * from its usage report by a RateCard
+ *
+ */
+export type ProjectCostReport = { project: string, currency: string, reports: { [key in string]?: DailyProjectCostReport }, users: { [key in string]?: string }, };
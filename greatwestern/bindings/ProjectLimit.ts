@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Usage } from "./Usage";
+import type { UserLimit } from "./UserLimit";
+
+/**
+ * The limit of a project, together with the limits of those of its
+ * members that have their own
+ *
+ */
+export type ProjectLimit = { 
+/**
+ * The limit of the project as a whole, as returned by `get_limit`
+ */
+limit: Usage, 
+/**
+ * The limits of the members that have them, keyed by the user's
+ * identifier (or, until the cluster has mapped them, their
+ * local username)
+ */
+users: { [key in string]?: UserLimit }, };
@@ -1,7 +1,10 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
 /**
- * Represents a storage quota with a limit and optional current usage
+ * Represents a storage quota with a limit and optional current usage.
+ * The inode (file count) limit and usage are carried in their own
+ * optional fields, so that peers that predate inode quotas still see
+ * a plain size in `limit`, and simply ignore the fields they don't know
  */
 export type Quota = { 
 /**
@@ -11,4 +14,12 @@ limit: string,
 /**
  * Current usage expressed as a human-readable size string (e.g. "2.5GB")
  */
-usage?: string, };
+usage?: string, 
+/**
+ * Limit on the number of files (inodes), where 0 means unlimited
+ */
+inode_limit?: bigint, 
+/**
+ * Current number of files (inodes) used
+ */
+inode_usage?: bigint, };
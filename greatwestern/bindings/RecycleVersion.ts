@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ *
+ * Identifies one version of a recycled directory. This is the UTC time
+ * the directory was recycled (e.g. "20260314T101500Z"), with a "-2",
+ * "-3" etc. suffix if it was recycled more than once in the same second,
+ * or "legacy" for a directory recycled before versions were kept.
+ *
+ */
+export type RecycleVersion = string;
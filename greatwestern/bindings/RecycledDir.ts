@@ -0,0 +1,25 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ *
+ * A user or project directory that has been removed, and is waiting
+ * in the recycle bin of its volume until it is restored or purged
+ *
+ */
+export type RecycledDir = { 
+/**
+ * The volume the directory was on
+ */
+volume: string, 
+/**
+ * The path the directory had, and will be restored to
+ */
+path: string, 
+/**
+ * The version, used to choose which copy to restore
+ */
+version: string, 
+/**
+ * When the directory was recycled
+ */
+recycled: string, };
@@ -0,0 +1,35 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A reservation of nodes for the members of a project, e.g. for a
+ * workshop or a deadline. The nodes are given either by name or by
+ * number (in which case the scheduler chooses them). Reservations
+ * read back from the scheduler give both, and if both are given then
+ * the named nodes are reserved.
+ *
+ */
+export type Reservation = { 
+/**
+ * The name of the reservation, unique within the project
+ */
+name: string, 
+/**
+ * The nodes to reserve (names or hostlist ranges)
+ */
+nodes?: Array<string>, 
+/**
+ * The number of nodes to reserve
+ */
+node_count?: number, 
+/**
+ * When the reservation starts (UTC)
+ */
+start_time: string, 
+/**
+ * When the reservation ends (UTC)
+ */
+end_time: string, 
+/**
+ * The partition to reserve the nodes from
+ */
+partition?: string, };
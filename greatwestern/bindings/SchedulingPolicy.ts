@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The scheduling entitlements of a project - which QoS its jobs may
+ * use, which of those is the default, and which partitions its members
+ * can submit to. Scheduler agents apply this to the project's account.
+ *
+ * As for UserProfile, every field is an "option". In a
+ * `set_scheduling_policy` the policy replaces what the account held,
+ * so a missing field is cleared (the QoS go back to those inherited
+ * from the parent account, and the members lose any partitions).
+ *
+ */
+export type SchedulingPolicy = { 
+/**
+ * The QoS that jobs of the project may use
+ */
+qos?: Array<string>, 
+/**
+ * The QoS that jobs use if they don't ask for one. This must
+ * be one of `qos`, if that is set
+ */
+default_qos?: string, 
+/**
+ * The partitions that members of the project can submit to
+ */
+partitions?: Array<string>, };
@@ -0,0 +1,33 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Usage } from "./Usage";
+
+/**
+ * The limits on one member of a project, within the project's own
+ * limit, so that one member cannot use up the whole allocation.
+ * Scheduler agents apply these to the user's association with the
+ * project's account.
+ *
+ * As for SchedulingPolicy, every field is an "option". In a
+ * `set_user_limit` the limit replaces what the user held, so a missing
+ * field is cleared.
+ *
+ */
+export type UserLimit = { 
+/**
+ * The most that the user can use, in the same units as the
+ * project's limit
+ */
+usage?: Usage, 
+/**
+ * The most jobs that the user can have running at once
+ */
+max_jobs?: number, 
+/**
+ * The most jobs that the user can have running or queued at once
+ */
+max_submit_jobs?: number, 
+/**
+ * The most of each trackable resource (e.g. "cpu", "mem" or
+ * "gres/gpu") that the user's running jobs can hold at once
+ */
+grp_tres?: { [key in string]?: bigint }, };
@@ -0,0 +1,30 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The personal details of a user that account agents write into the
+ * user's account, so that they can log in (with their SSH keys) and be
+ * contacted from the first day their account exists.
+ *
+ * As for AwardDetails, every field is an "option". In a
+ * `set_user_profile` the profile replaces what the account held, so
+ * a missing field is cleared. In an `update_user_profile` only the
+ * fields that are set are changed.
+ *
+ */
+export type UserProfile = { 
+/**
+ * The user's full name, as it should be displayed
+ */
+display_name?: string, 
+/**
+ * The user's email address
+ */
+email?: string, 
+/**
+ * The user's SSH public keys, one OpenSSH public key per entry
+ */
+ssh_keys?: Array<string>, 
+/**
+ * The user's login shell (e.g. "/bin/bash")
+ */
+shell?: string, };
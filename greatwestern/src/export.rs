@@ -0,0 +1,802 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+use chrono::Datelike;
+use std::collections::{BTreeMap, HashMap};
+
+use templemeads::named::NamedType;
+use templemeads::Error;
+
+use crate::grammar::{Date, UserIdentifier};
+use crate::jobrecords::{JobRecord, JobRecords};
+use crate::usagereport::{ProjectUsageReport, Usage, UsageReport};
+
+///
+/// The formats that reports can be exported in, for accounting
+/// systems that cannot read OpenPortal's own JSON
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// OpenPortal's own JSON, unchanged
+    Json,
+
+    /// Comma-separated values, with a header row
+    Csv,
+
+    /// The pipe-separated `sacct` records that Open XDMoD's Slurm
+    /// shredder ingests into its Jobs realm - job records only
+    Xdmod,
+
+    /// APEL (EMI) summary job records, one per user, project and
+    /// month - usage reports only. The reports do not record wall or
+    /// CPU time, so the duration fields are left out.
+    Apel,
+
+    /// APEL summary job records as `Apel`, with the usage charged to
+    /// the project (in the units of the report) given as each of the
+    /// four duration fields, for repositories that require them
+    ApelBilled,
+}
+
+impl std::fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Csv => write!(f, "csv"),
+            Self::Xdmod => write!(f, "xdmod"),
+            Self::Apel => write!(f, "apel"),
+            Self::ApelBilled => write!(f, "apel-billed"),
+        }
+    }
+}
+
+impl ReportFormat {
+    pub fn parse(format: &str) -> Result<Self, Error> {
+        match format.trim().to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "xdmod" => Ok(Self::Xdmod),
+            "apel" => Ok(Self::Apel),
+            "apel-billed" => Ok(Self::ApelBilled),
+            _ => Err(Error::Parse(format!(
+                "Unknown report format '{}' - this should be one of json, csv, xdmod, apel or apel-billed",
+                format
+            ))),
+        }
+    }
+
+    /// The media (MIME) type of reports exported in this format
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv",
+            Self::Xdmod => "text/plain",
+            Self::Apel | Self::ApelBilled => "text/plain",
+        }
+    }
+}
+
+/// The fields of each record, in the order of `sacct --format` that
+/// Open XDMoD's Slurm shredder expects
+const XDMOD_FIELDS: usize = 26;
+
+/// The header line of an APEL summary message
+const APEL_HEADER: &str = "APEL-summary-job-message: v0.2";
+
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+fn csv_row(fields: &[String]) -> String {
+    let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+    format!("{}\n", fields.join(","))
+}
+
+fn hours(usage: &Usage) -> String {
+    format!("{:.3}", usage.hours())
+}
+
+///
+/// Return the portal user of each local user in the report
+///
+fn local_to_portal(report: &ProjectUsageReport) -> HashMap<String, UserIdentifier> {
+    report
+        .user_mapping()
+        .into_iter()
+        .map(|(user, local_user)| (local_user, user))
+        .collect()
+}
+
+const USAGE_CSV_HEADER: [&str; 10] = [
+    "portal",
+    "project",
+    "date",
+    "user",
+    "local_user",
+    "component",
+    "usage_seconds",
+    "usage_hours",
+    "jobs",
+    "wait_seconds",
+];
+
+///
+/// Return the CSV rows (without the header) of a project's usage - one
+/// row per user per day, followed by one row for each component of
+/// their usage on that day. Jobs and waits are only given in the first
+/// row, so that they are not counted more than once.
+///
+fn project_usage_csv_rows(report: &ProjectUsageReport) -> String {
+    let users = local_to_portal(report);
+    let project = report.project();
+    let mut rows = String::new();
+
+    for date in report.dates() {
+        let Some(daily) = report.daily_report(&date) else {
+            continue;
+        };
+
+        let mut local_users = daily.local_users();
+        local_users.sort();
+
+        let components: Vec<_> = daily
+            .components()
+            .into_iter()
+            .map(|c| (daily.get_component(&c), c))
+            .collect();
+
+        for local_user in local_users {
+            let user = users
+                .get(&local_user)
+                .map(|u| u.to_string())
+                .unwrap_or_default();
+
+            let usage = daily.usage(&local_user);
+
+            rows.push_str(&csv_row(&[
+                project.portal_identifier().to_string(),
+                project.to_string(),
+                date.to_string(),
+                user.clone(),
+                local_user.clone(),
+                String::new(),
+                usage.seconds().to_string(),
+                hours(&usage),
+                daily.num_jobs_for_user(&local_user).to_string(),
+                daily.wait_seconds_for_user(&local_user).to_string(),
+            ]));
+
+            for (component_report, component) in &components {
+                let usage = component_report.usage(&local_user);
+
+                if usage.is_zero() {
+                    continue;
+                }
+
+                rows.push_str(&csv_row(&[
+                    project.portal_identifier().to_string(),
+                    project.to_string(),
+                    date.to_string(),
+                    user.clone(),
+                    local_user.clone(),
+                    component.clone(),
+                    usage.seconds().to_string(),
+                    hours(&usage),
+                    String::new(),
+                    String::new(),
+                ]));
+            }
+        }
+    }
+
+    rows
+}
+
+fn usage_csv(reports: &[ProjectUsageReport]) -> String {
+    let header: Vec<String> = USAGE_CSV_HEADER.iter().map(|h| h.to_string()).collect();
+    let mut csv = csv_row(&header);
+
+    for report in reports {
+        csv.push_str(&project_usage_csv_rows(report));
+    }
+
+    csv
+}
+
+/// The month, project and (portal) user that an APEL summary is for
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct ApelKey {
+    year: i32,
+    month: u32,
+    project: String,
+    user: String,
+}
+
+/// The usage and jobs of an APEL summary, and the first and last days
+/// that they were in
+#[derive(Debug, Clone)]
+struct ApelSummary {
+    usage: Usage,
+    jobs: u64,
+    first: Date,
+    last: Date,
+}
+
+///
+/// Return the usage of `reports` as APEL summary records, one for
+/// each (portal) user of each project in each month, with a record
+/// for the usage of local users who are not mapped to a portal user.
+/// The reports only record the usage charged to each user, not their
+/// wall or CPU time, so the durations are only given if `billed`, when
+/// the charged usage is given as all four of them.
+///
+fn usage_apel(reports: &[ProjectUsageReport], site: &str, billed: bool) -> String {
+    let mut summaries: BTreeMap<ApelKey, ApelSummary> = BTreeMap::new();
+
+    for report in reports {
+        let users = local_to_portal(report);
+        let project = report.project().to_string();
+
+        for date in report.dates() {
+            let Some(daily) = report.daily_report(&date) else {
+                continue;
+            };
+
+            for local_user in daily.local_users() {
+                let usage = daily.usage(&local_user);
+                let jobs = daily.num_jobs_for_user(&local_user);
+
+                if usage.is_zero() && jobs == 0 {
+                    continue;
+                }
+
+                let user = users
+                    .get(&local_user)
+                    .map(|u| u.to_string())
+                    .unwrap_or_default();
+
+                let day = date.to_chrono();
+
+                let key = ApelKey {
+                    year: day.year(),
+                    month: day.month(),
+                    project: project.clone(),
+                    user,
+                };
+
+                let summary = summaries.entry(key).or_insert_with(|| ApelSummary {
+                    usage: Usage::default(),
+                    jobs: 0,
+                    first: date.clone(),
+                    last: date.clone(),
+                });
+
+                summary.usage += usage;
+                summary.jobs = summary.jobs.saturating_add(jobs);
+
+                if date < summary.first {
+                    summary.first = date.clone();
+                }
+
+                if date > summary.last {
+                    summary.last = date.clone();
+                }
+            }
+        }
+    }
+
+    let mut apel = format!("{}\n", APEL_HEADER);
+
+    for (
+        ApelKey {
+            year,
+            month,
+            project,
+            user,
+        },
+        ApelSummary {
+            usage,
+            jobs,
+            first,
+            last,
+        },
+    ) in summaries
+    {
+        apel.push_str(&format!("Site: {}\n", site));
+        apel.push_str(&format!("Month: {}\n", month));
+        apel.push_str(&format!("Year: {}\n", year));
+
+        if !user.is_empty() {
+            apel.push_str(&format!("GlobalUserName: {}\n", user));
+        }
+
+        apel.push_str(&format!("VO: {}\n", project));
+        apel.push_str("Infrastructure: OpenPortal\n");
+        apel.push_str(&format!("EarliestEndTime: {}\n", first.timestamp()));
+        apel.push_str(&format!(
+            "LatestEndTime: {}\n",
+            last.timestamp().saturating_add(86399)
+        ));
+
+        if billed {
+            apel.push_str(&format!("WallDuration: {}\n", usage.seconds()));
+            apel.push_str(&format!("CpuDuration: {}\n", usage.seconds()));
+            apel.push_str(&format!("NormalisedWallDuration: {}\n", usage.seconds()));
+            apel.push_str(&format!("NormalisedCpuDuration: {}\n", usage.seconds()));
+        }
+
+        apel.push_str(&format!("NumberOfJobs: {}\n", jobs));
+        apel.push_str("%%\n");
+    }
+
+    apel
+}
+
+///
+/// Export the usage report of a project in `format`. `site` names the
+/// site in formats that need one, and is the report's portal if None.
+///
+pub fn export_project_usage_report(
+    report: &ProjectUsageReport,
+    format: ReportFormat,
+    site: Option<&str>,
+) -> Result<String, Error> {
+    let site = site
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| report.portal().to_string());
+
+    match format {
+        ReportFormat::Json => report.to_json(),
+        ReportFormat::Csv => Ok(usage_csv(std::slice::from_ref(report))),
+        ReportFormat::Apel => Ok(usage_apel(std::slice::from_ref(report), &site, false)),
+        ReportFormat::ApelBilled => Ok(usage_apel(std::slice::from_ref(report), &site, true)),
+        ReportFormat::Xdmod => Err(not_supported(&ProjectUsageReport::type_name(), format)),
+    }
+}
+
+///
+/// Export the usage report of all of a portal's projects in `format`.
+/// `site` names the site in formats that need one, and is the report's
+/// portal if None.
+///
+pub fn export_usage_report(
+    report: &UsageReport,
+    format: ReportFormat,
+    site: Option<&str>,
+) -> Result<String, Error> {
+    let site = site
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| report.portal().to_string());
+
+    let reports: Vec<ProjectUsageReport> = report
+        .projects()
+        .iter()
+        .map(|p| report.get_report(p))
+        .collect();
+
+    match format {
+        ReportFormat::Json => report.to_json(),
+        ReportFormat::Csv => Ok(usage_csv(&reports)),
+        ReportFormat::Apel => Ok(usage_apel(&reports, &site, false)),
+        ReportFormat::ApelBilled => Ok(usage_apel(&reports, &site, true)),
+        ReportFormat::Xdmod => Err(not_supported(&UsageReport::type_name(), format)),
+    }
+}
+
+fn slurm_time(time: &chrono::DateTime<chrono::Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+fn slurm_elapsed(record: &JobRecord) -> String {
+    let seconds = record
+        .end_time()
+        .signed_duration_since(record.start_time())
+        .num_seconds()
+        .max(0);
+
+    let days = seconds / 86400;
+    let hms = format!(
+        "{:02}:{:02}:{:02}",
+        (seconds % 86400) / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    );
+
+    match days {
+        0 => hms,
+        days => format!("{}-{}", days, hms),
+    }
+}
+
+///
+/// Return the job records as the `sacct --parsable2` lines that Open
+/// XDMoD's Slurm shredder reads. The project is given as the account,
+/// and fields that OpenPortal does not record (e.g. the partition and
+/// uid) are left empty.
+///
+fn records_xdmod(records: &JobRecords, site: &str) -> String {
+    let mut lines = String::new();
+
+    for record in records.records() {
+        let tres = format!(
+            "billing={},cpu={},gres/gpu={},mem={}M,node={}",
+            record.billing(),
+            record.cpus(),
+            record.gpus(),
+            record.memory(),
+            record.nodes()
+        );
+
+        let fields: [String; XDMOD_FIELDS] = [
+            record.id().to_string(),            // jobid
+            record.id().to_string(),            // jobidraw
+            site.to_string(),                   // cluster
+            String::new(),                      // partition
+            record.qos().to_string(),           // qos
+            records.project().to_string(),      // account
+            String::new(),                      // group
+            String::new(),                      // gid
+            record.local_user().to_string(),    // user
+            String::new(),                      // uid
+            slurm_time(record.eligible_time()), // submit
+            slurm_time(record.eligible_time()), // eligible
+            slurm_time(record.start_time()),    // start
+            slurm_time(record.end_time()),      // end
+            slurm_elapsed(record),              // elapsed
+            "0:0".to_string(),                  // exitcode
+            record.state().to_string(),         // state
+            record.nodes().to_string(),         // nnodes
+            record.cpus().to_string(),          // ncpus
+            record.cpus().to_string(),          // reqcpus
+            format!("{}M", record.memory()),    // reqmem
+            tres.clone(),                       // reqtres
+            tres,                               // alloctres
+            String::new(),                      // timelimit
+            String::new(),                      // nodelist
+            String::new(),                      // jobname
+        ];
+
+        lines.push_str(&fields.map(|f| f.replace('|', "_")).join("|"));
+        lines.push('\n');
+    }
+
+    lines
+}
+
+const RECORDS_CSV_HEADER: [&str; 17] = [
+    "project",
+    "id",
+    "user",
+    "local_user",
+    "state",
+    "qos",
+    "eligible_time",
+    "start_time",
+    "end_time",
+    "wait_seconds",
+    "nodes",
+    "cpus",
+    "gpus",
+    "memory_mb",
+    "billing",
+    "usage_seconds",
+    "usage_hours",
+];
+
+fn records_csv(records: &JobRecords) -> String {
+    let header: Vec<String> = RECORDS_CSV_HEADER.iter().map(|h| h.to_string()).collect();
+    let mut csv = csv_row(&header);
+
+    for record in records.records() {
+        csv.push_str(&csv_row(&[
+            records.project().to_string(),
+            record.id().to_string(),
+            record.user().map(|u| u.to_string()).unwrap_or_default(),
+            record.local_user().to_string(),
+            record.state().to_string(),
+            record.qos().to_string(),
+            record.eligible_time().to_rfc3339(),
+            record.start_time().to_rfc3339(),
+            record.end_time().to_rfc3339(),
+            record.wait_seconds().to_string(),
+            record.nodes().to_string(),
+            record.cpus().to_string(),
+            record.gpus().to_string(),
+            record.memory().to_string(),
+            record.billing().to_string(),
+            record.usage().seconds().to_string(),
+            hours(record.usage()),
+        ]));
+    }
+
+    csv
+}
+
+///
+/// Export a page of job records in `format`. `site` names the cluster
+/// in formats that need one, and is the project's portal if None.
+///
+pub fn export_job_records(
+    records: &JobRecords,
+    format: ReportFormat,
+    site: Option<&str>,
+) -> Result<String, Error> {
+    let site = site
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| records.project().portal_identifier().to_string());
+
+    match format {
+        ReportFormat::Json => records.to_json(),
+        ReportFormat::Csv => Ok(records_csv(records)),
+        ReportFormat::Xdmod => Ok(records_xdmod(records, &site)),
+        ReportFormat::Apel | ReportFormat::ApelBilled => {
+            Err(not_supported(&JobRecords::type_name(), format))
+        }
+    }
+}
+
+fn not_supported(result_type: &str, format: ReportFormat) -> Error {
+    let hint = match format {
+        ReportFormat::Xdmod => " - XDMoD ingests individual jobs, so export job records instead",
+        ReportFormat::Apel | ReportFormat::ApelBilled => {
+            " - APEL summaries are made from usage reports"
+        }
+        _ => "",
+    };
+
+    Error::Incompatible(format!(
+        "A {} cannot be exported as {}{}",
+        result_type, format, hint
+    ))
+}
+
+///
+/// Export the result of a job, of type `result_type` and held as JSON
+/// in `result`, in `format`. Return the media type of the export
+/// together with its content.
+///
+pub fn export_result(
+    result_type: &str,
+    result: &str,
+    format: &str,
+    site: Option<&str>,
+) -> Result<(String, String), Error> {
+    let format = ReportFormat::parse(format)?;
+
+    let content = match result_type {
+        "ProjectUsageReport" => {
+            export_project_usage_report(&ProjectUsageReport::from_json(result)?, format, site)?
+        }
+        "UsageReport" => export_usage_report(&UsageReport::from_json(result)?, format, site)?,
+        "JobRecords" => export_job_records(&JobRecords::from_json(result)?, format, site)?,
+        _ => {
+            return Err(Error::Incompatible(format!(
+                "Results of type {} cannot be exported - only usage reports and job records can",
+                result_type
+            )))
+        }
+    };
+
+    Ok((format.media_type().to_string(), content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{DateRange, ProjectIdentifier, UserMapping};
+    use crate::jobrecords::Page;
+    use crate::usagereport::DailyProjectUsageReport;
+
+    const DAYS: [&str; 3] = ["2026-03-30", "2026-03-31", "2026-04-01"];
+
+    fn project() -> ProjectIdentifier {
+        ProjectIdentifier::parse("physics.brics").unwrap_or_else(|e| unreachable!("{:?}", e))
+    }
+
+    fn mapping() -> UserMapping {
+        UserMapping::parse("alice.physics.brics:alice_phy:physics")
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+    }
+
+    /// the usage of alice (who is mapped) and bob (who isn't) on `days`
+    fn usage_report(days: &[&str]) -> ProjectUsageReport {
+        let mut report = ProjectUsageReport::new(&project());
+        report
+            .add_mapping(&mapping())
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        for day in days {
+            let mut daily = DailyProjectUsageReport::default();
+            daily.add_usage("alice_phy", Usage::from_hours(2.0));
+            daily.add_component_usage("gpu", "alice_phy", Usage::from_hours(1.0));
+            daily.add_jobs("alice_phy", 3);
+            daily.add_usage("bob_phy", Usage::from_hours(1.0));
+            daily.add_jobs("bob_phy", 1);
+            report.set_report(
+                &Date::parse(day).unwrap_or_else(|e| unreachable!("{:?}", e)),
+                &daily,
+            );
+        }
+
+        report
+    }
+
+    /// a job of alice's that waited 5 minutes and then ran for 25 hours
+    fn job_records() -> JobRecords {
+        let mut record = JobRecord::new(42, "alice_phy", "COMPLETED", "normal");
+        let start = chrono::DateTime::parse_from_rfc3339("2026-03-30T09:00:00Z")
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+            .with_timezone(&chrono::Utc);
+        record.set_times(
+            &(start - chrono::Duration::minutes(5)),
+            &start,
+            &(start + chrono::Duration::hours(25)),
+        );
+        record.set_resources(1, 64, 0, 4096, 64);
+
+        let mut records = JobRecords::paginate(
+            &project(),
+            &DateRange::parse("2026-03-30:2026-04-01").unwrap_or_else(|e| unreachable!("{:?}", e)),
+            &Page::default(),
+            vec![record],
+        );
+        records.add_mappings(&[mapping()]);
+
+        records
+    }
+
+    #[test]
+    fn test_usage_csv() {
+        let csv = export_project_usage_report(&usage_report(&DAYS), ReportFormat::Csv, None)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let mut expected = String::from(
+            "portal,project,date,user,local_user,component,usage_seconds,usage_hours,jobs,wait_seconds\n",
+        );
+
+        for day in DAYS {
+            expected.push_str(&format!(
+                "brics,physics.brics,{day},alice.physics.brics,alice_phy,,7200,2.000,3,0\n\
+                 brics,physics.brics,{day},alice.physics.brics,alice_phy,gpu,3600,1.000,,\n\
+                 brics,physics.brics,{day},,bob_phy,,3600,1.000,1,0\n"
+            ));
+        }
+
+        assert_eq!(csv, expected);
+    }
+
+    #[test]
+    fn test_usage_apel() {
+        let apel = export_project_usage_report(
+            &usage_report(&DAYS),
+            ReportFormat::Apel,
+            Some("BRICS-SITE"),
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        // bob (who has no portal user) then alice, in March and then
+        // April, with no durations as the report doesn't record them
+        assert_eq!(
+            apel,
+            "APEL-summary-job-message: v0.2\n\
+             Site: BRICS-SITE\nMonth: 3\nYear: 2026\nVO: physics.brics\n\
+             Infrastructure: OpenPortal\n\
+             EarliestEndTime: 1774828800\nLatestEndTime: 1775001599\n\
+             NumberOfJobs: 2\n%%\n\
+             Site: BRICS-SITE\nMonth: 3\nYear: 2026\n\
+             GlobalUserName: alice.physics.brics\nVO: physics.brics\n\
+             Infrastructure: OpenPortal\n\
+             EarliestEndTime: 1774828800\nLatestEndTime: 1775001599\n\
+             NumberOfJobs: 6\n%%\n\
+             Site: BRICS-SITE\nMonth: 4\nYear: 2026\nVO: physics.brics\n\
+             Infrastructure: OpenPortal\n\
+             EarliestEndTime: 1775001600\nLatestEndTime: 1775087999\n\
+             NumberOfJobs: 1\n%%\n\
+             Site: BRICS-SITE\nMonth: 4\nYear: 2026\n\
+             GlobalUserName: alice.physics.brics\nVO: physics.brics\n\
+             Infrastructure: OpenPortal\n\
+             EarliestEndTime: 1775001600\nLatestEndTime: 1775087999\n\
+             NumberOfJobs: 3\n%%\n"
+        );
+
+        assert!(
+            export_project_usage_report(&usage_report(&DAYS), ReportFormat::Xdmod, None).is_err()
+        );
+    }
+
+    #[test]
+    fn test_usage_apel_billed() {
+        let report = usage_report(&["2026-03-30", "2026-03-31"]);
+
+        let apel = export_project_usage_report(&report, ReportFormat::ApelBilled, None)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        // the charged usage is given as each of the durations, and the
+        // site defaults to the portal
+        assert_eq!(
+            apel,
+            "APEL-summary-job-message: v0.2\n\
+             Site: brics\nMonth: 3\nYear: 2026\nVO: physics.brics\n\
+             Infrastructure: OpenPortal\n\
+             EarliestEndTime: 1774828800\nLatestEndTime: 1775001599\n\
+             WallDuration: 7200\nCpuDuration: 7200\n\
+             NormalisedWallDuration: 7200\nNormalisedCpuDuration: 7200\n\
+             NumberOfJobs: 2\n%%\n\
+             Site: brics\nMonth: 3\nYear: 2026\n\
+             GlobalUserName: alice.physics.brics\nVO: physics.brics\n\
+             Infrastructure: OpenPortal\n\
+             EarliestEndTime: 1774828800\nLatestEndTime: 1775001599\n\
+             WallDuration: 14400\nCpuDuration: 14400\n\
+             NormalisedWallDuration: 14400\nNormalisedCpuDuration: 14400\n\
+             NumberOfJobs: 6\n%%\n"
+        );
+    }
+
+    #[test]
+    fn test_job_records_xdmod() {
+        let xdmod = export_job_records(&job_records(), ReportFormat::Xdmod, Some("cluster1"))
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let tres = "billing=64,cpu=64,gres/gpu=0,mem=4096M,node=1";
+
+        assert_eq!(
+            xdmod,
+            format!(
+                "42|42|cluster1||normal|physics.brics|||alice_phy||\
+                 2026-03-30T08:55:00|2026-03-30T08:55:00|2026-03-30T09:00:00|2026-03-31T10:00:00|\
+                 1-01:00:00|0:0|COMPLETED|1|64|64|4096M|{tres}|{tres}|||\n"
+            )
+        );
+        assert_eq!(xdmod.trim_end().split('|').count(), XDMOD_FIELDS);
+
+        assert!(export_job_records(&job_records(), ReportFormat::Apel, None).is_err());
+    }
+
+    #[test]
+    fn test_job_records_csv() {
+        let csv = export_job_records(&job_records(), ReportFormat::Csv, None)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            csv,
+            "project,id,user,local_user,state,qos,eligible_time,start_time,end_time,\
+             wait_seconds,nodes,cpus,gpus,memory_mb,billing,usage_seconds,usage_hours\n\
+             physics.brics,42,alice.physics.brics,alice_phy,COMPLETED,normal,\
+             2026-03-30T08:55:00+00:00,2026-03-30T09:00:00+00:00,2026-03-31T10:00:00+00:00,\
+             300,1,64,0,4096,64,0,0.000\n"
+        );
+    }
+
+    #[test]
+    fn test_export_result() {
+        let records = job_records();
+
+        // the result of a job is exported by its type
+        let (media_type, content) = export_result(
+            "JobRecords",
+            &records
+                .to_json()
+                .unwrap_or_else(|e| unreachable!("{:?}", e)),
+            "CSV",
+            None,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(media_type, "text/csv");
+        assert_eq!(
+            content,
+            export_job_records(&records, ReportFormat::Csv, None)
+                .unwrap_or_else(|e| unreachable!("{:?}", e))
+        );
+
+        assert_eq!(
+            ReportFormat::parse("APEL-billed").ok(),
+            Some(ReportFormat::ApelBilled)
+        );
+        assert!(export_result("JobRecords", "{}", "pdf", None).is_err());
+        assert!(export_result("Usage", "{}", "csv", None).is_err());
+    }
+}
@@ -1,7 +1,9 @@
 // SPDX-FileCopyrightText: © 2024 Christopher Woods <Christopher.Woods@bristol.ac.uk>
 // SPDX-License-Identifier: MIT
 
-use crate::storage::{QuotaLimit, Volume};
+use crate::jobrecords::Page;
+use crate::scheduling::{validate_reservation_name, Reservation, SchedulingPolicy, UserLimit};
+use crate::storage::{QuotaLimit, RecycleVersion, Volume};
 use crate::usagereport::Usage;
 use templemeads::destination::{Destination, Destinations};
 use templemeads::named::NamedType;
@@ -2829,6 +2831,396 @@ impl std::fmt::Display for AwardDetails {
 /// New code should use AwardDetails directly.
 pub type ProjectDetails = AwardDetails;
 
+/// The SSH public key types that may be written into a user's account.
+/// Anything else - including a line starting with `authorized_keys`
+/// options such as `command="..."` - is rejected.
+const SSH_KEY_TYPES: [&str; 7] = [
+    "ssh-ed25519",
+    "ssh-rsa",
+    "ecdsa-sha2-nistp256",
+    "ecdsa-sha2-nistp384",
+    "ecdsa-sha2-nistp521",
+    "sk-ssh-ed25519@openssh.com",
+    "sk-ecdsa-sha2-nistp256@openssh.com",
+];
+
+/// The most SSH public keys a single profile may carry
+const MAX_SSH_KEYS: usize = 32;
+
+/// Validates that a string is a single-line OpenSSH public key
+/// (`<type> <base64> [comment]`), with no `authorized_keys` options.
+pub(crate) fn validate_ssh_public_key(key: &str) -> Result<(), Error> {
+    if key.chars().any(|c| c.is_control()) {
+        return Err(Error::Parse(
+            "SSH public key must be a single line with no control characters".to_string(),
+        ));
+    }
+
+    let mut parts = key.split_whitespace();
+
+    let key_type = parts
+        .next()
+        .ok_or_else(|| Error::Parse("SSH public key cannot be empty".to_string()))?;
+
+    if !SSH_KEY_TYPES.contains(&key_type) {
+        return Err(Error::Parse(format!(
+            "SSH public key type '{}' is not supported",
+            key_type
+        )));
+    }
+
+    let data = parts
+        .next()
+        .ok_or_else(|| Error::Parse("SSH public key is missing its key data".to_string()))?;
+
+    if data.len() < 16
+        || !data
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '='))
+    {
+        return Err(Error::Parse(
+            "SSH public key data is not valid base64".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validates that a string is a plausible login shell - an absolute path
+/// made only of path-safe characters.
+pub(crate) fn validate_login_shell(shell: &str) -> Result<(), Error> {
+    if !shell.starts_with('/') {
+        return Err(Error::Parse(format!(
+            "Login shell '{}' is not an absolute path",
+            shell
+        )));
+    }
+
+    if !shell
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '_' | '-' | '+'))
+    {
+        return Err(Error::Parse(format!(
+            "Login shell '{}' contains invalid characters",
+            shell
+        )));
+    }
+
+    if shell.split('/').any(|part| part == "..") {
+        return Err(Error::Parse(format!(
+            "Login shell '{}' contains a '..' component",
+            shell
+        )));
+    }
+
+    Ok(())
+}
+
+/// Validates a display name - it ends up in a GECOS field and an LDAP
+/// attribute, so it must be a single line without the ':' separator.
+pub(crate) fn validate_display_name(name: &str) -> Result<(), Error> {
+    if name.chars().count() > 256 {
+        return Err(Error::Parse("Display name is too long".to_string()));
+    }
+
+    if name.chars().any(|c| c.is_control() || c == ':') {
+        return Err(Error::Parse(format!(
+            "Display name '{}' contains invalid characters",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+/// The personal details of a user that account agents write into the
+/// user's account, so that they can log in (with their SSH keys) and be
+/// contacted from the first day their account exists.
+///
+/// As for AwardDetails, every field is an "option". In a
+/// `set_user_profile` the profile replaces what the account held, so
+/// a missing field is cleared. In an `update_user_profile` only the
+/// fields that are set are changed.
+///
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct UserProfile {
+    /// The user's full name, as it should be displayed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    display_name: Option<String>,
+
+    /// The user's email address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    email: Option<String>,
+
+    /// The user's SSH public keys, one OpenSSH public key per entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    ssh_keys: Option<Vec<String>>,
+
+    /// The user's login shell (e.g. "/bin/bash")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    shell: Option<String>,
+}
+
+impl NamedType for UserProfile {
+    fn type_name() -> String {
+        "UserProfile".to_string()
+    }
+}
+
+impl UserProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Parse a profile from JSON. Every field is validated, as the values
+    /// are written straight into account records and `authorized_keys`.
+    ///
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        UserProfile::from_json(json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let profile: UserProfile =
+            serde_json::from_str(json).map_err(|e| Error::Parse(e.to_string()))?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    ///
+    /// Check that every field that is set holds a valid value
+    ///
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(display_name) = &self.display_name {
+            validate_display_name(display_name)?;
+        }
+
+        if let Some(email) = &self.email {
+            validate_email_address(email)?;
+        }
+
+        if let Some(ssh_keys) = &self.ssh_keys {
+            if ssh_keys.len() > MAX_SSH_KEYS {
+                return Err(Error::Parse(format!(
+                    "A user profile can hold at most {} SSH keys",
+                    MAX_SSH_KEYS
+                )));
+            }
+
+            for key in ssh_keys {
+                validate_ssh_public_key(key)?;
+            }
+        }
+
+        if let Some(shell) = &self.shell {
+            validate_login_shell(shell)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.display_name.is_none()
+            && self.email.is_none()
+            && self.ssh_keys.is_none()
+            && self.shell.is_none()
+    }
+
+    pub fn display_name(&self) -> Option<String> {
+        self.display_name.clone()
+    }
+
+    ///
+    /// Return the given name(s) - everything in the display name
+    /// before the last word
+    ///
+    pub fn given_name(&self) -> Option<String> {
+        let name = self.display_name.as_ref()?;
+
+        match name.rsplit_once(' ') {
+            Some((given, _)) => Some(given.trim().to_string()),
+            None => Some(name.clone()),
+        }
+    }
+
+    ///
+    /// Return the family name - the last word of the display name
+    ///
+    pub fn family_name(&self) -> Option<String> {
+        let name = self.display_name.as_ref()?;
+
+        match name.rsplit_once(' ') {
+            Some((_, family)) => Some(family.to_string()),
+            None => Some(name.clone()),
+        }
+    }
+
+    pub fn set_display_name(&mut self, display_name: &str) -> Result<(), Error> {
+        let display_name = display_name
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if display_name.is_empty() {
+            self.display_name = None;
+        } else {
+            validate_display_name(&display_name)?;
+            self.display_name = Some(display_name);
+        }
+
+        Ok(())
+    }
+
+    pub fn clear_display_name(&mut self) {
+        self.display_name = None;
+    }
+
+    pub fn email(&self) -> Option<String> {
+        self.email.clone()
+    }
+
+    pub fn set_email(&mut self, email: &str) -> Result<(), Error> {
+        let email = email.trim();
+
+        if email.is_empty() {
+            self.email = None;
+        } else {
+            validate_email_address(email)?;
+            self.email = Some(email.to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn clear_email(&mut self) {
+        self.email = None;
+    }
+
+    pub fn ssh_keys(&self) -> Option<Vec<String>> {
+        self.ssh_keys.clone()
+    }
+
+    ///
+    /// Validate and replace all SSH keys. All keys are validated before
+    /// any are applied; if any is invalid the existing keys are unchanged.
+    /// An empty list is kept, as it means "remove all of the user's keys".
+    ///
+    pub fn set_ssh_keys(&mut self, ssh_keys: Vec<String>) -> Result<(), Error> {
+        let ssh_keys: Vec<String> = ssh_keys.iter().map(|k| k.trim().to_string()).collect();
+
+        if ssh_keys.len() > MAX_SSH_KEYS {
+            return Err(Error::Parse(format!(
+                "A user profile can hold at most {} SSH keys",
+                MAX_SSH_KEYS
+            )));
+        }
+
+        for key in &ssh_keys {
+            validate_ssh_public_key(key)?;
+        }
+
+        self.ssh_keys = Some(ssh_keys);
+
+        Ok(())
+    }
+
+    pub fn clear_ssh_keys(&mut self) {
+        self.ssh_keys = None;
+    }
+
+    pub fn shell(&self) -> Option<String> {
+        self.shell.clone()
+    }
+
+    pub fn set_shell(&mut self, shell: &str) -> Result<(), Error> {
+        let shell = shell.trim();
+
+        if shell.is_empty() {
+            self.shell = None;
+        } else {
+            validate_login_shell(shell)?;
+            self.shell = Some(shell.to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn clear_shell(&mut self) {
+        self.shell = None;
+    }
+
+    ///
+    /// Return this profile updated with every field that is set in `other`
+    ///
+    pub fn merge(&self, other: &UserProfile) -> UserProfile {
+        let mut merged = self.clone();
+
+        if other.display_name.is_some() {
+            merged.display_name = other.display_name.clone();
+        }
+
+        if other.email.is_some() {
+            merged.email = other.email.clone();
+        }
+
+        if other.ssh_keys.is_some() {
+            merged.ssh_keys = other.ssh_keys.clone();
+        }
+
+        if other.shell.is_some() {
+            merged.shell = other.shell.clone();
+        }
+
+        merged
+    }
+}
+
+impl std::fmt::Display for UserProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+///
+/// Validate and normalise the role of a member of a project (e.g. "pi",
+/// "manager", "member"), as passed in a `set_user_role`. Roles are
+/// compared case-insensitively, so this returns the role in lower case.
+/// A role is a single word of letters, digits, '-' and '_', as it
+/// travels as one argument of the instruction.
+///
+pub fn validate_role(role: &str) -> Result<String, Error> {
+    let role = role.trim().to_lowercase();
+
+    if role.is_empty() {
+        return Err(Error::Parse("Member role cannot be empty".to_string()));
+    }
+
+    if role.len() > 64 {
+        return Err(Error::Parse(format!("Member role '{}' is too long", role)));
+    }
+
+    if !role
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::Parse(format!(
+            "Member role '{}' contains invalid characters",
+            role
+        )));
+    }
+
+    Ok(role)
+}
+
 ///
 /// Enum of all of the instructions that can be sent to agents
 ///
@@ -2917,6 +3309,30 @@ pub enum Instruction {
     /// for a project - not that these may not yet exist
     GetProjectDirs(ProjectIdentifier),
 
+    /// An instruction to list the removed directories of a user that
+    /// are waiting in the recycle bin, in every version
+    GetRecycledUserDirs(UserIdentifier),
+
+    /// An instruction to list the removed directories of a project that
+    /// are waiting in the recycle bin, in every version
+    GetRecycledProjectDirs(ProjectIdentifier),
+
+    /// An instruction to restore the specified version of the recycled
+    /// directories of a user on a volume
+    RestoreUserDir(UserIdentifier, Volume, RecycleVersion),
+
+    /// An instruction to restore the specified version of the recycled
+    /// directories of a project on a volume
+    RestoreProjectDir(ProjectIdentifier, Volume, RecycleVersion),
+
+    /// An instruction to permanently delete every recycled version of
+    /// the directories of a user, without waiting for them to expire
+    PurgeRecycledUserDirs(UserIdentifier),
+
+    /// An instruction to permanently delete every recycled version of
+    /// the directories of a project, without waiting for them to expire
+    PurgeRecycledProjectDirs(ProjectIdentifier),
+
     /// An instruction to add a local user
     AddLocalUser(UserMapping),
 
@@ -2932,12 +3348,51 @@ pub enum Instruction {
     /// An instruction to get a local project report
     GetLocalUsageReport(ProjectMapping, DateRange),
 
+    /// An instruction to get a page of the records of the jobs
+    /// of a local project in the specified date range
+    GetLocalJobRecords(ProjectMapping, DateRange, Page),
+
     /// An instruction to get the limit of a local project
     GetLocalLimit(ProjectMapping),
 
+    /// An instruction to get the limit of a local project, together
+    /// with the limits of its members
+    GetLocalMemberLimits(ProjectMapping),
+
     /// An instruction to set the limit of a local project
     SetLocalLimit(ProjectMapping, Usage),
 
+    /// An instruction to get the limits of a local user within
+    /// their project
+    GetLocalUserLimit(UserMapping),
+
+    /// An instruction to set the limits of a local user within
+    /// their project
+    SetLocalUserLimit(UserMapping, UserLimit),
+
+    /// An instruction to get the scheduling policy (QoS and
+    /// partitions) of a local project
+    GetLocalSchedulingPolicy(ProjectMapping),
+
+    /// An instruction to set the scheduling policy of a local project
+    SetLocalSchedulingPolicy(ProjectMapping, SchedulingPolicy),
+
+    /// An instruction to set the scheduling policy of a local project
+    /// to the one that the scheduler has configured for a template
+    SetLocalProjectTemplate(ProjectMapping, ProjectTemplate),
+
+    /// An instruction to get the node reservations of a local project
+    GetLocalReservations(ProjectMapping),
+
+    /// An instruction to create a node reservation for a local project
+    CreateLocalReservation(ProjectMapping, Reservation),
+
+    /// An instruction to update a node reservation of a local project
+    UpdateLocalReservation(ProjectMapping, Reservation),
+
+    /// An instruction to delete a node reservation of a local project
+    DeleteLocalReservation(ProjectMapping, String),
+
     /// An instruction to clear the quota of a local project on a volume
     ClearLocalProjectQuota(ProjectMapping, Volume),
 
@@ -2974,9 +3429,45 @@ pub enum Instruction {
     /// (note this does not guarantee the directories exist)
     GetLocalProjectDirs(ProjectMapping),
 
+    /// Return the recycled directories of a local user
+    GetLocalRecycledUserDirs(UserMapping),
+
+    /// Return the recycled directories of a local project
+    GetLocalRecycledProjectDirs(ProjectMapping),
+
+    /// An instruction to restore a version of the recycled
+    /// directories of a local user on a volume
+    RestoreLocalUserDir(UserMapping, Volume, RecycleVersion),
+
+    /// An instruction to restore a version of the recycled
+    /// directories of a local project on a volume
+    RestoreLocalProjectDir(ProjectMapping, Volume, RecycleVersion),
+
+    /// An instruction to purge the recycled directories of a local user
+    PurgeLocalRecycledUserDirs(UserMapping),
+
+    /// An instruction to purge the recycled directories of a local project
+    PurgeLocalRecycledProjectDirs(ProjectMapping),
+
     /// An instruction to update the home directory of a user
     UpdateHomeDir(UserIdentifier, String),
 
+    /// An instruction to replace the profile (name, email, SSH keys
+    /// and shell) of a user - fields missing from the profile are cleared
+    SetUserProfile(UserIdentifier, UserProfile),
+
+    /// An instruction to update the profile of a user - only the
+    /// fields that are set in the profile are changed
+    UpdateUserProfile(UserIdentifier, UserProfile),
+
+    /// An instruction to set the role (e.g. "pi", "manager", "member")
+    /// of a user within their project
+    SetUserRole(UserIdentifier, String),
+
+    /// An instruction to set the role of a local user within their
+    /// local project (account)
+    SetLocalUserRole(UserMapping, String),
+
     /// An instruction to get the local storage report for a project
     /// from the filesystem agent in the specified date range (defaults to today)
     GetLocalStorageReport(ProjectMapping, DateRange),
@@ -2999,12 +3490,54 @@ pub enum Instruction {
     /// date range
     GetUsageReports(PortalIdentifier, DateRange),
 
+    /// An instruction to get the cost (in credits or money) of the
+    /// usage of a single project in the specified date range
+    GetCostReport(ProjectIdentifier, DateRange),
+
+    /// An instruction to get a page of the accounting records of
+    /// the jobs charged to a single project in the specified
+    /// date range
+    GetJobRecords(ProjectIdentifier, DateRange, Page),
+
     /// An instruction to set the usage limit for a project
     SetLimit(ProjectIdentifier, Usage),
 
     /// An instruction to get the usage limit for a project
     GetLimit(ProjectIdentifier),
 
+    /// An instruction to get the usage limit for a project, together
+    /// with the limits of its members
+    GetMemberLimits(ProjectIdentifier),
+
+    /// An instruction to get the limits of a user within their project
+    GetUserLimit(UserIdentifier),
+
+    /// An instruction to set the limits of a user within their project
+    SetUserLimit(UserIdentifier, UserLimit),
+
+    /// An instruction to get the scheduling policy (QoS and
+    /// partitions) of a project
+    GetSchedulingPolicy(ProjectIdentifier),
+
+    /// An instruction to set the scheduling policy of a project
+    SetSchedulingPolicy(ProjectIdentifier, SchedulingPolicy),
+
+    /// An instruction to set the scheduling policy of a project to
+    /// the one that the scheduler has configured for a template
+    SetProjectTemplate(ProjectIdentifier, ProjectTemplate),
+
+    /// An instruction to get the node reservations of a project
+    GetReservations(ProjectIdentifier),
+
+    /// An instruction to create a node reservation for a project
+    CreateReservation(ProjectIdentifier, Reservation),
+
+    /// An instruction to update a node reservation of a project
+    UpdateReservation(ProjectIdentifier, Reservation),
+
+    /// An instruction to delete a node reservation of a project
+    DeleteReservation(ProjectIdentifier, String),
+
     /// An instruction to clear a storage quota for a project on a volume
     ClearProjectQuota(ProjectIdentifier, Volume),
 
@@ -3041,6 +3574,11 @@ pub enum Instruction {
 
     /// An instruction to get the list of offerings from an agent
     GetOfferings(),
+
+    /// An instruction to list the lifecycle transitions (warnings,
+    /// blocks and removals) that are due to the projects of a portal's
+    /// offerings, without carrying any of them out
+    GetLifecycleTransitions(),
 }
 
 impl Instruction {
@@ -3374,13 +3912,207 @@ impl Instruction {
                     }
                 }
             }
-            "get_local_usage_report" => {
-                if parts.len() < 2 {
-                    tracing::error!("get_local_usage_report failed to parse: {}", &rest(1));
-                    return Err(Error::Parse(format!(
-                        "get_local_usage_report failed to parse: {}",
+            "set_user_profile" => match UserIdentifier::parse(arg(1)) {
+                Ok(user) => match UserProfile::parse(&rest(2)) {
+                    Ok(profile) => Ok(Instruction::SetUserProfile(user, profile)),
+                    Err(_) => {
+                        tracing::error!("set_user_profile failed to parse: {}", &rest(2));
+                        Err(Error::Parse(format!(
+                            "set_user_profile failed to parse: {}",
+                            rest(2)
+                        )))
+                    }
+                },
+                Err(_) => {
+                    tracing::error!("set_user_profile failed to parse: {}", &rest(1));
+                    Err(Error::Parse(format!(
+                        "set_user_profile failed to parse: {}",
                         rest(1)
-                    )));
+                    )))
+                }
+            },
+            "update_user_profile" => match UserIdentifier::parse(arg(1)) {
+                Ok(user) => match UserProfile::parse(&rest(2)) {
+                    Ok(profile) => Ok(Instruction::UpdateUserProfile(user, profile)),
+                    Err(_) => {
+                        tracing::error!("update_user_profile failed to parse: {}", &rest(2));
+                        Err(Error::Parse(format!(
+                            "update_user_profile failed to parse: {}",
+                            rest(2)
+                        )))
+                    }
+                },
+                Err(_) => {
+                    tracing::error!("update_user_profile failed to parse: {}", &rest(1));
+                    Err(Error::Parse(format!(
+                        "update_user_profile failed to parse: {}",
+                        rest(1)
+                    )))
+                }
+            },
+            "set_user_role" => {
+                if parts.len() != 3 {
+                    tracing::error!("set_user_role failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "set_user_role failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match (UserIdentifier::parse(arg(1)), validate_role(arg(2))) {
+                    (Ok(user), Ok(role)) => Ok(Instruction::SetUserRole(user, role)),
+                    _ => {
+                        tracing::error!("set_user_role failed to parse: {}", &rest(1));
+                        Err(Error::Parse(format!(
+                            "set_user_role failed to parse: {}",
+                            rest(1)
+                        )))
+                    }
+                }
+            }
+            "set_local_user_role" => {
+                if parts.len() != 3 {
+                    tracing::error!("set_local_user_role failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "set_local_user_role failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match (UserMapping::parse(arg(1)), validate_role(arg(2))) {
+                    (Ok(mapping), Ok(role)) => Ok(Instruction::SetLocalUserRole(mapping, role)),
+                    _ => {
+                        tracing::error!("set_local_user_role failed to parse: {}", &rest(1));
+                        Err(Error::Parse(format!(
+                            "set_local_user_role failed to parse: {}",
+                            rest(1)
+                        )))
+                    }
+                }
+            }
+            "get_user_limit" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_user_limit failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_user_limit failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match UserIdentifier::parse(arg(1)) {
+                    Ok(user) => Ok(Instruction::GetUserLimit(user)),
+                    Err(e) => {
+                        tracing::error!("get_user_limit failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "get_user_limit failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "set_user_limit" => {
+                if parts.len() < 3 {
+                    tracing::error!("set_user_limit failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "set_user_limit failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match UserIdentifier::parse(arg(1)) {
+                    Ok(user) => match UserLimit::parse(&rest(2)) {
+                        Ok(limit) => Ok(Instruction::SetUserLimit(user, limit)),
+                        Err(e) => {
+                            tracing::error!("set_user_limit failed to parse '{}': {}", &rest(1), e);
+                            Err(Error::Parse(format!(
+                                "set_user_limit failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!("set_user_limit failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "set_user_limit failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_local_user_limit" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_local_user_limit failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_local_user_limit failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match UserMapping::parse(arg(1)) {
+                    Ok(user) => Ok(Instruction::GetLocalUserLimit(user)),
+                    Err(e) => {
+                        tracing::error!(
+                            "get_local_user_limit failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "get_local_user_limit failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "set_local_user_limit" => {
+                if parts.len() < 3 {
+                    tracing::error!("set_local_user_limit failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "set_local_user_limit failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match UserMapping::parse(arg(1)) {
+                    Ok(user) => match UserLimit::parse(&rest(2)) {
+                        Ok(limit) => Ok(Instruction::SetLocalUserLimit(user, limit)),
+                        Err(e) => {
+                            tracing::error!(
+                                "set_local_user_limit failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "set_local_user_limit failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!(
+                            "set_local_user_limit failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "set_local_user_limit failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_local_usage_report" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_local_usage_report failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_local_usage_report failed to parse: {}",
+                        rest(1)
+                    )));
                 }
 
                 match ProjectMapping::parse(arg(1)) {
@@ -3536,6 +4268,157 @@ impl Instruction {
                     }
                 }
             }
+            "get_cost_report" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_cost_report failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_cost_report failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => {
+                        match DateRange::parse(parts.get(2).cloned().unwrap_or("this_week")) {
+                            Ok(date_range) => Ok(Instruction::GetCostReport(project, date_range)),
+                            Err(e) => {
+                                tracing::error!(
+                                    "get_cost_report failed to parse '{}': {}",
+                                    &rest(1),
+                                    e
+                                );
+                                Err(Error::Parse(format!(
+                                    "get_cost_report failed to parse '{}': {}",
+                                    rest(1),
+                                    e
+                                )))
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("get_cost_report failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "get_cost_report failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_job_records" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_job_records failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_job_records failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => {
+                        match DateRange::parse(parts.get(2).cloned().unwrap_or("this_week")) {
+                            Ok(date_range) => {
+                                match Page::parse(parts.get(3).cloned(), parts.get(4).cloned()) {
+                                    Ok(page) => {
+                                        Ok(Instruction::GetJobRecords(project, date_range, page))
+                                    }
+                                    Err(e) => {
+                                        tracing::error!(
+                                            "get_job_records failed to parse '{}': {}",
+                                            &rest(1),
+                                            e
+                                        );
+                                        Err(Error::Parse(format!(
+                                            "get_job_records failed to parse '{}': {}",
+                                            rest(1),
+                                            e
+                                        )))
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                tracing::error!(
+                                    "get_job_records failed to parse '{}': {}",
+                                    &rest(1),
+                                    e
+                                );
+                                Err(Error::Parse(format!(
+                                    "get_job_records failed to parse '{}': {}",
+                                    rest(1),
+                                    e
+                                )))
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("get_job_records failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "get_job_records failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_local_job_records" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_local_job_records failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_local_job_records failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(mapping) => {
+                        match DateRange::parse(parts.get(2).cloned().unwrap_or("this_week")) {
+                            Ok(date_range) => {
+                                match Page::parse(parts.get(3).cloned(), parts.get(4).cloned()) {
+                                    Ok(page) => Ok(Instruction::GetLocalJobRecords(
+                                        mapping, date_range, page,
+                                    )),
+                                    Err(e) => {
+                                        tracing::error!(
+                                            "get_local_job_records failed to parse '{}': {}",
+                                            &rest(1),
+                                            e
+                                        );
+                                        Err(Error::Parse(format!(
+                                            "get_local_job_records failed to parse '{}': {}",
+                                            rest(1),
+                                            e
+                                        )))
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                tracing::error!(
+                                    "get_local_job_records failed to parse '{}': {}",
+                                    &rest(1),
+                                    e
+                                );
+                                Err(Error::Parse(format!(
+                                    "get_local_job_records failed to parse '{}': {}",
+                                    rest(1),
+                                    e
+                                )))
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            "get_local_job_records failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "get_local_job_records failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
             "get_usage_reports" => {
                 if parts.len() < 2 {
                     tracing::error!("get_usage_reports failed to parse: {}", &rest(1));
@@ -3564,117 +4447,637 @@ impl Instruction {
                         }
                     }
                     Err(e) => {
-                        tracing::error!("get_usage_reports failed to parse '{}': {}", &rest(1), e);
+                        tracing::error!("get_usage_reports failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "get_usage_reports failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "set_local_limit" => {
+                if parts.len() < 3 {
+                    tracing::error!("set_local_limit failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "set_local_limit failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(mapping) => match Usage::parse(arg(2)) {
+                        Ok(usage) => Ok(Instruction::SetLocalLimit(mapping, usage)),
+                        Err(e) => {
+                            tracing::error!(
+                                "set_local_limit failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "set_local_limit failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!("set_local_limit failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "set_local_limit failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_local_limit" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_local_limit failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_local_limit failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(mapping) => Ok(Instruction::GetLocalLimit(mapping)),
+                    Err(e) => {
+                        tracing::error!("get_local_limit failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "get_local_limit failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_local_member_limits" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_local_member_limits failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_local_member_limits failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(mapping) => Ok(Instruction::GetLocalMemberLimits(mapping)),
+                    Err(e) => {
+                        tracing::error!(
+                            "get_local_member_limits failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "get_local_member_limits failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "set_limit" => {
+                if parts.len() < 3 {
+                    tracing::error!("set_limit failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "set_limit failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => match Usage::parse(&rest(2)) {
+                        Ok(usage) => Ok(Instruction::SetLimit(project, usage)),
+                        Err(e) => {
+                            tracing::error!("set_limit failed to parse '{}': {}", &rest(1), e);
+                            Err(Error::Parse(format!(
+                                "set_limit failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!("set_limit failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "set_limit failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_limit" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_limit failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_limit failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => Ok(Instruction::GetLimit(project)),
+                    Err(e) => {
+                        tracing::error!("get_limit failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "get_limit failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_member_limits" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_member_limits failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_member_limits failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => Ok(Instruction::GetMemberLimits(project)),
+                    Err(e) => {
+                        tracing::error!("get_member_limits failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "get_member_limits failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_scheduling_policy" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_scheduling_policy failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_scheduling_policy failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => Ok(Instruction::GetSchedulingPolicy(project)),
+                    Err(e) => {
+                        tracing::error!(
+                            "get_scheduling_policy failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "get_scheduling_policy failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "set_scheduling_policy" => {
+                if parts.len() < 3 {
+                    tracing::error!("set_scheduling_policy failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "set_scheduling_policy failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => match SchedulingPolicy::parse(&rest(2)) {
+                        Ok(value) => Ok(Instruction::SetSchedulingPolicy(project, value)),
+                        Err(e) => {
+                            tracing::error!(
+                                "set_scheduling_policy failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "set_scheduling_policy failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!(
+                            "set_scheduling_policy failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "set_scheduling_policy failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "set_project_template" => {
+                if parts.len() < 3 {
+                    tracing::error!("set_project_template failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "set_project_template failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => match ProjectTemplate::parse(arg(2)) {
+                        Ok(value) => Ok(Instruction::SetProjectTemplate(project, value)),
+                        Err(e) => {
+                            tracing::error!(
+                                "set_project_template failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "set_project_template failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!(
+                            "set_project_template failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "set_project_template failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_reservations" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_reservations failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_reservations failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => Ok(Instruction::GetReservations(project)),
+                    Err(e) => {
+                        tracing::error!("get_reservations failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "get_reservations failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "create_reservation" => {
+                if parts.len() < 3 {
+                    tracing::error!("create_reservation failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "create_reservation failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => match Reservation::parse(&rest(2)) {
+                        Ok(value) => Ok(Instruction::CreateReservation(project, value)),
+                        Err(e) => {
+                            tracing::error!(
+                                "create_reservation failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "create_reservation failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!("create_reservation failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "create_reservation failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "update_reservation" => {
+                if parts.len() < 3 {
+                    tracing::error!("update_reservation failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "update_reservation failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => match Reservation::parse(&rest(2)) {
+                        Ok(value) => Ok(Instruction::UpdateReservation(project, value)),
+                        Err(e) => {
+                            tracing::error!(
+                                "update_reservation failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "update_reservation failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!("update_reservation failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "update_reservation failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "delete_reservation" => {
+                if parts.len() < 3 {
+                    tracing::error!("delete_reservation failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "delete_reservation failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => match validate_reservation_name(arg(2)) {
+                        Ok(value) => Ok(Instruction::DeleteReservation(project, value)),
+                        Err(e) => {
+                            tracing::error!(
+                                "delete_reservation failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "delete_reservation failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!("delete_reservation failed to parse '{}': {}", &rest(1), e);
+                        Err(Error::Parse(format!(
+                            "delete_reservation failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "get_local_reservations" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_local_reservations failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_local_reservations failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(project) => Ok(Instruction::GetLocalReservations(project)),
+                    Err(e) => {
+                        tracing::error!(
+                            "get_local_reservations failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "get_local_reservations failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "create_local_reservation" => {
+                if parts.len() < 3 {
+                    tracing::error!("create_local_reservation failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "create_local_reservation failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(project) => match Reservation::parse(&rest(2)) {
+                        Ok(value) => Ok(Instruction::CreateLocalReservation(project, value)),
+                        Err(e) => {
+                            tracing::error!(
+                                "create_local_reservation failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "create_local_reservation failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!(
+                            "create_local_reservation failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "create_local_reservation failed to parse '{}': {}",
+                            rest(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "update_local_reservation" => {
+                if parts.len() < 3 {
+                    tracing::error!("update_local_reservation failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "update_local_reservation failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(project) => match Reservation::parse(&rest(2)) {
+                        Ok(value) => Ok(Instruction::UpdateLocalReservation(project, value)),
+                        Err(e) => {
+                            tracing::error!(
+                                "update_local_reservation failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "update_local_reservation failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!(
+                            "update_local_reservation failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
                         Err(Error::Parse(format!(
-                            "get_usage_reports failed to parse '{}': {}",
+                            "update_local_reservation failed to parse '{}': {}",
                             rest(1),
                             e
                         )))
                     }
                 }
             }
-            "set_local_limit" => {
+            "delete_local_reservation" => {
                 if parts.len() < 3 {
-                    tracing::error!("set_local_limit failed to parse: {}", &rest(1));
+                    tracing::error!("delete_local_reservation failed to parse: {}", &rest(1));
                     return Err(Error::Parse(format!(
-                        "set_local_limit failed to parse: {}",
+                        "delete_local_reservation failed to parse: {}",
                         rest(1)
                     )));
                 }
 
                 match ProjectMapping::parse(arg(1)) {
-                    Ok(mapping) => match Usage::parse(arg(2)) {
-                        Ok(usage) => Ok(Instruction::SetLocalLimit(mapping, usage)),
+                    Ok(project) => match validate_reservation_name(arg(2)) {
+                        Ok(value) => Ok(Instruction::DeleteLocalReservation(project, value)),
                         Err(e) => {
                             tracing::error!(
-                                "set_local_limit failed to parse '{}': {}",
+                                "delete_local_reservation failed to parse '{}': {}",
                                 &rest(1),
                                 e
                             );
                             Err(Error::Parse(format!(
-                                "set_local_limit failed to parse '{}': {}",
+                                "delete_local_reservation failed to parse '{}': {}",
                                 rest(1),
                                 e
                             )))
                         }
                     },
                     Err(e) => {
-                        tracing::error!("set_local_limit failed to parse '{}': {}", &rest(1), e);
+                        tracing::error!(
+                            "delete_local_reservation failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
                         Err(Error::Parse(format!(
-                            "set_local_limit failed to parse '{}': {}",
+                            "delete_local_reservation failed to parse '{}': {}",
                             rest(1),
                             e
                         )))
                     }
                 }
             }
-            "get_local_limit" => {
+            "get_local_scheduling_policy" => {
                 if parts.len() < 2 {
-                    tracing::error!("get_local_limit failed to parse: {}", &rest(1));
+                    tracing::error!("get_local_scheduling_policy failed to parse: {}", &rest(1));
                     return Err(Error::Parse(format!(
-                        "get_local_limit failed to parse: {}",
+                        "get_local_scheduling_policy failed to parse: {}",
                         rest(1)
                     )));
                 }
 
                 match ProjectMapping::parse(arg(1)) {
-                    Ok(mapping) => Ok(Instruction::GetLocalLimit(mapping)),
+                    Ok(project) => Ok(Instruction::GetLocalSchedulingPolicy(project)),
                     Err(e) => {
-                        tracing::error!("get_local_limit failed to parse '{}': {}", &rest(1), e);
+                        tracing::error!(
+                            "get_local_scheduling_policy failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
                         Err(Error::Parse(format!(
-                            "get_local_limit failed to parse '{}': {}",
+                            "get_local_scheduling_policy failed to parse '{}': {}",
                             rest(1),
                             e
                         )))
                     }
                 }
             }
-            "set_limit" => {
+            "set_local_scheduling_policy" => {
                 if parts.len() < 3 {
-                    tracing::error!("set_limit failed to parse: {}", &rest(1));
+                    tracing::error!("set_local_scheduling_policy failed to parse: {}", &rest(1));
                     return Err(Error::Parse(format!(
-                        "set_limit failed to parse: {}",
+                        "set_local_scheduling_policy failed to parse: {}",
                         rest(1)
                     )));
                 }
 
-                match ProjectIdentifier::parse(arg(1)) {
-                    Ok(project) => match Usage::parse(&rest(2)) {
-                        Ok(usage) => Ok(Instruction::SetLimit(project, usage)),
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(project) => match SchedulingPolicy::parse(&rest(2)) {
+                        Ok(value) => Ok(Instruction::SetLocalSchedulingPolicy(project, value)),
                         Err(e) => {
-                            tracing::error!("set_limit failed to parse '{}': {}", &rest(1), e);
+                            tracing::error!(
+                                "set_local_scheduling_policy failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
                             Err(Error::Parse(format!(
-                                "set_limit failed to parse '{}': {}",
+                                "set_local_scheduling_policy failed to parse '{}': {}",
                                 rest(1),
                                 e
                             )))
                         }
                     },
                     Err(e) => {
-                        tracing::error!("set_limit failed to parse '{}': {}", &rest(1), e);
+                        tracing::error!(
+                            "set_local_scheduling_policy failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
                         Err(Error::Parse(format!(
-                            "set_limit failed to parse '{}': {}",
+                            "set_local_scheduling_policy failed to parse '{}': {}",
                             rest(1),
                             e
                         )))
                     }
                 }
             }
-            "get_limit" => {
-                if parts.len() < 2 {
-                    tracing::error!("get_limit failed to parse: {}", &rest(1));
+            "set_local_project_template" => {
+                if parts.len() < 3 {
+                    tracing::error!("set_local_project_template failed to parse: {}", &rest(1));
                     return Err(Error::Parse(format!(
-                        "get_limit failed to parse: {}",
+                        "set_local_project_template failed to parse: {}",
                         rest(1)
                     )));
                 }
 
-                match ProjectIdentifier::parse(arg(1)) {
-                    Ok(project) => Ok(Instruction::GetLimit(project)),
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(project) => match ProjectTemplate::parse(arg(2)) {
+                        Ok(value) => Ok(Instruction::SetLocalProjectTemplate(project, value)),
+                        Err(e) => {
+                            tracing::error!(
+                                "set_local_project_template failed to parse '{}': {}",
+                                &rest(1),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "set_local_project_template failed to parse '{}': {}",
+                                rest(1),
+                                e
+                            )))
+                        }
+                    },
                     Err(e) => {
-                        tracing::error!("get_limit failed to parse '{}': {}", &rest(1), e);
+                        tracing::error!(
+                            "set_local_project_template failed to parse '{}': {}",
+                            &rest(1),
+                            e
+                        );
                         Err(Error::Parse(format!(
-                            "get_limit failed to parse '{}': {}",
+                            "set_local_project_template failed to parse '{}': {}",
                             rest(1),
                             e
                         )))
@@ -4351,70 +5754,377 @@ impl Instruction {
                     )))
                 }
             },
+            "get_recycled_user_dirs" => match UserIdentifier::parse(&rest(1)) {
+                Ok(user) => Ok(Instruction::GetRecycledUserDirs(user)),
+                Err(_) => {
+                    tracing::error!("get_recycled_user_dirs failed to parse: {}", &rest(1));
+                    Err(Error::Parse(format!(
+                        "get_recycled_user_dirs failed to parse: {}",
+                        rest(1)
+                    )))
+                }
+            },
+            "get_recycled_project_dirs" => match ProjectIdentifier::parse(&rest(1)) {
+                Ok(project) => Ok(Instruction::GetRecycledProjectDirs(project)),
+                Err(_) => {
+                    tracing::error!("get_recycled_project_dirs failed to parse: {}", &rest(1));
+                    Err(Error::Parse(format!(
+                        "get_recycled_project_dirs failed to parse: {}",
+                        rest(1)
+                    )))
+                }
+            },
+            "restore_user_dir" => {
+                if parts.len() < 4 {
+                    tracing::error!("restore_user_dir failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "restore_user_dir failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match UserIdentifier::parse(arg(1)) {
+                    Ok(user) => match Volume::parse(arg(2)) {
+                        Ok(volume) => match RecycleVersion::parse(&rest(3)) {
+                            Ok(version) => Ok(Instruction::RestoreUserDir(user, volume, version)),
+                            Err(e) => {
+                                tracing::error!(
+                                    "restore_user_dir failed to parse version '{}': {}",
+                                    &rest(3),
+                                    e
+                                );
+                                Err(Error::Parse(format!(
+                                    "restore_user_dir failed to parse version '{}': {}",
+                                    rest(3),
+                                    e
+                                )))
+                            }
+                        },
+                        Err(e) => {
+                            tracing::error!(
+                                "restore_user_dir failed to parse volume '{}': {}",
+                                arg(2),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "restore_user_dir failed to parse volume '{}': {}",
+                                arg(2),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!(
+                            "restore_user_dir failed to parse user '{}': {}",
+                            arg(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "restore_user_dir failed to parse user '{}': {}",
+                            arg(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "restore_project_dir" => {
+                if parts.len() < 4 {
+                    tracing::error!("restore_project_dir failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "restore_project_dir failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectIdentifier::parse(arg(1)) {
+                    Ok(project) => match Volume::parse(arg(2)) {
+                        Ok(volume) => match RecycleVersion::parse(&rest(3)) {
+                            Ok(version) => {
+                                Ok(Instruction::RestoreProjectDir(project, volume, version))
+                            }
+                            Err(e) => {
+                                tracing::error!(
+                                    "restore_project_dir failed to parse version '{}': {}",
+                                    &rest(3),
+                                    e
+                                );
+                                Err(Error::Parse(format!(
+                                    "restore_project_dir failed to parse version '{}': {}",
+                                    rest(3),
+                                    e
+                                )))
+                            }
+                        },
+                        Err(e) => {
+                            tracing::error!(
+                                "restore_project_dir failed to parse volume '{}': {}",
+                                arg(2),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "restore_project_dir failed to parse volume '{}': {}",
+                                arg(2),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!(
+                            "restore_project_dir failed to parse project '{}': {}",
+                            arg(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "restore_project_dir failed to parse project '{}': {}",
+                            arg(1),
+                            e
+                        )))
+                    }
+                }
+            }
+            "purge_recycled_user_dirs" => match UserIdentifier::parse(&rest(1)) {
+                Ok(user) => Ok(Instruction::PurgeRecycledUserDirs(user)),
+                Err(_) => {
+                    tracing::error!("purge_recycled_user_dirs failed to parse: {}", &rest(1));
+                    Err(Error::Parse(format!(
+                        "purge_recycled_user_dirs failed to parse: {}",
+                        rest(1)
+                    )))
+                }
+            },
+            "purge_recycled_project_dirs" => match ProjectIdentifier::parse(&rest(1)) {
+                Ok(project) => Ok(Instruction::PurgeRecycledProjectDirs(project)),
+                Err(_) => {
+                    tracing::error!("purge_recycled_project_dirs failed to parse: {}", &rest(1));
+                    Err(Error::Parse(format!(
+                        "purge_recycled_project_dirs failed to parse: {}",
+                        rest(1)
+                    )))
+                }
+            },
             "get_local_home_dir" => match UserMapping::parse(&rest(1)) {
                 Ok(mapping) => Ok(Instruction::GetLocalHomeDir(mapping)),
                 Err(_) => {
                     tracing::error!("get_local_home_dir failed to parse: {}", &rest(1));
                     Err(Error::Parse(format!(
-                        "get_local_home_dir failed to parse: {}",
+                        "get_local_home_dir failed to parse: {}",
+                        rest(1)
+                    )))
+                }
+            },
+            "get_local_storage_report" => {
+                if parts.len() < 2 {
+                    tracing::error!("get_local_storage_report failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "get_local_storage_report failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(mapping) => {
+                        match DateRange::parse(parts.get(2).cloned().unwrap_or("today")) {
+                            Ok(date_range) => {
+                                Ok(Instruction::GetLocalStorageReport(mapping, date_range))
+                            }
+                            Err(e) => {
+                                tracing::error!(
+                                    "get_local_storage_report failed to parse '{}': {}",
+                                    &rest(1),
+                                    e
+                                );
+                                Err(Error::Parse(format!(
+                                    "get_local_storage_report failed to parse '{}': {}",
+                                    rest(1),
+                                    e
+                                )))
+                            }
+                        }
+                    }
+                    Err(_) => {
+                        tracing::error!("get_local_storage_report failed to parse: {}", &rest(1));
+                        Err(Error::Parse(format!(
+                            "get_local_storage_report failed to parse: {}",
+                            rest(1)
+                        )))
+                    }
+                }
+            }
+            "get_local_project_dirs" => match ProjectMapping::parse(&rest(1)) {
+                Ok(mapping) => Ok(Instruction::GetLocalProjectDirs(mapping)),
+                Err(_) => {
+                    tracing::error!("get_local_project_dirs failed to parse: {}", &rest(1));
+                    Err(Error::Parse(format!(
+                        "get_local_project_dirs failed to parse: {}",
+                        rest(1)
+                    )))
+                }
+            },
+            "get_local_user_dirs" => match UserMapping::parse(&rest(1)) {
+                Ok(mapping) => Ok(Instruction::GetLocalUserDirs(mapping)),
+                Err(_) => {
+                    tracing::error!("get_local_user_dirs failed to parse: {}", &rest(1));
+                    Err(Error::Parse(format!(
+                        "get_local_user_dirs failed to parse: {}",
+                        rest(1)
+                    )))
+                }
+            },
+            "get_local_recycled_user_dirs" => match UserMapping::parse(&rest(1)) {
+                Ok(mapping) => Ok(Instruction::GetLocalRecycledUserDirs(mapping)),
+                Err(_) => {
+                    tracing::error!("get_local_recycled_user_dirs failed to parse: {}", &rest(1));
+                    Err(Error::Parse(format!(
+                        "get_local_recycled_user_dirs failed to parse: {}",
+                        rest(1)
+                    )))
+                }
+            },
+            "get_local_recycled_project_dirs" => match ProjectMapping::parse(&rest(1)) {
+                Ok(mapping) => Ok(Instruction::GetLocalRecycledProjectDirs(mapping)),
+                Err(_) => {
+                    tracing::error!(
+                        "get_local_recycled_project_dirs failed to parse: {}",
+                        &rest(1)
+                    );
+                    Err(Error::Parse(format!(
+                        "get_local_recycled_project_dirs failed to parse: {}",
                         rest(1)
                     )))
                 }
             },
-            "get_local_storage_report" => {
-                if parts.len() < 2 {
-                    tracing::error!("get_local_storage_report failed to parse: {}", &rest(1));
+            "restore_local_user_dir" => {
+                if parts.len() < 4 {
+                    tracing::error!("restore_local_user_dir failed to parse: {}", &rest(1));
                     return Err(Error::Parse(format!(
-                        "get_local_storage_report failed to parse: {}",
+                        "restore_local_user_dir failed to parse: {}",
                         rest(1)
                     )));
                 }
 
-                match ProjectMapping::parse(arg(1)) {
-                    Ok(mapping) => {
-                        match DateRange::parse(parts.get(2).cloned().unwrap_or("today")) {
-                            Ok(date_range) => {
-                                Ok(Instruction::GetLocalStorageReport(mapping, date_range))
+                match UserMapping::parse(arg(1)) {
+                    Ok(mapping) => match Volume::parse(arg(2)) {
+                        Ok(volume) => match RecycleVersion::parse(&rest(3)) {
+                            Ok(version) => {
+                                Ok(Instruction::RestoreLocalUserDir(mapping, volume, version))
                             }
                             Err(e) => {
                                 tracing::error!(
-                                    "get_local_storage_report failed to parse '{}': {}",
-                                    &rest(1),
+                                    "restore_local_user_dir failed to parse version '{}': {}",
+                                    &rest(3),
                                     e
                                 );
                                 Err(Error::Parse(format!(
-                                    "get_local_storage_report failed to parse '{}': {}",
-                                    rest(1),
+                                    "restore_local_user_dir failed to parse version '{}': {}",
+                                    rest(3),
                                     e
                                 )))
                             }
+                        },
+                        Err(e) => {
+                            tracing::error!(
+                                "restore_local_user_dir failed to parse volume '{}': {}",
+                                arg(2),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "restore_local_user_dir failed to parse volume '{}': {}",
+                                arg(2),
+                                e
+                            )))
                         }
+                    },
+                    Err(e) => {
+                        tracing::error!(
+                            "restore_local_user_dir failed to parse mapping '{}': {}",
+                            arg(1),
+                            e
+                        );
+                        Err(Error::Parse(format!(
+                            "restore_local_user_dir failed to parse mapping '{}': {}",
+                            arg(1),
+                            e
+                        )))
                     }
-                    Err(_) => {
-                        tracing::error!("get_local_storage_report failed to parse: {}", &rest(1));
+                }
+            }
+            "restore_local_project_dir" => {
+                if parts.len() < 4 {
+                    tracing::error!("restore_local_project_dir failed to parse: {}", &rest(1));
+                    return Err(Error::Parse(format!(
+                        "restore_local_project_dir failed to parse: {}",
+                        rest(1)
+                    )));
+                }
+
+                match ProjectMapping::parse(arg(1)) {
+                    Ok(mapping) => match Volume::parse(arg(2)) {
+                        Ok(volume) => match RecycleVersion::parse(&rest(3)) {
+                            Ok(version) => Ok(Instruction::RestoreLocalProjectDir(
+                                mapping, volume, version,
+                            )),
+                            Err(e) => {
+                                tracing::error!(
+                                    "restore_local_project_dir failed to parse version '{}': {}",
+                                    &rest(3),
+                                    e
+                                );
+                                Err(Error::Parse(format!(
+                                    "restore_local_project_dir failed to parse version '{}': {}",
+                                    rest(3),
+                                    e
+                                )))
+                            }
+                        },
+                        Err(e) => {
+                            tracing::error!(
+                                "restore_local_project_dir failed to parse volume '{}': {}",
+                                arg(2),
+                                e
+                            );
+                            Err(Error::Parse(format!(
+                                "restore_local_project_dir failed to parse volume '{}': {}",
+                                arg(2),
+                                e
+                            )))
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!(
+                            "restore_local_project_dir failed to parse mapping '{}': {}",
+                            arg(1),
+                            e
+                        );
                         Err(Error::Parse(format!(
-                            "get_local_storage_report failed to parse: {}",
-                            rest(1)
+                            "restore_local_project_dir failed to parse mapping '{}': {}",
+                            arg(1),
+                            e
                         )))
                     }
                 }
             }
-            "get_local_project_dirs" => match ProjectMapping::parse(&rest(1)) {
-                Ok(mapping) => Ok(Instruction::GetLocalProjectDirs(mapping)),
+            "purge_local_recycled_user_dirs" => match UserMapping::parse(&rest(1)) {
+                Ok(mapping) => Ok(Instruction::PurgeLocalRecycledUserDirs(mapping)),
                 Err(_) => {
-                    tracing::error!("get_local_project_dirs failed to parse: {}", &rest(1));
+                    tracing::error!(
+                        "purge_local_recycled_user_dirs failed to parse: {}",
+                        &rest(1)
+                    );
                     Err(Error::Parse(format!(
-                        "get_local_project_dirs failed to parse: {}",
+                        "purge_local_recycled_user_dirs failed to parse: {}",
                         rest(1)
                     )))
                 }
             },
-            "get_local_user_dirs" => match UserMapping::parse(&rest(1)) {
-                Ok(mapping) => Ok(Instruction::GetLocalUserDirs(mapping)),
+            "purge_local_recycled_project_dirs" => match ProjectMapping::parse(&rest(1)) {
+                Ok(mapping) => Ok(Instruction::PurgeLocalRecycledProjectDirs(mapping)),
                 Err(_) => {
-                    tracing::error!("get_local_user_dirs failed to parse: {}", &rest(1));
+                    tracing::error!(
+                        "purge_local_recycled_project_dirs failed to parse: {}",
+                        &rest(1)
+                    );
                     Err(Error::Parse(format!(
-                        "get_local_user_dirs failed to parse: {}",
+                        "purge_local_recycled_project_dirs failed to parse: {}",
                         rest(1)
                     )))
                 }
@@ -4450,6 +6160,7 @@ impl Instruction {
                 }
             },
             "get_offerings" => Ok(Instruction::GetOfferings()),
+            "get_lifecycle_transitions" => Ok(Instruction::GetLifecycleTransitions()),
             _ => {
                 tracing::error!("Invalid instruction: {}", s);
                 Err(Error::Parse(format!("Invalid instruction: {}", s)))
@@ -4482,12 +6193,20 @@ impl Instruction {
             Instruction::GetHomeDir(_) => "get_home_dir".to_string(),
             Instruction::GetUserDirs(_) => "get_user_dirs".to_string(),
             Instruction::GetProjectDirs(_) => "get_project_dirs".to_string(),
+            Instruction::GetRecycledUserDirs(_) => "get_recycled_user_dirs".to_string(),
+            Instruction::GetRecycledProjectDirs(_) => "get_recycled_project_dirs".to_string(),
+            Instruction::RestoreUserDir(_, _, _) => "restore_user_dir".to_string(),
+            Instruction::RestoreProjectDir(_, _, _) => "restore_project_dir".to_string(),
+            Instruction::PurgeRecycledUserDirs(_) => "purge_recycled_user_dirs".to_string(),
+            Instruction::PurgeRecycledProjectDirs(_) => "purge_recycled_project_dirs".to_string(),
             Instruction::AddLocalUser(_) => "add_local_user".to_string(),
             Instruction::RemoveLocalUser(_) => "remove_local_user".to_string(),
             Instruction::AddLocalProject(_) => "add_local_project".to_string(),
             Instruction::RemoveLocalProject(_) => "remove_local_project".to_string(),
             Instruction::GetLocalUsageReport(_, _) => "get_local_usage_report".to_string(),
+            Instruction::GetLocalJobRecords(_, _, _) => "get_local_job_records".to_string(),
             Instruction::GetLocalLimit(_) => "get_local_limit".to_string(),
+            Instruction::GetLocalMemberLimits(_) => "get_local_member_limits".to_string(),
             Instruction::SetLocalLimit(_, _) => "set_local_limit".to_string(),
             Instruction::GetLocalProjectQuota(_, _) => "get_local_project_quota".to_string(),
             Instruction::ClearLocalProjectQuota(_, _) => "clear_local_project_quota".to_string(),
@@ -4500,14 +6219,53 @@ impl Instruction {
             Instruction::GetLocalHomeDir(_) => "get_local_home_dir".to_string(),
             Instruction::GetLocalUserDirs(_) => "get_local_user_dirs".to_string(),
             Instruction::GetLocalProjectDirs(_) => "get_local_project_dirs".to_string(),
+            Instruction::GetLocalRecycledUserDirs(_) => "get_local_recycled_user_dirs".to_string(),
+            Instruction::GetLocalRecycledProjectDirs(_) => {
+                "get_local_recycled_project_dirs".to_string()
+            }
+            Instruction::RestoreLocalUserDir(_, _, _) => "restore_local_user_dir".to_string(),
+            Instruction::RestoreLocalProjectDir(_, _, _) => "restore_local_project_dir".to_string(),
+            Instruction::PurgeLocalRecycledUserDirs(_) => {
+                "purge_local_recycled_user_dirs".to_string()
+            }
+            Instruction::PurgeLocalRecycledProjectDirs(_) => {
+                "purge_local_recycled_project_dirs".to_string()
+            }
             Instruction::UpdateHomeDir(_, _) => "update_homedir".to_string(),
+            Instruction::SetUserProfile(_, _) => "set_user_profile".to_string(),
+            Instruction::UpdateUserProfile(_, _) => "update_user_profile".to_string(),
+            Instruction::SetUserRole(_, _) => "set_user_role".to_string(),
+            Instruction::SetLocalUserRole(_, _) => "set_local_user_role".to_string(),
             Instruction::GetLocalStorageReport(_, _) => "get_local_storage_report".to_string(),
             Instruction::GetStorageReport(_, _) => "get_storage_report".to_string(),
             Instruction::GetStorageReports(_, _) => "get_storage_reports".to_string(),
             Instruction::GetUsageReport(_, _) => "get_usage_report".to_string(),
+            Instruction::GetCostReport(_, _) => "get_cost_report".to_string(),
+            Instruction::GetJobRecords(_, _, _) => "get_job_records".to_string(),
             Instruction::GetUsageReports(_, _) => "get_usage_reports".to_string(),
             Instruction::SetLimit(_, _) => "set_limit".to_string(),
             Instruction::GetLimit(_) => "get_limit".to_string(),
+            Instruction::GetMemberLimits(_) => "get_member_limits".to_string(),
+            Instruction::GetUserLimit(_) => "get_user_limit".to_string(),
+            Instruction::SetUserLimit(_, _) => "set_user_limit".to_string(),
+            Instruction::GetLocalUserLimit(_) => "get_local_user_limit".to_string(),
+            Instruction::SetLocalUserLimit(_, _) => "set_local_user_limit".to_string(),
+            Instruction::GetSchedulingPolicy(_) => "get_scheduling_policy".to_string(),
+            Instruction::SetSchedulingPolicy(_, _) => "set_scheduling_policy".to_string(),
+            Instruction::SetProjectTemplate(_, _) => "set_project_template".to_string(),
+            Instruction::GetLocalSchedulingPolicy(_) => "get_local_scheduling_policy".to_string(),
+            Instruction::SetLocalSchedulingPolicy(_, _) => {
+                "set_local_scheduling_policy".to_string()
+            }
+            Instruction::SetLocalProjectTemplate(_, _) => "set_local_project_template".to_string(),
+            Instruction::GetReservations(_) => "get_reservations".to_string(),
+            Instruction::CreateReservation(_, _) => "create_reservation".to_string(),
+            Instruction::UpdateReservation(_, _) => "update_reservation".to_string(),
+            Instruction::DeleteReservation(_, _) => "delete_reservation".to_string(),
+            Instruction::GetLocalReservations(_) => "get_local_reservations".to_string(),
+            Instruction::CreateLocalReservation(_, _) => "create_local_reservation".to_string(),
+            Instruction::UpdateLocalReservation(_, _) => "update_local_reservation".to_string(),
+            Instruction::DeleteLocalReservation(_, _) => "delete_local_reservation".to_string(),
             Instruction::GetProjectQuota(_, _) => "get_project_quota".to_string(),
             Instruction::SetProjectQuota(_, _, _) => "set_project_quota".to_string(),
             Instruction::ClearProjectQuota(_, _) => "clear_project_quota".to_string(),
@@ -4523,6 +6281,7 @@ impl Instruction {
             Instruction::AddOfferings(_) => "add_offerings".to_string(),
             Instruction::RemoveOfferings(_) => "remove_offerings".to_string(),
             Instruction::GetOfferings() => "get_offerings".to_string(),
+            Instruction::GetLifecycleTransitions() => "get_lifecycle_transitions".to_string(),
         }
     }
 
@@ -4557,6 +6316,16 @@ impl Instruction {
             Instruction::GetHomeDir(user) => vec![user.to_string()],
             Instruction::GetProjectDirs(project) => vec![project.to_string()],
             Instruction::GetUserDirs(user) => vec![user.to_string()],
+            Instruction::GetRecycledUserDirs(user) => vec![user.to_string()],
+            Instruction::GetRecycledProjectDirs(project) => vec![project.to_string()],
+            Instruction::RestoreUserDir(user, volume, version) => {
+                vec![user.to_string(), volume.to_string(), version.to_string()]
+            }
+            Instruction::RestoreProjectDir(project, volume, version) => {
+                vec![project.to_string(), volume.to_string(), version.to_string()]
+            }
+            Instruction::PurgeRecycledUserDirs(user) => vec![user.to_string()],
+            Instruction::PurgeRecycledProjectDirs(project) => vec![project.to_string()],
             Instruction::AddLocalUser(mapping) => vec![mapping.to_string()],
             Instruction::RemoveLocalUser(mapping) => vec![mapping.to_string()],
             Instruction::AddLocalProject(mapping) => vec![mapping.to_string()],
@@ -4564,7 +6333,16 @@ impl Instruction {
             Instruction::GetLocalUsageReport(mapping, date_range) => {
                 vec![mapping.to_string(), date_range.to_string()]
             }
+            Instruction::GetLocalJobRecords(mapping, date_range, page) => {
+                vec![
+                    mapping.to_string(),
+                    date_range.to_string(),
+                    page.number().to_string(),
+                    page.size().to_string(),
+                ]
+            }
             Instruction::GetLocalLimit(mapping) => vec![mapping.to_string()],
+            Instruction::GetLocalMemberLimits(mapping) => vec![mapping.to_string()],
             Instruction::SetLocalLimit(mapping, usage) => {
                 vec![mapping.to_string(), usage.seconds().to_string()]
             }
@@ -4591,12 +6369,32 @@ impl Instruction {
             Instruction::GetLocalHomeDir(mapping) => vec![mapping.to_string()],
             Instruction::GetLocalUserDirs(mapping) => vec![mapping.to_string()],
             Instruction::GetLocalProjectDirs(mapping) => vec![mapping.to_string()],
+            Instruction::GetLocalRecycledUserDirs(mapping) => vec![mapping.to_string()],
+            Instruction::GetLocalRecycledProjectDirs(mapping) => vec![mapping.to_string()],
+            Instruction::RestoreLocalUserDir(mapping, volume, version) => {
+                vec![mapping.to_string(), volume.to_string(), version.to_string()]
+            }
+            Instruction::RestoreLocalProjectDir(mapping, volume, version) => {
+                vec![mapping.to_string(), volume.to_string(), version.to_string()]
+            }
+            Instruction::PurgeLocalRecycledUserDirs(mapping) => vec![mapping.to_string()],
+            Instruction::PurgeLocalRecycledProjectDirs(mapping) => vec![mapping.to_string()],
             Instruction::GetLocalStorageReport(mapping, date_range) => {
                 vec![mapping.to_string(), date_range.to_string()]
             }
             Instruction::UpdateHomeDir(user, homedir) => {
                 vec![user.to_string(), homedir.clone()]
             }
+            Instruction::SetUserProfile(user, profile) => {
+                vec![user.to_string(), profile.to_string()]
+            }
+            Instruction::UpdateUserProfile(user, profile) => {
+                vec![user.to_string(), profile.to_string()]
+            }
+            Instruction::SetUserRole(user, role) => vec![user.to_string(), role.clone()],
+            Instruction::SetLocalUserRole(mapping, role) => {
+                vec![mapping.to_string(), role.clone()]
+            }
             Instruction::GetStorageReport(project, date_range) => {
                 vec![project.to_string(), date_range.to_string()]
             }
@@ -4606,6 +6404,17 @@ impl Instruction {
             Instruction::GetUsageReport(project, date_range) => {
                 vec![project.to_string(), date_range.to_string()]
             }
+            Instruction::GetCostReport(project, date_range) => {
+                vec![project.to_string(), date_range.to_string()]
+            }
+            Instruction::GetJobRecords(project, date_range, page) => {
+                vec![
+                    project.to_string(),
+                    date_range.to_string(),
+                    page.number().to_string(),
+                    page.size().to_string(),
+                ]
+            }
             Instruction::GetUsageReports(portal, date_range) => {
                 vec![portal.to_string(), date_range.to_string()]
             }
@@ -4613,6 +6422,47 @@ impl Instruction {
                 vec![project.to_string(), usage.seconds().to_string()]
             }
             Instruction::GetLimit(project) => vec![project.to_string()],
+            Instruction::GetMemberLimits(project) => vec![project.to_string()],
+            Instruction::GetUserLimit(user) => vec![user.to_string()],
+            Instruction::SetUserLimit(user, limit) => vec![user.to_string(), limit.to_string()],
+            Instruction::GetLocalUserLimit(mapping) => vec![mapping.to_string()],
+            Instruction::SetLocalUserLimit(mapping, limit) => {
+                vec![mapping.to_string(), limit.to_string()]
+            }
+            Instruction::GetSchedulingPolicy(project) => vec![project.to_string()],
+            Instruction::SetSchedulingPolicy(project, policy) => {
+                vec![project.to_string(), policy.to_string()]
+            }
+            Instruction::SetProjectTemplate(project, template) => {
+                vec![project.to_string(), template.to_string()]
+            }
+            Instruction::GetLocalSchedulingPolicy(mapping) => vec![mapping.to_string()],
+            Instruction::SetLocalSchedulingPolicy(mapping, policy) => {
+                vec![mapping.to_string(), policy.to_string()]
+            }
+            Instruction::SetLocalProjectTemplate(mapping, template) => {
+                vec![mapping.to_string(), template.to_string()]
+            }
+            Instruction::GetReservations(project) => vec![project.to_string()],
+            Instruction::CreateReservation(project, reservation) => {
+                vec![project.to_string(), reservation.to_string()]
+            }
+            Instruction::UpdateReservation(project, reservation) => {
+                vec![project.to_string(), reservation.to_string()]
+            }
+            Instruction::DeleteReservation(project, name) => {
+                vec![project.to_string(), name.clone()]
+            }
+            Instruction::GetLocalReservations(mapping) => vec![mapping.to_string()],
+            Instruction::CreateLocalReservation(mapping, reservation) => {
+                vec![mapping.to_string(), reservation.to_string()]
+            }
+            Instruction::UpdateLocalReservation(mapping, reservation) => {
+                vec![mapping.to_string(), reservation.to_string()]
+            }
+            Instruction::DeleteLocalReservation(mapping, name) => {
+                vec![mapping.to_string(), name.clone()]
+            }
             Instruction::GetProjectQuota(project, volume) => {
                 vec![project.to_string(), volume.to_string()]
             }
@@ -4640,6 +6490,7 @@ impl Instruction {
             Instruction::AddOfferings(offerings) => vec![offerings.to_string()],
             Instruction::RemoveOfferings(offerings) => vec![offerings.to_string()],
             Instruction::GetOfferings() => vec![],
+            Instruction::GetLifecycleTransitions() => vec![],
         }
     }
 }
@@ -4680,11 +6531,28 @@ impl std::fmt::Display for Instruction {
             Instruction::UpdateHomeDir(user, homedir) => {
                 write!(f, "update_homedir {} {}", user, homedir)
             }
+            Instruction::SetUserProfile(user, profile) => {
+                write!(f, "set_user_profile {} {}", user, profile)
+            }
+            Instruction::UpdateUserProfile(user, profile) => {
+                write!(f, "update_user_profile {} {}", user, profile)
+            }
+            Instruction::SetUserRole(user, role) => write!(f, "set_user_role {} {}", user, role),
+            Instruction::SetLocalUserRole(mapping, role) => {
+                write!(f, "set_local_user_role {} {}", mapping, role)
+            }
             Instruction::GetUserMapping(user) => write!(f, "get_user_mapping {}", user),
             Instruction::GetProjectMapping(project) => write!(f, "get_project_mapping {}", project),
             Instruction::GetLocalUsageReport(mapping, date_range) => {
                 write!(f, "get_local_usage_report {} {}", mapping, date_range)
             }
+            Instruction::GetLocalJobRecords(mapping, date_range, page) => {
+                write!(
+                    f,
+                    "get_local_job_records {} {} {}",
+                    mapping, date_range, page
+                )
+            }
             Instruction::GetStorageReport(project, date_range) => {
                 write!(f, "get_storage_report {} {}", project, date_range)
             }
@@ -4694,10 +6562,19 @@ impl std::fmt::Display for Instruction {
             Instruction::GetUsageReport(project, date_range) => {
                 write!(f, "get_usage_report {} {}", project, date_range)
             }
+            Instruction::GetCostReport(project, date_range) => {
+                write!(f, "get_cost_report {} {}", project, date_range)
+            }
+            Instruction::GetJobRecords(project, date_range, page) => {
+                write!(f, "get_job_records {} {} {}", project, date_range, page)
+            }
             Instruction::GetUsageReports(portal, date_range) => {
                 write!(f, "get_usage_reports {} {}", portal, date_range)
             }
             Instruction::GetLocalLimit(mapping) => write!(f, "get_local_limit {}", mapping),
+            Instruction::GetLocalMemberLimits(mapping) => {
+                write!(f, "get_local_member_limits {}", mapping)
+            }
             Instruction::SetLocalLimit(mapping, usage) => {
                 write!(f, "set_local_limit {} {}", mapping, usage.seconds())
             }
@@ -4753,6 +6630,57 @@ impl std::fmt::Display for Instruction {
             }
             Instruction::GetUserQuotas(user) => write!(f, "get_user_quotas {}", user),
             Instruction::GetLimit(project) => write!(f, "get_limit {}", project),
+            Instruction::GetMemberLimits(project) => write!(f, "get_member_limits {}", project),
+            Instruction::GetUserLimit(user) => write!(f, "get_user_limit {}", user),
+            Instruction::SetUserLimit(user, limit) => {
+                write!(f, "set_user_limit {} {}", user, limit)
+            }
+            Instruction::GetLocalUserLimit(mapping) => {
+                write!(f, "get_local_user_limit {}", mapping)
+            }
+            Instruction::SetLocalUserLimit(mapping, limit) => {
+                write!(f, "set_local_user_limit {} {}", mapping, limit)
+            }
+            Instruction::GetSchedulingPolicy(project) => {
+                write!(f, "get_scheduling_policy {}", project)
+            }
+            Instruction::SetSchedulingPolicy(project, policy) => {
+                write!(f, "set_scheduling_policy {} {}", project, policy)
+            }
+            Instruction::SetProjectTemplate(project, template) => {
+                write!(f, "set_project_template {} {}", project, template)
+            }
+            Instruction::GetLocalSchedulingPolicy(mapping) => {
+                write!(f, "get_local_scheduling_policy {}", mapping)
+            }
+            Instruction::SetLocalSchedulingPolicy(mapping, policy) => {
+                write!(f, "set_local_scheduling_policy {} {}", mapping, policy)
+            }
+            Instruction::SetLocalProjectTemplate(mapping, template) => {
+                write!(f, "set_local_project_template {} {}", mapping, template)
+            }
+            Instruction::GetReservations(project) => write!(f, "get_reservations {}", project),
+            Instruction::CreateReservation(project, reservation) => {
+                write!(f, "create_reservation {} {}", project, reservation)
+            }
+            Instruction::UpdateReservation(project, reservation) => {
+                write!(f, "update_reservation {} {}", project, reservation)
+            }
+            Instruction::DeleteReservation(project, name) => {
+                write!(f, "delete_reservation {} {}", project, name)
+            }
+            Instruction::GetLocalReservations(mapping) => {
+                write!(f, "get_local_reservations {}", mapping)
+            }
+            Instruction::CreateLocalReservation(mapping, reservation) => {
+                write!(f, "create_local_reservation {} {}", mapping, reservation)
+            }
+            Instruction::UpdateLocalReservation(mapping, reservation) => {
+                write!(f, "update_local_reservation {} {}", mapping, reservation)
+            }
+            Instruction::DeleteLocalReservation(mapping, name) => {
+                write!(f, "delete_local_reservation {} {}", mapping, name)
+            }
             Instruction::IsProtectedUser(user) => write!(f, "is_protected_user {}", user),
             Instruction::IsExistingUser(user) => write!(f, "is_existing_user {}", user),
             Instruction::IsExistingProject(project) => {
@@ -4766,6 +6694,48 @@ impl std::fmt::Display for Instruction {
             Instruction::GetLocalProjectDirs(mapping) => {
                 write!(f, "get_local_project_dirs {}", mapping)
             }
+            Instruction::GetRecycledUserDirs(user) => write!(f, "get_recycled_user_dirs {}", user),
+            Instruction::GetRecycledProjectDirs(project) => {
+                write!(f, "get_recycled_project_dirs {}", project)
+            }
+            Instruction::RestoreUserDir(user, volume, version) => {
+                write!(f, "restore_user_dir {} {} {}", user, volume, version)
+            }
+            Instruction::RestoreProjectDir(project, volume, version) => {
+                write!(f, "restore_project_dir {} {} {}", project, volume, version)
+            }
+            Instruction::PurgeRecycledUserDirs(user) => {
+                write!(f, "purge_recycled_user_dirs {}", user)
+            }
+            Instruction::PurgeRecycledProjectDirs(project) => {
+                write!(f, "purge_recycled_project_dirs {}", project)
+            }
+            Instruction::GetLocalRecycledUserDirs(mapping) => {
+                write!(f, "get_local_recycled_user_dirs {}", mapping)
+            }
+            Instruction::GetLocalRecycledProjectDirs(mapping) => {
+                write!(f, "get_local_recycled_project_dirs {}", mapping)
+            }
+            Instruction::RestoreLocalUserDir(mapping, volume, version) => {
+                write!(
+                    f,
+                    "restore_local_user_dir {} {} {}",
+                    mapping, volume, version
+                )
+            }
+            Instruction::RestoreLocalProjectDir(mapping, volume, version) => {
+                write!(
+                    f,
+                    "restore_local_project_dir {} {} {}",
+                    mapping, volume, version
+                )
+            }
+            Instruction::PurgeLocalRecycledUserDirs(mapping) => {
+                write!(f, "purge_local_recycled_user_dirs {}", mapping)
+            }
+            Instruction::PurgeLocalRecycledProjectDirs(mapping) => {
+                write!(f, "purge_local_recycled_project_dirs {}", mapping)
+            }
             Instruction::GetLocalStorageReport(mapping, date_range) => {
                 write!(f, "get_local_storage_report {} {}", mapping, date_range)
             }
@@ -4773,6 +6743,7 @@ impl std::fmt::Display for Instruction {
             Instruction::AddOfferings(offerings) => write!(f, "add_offerings {}", offerings),
             Instruction::RemoveOfferings(offerings) => write!(f, "remove_offerings {}", offerings),
             Instruction::GetOfferings() => write!(f, "get_offerings"),
+            Instruction::GetLifecycleTransitions() => write!(f, "get_lifecycle_transitions"),
         }
     }
 }
@@ -4815,6 +6786,14 @@ pub fn owning_portal(instruction: &Instruction) -> Option<PortalIdentifier> {
         Instruction::AddLocalUser(user) => Some(user.user().clone()),
         Instruction::RemoveLocalUser(user) => Some(user.user().clone()),
         Instruction::UpdateHomeDir(user, _) => Some(user),
+        Instruction::SetUserProfile(user, _) => Some(user),
+        Instruction::UpdateUserProfile(user, _) => Some(user),
+        Instruction::SetUserRole(user, _) => Some(user),
+        Instruction::SetLocalUserRole(user, _) => Some(user.user().clone()),
+        Instruction::GetUserLimit(user) => Some(user),
+        Instruction::SetUserLimit(user, _) => Some(user),
+        Instruction::GetLocalUserLimit(user) => Some(user.user().clone()),
+        Instruction::SetLocalUserLimit(user, _) => Some(user.user().clone()),
         Instruction::GetUserMapping(user) => Some(user),
         Instruction::IsProtectedUser(user) => Some(user),
         Instruction::IsExistingUser(user) => Some(user),
@@ -4830,6 +6809,12 @@ pub fn owning_portal(instruction: &Instruction) -> Option<PortalIdentifier> {
         Instruction::GetLocalUserQuotas(user) => Some(user.user().clone()),
         Instruction::GetUserDirs(user) => Some(user),
         Instruction::GetLocalUserDirs(user) => Some(user.user().clone()),
+        Instruction::GetRecycledUserDirs(user) => Some(user),
+        Instruction::RestoreUserDir(user, _, _) => Some(user),
+        Instruction::PurgeRecycledUserDirs(user) => Some(user),
+        Instruction::GetLocalRecycledUserDirs(user) => Some(user.user().clone()),
+        Instruction::RestoreLocalUserDir(user, _, _) => Some(user.user().clone()),
+        Instruction::PurgeLocalRecycledUserDirs(user) => Some(user.user().clone()),
         // The block/unblock family was missing, so the portal-ownership check
         // silently no-op'd for it - letting one portal's client block or
         // unblock another portal's users. See
@@ -4856,14 +6841,39 @@ pub fn owning_portal(instruction: &Instruction) -> Option<PortalIdentifier> {
         Instruction::GetUsers(project) => Some(project),
         Instruction::RemoveProject(project) => Some(project),
         Instruction::GetUsageReport(project, _) => Some(project),
+        Instruction::GetCostReport(project, _) => Some(project),
+        Instruction::GetJobRecords(project, _, _) => Some(project),
         Instruction::GetLocalUsageReport(project, _) => Some(project.project().clone()),
+        Instruction::GetLocalJobRecords(project, _, _) => Some(project.project().clone()),
         Instruction::GetProjectMapping(project) => Some(project),
         Instruction::GetLocalLimit(project) => Some(project.project().clone()),
+        Instruction::GetLocalMemberLimits(project) => Some(project.project().clone()),
         Instruction::SetLocalLimit(project, _) => Some(project.project().clone()),
         Instruction::GetLimit(project) => Some(project),
+        Instruction::GetMemberLimits(project) => Some(project),
         Instruction::SetLimit(project, _) => Some(project),
+        Instruction::GetSchedulingPolicy(project) => Some(project),
+        Instruction::SetSchedulingPolicy(project, _) => Some(project),
+        Instruction::SetProjectTemplate(project, _) => Some(project),
+        Instruction::GetLocalSchedulingPolicy(project) => Some(project.project().clone()),
+        Instruction::SetLocalSchedulingPolicy(project, _) => Some(project.project().clone()),
+        Instruction::SetLocalProjectTemplate(project, _) => Some(project.project().clone()),
+        Instruction::GetReservations(project) => Some(project),
+        Instruction::CreateReservation(project, _) => Some(project),
+        Instruction::UpdateReservation(project, _) => Some(project),
+        Instruction::DeleteReservation(project, _) => Some(project),
+        Instruction::GetLocalReservations(project) => Some(project.project().clone()),
+        Instruction::CreateLocalReservation(project, _) => Some(project.project().clone()),
+        Instruction::UpdateLocalReservation(project, _) => Some(project.project().clone()),
+        Instruction::DeleteLocalReservation(project, _) => Some(project.project().clone()),
         Instruction::GetProjectDirs(project) => Some(project),
         Instruction::GetLocalProjectDirs(project) => Some(project.project().clone()),
+        Instruction::GetRecycledProjectDirs(project) => Some(project),
+        Instruction::RestoreProjectDir(project, _, _) => Some(project),
+        Instruction::PurgeRecycledProjectDirs(project) => Some(project),
+        Instruction::GetLocalRecycledProjectDirs(project) => Some(project.project().clone()),
+        Instruction::RestoreLocalProjectDir(project, _, _) => Some(project.project().clone()),
+        Instruction::PurgeLocalRecycledProjectDirs(project) => Some(project.project().clone()),
         Instruction::GetProjectQuota(project, _) => Some(project),
         Instruction::SetProjectQuota(project, _, _) => Some(project),
         Instruction::ClearProjectQuota(project, _) => Some(project),
@@ -5168,9 +7178,19 @@ mod tests {
             .unwrap_or_else(|e| unreachable!("dates: {:?}", e));
         let volume = Volume::parse("home").unwrap_or_else(|e| unreachable!("volume: {:?}", e));
         let quota = QuotaLimit::parse("1 GB").unwrap_or_else(|e| unreachable!("quota: {:?}", e));
+        let version = RecycleVersion::parse("20260314T101500Z")
+            .unwrap_or_else(|e| unreachable!("version: {:?}", e));
         let usage = Usage::new(3600);
         let details = ProjectDetails::default();
         let homedir = "/home/bob.proj".to_string();
+        let profile = UserProfile::default();
+        let template =
+            ProjectTemplate::parse("gpu").unwrap_or_else(|e| unreachable!("template: {:?}", e));
+        let reservation = Reservation::parse(
+            r#"{"name": "workshop", "node_count": 2,
+                "start_time": "2026-11-02T09:00:00Z", "end_time": "2026-11-02T17:00:00Z"}"#,
+        )
+        .unwrap_or_else(|e| unreachable!("reservation: {:?}", e));
 
         // Every variant that names a user, project or portal, with the portal
         // each one should resolve to.
@@ -5180,6 +7200,10 @@ mod tests {
             Instruction::AddLocalUser(user_mapping.clone()),
             Instruction::RemoveLocalUser(user_mapping.clone()),
             Instruction::UpdateHomeDir(user.clone(), homedir.clone()),
+            Instruction::SetUserProfile(user.clone(), profile.clone()),
+            Instruction::UpdateUserProfile(user.clone(), profile.clone()),
+            Instruction::SetUserRole(user.clone(), "pi".to_string()),
+            Instruction::SetLocalUserRole(user_mapping.clone(), "pi".to_string()),
             Instruction::GetUserMapping(user.clone()),
             Instruction::IsProtectedUser(user.clone()),
             Instruction::IsExistingUser(user.clone()),
@@ -5195,6 +7219,12 @@ mod tests {
             Instruction::GetLocalUserQuotas(user_mapping.clone()),
             Instruction::GetUserDirs(user.clone()),
             Instruction::GetLocalUserDirs(user_mapping.clone()),
+            Instruction::GetRecycledUserDirs(user.clone()),
+            Instruction::RestoreUserDir(user.clone(), volume.clone(), version.clone()),
+            Instruction::PurgeRecycledUserDirs(user.clone()),
+            Instruction::GetLocalRecycledUserDirs(user_mapping.clone()),
+            Instruction::RestoreLocalUserDir(user_mapping.clone(), volume.clone(), version.clone()),
+            Instruction::PurgeLocalRecycledUserDirs(user_mapping.clone()),
             Instruction::BlockUser(user.clone()),
             Instruction::UnblockUser(user.clone()),
             Instruction::IsBlockedUser(user.clone()),
@@ -5209,14 +7239,50 @@ mod tests {
             Instruction::GetUsers(project.clone()),
             Instruction::RemoveProject(project.clone()),
             Instruction::GetUsageReport(project.clone(), dates.clone()),
+            Instruction::GetCostReport(project.clone(), dates.clone()),
+            Instruction::GetJobRecords(project.clone(), dates.clone(), Page::default()),
             Instruction::GetLocalUsageReport(project_mapping.clone(), dates.clone()),
+            Instruction::GetLocalJobRecords(
+                project_mapping.clone(),
+                dates.clone(),
+                Page::default(),
+            ),
             Instruction::GetProjectMapping(project.clone()),
             Instruction::GetLocalLimit(project_mapping.clone()),
+            Instruction::GetLocalMemberLimits(project_mapping.clone()),
             Instruction::SetLocalLimit(project_mapping.clone(), usage),
             Instruction::GetLimit(project.clone()),
+            Instruction::GetMemberLimits(project.clone()),
+            Instruction::GetUserLimit(user.clone()),
+            Instruction::SetUserLimit(user.clone(), UserLimit::new()),
+            Instruction::GetLocalUserLimit(user_mapping.clone()),
+            Instruction::SetLocalUserLimit(user_mapping.clone(), UserLimit::new()),
             Instruction::SetLimit(project.clone(), usage),
+            Instruction::GetSchedulingPolicy(project.clone()),
+            Instruction::SetSchedulingPolicy(project.clone(), SchedulingPolicy::default()),
+            Instruction::SetProjectTemplate(project.clone(), template.clone()),
+            Instruction::GetLocalSchedulingPolicy(project_mapping.clone()),
+            Instruction::SetLocalSchedulingPolicy(
+                project_mapping.clone(),
+                SchedulingPolicy::default(),
+            ),
+            Instruction::SetLocalProjectTemplate(project_mapping.clone(), template.clone()),
+            Instruction::GetReservations(project.clone()),
+            Instruction::CreateReservation(project.clone(), reservation.clone()),
+            Instruction::UpdateReservation(project.clone(), reservation.clone()),
+            Instruction::DeleteReservation(project.clone(), "workshop".to_string()),
+            Instruction::GetLocalReservations(project_mapping.clone()),
+            Instruction::CreateLocalReservation(project_mapping.clone(), reservation.clone()),
+            Instruction::UpdateLocalReservation(project_mapping.clone(), reservation.clone()),
+            Instruction::DeleteLocalReservation(project_mapping.clone(), "workshop".to_string()),
             Instruction::GetProjectDirs(project.clone()),
             Instruction::GetLocalProjectDirs(project_mapping.clone()),
+            Instruction::GetRecycledProjectDirs(project.clone()),
+            Instruction::RestoreProjectDir(project.clone(), volume.clone(), version.clone()),
+            Instruction::PurgeRecycledProjectDirs(project.clone()),
+            Instruction::GetLocalRecycledProjectDirs(project_mapping.clone()),
+            Instruction::RestoreLocalProjectDir(project_mapping.clone(), volume.clone(), version),
+            Instruction::PurgeLocalRecycledProjectDirs(project_mapping.clone()),
             Instruction::GetProjectQuota(project.clone(), volume.clone()),
             Instruction::SetProjectQuota(project.clone(), volume.clone(), quota.clone()),
             Instruction::ClearProjectQuota(project.clone(), volume.clone()),
@@ -5251,6 +7317,7 @@ mod tests {
         // and the offerings family is addressed by destination, not by portal.
         for instruction in [
             Instruction::GetOfferings(),
+            Instruction::GetLifecycleTransitions(),
             Instruction::AddOfferings(Destinations::default()),
             Instruction::RemoveOfferings(Destinations::default()),
             Instruction::SyncOfferings(Destinations::default()),
@@ -5322,11 +7389,20 @@ mod tests {
             "add_local_user",
             "remove_local_user",
             "get_usage_report",
+            "get_cost_report",
+            "get_job_records",
             "get_local_usage_report",
+            "get_local_job_records",
             "get_limit",
             "set_limit",
             "get_local_limit",
             "set_local_limit",
+            "get_member_limits",
+            "get_local_member_limits",
+            "get_user_limit",
+            "set_user_limit",
+            "get_local_user_limit",
+            "set_local_user_limit",
             "block_user",
             "unblock_user",
             "is_blocked_user",
@@ -5336,10 +7412,41 @@ mod tests {
             "get_storage_report",
             "get_home_dir",
             "update_home_dir",
+            "set_user_profile",
+            "update_user_profile",
+            "set_user_role",
+            "set_local_user_role",
+            "get_scheduling_policy",
+            "set_scheduling_policy",
+            "set_project_template",
+            "get_local_scheduling_policy",
+            "set_local_scheduling_policy",
+            "set_local_project_template",
+            "get_reservations",
+            "create_reservation",
+            "update_reservation",
+            "delete_reservation",
+            "get_local_reservations",
+            "create_local_reservation",
+            "update_local_reservation",
+            "delete_local_reservation",
             "get_offerings",
             "add_offerings",
             "remove_offerings",
             "sync_offerings",
+            "get_lifecycle_transitions",
+            "get_recycled_user_dirs",
+            "get_recycled_project_dirs",
+            "restore_user_dir",
+            "restore_project_dir",
+            "purge_recycled_user_dirs",
+            "purge_recycled_project_dirs",
+            "get_local_recycled_user_dirs",
+            "get_local_recycled_project_dirs",
+            "restore_local_user_dir",
+            "restore_local_project_dir",
+            "purge_local_recycled_user_dirs",
+            "purge_local_recycled_project_dirs",
             "not_a_real_instruction",
         ];
 
@@ -5724,6 +7831,313 @@ mod tests {
         assert!(validate_email_address("alice@@example.com").is_err());
     }
 
+    #[test]
+    fn test_user_profile() {
+        let key = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIBq8oXkN1wqLmJ0cZ alice@laptop";
+
+        let mut profile = UserProfile::new();
+        assert!(profile.is_empty());
+
+        profile
+            .set_display_name("  Alice   van der Berg ")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        profile
+            .set_email("alice@example.com")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        profile
+            .set_ssh_keys(vec![key.to_string()])
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        profile
+            .set_shell("/bin/bash")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            profile.display_name(),
+            Some("Alice van der Berg".to_string())
+        );
+        assert_eq!(profile.given_name(), Some("Alice van der".to_string()));
+        assert_eq!(profile.family_name(), Some("Berg".to_string()));
+
+        // keys that could smuggle authorized_keys options or extra lines
+        // are refused, and leave the existing keys in place
+        for bad in [
+            format!("command=\"/bin/sh\" {}", key),
+            format!("{}\nssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQ", key),
+            "ssh-ed25519".to_string(),
+            "ssh-ed25519 not*base64*at*all".to_string(),
+        ] {
+            assert!(profile.set_ssh_keys(vec![bad]).is_err());
+        }
+        assert_eq!(profile.ssh_keys(), Some(vec![key.to_string()]));
+
+        assert!(profile.set_shell("bash").is_err());
+        assert!(profile.set_shell("/bin/../tmp/sh").is_err());
+        assert!(profile.set_shell("/bin/bash; rm -rf /").is_err());
+        assert!(profile.set_display_name("Alice:0:0").is_err());
+        assert!(profile.set_email("not an email").is_err());
+
+        // round trip through an instruction
+        let user =
+            UserIdentifier::parse("alice.proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let instruction = Instruction::SetUserProfile(user.clone(), profile.clone());
+        let parsed = Instruction::parse(&instruction.to_string())
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(parsed, instruction);
+        assert_eq!(parsed.command(), "set_user_profile");
+
+        let mut update = UserProfile::new();
+        update
+            .set_shell("/bin/zsh")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let instruction = Instruction::parse(&format!("update_user_profile {} {}", user, update))
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(
+            instruction,
+            Instruction::UpdateUserProfile(user.clone(), update.clone())
+        );
+
+        let merged = profile.merge(&update);
+        assert_eq!(merged.shell(), Some("/bin/zsh".to_string()));
+        assert_eq!(merged.email(), profile.email());
+
+        // a profile that bypasses the setters is still validated on parse
+        assert!(Instruction::parse(&format!(
+            "set_user_profile {} {{\"shell\": \"sh -c id\"}}",
+            user
+        ))
+        .is_err());
+        assert!(Instruction::parse(&format!(
+            "set_user_profile {} {{\"ssh_keys\": [\"from=\\\"*\\\" {}\"]}}",
+            user, key
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_set_user_role() {
+        let user =
+            UserIdentifier::parse("alice.proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let mapping =
+            UserMapping::new(&user, "alice", "proj").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        // roles are normalised to lower case
+        let instruction = Instruction::parse(&format!("set_user_role {} PI", user))
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(
+            instruction,
+            Instruction::SetUserRole(user.clone(), "pi".to_string())
+        );
+        assert_eq!(
+            instruction.arguments(),
+            vec![user.to_string(), "pi".to_string()]
+        );
+
+        let instruction = Instruction::SetLocalUserRole(mapping.clone(), "manager".to_string());
+        let parsed = Instruction::parse(&instruction.to_string())
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(parsed, instruction);
+        assert_eq!(parsed.command(), "set_local_user_role");
+
+        for bad in ["", "pi manager", "p;i", "pi\""] {
+            assert!(Instruction::parse(&format!("set_user_role {} {}", user, bad)).is_err());
+        }
+        assert!(Instruction::parse(&format!("set_local_user_role {} ../pi", mapping)).is_err());
+    }
+
+    #[test]
+    fn test_scheduling_policy_instructions() {
+        let project =
+            ProjectIdentifier::parse("proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let policy = SchedulingPolicy::parse(
+            r#"{"qos": ["gpu", "normal"], "default_qos": "normal", "partitions": ["gpu"]}"#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let instruction = Instruction::SetSchedulingPolicy(project.clone(), policy.clone());
+        let parsed = Instruction::parse(&instruction.to_string())
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(parsed, instruction);
+        assert_eq!(parsed.command(), "set_scheduling_policy");
+
+        let instruction = Instruction::parse(&format!("set_project_template {} gpu", project))
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(instruction.command(), "set_project_template");
+
+        // a policy that bypasses the setters is still validated on parse
+        assert!(Instruction::parse(&format!(
+            "set_scheduling_policy {} {{\"qos\": [\"gpu;id\"]}}",
+            project
+        ))
+        .is_err());
+        assert!(Instruction::parse(&format!("set_project_template {} g/pu", project)).is_err());
+    }
+
+    #[test]
+    fn test_user_limit_instructions() {
+        let user =
+            UserIdentifier::parse("alice.proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let mapping =
+            UserMapping::new(&user, "alice", "proj").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let limit = UserLimit::parse(r#"{"max_jobs": 4, "grp_tres": {"gres/gpu": 2}}"#)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        for instruction in [
+            Instruction::SetUserLimit(user.clone(), limit.clone()),
+            Instruction::GetUserLimit(user.clone()),
+            Instruction::SetLocalUserLimit(mapping.clone(), limit.clone()),
+            Instruction::GetLocalUserLimit(mapping.clone()),
+        ] {
+            let parsed = Instruction::parse(&instruction.to_string())
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+            assert_eq!(parsed, instruction);
+        }
+
+        assert!(Instruction::parse(&format!("set_user_limit {} max_jobs=4", user)).is_err());
+    }
+
+    #[test]
+    fn test_member_limit_instructions() {
+        let project =
+            ProjectIdentifier::parse("proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let mapping =
+            ProjectMapping::new(&project, "proj").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        for (instruction, text) in [
+            (
+                Instruction::GetMemberLimits(project.clone()),
+                "get_member_limits proj.portal",
+            ),
+            (
+                Instruction::GetLocalMemberLimits(mapping.clone()),
+                "get_local_member_limits proj.portal:proj",
+            ),
+        ] {
+            assert_eq!(instruction.to_string(), text);
+
+            let parsed = Instruction::parse(text).unwrap_or_else(|e| unreachable!("{:?}", e));
+            assert_eq!(parsed, instruction);
+        }
+
+        // get_limit still names the project alone, and returns a Usage
+        assert_eq!(
+            Instruction::parse("get_limit proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e)),
+            Instruction::GetLimit(project)
+        );
+        assert!(Instruction::parse("get_member_limits").is_err());
+    }
+
+    #[test]
+    fn test_job_records_instructions() {
+        let project =
+            ProjectIdentifier::parse("proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let mapping =
+            ProjectMapping::new(&project, "proj").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let dates =
+            DateRange::parse("2026-01-01:2026-03-31").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let page = Page::new(3, 250).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        for instruction in [
+            Instruction::GetJobRecords(project.clone(), dates.clone(), page),
+            Instruction::GetLocalJobRecords(mapping.clone(), dates.clone(), page),
+        ] {
+            let parsed = Instruction::parse(&instruction.to_string())
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+            assert_eq!(parsed, instruction);
+        }
+
+        // the dates and page are optional
+        assert_eq!(
+            Instruction::parse(&format!("get_job_records {}", project))
+                .unwrap_or_else(|e| unreachable!("{:?}", e)),
+            Instruction::GetJobRecords(
+                project.clone(),
+                DateRange::parse("this_week").unwrap_or_else(|e| unreachable!("{:?}", e)),
+                Page::default()
+            )
+        );
+
+        assert!(Instruction::parse(&format!("get_job_records {} {} 0", project, dates)).is_err());
+        assert!(
+            Instruction::parse(&format!("get_job_records {} {} 1 1000000", project, dates))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_recycle_bin_instructions() {
+        let user =
+            UserIdentifier::parse("fred.proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let project =
+            ProjectIdentifier::parse("proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let user_mapping =
+            UserMapping::new(&user, "fred", "proj").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let project_mapping =
+            ProjectMapping::new(&project, "proj").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let volume = Volume::parse("home").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let version =
+            RecycleVersion::parse("20260314T101500Z-2").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        for instruction in [
+            Instruction::GetRecycledUserDirs(user.clone()),
+            Instruction::GetRecycledProjectDirs(project.clone()),
+            Instruction::RestoreUserDir(user.clone(), volume.clone(), version.clone()),
+            Instruction::RestoreProjectDir(project.clone(), volume.clone(), version.clone()),
+            Instruction::PurgeRecycledUserDirs(user.clone()),
+            Instruction::PurgeRecycledProjectDirs(project.clone()),
+            Instruction::GetLocalRecycledUserDirs(user_mapping.clone()),
+            Instruction::GetLocalRecycledProjectDirs(project_mapping.clone()),
+            Instruction::RestoreLocalUserDir(user_mapping.clone(), volume.clone(), version.clone()),
+            Instruction::RestoreLocalProjectDir(project_mapping.clone(), volume.clone(), version),
+            Instruction::PurgeLocalRecycledUserDirs(user_mapping.clone()),
+            Instruction::PurgeLocalRecycledProjectDirs(project_mapping.clone()),
+        ] {
+            let parsed = Instruction::parse(&instruction.to_string())
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+            assert_eq!(parsed, instruction);
+        }
+
+        assert_eq!(
+            Instruction::parse(&format!("restore_user_dir {} home legacy", user))
+                .unwrap_or_else(|e| unreachable!("{:?}", e)),
+            Instruction::RestoreUserDir(user.clone(), volume, RecycleVersion::legacy())
+        );
+
+        // the version is needed, and must be one that was given out
+        assert!(Instruction::parse(&format!("restore_user_dir {} home", user)).is_err());
+        assert!(Instruction::parse(&format!("restore_user_dir {} home latest", user)).is_err());
+        assert!(Instruction::parse(&format!("restore_user_dir {} home ../../etc", user)).is_err());
+    }
+
+    #[test]
+    fn test_reservation_instructions() {
+        let project =
+            ProjectIdentifier::parse("proj.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let reservation = Reservation::parse(
+            r#"{"name": "workshop", "nodes": ["gpu[01-04]"], "partition": "gpu",
+                "start_time": "2026-11-02T09:00:00Z", "end_time": "2026-11-02T17:00:00Z"}"#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        for instruction in [
+            Instruction::CreateReservation(project.clone(), reservation.clone()),
+            Instruction::UpdateReservation(project.clone(), reservation.clone()),
+            Instruction::DeleteReservation(project.clone(), "workshop".to_string()),
+            Instruction::GetReservations(project.clone()),
+        ] {
+            let parsed = Instruction::parse(&instruction.to_string())
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+            assert_eq!(parsed, instruction);
+        }
+
+        // the name is passed on to scontrol, so must be validated
+        assert!(Instruction::parse(&format!("delete_reservation {} a=b", project)).is_err());
+    }
+
     #[test]
     fn test_add_member_validation() {
         #[allow(clippy::unwrap_used)]
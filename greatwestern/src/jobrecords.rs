@@ -0,0 +1,536 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use templemeads::Error;
+
+use crate::grammar::{DateRange, ProjectIdentifier, UserIdentifier, UserMapping};
+use crate::usagereport::Usage;
+use templemeads::named::NamedType;
+
+impl NamedType for JobRecord {
+    fn type_name() -> String {
+        "JobRecord".to_string()
+    }
+}
+
+impl NamedType for JobRecords {
+    fn type_name() -> String {
+        "JobRecords".to_string()
+    }
+}
+
+/// The number of job records in a page, if this is not given
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// The most job records that can be asked for in a single page
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+///
+/// A page of job records - the page number (counting from 1) and
+/// the number of records in each page
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Page {
+    number: u32,
+    size: u32,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            size: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+impl std::fmt::Display for Page {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.number, self.size)
+    }
+}
+
+impl Page {
+    pub fn new(number: u32, size: u32) -> Result<Self, Error> {
+        if number == 0 {
+            return Err(Error::Parse(
+                "Invalid page 0 - pages are numbered from 1".to_string(),
+            ));
+        }
+
+        if size == 0 || size > MAX_PAGE_SIZE {
+            return Err(Error::Parse(format!(
+                "Invalid page size {} - this should be from 1 to {}",
+                size, MAX_PAGE_SIZE
+            )));
+        }
+
+        Ok(Self { number, size })
+    }
+
+    ///
+    /// Parse the page from the (optional) page number and page size
+    ///
+    pub fn parse(number: Option<&str>, size: Option<&str>) -> Result<Self, Error> {
+        let number = match number {
+            Some(number) => number.trim().parse::<u32>().map_err(|_| {
+                Error::Parse(format!(
+                    "Invalid page '{}' - this should be a number from 1",
+                    number
+                ))
+            })?,
+            None => 1,
+        };
+
+        let size = match size {
+            Some(size) => size.trim().parse::<u32>().map_err(|_| {
+                Error::Parse(format!(
+                    "Invalid page size '{}' - this should be a number from 1 to {}",
+                    size, MAX_PAGE_SIZE
+                ))
+            })?,
+            None => DEFAULT_PAGE_SIZE,
+        };
+
+        Self::new(number, size)
+    }
+
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// The index of the first record in this page
+    pub fn offset(&self) -> usize {
+        (self.number as usize)
+            .saturating_sub(1)
+            .saturating_mul(self.size as usize)
+    }
+}
+
+///
+/// The accounting record of a single job that was charged to a
+/// project. The usage is only that which fell within the dates
+/// that the records were asked for.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct JobRecord {
+    /// The scheduler's ID of the job
+    id: u64,
+
+    /// The user who ran the job, if they are known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(as = "Option<String>", optional = nullable)]
+    user: Option<UserIdentifier>,
+
+    /// The local (scheduler) username of the user who ran the job
+    local_user: String,
+
+    /// The state of the job, e.g. "COMPLETED" or "RUNNING"
+    state: String,
+
+    /// The QoS the job ran under
+    qos: String,
+
+    /// When the job became eligible to run
+    #[ts(as = "String")]
+    eligible_time: chrono::DateTime<chrono::Utc>,
+
+    /// When the job started running
+    #[ts(as = "String")]
+    start_time: chrono::DateTime<chrono::Utc>,
+
+    /// When the job ended, or the end of the dates if it ran on
+    #[ts(as = "String")]
+    end_time: chrono::DateTime<chrono::Utc>,
+
+    /// The number of nodes allocated to the job
+    nodes: u64,
+
+    /// The number of CPUs allocated to the job
+    cpus: u64,
+
+    /// The number of GPUs allocated to the job
+    gpus: u64,
+
+    /// The memory (in MB) allocated to the job
+    memory: u64,
+
+    /// The billing units allocated to the job
+    billing: u64,
+
+    /// The usage charged to the project for the job
+    usage: Usage,
+}
+
+impl std::fmt::Display for JobRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} - {} : {}",
+            self.id,
+            match &self.user {
+                Some(user) => user.to_string(),
+                None => self.local_user.clone(),
+            },
+            self.state,
+            self.start_time,
+            self.end_time,
+            self.usage
+        )
+    }
+}
+
+impl JobRecord {
+    pub fn new(id: u64, local_user: &str, state: &str, qos: &str) -> Self {
+        let now = chrono::Utc::now();
+
+        Self {
+            id,
+            user: None,
+            local_user: local_user.to_string(),
+            state: state.to_string(),
+            qos: qos.to_string(),
+            eligible_time: now,
+            start_time: now,
+            end_time: now,
+            nodes: 0,
+            cpus: 0,
+            gpus: 0,
+            memory: 0,
+            billing: 0,
+            usage: Usage::default(),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn user(&self) -> Option<&UserIdentifier> {
+        self.user.as_ref()
+    }
+
+    pub fn local_user(&self) -> &str {
+        &self.local_user
+    }
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    pub fn qos(&self) -> &str {
+        &self.qos
+    }
+
+    pub fn eligible_time(&self) -> &chrono::DateTime<chrono::Utc> {
+        &self.eligible_time
+    }
+
+    pub fn start_time(&self) -> &chrono::DateTime<chrono::Utc> {
+        &self.start_time
+    }
+
+    pub fn end_time(&self) -> &chrono::DateTime<chrono::Utc> {
+        &self.end_time
+    }
+
+    pub fn set_times(
+        &mut self,
+        eligible_time: &chrono::DateTime<chrono::Utc>,
+        start_time: &chrono::DateTime<chrono::Utc>,
+        end_time: &chrono::DateTime<chrono::Utc>,
+    ) {
+        self.eligible_time = *eligible_time;
+        self.start_time = *start_time;
+        self.end_time = *end_time;
+    }
+
+    ///
+    /// The time the job waited in the queue before it started, clamped
+    /// to zero in case the scheduler's timestamps disagree
+    ///
+    pub fn wait_seconds(&self) -> u64 {
+        self.start_time
+            .signed_duration_since(self.eligible_time)
+            .num_seconds()
+            .max(0) as u64
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn cpus(&self) -> u64 {
+        self.cpus
+    }
+
+    pub fn gpus(&self) -> u64 {
+        self.gpus
+    }
+
+    pub fn memory(&self) -> u64 {
+        self.memory
+    }
+
+    pub fn billing(&self) -> u64 {
+        self.billing
+    }
+
+    pub fn set_resources(&mut self, nodes: u64, cpus: u64, gpus: u64, memory: u64, billing: u64) {
+        self.nodes = nodes;
+        self.cpus = cpus;
+        self.gpus = gpus;
+        self.memory = memory;
+        self.billing = billing;
+    }
+
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    ///
+    /// Add the usage of the job in a later window of time that ended at
+    /// `end_time`, for jobs whose usage is read in several windows
+    ///
+    pub fn add_usage(&mut self, usage: Usage, end_time: &chrono::DateTime<chrono::Utc>) {
+        self.usage += usage;
+
+        if *end_time > self.end_time {
+            self.end_time = *end_time;
+        }
+    }
+}
+
+///
+/// A page of the job records of a project over a range of dates,
+/// in the order that the jobs started
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct JobRecords {
+    #[ts(as = "String")]
+    project: ProjectIdentifier,
+
+    #[ts(as = "String")]
+    dates: DateRange,
+
+    /// The number of this page, counting from 1
+    page: u32,
+
+    /// The most records in each page
+    page_size: u32,
+
+    /// The number of records in all of the pages
+    total: u64,
+
+    records: Vec<JobRecord>,
+}
+
+impl std::fmt::Display for JobRecords {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} {}: page {} of {} ({} jobs)",
+            self.project,
+            self.dates,
+            self.page,
+            self.num_pages(),
+            self.total
+        )?;
+
+        for record in &self.records {
+            writeln!(f, "{}", record)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl JobRecords {
+    ///
+    /// Return the requested page of `records`, which are the records of
+    /// all of the jobs of `project` within `dates`
+    ///
+    pub fn paginate(
+        project: &ProjectIdentifier,
+        dates: &DateRange,
+        page: &Page,
+        mut records: Vec<JobRecord>,
+    ) -> Self {
+        records.sort_by(|a, b| {
+            a.start_time
+                .cmp(&b.start_time)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let total = records.len() as u64;
+
+        let records = records
+            .into_iter()
+            .skip(page.offset())
+            .take(page.size() as usize)
+            .collect();
+
+        Self {
+            project: project.clone(),
+            dates: dates.clone(),
+            page: page.number(),
+            page_size: page.size(),
+            total,
+            records,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string(self)
+            .with_context(|| "Failed to serialize job records to JSON")
+            .map_err(Error::from)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .with_context(|| format!("Failed to deserialize job records from JSON: {}", json))
+            .map_err(Error::from)
+    }
+
+    pub fn project(&self) -> &ProjectIdentifier {
+        &self.project
+    }
+
+    pub fn dates(&self) -> &DateRange {
+        &self.dates
+    }
+
+    pub fn page(&self) -> u32 {
+        self.page
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn num_pages(&self) -> u64 {
+        match self.page_size {
+            0 => 0,
+            size => self.total.div_ceil(size as u64),
+        }
+    }
+
+    /// Whether or not there are records in later pages
+    pub fn has_more(&self) -> bool {
+        (self.page as u64) < self.num_pages()
+    }
+
+    pub fn records(&self) -> &Vec<JobRecord> {
+        &self.records
+    }
+
+    ///
+    /// Set the user of each record from the mappings of the project's
+    /// users to their local usernames
+    ///
+    pub fn add_mappings(&mut self, mappings: &[UserMapping]) {
+        for mapping in mappings {
+            if mapping.user().project_identifier() != self.project {
+                tracing::warn!(
+                    "Ignoring mapping for wrong project: {}. These records are for {}",
+                    mapping,
+                    self.project
+                );
+                continue;
+            }
+
+            for record in self
+                .records
+                .iter_mut()
+                .filter(|r| r.local_user == mapping.local_user().as_str())
+            {
+                record.user = Some(mapping.user().clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_records_are_paginated() {
+        let project =
+            ProjectIdentifier::parse("jobs.brics").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let dates =
+            DateRange::parse("2026-03-01:2026-03-31").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let start = chrono::DateTime::parse_from_rfc3339("2026-03-02T09:00:00Z")
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+            .with_timezone(&chrono::Utc);
+
+        // add the records out of order, to check they are sorted by start time
+        let records: Vec<JobRecord> = (0..5)
+            .rev()
+            .map(|i| {
+                let mut record = JobRecord::new(100 + i, "alice", "COMPLETED", "normal");
+                let started = start + chrono::Duration::hours(i as i64);
+                record.set_times(
+                    &(started - chrono::Duration::minutes(10)),
+                    &started,
+                    &(started + chrono::Duration::minutes(30)),
+                );
+                record
+            })
+            .collect();
+
+        let page = Page::parse(Some("2"), Some("2")).unwrap_or_else(|e| unreachable!("{:?}", e));
+        let mut records = JobRecords::paginate(&project, &dates, &page, records);
+
+        assert_eq!(records.total(), 5);
+        assert_eq!(records.num_pages(), 3);
+        assert!(records.has_more());
+        assert_eq!(
+            records.records().iter().map(|r| r.id()).collect::<Vec<_>>(),
+            vec![102, 103]
+        );
+        assert_eq!(records.records()[0].wait_seconds(), 600);
+
+        // the records are mapped to the users of the project only
+        let mapping = UserMapping::parse("alice.jobs.brics:alice:jobs")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        let other = UserMapping::parse("alice.other.brics:alice:other")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        records.add_mappings(&[other, mapping.clone()]);
+        assert_eq!(records.records()[1].user(), Some(mapping.user()));
+
+        let json = records
+            .to_json()
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(
+            JobRecords::from_json(&json).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            records
+        );
+
+        // pages past the end are empty
+        let page = Page::new(4, 2).unwrap_or_else(|e| unreachable!("{:?}", e));
+        let empty = JobRecords::paginate(&project, &dates, &page, Vec::new());
+        assert!(empty.records().is_empty());
+        assert!(!empty.has_more());
+
+        assert!(Page::parse(Some("0"), None).is_err());
+        assert!(Page::parse(None, Some("100000")).is_err());
+        assert!(Page::parse(Some("first"), None).is_err());
+    }
+}
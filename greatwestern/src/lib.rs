@@ -13,9 +13,14 @@
 //! wants to bring for a different kind of infrastructure entirely.
 
 pub mod errorkind;
+pub mod export;
 pub mod grammar;
 mod job_bindings;
+pub mod jobrecords;
+pub mod lifecycle;
 pub mod notification;
+pub mod pricing;
+pub mod scheduling;
 pub mod storage;
 pub mod storagereport;
 pub mod usagereport;
@@ -67,6 +72,15 @@ impl Domain for Hpc {
         errorkind::classify(message)
     }
 
+    fn export_result(
+        result_type: &str,
+        result: &str,
+        format: &str,
+        site: Option<&str>,
+    ) -> Result<(String, String), Error> {
+        export::export_result(result_type, result, format, site)
+    }
+
     fn assume_legacy_domain_version(engine_version: &str) -> Option<&'static str> {
         // Before the templemeads/greatwestern split, templemeads only ever
         // spoke this vocabulary - there was no separable "domain" at all, so
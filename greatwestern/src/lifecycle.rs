@@ -0,0 +1,397 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use templemeads::Error;
+
+use crate::grammar::{Date, ProjectIdentifier};
+use templemeads::named::NamedType;
+
+impl NamedType for LifecycleTransition {
+    fn type_name() -> String {
+        "LifecycleTransition".to_string()
+    }
+}
+
+/// The most days that any lifecycle period can be
+const MAX_DAYS: u32 = 10 * 366;
+
+///
+/// What happens to a project when it reaches a stage of its lifecycle
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum LifecycleAction {
+    /// Warn that the award ends in this many days
+    Warn(u32),
+
+    /// Block the project, as its award has ended
+    Block,
+
+    /// Remove the project, as its award ended more than the
+    /// retention period ago
+    Remove,
+}
+
+impl std::fmt::Display for LifecycleAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Warn(days) => write!(f, "warn ({} days left)", days),
+            Self::Block => write!(f, "block"),
+            Self::Remove => write!(f, "remove"),
+        }
+    }
+}
+
+///
+/// A change to a project on a resource that falls due on a date,
+/// because of the end date of the project's award
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct LifecycleTransition {
+    /// The project (award) that changes
+    #[ts(as = "String")]
+    project: ProjectIdentifier,
+
+    /// The (virtual) resource the project is on
+    resource: String,
+
+    /// The date on which the change is due
+    #[ts(as = "String")]
+    date: Date,
+
+    /// What happens to the project
+    action: LifecycleAction,
+}
+
+impl LifecycleTransition {
+    pub fn new(
+        project: &ProjectIdentifier,
+        resource: &str,
+        date: &Date,
+        action: LifecycleAction,
+    ) -> Self {
+        Self {
+            project: project.clone(),
+            resource: resource.to_string(),
+            date: date.clone(),
+            action,
+        }
+    }
+
+    pub fn project(&self) -> &ProjectIdentifier {
+        &self.project
+    }
+
+    pub fn resource(&self) -> &str {
+        &self.resource
+    }
+
+    pub fn date(&self) -> &Date {
+        &self.date
+    }
+
+    pub fn action(&self) -> LifecycleAction {
+        self.action
+    }
+}
+
+impl std::fmt::Display for LifecycleTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} {} on {}",
+            self.date, self.action, self.project, self.resource
+        )
+    }
+}
+
+fn add_days(date: &Date, days: u32) -> Date {
+    Date::from_chrono(
+        &date
+            .to_chrono()
+            .checked_add_days(chrono::Days::new(days as u64))
+            .unwrap_or(chrono::NaiveDate::MAX),
+    )
+}
+
+fn sub_days(date: &Date, days: u32) -> Date {
+    Date::from_chrono(
+        &date
+            .to_chrono()
+            .checked_sub_days(chrono::Days::new(days as u64))
+            .unwrap_or(chrono::NaiveDate::MIN),
+    )
+}
+
+fn parse_days(days: &str, name: &str) -> Result<u32, Error> {
+    match days.trim().parse::<u32>() {
+        Ok(days) if days <= MAX_DAYS => Ok(days),
+        _ => Err(Error::Parse(format!(
+            "Invalid {} '{}' - this should be a number of days from 0 to {}",
+            name, days, MAX_DAYS
+        ))),
+    }
+}
+
+///
+/// The policy that turns the end date of an award into the transitions
+/// of its project: warnings a number of days before the end date, a
+/// block once the grace period after the end date has passed, and
+/// (optionally) removal once the retention period has passed.
+///
+/// The end date is the last day of the award, so a project with no
+/// grace period is blocked on the following day.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecyclePolicy {
+    /// The days before the end date on which to warn, latest first
+    warnings: Vec<u32>,
+    grace_days: u32,
+    retention_days: Option<u32>,
+}
+
+impl LifecyclePolicy {
+    pub fn new(
+        warnings: &[u32],
+        grace_days: u32,
+        retention_days: Option<u32>,
+    ) -> Result<Self, Error> {
+        let mut warnings = warnings.to_vec();
+
+        if let Some(days) = warnings.iter().find(|d| **d == 0 || **d > MAX_DAYS) {
+            return Err(Error::Parse(format!(
+                "Invalid warning of {} days - warnings must be from 1 to {} days before the end date",
+                days, MAX_DAYS
+            )));
+        }
+
+        if grace_days > MAX_DAYS {
+            return Err(Error::Parse(format!(
+                "The grace period cannot be more than {} days",
+                MAX_DAYS
+            )));
+        }
+
+        if let Some(retention_days) = retention_days {
+            if retention_days < grace_days || retention_days > MAX_DAYS {
+                return Err(Error::Parse(format!(
+                    "The retention period of {} days must be from the grace period ({} days) to {} days",
+                    retention_days, grace_days, MAX_DAYS
+                )));
+            }
+        }
+
+        warnings.sort_by(|a, b| b.cmp(a));
+        warnings.dedup();
+
+        Ok(Self {
+            warnings,
+            grace_days,
+            retention_days,
+        })
+    }
+
+    ///
+    /// Parse the policy from its configuration - a comma-separated list
+    /// of warning days, the grace period in days, and the retention
+    /// period in days (empty if projects should never be removed)
+    ///
+    pub fn parse(warnings: &str, grace_days: &str, retention_days: &str) -> Result<Self, Error> {
+        let warnings = warnings
+            .split(',')
+            .filter(|w| !w.trim().is_empty())
+            .map(|w| parse_days(w, "warning"))
+            .collect::<Result<Vec<u32>, Error>>()?;
+
+        let grace_days = parse_days(grace_days, "grace period")?;
+
+        let retention_days = match retention_days.trim() {
+            "" => None,
+            days => Some(parse_days(days, "retention period")?),
+        };
+
+        Self::new(&warnings, grace_days, retention_days)
+    }
+
+    pub fn warnings(&self) -> Vec<u32> {
+        self.warnings.clone()
+    }
+
+    pub fn grace_days(&self) -> u32 {
+        self.grace_days
+    }
+
+    pub fn retention_days(&self) -> Option<u32> {
+        self.retention_days
+    }
+
+    ///
+    /// Return all of the transitions of a project on `resource` whose
+    /// award ends on `end_date`, in date order
+    ///
+    pub fn transitions(
+        &self,
+        project: &ProjectIdentifier,
+        resource: &str,
+        end_date: &Date,
+    ) -> Vec<LifecycleTransition> {
+        let mut transitions: Vec<LifecycleTransition> = self
+            .warnings
+            .iter()
+            .map(|days| {
+                LifecycleTransition::new(
+                    project,
+                    resource,
+                    &sub_days(end_date, *days),
+                    LifecycleAction::Warn(*days),
+                )
+            })
+            .collect();
+
+        transitions.push(LifecycleTransition::new(
+            project,
+            resource,
+            &add_days(end_date, self.grace_days + 1),
+            LifecycleAction::Block,
+        ));
+
+        if let Some(retention_days) = self.retention_days {
+            transitions.push(LifecycleTransition::new(
+                project,
+                resource,
+                &add_days(end_date, retention_days + 1),
+                LifecycleAction::Remove,
+            ));
+        }
+
+        // a retention period equal to the grace period still blocks first
+        transitions.sort_by(|a, b| a.date.cmp(&b.date));
+        transitions
+    }
+
+    ///
+    /// Return the transition that the project should be in on `today`,
+    /// i.e. the latest one that has fallen due, or None if none have
+    ///
+    pub fn due(
+        &self,
+        project: &ProjectIdentifier,
+        resource: &str,
+        end_date: &Date,
+        today: &Date,
+    ) -> Option<LifecycleTransition> {
+        self.transitions(project, resource, end_date)
+            .into_iter()
+            .rfind(|t| t.date <= *today)
+    }
+
+    ///
+    /// Return the transitions of the project that have not yet been
+    /// carried out: the one that is due on `today` (unless it is `done`)
+    /// followed by all of those that fall due later
+    ///
+    pub fn upcoming(
+        &self,
+        project: &ProjectIdentifier,
+        resource: &str,
+        end_date: &Date,
+        today: &Date,
+        done: Option<&LifecycleTransition>,
+    ) -> Vec<LifecycleTransition> {
+        let mut upcoming = Vec::new();
+
+        if let Some(due) = self.due(project, resource, end_date, today) {
+            if Some(&due) != done {
+                upcoming.push(due);
+            }
+        }
+
+        upcoming.extend(
+            self.transitions(project, resource, end_date)
+                .into_iter()
+                .filter(|t| t.date > *today),
+        );
+
+        upcoming
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle_transitions() {
+        let policy = LifecyclePolicy::parse("7, 30,7", "14", "90")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(policy.warnings(), vec![30, 7]);
+
+        let project =
+            ProjectIdentifier::parse("ending.brics").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let end_date = Date::parse("2026-06-30").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let date = |d: &str| Date::parse(d).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let transitions = policy.transitions(&project, "cluster1", &end_date);
+
+        assert_eq!(
+            transitions
+                .iter()
+                .map(|t| (t.date().to_string(), t.action()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("2026-05-31".to_string(), LifecycleAction::Warn(30)),
+                ("2026-06-23".to_string(), LifecycleAction::Warn(7)),
+                ("2026-07-15".to_string(), LifecycleAction::Block),
+                ("2026-09-29".to_string(), LifecycleAction::Remove),
+            ]
+        );
+
+        assert_eq!(
+            policy.due(&project, "cluster1", &end_date, &date("2026-05-01")),
+            None
+        );
+        assert_eq!(
+            policy
+                .due(&project, "cluster1", &end_date, &date("2026-07-01"))
+                .map(|t| t.action()),
+            Some(LifecycleAction::Warn(7))
+        );
+        assert_eq!(
+            policy
+                .due(&project, "cluster1", &end_date, &date("2027-01-01"))
+                .map(|t| t.action()),
+            Some(LifecycleAction::Remove)
+        );
+
+        // the due transition is only listed until it has been carried out
+        let today = date("2026-07-20");
+        let done = policy.due(&project, "cluster1", &end_date, &today);
+
+        assert_eq!(
+            policy
+                .upcoming(&project, "cluster1", &end_date, &today, None)
+                .iter()
+                .map(|t| t.action())
+                .collect::<Vec<_>>(),
+            vec![LifecycleAction::Block, LifecycleAction::Remove]
+        );
+        assert_eq!(
+            policy
+                .upcoming(&project, "cluster1", &end_date, &today, done.as_ref())
+                .iter()
+                .map(|t| t.action())
+                .collect::<Vec<_>>(),
+            vec![LifecycleAction::Remove]
+        );
+
+        // projects cannot be removed before they are blocked
+        assert!(LifecyclePolicy::parse("30", "14", "7").is_err());
+        assert!(LifecyclePolicy::parse("0", "14", "").is_err());
+        assert!(LifecyclePolicy::parse("30", "soon", "").is_err());
+    }
+}
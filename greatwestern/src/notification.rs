@@ -44,6 +44,14 @@ pub enum NotificationEvent {
     AwardAccepted(ProjectIdentifier),
     /// An award was rejected by the receiving portal
     AwardRejected(ProjectIdentifier),
+    /// A project has used at least this percentage of its usage limit
+    LimitThresholdReached(ProjectIdentifier, u8),
+    /// A project has used all of its usage limit
+    LimitExhausted(ProjectIdentifier),
+    /// The award of a project ends in this many days
+    AwardEnding(ProjectIdentifier, u32),
+    /// The award of a project has ended, so the project has been blocked
+    AwardExpired(ProjectIdentifier),
     /// Infrastructure-only: used by the bridge agent to ask the portal to forward
     /// an inner notification southbound, stripping the bridge from the path.
     /// Analogous to `Instruction::Submit` for Jobs. Not accepted by `parse()`.
@@ -73,6 +81,37 @@ impl NotificationEvent {
             "award_changed" => Ok(Self::AwardChanged(ProjectIdentifier::parse(rest)?)),
             "award_accepted" => Ok(Self::AwardAccepted(ProjectIdentifier::parse(rest)?)),
             "award_rejected" => Ok(Self::AwardRejected(ProjectIdentifier::parse(rest)?)),
+            "limit_threshold_reached" => match rest.split_once(' ') {
+                Some((project, percent)) => {
+                    let percent = percent.trim().parse::<u8>().map_err(|e| {
+                        Error::Parse(format!("Invalid limit percentage '{}': {}", percent, e))
+                    })?;
+
+                    Ok(Self::LimitThresholdReached(
+                        ProjectIdentifier::parse(project)?,
+                        percent,
+                    ))
+                }
+                None => Err(Error::Parse(format!(
+                    "limit_threshold_reached needs a project and a percentage: '{}'",
+                    rest
+                ))),
+            },
+            "limit_exhausted" => Ok(Self::LimitExhausted(ProjectIdentifier::parse(rest)?)),
+            "award_ending" => match rest.split_once(' ') {
+                Some((project, days)) => {
+                    let days = days.trim().parse::<u32>().map_err(|e| {
+                        Error::Parse(format!("Invalid number of days '{}': {}", days, e))
+                    })?;
+
+                    Ok(Self::AwardEnding(ProjectIdentifier::parse(project)?, days))
+                }
+                None => Err(Error::Parse(format!(
+                    "award_ending needs a project and a number of days: '{}'",
+                    rest
+                ))),
+            },
+            "award_expired" => Ok(Self::AwardExpired(ProjectIdentifier::parse(rest)?)),
             "forward" => Err(Error::Parse(
                 "NotificationEvent::Forward is an infrastructure-only event and cannot be parsed from a string".to_owned(),
             )),
@@ -102,6 +141,12 @@ impl fmt::Display for NotificationEvent {
             Self::AwardChanged(p) => write!(f, "award_changed {}", p),
             Self::AwardAccepted(p) => write!(f, "award_accepted {}", p),
             Self::AwardRejected(p) => write!(f, "award_rejected {}", p),
+            Self::LimitThresholdReached(p, percent) => {
+                write!(f, "limit_threshold_reached {} {}", p, percent)
+            }
+            Self::LimitExhausted(p) => write!(f, "limit_exhausted {}", p),
+            Self::AwardEnding(p, days) => write!(f, "award_ending {} {}", p, days),
+            Self::AwardExpired(p) => write!(f, "award_expired {}", p),
             Self::Forward(n) => write!(f, "forward [{}]", n),
         }
     }
@@ -156,6 +201,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_limit_notification_events() {
+        for case in [
+            "limit_threshold_reached myproject.brics 75",
+            "limit_exhausted myproject.brics",
+        ] {
+            let event = NotificationEvent::parse(case).unwrap_or_else(|e| unreachable!("{:?}", e));
+            assert_eq!(event.to_string(), case);
+        }
+
+        assert!(NotificationEvent::parse("limit_threshold_reached myproject.brics").is_err());
+        assert!(NotificationEvent::parse("limit_threshold_reached myproject.brics 300").is_err());
+    }
+
+    #[test]
+    fn test_lifecycle_notification_events() {
+        for case in [
+            "award_ending myproject.brics 7",
+            "award_expired myproject.brics",
+        ] {
+            let event = NotificationEvent::parse(case).unwrap_or_else(|e| unreachable!("{:?}", e));
+            assert_eq!(event.to_string(), case);
+        }
+
+        assert!(NotificationEvent::parse("award_ending myproject.brics").is_err());
+        assert!(NotificationEvent::parse("award_ending myproject.brics soon").is_err());
+    }
+
     #[test]
     fn test_unknown_event_errors() {
         let result = NotificationEvent::parse("nonexistent_event foo.bar.brics");
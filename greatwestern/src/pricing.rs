@@ -0,0 +1,677 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use ts_rs::TS;
+
+use templemeads::Error;
+
+use crate::grammar::{Date, DateRange, ProjectIdentifier, UserIdentifier};
+use crate::usagereport::{split_partition, ProjectUsageReport, Usage};
+use templemeads::named::NamedType;
+
+impl NamedType for DailyProjectCostReport {
+    fn type_name() -> String {
+        "DailyProjectCostReport".to_string()
+    }
+}
+
+impl NamedType for ProjectCostReport {
+    fn type_name() -> String {
+        "ProjectCostReport".to_string()
+    }
+}
+
+/// The name of the "component" that prices a project's main (billed)
+/// usage, rather than one of the components of the usage reports
+pub const USAGE_COMPONENT: &str = "usage";
+
+fn default_currency() -> String {
+    "credits".to_string()
+}
+
+///
+/// The price of an hour of one component of usage (e.g. "gpu"),
+/// optionally only on one partition or node type, and only for
+/// usage within a period of dates
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rate {
+    /// The usage component that this prices, e.g. "cpu", "gpu",
+    /// "memory", "billing", or "usage" for the main usage
+    component: String,
+
+    /// The partition or node type that this rate applies to. Rates
+    /// without one apply to all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    node: Option<String>,
+
+    /// The first day that this rate applies (inclusive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from: Option<Date>,
+
+    /// The last day that this rate applies (inclusive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    to: Option<Date>,
+
+    /// The price per hour of usage of the component
+    price: f64,
+}
+
+impl Rate {
+    pub fn new(component: &str, price: f64) -> Result<Self, Error> {
+        let rate = Self {
+            component: component.trim().to_string(),
+            node: None,
+            from: None,
+            to: None,
+            price,
+        };
+
+        rate.validate()?;
+        Ok(rate)
+    }
+
+    pub fn with_node(mut self, node: &str) -> Self {
+        self.node = Some(node.trim().to_string());
+        self
+    }
+
+    pub fn with_period(mut self, from: Option<Date>, to: Option<Date>) -> Self {
+        self.from = from;
+        self.to = to;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.component.trim().is_empty() {
+            return Err(Error::Parse("A rate must name a component".to_string()));
+        }
+
+        if !self.price.is_finite() || self.price < 0.0 {
+            return Err(Error::Parse(format!(
+                "The price of '{}' must be zero or more, not {}",
+                self.component, self.price
+            )));
+        }
+
+        if let (Some(from), Some(to)) = (&self.from, &self.to) {
+            if from > to {
+                return Err(Error::Parse(format!(
+                    "The rate for '{}' runs from {} to the earlier {}",
+                    self.component, from, to
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn component(&self) -> &str {
+        &self.component
+    }
+
+    pub fn node(&self) -> Option<&str> {
+        self.node.as_deref()
+    }
+
+    pub fn price(&self) -> f64 {
+        self.price
+    }
+
+    ///
+    /// Return whether this rate prices the component on the passed
+    /// node type on the passed day
+    ///
+    pub fn applies_to(&self, component: &str, node: Option<&str>, date: &Date) -> bool {
+        if self.component != component {
+            return false;
+        }
+
+        if let Some(rate_node) = &self.node {
+            if node != Some(rate_node.as_str()) {
+                return false;
+            }
+        }
+
+        if let Some(from) = &self.from {
+            if date < from {
+                return false;
+            }
+        }
+
+        if let Some(to) = &self.to {
+            if date > to {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+///
+/// The set of rates used to turn usage into a cost, as loaded from
+/// a rates file
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateCard {
+    /// The unit that costs are given in, e.g. "credits" or "GBP"
+    #[serde(default = "default_currency")]
+    currency: String,
+
+    rates: Vec<Rate>,
+}
+
+impl Default for RateCard {
+    fn default() -> Self {
+        Self {
+            currency: default_currency(),
+            rates: Vec::new(),
+        }
+    }
+}
+
+impl RateCard {
+    pub fn new(currency: &str) -> Self {
+        Self {
+            currency: currency.trim().to_string(),
+            rates: Vec::new(),
+        }
+    }
+
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        let card: RateCard = serde_json::from_str(json)
+            .with_context(|| "Failed to deserialize RateCard from JSON".to_string())?;
+
+        card.validate()?;
+        Ok(card)
+    }
+
+    ///
+    /// Load the rate card from the JSON rates file at `path`
+    ///
+    pub fn load(path: &std::path::Path) -> Result<Self, Error> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read the rates file {}", path.display()))?;
+
+        RateCard::parse(&json)
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.currency.trim().is_empty() {
+            return Err(Error::Parse("The currency cannot be empty".to_string()));
+        }
+
+        for rate in &self.rates {
+            rate.validate()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn add_rate(&mut self, rate: Rate) -> Result<(), Error> {
+        rate.validate()?;
+        self.rates.push(rate);
+        Ok(())
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn rates(&self) -> &Vec<Rate> {
+        &self.rates
+    }
+
+    ///
+    /// Return the rate for the component on the node type on the passed
+    /// day. This is the first matching rate that names the node type,
+    /// or else the first matching rate that applies to all node types.
+    ///
+    pub fn rate_for(&self, component: &str, node: Option<&str>, date: &Date) -> Option<&Rate> {
+        self.rates
+            .iter()
+            .find(|r| r.node.is_some() && r.applies_to(component, node, date))
+            .or_else(|| {
+                self.rates
+                    .iter()
+                    .find(|r| r.applies_to(component, node, date))
+            })
+    }
+
+    ///
+    /// Cost the passed usage report. Usage that the report breaks down by
+    /// partition (see [`partition_component`]) is charged at the rates for
+    /// its partition. Any usage that is not broken down, e.g. in reports
+    /// from older agents, is charged at the rates for `default_node`.
+    /// Components without a rate are free.
+    ///
+    pub fn cost(
+        &self,
+        report: &ProjectUsageReport,
+        default_node: Option<&str>,
+    ) -> ProjectCostReport {
+        let mut costs = ProjectCostReport::new(&report.project(), &self.currency);
+        costs.users = report.user_mapping();
+
+        for date in report.dates() {
+            let Some(usage) = report.daily_report(&date) else {
+                continue;
+            };
+
+            let mut daily = DailyProjectCostReport::default();
+
+            // the usage of each (component, user) not yet charged to a partition
+            let mut unpartitioned: HashMap<(String, String), u64> = HashMap::new();
+
+            for user in usage.local_users() {
+                unpartitioned.insert(
+                    (USAGE_COMPONENT.to_string(), user.clone()),
+                    usage.usage(&user).seconds(),
+                );
+            }
+
+            let mut partitioned = Vec::new();
+
+            for name in usage.components() {
+                let component_usage = usage.get_component(&name);
+
+                match split_partition(&name) {
+                    (component, Some(partition)) => partitioned.push((
+                        component.to_string(),
+                        partition.to_string(),
+                        component_usage,
+                    )),
+                    (component, None) => {
+                        for user in component_usage.local_users() {
+                            unpartitioned.insert(
+                                (component.to_string(), user.clone()),
+                                component_usage.usage(&user).seconds(),
+                            );
+                        }
+                    }
+                }
+            }
+
+            for (component, partition, component_usage) in partitioned {
+                let rate = self.rate_for(&component, Some(&partition), &date);
+
+                for user in component_usage.local_users() {
+                    let used = component_usage.usage(&user);
+
+                    if let Some(rest) = unpartitioned.get_mut(&(component.clone(), user.clone())) {
+                        *rest = rest.saturating_sub(used.seconds());
+                    }
+
+                    if let Some(rate) = rate {
+                        daily.add_cost(&component, &user, used.hours() * rate.price());
+                    }
+                }
+            }
+
+            for ((component, user), seconds) in unpartitioned {
+                if seconds == 0 {
+                    continue;
+                }
+
+                if let Some(rate) = self.rate_for(&component, default_node, &date) {
+                    daily.add_cost(
+                        &component,
+                        &user,
+                        Usage::new(seconds).hours() * rate.price(),
+                    );
+                }
+            }
+
+            costs.reports.insert(date, daily);
+        }
+
+        costs
+    }
+}
+
+///
+/// The cost of a project's usage on a single day, per (local) user
+/// and per component
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct DailyProjectCostReport {
+    users: HashMap<String, f64>,
+    components: HashMap<String, f64>,
+}
+
+impl DailyProjectCostReport {
+    pub fn add_cost(&mut self, component: &str, local_user: &str, cost: f64) {
+        *self.users.entry(local_user.to_string()).or_default() += cost;
+        *self.components.entry(component.to_string()).or_default() += cost;
+    }
+
+    pub fn cost(&self, local_user: &str) -> f64 {
+        self.users.get(local_user).cloned().unwrap_or_default()
+    }
+
+    pub fn component_cost(&self, component: &str) -> f64 {
+        self.components.get(component).cloned().unwrap_or_default()
+    }
+
+    pub fn local_users(&self) -> Vec<String> {
+        let mut users: Vec<String> = self.users.keys().cloned().collect();
+        users.sort();
+        users
+    }
+
+    pub fn components(&self) -> Vec<String> {
+        let mut components: Vec<String> = self.components.keys().cloned().collect();
+        components.sort();
+        components
+    }
+
+    pub fn total_cost(&self) -> f64 {
+        self.users.values().sum()
+    }
+}
+
+///
+/// The cost of a project's usage over a range of days, as calculated
+/// from its usage report by a RateCard
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ProjectCostReport {
+    #[ts(as = "String")]
+    project: ProjectIdentifier,
+    currency: String,
+    #[ts(as = "HashMap<String, DailyProjectCostReport>")]
+    reports: HashMap<Date, DailyProjectCostReport>,
+    #[ts(as = "HashMap<String, String>")]
+    users: HashMap<UserIdentifier, String>,
+}
+
+impl std::fmt::Display for ProjectCostReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{}", self.project())?;
+
+        for date in self.dates() {
+            writeln!(
+                f,
+                "{}: {:.2} {}",
+                date,
+                self.daily_cost(&date),
+                self.currency
+            )?;
+        }
+
+        let mut users = self.users();
+        users.sort_by_key(|u| u.to_string());
+
+        for user in users {
+            writeln!(f, "{}: {:.2} {}", user, self.cost(&user), self.currency)?;
+        }
+
+        let unmapped = self.unmapped_cost();
+
+        if unmapped > 0.0 {
+            writeln!(f, "unmapped: {:.2} {}", unmapped, self.currency)?;
+        }
+
+        write!(f, "Total: {:.2} {}", self.total_cost(), self.currency)
+    }
+}
+
+impl ProjectCostReport {
+    pub fn new(project: &ProjectIdentifier, currency: &str) -> Self {
+        Self {
+            project: project.clone(),
+            currency: currency.to_string(),
+            reports: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string(self)
+            .with_context(|| "Failed to serialize ProjectCostReport to JSON".to_string())
+            .map_err(Error::from)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .with_context(|| "Failed to deserialize ProjectCostReport from JSON".to_string())
+            .map_err(Error::from)
+    }
+
+    pub fn project(&self) -> ProjectIdentifier {
+        self.project.clone()
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn dates(&self) -> Vec<Date> {
+        let mut dates: Vec<Date> = self.reports.keys().cloned().collect();
+        dates.sort();
+        dates
+    }
+
+    pub fn users(&self) -> Vec<UserIdentifier> {
+        self.users.keys().cloned().collect()
+    }
+
+    pub fn daily_report(&self, date: &Date) -> Option<DailyProjectCostReport> {
+        self.reports.get(date).cloned()
+    }
+
+    /// Return the total cost of the passed day
+    pub fn daily_cost(&self, date: &Date) -> f64 {
+        self.reports
+            .get(date)
+            .map(|r| r.total_cost())
+            .unwrap_or_default()
+    }
+
+    /// Return the total cost of each day, in date order
+    pub fn daily_costs(&self) -> Vec<(Date, f64)> {
+        self.dates()
+            .into_iter()
+            .map(|date| {
+                let cost = self.daily_cost(&date);
+                (date, cost)
+            })
+            .collect()
+    }
+
+    /// Return the total cost of the passed user over all days
+    pub fn cost(&self, user: &UserIdentifier) -> f64 {
+        match self.users.get(user) {
+            Some(local_user) => self.reports.values().map(|r| r.cost(local_user)).sum(),
+            None => 0.0,
+        }
+    }
+
+    /// Return the total cost of each user over all days
+    pub fn user_costs(&self) -> HashMap<UserIdentifier, f64> {
+        self.users
+            .keys()
+            .map(|user| (user.clone(), self.cost(user)))
+            .collect()
+    }
+
+    /// Return the cost of usage by local users who are not mapped to
+    /// a portal user (including unattributed usage)
+    pub fn unmapped_cost(&self) -> f64 {
+        self.total_cost() - self.users.keys().map(|u| self.cost(u)).sum::<f64>()
+    }
+
+    /// Return the total cost of each component over all days
+    pub fn component_costs(&self) -> HashMap<String, f64> {
+        let mut costs: HashMap<String, f64> = HashMap::new();
+
+        for report in self.reports.values() {
+            for (component, cost) in &report.components {
+                *costs.entry(component.clone()).or_default() += cost;
+            }
+        }
+
+        costs
+    }
+
+    pub fn total_cost(&self) -> f64 {
+        self.reports.values().map(|r| r.total_cost()).sum()
+    }
+
+    /// Return a copy of this report containing only days that fall within
+    /// `range` (inclusive on both ends).
+    pub fn filter(&self, range: &DateRange) -> Self {
+        let reports = self
+            .reports
+            .iter()
+            .filter(|(date, _)| *date >= range.start_date() && *date <= range.end_date())
+            .map(|(date, report)| (date.clone(), report.clone()))
+            .collect();
+
+        Self {
+            project: self.project.clone(),
+            currency: self.currency.clone(),
+            reports,
+            users: self.users.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::UserMapping;
+    use crate::usagereport::{partition_component, DailyProjectUsageReport};
+
+    #[test]
+    fn test_cost_report() {
+        let project =
+            ProjectIdentifier::parse("project.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let mapping = UserMapping::parse("alice.project.portal:alice:project")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let day1 = Date::parse("2026-03-01").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let day2 = Date::parse("2026-04-01").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let mut daily = DailyProjectUsageReport::default();
+        daily.add_usage("alice", Usage::from_hours(10.0));
+        daily.add_usage("bob", Usage::from_hours(2.0));
+        daily.add_component_usage("gpu", "alice", Usage::from_hours(4.0));
+
+        let mut usage = ProjectUsageReport::new(&project);
+        usage.set_report(&day1, &daily);
+        usage.set_report(&day2, &daily);
+        usage
+            .add_mapping(&mapping)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let card = RateCard::parse(
+            r#"{
+                "currency": "credits",
+                "rates": [
+                    {"component": "usage", "price": 1.0},
+                    {"component": "gpu", "price": 2.0, "to": "2026-03-31"},
+                    {"component": "gpu", "price": 3.0, "from": "2026-04-01"},
+                    {"component": "gpu", "node": "a100", "price": 5.0}
+                ]
+            }"#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let costs = card.cost(&usage, None);
+
+        // 12 hours of usage each day, plus 4 GPU hours at 2 then 3
+        assert_eq!(costs.daily_cost(&day1), 12.0 + 8.0);
+        assert_eq!(costs.daily_cost(&day2), 12.0 + 12.0);
+        assert_eq!(costs.total_cost(), 44.0);
+        assert_eq!(costs.cost(mapping.user()), 20.0 + 8.0 + 12.0);
+        assert_eq!(costs.unmapped_cost(), 4.0);
+        assert_eq!(costs.component_costs().get("gpu"), Some(&20.0));
+
+        // rates for the default node type are preferred over general ones
+        let costs = card.cost(&usage, Some("a100"));
+        assert_eq!(costs.daily_cost(&day1), 12.0 + 20.0);
+
+        let costs = ProjectCostReport::from_json(
+            &costs.to_json().unwrap_or_else(|e| unreachable!("{:?}", e)),
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(costs.total_cost(), 64.0);
+
+        // negative prices and backwards periods are refused
+        assert!(RateCard::parse(r#"{"rates": [{"component": "cpu", "price": -1}]}"#).is_err());
+        assert!(RateCard::parse(
+            r#"{"rates": [{"component": "cpu", "price": 1, "from": "2026-02-01", "to": "2026-01-01"}]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_each_partition_is_charged_at_its_own_rate() {
+        let project =
+            ProjectIdentifier::parse("project.portal").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let day = Date::parse("2026-03-01").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        // alice used 6 hours on "cpu" and 4 on "gpu", of which 4 were GPU
+        // hours on "gpu". bob's 2 hours are not broken down by partition.
+        let mut daily = DailyProjectUsageReport::default();
+        daily.add_usage("alice", Usage::from_hours(10.0));
+        daily.add_usage("bob", Usage::from_hours(2.0));
+        daily.add_component_usage(
+            &partition_component(USAGE_COMPONENT, "cpu"),
+            "alice",
+            Usage::from_hours(6.0),
+        );
+        daily.add_component_usage(
+            &partition_component(USAGE_COMPONENT, "gpu"),
+            "alice",
+            Usage::from_hours(4.0),
+        );
+        daily.add_component_usage("gpu", "alice", Usage::from_hours(4.0));
+        daily.add_component_usage(
+            &partition_component("gpu", "gpu"),
+            "alice",
+            Usage::from_hours(4.0),
+        );
+
+        let mut usage = ProjectUsageReport::new(&project);
+        usage.set_report(&day, &daily);
+
+        let card = RateCard::parse(
+            r#"{
+                "rates": [
+                    {"component": "usage", "price": 1.0},
+                    {"component": "usage", "node": "gpu", "price": 10.0},
+                    {"component": "gpu", "node": "gpu", "price": 5.0}
+                ]
+            }"#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let costs = card.cost(&usage, None);
+        let daily = costs
+            .daily_report(&day)
+            .unwrap_or_else(|| unreachable!("no costs for {}", day));
+
+        // 6 hours at 1 on "cpu", and 4 hours at 10 on "gpu", plus 4 GPU
+        // hours at 5 on "gpu"
+        assert_eq!(daily.cost("alice"), 6.0 + 40.0 + 20.0);
+
+        // bob's usage is charged at the general rate
+        assert_eq!(daily.cost("bob"), 2.0);
+
+        assert_eq!(daily.component_cost(USAGE_COMPONENT), 6.0 + 40.0 + 2.0);
+        assert_eq!(daily.component_cost("gpu"), 20.0);
+        assert_eq!(daily.components(), vec!["gpu", USAGE_COMPONENT]);
+
+        // the default node type only applies to usage without a partition
+        let costs = card.cost(&usage, Some("gpu"));
+        assert_eq!(costs.daily_cost(&day), 66.0 + 20.0);
+    }
+}
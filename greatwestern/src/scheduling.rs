@@ -0,0 +1,953 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+use templemeads::Error;
+
+use crate::grammar::UserMapping;
+use crate::usagereport::Usage;
+
+use templemeads::named::NamedType;
+
+impl NamedType for SchedulingPolicy {
+    fn type_name() -> String {
+        "SchedulingPolicy".to_string()
+    }
+}
+
+impl NamedType for Reservation {
+    fn type_name() -> String {
+        "Reservation".to_string()
+    }
+}
+
+impl NamedType for UserLimit {
+    fn type_name() -> String {
+        "UserLimit".to_string()
+    }
+}
+
+impl NamedType for ProjectLimit {
+    fn type_name() -> String {
+        "ProjectLimit".to_string()
+    }
+}
+
+/// The most QoS or partitions that a policy can name
+const MAX_NAMES: usize = 64;
+
+///
+/// Validate the name of a QoS or partition, returning it trimmed.
+/// Names are sent to sacctmgr as part of a single argument, so only
+/// letters, digits, '-', '_' and '.' are allowed.
+///
+fn validate_name(name: &str, kind: &str) -> Result<String, Error> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err(Error::Parse(format!("A {} name cannot be empty", kind)));
+    }
+
+    if name.len() > 64 {
+        return Err(Error::Parse(format!(
+            "The {} name '{}' is too long",
+            kind, name
+        )));
+    }
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        return Err(Error::Parse(format!(
+            "The {} name '{}' contains invalid characters",
+            kind, name
+        )));
+    }
+
+    Ok(name.to_string())
+}
+
+///
+/// Validate a list of QoS or partition names, returning them trimmed
+/// and with any duplicates removed (keeping the first)
+///
+fn validate_names(names: &[String], kind: &str) -> Result<Vec<String>, Error> {
+    if names.len() > MAX_NAMES {
+        return Err(Error::Parse(format!(
+            "A scheduling policy can name at most {} {}s",
+            MAX_NAMES, kind
+        )));
+    }
+
+    let mut validated: Vec<String> = Vec::new();
+
+    for name in names {
+        let name = validate_name(name, kind)?;
+
+        if !validated.contains(&name) {
+            validated.push(name);
+        }
+    }
+
+    Ok(validated)
+}
+
+/// The scheduling entitlements of a project - which QoS its jobs may
+/// use, which of those is the default, and which partitions its members
+/// can submit to. Scheduler agents apply this to the project's account.
+///
+/// As for UserProfile, every field is an "option". In a
+/// `set_scheduling_policy` the policy replaces what the account held,
+/// so a missing field is cleared (the QoS go back to those inherited
+/// from the parent account, and the members lose any partitions).
+///
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct SchedulingPolicy {
+    /// The QoS that jobs of the project may use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    qos: Option<Vec<String>>,
+
+    /// The QoS that jobs use if they don't ask for one. This must
+    /// be one of `qos`, if that is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    default_qos: Option<String>,
+
+    /// The partitions that members of the project can submit to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    partitions: Option<Vec<String>>,
+}
+
+impl SchedulingPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Parse a policy from JSON. Every name is validated, as they are
+    /// passed on to the scheduler's command line tools.
+    ///
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        SchedulingPolicy::from_json(json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let mut policy: SchedulingPolicy =
+            serde_json::from_str(json).map_err(|e| Error::Parse(e.to_string()))?;
+
+        // go through the setters so that the names are trimmed and de-duplicated
+        if let Some(qos) = policy.qos.take() {
+            policy.set_qos(qos)?;
+        }
+
+        if let Some(default_qos) = policy.default_qos.take() {
+            policy.set_default_qos(&default_qos)?;
+        }
+
+        if let Some(partitions) = policy.partitions.take() {
+            policy.set_partitions(partitions)?;
+        }
+
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    ///
+    /// Check that every name is valid, and that the default QoS is one
+    /// of the allowed QoS
+    ///
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(qos) = &self.qos {
+            validate_names(qos, "QoS")?;
+        }
+
+        if let Some(default_qos) = &self.default_qos {
+            let default_qos = validate_name(default_qos, "QoS")?;
+
+            if let Some(qos) = &self.qos {
+                if !qos.iter().any(|q| q.trim() == default_qos) {
+                    return Err(Error::Parse(format!(
+                        "The default QoS '{}' is not one of the allowed QoS {:?}",
+                        default_qos, qos
+                    )));
+                }
+            }
+        }
+
+        if let Some(partitions) = &self.partitions {
+            validate_names(partitions, "partition")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.qos.is_none() && self.default_qos.is_none() && self.partitions.is_none()
+    }
+
+    pub fn qos(&self) -> Option<Vec<String>> {
+        self.qos.clone()
+    }
+
+    pub fn set_qos(&mut self, qos: Vec<String>) -> Result<(), Error> {
+        self.qos = Some(validate_names(&qos, "QoS")?);
+        Ok(())
+    }
+
+    pub fn clear_qos(&mut self) {
+        self.qos = None;
+    }
+
+    pub fn default_qos(&self) -> Option<String> {
+        self.default_qos.clone()
+    }
+
+    ///
+    /// Set the default QoS. Note that this is not checked against the
+    /// allowed QoS until the policy is validated, so that these can be
+    /// set in either order.
+    ///
+    pub fn set_default_qos(&mut self, qos: &str) -> Result<(), Error> {
+        self.default_qos = Some(validate_name(qos, "QoS")?);
+        Ok(())
+    }
+
+    pub fn clear_default_qos(&mut self) {
+        self.default_qos = None;
+    }
+
+    pub fn partitions(&self) -> Option<Vec<String>> {
+        self.partitions.clone()
+    }
+
+    pub fn set_partitions(&mut self, partitions: Vec<String>) -> Result<(), Error> {
+        self.partitions = Some(validate_names(&partitions, "partition")?);
+        Ok(())
+    }
+
+    pub fn clear_partitions(&mut self) {
+        self.partitions = None;
+    }
+}
+
+impl std::fmt::Display for SchedulingPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+///
+/// Validate the name of a node, or a range of nodes in Slurm's hostlist
+/// format (e.g. "node[001-004]" or "gpu[01-04,07]"), returning it trimmed.
+/// Commas are only allowed within brackets, as outside them they would
+/// separate one node from the next.
+///
+fn validate_node_name(name: &str) -> Result<String, Error> {
+    let name = name.trim();
+
+    if name.is_empty() || name.len() > 256 {
+        return Err(Error::Parse(format!("Invalid node name '{}'", name)));
+    }
+
+    let mut in_brackets = false;
+    let mut previous = None;
+
+    for c in name.chars() {
+        let valid = match c {
+            '[' if !in_brackets => {
+                in_brackets = true;
+                true
+            }
+            ']' if in_brackets => {
+                in_brackets = false;
+                // no empty ranges, and no trailing separators within them
+                !matches!(previous, Some('[') | Some(',') | Some('-'))
+            }
+            ',' => in_brackets && !matches!(previous, Some('[') | Some(',')),
+            c => c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.',
+        };
+
+        if !valid {
+            return Err(Error::Parse(format!(
+                "The node name '{}' contains invalid characters",
+                name
+            )));
+        }
+
+        previous = Some(c);
+    }
+
+    if in_brackets {
+        return Err(Error::Parse(format!(
+            "The node name '{}' has an unclosed '['",
+            name
+        )));
+    }
+
+    Ok(name.to_string())
+}
+
+///
+/// Validate the name of a reservation, returning it trimmed. This is
+/// the name within the project - schedulers prefix it with the
+/// project's account, so that projects cannot see or change each
+/// other's reservations.
+///
+pub fn validate_reservation_name(name: &str) -> Result<String, Error> {
+    validate_name(name, "reservation")
+}
+
+/// A reservation of nodes for the members of a project, e.g. for a
+/// workshop or a deadline. The nodes are given either by name or by
+/// number (in which case the scheduler chooses them). Reservations
+/// read back from the scheduler give both, and if both are given then
+/// the named nodes are reserved.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct Reservation {
+    /// The name of the reservation, unique within the project
+    name: String,
+
+    /// The nodes to reserve (names or hostlist ranges)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    nodes: Option<Vec<String>>,
+
+    /// The number of nodes to reserve
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    node_count: Option<u32>,
+
+    /// When the reservation starts (UTC)
+    #[ts(as = "String")]
+    start_time: DateTime<Utc>,
+
+    /// When the reservation ends (UTC)
+    #[ts(as = "String")]
+    end_time: DateTime<Utc>,
+
+    /// The partition to reserve the nodes from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    partition: Option<String>,
+}
+
+impl Reservation {
+    pub fn new(
+        name: &str,
+        start_time: &DateTime<Utc>,
+        end_time: &DateTime<Utc>,
+    ) -> Result<Self, Error> {
+        let reservation = Self {
+            name: validate_reservation_name(name)?,
+            nodes: None,
+            node_count: None,
+            start_time: *start_time,
+            end_time: *end_time,
+            partition: None,
+        };
+
+        reservation.validate_times()?;
+
+        Ok(reservation)
+    }
+
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        Reservation::from_json(json)
+    }
+
+    ///
+    /// Parse a reservation from JSON. The names are validated, as they
+    /// are passed on to the scheduler's command line tools.
+    ///
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let mut reservation: Reservation =
+            serde_json::from_str(json).map_err(|e| Error::Parse(e.to_string()))?;
+
+        reservation.name = validate_reservation_name(&reservation.name)?;
+
+        if let Some(nodes) = reservation.nodes.take() {
+            let node_count = reservation.node_count;
+            reservation.set_nodes(nodes)?;
+            reservation.node_count = node_count;
+        }
+
+        if let Some(partition) = reservation.partition.take() {
+            reservation.set_partition(&partition)?;
+        }
+
+        reservation.validate()?;
+        Ok(reservation)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn validate_times(&self) -> Result<(), Error> {
+        match self.end_time > self.start_time {
+            true => Ok(()),
+            false => Err(Error::Parse(format!(
+                "The reservation '{}' must end after it starts",
+                self.name
+            ))),
+        }
+    }
+
+    ///
+    /// Check that the names are valid, that the nodes are given (by
+    /// name or number), and that the reservation ends after it starts
+    ///
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_reservation_name(&self.name)?;
+
+        if let Some(nodes) = &self.nodes {
+            if nodes.is_empty() || nodes.len() > MAX_NAMES {
+                return Err(Error::Parse(format!(
+                    "A reservation must name from 1 to {} nodes",
+                    MAX_NAMES
+                )));
+            }
+
+            for node in nodes {
+                validate_node_name(node)?;
+            }
+        }
+
+        match (&self.nodes, self.node_count) {
+            (None, None) => {
+                return Err(Error::Parse(format!(
+                    "The reservation '{}' needs either nodes or a node_count",
+                    self.name
+                )))
+            }
+            (_, Some(0)) => {
+                return Err(Error::Parse(format!(
+                    "The reservation '{}' must reserve at least one node",
+                    self.name
+                )))
+            }
+            _ => {}
+        }
+
+        if let Some(partition) = &self.partition {
+            validate_name(partition, "partition")?;
+        }
+
+        self.validate_times()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn nodes(&self) -> Option<Vec<String>> {
+        self.nodes.clone()
+    }
+
+    ///
+    /// Reserve these nodes. This clears any node count, so that the
+    /// scheduler reserves exactly these nodes.
+    ///
+    pub fn set_nodes(&mut self, nodes: Vec<String>) -> Result<(), Error> {
+        let mut validated = Vec::new();
+
+        for node in nodes {
+            let node = validate_node_name(&node)?;
+
+            if !validated.contains(&node) {
+                validated.push(node);
+            }
+        }
+
+        self.nodes = Some(validated);
+        self.node_count = None;
+        Ok(())
+    }
+
+    pub fn node_count(&self) -> Option<u32> {
+        self.node_count
+    }
+
+    ///
+    /// Reserve this number of nodes, chosen by the scheduler. This
+    /// clears any named nodes.
+    ///
+    pub fn set_node_count(&mut self, count: u32) {
+        self.node_count = Some(count);
+        self.nodes = None;
+    }
+
+    pub fn start_time(&self) -> &DateTime<Utc> {
+        &self.start_time
+    }
+
+    pub fn end_time(&self) -> &DateTime<Utc> {
+        &self.end_time
+    }
+
+    pub fn set_times(
+        &mut self,
+        start_time: &DateTime<Utc>,
+        end_time: &DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let mut updated = self.clone();
+        updated.start_time = *start_time;
+        updated.end_time = *end_time;
+        updated.validate_times()?;
+
+        *self = updated;
+        Ok(())
+    }
+
+    pub fn partition(&self) -> Option<String> {
+        self.partition.clone()
+    }
+
+    pub fn set_partition(&mut self, partition: &str) -> Result<(), Error> {
+        self.partition = Some(validate_name(partition, "partition")?);
+        Ok(())
+    }
+
+    pub fn clear_partition(&mut self) {
+        self.partition = None;
+    }
+}
+
+impl std::fmt::Display for Reservation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+///
+/// Validate the name of a trackable resource (e.g. "cpu", "mem" or
+/// "gres/gpu"), returning it trimmed and in lower case
+///
+fn validate_tres_name(name: &str) -> Result<String, Error> {
+    let name = name.trim().to_ascii_lowercase();
+
+    if name.is_empty() || name.len() > 64 {
+        return Err(Error::Parse(format!("Invalid TRES name '{}'", name)));
+    }
+
+    if !name.chars().all(|c| {
+        c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '/' || c == ':'
+    }) {
+        return Err(Error::Parse(format!(
+            "The TRES name '{}' contains invalid characters",
+            name
+        )));
+    }
+
+    Ok(name)
+}
+
+/// The limits on one member of a project, within the project's own
+/// limit, so that one member cannot use up the whole allocation.
+/// Scheduler agents apply these to the user's association with the
+/// project's account.
+///
+/// As for SchedulingPolicy, every field is an "option". In a
+/// `set_user_limit` the limit replaces what the user held, so a missing
+/// field is cleared.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct UserLimit {
+    /// The most that the user can use, in the same units as the
+    /// project's limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    usage: Option<Usage>,
+
+    /// The most jobs that the user can have running at once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    max_jobs: Option<u32>,
+
+    /// The most jobs that the user can have running or queued at once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    max_submit_jobs: Option<u32>,
+
+    /// The most of each trackable resource (e.g. "cpu", "mem" or
+    /// "gres/gpu") that the user's running jobs can hold at once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    grp_tres: Option<BTreeMap<String, u64>>,
+}
+
+impl UserLimit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        UserLimit::from_json(json)
+    }
+
+    ///
+    /// Parse a user limit from JSON. The TRES names are validated, as
+    /// they are passed on to the scheduler's command line tools.
+    ///
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let mut limit: UserLimit =
+            serde_json::from_str(json).map_err(|e| Error::Parse(e.to_string()))?;
+
+        if let Some(grp_tres) = limit.grp_tres.take() {
+            limit.set_grp_tres(grp_tres)?;
+        }
+
+        Ok(limit)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    ///
+    /// Return whether or not no limits are set, i.e. the user can use
+    /// as much as the project allows
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.usage.is_none()
+            && self.max_jobs.is_none()
+            && self.max_submit_jobs.is_none()
+            && self.grp_tres.is_none()
+    }
+
+    pub fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+
+    pub fn set_usage(&mut self, usage: Usage) {
+        self.usage = Some(usage);
+    }
+
+    pub fn clear_usage(&mut self) {
+        self.usage = None;
+    }
+
+    pub fn max_jobs(&self) -> Option<u32> {
+        self.max_jobs
+    }
+
+    pub fn set_max_jobs(&mut self, max_jobs: u32) {
+        self.max_jobs = Some(max_jobs);
+    }
+
+    pub fn clear_max_jobs(&mut self) {
+        self.max_jobs = None;
+    }
+
+    pub fn max_submit_jobs(&self) -> Option<u32> {
+        self.max_submit_jobs
+    }
+
+    pub fn set_max_submit_jobs(&mut self, max_submit_jobs: u32) {
+        self.max_submit_jobs = Some(max_submit_jobs);
+    }
+
+    pub fn clear_max_submit_jobs(&mut self) {
+        self.max_submit_jobs = None;
+    }
+
+    pub fn grp_tres(&self) -> Option<BTreeMap<String, u64>> {
+        self.grp_tres.clone()
+    }
+
+    ///
+    /// Set the most of each trackable resource that the user can hold
+    /// at once. An empty map clears this limit.
+    ///
+    pub fn set_grp_tres(&mut self, grp_tres: BTreeMap<String, u64>) -> Result<(), Error> {
+        if grp_tres.len() > MAX_NAMES {
+            return Err(Error::Parse(format!(
+                "A user limit can name at most {} TRES",
+                MAX_NAMES
+            )));
+        }
+
+        let mut validated = BTreeMap::new();
+
+        for (name, count) in grp_tres {
+            validated.insert(validate_tres_name(&name)?, count);
+        }
+
+        self.grp_tres = match validated.is_empty() {
+            true => None,
+            false => Some(validated),
+        };
+
+        Ok(())
+    }
+
+    pub fn clear_grp_tres(&mut self) {
+        self.grp_tres = None;
+    }
+}
+
+impl std::fmt::Display for UserLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+/// The limit of a project, together with the limits of those of its
+/// members that have their own
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ProjectLimit {
+    /// The limit of the project as a whole, as returned by `get_limit`
+    limit: Usage,
+
+    /// The limits of the members that have them, keyed by the user's
+    /// identifier (or, until the cluster has mapped them, their
+    /// local username)
+    #[serde(default)]
+    users: BTreeMap<String, UserLimit>,
+}
+
+impl ProjectLimit {
+    pub fn new(limit: &Usage) -> Self {
+        Self {
+            limit: *limit,
+            users: BTreeMap::new(),
+        }
+    }
+
+    pub fn limit(&self) -> &Usage {
+        &self.limit
+    }
+
+    pub fn users(&self) -> &BTreeMap<String, UserLimit> {
+        &self.users
+    }
+
+    ///
+    /// Add the limit of `user`, which is skipped if it has no limits
+    ///
+    pub fn set_user_limit(&mut self, user: &str, limit: &UserLimit) {
+        match limit.is_empty() {
+            true => self.users.remove(user),
+            false => self.users.insert(user.to_string(), limit.clone()),
+        };
+    }
+
+    ///
+    /// Key the limits of the users in `mappings` by their user
+    /// identifiers, rather than their local usernames
+    ///
+    pub fn add_mappings(&mut self, mappings: &[UserMapping]) {
+        for mapping in mappings {
+            if let Some(limit) = self.users.remove(mapping.local_user().as_str()) {
+                self.users.insert(mapping.user().to_string(), limit);
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ProjectLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} users with their own limits)",
+            self.limit,
+            self.users.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduling_policy_is_validated() {
+        let mut policy = SchedulingPolicy::new();
+        assert!(policy.is_empty());
+
+        policy
+            .set_qos(vec![
+                " gpu ".to_string(),
+                "normal".to_string(),
+                "gpu".to_string(),
+            ])
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        policy
+            .set_default_qos("normal")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        policy
+            .set_partitions(vec!["gpu-a100".to_string()])
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            policy.qos(),
+            Some(vec!["gpu".to_string(), "normal".to_string()])
+        );
+
+        let parsed =
+            SchedulingPolicy::parse(&policy.to_json()).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(parsed, policy);
+
+        // names that could smuggle extra sacctmgr arguments are refused
+        assert!(policy.set_qos(vec!["gpu where".to_string()]).is_err());
+        assert!(policy.set_partitions(vec!["gpu,all".to_string()]).is_err());
+        assert!(policy.set_default_qos("").is_err());
+
+        // the default must be one of the allowed QoS
+        assert!(SchedulingPolicy::parse(r#"{"qos": ["gpu"], "default_qos": "normal"}"#).is_err());
+        assert!(SchedulingPolicy::parse(r#"{"default_qos": "normal"}"#).is_ok());
+        assert!(SchedulingPolicy::parse(r#"{"partitions": ["a=b"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_reservation_is_validated() {
+        let reservation = Reservation::parse(
+            r#"{"name": "workshop", "nodes": ["gpu[01-04]", "gpu05"],
+                "start_time": "2026-11-02T09:00:00Z", "end_time": "2026-11-02T17:00:00Z",
+                "partition": "gpu"}"#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(reservation.name(), "workshop");
+        assert_eq!(
+            reservation.nodes(),
+            Some(vec!["gpu[01-04]".to_string(), "gpu05".to_string()])
+        );
+        assert_eq!(reservation.partition(), Some("gpu".to_string()));
+
+        let parsed =
+            Reservation::parse(&reservation.to_json()).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(parsed, reservation);
+
+        let mut counted = reservation.clone();
+        counted.set_node_count(2);
+        assert_eq!(counted.nodes(), None);
+
+        // reversed times, missing nodes, and names that could smuggle
+        // extra scontrol arguments are all refused
+        assert!(counted
+            .set_times(reservation.end_time(), reservation.start_time())
+            .is_err());
+        assert!(Reservation::parse(
+            r#"{"name": "workshop", "start_time": "2026-11-02T09:00:00Z",
+                "end_time": "2026-11-02T17:00:00Z"}"#
+        )
+        .is_err());
+        assert!(Reservation::parse(
+            r#"{"name": "a b", "node_count": 1, "start_time": "2026-11-02T09:00:00Z",
+                "end_time": "2026-11-02T17:00:00Z"}"#
+        )
+        .is_err());
+        assert!(counted
+            .set_nodes(vec!["gpu01 Flags=MAINT".to_string()])
+            .is_err());
+    }
+
+    #[test]
+    fn test_node_names_are_hostlists() {
+        for valid in [
+            "gpu01",
+            "gpu[01-04]",
+            "gpu[01-04,07]",
+            "gpu[01,03]-ib",
+            "rack[1-2]-node[01-08,10]",
+        ] {
+            assert_eq!(
+                validate_node_name(valid).unwrap_or_else(|e| unreachable!("{:?}", e)),
+                valid
+            );
+        }
+
+        for invalid in [
+            "",
+            "gpu01,gpu02",
+            "gpu[01-04",
+            "gpu01]",
+            "gpu[[01]]",
+            "gpu[]",
+            "gpu[01,]",
+            "gpu[,01]",
+            "gpu[01,,02]",
+            "gpu[01-]",
+            "gpu[01 02]",
+        ] {
+            assert!(validate_node_name(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_user_limit_is_validated() {
+        let limit = UserLimit::parse(
+            r#"{"usage": {"seconds": 36000}, "max_jobs": 4,
+                "grp_tres": {"CPU": 128, "gres/gpu": 2}}"#,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(limit.usage(), Some(Usage::new(36000)));
+        assert_eq!(limit.max_jobs(), Some(4));
+        assert_eq!(limit.max_submit_jobs(), None);
+        assert_eq!(
+            limit.grp_tres(),
+            Some(BTreeMap::from([
+                ("cpu".to_string(), 128),
+                ("gres/gpu".to_string(), 2)
+            ]))
+        );
+
+        let parsed = UserLimit::parse(&limit.to_json()).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(parsed, limit);
+
+        assert!(UserLimit::parse("{}")
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+            .is_empty());
+
+        // TRES names are passed to sacctmgr, so cannot add arguments
+        assert!(UserLimit::parse(r#"{"grp_tres": {"cpu=1 MaxJobs": 1}}"#).is_err());
+    }
+
+    #[test]
+    fn test_project_limit_holds_the_capped_members() {
+        let mut limit = ProjectLimit::new(&Usage::new(3600));
+
+        let mut user = UserLimit::new();
+        user.set_max_jobs(2);
+
+        // only the members with limits of their own are listed
+        limit.set_user_limit("alice", &user);
+        limit.set_user_limit("bob", &UserLimit::new());
+        assert_eq!(limit.users().len(), 1);
+
+        let json = serde_json::to_string(&limit).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(
+            json,
+            r#"{"limit":{"seconds":3600},"users":{"alice":{"max_jobs":2}}}"#
+        );
+
+        // ...and a limit that is removed takes them off the list
+        let mut unlimited = limit.clone();
+        unlimited.set_user_limit("alice", &UserLimit::new());
+        assert!(unlimited.users().is_empty());
+
+        let parsed: ProjectLimit =
+            serde_json::from_str(&json).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(parsed, limit);
+    }
+}
@@ -39,6 +39,18 @@ impl NamedType for Volume {
     }
 }
 
+impl NamedType for RecycleVersion {
+    fn type_name() -> String {
+        "RecycleVersion".to_string()
+    }
+}
+
+impl NamedType for RecycledDir {
+    fn type_name() -> String {
+        "RecycledDir".to_string()
+    }
+}
+
 /// Represents a quantity of storage in bytes
 #[derive(Copy, Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct StorageSize {
@@ -351,16 +363,21 @@ impl std::fmt::Display for StorageUsage {
     }
 }
 
-/// Represents the limit of a storage quota
+/// Represents the limit of a storage quota. This is a limit on the
+/// storage size (which may be unlimited), plus an optional limit on
+/// the number of files (inodes). An inode limit of `None` means that
+/// no inode limit was specified, so the volume's default is used,
+/// while `Some(0)` means that the number of files is unlimited.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum QuotaLimit {
-    /// A hard limit on storage size
-    Limited(StorageSize),
-    /// No limit on storage size
-    Unlimited,
+pub struct QuotaLimit {
+    /// The hard limit on storage size, or `None` if unlimited
+    size: Option<StorageSize>,
+    /// The hard limit on the number of files, or `None` if not specified
+    inodes: Option<u64>,
 }
 
-// Custom serialization: serialize as a human-readable string (e.g., "5TB" or "unlimited")
+// Custom serialization: serialize as a human-readable string (e.g., "5TB",
+// "unlimited" or "5TB 1000000 files")
 impl Serialize for QuotaLimit {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -370,7 +387,8 @@ impl Serialize for QuotaLimit {
     }
 }
 
-// Custom deserialization: parse from a string (e.g., "5TB" or "unlimited")
+// Custom deserialization: parse from a string (e.g., "5TB", "unlimited"
+// or "5TB 1000000 files")
 impl<'de> Deserialize<'de> for QuotaLimit {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -382,7 +400,9 @@ impl<'de> Deserialize<'de> for QuotaLimit {
             type Value = QuotaLimit;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("a quota limit string (e.g., '5TB', 'unlimited')")
+                formatter.write_str(
+                    "a quota limit string (e.g., '5TB', 'unlimited', '5TB 1000000 files')",
+                )
             }
 
             fn visit_str<E>(self, value: &str) -> Result<QuotaLimit, E>
@@ -397,37 +417,140 @@ impl<'de> Deserialize<'de> for QuotaLimit {
     }
 }
 
-// make sure we can compare QuotaLimits
+/// Compare two inode limits, where 0 means unlimited
+fn compare_inodes(a: u64, b: u64) -> std::cmp::Ordering {
+    match (a, b) {
+        (0, 0) => std::cmp::Ordering::Equal,
+        (0, _) => std::cmp::Ordering::Greater,
+        (_, 0) => std::cmp::Ordering::Less,
+        (a, b) => a.cmp(&b),
+    }
+}
+
+// make sure we can compare QuotaLimits. The inode limits are only
+// compared if both limits specify them, and limits that are larger
+// in one dimension but smaller in the other are not comparable
 impl PartialOrd for QuotaLimit {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        match (self, other) {
-            (QuotaLimit::Unlimited, QuotaLimit::Unlimited) => Some(std::cmp::Ordering::Equal),
-            (QuotaLimit::Unlimited, _) => Some(std::cmp::Ordering::Greater),
-            (_, QuotaLimit::Unlimited) => Some(std::cmp::Ordering::Less),
-            (QuotaLimit::Limited(a), QuotaLimit::Limited(b)) => a.partial_cmp(b),
+        let size = match (self.size, other.size) {
+            (None, None) => std::cmp::Ordering::Equal,
+            (None, _) => std::cmp::Ordering::Greater,
+            (_, None) => std::cmp::Ordering::Less,
+            (Some(a), Some(b)) => a.partial_cmp(&b)?,
+        };
+
+        let inodes = match (self.inodes, other.inodes) {
+            (Some(a), Some(b)) => compare_inodes(a, b),
+            _ => return Some(size),
+        };
+
+        match (size, inodes) {
+            (std::cmp::Ordering::Equal, inodes) => Some(inodes),
+            (size, std::cmp::Ordering::Equal) => Some(size),
+            (size, inodes) if size == inodes => Some(size),
+            _ => None,
         }
     }
 }
 
+/// Parse a limit on the number of files, where "unlimited" is
+/// returned as 0
+fn parse_inode_count(s: &str) -> Result<u64, Error> {
+    if s.eq_ignore_ascii_case("unlimited") {
+        return Ok(0);
+    }
+
+    s.parse::<u64>()
+        .with_context(|| format!("Invalid number of files '{}'", s))
+        .map_err(|e| Error::Parse(e.to_string()))
+}
+
 impl QuotaLimit {
+    /// An unlimited quota, with no inode limit specified
+    pub const UNLIMITED: QuotaLimit = QuotaLimit {
+        size: None,
+        inodes: None,
+    };
+
+    /// Return a quota limited to the passed size
+    pub fn limited(size: StorageSize) -> Self {
+        Self {
+            size: Some(size),
+            inodes: None,
+        }
+    }
+
+    /// Return a quota with no limit on storage size
+    pub fn unlimited() -> Self {
+        Self::UNLIMITED
+    }
+
+    /// Return a copy of this limit that also limits the number of
+    /// files to `inodes` (0 means unlimited)
+    pub fn with_inodes(&self, inodes: u64) -> Self {
+        Self {
+            size: self.size,
+            inodes: Some(inodes),
+        }
+    }
+
+    /// Return a copy of this limit without any inode limit, i.e.
+    /// only the limit on storage size
+    pub fn without_inodes(&self) -> Self {
+        Self {
+            size: self.size,
+            inodes: None,
+        }
+    }
+
+    /// Set the limit on the number of files (0 means unlimited, and
+    /// `None` means that the volume's default should be used)
+    pub fn set_inodes(&mut self, inodes: Option<u64>) {
+        self.inodes = inodes;
+    }
+
+    /// Return whether the storage size is unlimited
     pub fn is_unlimited(&self) -> bool {
-        matches!(self, QuotaLimit::Unlimited)
+        self.size.is_none()
     }
 
+    /// Return whether the storage size is limited
     pub fn is_limited(&self) -> bool {
-        matches!(self, QuotaLimit::Limited(_))
+        self.size.is_some()
     }
 
     pub fn size(&self) -> Option<StorageSize> {
-        match self {
-            QuotaLimit::Limited(size) => Some(*size),
-            QuotaLimit::Unlimited => None,
-        }
+        self.size
+    }
+
+    /// Return the limit on the number of files, if one was specified.
+    /// A value of 0 means that the number of files is unlimited
+    pub fn inodes(&self) -> Option<u64> {
+        self.inodes
+    }
+
+    /// Return whether this limit is larger than `max` in either storage
+    /// size or number of files. The number of files is only checked if
+    /// both limits specify it
+    pub fn exceeds(&self, max: &QuotaLimit) -> bool {
+        let size = match (self.size, max.size) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(size), Some(max)) => size.as_bytes() > max.as_bytes(),
+        };
+
+        let inodes = match (self.inodes, max.inodes) {
+            (Some(inodes), Some(max)) => compare_inodes(inodes, max) == std::cmp::Ordering::Greater,
+            _ => false,
+        };
+
+        size || inodes
     }
 
     /// Parse a QuotaLimit from a string
-    /// Format: "unlimited" or "<size>"
-    /// Examples: "unlimited", "100GB"
+    /// Format: "<size|unlimited> [<count|unlimited> files]"
+    /// Examples: "unlimited", "100GB", "100GB 1000000 files",
+    ///           "unlimited 500000 files", "100GB unlimited files"
     pub fn parse(s: &str) -> Result<Self, Error> {
         let s = s.trim();
 
@@ -435,35 +558,71 @@ impl QuotaLimit {
             return Err(Error::Parse("Quota limit cannot be empty".to_string()));
         }
 
-        // Check if it's unlimited
-        if s.eq_ignore_ascii_case("unlimited") {
-            return Ok(Self::Unlimited);
+        let parts: Vec<&str> = s.split_whitespace().collect();
+
+        // an optional trailing "<count> files" gives the inode limit
+        let (size_parts, inodes) = match parts.split_last() {
+            Some((last, rest))
+                if last.eq_ignore_ascii_case("files") || last.eq_ignore_ascii_case("inodes") =>
+            {
+                match rest.split_last() {
+                    Some((count, size_parts)) => (size_parts, Some(parse_inode_count(count)?)),
+                    None => {
+                        return Err(Error::Parse(format!(
+                            "Quota limit '{}' is missing the number of files",
+                            s
+                        )))
+                    }
+                }
+            }
+            _ => (parts.as_slice(), None),
+        };
+
+        let size_str = size_parts.join("");
+
+        if size_str.is_empty() {
+            return Err(Error::Parse(format!(
+                "Quota limit '{}' is missing the storage size",
+                s
+            )));
         }
 
-        // Parse as a size
-        let parts: Vec<&str> = s.split_whitespace().collect();
-        let size_str = parts.join("");
-        let size = StorageSize::parse(&size_str)?;
-        Ok(Self::Limited(size))
+        // Check if it's unlimited
+        let size = if size_str.eq_ignore_ascii_case("unlimited") {
+            None
+        } else {
+            Some(StorageSize::parse(&size_str)?)
+        };
+
+        Ok(Self { size, inodes })
     }
 }
 
 impl std::fmt::Display for QuotaLimit {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            QuotaLimit::Limited(size) => write!(f, "{}", size),
-            QuotaLimit::Unlimited => write!(f, "unlimited"),
+        match self.size {
+            Some(size) => write!(f, "{}", size)?,
+            None => write!(f, "unlimited")?,
+        }
+
+        match self.inodes {
+            Some(0) => write!(f, " unlimited files"),
+            Some(inodes) => write!(f, " {} files", inodes),
+            None => Ok(()),
         }
     }
 }
 
 impl From<StorageSize> for QuotaLimit {
     fn from(size: StorageSize) -> Self {
-        QuotaLimit::Limited(size)
+        QuotaLimit::limited(size)
     }
 }
 
-/// Represents a storage quota with a limit and optional current usage
+/// Represents a storage quota with a limit and optional current usage.
+/// The inode (file count) limit and usage are carried in their own
+/// optional fields, so that peers that predate inode quotas still see
+/// a plain size in `limit`, and simply ignore the fields they don't know
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
 #[ts(export)]
 pub struct Quota {
@@ -474,36 +633,53 @@ pub struct Quota {
     /// Current usage expressed as a human-readable size string (e.g. "2.5GB")
     #[ts(as = "Option<String>", optional)]
     usage: Option<StorageUsage>,
+    /// Limit on the number of files (inodes), where 0 means unlimited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    inode_limit: Option<u64>,
+    /// Current number of files (inodes) used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    inode_usage: Option<u64>,
 }
 
 impl Quota {
     pub fn limited(limit: StorageSize) -> Self {
         Self {
-            limit: QuotaLimit::Limited(limit),
+            limit: QuotaLimit::limited(limit),
             usage: None,
+            inode_limit: None,
+            inode_usage: None,
         }
     }
 
     pub fn unlimited() -> Self {
         Self {
-            limit: QuotaLimit::Unlimited,
+            limit: QuotaLimit::unlimited(),
             usage: None,
+            inode_limit: None,
+            inode_usage: None,
         }
     }
 
     pub fn with_usage(limit: QuotaLimit, usage: StorageUsage) -> Self {
-        Self {
-            limit,
-            usage: Some(usage),
-        }
+        let mut quota = Self::unlimited();
+        quota.set_limit(limit);
+        quota.set_usage(usage);
+        quota
     }
 
-    pub fn limit(&self) -> &QuotaLimit {
-        &self.limit
+    /// Return the limit of this quota, including any inode limit
+    pub fn limit(&self) -> QuotaLimit {
+        match self.inode_limit.or(self.limit.inodes()) {
+            Some(inodes) => self.limit.with_inodes(inodes),
+            None => self.limit.without_inodes(),
+        }
     }
 
     pub fn set_limit(&mut self, limit: QuotaLimit) {
-        self.limit = limit;
+        self.inode_limit = limit.inodes();
+        self.limit = limit.without_inodes();
     }
 
     pub fn usage(&self) -> Option<StorageUsage> {
@@ -514,29 +690,58 @@ impl Quota {
         self.usage = Some(usage);
     }
 
+    /// Return the number of files (inodes) used, if known
+    pub fn inode_usage(&self) -> Option<u64> {
+        self.inode_usage
+    }
+
+    pub fn set_inode_usage(&mut self, inode_usage: u64) {
+        self.inode_usage = Some(inode_usage);
+    }
+
     pub fn is_unlimited(&self) -> bool {
         self.limit.is_unlimited()
     }
 
+    /// Return whether the usage is over either the storage size
+    /// or the inode limit
     pub fn is_over_quota(&self) -> bool {
-        match (&self.limit, self.usage) {
-            (QuotaLimit::Limited(limit), Some(usage)) => usage.as_bytes() > limit.as_bytes(),
+        let size = match (self.limit.size(), self.usage) {
+            (Some(limit), Some(usage)) => usage.as_bytes() > limit.as_bytes(),
             _ => false,
-        }
+        };
+
+        let inodes = match (self.limit().inodes(), self.inode_usage) {
+            (Some(limit), Some(usage)) if limit > 0 => usage > limit,
+            _ => false,
+        };
+
+        size || inodes
     }
 
     pub fn percentage_used(&self) -> Option<f64> {
-        match (&self.limit, self.usage) {
-            (QuotaLimit::Limited(limit), Some(usage)) if limit.as_bytes() > 0 => {
+        match (self.limit.size(), self.usage) {
+            (Some(limit), Some(usage)) if limit.as_bytes() > 0 => {
                 Some((usage.as_bytes() as f64 / limit.as_bytes() as f64) * 100.0)
             }
             _ => None,
         }
     }
 
+    /// Return the percentage of the inode limit that has been used,
+    /// if both the limit and usage are known
+    pub fn percentage_inodes_used(&self) -> Option<f64> {
+        match (self.limit().inodes(), self.inode_usage) {
+            (Some(limit), Some(usage)) if limit > 0 => Some((usage as f64 / limit as f64) * 100.0),
+            _ => None,
+        }
+    }
+
     /// Parse a Quota from a string
-    /// Format: "unlimited" or "limit <size>" or "limit <size> used <size>"
-    /// Examples: "unlimited", "100GB", "100GB used 50GB"
+    /// Format: "<limit>" or "<limit> used <size> [<count> files]", where
+    /// <limit> is as for [`QuotaLimit::parse`]
+    /// Examples: "unlimited", "100GB", "100GB used 50GB",
+    ///           "100GB 1000000 files used 50GB 1200 files"
     pub fn parse(s: &str) -> Result<Self, Error> {
         let s = s.trim();
 
@@ -544,38 +749,66 @@ impl Quota {
             return Err(Error::Parse("Storage quota cannot be empty".to_string()));
         }
 
-        // Check if it's unlimited
-        if s.eq_ignore_ascii_case("unlimited") {
-            return Ok(Self::unlimited());
-        }
-
         // Split by "used" to separate limit from usage
         let parts: Vec<&str> = s.split_whitespace().collect();
 
         // Find if there's a "used" keyword
         if let Some(used_idx) = parts.iter().position(|&p| p.eq_ignore_ascii_case("used")) {
             // Format: "<limit> used <usage>"
-            let limit_str = parts.get(..used_idx).unwrap_or_default().join("");
-            let usage_str = parts.get(used_idx + 1..).unwrap_or_default().join("");
+            let limit_str = parts.get(..used_idx).unwrap_or_default().join(" ");
+            let usage_parts = parts.get(used_idx + 1..).unwrap_or_default();
+
+            let limit = QuotaLimit::parse(&limit_str)?;
+
+            // the usage may end with "<count> files"
+            let (usage_parts, inode_usage) = match usage_parts.split_last() {
+                Some((last, rest))
+                    if last.eq_ignore_ascii_case("files")
+                        || last.eq_ignore_ascii_case("inodes") =>
+                {
+                    match rest.split_last() {
+                        Some((count, usage_parts)) => (
+                            usage_parts,
+                            Some(
+                                count
+                                    .parse::<u64>()
+                                    .with_context(|| {
+                                        format!("Invalid number of files used '{}'", count)
+                                    })
+                                    .map_err(|e| Error::Parse(e.to_string()))?,
+                            ),
+                        ),
+                        None => {
+                            return Err(Error::Parse(format!(
+                                "Storage quota '{}' is missing the number of files used",
+                                s
+                            )))
+                        }
+                    }
+                }
+                _ => (usage_parts, None),
+            };
 
-            let limit = StorageSize::parse(&limit_str)?;
-            let usage = StorageSize::parse(&usage_str)?;
+            let usage = StorageSize::parse(&usage_parts.join(""))?;
 
-            Ok(Self::with_usage(
-                QuotaLimit::Limited(limit),
-                StorageUsage::new(usage),
-            ))
+            let mut quota = Self::with_usage(limit, StorageUsage::new(usage));
+
+            if let Some(inode_usage) = inode_usage {
+                quota.set_inode_usage(inode_usage);
+            }
+
+            Ok(quota)
         } else {
             // Just a limit, no usage
-            let limit_str = parts.join("");
-            let limit = StorageSize::parse(&limit_str)?;
-            Ok(Self::limited(limit))
+            let mut quota = Self::unlimited();
+            quota.set_limit(QuotaLimit::parse(s)?);
+            Ok(quota)
         }
     }
 
     /// Parse a Quota limit from a string (without usage information)
-    /// Format: "unlimited" or "<size>"
-    /// Examples: "unlimited", "100GB"
+    /// Format: as for [`QuotaLimit::parse`]
+    /// Examples: "unlimited", "100GB", "100GB 1000000 files"
     /// This will error if the string contains usage information (i.e., "used" keyword)
     pub fn parse_limit_only(s: &str) -> Result<Self, Error> {
         let s = s.trim();
@@ -584,11 +817,6 @@ impl Quota {
             return Err(Error::Parse("Storage quota cannot be empty".to_string()));
         }
 
-        // Check if it's unlimited
-        if s.eq_ignore_ascii_case("unlimited") {
-            return Ok(Self::unlimited());
-        }
-
         // Check if the string contains "used" keyword
         let parts: Vec<&str> = s.split_whitespace().collect();
         if parts.iter().any(|&p| p.eq_ignore_ascii_case("used")) {
@@ -598,26 +826,40 @@ impl Quota {
         }
 
         // Parse just the limit
-        let limit_str = parts.join("");
-        let limit = StorageSize::parse(&limit_str)?;
-        Ok(Self::limited(limit))
+        let mut quota = Self::unlimited();
+        quota.set_limit(QuotaLimit::parse(s)?);
+        Ok(quota)
     }
 }
 
 impl std::fmt::Display for Quota {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self.usage {
-            Some(usage) => match &self.limit {
-                QuotaLimit::Limited(limit) => {
+            Some(usage) => match self.limit.size() {
+                Some(limit) => {
                     if let Some(percentage) = self.percentage_used() {
-                        write!(f, "{} / {} | {:.1}%", usage, limit, percentage)
+                        write!(f, "{} / {} | {:.1}%", usage, limit, percentage)?
                     } else {
-                        write!(f, "{} / {}", usage, limit)
+                        write!(f, "{} / {}", usage, limit)?
                     }
                 }
-                QuotaLimit::Unlimited => write!(f, "{} / unlimited", usage),
+                None => write!(f, "{} / unlimited", usage)?,
             },
-            None => write!(f, "{}", self.limit),
+            None => write!(f, "{}", self.limit)?,
+        }
+
+        match (self.inode_usage, self.limit().inodes()) {
+            (Some(used), Some(0)) => write!(f, ", {} / unlimited files", used),
+            (Some(used), Some(limit)) => match self.percentage_inodes_used() {
+                Some(percentage) => {
+                    write!(f, ", {} / {} files | {:.1}%", used, limit, percentage)
+                }
+                None => write!(f, ", {} / {} files", used, limit),
+            },
+            (Some(used), None) => write!(f, ", {} files", used),
+            (None, Some(0)) => write!(f, ", unlimited files"),
+            (None, Some(limit)) => write!(f, ", {} files", limit),
+            (None, None) => Ok(()),
         }
     }
 }
@@ -690,6 +932,196 @@ impl std::fmt::Display for Volume {
     }
 }
 
+/// The format of the timestamp that names a recycled directory version
+const RECYCLE_VERSION_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// The version given to a directory that was recycled before recycled
+/// directories were versioned, so has no timestamp in its name
+const LEGACY_RECYCLE_VERSION: &str = "legacy";
+
+///
+/// Identifies one version of a recycled directory. This is the UTC time
+/// the directory was recycled (e.g. "20260314T101500Z"), with a "-2",
+/// "-3" etc. suffix if it was recycled more than once in the same second,
+/// or "legacy" for a directory recycled before versions were kept.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Hash, TS)]
+#[serde(try_from = "String")]
+#[ts(export, type = "string")]
+pub struct RecycleVersion {
+    version: String,
+}
+
+impl TryFrom<String> for RecycleVersion {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        RecycleVersion::parse(&value)
+    }
+}
+
+impl Serialize for RecycleVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.version)
+    }
+}
+
+impl RecycleVersion {
+    ///
+    /// Return the version for a directory recycled at `recycled`. `count`
+    /// is the number of versions already recycled in that same second.
+    ///
+    pub fn new(recycled: &chrono::DateTime<chrono::Utc>, count: u32) -> Self {
+        let timestamp = recycled.format(RECYCLE_VERSION_FORMAT).to_string();
+
+        match count {
+            0 => Self { version: timestamp },
+            _ => Self {
+                version: format!("{}-{}", timestamp, count.saturating_add(1)),
+            },
+        }
+    }
+
+    pub fn legacy() -> Self {
+        Self {
+            version: LEGACY_RECYCLE_VERSION.to_string(),
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.version == LEGACY_RECYCLE_VERSION
+    }
+
+    ///
+    /// Return the time the directory was recycled, or None if this is
+    /// a legacy version, whose time is only known from the directory
+    ///
+    pub fn timestamp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let timestamp = match self.version.split_once('-') {
+            Some((timestamp, _)) => timestamp,
+            None => &self.version,
+        };
+
+        chrono::NaiveDateTime::parse_from_str(timestamp, RECYCLE_VERSION_FORMAT)
+            .ok()
+            .map(|t| t.and_utc())
+    }
+
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let version = s.trim();
+
+        if version == LEGACY_RECYCLE_VERSION {
+            return Ok(Self::legacy());
+        }
+
+        let (timestamp, count) = match version.split_once('-') {
+            Some((timestamp, count)) => (timestamp, Some(count)),
+            None => (version, None),
+        };
+
+        // the version becomes part of a path, so only accept exactly
+        // what `new` produces
+        let valid_count = match count {
+            Some(count) => {
+                !count.is_empty()
+                    && !count.starts_with('0')
+                    && count.chars().all(|c| c.is_ascii_digit())
+                    && count.parse::<u32>().is_ok_and(|c| c > 1)
+            }
+            None => true,
+        };
+
+        if !valid_count
+            || chrono::NaiveDateTime::parse_from_str(timestamp, RECYCLE_VERSION_FORMAT).is_err()
+            || timestamp.len() != "YYYYMMDDTHHMMSSZ".len()
+        {
+            return Err(Error::Parse(format!(
+                "Invalid recycle version '{}' - this should be a time like \
+                 '20260314T101500Z' (optionally with a '-N' suffix), or '{}'",
+                version, LEGACY_RECYCLE_VERSION
+            )));
+        }
+
+        Ok(Self {
+            version: version.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for RecycleVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.version)
+    }
+}
+
+///
+/// A user or project directory that has been removed, and is waiting
+/// in the recycle bin of its volume until it is restored or purged
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RecycledDir {
+    /// The volume the directory was on
+    #[ts(as = "String")]
+    volume: Volume,
+
+    /// The path the directory had, and will be restored to
+    path: String,
+
+    /// The version, used to choose which copy to restore
+    #[ts(as = "String")]
+    version: RecycleVersion,
+
+    /// When the directory was recycled
+    #[ts(as = "String")]
+    recycled: chrono::DateTime<chrono::Utc>,
+}
+
+impl std::fmt::Display for RecycledDir {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} (recycled {})",
+            self.volume, self.path, self.version, self.recycled
+        )
+    }
+}
+
+impl RecycledDir {
+    pub fn new(
+        volume: &Volume,
+        path: &str,
+        version: &RecycleVersion,
+        recycled: &chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        Self {
+            volume: volume.clone(),
+            path: path.to_string(),
+            version: version.clone(),
+            recycled: *recycled,
+        }
+    }
+
+    pub fn volume(&self) -> &Volume {
+        &self.volume
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn version(&self) -> &RecycleVersion {
+        &self.version
+    }
+
+    pub fn recycled(&self) -> &chrono::DateTime<chrono::Utc> {
+        &self.recycled
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -770,4 +1202,146 @@ mod tests {
         assert_eq!((StorageSize::from_bytes(100) / 4).as_bytes(), 25);
         assert_eq!((StorageSize::from_bytes(100) * 3).as_bytes(), 300);
     }
+
+    #[test]
+    fn test_quota_limit_with_inodes() {
+        let limit =
+            QuotaLimit::parse("100GB 1000000 files").unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(limit.size(), StorageSize::parse("100GB").ok());
+        assert_eq!(limit.inodes(), Some(1_000_000));
+        assert_eq!(
+            QuotaLimit::parse(&limit.to_string()).ok(),
+            Some(limit.clone())
+        );
+
+        // a plain size is unchanged, so older peers see the same strings
+        let plain = QuotaLimit::parse("100GB").unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(plain.inodes(), None);
+        assert!(!plain.to_string().contains("files"));
+
+        let unlimited = QuotaLimit::parse("unlimited unlimited files")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(unlimited.is_unlimited());
+        assert_eq!(unlimited.inodes(), Some(0));
+
+        assert!(QuotaLimit::parse("files").is_err());
+        assert!(QuotaLimit::parse("100GB files").is_err());
+        assert!(QuotaLimit::parse("100GB many files").is_err());
+
+        // limits are checked against the max in both dimensions
+        let max =
+            QuotaLimit::parse("1TB 2000000 files").unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(!limit.exceeds(&max));
+        assert!(!plain.exceeds(&max));
+        assert!(limit.with_inodes(3_000_000).exceeds(&max));
+        assert!(limit.with_inodes(0).exceeds(&max));
+        assert!(QuotaLimit::unlimited().exceeds(&max));
+        assert!(!limit.exceeds(&QuotaLimit::unlimited()));
+
+        assert!(limit < max);
+        assert_eq!(limit.with_inodes(3_000_000).partial_cmp(&max), None);
+    }
+
+    #[test]
+    fn test_quota_inodes_are_backwards_compatible() {
+        let mut quota = Quota::with_usage(
+            QuotaLimit::parse("100GB 1000000 files").unwrap_or_else(|e| unreachable!("{:?}", e)),
+            StorageUsage::new(
+                StorageSize::parse("50GB").unwrap_or_else(|e| unreachable!("{:?}", e)),
+            ),
+        );
+        quota.set_inode_usage(1200);
+
+        assert_eq!(quota.limit().inodes(), Some(1_000_000));
+        assert!(!quota.is_over_quota());
+        assert_eq!(
+            Quota::parse(&format!("{} used 50GB 1200 files", quota.limit())).ok(),
+            Some(quota.clone())
+        );
+
+        // the size limit is still a plain size on the wire, with the
+        // inode limit and usage in their own fields
+        let json = serde_json::to_value(&quota).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(json.get("limit"), Some(&serde_json::json!("100.00 GB")));
+        assert_eq!(json.get("inode_limit"), Some(&serde_json::json!(1_000_000)));
+        assert_eq!(json.get("inode_usage"), Some(&serde_json::json!(1200)));
+
+        let roundtrip: Quota =
+            serde_json::from_value(json).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(roundtrip, quota);
+
+        // a quota from an older peer has no inode fields
+        let old: Quota = serde_json::from_str(r#"{"limit": "100GB", "usage": "50GB"}"#)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(old.limit().inodes(), None);
+        assert_eq!(old.inode_usage(), None);
+        assert!(!serde_json::to_string(&old)
+            .unwrap_or_default()
+            .contains("inode"));
+
+        quota.set_inode_usage(1_000_001);
+        assert!(quota.is_over_quota());
+    }
+
+    #[test]
+    fn test_recycle_version() {
+        let recycled = chrono::NaiveDate::from_ymd_opt(2026, 3, 14)
+            .and_then(|d| d.and_hms_opt(10, 15, 0))
+            .map(|t| t.and_utc())
+            .unwrap_or_else(|| unreachable!("valid date"));
+
+        let version = RecycleVersion::new(&recycled, 0);
+        assert_eq!(version.to_string(), "20260314T101500Z");
+        assert_eq!(version.timestamp(), Some(recycled));
+        assert_eq!(
+            RecycleVersion::parse("20260314T101500Z").ok(),
+            Some(version)
+        );
+
+        // the second version recycled in the same second
+        let second = RecycleVersion::new(&recycled, 1);
+        assert_eq!(second.to_string(), "20260314T101500Z-2");
+        assert_eq!(second.timestamp(), Some(recycled));
+        assert_eq!(
+            RecycleVersion::parse("20260314T101500Z-2").ok(),
+            Some(second)
+        );
+
+        let legacy = RecycleVersion::parse("legacy").unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(legacy.is_legacy());
+        assert_eq!(legacy.timestamp(), None);
+
+        // versions become part of a path, so nothing else is accepted
+        for bad in [
+            "",
+            "latest",
+            "20260314",
+            "20260314T101500",
+            "20260314T101500Z-",
+            "20260314T101500Z-1",
+            "20260314T101500Z-02",
+            "20260314T101500Z-x",
+            "20260314T101500Z/..",
+            "../20260314T101500Z",
+            "20261314T101500Z",
+        ] {
+            assert!(
+                RecycleVersion::parse(bad).is_err(),
+                "{} must be refused",
+                bad
+            );
+        }
+
+        // the version goes through `parse` on the wire too
+        assert!(serde_json::from_str::<RecycleVersion>(r#""../fred""#).is_err());
+        let json = serde_json::to_string(&RecycledDir::new(
+            &Volume::new("home"),
+            "/home/proj/fred",
+            &RecycleVersion::new(&recycled, 0),
+            &recycled,
+        ))
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(json.contains(r#""version":"20260314T101500Z""#));
+        assert!(serde_json::from_str::<RecycledDir>(&json).is_ok());
+    }
 }
@@ -378,6 +378,52 @@ impl ProjectStorageReport {
         }
     }
 
+    /// Return a copy of this report holding only the snapshots - historical
+    /// or current - whose date falls within `range`, with the newest of them
+    /// as the top-level snapshot. Unlike `filter`, the current snapshot is
+    /// dropped if it lies outside the range. If no snapshot does, the result
+    /// is an empty report dated at the start of the range, so that it never
+    /// stands in for a day it does not cover.
+    pub fn within(&self, range: &DateRange) -> Self {
+        let in_range = |date: &Date| date >= range.start_date() && date <= range.end_date();
+
+        let mut snapshots: Vec<(Date, DailyStorageReport)> = self
+            .daily_reports
+            .iter()
+            .filter(|(date, _)| in_range(date))
+            .map(|(date, snapshot)| (date.clone(), snapshot.clone()))
+            .collect();
+
+        let current_date = Date::from_chrono(&self.generated_at.date_naive());
+
+        if in_range(&current_date) {
+            snapshots.push((current_date, DailyStorageReport::from(self)));
+        }
+
+        snapshots.sort_by_key(|(date, _)| date.clone());
+
+        match snapshots.pop() {
+            Some((_, newest)) => {
+                let mut report = ProjectStorageReport::from(newest);
+                report.users = self.users.clone();
+                report.daily_reports = snapshots.into_iter().collect();
+                report
+            }
+            None => ProjectStorageReport {
+                project: self.project.clone(),
+                generated_at: chrono::NaiveDateTime::new(
+                    range.start_date().to_chrono(),
+                    chrono::NaiveTime::MIN,
+                )
+                .and_utc(),
+                project_quotas: HashMap::new(),
+                user_quotas: HashMap::new(),
+                users: self.users.clone(),
+                daily_reports: HashMap::new(),
+            },
+        }
+    }
+
     /// Remap this report to a new project identifier.
     ///
     /// Updates the top-level `project` field and rebuilds the `users` and
@@ -1027,4 +1073,38 @@ mod tests {
         assert!(!lifted.is_empty());
         assert!(!lifted.get_report(&project).is_empty());
     }
+
+    #[test]
+    fn within_keeps_only_the_snapshots_in_the_range() {
+        let project = ProjectIdentifier::parse("myproject.myportal")
+            .unwrap_or_else(|e| unreachable!("parse: {:?}", e));
+
+        let volume = Volume::parse("home").unwrap_or_else(|e| unreachable!("parse: {:?}", e));
+        let quota = Quota::parse("10GB").unwrap_or_else(|e| unreachable!("parse: {:?}", e));
+
+        let mut older = ProjectStorageReport::new(&project);
+        older.generated_at = Utc::now() - chrono::Duration::days(2);
+        older.set_project_quotas(HashMap::from([(volume, quota)]));
+
+        let newer = ProjectStorageReport::new(&project);
+
+        let history = older.clone() + newer;
+
+        let older_date = Date::from_chrono(&older.generated_at.date_naive());
+        let past = history.within(&older_date.day());
+
+        assert_eq!(past.generated_at(), older.generated_at());
+        assert!(!past.is_empty());
+        assert!(past.daily_reports.is_empty());
+
+        // nothing was recorded the day after, so nothing is returned for it -
+        // and the empty report is dated on that day, not today
+        let gap = history.within(&older_date.next().day());
+
+        assert!(gap.is_empty());
+        assert_eq!(
+            Date::from_chrono(&gap.generated_at().date_naive()),
+            older_date.next()
+        );
+    }
 }
@@ -406,6 +406,26 @@ impl UserUsageReport {
     }
 }
 
+///
+/// Return the name of the component that holds the usage of `component`
+/// on a single `partition` (or node type), e.g. "gpu@ampere". The main
+/// usage on a partition is held in "usage@<partition>".
+///
+pub fn partition_component(component: &str, partition: &str) -> String {
+    format!("{}@{}", component, partition)
+}
+
+///
+/// Split the name of a component into the component and, if it holds the
+/// usage on a single partition, the name of that partition
+///
+pub fn split_partition(component: &str) -> (&str, Option<&str>) {
+    match component.split_once('@') {
+        Some((component, partition)) => (component, Some(partition)),
+        None => (component, None),
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
 #[ts(export)]
 pub struct DailyProjectUsageReport {
@@ -1436,6 +1456,11 @@ impl ProjectUsageReport {
         }
     }
 
+    /// Return the daily report for the passed date, if there is one
+    pub fn daily_report(&self, date: &Date) -> Option<DailyProjectUsageReport> {
+        self.reports.get(date).cloned()
+    }
+
     pub fn is_complete(&self) -> bool {
         self.reports.values().all(|r| r.is_complete())
     }
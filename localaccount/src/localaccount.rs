@@ -23,13 +23,17 @@
 
 use anyhow::Result;
 use chrono::Utc;
-use greatwestern::grammar::{ProjectIdentifier, ProjectMapping, UserIdentifier, UserMapping};
+use greatwestern::grammar::{
+    ProjectIdentifier, ProjectMapping, UserIdentifier, UserMapping, UserProfile,
+};
 use once_cell::sync::OnceCell;
 use std::collections::HashMap;
+use std::process::Stdio;
 use templemeads::agent::Peer;
 use templemeads::job::assert_not_expired;
 use templemeads::portal_identifier::PortalIdentifier;
 use templemeads::Error;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 
 static COMMANDS: OnceCell<Commands> = OnceCell::new();
@@ -46,6 +50,9 @@ pub struct Commands {
     usermod: Vec<String>,
     getent: Vec<String>,
     gpasswd: Vec<String>,
+    /// Runs a command as a managed user, used to write their
+    /// `~/.ssh/authorized_keys` with only that user's permissions.
+    runuser: Vec<String>,
     /// Group that all users managed by this agent are added to, used to
     /// distinguish managed users from pre-existing system accounts.
     managed_group: String,
@@ -73,6 +80,7 @@ impl Commands {
         usermod: &str,
         getent: &str,
         gpasswd: &str,
+        runuser: &str,
         managed_group: &str,
         system_groups: Vec<String>,
         instance_groups: HashMap<String, Vec<String>>,
@@ -84,6 +92,7 @@ impl Commands {
             usermod: Self::parse_cmd(usermod),
             getent: Self::parse_cmd(getent),
             gpasswd: Self::parse_cmd(gpasswd),
+            runuser: Self::parse_cmd(runuser),
             managed_group: managed_group.to_owned(),
             system_groups,
             instance_groups,
@@ -113,6 +122,17 @@ fn get_commands() -> Result<&'static Commands, Error> {
 /// Returns (exit_code, stdout, stderr).
 ///
 async fn run_command(parts: &[String], args: &[&str]) -> Result<(i32, String, String), Error> {
+    run_command_with_input(parts, args, None).await
+}
+
+///
+/// As `run_command`, but also writing `input` (if any) to the command's stdin
+///
+async fn run_command_with_input(
+    parts: &[String],
+    args: &[&str],
+    input: Option<&str>,
+) -> Result<(i32, String, String), Error> {
     if parts.is_empty() {
         return Err(Error::Call("Empty command template".to_owned()));
     }
@@ -131,7 +151,14 @@ async fn run_command(parts: &[String], args: &[&str]) -> Result<(i32, String, St
         cmd.arg(arg);
     }
 
-    let output = cmd.output().await.map_err(|e| {
+    cmd.stdin(match input {
+        Some(_) => Stdio::piped(),
+        None => Stdio::null(),
+    })
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped());
+
+    let mut child = cmd.spawn().map_err(|e| {
         Error::Call(format!(
             "Failed to spawn command {}: {}",
             parts.join(" "),
@@ -139,6 +166,25 @@ async fn run_command(parts: &[String], args: &[&str]) -> Result<(i32, String, St
         ))
     })?;
 
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        stdin.write_all(input.as_bytes()).await.map_err(|e| {
+            Error::Call(format!(
+                "Failed to write to command {}: {}",
+                parts.join(" "),
+                e
+            ))
+        })?;
+        // dropping stdin closes it, so the command sees end-of-input
+    }
+
+    let output = child.wait_with_output().await.map_err(|e| {
+        Error::Call(format!(
+            "Failed to wait for command {}: {}",
+            parts.join(" "),
+            e
+        ))
+    })?;
+
     let exit_code = output.status.code().unwrap_or(-1);
     let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
     let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
@@ -901,6 +947,224 @@ pub async fn update_homedir(
     Ok(())
 }
 
+/// The lines that delimit the keys this agent manages in a user's
+/// `authorized_keys`. Anything outside them is the user's own, and is kept.
+const MANAGED_KEYS_BEGIN: &str =
+    "# BEGIN OpenPortal managed keys - changes here will be overwritten";
+const MANAGED_KEYS_END: &str = "# END OpenPortal managed keys";
+
+///
+/// Return `existing` (the contents of an `authorized_keys` file) with the
+/// block of managed keys replaced by `keys`. The block is removed if there
+/// are no keys, and added to the end if there wasn't one before.
+///
+fn replace_managed_keys(existing: &str, keys: &[String]) -> String {
+    let mut lines = Vec::new();
+    let mut in_block = false;
+    let mut position = None;
+
+    for line in existing.lines() {
+        if line.trim() == MANAGED_KEYS_BEGIN {
+            in_block = true;
+            position.get_or_insert(lines.len());
+        } else if line.trim() == MANAGED_KEYS_END {
+            in_block = false;
+        } else if !in_block {
+            lines.push(line.to_string());
+        }
+    }
+
+    if !keys.is_empty() {
+        let mut block = vec![MANAGED_KEYS_BEGIN.to_string()];
+        block.extend(keys.iter().cloned());
+        block.push(MANAGED_KEYS_END.to_string());
+
+        let tail = lines.split_off(position.unwrap_or(lines.len()));
+        lines.extend(block);
+        lines.extend(tail);
+    }
+
+    match lines.is_empty() {
+        true => String::new(),
+        false => format!("{}\n", lines.join("\n")),
+    }
+}
+
+///
+/// Write `keys` as the managed block of `local_user`'s
+/// `~/.ssh/authorized_keys`. Every step runs as the user (via `runuser`),
+/// so a symlink the user has planted in their home directory can only
+/// ever lead to a file they could write anyway.
+///
+async fn write_authorized_keys(local_user: &str, keys: &[String]) -> Result<(), Error> {
+    let cmds = get_commands()?;
+
+    let (exit_code, stdout, stderr) = run_command(&cmds.getent, &["passwd", local_user]).await?;
+
+    if exit_code != 0 {
+        return Err(Error::Call(format!(
+            "getent passwd failed for '{}': exit code {}, stderr: {}",
+            local_user, exit_code, stderr
+        )));
+    }
+
+    // Output: name:x:uid:gid:gecos:home:shell
+    let home = stdout
+        .trim()
+        .split(':')
+        .nth(5)
+        .unwrap_or_default()
+        .to_string();
+    check_homedir(&home)?;
+
+    let ssh_dir = format!("{}/.ssh", home.trim_end_matches('/'));
+    let authorized_keys = format!("{}/authorized_keys", ssh_dir);
+
+    let as_user = |args: &[&str]| -> Vec<String> {
+        ["-u", local_user, "--"]
+            .iter()
+            .chain(args.iter())
+            .map(|a| a.to_string())
+            .collect()
+    };
+
+    // a missing file just means there are no keys yet
+    let args = as_user(&["cat", "--", &authorized_keys]);
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    let (exit_code, existing, _) = run_command(&cmds.runuser, &args).await?;
+    let existing = match exit_code {
+        0 => existing,
+        _ => String::new(),
+    };
+
+    let updated = replace_managed_keys(&existing, keys);
+
+    if updated == existing {
+        tracing::debug!("authorized_keys for {} is already up to date", local_user);
+        return Ok(());
+    }
+
+    for (command, input) in [
+        (
+            vec!["mkdir", "-p", "-m", "700", "--", ssh_dir.as_str()],
+            None,
+        ),
+        (
+            vec!["tee", "--", authorized_keys.as_str()],
+            Some(updated.as_str()),
+        ),
+        (vec!["chmod", "600", "--", authorized_keys.as_str()], None),
+    ] {
+        let args = as_user(&command);
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+
+        let (exit_code, _, stderr) = run_command_with_input(&cmds.runuser, &args, input).await?;
+
+        if exit_code != 0 {
+            return Err(Error::Call(format!(
+                "Could not write the SSH keys for '{}' ({} failed): exit code {}, stderr: {}",
+                local_user,
+                command.first().unwrap_or(&""),
+                exit_code,
+                stderr
+            )));
+        }
+    }
+
+    tracing::info!("Wrote {} SSH key(s) for {}", keys.len(), local_user);
+
+    Ok(())
+}
+
+///
+/// Write the passed profile into the user's local account - the display
+/// name into the GECOS field (`usermod -c`), the shell with `usermod -s`,
+/// and the SSH keys into a managed block in `~/.ssh/authorized_keys`. A
+/// local account has nowhere to keep an email address, so that is ignored.
+/// If `replace` is true then a missing name or set of keys is cleared
+/// (the shell is left alone, as an account always needs one). Otherwise
+/// only the fields that are set are changed. Returns the profile written.
+///
+pub async fn set_user_profile(
+    user: &UserIdentifier,
+    profile: &UserProfile,
+    replace: bool,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<UserProfile, Error> {
+    assert_not_expired(expires)?;
+    assert_not_internal_portal(&user.project_identifier())?;
+    profile.validate()?;
+
+    let local_user = identifier_to_userid(user);
+    let cmds = get_commands()?;
+
+    if !is_existing_user(user, expires).await? {
+        return Err(Error::Call(format!("User does not exist: {}", user)));
+    }
+
+    if is_protected_user(user, expires).await? {
+        tracing::warn!(
+            "Refusing to set the profile of {} as they are not managed by this agent",
+            local_user
+        );
+        return Err(Error::UnmanagedUser(format!(
+            "Cannot set the profile of {} as they are not managed by this agent",
+            local_user
+        )));
+    }
+
+    tracing::info!("Updating profile for {}", local_user);
+
+    let display_name = match (profile.display_name(), replace) {
+        (Some(display_name), _) => Some(display_name),
+        (None, true) => Some(String::new()),
+        (None, false) => None,
+    };
+
+    if let Some(display_name) = display_name {
+        let (exit_code, _, stderr) =
+            run_command(&cmds.usermod, &["-c", &display_name, "--", &local_user]).await?;
+
+        if exit_code != 0 {
+            return Err(Error::Call(format!(
+                "usermod -c failed for '{}': exit code {}, stderr: {}",
+                local_user, exit_code, stderr
+            )));
+        }
+    }
+
+    if let Some(shell) = profile.shell() {
+        let (exit_code, _, stderr) =
+            run_command(&cmds.usermod, &["-s", &shell, "--", &local_user]).await?;
+
+        if exit_code != 0 {
+            return Err(Error::Call(format!(
+                "usermod -s failed for '{}': exit code {}, stderr: {}",
+                local_user, exit_code, stderr
+            )));
+        }
+    }
+
+    let keys = match (profile.ssh_keys(), replace) {
+        (Some(keys), _) => Some(keys),
+        (None, true) => Some(Vec::new()),
+        (None, false) => None,
+    };
+
+    if let Some(keys) = keys {
+        write_authorized_keys(&local_user, &keys).await?;
+    }
+
+    if profile.email().is_some() {
+        tracing::debug!(
+            "Not storing an email address for {} - local accounts have nowhere to keep it",
+            local_user
+        );
+    }
+
+    Ok(profile.clone())
+}
+
 ///
 /// Return all project mappings for the given portal by scanning
 /// `getent group` output for groups named "{portal}.{project}".
@@ -1393,6 +1657,40 @@ pub async fn is_protected_user(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_replace_managed_keys_keeps_the_users_own_keys() {
+        let own = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOwn own@laptop";
+        let first = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFirst portal@one".to_string();
+        let second = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQSecond portal@two".to_string();
+
+        // no file yet - just the managed block
+        let written = replace_managed_keys("", std::slice::from_ref(&first));
+        assert_eq!(
+            written,
+            format!("{}\n{}\n{}\n", MANAGED_KEYS_BEGIN, first, MANAGED_KEYS_END)
+        );
+
+        // the user's own key is kept, and the block replaced in place
+        let existing = format!("{}\n{}", own, written);
+        let written = replace_managed_keys(&existing, std::slice::from_ref(&second));
+        assert_eq!(
+            written,
+            format!(
+                "{}\n{}\n{}\n{}\n",
+                own, MANAGED_KEYS_BEGIN, second, MANAGED_KEYS_END
+            )
+        );
+
+        // writing the same keys again changes nothing
+        assert_eq!(
+            replace_managed_keys(&written, std::slice::from_ref(&second)),
+            written
+        );
+
+        // no keys removes the block, but not the user's key
+        assert_eq!(replace_managed_keys(&written, &[]), format!("{}\n", own));
+    }
+
     #[test]
     fn test_check_homedir_rejects_dangerous_paths() {
         // Regression test for finding R13. The home directory is a bare
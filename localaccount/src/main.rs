@@ -10,7 +10,7 @@ mod localaccount;
 use greatwestern::grammar::Instruction::{
     AddProject, AddUser, BlockUser, GetProjectMapping, GetProjects, GetUserMapping, GetUsers,
     IsBlockedUser, IsExistingProject, IsExistingUser, IsProtectedUser, RemoveProject, RemoveUser,
-    UnblockUser, UpdateHomeDir,
+    SetUserProfile, SetUserRole, UnblockUser, UpdateHomeDir, UpdateUserProfile,
 };
 use greatwestern::grammar::UserMapping;
 use greatwestern::Hpc;
@@ -92,6 +92,7 @@ async fn main() -> Result<()> {
     let usermod = config.option("usermod", "usermod");
     let getent = config.option("getent", "getent");
     let gpasswd = config.option("gpasswd", "gpasswd");
+    let runuser = config.option("runuser", "runuser");
 
     // The managed group distinguishes users created by this agent from
     // pre-existing system users.  All managed users are added to it.
@@ -113,6 +114,7 @@ async fn main() -> Result<()> {
         &usermod,
         &getent,
         &gpasswd,
+        &runuser,
         &managed_group,
         system_groups,
         instance_groups,
@@ -174,6 +176,21 @@ async fn main() -> Result<()> {
                     localaccount::update_homedir(&user, &homedir, job.expires()).await?;
                     job.completed(homedir)
                 },
+                SetUserProfile(user, profile) => {
+                    let profile = localaccount::set_user_profile(&user, &profile, true, job.expires()).await?;
+                    job.completed(profile)
+                },
+                UpdateUserProfile(user, profile) => {
+                    let profile = localaccount::set_user_profile(&user, &profile, false, job.expires()).await?;
+                    job.completed(profile)
+                },
+                SetUserRole(user, role) => {
+                    // local accounts have no managers group, so the role
+                    // only matters to the scheduler
+                    tracing::info!("Role of {} set to '{}' - nothing to change locally", user, role);
+                    let mapping = localaccount::get_user_mapping(&user, job.expires()).await?;
+                    job.completed(mapping)
+                },
                 GetProjectMapping(project) => {
                     let mapping = localaccount::get_project_mapping(&project, job.expires()).await?;
                     job.completed(mapping)
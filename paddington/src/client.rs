@@ -6,6 +6,7 @@ use crate::connection::Connection;
 use crate::error::Error;
 use crate::exchange;
 use crate::healthcheck;
+use crate::rotation;
 
 pub async fn run_once(config: ServiceConfig, peer: PeerConfig) -> Result<(), Error> {
     let service_name = config.name();
@@ -50,13 +51,43 @@ pub async fn run(config: ServiceConfig, peer: PeerConfig) -> Result<(), Error> {
         healthcheck::spawn(config.ip(), healthcheck_port).await?;
     }
 
+    // whether to dial with the keys that in-band rotation replaced, which
+    // is tried in turn with the current keys if those are refused
+    let mut use_previous_keys = false;
+
     loop {
-        match run_once(config.clone(), peer.clone()).await {
+        let config = rotation::live_config(&config);
+        let server = config
+            .servers()
+            .into_iter()
+            .find(|s| s.name() == peer.name() && s.zone() == peer.zone());
+
+        let (peer_to_dial, previous_keys) = match server {
+            Some(server) => {
+                let previous = server.with_previous_keys();
+
+                match (&previous, use_previous_keys) {
+                    (Some(previous), true) => {
+                        tracing::info!(
+                            "Trying the previous keys of {}, as the current ones were refused",
+                            previous.name()
+                        );
+                        (previous.to_peer(), true)
+                    }
+                    _ => (server.to_peer(), previous.is_some()),
+                }
+            }
+            None => (peer.clone(), false),
+        };
+
+        match run_once(config, peer_to_dial).await {
             Ok(_) => {
                 tracing::info!("Client exited successfully.");
+                use_previous_keys = false;
             }
             Err(e) => {
                 tracing::error!("Client exited with error: {:?}", e);
+                use_previous_keys = previous_keys && !use_previous_keys;
             }
         }
 
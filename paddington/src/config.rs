@@ -1,11 +1,12 @@
 // SPDX-FileCopyrightText: © 2024 Christopher Woods <Christopher.Woods@bristol.ac.uk>
 // SPDX-License-Identifier: MIT
 
-use crate::crypto::{Key, SecretKey};
+use crate::crypto::{Key, PublicKey, SecretKey};
 use crate::error::Error;
 use crate::invite::Invite;
 
 use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
 use iptools::iprange::IpRange;
 use secrecy::zeroize::Zeroizing;
 use secrecy::ExposeSecret;
@@ -19,6 +20,10 @@ fn default_zone() -> String {
     "default".to_string()
 }
 
+fn key_is_null(key: &SecretKey) -> bool {
+    key.expose_secret().is_null()
+}
+
 pub fn load<T: serde::de::DeserializeOwned + serde::Serialize>(
     config_file: &path::PathBuf,
 ) -> Result<T, Error> {
@@ -59,6 +64,42 @@ pub fn save<T: serde::de::DeserializeOwned + serde::Serialize>(
     Ok(())
 }
 
+///
+/// A config file that holds a `ServiceConfig`, so that the peer keys in it
+/// can be kept in the service's external secret backend (if it has one)
+/// rather than in the file.
+///
+pub trait HasServiceConfig {
+    fn service_config(&self) -> &ServiceConfig;
+    fn service_config_mut(&mut self) -> &mut ServiceConfig;
+}
+
+///
+/// As `load`, but also fetch back any peer keys that `save_service_config`
+/// moved into the service's external secret backend.
+///
+pub fn load_service_config<T>(config_file: &path::PathBuf) -> Result<T, Error>
+where
+    T: serde::de::DeserializeOwned + serde::Serialize + HasServiceConfig,
+{
+    let mut config: T = load(config_file)?;
+    config.service_config_mut().fetch_peer_keys()?;
+    Ok(config)
+}
+
+///
+/// As `save`, but if the service uses an external secret backend, store
+/// every peer's keys there and write only a reference to them to the file.
+///
+pub fn save_service_config<T>(config: &T, config_file: &path::Path) -> Result<(), Error>
+where
+    T: serde::de::DeserializeOwned + serde::Serialize + HasServiceConfig + Clone,
+{
+    let mut config = config.clone();
+    config.service_config_mut().stash_peer_keys()?;
+    save(&config, config_file)
+}
+
 /// Write `contents` to `path` as an owner-only (mode 0600) file, creating the
 /// parent directory owner-only (mode 0700) if it does not exist.
 ///
@@ -264,8 +305,28 @@ pub struct ServerConfig {
     /// `docs/specifications/security-review-2.md` (finding R3).
     #[serde(default, rename = "type")]
     agent_type: Option<String>,
+    /// Where this peer's keys are kept when the service's `EncryptionScheme`
+    /// is an external secret backend - the keys are then left out of the
+    /// config file, and are fetched back by `load_service_config`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keys: Option<String>,
+    /// The peer's Ed25519 identity, if it authenticates with a keypair
+    /// rather than the pre-shared `inner_key`/`outer_key` (which are then
+    /// null) - see `Connection`'s public-key handshake.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public_key: Option<PublicKey>,
+    #[serde(default = "Key::null", skip_serializing_if = "key_is_null")]
     inner_key: SecretKey,
+    #[serde(default = "Key::null", skip_serializing_if = "key_is_null")]
     outer_key: SecretKey,
+    /// When `inner_key`/`outer_key` were last rotated, if this is known -
+    /// keys of unknown age are due for in-band rotation straight away
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keys_rotated: Option<DateTime<Utc>>,
+    /// The keys replaced by the last in-band rotation, which are still
+    /// accepted until they expire - see `crate::rotation`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_keys: Option<PreviousKeys>,
 }
 
 impl Display for ServerConfig {
@@ -324,8 +385,12 @@ impl ServerConfig {
             proxy: None,
             zone: zone.to_string(),
             agent_type: None,
+            keys: None,
+            public_key: None,
             inner_key: Key::generate(),
             outer_key: Key::generate(),
+            keys_rotated: Some(Utc::now()),
+            previous_keys: None,
         }
     }
 
@@ -340,8 +405,12 @@ impl ServerConfig {
             // Absent for invites written by older versions, which then simply
             // are not checked.
             agent_type: invite.agent_type(),
+            keys: None,
+            public_key: invite.public_key(),
             inner_key: invite.inner_key(),
             outer_key: invite.outer_key(),
+            keys_rotated: Some(Utc::now()),
+            previous_keys: None,
         })
     }
 
@@ -356,14 +425,25 @@ impl ServerConfig {
             return Err(Error::Peer("No relay name provided.".to_string()));
         }
 
+        // the relay carries the pre-shared-key handshake only
+        if invite.public_key().is_some() {
+            return Err(Error::Peer(
+                "A relayed peer cannot use a public-key identity.".to_string(),
+            ));
+        }
+
         Ok(ServerConfig {
             name: invite.name(),
             url: "".to_string(),
             proxy: Some(relay.trim().to_string()),
             zone: invite.zone(),
             agent_type: invite.agent_type(),
+            keys: None,
+            public_key: None,
             inner_key: invite.inner_key(),
             outer_key: invite.outer_key(),
+            keys_rotated: Some(Utc::now()),
+            previous_keys: None,
         })
     }
 
@@ -374,8 +454,12 @@ impl ServerConfig {
             proxy: None,
             zone: "".to_string(),
             agent_type: None,
+            keys: None,
+            public_key: None,
             inner_key: Key::null(),
             outer_key: Key::null(),
+            keys_rotated: None,
+            previous_keys: None,
         }
     }
 
@@ -436,6 +520,12 @@ impl ServerConfig {
         self.outer_key.clone()
     }
 
+    /// The peer's Ed25519 identity, if it authenticates with a keypair
+    /// rather than pre-shared keys
+    pub fn public_key(&self) -> Option<PublicKey> {
+        self.public_key.clone()
+    }
+
     pub fn rotate_keys(&mut self, invite: &Invite) -> Result<(), Error> {
         // verify that the name and zone match the invite
         if self.name != invite.name() || self.zone != invite.zone() {
@@ -445,12 +535,81 @@ impl ServerConfig {
             )));
         }
 
-        // copy the keys from the invite
+        // copy the keys (or the public key) from the invite
         self.inner_key = invite.inner_key();
         self.outer_key = invite.outer_key();
+        self.public_key = invite.public_key();
+        self.keys_rotated = Some(Utc::now());
+        self.previous_keys = None;
 
         Ok(())
     }
+
+    /// When this peer's pre-shared keys were last rotated, if known
+    pub fn keys_rotated(&self) -> Option<DateTime<Utc>> {
+        self.keys_rotated
+    }
+
+    ///
+    /// Switch to keys received by in-band rotation, keeping the current
+    /// ones as the previous keys until `rotated + grace`. Previous keys
+    /// that have not yet expired are kept instead - if the server never
+    /// heard that we accepted them, it is still using those.
+    ///
+    pub(crate) fn replace_keys(
+        &mut self,
+        inner_key: &SecretKey,
+        outer_key: &SecretKey,
+        rotated: &DateTime<Utc>,
+        grace: &Duration,
+    ) {
+        self.previous_keys = match self.previous_keys.take() {
+            Some(previous) if !previous.has_expired() => Some(previous),
+            _ => Some(PreviousKeys {
+                inner_key: self.inner_key.clone(),
+                outer_key: self.outer_key.clone(),
+                expires: *rotated + *grace,
+            }),
+        };
+        self.inner_key = inner_key.clone();
+        self.outer_key = outer_key.clone();
+        self.keys_rotated = Some(*rotated);
+    }
+
+    ///
+    /// A copy of this peer that uses its previous keys, if they have not
+    /// yet expired - to dial with when the current keys are refused
+    ///
+    pub(crate) fn with_previous_keys(&self) -> Option<ServerConfig> {
+        let previous = self.previous_keys.as_ref()?;
+
+        if previous.has_expired() {
+            return None;
+        }
+
+        let mut server = self.clone();
+        server.inner_key = previous.inner_key.clone();
+        server.outer_key = previous.outer_key.clone();
+        server.previous_keys = None;
+        Some(server)
+    }
+}
+
+///
+/// The pre-shared keys a peer used before its last in-band rotation, and
+/// when they stop being accepted
+///
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PreviousKeys {
+    inner_key: SecretKey,
+    outer_key: SecretKey,
+    expires: DateTime<Utc>,
+}
+
+impl PreviousKeys {
+    fn has_expired(&self) -> bool {
+        self.expires <= Utc::now()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize)]
@@ -754,8 +913,28 @@ pub struct ClientConfig {
     /// `docs/specifications/security-review-2.md` (finding R3).
     #[serde(default, rename = "type")]
     agent_type: Option<String>,
+    /// Where this peer's keys are kept when the service's `EncryptionScheme`
+    /// is an external secret backend - the keys are then left out of the
+    /// config file, and are fetched back by `load_service_config`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keys: Option<String>,
+    /// The peer's Ed25519 identity, if it authenticates with a keypair
+    /// rather than the pre-shared `inner_key`/`outer_key` (which are then
+    /// null) - see `Connection`'s public-key handshake.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public_key: Option<PublicKey>,
+    #[serde(default = "Key::null", skip_serializing_if = "key_is_null")]
     inner_key: SecretKey,
+    #[serde(default = "Key::null", skip_serializing_if = "key_is_null")]
     outer_key: SecretKey,
+    /// When `inner_key`/`outer_key` were last rotated, if this is known -
+    /// keys of unknown age are due for in-band rotation straight away
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keys_rotated: Option<DateTime<Utc>>,
+    /// The keys replaced by the last in-band rotation, which are still
+    /// accepted until they expire - see `crate::rotation`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_keys: Option<PreviousKeys>,
 }
 
 impl Display for ClientConfig {
@@ -788,8 +967,12 @@ impl ClientConfig {
             proxy: None,
             zone: zone.to_string(),
             agent_type: agent_type.clone(),
+            keys: None,
+            public_key: None,
             inner_key: Key::generate(),
             outer_key: Key::generate(),
+            keys_rotated: Some(Utc::now()),
+            previous_keys: None,
         }
     }
 
@@ -815,8 +998,12 @@ impl ClientConfig {
             proxy: Some(relay.trim().to_string()),
             zone: zone.to_string(),
             agent_type: agent_type.clone(),
+            keys: None,
+            public_key: None,
             inner_key: Key::generate(),
             outer_key: Key::generate(),
+            keys_rotated: Some(Utc::now()),
+            previous_keys: None,
         })
     }
 
@@ -828,8 +1015,12 @@ impl ClientConfig {
             proxy: None,
             zone: "".to_string(),
             agent_type: None,
+            keys: None,
+            public_key: None,
             inner_key: Key::null(),
             outer_key: Key::null(),
+            keys_rotated: None,
+            previous_keys: None,
         }
     }
 
@@ -897,9 +1088,62 @@ impl ClientConfig {
         self.outer_key.clone()
     }
 
+    /// The peer's Ed25519 identity, if it authenticates with a keypair
+    /// rather than pre-shared keys
+    pub fn public_key(&self) -> Option<PublicKey> {
+        self.public_key.clone()
+    }
+
     pub fn rotate_keys(&mut self) {
         self.inner_key = Key::generate();
         self.outer_key = Key::generate();
+        self.keys_rotated = Some(Utc::now());
+        self.previous_keys = None;
+    }
+
+    /// When this peer's pre-shared keys were last rotated, if known
+    pub fn keys_rotated(&self) -> Option<DateTime<Utc>> {
+        self.keys_rotated
+    }
+
+    ///
+    /// Switch to keys agreed by in-band rotation, keeping the current ones
+    /// as the previous keys until `rotated + grace`
+    ///
+    pub(crate) fn replace_keys(
+        &mut self,
+        inner_key: &SecretKey,
+        outer_key: &SecretKey,
+        rotated: &DateTime<Utc>,
+        grace: &Duration,
+    ) {
+        self.previous_keys = Some(PreviousKeys {
+            inner_key: self.inner_key.clone(),
+            outer_key: self.outer_key.clone(),
+            expires: *rotated + *grace,
+        });
+        self.inner_key = inner_key.clone();
+        self.outer_key = outer_key.clone();
+        self.keys_rotated = Some(*rotated);
+    }
+
+    ///
+    /// A copy of this peer that uses its previous keys, if they have not
+    /// yet expired - so that a client still using them is accepted during
+    /// the grace period
+    ///
+    pub(crate) fn with_previous_keys(&self) -> Option<ClientConfig> {
+        let previous = self.previous_keys.as_ref()?;
+
+        if previous.has_expired() {
+            return None;
+        }
+
+        let mut client = self.clone();
+        client.inner_key = previous.inner_key.clone();
+        client.outer_key = previous.outer_key.clone();
+        client.previous_keys = None;
+        Some(client)
     }
 }
 
@@ -982,6 +1226,23 @@ impl PeerConfig {
 /// See docs/specifications/security-review.md (finding F2).
 const SECRET_V1_PREFIX: &str = "op-secret-v1:";
 
+/// Prefix marking a secret kept in an external store (`VaultKv` or `Exec`) -
+/// what follows is the name it is stored under, not the secret.
+const SECRET_REF_PREFIX: &str = "op-secret-ref:";
+
+/// Prefix marking a secret encrypted by a Vault transit key (`VaultTransit`)
+const SECRET_TRANSIT_PREFIX: &str = "op-secret-transit:";
+
+///
+/// Return whether `value` is a secret written by `ServiceConfig::encrypt`
+/// or `store_secret` in any of the current formats
+///
+pub fn is_stored_secret(value: &str) -> bool {
+    [SECRET_V1_PREFIX, SECRET_REF_PREFIX, SECRET_TRANSIT_PREFIX]
+        .iter()
+        .any(|prefix| value.starts_with(prefix))
+}
+
 /// Length (bytes) of the per-secret random salt used by v1 encryption.
 const SECRET_SALT_SIZE: usize = 16;
 
@@ -1002,9 +1263,85 @@ pub enum EncryptionScheme {
     /// development or low-security deployments. Use `Environment` in
     /// production. See docs/specifications/security-review.md (finding F2).
     Simple {},
-    /*Vault {
-        url: String,
-    }*/
+    /// Keep each secret in a HashiCorp Vault KV (v2) engine mounted at
+    /// `mount`, at `<path>/<service name>/<secret name>`. The config holds
+    /// only a reference (`op-secret-ref:<secret name>`), never the secret.
+    /// The `vault` CLI is run against `address`, authenticated however the
+    /// agent's environment provides (e.g. `VAULT_TOKEN`).
+    VaultKv {
+        address: String,
+        mount: String,
+        path: String,
+    },
+    /// Encrypt each secret with the named key of a Vault transit engine
+    /// mounted at `mount`. The config holds Vault's ciphertext
+    /// (`op-secret-transit:vault:v1:...`), which is useless without access
+    /// to the key - and the key never leaves Vault.
+    VaultTransit {
+        address: String,
+        mount: String,
+        key: String,
+    },
+    /// Keep each secret with a site-provided helper command, run as
+    /// `<command...> store <service>/<name>` (value on stdin) and
+    /// `<command...> fetch <service>/<name>` (value on stdout). The config
+    /// holds only a reference (`op-secret-ref:<name>`).
+    Exec { command: Vec<String> },
+}
+
+/// A peer's keys, as kept in an external secret backend
+#[derive(Serialize, Deserialize)]
+struct PeerKeys {
+    inner_key: SecretKey,
+    outer_key: SecretKey,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_keys: Option<PreviousKeys>,
+}
+
+/// A century - longer than any sensible interval, and well inside what
+/// `chrono::Duration` can hold
+const MAX_KEY_ROTATION_HOURS: u64 = 100 * 365 * 24;
+
+///
+/// How often a server proposes fresh pre-shared keys to each of its
+/// clients over their connection, and how long the replaced keys are still
+/// accepted afterwards - see `crate::rotation`
+///
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct KeyRotation {
+    interval_hours: u64,
+    grace_hours: u64,
+}
+
+impl KeyRotation {
+    pub fn new(interval_hours: u64, grace_hours: u64) -> Result<Self, Error> {
+        if interval_hours == 0 {
+            return Err(Error::Parse(
+                "The key rotation interval must be at least one hour.".to_string(),
+            ));
+        }
+
+        Ok(KeyRotation {
+            interval_hours,
+            grace_hours,
+        })
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::hours(self.interval_hours.min(MAX_KEY_ROTATION_HOURS) as i64)
+    }
+
+    pub fn grace(&self) -> Duration {
+        Duration::hours(self.grace_hours.min(MAX_KEY_ROTATION_HOURS) as i64)
+    }
+
+    pub fn interval_hours(&self) -> u64 {
+        self.interval_hours
+    }
+
+    pub fn grace_hours(&self) -> u64 {
+        self.grace_hours
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -1031,6 +1368,18 @@ pub struct ServiceConfig {
     servers: Vec<ServerConfig>,
     clients: Vec<ClientConfig>,
     encryption: Option<EncryptionScheme>,
+
+    /// Seed of this service's Ed25519 identity keypair, used to
+    /// authenticate to (and as) peers that have a `public_key`. Generated
+    /// on first use - see `ensure_identity`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    identity: Option<SecretKey>,
+
+    /// Scheduled in-band rotation of the keys of this service's clients,
+    /// if enabled. Only the server side of a connection proposes keys, so
+    /// this has no effect on a service without clients.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_rotation: Option<KeyRotation>,
 }
 
 impl ServiceConfig {
@@ -1072,6 +1421,8 @@ impl ServiceConfig {
             servers: Vec::new(),
             clients: Vec::new(),
             encryption: None,
+            identity: None,
+            key_rotation: None,
         })
     }
 
@@ -1101,6 +1452,10 @@ impl ServiceConfig {
                 // (see `EncryptionScheme::Simple`).
                 Ok(Zeroizing::new(self.name.clone()))
             }
+            Some(scheme) => Err(Error::Incompatible(format!(
+                "The {:?} scheme keeps secrets in an external backend, and has no password.",
+                scheme
+            ))),
             None => Err(Error::Null(
                 "No encryption in use. Please choose a scheme from the options provided."
                     .to_string(),
@@ -1120,15 +1475,344 @@ impl ServiceConfig {
         Ok(())
     }
 
+    pub fn set_vault_kv_encryption(
+        &mut self,
+        address: &str,
+        mount: &str,
+        path: &str,
+    ) -> Result<(), Error> {
+        if address.trim().is_empty() || mount.trim().is_empty() || path.trim().is_empty() {
+            return Err(Error::Parse(
+                "Vault KV encryption needs an address, mount and path.".to_string(),
+            ));
+        }
+
+        self.encryption = Some(EncryptionScheme::VaultKv {
+            address: address.trim().to_string(),
+            mount: mount.trim().to_string(),
+            path: path.trim().trim_end_matches('/').to_string(),
+        });
+        Ok(())
+    }
+
+    pub fn set_vault_transit_encryption(
+        &mut self,
+        address: &str,
+        mount: &str,
+        key: &str,
+    ) -> Result<(), Error> {
+        if address.trim().is_empty() || mount.trim().is_empty() || key.trim().is_empty() {
+            return Err(Error::Parse(
+                "Vault transit encryption needs an address, mount and key.".to_string(),
+            ));
+        }
+
+        self.encryption = Some(EncryptionScheme::VaultTransit {
+            address: address.trim().to_string(),
+            mount: mount.trim().trim_end_matches('/').to_string(),
+            key: key.trim().to_string(),
+        });
+        Ok(())
+    }
+
+    pub fn set_exec_encryption(&mut self, command: &[String]) -> Result<(), Error> {
+        if command
+            .first()
+            .is_none_or(|program| program.trim().is_empty())
+        {
+            return Err(Error::Parse(
+                "Exec encryption needs a helper command.".to_string(),
+            ));
+        }
+
+        self.encryption = Some(EncryptionScheme::Exec {
+            command: command.to_vec(),
+        });
+        Ok(())
+    }
+
+    ///
+    /// Return whether secrets are kept in an external backend, rather than
+    /// encrypted into the config file with a locally-derived key
+    ///
+    pub fn uses_external_secrets(&self) -> bool {
+        matches!(
+            self.encryption,
+            Some(EncryptionScheme::VaultKv { .. })
+                | Some(EncryptionScheme::VaultTransit { .. })
+                | Some(EncryptionScheme::Exec { .. })
+        )
+    }
+
+    ///
+    /// Hand `value` to the external backend under `name`, returning what
+    /// should be written to the config in its place. Only valid when
+    /// `uses_external_secrets` is true.
+    ///
+    fn store_external(&self, name: &str, value: &str) -> Result<String, Error> {
+        let stored_name = format!("{}/{}", self.name, name);
+
+        match &self.encryption {
+            Some(EncryptionScheme::VaultKv {
+                address,
+                mount,
+                path,
+            }) => {
+                crate::secretstore::vault_kv_put(address, mount, path, &stored_name, value)?;
+                Ok(format!("{}{}", SECRET_REF_PREFIX, name))
+            }
+            Some(EncryptionScheme::VaultTransit {
+                address,
+                mount,
+                key,
+            }) => Ok(format!(
+                "{}{}",
+                SECRET_TRANSIT_PREFIX,
+                crate::secretstore::vault_transit_encrypt(address, mount, key, value)?
+            )),
+            Some(EncryptionScheme::Exec { command }) => {
+                crate::secretstore::exec_store(command, &stored_name, value)?;
+                Ok(format!("{}{}", SECRET_REF_PREFIX, name))
+            }
+            _ => Err(Error::Incompatible(
+                "This service does not use an external secret backend.".to_string(),
+            )),
+        }
+    }
+
+    ///
+    /// Fetch the value that `store_external` replaced with `stored`, or
+    /// return `None` if `stored` is not in an external format
+    ///
+    fn fetch_external(&self, stored: &str) -> Result<Option<Zeroizing<String>>, Error> {
+        let (reference, ciphertext) = match (
+            stored.strip_prefix(SECRET_REF_PREFIX),
+            stored.strip_prefix(SECRET_TRANSIT_PREFIX),
+        ) {
+            (Some(name), _) => (Some(name), None),
+            (_, Some(ciphertext)) => (None, Some(ciphertext)),
+            _ => return Ok(None),
+        };
+
+        let value = match (&self.encryption, reference, ciphertext) {
+            (
+                Some(EncryptionScheme::VaultKv {
+                    address,
+                    mount,
+                    path,
+                }),
+                Some(name),
+                _,
+            ) => crate::secretstore::vault_kv_get(
+                address,
+                mount,
+                path,
+                &format!("{}/{}", self.name, name),
+            )?,
+            (Some(EncryptionScheme::Exec { command }), Some(name), _) => {
+                crate::secretstore::exec_fetch(command, &format!("{}/{}", self.name, name))?
+            }
+            (
+                Some(EncryptionScheme::VaultTransit {
+                    address,
+                    mount,
+                    key,
+                }),
+                _,
+                Some(ciphertext),
+            ) => crate::secretstore::vault_transit_decrypt(address, mount, key, ciphertext)?,
+            _ => {
+                // the value names the backend it came from, but that is not
+                // (or no longer) the configured one
+                return Err(Error::Incompatible(format!(
+                    "This secret was stored by a different encryption scheme than the \
+                     one now configured ({:?}). Re-run the `secret` command to store it again.",
+                    self.encryption
+                )));
+            }
+        };
+
+        Ok(Some(value))
+    }
+
+    ///
+    /// Store a secret value under `name` (e.g. the config key it belongs to),
+    /// returning what should be written to the config in its place. With an
+    /// external backend the value is kept there under `name`; otherwise this
+    /// is the same as `encrypt`.
+    ///
+    pub fn store_secret<T>(&self, name: &str, data: &T) -> Result<String, Error>
+    where
+        T: Serialize,
+    {
+        if !self.uses_external_secrets() {
+            return self.encrypt(data);
+        }
+
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+        {
+            return Err(Error::Parse(format!(
+                "Secret name '{}' is invalid. It must be non-empty and alphanumeric or - _ .",
+                name
+            )));
+        }
+
+        let value = Zeroizing::new(
+            serde_json::to_string(data).with_context(|| "Could not serialise the secret")?,
+        );
+
+        self.store_external(name, &value)
+    }
+
+    ///
+    /// Move the keys of every peer into the external secret backend, leaving
+    /// only a reference to them - or, if there is no external backend, make
+    /// sure every peer's keys are held inline again. Called on a copy of the
+    /// config just before it is written (see `save_service_config`).
+    ///
+    fn stash_peer_keys(&mut self) -> Result<(), Error> {
+        let external = self.uses_external_secrets();
+        let mut stashed = Vec::new();
+
+        for (kind, name, inner_key, outer_key, previous_keys) in self
+            .servers
+            .iter()
+            .map(|s| {
+                (
+                    "server",
+                    &s.name,
+                    &s.inner_key,
+                    &s.outer_key,
+                    &s.previous_keys,
+                )
+            })
+            .chain(self.clients.iter().map(|c| {
+                (
+                    "client",
+                    &c.name,
+                    &c.inner_key,
+                    &c.outer_key,
+                    &c.previous_keys,
+                )
+            }))
+        {
+            // a peer with a public-key identity has no pre-shared keys
+            if !external || inner_key.expose_secret().is_null() {
+                stashed.push(None);
+                continue;
+            }
+
+            let keys = PeerKeys {
+                inner_key: inner_key.clone(),
+                outer_key: outer_key.clone(),
+                previous_keys: previous_keys.clone(),
+            };
+
+            stashed.push(Some(
+                self.store_secret(&format!("peer-{}-{}", kind, name), &keys)?,
+            ));
+        }
+
+        let mut stashed = stashed.into_iter();
+
+        for (keys, inner_key, outer_key, previous_keys) in self
+            .servers
+            .iter_mut()
+            .map(|s| {
+                (
+                    &mut s.keys,
+                    &mut s.inner_key,
+                    &mut s.outer_key,
+                    &mut s.previous_keys,
+                )
+            })
+            .chain(self.clients.iter_mut().map(|c| {
+                (
+                    &mut c.keys,
+                    &mut c.inner_key,
+                    &mut c.outer_key,
+                    &mut c.previous_keys,
+                )
+            }))
+        {
+            *keys = stashed.next().flatten();
+
+            if keys.is_some() {
+                *inner_key = Key::null();
+                *outer_key = Key::null();
+                *previous_keys = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Fetch back the keys of every peer that `stash_peer_keys` moved into
+    /// the external secret backend (see `load_service_config`)
+    ///
+    fn fetch_peer_keys(&mut self) -> Result<(), Error> {
+        let mut fetched = Vec::new();
+
+        for (name, keys) in self
+            .servers
+            .iter()
+            .map(|s| (&s.name, &s.keys))
+            .chain(self.clients.iter().map(|c| (&c.name, &c.keys)))
+        {
+            match keys {
+                Some(keys) => {
+                    let keys: PeerKeys = self
+                        .decrypt(keys)
+                        .with_context(|| format!("Could not fetch the keys of peer '{}'", name))?;
+                    fetched.push(Some(keys));
+                }
+                None => fetched.push(None),
+            }
+        }
+
+        let mut fetched = fetched.into_iter();
+
+        for (inner_key, outer_key, previous_keys) in self
+            .servers
+            .iter_mut()
+            .map(|s| (&mut s.inner_key, &mut s.outer_key, &mut s.previous_keys))
+            .chain(
+                self.clients
+                    .iter_mut()
+                    .map(|c| (&mut c.inner_key, &mut c.outer_key, &mut c.previous_keys)),
+            )
+        {
+            if let Some(keys) = fetched.next().flatten() {
+                *inner_key = keys.inner_key;
+                *outer_key = keys.outer_key;
+                *previous_keys = keys.previous_keys;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Encrypt a secret value for storage in the config (`extras`), using the
     /// versioned (v1) format: a fresh random salt, a strong salted Argon2
     /// derivation, then XChaCha20-Poly1305 AEAD. The salt is stored alongside
     /// the ciphertext (`op-secret-v1:<hex salt>:<hex ciphertext>`). See
     /// docs/specifications/security-review.md (finding F2).
+    ///
+    /// With an external backend the value is instead kept there, under a
+    /// random name - use `store_secret` to choose the name.
     pub fn encrypt<T>(&self, data: &T) -> Result<String, Error>
     where
         T: Serialize,
     {
+        if self.uses_external_secrets() {
+            let name = format!("secret-{}", hex::encode(crate::crypto::random_bytes(8)?));
+            return self.store_secret(&name, data);
+        }
+
         let password = self.get_password()?;
         let salt = crate::crypto::random_bytes(SECRET_SALT_SIZE)?;
         let key = Key::from_password_with_salt(&password, &salt)?;
@@ -1145,10 +1829,18 @@ impl ServiceConfig {
     /// the `op-secret-v1:` prefix use the salted strong derivation; any other
     /// value is treated as a legacy (v0) secret and decrypted with the old
     /// fixed-salt derivation, so pre-existing config files keep working.
+    ///
+    /// Values carrying the `op-secret-ref:` or `op-secret-transit:` prefix
+    /// are fetched from (or decrypted by) the external backend.
     pub fn decrypt<T>(&self, data: &str) -> Result<T, Error>
     where
         T: for<'de> Deserialize<'de>,
     {
+        if let Some(value) = self.fetch_external(data)? {
+            return Ok(serde_json::from_str(&value)
+                .with_context(|| "Could not deserialise the secret from the backend")?);
+        }
+
         let password = self.get_password()?;
 
         if let Some(rest) = data.strip_prefix(SECRET_V1_PREFIX) {
@@ -1348,6 +2040,70 @@ impl ServiceConfig {
         ))
     }
 
+    ///
+    /// Add a client that authenticates with the Ed25519 identity
+    /// `public_key` (as printed by its `identity` command) rather than with
+    /// pre-shared keys. The returned invite carries only this service's own
+    /// public key and its fingerprint, so nothing in it is secret. This
+    /// service's identity is generated if it does not have one yet.
+    ///
+    pub fn add_client_with_public_key(
+        &mut self,
+        name: &str,
+        ip: &str,
+        zone: &Option<String>,
+        agent_type: &Option<String>,
+        public_key: &PublicKey,
+    ) -> Result<Invite, Error> {
+        let identity = self.ensure_identity()?;
+
+        let invite = self.add_client(name, ip, zone, agent_type)?;
+
+        let client = self
+            .clients
+            .iter_mut()
+            .find(|c| c.name == name && c.zone == invite.zone())
+            .ok_or_else(|| Error::Peer(format!("Client with name '{}' was not added.", name)))?;
+
+        client.public_key = Some(public_key.clone());
+        client.inner_key = Key::null();
+        client.outer_key = Key::null();
+
+        Ok(Invite::with_public_key(
+            &self.name,
+            &self.url,
+            &invite.zone(),
+            &identity,
+        ))
+    }
+
+    ///
+    /// Return the public key of this service's Ed25519 identity, generating
+    /// the identity first if it does not have one
+    ///
+    pub fn ensure_identity(&mut self) -> Result<PublicKey, Error> {
+        if self.identity.is_none() {
+            self.identity = Some(Key::generate());
+        }
+
+        self.identity_public_key()?
+            .ok_or_else(|| Error::Null("This service has no identity.".to_string()))
+    }
+
+    ///
+    /// Return the public key of this service's Ed25519 identity, if it has one
+    ///
+    pub fn identity_public_key(&self) -> Result<Option<PublicKey>, Error> {
+        match &self.identity {
+            Some(identity) => Ok(Some(identity.expose_secret().identity_public_key()?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn identity(&self) -> Option<SecretKey> {
+        self.identity.clone()
+    }
+
     ///
     /// Add a client that can only reach us via the blind relay proxy
     /// named `relay` (an existing `servers` entry), rather than a direct,
@@ -1558,6 +2314,14 @@ impl ServiceConfig {
                 ))
             })?;
 
+        if client.public_key.is_some() {
+            return Err(Error::Peer(format!(
+                "Client '{}' authenticates with its public key, so has no pre-shared keys to \
+                 rotate. Session keys are already new for every connection.",
+                name
+            )));
+        }
+
         // rotate the keys
         client.rotate_keys();
 
@@ -1592,6 +2356,78 @@ impl ServiceConfig {
         Ok(())
     }
 
+    /// The scheduled in-band key rotation of this service's clients, if
+    /// enabled
+    pub fn key_rotation(&self) -> Option<KeyRotation> {
+        self.key_rotation.clone()
+    }
+
+    ///
+    /// Enable (or, with `None`, disable) scheduled in-band rotation of the
+    /// keys of this service's clients
+    ///
+    pub fn set_key_rotation(&mut self, key_rotation: Option<KeyRotation>) {
+        self.key_rotation = key_rotation;
+    }
+
+    ///
+    /// Switch the client `name` in `zone` to keys agreed by in-band
+    /// rotation - see `crate::rotation`
+    ///
+    pub(crate) fn replace_client_keys(
+        &mut self,
+        name: &str,
+        zone: &str,
+        inner_key: &SecretKey,
+        outer_key: &SecretKey,
+        rotated: &DateTime<Utc>,
+        grace: &Duration,
+    ) -> Result<(), Error> {
+        let client = self
+            .clients
+            .iter_mut()
+            .find(|c| c.name == name && c.zone == zone)
+            .ok_or_else(|| {
+                Error::Peer(format!(
+                    "Client with name '{}' not found in zone {}.",
+                    name, zone
+                ))
+            })?;
+
+        client.replace_keys(inner_key, outer_key, rotated, grace);
+
+        Ok(())
+    }
+
+    ///
+    /// Switch the server `name` in `zone` to keys it proposed by in-band
+    /// rotation - see `crate::rotation`
+    ///
+    pub(crate) fn replace_server_keys(
+        &mut self,
+        name: &str,
+        zone: &str,
+        inner_key: &SecretKey,
+        outer_key: &SecretKey,
+        rotated: &DateTime<Utc>,
+        grace: &Duration,
+    ) -> Result<(), Error> {
+        let server = self
+            .servers
+            .iter_mut()
+            .find(|s| s.name == name && s.zone == zone)
+            .ok_or_else(|| {
+                Error::Peer(format!(
+                    "Server with name '{}' not found in zone {}.",
+                    name, zone
+                ))
+            })?;
+
+        server.replace_keys(inner_key, outer_key, rotated, grace);
+
+        Ok(())
+    }
+
     pub fn create(
         config_file: &path::PathBuf,
         name: String,
@@ -1881,6 +2717,73 @@ mod tests {
         assert_eq!(decrypted, "legacy-secret");
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_encryption_keeps_secrets_and_peer_keys_out_of_the_config() {
+        // a stand-in helper that keeps secrets as files in a directory
+        let dir = std::env::temp_dir().join(format!(
+            "exec-encryption-{}",
+            hex::encode(crate::crypto::random_bytes(8).unwrap_or_default())
+        ));
+        std::fs::create_dir_all(dir.join("test-service"))
+            .unwrap_or_else(|e| unreachable!("Could not create dir: {:?}", e));
+
+        let script = format!(
+            "case \"$1\" in store) cat > '{dir}'/\"$2\";; fetch) cat '{dir}'/\"$2\";; esac",
+            dir = dir.display()
+        );
+
+        let mut config = simple_config();
+        config
+            .set_exec_encryption(&[
+                "sh".to_string(),
+                "-c".to_string(),
+                script,
+                "helper".to_string(),
+            ])
+            .unwrap_or_else(|e| unreachable!("Could not set encryption: {:?}", e));
+
+        let stored = config
+            .store_secret("freeipa-password", &"hunter2".to_string())
+            .unwrap_or_else(|e| unreachable!("Could not store: {:?}", e));
+        assert_eq!(stored, "op-secret-ref:freeipa-password");
+        assert!(is_stored_secret(&stored));
+
+        let fetched: String = config
+            .decrypt(&stored)
+            .unwrap_or_else(|e| unreachable!("Could not fetch: {:?}", e));
+        assert_eq!(fetched, "hunter2");
+
+        config
+            .add_client("peer", "10.0.0.5", &None, &None)
+            .unwrap_or_else(|e| unreachable!("Could not add client: {:?}", e));
+        let inner_key = config.clients()[0].inner_key();
+
+        let mut stashed = config.clone();
+        stashed
+            .stash_peer_keys()
+            .unwrap_or_else(|e| unreachable!("Could not stash: {:?}", e));
+
+        let toml = toml::to_string(&stashed).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(toml.contains("op-secret-ref:peer-client-peer"));
+        assert!(!toml.contains("inner_key") && !toml.contains("outer_key"));
+
+        let mut loaded: ServiceConfig =
+            toml::from_str(&toml).unwrap_or_else(|e| unreachable!("{:?}", e));
+        loaded
+            .fetch_peer_keys()
+            .unwrap_or_else(|e| unreachable!("Could not fetch keys: {:?}", e));
+        assert!(loaded.clients()[0]
+            .inner_key()
+            .expose_secret()
+            .equals(inner_key.expose_secret()));
+
+        // a secret stored by the helper cannot be read under another scheme
+        assert!(simple_config().decrypt::<String>(&stored).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_may_attempt_connection() {
         let mut config = simple_config();
@@ -2689,4 +3592,118 @@ mod tests {
             .unwrap_or_else(|| unreachable!("someone_else should be a client of airr"));
         assert_eq!(someone_else.proxy(), Some("proxy2".to_string()));
     }
+
+    #[test]
+    fn test_rotated_keys_keep_the_previous_ones_for_the_grace_period() {
+        let mut primary = ServiceConfig::new(
+            "primary",
+            "http://localhost",
+            "127.0.0.1",
+            &5544,
+            &None,
+            &None,
+        )
+        .unwrap_or_else(|e| unreachable!("Cannot create service config: {}", e));
+
+        let mut secondary = ServiceConfig::new(
+            "secondary",
+            "http://localhost",
+            "127.0.0.1",
+            &5545,
+            &None,
+            &None,
+        )
+        .unwrap_or_else(|e| unreachable!("Cannot create service config: {}", e));
+
+        let invite = primary
+            .add_client("secondary", "127.0.0.1", &None, &None)
+            .unwrap_or_else(|e| unreachable!("Cannot add secondary to primary: {}", e));
+        secondary
+            .add_server(&invite)
+            .unwrap_or_else(|e| unreachable!("Cannot add primary to secondary: {}", e));
+
+        let old_inner_key = invite.inner_key();
+        let inner_key = Key::generate();
+        let outer_key = Key::generate();
+        let rotated = Utc::now();
+
+        for grace in [Duration::hours(24), Duration::zero()] {
+            let mut primary = primary.clone();
+
+            primary
+                .replace_client_keys(
+                    "secondary",
+                    "default",
+                    &inner_key,
+                    &outer_key,
+                    &rotated,
+                    &grace,
+                )
+                .unwrap_or_else(|e| unreachable!("Cannot replace keys: {}", e));
+
+            let client = primary
+                .clients()
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| unreachable!("primary should have a client"));
+
+            assert_eq!(client.keys_rotated(), Some(rotated));
+            assert!(client
+                .inner_key()
+                .expose_secret()
+                .equals(inner_key.expose_secret()));
+
+            match client.with_previous_keys() {
+                Some(previous) => {
+                    assert!(grace > Duration::zero());
+                    assert!(previous
+                        .inner_key()
+                        .expose_secret()
+                        .equals(old_inner_key.expose_secret()));
+                }
+                // expired straight away
+                None => assert_eq!(grace, Duration::zero()),
+            }
+        }
+
+        // the client keeps unexpired previous keys when rotated again, as
+        // the server may never have heard that the last keys were accepted
+        for _ in 0..2 {
+            secondary
+                .replace_server_keys(
+                    "primary",
+                    "default",
+                    &Key::generate(),
+                    &Key::generate(),
+                    &Utc::now(),
+                    &Duration::hours(24),
+                )
+                .unwrap_or_else(|e| unreachable!("Cannot replace keys: {}", e));
+        }
+
+        let server = secondary
+            .servers()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| unreachable!("secondary should have a server"))
+            .with_previous_keys()
+            .unwrap_or_else(|| unreachable!("the previous keys should not have expired"));
+
+        assert!(server
+            .inner_key()
+            .expose_secret()
+            .equals(old_inner_key.expose_secret()));
+
+        assert!(primary
+            .replace_client_keys(
+                "unknown",
+                "default",
+                &inner_key,
+                &outer_key,
+                &rotated,
+                &Duration::hours(1)
+            )
+            .is_err());
+        assert!(KeyRotation::new(0, 24).is_err());
+    }
 }
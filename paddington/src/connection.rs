@@ -32,10 +32,11 @@ use tungstenite::handshake::server::{
 use crate::anti_replay::{HandshakeNonceState, NoncedPayload, ReplayWindow};
 use crate::command::Command;
 use crate::config::{ClientConfig, PeerConfig, ServiceConfig};
-use crate::crypto::{random_bytes, Key, Salt, SecretKey, KEY_SIZE};
+use crate::crypto::{random_bytes, EphemeralKey, Key, PublicKey, Salt, SecretKey, KEY_SIZE};
 use crate::error::Error;
 use crate::exchange;
 use crate::message::Message;
+use crate::rotation;
 
 /// Largest WebSocket message (and frame) either side will accept.
 ///
@@ -472,6 +473,227 @@ struct Handshake {
     epoch: Option<u64>,
 }
 
+/// Header with which a client asks for the public-key handshake, for a
+/// server whose invite carried a `public_key`. Without it, the server
+/// expects the pre-shared-key `Handshake`.
+const HANDSHAKE_MODE_HEADER: &str = "openportal-handshake";
+const IDENTITY_HANDSHAKE: &str = "identity-v1";
+
+/// Opens the public-key handshake, sent in the clear by each side - there is
+/// no shared key yet, and nothing here is secret. Each side has a fresh
+/// X25519 `ephemeral` key for this connection alone, which is what gives
+/// forward secrecy: the session keys are derived from those, and the
+/// long-term Ed25519 `identity` keys only ever sign. The server's hello
+/// carries its signature of the transcript; the client's follows in
+/// `IdentityFinish`, as it cannot sign until it has seen the server's
+/// ephemeral key.
+///
+/// A replayed hello gets nowhere - completing the handshake needs the
+/// private half of a fresh ephemeral key - so unlike `Handshake` these need
+/// no nonce.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IdentityHello {
+    identity: PublicKey,
+    ephemeral: PublicKey,
+    engine: String,
+    version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+/// The client's signature of the transcript, sent enveloped in the new
+/// session keys - so it also proves the client derived the same keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IdentityFinish {
+    signature: String,
+}
+
+impl IdentityHello {
+    fn new(identity: &PublicKey, ephemeral: &PublicKey) -> Self {
+        IdentityHello {
+            identity: identity.clone(),
+            ephemeral: ephemeral.clone(),
+            engine: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            signature: None,
+        }
+    }
+}
+
+///
+/// Everything both sides of the public-key handshake agreed on - what each
+/// signs, and what the session keys are bound to. The keys and salts are
+/// fixed length and the strings are NUL-terminated, so this is unambiguous.
+///
+fn identity_transcript(
+    inner_key_salt: &Salt,
+    outer_key_salt: &Salt,
+    client: &IdentityHello,
+    server: &IdentityHello,
+) -> Vec<u8> {
+    let mut transcript = b"openportal-identity-v1\0".to_vec();
+    transcript.extend(inner_key_salt.to_string().as_bytes());
+    transcript.extend(outer_key_salt.to_string().as_bytes());
+
+    for hello in [client, server] {
+        transcript.extend(hello.identity.as_bytes());
+        transcript.extend(hello.ephemeral.as_bytes());
+        transcript.extend(hello.engine.as_bytes());
+        transcript.push(0);
+        transcript.extend(hello.version.as_bytes());
+        transcript.push(0);
+    }
+
+    transcript
+}
+
+///
+/// Derive the session inner and outer keys from the X25519 shared secret,
+/// the connection's salts and the transcript, so that both are unique to
+/// this connection
+///
+fn identity_session_keys(
+    shared: &SecretKey,
+    inner_key_salt: &Salt,
+    outer_key_salt: &Salt,
+    transcript: &[u8],
+) -> Result<(SecretKey, SecretKey), Error> {
+    let bound = crate::crypto::digest(transcript)?;
+
+    Ok((
+        shared
+            .expose_secret()
+            .derive(inner_key_salt, Some(format!("inner:{}", bound).as_bytes()))?,
+        shared
+            .expose_secret()
+            .derive(outer_key_salt, Some(format!("outer:{}", bound).as_bytes()))?,
+    ))
+}
+
+///
+/// The client's half of the public-key handshake, once the server has
+/// replied to `hello` with `response`: check the server is `expected` and
+/// signed the transcript, then derive the session keys. Returns the keys,
+/// the server's hello, and the `IdentityFinish` to send back.
+///
+fn identity_client_finish(
+    identity: &SecretKey,
+    ephemeral: EphemeralKey,
+    hello: &IdentityHello,
+    expected: &PublicKey,
+    response: TokioMessage,
+    inner_key_salt: &Salt,
+    outer_key_salt: &Salt,
+) -> Result<(SecretKey, SecretKey, IdentityHello, TokioMessage), Error> {
+    let server: IdentityHello = serde_json::from_str(response.to_text()?)
+        .with_context(|| "Could not parse the server's identity hello")?;
+
+    if &server.identity != expected {
+        return Err(Error::InvalidPeer(format!(
+            "The server presented identity {}, not the {} in its invite.",
+            server.identity.fingerprint(),
+            expected.fingerprint()
+        )));
+    }
+
+    let transcript = identity_transcript(inner_key_salt, outer_key_salt, hello, &server);
+
+    let signature = hex::decode(server.signature.as_deref().unwrap_or_default())
+        .with_context(|| "Could not decode the server's signature")?;
+
+    expected.verify(&[transcript.as_slice(), b"server"].concat(), &signature)?;
+
+    let shared = ephemeral.agree(&server.ephemeral)?;
+    let (inner_key, outer_key) =
+        identity_session_keys(&shared, inner_key_salt, outer_key_salt, &transcript)?;
+
+    let finish = IdentityFinish {
+        signature: hex::encode(
+            identity
+                .expose_secret()
+                .identity_sign(&[transcript.as_slice(), b"client"].concat())?,
+        ),
+    };
+
+    let finish = envelope_message(
+        finish,
+        &inner_key,
+        &outer_key,
+        inner_key_salt,
+        outer_key_salt,
+    )?;
+
+    Ok((inner_key, outer_key, server, finish))
+}
+
+///
+/// The server's half of the public-key handshake: reply to the client's
+/// `hello`, signing the transcript with this service's `identity`. Returns
+/// the session keys, the transcript (to check the client's `IdentityFinish`
+/// against) and the reply to send.
+///
+fn identity_server_reply(
+    identity: &SecretKey,
+    hello: &IdentityHello,
+    inner_key_salt: &Salt,
+    outer_key_salt: &Salt,
+) -> Result<(SecretKey, SecretKey, Vec<u8>, TokioMessage), Error> {
+    let ephemeral = EphemeralKey::generate()?;
+
+    let mut reply = IdentityHello::new(
+        &identity.expose_secret().identity_public_key()?,
+        &ephemeral.public_key(),
+    );
+
+    let transcript = identity_transcript(inner_key_salt, outer_key_salt, hello, &reply);
+
+    reply.signature =
+        Some(hex::encode(identity.expose_secret().identity_sign(
+            &[transcript.as_slice(), b"server"].concat(),
+        )?));
+
+    let shared = ephemeral.agree(&hello.ephemeral)?;
+    let (inner_key, outer_key) =
+        identity_session_keys(&shared, inner_key_salt, outer_key_salt, &transcript)?;
+
+    let reply = TokioMessage::text(
+        serde_json::to_string(&reply).with_context(|| "Could not serialise identity hello")?,
+    );
+
+    Ok((inner_key, outer_key, transcript, reply))
+}
+
+///
+/// The end of the server's half of the public-key handshake: check that
+/// the client's `IdentityFinish` in `message` was enveloped in the session
+/// keys, and that it signed the `transcript` with the identity in its `hello`
+///
+fn identity_server_finish(
+    hello: &IdentityHello,
+    transcript: &[u8],
+    message: TokioMessage,
+    inner_key: &SecretKey,
+    outer_key: &SecretKey,
+    inner_key_salt: &Salt,
+    outer_key_salt: &Salt,
+) -> Result<(), Error> {
+    let finish = deenvelope_message::<IdentityFinish>(
+        message,
+        inner_key,
+        outer_key,
+        inner_key_salt,
+        outer_key_salt,
+    )
+    .with_context(|| "Error de-enveloping identity proof - closing connection.")?;
+
+    let signature = hex::decode(&finish.signature)
+        .with_context(|| "Could not decode the client's signature")?;
+
+    hello
+        .identity
+        .verify(&[transcript, b"client"].concat(), &signature)
+}
+
 impl Connection {
     pub fn new(config: ServiceConfig) -> Self {
         Connection {
@@ -747,6 +969,15 @@ impl Connection {
             })?,
         );
 
+        if server.public_key().is_some() {
+            request.headers_mut().insert(
+                HANDSHAKE_MODE_HEADER,
+                IDENTITY_HANDSHAKE.parse().with_context(|| {
+                    format!("Error setting handshake mode for WebSocket at: {}", url)
+                })?,
+            );
+        }
+
         let socket = match connect_async_with_config(request, Some(websocket_config()), false).await
         {
             Ok((socket, _)) => socket,
@@ -760,103 +991,184 @@ impl Connection {
         // Split the WebSocket stream into incoming and outgoing parts
         let (mut outgoing, mut incoming) = socket.split();
 
-        // the client generates a handshake that contains the new session outer key,
-        // the name of its comms engine and version, and sends this to the server
-        // using the pre-shared client/server inner and outer keys
-        let outer_key = Key::generate();
-        let handshake_nonce = take_handshake_nonce(&peer_name, &peer_zone).await;
+        let (inner_key, outer_key, peer_engine, peer_version) = match server.public_key() {
+            Some(server_public) => {
+                // the public-key handshake - see `IdentityHello`
+                let Some(identity) = self.config.identity() else {
+                    self.set_error().await;
+                    return Err(Error::InvalidPeer(format!(
+                        "Server '{}' expects a public-key identity, but this service has none. \
+                         Run the `identity` command, and have the server re-add this client \
+                         with the printed key.",
+                        peer_name
+                    )));
+                };
 
-        let handshake = Handshake {
-            session_key: outer_key.clone(),
-            engine: env!("CARGO_PKG_NAME").to_string(),
-            version: env!("CARGO_PKG_VERSION").to_string(),
-            nonce: Some(handshake_nonce),
-            epoch: Some(crate::anti_replay::process_epoch()),
-        };
+                let ephemeral = EphemeralKey::generate()?;
+                let hello = IdentityHello::new(
+                    &identity.expose_secret().identity_public_key()?,
+                    &ephemeral.public_key(),
+                );
 
-        let message = match envelope_message(
-            handshake,
-            &server.inner_key(),
-            &server.outer_key(),
-            &inner_key_salt,
-            &outer_key_salt,
-        ) {
-            Ok(message) => message,
-            Err(e) => {
-                tracing::warn!("Error enveloping message: {:?}", e);
-                self.set_error().await;
-                return Err(e.into());
-            }
-        };
+                let message = TokioMessage::text(
+                    serde_json::to_string(&hello)
+                        .with_context(|| "Could not serialise identity hello")?,
+                );
 
-        if let Err(r) = outgoing.send(message).await {
-            self.set_error().await;
-            return Err(Error::Any(r.into()));
-        }
+                if let Err(r) = outgoing.send(message).await {
+                    self.set_error().await;
+                    return Err(Error::Any(r.into()));
+                }
 
-        // receive the response
-        let response = match incoming.next().await {
-            Some(response) => response,
-            None => {
-                tracing::warn!("Error receiving response from peer. Ensure the peer is valid and the connection is open.");
-                self.set_error().await;
-                return Err(Error::InvalidPeer(
-                    "Error receiving response from peer. Ensure the peer is valid and the connection is open.".to_string(),
-                ));
-            }
-        };
+                let response = match incoming.next().await {
+                    Some(Ok(response)) => response,
+                    Some(Err(e)) => {
+                        tracing::warn!("Error receiving response from peer: {:?}", e);
+                        self.set_error().await;
+                        return Err(Error::Any(e.into()));
+                    }
+                    None => {
+                        tracing::warn!("Error receiving response from peer. Ensure the peer is valid and the connection is open.");
+                        self.set_error().await;
+                        return Err(Error::InvalidPeer(
+                            "Error receiving response from peer. Ensure the peer is valid and the connection is open.".to_string(),
+                        ));
+                    }
+                };
 
-        let response = match response {
-            Ok(response) => response,
-            Err(e) => {
-                tracing::warn!("Error receiving response from peer: {:?}", e);
-                self.set_error().await;
-                return Err(Error::Any(e.into()));
-            }
-        };
+                let (inner_key, outer_key, server_hello, finish) = match identity_client_finish(
+                    &identity,
+                    ephemeral,
+                    &hello,
+                    &server_public,
+                    response,
+                    &inner_key_salt,
+                    &outer_key_salt,
+                ) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        tracing::warn!("Public-key handshake with {} failed: {:?}", peer_name, e);
+                        self.set_error().await;
+                        return Err(e);
+                    }
+                };
 
-        // the server has generated a new session inner key, and has put this into
-        // a handshake with its comms engine and version, and sent that
-        // wrapped using the client/server inner key and the new session outer key
-        let handshake: Handshake = match deenvelope_message(
-            response,
-            &server.inner_key(),
-            &outer_key,
-            &inner_key_salt,
-            &outer_key_salt,
-        ) {
-            Ok(inner_key) => inner_key,
-            Err(e) => {
-                tracing::warn!("Error de-enveloping message: {:?}", e);
-                self.set_error().await;
-                return Err(e.into());
+                if let Err(r) = outgoing.send(finish).await {
+                    self.set_error().await;
+                    return Err(Error::Any(r.into()));
+                }
+
+                (
+                    inner_key,
+                    outer_key,
+                    server_hello.engine,
+                    server_hello.version,
+                )
             }
-        };
+            None => {
+                // the client generates a handshake that contains the new session outer key,
+                // the name of its comms engine and version, and sends this to the server
+                // using the pre-shared client/server inner and outer keys
+                let outer_key = Key::generate();
+                let handshake_nonce = take_handshake_nonce(&peer_name, &peer_zone).await;
+
+                let handshake = Handshake {
+                    session_key: outer_key.clone(),
+                    engine: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    nonce: Some(handshake_nonce),
+                    epoch: Some(crate::anti_replay::process_epoch()),
+                };
 
-        if !check_handshake_replay(&peer_name, &peer_zone, handshake.epoch, handshake.nonce).await {
-            tracing::warn!(
-                "Rejected replayed (or too-old) Handshake response from {}@{} (nonce {:?})",
-                peer_name,
-                peer_zone,
-                handshake.nonce
-            );
-            self.set_error().await;
-            return Err(Error::InvalidPeer(
-                "Rejected replayed Handshake response - closing connection.".to_string(),
-            ));
-        }
+                let message = match envelope_message(
+                    handshake,
+                    &server.inner_key(),
+                    &server.outer_key(),
+                    &inner_key_salt,
+                    &outer_key_salt,
+                ) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        tracing::warn!("Error enveloping message: {:?}", e);
+                        self.set_error().await;
+                        return Err(e.into());
+                    }
+                };
 
-        let inner_key = handshake.session_key.clone();
+                if let Err(r) = outgoing.send(message).await {
+                    self.set_error().await;
+                    return Err(Error::Any(r.into()));
+                }
 
-        // Reject a null (all-zero) session key from the peer (finding F15).
-        // Only the peer could send this, and it would weaken the session, so
-        // refuse rather than proceed with a degenerate key.
-        if inner_key.expose_secret().is_null() {
-            tracing::warn!("Rejected null session key from server - closing connection.");
-            return Err(Error::InvalidPeer(
-                "Peer sent a null session key - closing connection.".to_string(),
-            ));
-        }
+                // receive the response
+                let response = match incoming.next().await {
+                    Some(response) => response,
+                    None => {
+                        tracing::warn!("Error receiving response from peer. Ensure the peer is valid and the connection is open.");
+                        self.set_error().await;
+                        return Err(Error::InvalidPeer(
+                            "Error receiving response from peer. Ensure the peer is valid and the connection is open.".to_string(),
+                        ));
+                    }
+                };
+
+                let response = match response {
+                    Ok(response) => response,
+                    Err(e) => {
+                        tracing::warn!("Error receiving response from peer: {:?}", e);
+                        self.set_error().await;
+                        return Err(Error::Any(e.into()));
+                    }
+                };
+
+                // the server has generated a new session inner key, and has put this into
+                // a handshake with its comms engine and version, and sent that
+                // wrapped using the client/server inner key and the new session outer key
+                let handshake: Handshake = match deenvelope_message(
+                    response,
+                    &server.inner_key(),
+                    &outer_key,
+                    &inner_key_salt,
+                    &outer_key_salt,
+                ) {
+                    Ok(inner_key) => inner_key,
+                    Err(e) => {
+                        tracing::warn!("Error de-enveloping message: {:?}", e);
+                        self.set_error().await;
+                        return Err(e.into());
+                    }
+                };
+
+                if !check_handshake_replay(&peer_name, &peer_zone, handshake.epoch, handshake.nonce)
+                    .await
+                {
+                    tracing::warn!(
+                        "Rejected replayed (or too-old) Handshake response from {}@{} (nonce {:?})",
+                        peer_name,
+                        peer_zone,
+                        handshake.nonce
+                    );
+                    self.set_error().await;
+                    return Err(Error::InvalidPeer(
+                        "Rejected replayed Handshake response - closing connection.".to_string(),
+                    ));
+                }
+
+                let inner_key = handshake.session_key.clone();
+
+                // Reject a null (all-zero) session key from the peer (finding F15).
+                // Only the peer could send this, and it would weaken the session, so
+                // refuse rather than proceed with a degenerate key.
+                if inner_key.expose_secret().is_null() {
+                    tracing::warn!("Rejected null session key from server - closing connection.");
+                    return Err(Error::InvalidPeer(
+                        "Peer sent a null session key - closing connection.".to_string(),
+                    ));
+                }
+
+                (inner_key, outer_key, handshake.engine, handshake.version)
+            }
+        };
 
         // the final step is for the client to send the server its PeerDetails,
         // and for the server to respond. These should match up with
@@ -949,8 +1261,8 @@ impl Connection {
         tracing::info!(
             "Connecting to peer {}, comms engine {} version {}",
             peer_details,
-            handshake.engine,
-            handshake.version
+            peer_engine,
+            peer_version
         );
 
         // eventually we could check the engine and version here,
@@ -1174,6 +1486,17 @@ impl Connection {
                 return future::ok(());
             }
 
+            // key rotation control messages are handled by paddington
+            // itself, never by the message handler - see `crate::rotation`
+            if rotation::is_key_rotation(&msg) {
+                tokio::spawn(rotation::received_from_server(
+                    peer_name.clone(),
+                    peer_zone.clone(),
+                    msg,
+                ));
+                return future::ok(());
+            }
+
             exchange::received(Message::received_from(&peer_name, &peer_zone, &msg))
                 .unwrap_or_else(|e| {
                     tracing::warn!("Error handling message: {:?}", e);
@@ -1189,13 +1512,7 @@ impl Connection {
 
         // now tell ourselves who has connected
         match exchange::received(
-            Command::connected(
-                &peer_name,
-                &peer_zone,
-                &handshake.engine,
-                &handshake.version,
-            )
-            .into(),
+            Command::connected(&peer_name, &peer_zone, &peer_engine, &peer_version).into(),
         ) {
             Ok(_) => (),
             Err(e) => {
@@ -1308,6 +1625,10 @@ impl Connection {
         // docs/specifications/security-review.md (finding F15).
         let mut client_salt_is_plain = false;
 
+        // Whether the client asked for the public-key handshake (see
+        // `IdentityHello`) rather than the pre-shared-key `Handshake`
+        let mut client_wants_identity = false;
+
         let process_headers = |request: &HandshakeRequest,
                                response: HandshakeResponse|
          -> Result<HandshakeResponse, HandshakeErrorResponse> {
@@ -1321,6 +1642,13 @@ impl Connection {
                 }
             }
 
+            client_wants_identity = request
+                .headers()
+                .get(HANDSHAKE_MODE_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value == IDENTITY_HANDSHAKE)
+                .unwrap_or(false);
+
             client_salt_is_plain = request
                 .headers()
                 .get("openportal-salt-format")
@@ -1437,12 +1765,14 @@ impl Connection {
 
         tracing::info!("Accepted connection from peer: {}", client_ip);
 
+        // a client whose keys were rotated in-band may still use its
+        // previous keys during the grace period, so each is tried as well
         let clients: Vec<ClientConfig> = self
             .config
             .clients()
             .iter()
             .filter(|client| client.matches(client_ip))
-            .cloned()
+            .flat_map(|client| std::iter::once(client.clone()).chain(client.with_previous_keys()))
             .collect();
 
         if clients.is_empty() {
@@ -1473,164 +1803,275 @@ impl Connection {
             ));
         }
 
-        // find a client that can de-envelope the message - this is the
-        // client that we will be connecting to
-        let clients: Vec<ClientConfig> = clients
-            .iter()
-            .filter(|client| {
-                // note, could use
-                // deenvelope_message::<SecretKey>(message.clone(), &client.inner_key, &client.outer_key).is_ok()
-                // but then we would lose tracing messages - these are very helpful
-                // to debug issues
-
-                // Un-mask the salts unless the client advertised the plain
-                // format (finding F15). Legacy clients XOR the salts with the
-                // pre-shared keys; the un-masking key is the client's own key,
-                // which is why this is done per candidate client here.
-                let (eff_inner_salt, eff_outer_salt) = if client_salt_is_plain {
-                    (inner_key_salt.clone(), outer_key_salt.clone())
-                } else {
-                    (
-                        inner_key_salt.xor(client.outer_key().expose_secret()),
-                        outer_key_salt.xor(client.inner_key().expose_secret()),
-                    )
+        let (peer, inner_key_salt, outer_key_salt, inner_key, outer_key, peer_engine, peer_version) =
+            if client_wants_identity {
+                // the public-key handshake - see `IdentityHello`
+                if !client_salt_is_plain {
+                    return Err(Error::InvalidPeer(
+                        "The public-key handshake needs plain salts - closing connection."
+                            .to_string(),
+                    ));
+                }
+
+                let hello: IdentityHello = serde_json::from_str(message.to_text()?)
+                    .with_context(|| "Could not parse the client's identity hello")?;
+
+                let Some(peer) = clients
+                    .iter()
+                    .find(|client| client.public_key().as_ref() == Some(&hello.identity))
+                    .cloned()
+                else {
+                    tracing::warn!(
+                        "No client at address {} has identity {}",
+                        client_ip,
+                        hello.identity.fingerprint()
+                    );
+                    return Err(Error::InvalidPeer(
+                        "No matching peer could authenticate for address.".to_string(),
+                    ));
                 };
 
-                match deenvelope_message::<Handshake>(
-                    message.clone(),
-                    &client.inner_key(),
-                    &client.outer_key(),
-                    &eff_inner_salt,
-                    &eff_outer_salt,
-                ) {
-                    Ok(_) => {
-                        tracing::info!(
-                            "Client {:?} authenticated for address: {}",
-                            client.name(),
-                            client_ip
-                        );
-                        true
-                    }
-                    Err(_) => false,
+                if peer.name().is_empty() {
+                    tracing::warn!("Peer must have a name to handle a connection.");
+                    return Err(Error::InvalidPeer(
+                        "Peer must have a name to handle a connection.".to_string(),
+                    ));
                 }
-            })
-            .cloned()
-            .collect();
 
-        if clients.len() > 1 {
-            tracing::warn!(
-                "Multiple matching peers found for address: {} - \
-                    {:?}. Ignoring all but the first...",
-                client_ip,
-                clients
-            );
-        }
+                let Some(identity) = self.config.identity() else {
+                    return Err(Error::InvalidPeer(
+                        "This service has no identity for the public-key handshake.".to_string(),
+                    ));
+                };
 
-        // Taken via `first()` rather than `[0]` so that "no peer matched" is
-        // a single error path that cannot panic - see
-        // docs/specifications/security-review-2.md (finding R1).
-        let Some(peer) = clients.first().cloned() else {
-            tracing::warn!(
-                "No matching peer could authenticate for address: {}",
-                client_ip
-            );
-            return Err(Error::InvalidPeer(
-                "No matching peer could authenticate for address.".to_string(),
-            ));
-        };
+                tracing::info!(
+                    "Initiating public-key connection: {:?} <=> {:?}",
+                    service_name,
+                    peer.name()
+                );
 
-        let peer_name = peer.name();
-        let peer_zone = peer.zone();
+                let (inner_key, outer_key, transcript, reply) =
+                    identity_server_reply(&identity, &hello, &inner_key_salt, &outer_key_salt)?;
 
-        if peer_name.is_empty() {
-            tracing::warn!("Peer must have a name to handle a connection.");
-            return Err(Error::InvalidPeer(
-                "Peer must have a name to handle a connection.".to_string(),
-            ));
-        }
+                outgoing
+                    .send(reply)
+                    .await
+                    .with_context(|| "Error sending response to peer")?;
 
-        tracing::info!(
-            "Initiating connection: {:?} <=> {:?}",
-            service_name,
-            peer_name
-        );
+                let message = incoming.next().await.ok_or_else(|| {
+                    tracing::warn!("No identity proof received - closing connection.");
+                    Error::InvalidPeer(
+                        "No identity proof received - closing connection.".to_string(),
+                    )
+                })??;
 
-        // we have found the right client to xor the salts
-        // Un-mask the selected peer's salts (unless plain - see the filter above).
-        let inner_key_salt = if client_salt_is_plain {
-            inner_key_salt
-        } else {
-            inner_key_salt.xor(peer.outer_key().expose_secret())
-        };
-        let outer_key_salt = if client_salt_is_plain {
-            outer_key_salt
-        } else {
-            outer_key_salt.xor(peer.inner_key().expose_secret())
-        };
+                identity_server_finish(
+                    &hello,
+                    &transcript,
+                    message,
+                    &inner_key,
+                    &outer_key,
+                    &inner_key_salt,
+                    &outer_key_salt,
+                )?;
 
-        // the peer has sent us the new session outer key that should be used,
-        // wrapped in the client/server inner and outer keys
-        let handshake = deenvelope_message::<Handshake>(
-            message,
-            &peer.inner_key(),
-            &peer.outer_key(),
-            &inner_key_salt,
-            &outer_key_salt,
-        )
-        .with_context(|| "Error de-enveloping message - closing connection.")?;
+                tracing::info!(
+                    "Client {:?} authenticated with identity {} for address: {}",
+                    peer.name(),
+                    hello.identity.fingerprint(),
+                    client_ip
+                );
 
-        if !check_handshake_replay(&peer_name, &peer_zone, handshake.epoch, handshake.nonce).await {
-            tracing::warn!(
-                "Rejected replayed (or too-old) Handshake from {}@{} (nonce {:?})",
-                peer_name,
-                peer_zone,
-                handshake.nonce
-            );
-            return Err(Error::InvalidPeer(
-                "Rejected replayed Handshake - closing connection.".to_string(),
-            ));
-        }
+                (
+                    peer,
+                    inner_key_salt,
+                    outer_key_salt,
+                    inner_key,
+                    outer_key,
+                    hello.engine,
+                    hello.version,
+                )
+            } else {
+                // find a client that can de-envelope the message - this is the
+                // client that we will be connecting to
+                let clients: Vec<ClientConfig> = clients
+                    .iter()
+                    .filter(|client| {
+                        // a client with a public-key identity has no pre-shared
+                        // keys, and must never match on the (null) ones
+                        if client.public_key().is_some() {
+                            return false;
+                        }
+
+                        // note, could use
+                        // deenvelope_message::<SecretKey>(message.clone(), &client.inner_key, &client.outer_key).is_ok()
+                        // but then we would lose tracing messages - these are very helpful
+                        // to debug issues
+
+                        // Un-mask the salts unless the client advertised the plain
+                        // format (finding F15). Legacy clients XOR the salts with the
+                        // pre-shared keys; the un-masking key is the client's own key,
+                        // which is why this is done per candidate client here.
+                        let (eff_inner_salt, eff_outer_salt) = if client_salt_is_plain {
+                            (inner_key_salt.clone(), outer_key_salt.clone())
+                        } else {
+                            (
+                                inner_key_salt.xor(client.outer_key().expose_secret()),
+                                outer_key_salt.xor(client.inner_key().expose_secret()),
+                            )
+                        };
+
+                        match deenvelope_message::<Handshake>(
+                            message.clone(),
+                            &client.inner_key(),
+                            &client.outer_key(),
+                            &eff_inner_salt,
+                            &eff_outer_salt,
+                        ) {
+                            Ok(_) => {
+                                tracing::info!(
+                                    "Client {:?} authenticated for address: {}",
+                                    client.name(),
+                                    client_ip
+                                );
+                                true
+                            }
+                            Err(_) => false,
+                        }
+                    })
+                    .cloned()
+                    .collect();
+
+                if clients.len() > 1 {
+                    tracing::warn!(
+                        "Multiple matching peers found for address: {} - \
+                            {:?}. Ignoring all but the first...",
+                        client_ip,
+                        clients
+                    );
+                }
 
-        let outer_key = handshake.session_key.clone();
+                // Taken via `first()` rather than `[0]` so that "no peer matched" is
+                // a single error path that cannot panic - see
+                // docs/specifications/security-review-2.md (finding R1).
+                let Some(peer) = clients.first().cloned() else {
+                    tracing::warn!(
+                        "No matching peer could authenticate for address: {}",
+                        client_ip
+                    );
+                    return Err(Error::InvalidPeer(
+                        "No matching peer could authenticate for address.".to_string(),
+                    ));
+                };
 
-        // Reject a null (all-zero) session key from the peer (finding F15) -
-        // see the matching check on the client side.
-        if outer_key.expose_secret().is_null() {
-            tracing::warn!("Rejected null session key from client - closing connection.");
-            return Err(Error::InvalidPeer(
-                "Peer sent a null session key - closing connection.".to_string(),
-            ));
-        }
+                let peer_name = peer.name();
+                let peer_zone = peer.zone();
 
-        let peer_engine = handshake.engine;
-        let peer_version = handshake.version;
+                if peer_name.is_empty() {
+                    tracing::warn!("Peer must have a name to handle a connection.");
+                    return Err(Error::InvalidPeer(
+                        "Peer must have a name to handle a connection.".to_string(),
+                    ));
+                }
 
-        // we will create a new session inner key and send it back to the
-        // client, wrapped in the client/server inner key and session outer key
-        let inner_key = Key::generate();
-        let handshake_nonce = take_handshake_nonce(&peer_name, &peer_zone).await;
+                tracing::info!(
+                    "Initiating connection: {:?} <=> {:?}",
+                    service_name,
+                    peer_name
+                );
 
-        let handshake = Handshake {
-            session_key: inner_key.clone(),
-            engine: env!("CARGO_PKG_NAME").to_string(),
-            version: env!("CARGO_PKG_VERSION").to_string(),
-            nonce: Some(handshake_nonce),
-            epoch: Some(crate::anti_replay::process_epoch()),
-        };
+                // we have found the right client to xor the salts
+                // Un-mask the selected peer's salts (unless plain - see the filter above).
+                let inner_key_salt = if client_salt_is_plain {
+                    inner_key_salt
+                } else {
+                    inner_key_salt.xor(peer.outer_key().expose_secret())
+                };
+                let outer_key_salt = if client_salt_is_plain {
+                    outer_key_salt
+                } else {
+                    outer_key_salt.xor(peer.inner_key().expose_secret())
+                };
 
-        let response = envelope_message(
-            handshake,
-            &peer.inner_key(),
-            &outer_key,
-            &inner_key_salt,
-            &outer_key_salt,
-        )
-        .with_context(|| "Error enveloping message - closing connection.")?;
+                // the peer has sent us the new session outer key that should be used,
+                // wrapped in the client/server inner and outer keys
+                let handshake = deenvelope_message::<Handshake>(
+                    message,
+                    &peer.inner_key(),
+                    &peer.outer_key(),
+                    &inner_key_salt,
+                    &outer_key_salt,
+                )
+                .with_context(|| "Error de-enveloping message - closing connection.")?;
 
-        outgoing
-            .send(response)
-            .await
-            .with_context(|| "Error sending response to peer")?;
+                if !check_handshake_replay(&peer_name, &peer_zone, handshake.epoch, handshake.nonce)
+                    .await
+                {
+                    tracing::warn!(
+                        "Rejected replayed (or too-old) Handshake from {}@{} (nonce {:?})",
+                        peer_name,
+                        peer_zone,
+                        handshake.nonce
+                    );
+                    return Err(Error::InvalidPeer(
+                        "Rejected replayed Handshake - closing connection.".to_string(),
+                    ));
+                }
+
+                let outer_key = handshake.session_key.clone();
+
+                // Reject a null (all-zero) session key from the peer (finding F15) -
+                // see the matching check on the client side.
+                if outer_key.expose_secret().is_null() {
+                    tracing::warn!("Rejected null session key from client - closing connection.");
+                    return Err(Error::InvalidPeer(
+                        "Peer sent a null session key - closing connection.".to_string(),
+                    ));
+                }
+
+                let peer_engine = handshake.engine;
+                let peer_version = handshake.version;
+
+                // we will create a new session inner key and send it back to the
+                // client, wrapped in the client/server inner key and session outer key
+                let inner_key = Key::generate();
+                let handshake_nonce = take_handshake_nonce(&peer_name, &peer_zone).await;
+
+                let handshake = Handshake {
+                    session_key: inner_key.clone(),
+                    engine: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    nonce: Some(handshake_nonce),
+                    epoch: Some(crate::anti_replay::process_epoch()),
+                };
+
+                let response = envelope_message(
+                    handshake,
+                    &peer.inner_key(),
+                    &outer_key,
+                    &inner_key_salt,
+                    &outer_key_salt,
+                )
+                .with_context(|| "Error enveloping message - closing connection.")?;
+
+                outgoing
+                    .send(response)
+                    .await
+                    .with_context(|| "Error sending response to peer")?;
+
+                (
+                    peer,
+                    inner_key_salt,
+                    outer_key_salt,
+                    inner_key,
+                    outer_key,
+                    peer_engine,
+                    peer_version,
+                )
+            };
+
+        let peer_name = peer.name();
+        let peer_zone = peer.zone();
 
         // the peer will now send us its PeerDetails
         let message = incoming.next().await.ok_or_else(|| {
@@ -1945,6 +2386,17 @@ impl Connection {
                 return future::ok(());
             }
 
+            // key rotation control messages are handled by paddington
+            // itself, never by the message handler - see `crate::rotation`
+            if rotation::is_key_rotation(&msg) {
+                tokio::spawn(rotation::received_from_client(
+                    peer_name.clone(),
+                    peer_zone.clone(),
+                    msg,
+                ));
+                return future::ok(());
+            }
+
             exchange::received(Message::received_from(&peer_name, &peer_zone, &msg))
                 .unwrap_or_else(|e| {
                     tracing::warn!("Error handling message: {:?}", e);
@@ -2031,6 +2483,144 @@ mod tests {
         assert!(!check_handshake_replay(name, zone, epoch, Some(first)).await);
     }
 
+    ///
+    /// Run the client's half of the public-key handshake with identity
+    /// `client`, claiming to be `claimed`, against a server with identity
+    /// `server`, which the client expects to be `expected`. The messages
+    /// pass between the two halves exactly as they would over the socket.
+    ///
+    #[allow(clippy::type_complexity)]
+    fn identity_handshake(
+        client: &SecretKey,
+        claimed: &PublicKey,
+        server: &SecretKey,
+        expected: &PublicKey,
+    ) -> Result<((SecretKey, SecretKey), (SecretKey, SecretKey)), Error> {
+        let inner_key_salt = Salt::generate()?;
+        let outer_key_salt = Salt::generate()?;
+
+        let ephemeral = EphemeralKey::generate()?;
+        let hello = IdentityHello::new(claimed, &ephemeral.public_key());
+        let message = TokioMessage::text(
+            serde_json::to_string(&hello).with_context(|| "Could not serialise identity hello")?,
+        );
+
+        // the server reads the hello off the wire
+        let received: IdentityHello = serde_json::from_str(message.to_text()?)
+            .with_context(|| "Could not parse the client's identity hello")?;
+
+        let (server_inner, server_outer, transcript, reply) =
+            identity_server_reply(server, &received, &inner_key_salt, &outer_key_salt)?;
+
+        let (client_inner, client_outer, _, finish) = identity_client_finish(
+            client,
+            ephemeral,
+            &hello,
+            expected,
+            reply,
+            &inner_key_salt,
+            &outer_key_salt,
+        )?;
+
+        identity_server_finish(
+            &received,
+            &transcript,
+            finish,
+            &server_inner,
+            &server_outer,
+            &inner_key_salt,
+            &outer_key_salt,
+        )?;
+
+        Ok(((client_inner, client_outer), (server_inner, server_outer)))
+    }
+
+    #[test]
+    fn test_identity_handshake_agrees_session_keys() {
+        let client = Key::generate();
+        let server = Key::generate();
+
+        let client_public = client
+            .expose_secret()
+            .identity_public_key()
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        let server_public = server
+            .expose_secret()
+            .identity_public_key()
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let ((client_inner, client_outer), (server_inner, server_outer)) =
+            identity_handshake(&client, &client_public, &server, &server_public)
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert!(client_inner
+            .expose_secret()
+            .equals(server_inner.expose_secret()));
+        assert!(client_outer
+            .expose_secret()
+            .equals(server_outer.expose_secret()));
+        assert!(!client_inner
+            .expose_secret()
+            .equals(client_outer.expose_secret()));
+
+        // what one side envelopes in the session keys, the other can read
+        let inner_key_salt = Salt::generate().unwrap_or_else(|e| unreachable!("{:?}", e));
+        let outer_key_salt = Salt::generate().unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let envelope = envelope_message(
+            "Hello, server!",
+            &client_inner,
+            &client_outer,
+            &inner_key_salt,
+            &outer_key_salt,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let message: String = deenvelope_message(
+            envelope,
+            &server_inner,
+            &server_outer,
+            &inner_key_salt,
+            &outer_key_salt,
+        )
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(message, "Hello, server!");
+
+        // and every connection has its own session keys
+        let ((other_inner, _), _) =
+            identity_handshake(&client, &client_public, &server, &server_public)
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert!(!other_inner
+            .expose_secret()
+            .equals(client_inner.expose_secret()));
+    }
+
+    #[test]
+    fn test_identity_handshake_refuses_the_wrong_peer() {
+        let client = Key::generate();
+        let server = Key::generate();
+        let impostor = Key::generate();
+
+        let public = |key: &SecretKey| {
+            key.expose_secret()
+                .identity_public_key()
+                .unwrap_or_else(|e| unreachable!("{:?}", e))
+        };
+
+        // a server whose fingerprint is not the one in the client's invite
+        let result = identity_handshake(&client, &public(&client), &impostor, &public(&server));
+        assert!(matches!(result, Err(Error::InvalidPeer(_))));
+
+        // a client that claims another's identity cannot sign for it
+        let result = identity_handshake(&impostor, &public(&client), &server, &public(&server));
+        assert!(result.is_err());
+
+        // while the real client still can
+        assert!(identity_handshake(&client, &public(&client), &server, &public(&server)).is_ok());
+    }
+
     #[test]
     fn test_enveloping() {
         let inner_key = Key::generate();
@@ -3,7 +3,9 @@
 
 use anyhow::Context;
 use orion::{aead, auth, hazardous::kdf::hkdf, kdf};
-use secrecy::{zeroize::Zeroize, CloneableSecret, SecretBox, SerializableSecret};
+use ring::signature::KeyPair;
+use ring::{agreement, signature};
+use secrecy::{zeroize::Zeroize, CloneableSecret, ExposeSecret, SecretBox, SerializableSecret};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_with::serde_as;
 use std::fmt::Display;
@@ -73,6 +75,17 @@ pub fn random_bytes(size: usize) -> Result<Vec<u8>, Error> {
     Ok(data)
 }
 
+///
+/// Return the hex-encoded BLAKE2b-256 digest of the passed bytes. This is
+/// an unkeyed hash for integrity (e.g. chaining records together so that an
+/// edit is detectable), not for authentication - use `SecretKey::sign` when
+/// the origin of the data must be proven.
+///
+pub fn digest(data: &[u8]) -> Result<String, Error> {
+    let digest = orion::hash::digest(data).context("Failed to hash the data.")?;
+    Ok(hex::encode(digest.as_ref()))
+}
+
 ///
 /// Constant-time equality check for two byte slices, using orion's vetted
 /// `secure_cmp`. Returns `true` iff the slices are equal. Use this instead of
@@ -584,6 +597,186 @@ impl Key {
     }
 }
 
+///
+/// A public key - either the long-term Ed25519 identity of an agent, or the
+/// X25519 key one side of a single connection agrees its session keys with.
+/// Public keys are not secret, so unlike `Key` this is serialised as plain
+/// hex, prints itself, and may be put into an invite.
+///
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    data: vec::Vec<u8>,
+}
+
+impl PublicKey {
+    ///
+    /// A short, stable rendering of this key for operators to compare
+    /// out-of-band: the first 128 bits of its BLAKE2b-256 digest, as hex in
+    /// groups of four.
+    ///
+    pub fn fingerprint(&self) -> String {
+        let digest = digest(&self.data).unwrap_or_default();
+
+        digest
+            .as_bytes()
+            .chunks(4)
+            .take(8)
+            .map(|chunk| str::from_utf8(chunk).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    ///
+    /// Verify that `signature` is this (Ed25519) key's signature of `message`
+    ///
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+        signature::UnparsedPublicKey::new(&signature::ED25519, &self.data)
+            .verify(message, signature)
+            .map_err(|_| Error::InvalidPeer("The signature does not verify.".to_string()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(&self.data))
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PublicKey({})", self.fingerprint())
+    }
+}
+
+impl str::FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data = hex::decode(s.trim()).with_context(|| "Failed to decode the public key.")?;
+
+        if data.len() != KEY_SIZE {
+            return Err(Error::Parse(format!(
+                "a public key must be exactly {} bytes ({} hex characters), not {}",
+                KEY_SIZE,
+                KEY_SIZE * 2,
+                data.len()
+            )));
+        }
+
+        Ok(PublicKey { data })
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Key {
+    ///
+    /// Treat this key as the seed of an Ed25519 identity keypair. Any
+    /// `Key::generate()`d key is a valid seed.
+    ///
+    fn identity_keypair(&self) -> Result<signature::Ed25519KeyPair, Error> {
+        signature::Ed25519KeyPair::from_seed_unchecked(&self.data)
+            .map_err(|e| Error::Incompatible(format!("Invalid identity key: {}", e)))
+    }
+
+    ///
+    /// Return the public half of the Ed25519 identity keypair whose seed is
+    /// this key
+    ///
+    pub fn identity_public_key(&self) -> Result<PublicKey, Error> {
+        Ok(PublicKey {
+            data: self.identity_keypair()?.public_key().as_ref().to_vec(),
+        })
+    }
+
+    ///
+    /// Sign `message` with the Ed25519 identity keypair whose seed is this key
+    ///
+    pub fn identity_sign(&self, message: &[u8]) -> Result<vec::Vec<u8>, Error> {
+        Ok(self.identity_keypair()?.sign(message).as_ref().to_vec())
+    }
+}
+
+///
+/// The X25519 key one side of a connection generates for that connection
+/// alone. It is consumed by `agree`, so the private half is gone as soon as
+/// the session keys exist - which is what gives forward secrecy.
+///
+pub(crate) struct EphemeralKey {
+    private: agreement::EphemeralPrivateKey,
+    public: PublicKey,
+}
+
+impl EphemeralKey {
+    pub(crate) fn generate() -> Result<Self, Error> {
+        let rng = ring::rand::SystemRandom::new();
+
+        let private = agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng)
+            .map_err(|_| Error::Incompatible("Failed to generate an X25519 key.".to_string()))?;
+
+        let public = private
+            .compute_public_key()
+            .map_err(|_| Error::Incompatible("Failed to compute an X25519 key.".to_string()))?;
+
+        Ok(EphemeralKey {
+            private,
+            public: PublicKey {
+                data: public.as_ref().to_vec(),
+            },
+        })
+    }
+
+    pub(crate) fn public_key(&self) -> PublicKey {
+        self.public.clone()
+    }
+
+    ///
+    /// Agree the shared secret with the peer's ephemeral `peer` key. This is
+    /// only ever used as the input to `Key::derive`.
+    ///
+    pub(crate) fn agree(self, peer: &PublicKey) -> Result<SecretKey, Error> {
+        let shared = agreement::agree_ephemeral(
+            self.private,
+            &agreement::UnparsedPublicKey::new(&agreement::X25519, &peer.data),
+            |shared| shared.to_vec(),
+        )
+        .map_err(|_| Error::InvalidPeer("X25519 key agreement failed.".to_string()))?;
+
+        let shared: SecretKey = Box::new(Key { data: shared }).into();
+
+        // a low-order peer key yields an all-zero secret, which must never
+        // become a session key
+        if shared.expose_secret().is_null() {
+            return Err(Error::InvalidPeer(
+                "The peer's X25519 key is degenerate.".to_string(),
+            ));
+        }
+
+        Ok(shared)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use secrecy::ExposeSecret;
@@ -717,6 +910,66 @@ mod tests {
         assert_eq!(decrypted_data, "Hello, World!".to_string());
     }
 
+    #[test]
+    fn test_identity_signatures_and_key_agreement() {
+        let identity = Key::generate();
+        let public = identity
+            .expose_secret()
+            .identity_public_key()
+            .unwrap_or_else(|err| unreachable!("Failed to get public key: {}", err));
+
+        // the public key, and so the fingerprint, is stable for a given seed
+        assert_eq!(
+            public,
+            identity
+                .expose_secret()
+                .identity_public_key()
+                .unwrap_or_else(|err| unreachable!("Failed to get public key: {}", err))
+        );
+        assert_eq!(public.fingerprint().len(), 8 * 4 + 7);
+        assert_eq!(
+            public
+                .to_string()
+                .parse::<PublicKey>()
+                .unwrap_or_else(|err| unreachable!("Failed to parse: {}", err)),
+            public
+        );
+
+        let signature = identity
+            .expose_secret()
+            .identity_sign(b"transcript")
+            .unwrap_or_else(|err| unreachable!("Failed to sign: {}", err));
+        assert!(public.verify(b"transcript", &signature).is_ok());
+        assert!(public.verify(b"another transcript", &signature).is_err());
+
+        let client = EphemeralKey::generate()
+            .unwrap_or_else(|err| unreachable!("Failed to generate: {}", err));
+        let server = EphemeralKey::generate()
+            .unwrap_or_else(|err| unreachable!("Failed to generate: {}", err));
+
+        let client_public = client.public_key();
+        let server_public = server.public_key();
+
+        let client_secret = client
+            .agree(&server_public)
+            .unwrap_or_else(|err| unreachable!("Failed to agree: {}", err));
+        let server_secret = server
+            .agree(&client_public)
+            .unwrap_or_else(|err| unreachable!("Failed to agree: {}", err));
+
+        assert!(client_secret
+            .expose_secret()
+            .equals(server_secret.expose_secret()));
+
+        // a degenerate (all-zero) peer key is refused
+        let zero: PublicKey = "00".repeat(KEY_SIZE).parse().unwrap_or_else(|err| {
+            unreachable!("Failed to parse: {}", err);
+        });
+        let key = EphemeralKey::generate()
+            .unwrap_or_else(|err| unreachable!("Failed to generate: {}", err));
+        assert!(key.agree(&zero).is_err());
+    }
+
     #[test]
     fn test_key_sign_verify() {
         let key: SecretBox<Key> = Key::generate();
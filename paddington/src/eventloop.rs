@@ -5,7 +5,7 @@ use anyhow::Result;
 
 use crate::config::ServiceConfig;
 use crate::error::Error;
-use crate::{client, server};
+use crate::{client, rotation, server};
 
 pub async fn run(config: ServiceConfig) -> Result<(), Error> {
     match rustls::crypto::ring::default_provider().install_default() {
@@ -21,6 +21,14 @@ pub async fn run(config: ServiceConfig) -> Result<(), Error> {
     let mut server_handles = vec![];
     let mut client_handles = vec![];
 
+    // keys rotated in-band are applied to this copy, and picked up by
+    // every new connection - see `crate::rotation`
+    rotation::set_live_config(&config);
+
+    if config.key_rotation().is_some() && !config.clients().is_empty() {
+        rotation::spawn_scheduler();
+    }
+
     tracing::info!(
         "Communication layer: {} version {}",
         env!("CARGO_PKG_NAME"),
@@ -487,6 +487,19 @@ fn get_key(connection: &Connection) -> String {
     get_key_from_str(&connection.name(), &connection.zone())
 }
 
+///
+/// Record whether `name@zone` is connected. The series stays at zero once a
+/// peer disconnects, so a peer that has gone away can be alerted on.
+///
+fn set_connected_metric(name: &str, zone: &str, connected: bool) {
+    crate::metrics::set(
+        "paddington_peer_connected",
+        "Whether a connection to the peer is currently registered (1) or not (0)",
+        &[("peer", name), ("zone", zone)],
+        if connected { 1.0 } else { 0.0 },
+    );
+}
+
 pub async fn unregister(connection: &Connection) -> Result<(), Error> {
     let name = connection.name();
 
@@ -515,6 +528,7 @@ pub async fn unregister(connection: &Connection) -> Result<(), Error> {
 
     if exchange.connections.contains_key(&key) {
         exchange.connections.remove(&key);
+        set_connected_metric(&name, &zone, false);
     }
 
     Ok(())
@@ -646,6 +660,7 @@ async fn locked_register(connection: Connection) -> Result<bool, Error> {
 
     if !is_standby_only {
         exchange.connections.insert(key, connection);
+        set_connected_metric(&name, &zone, true);
     }
 
     Ok(is_standby_only)
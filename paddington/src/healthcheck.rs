@@ -6,7 +6,7 @@ use crate::Error;
 use anyhow::Result;
 use axum::{
     extract::Json,
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     routing::get,
     Router,
@@ -30,6 +30,17 @@ async fn health() -> Result<Json<serde_json::Value>, AppError> {
     })))
 }
 
+//
+// OpenMetrics endpoint, for scraping by the site's monitoring
+//
+#[tracing::instrument(skip_all)]
+async fn metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, crate::metrics::CONTENT_TYPE)],
+        crate::metrics::render(),
+    )
+}
+
 ///
 /// Function spawned to run the API server in a background thread
 ///
@@ -49,7 +60,8 @@ async fn run_server(app: Router, listener: TcpListener) -> Result<()> {
 static IS_RUNNING: Lazy<RwLock<bool>> = Lazy::new(|| RwLock::new(false));
 
 ///
-/// Spawn a small http server that responds to health checks
+/// Spawn a small http server that responds to health checks, and serves
+/// this agent's metrics
 ///
 pub async fn spawn(ip: IpAddr, port: u16) -> Result<(), Error> {
     // check if the server is already running
@@ -84,10 +96,16 @@ pub async fn spawn(ip: IpAddr, port: u16) -> Result<(), Error> {
         }
     }
 
-    tracing::info!("Starting health check server on {}:{}/health", ip, port);
+    tracing::info!(
+        "Starting health check server on {}:{}/health (metrics on /metrics)",
+        ip,
+        port
+    );
 
     // create the web API
-    let app = Router::new().route("/health", get(health));
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/metrics", get(metrics));
 
     // create a TCP listener on the specified port
     let listener = tokio::net::TcpListener::bind(&std::net::SocketAddr::new(ip, port)).await?;
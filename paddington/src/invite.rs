@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: © 2024 Christopher Woods <Christopher.Woods@bristol.ac.uk>
 // SPDX-License-Identifier: MIT
 
-use crate::crypto::SecretKey;
+use crate::crypto::{Key, PublicKey, SecretKey};
 use crate::error::Error;
 use anyhow::Context;
 use secrecy::zeroize::Zeroizing;
@@ -15,8 +15,20 @@ pub struct Invite {
     name: String,
     url: String,
     zone: String,
+    #[serde(default = "Key::null", skip_serializing_if = "key_is_null")]
     inner_key: SecretKey,
+    #[serde(default = "Key::null", skip_serializing_if = "key_is_null")]
     outer_key: SecretKey,
+    /// The issuer's Ed25519 identity, for a peer that authenticates with a
+    /// keypair rather than the pre-shared keys above (which are then absent).
+    /// Nothing secret is in such an invite.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public_key: Option<PublicKey>,
+    /// `public_key`'s fingerprint, for the operators to compare out-of-band
+    /// before the invite is imported. Checked against the key on load, so a
+    /// hand-edited key is caught.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<String>,
     /// Name of the blind relay proxy the issuer reaches this peer through,
     /// if any (see `docs/plans/archive/blind-relay-proxy-design.md`) - carried in
     /// the invite so the importing side doesn't need to be told separately
@@ -57,11 +69,31 @@ impl Invite {
             zone: zone.to_string(),
             inner_key: inner_key.clone(),
             outer_key: outer_key.clone(),
+            public_key: None,
+            fingerprint: None,
             proxy: proxy.clone(),
             agent_type: None,
         }
     }
 
+    ///
+    /// Create an invite to a service that authenticates with the Ed25519
+    /// identity `public_key`, rather than with pre-shared keys
+    ///
+    pub fn with_public_key(name: &str, url: &str, zone: &str, public_key: &PublicKey) -> Self {
+        Invite {
+            name: name.to_string(),
+            url: url.to_string(),
+            zone: zone.to_string(),
+            inner_key: Key::null(),
+            outer_key: Key::null(),
+            public_key: Some(public_key.clone()),
+            fingerprint: Some(public_key.fingerprint()),
+            proxy: None,
+            agent_type: None,
+        }
+    }
+
     pub fn name(&self) -> String {
         self.name.clone()
     }
@@ -82,6 +114,16 @@ impl Invite {
         self.outer_key.clone()
     }
 
+    /// The issuer's Ed25519 identity, if it uses one instead of pre-shared
+    /// keys
+    pub fn public_key(&self) -> Option<PublicKey> {
+        self.public_key.clone()
+    }
+
+    pub fn fingerprint(&self) -> Option<String> {
+        self.fingerprint.clone()
+    }
+
     /// The blind relay proxy this peer must be reached through, if any -
     /// `None` for an ordinary, directly-dialled peer.
     pub fn proxy(&self) -> Option<String> {
@@ -146,6 +188,25 @@ impl Invite {
             )));
         }
 
+        if let Some(public_key) = &self.public_key {
+            // exactly one way of authenticating
+            if !self.inner_key.expose_secret().is_null()
+                || !self.outer_key.expose_secret().is_null()
+            {
+                return Err(Error::InvalidPeer(
+                    "Invite has both a public key and pre-shared keys".to_string(),
+                ));
+            }
+
+            if self.fingerprint.as_deref() != Some(public_key.fingerprint().as_str()) {
+                return Err(Error::InvalidPeer(
+                    "Invite fingerprint does not match its public key".to_string(),
+                ));
+            }
+
+            return Ok(());
+        }
+
         if self.inner_key.expose_secret().is_null() {
             return Err(Error::InvalidPeer("Invite inner key is null".to_string()));
         }
@@ -208,6 +269,10 @@ impl Invite {
     }
 }
 
+fn key_is_null(key: &SecretKey) -> bool {
+    key.expose_secret().is_null()
+}
+
 impl Display for Invite {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.proxy {
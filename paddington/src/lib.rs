@@ -10,12 +10,13 @@ mod error;
 mod eventloop;
 mod exchange;
 mod healthcheck;
+mod secretstore;
 mod server;
 
 // public API
 pub mod command;
 pub mod config;
-pub use crypto::{constant_time_eq, Key, SecretKey, Signature};
+pub use crypto::{constant_time_eq, digest, Key, PublicKey, SecretKey, Signature};
 pub use error::Error;
 pub use eventloop::run;
 pub use exchange::disconnect;
@@ -28,4 +29,6 @@ pub use exchange::worker_count;
 pub use exchange::SoftRestartGuard;
 pub mod invite;
 pub mod message;
+pub mod metrics;
 pub mod relay;
+pub mod rotation;
@@ -0,0 +1,341 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! A process-wide registry of counters, gauges and histograms, served in
+//! OpenMetrics text format from `/metrics` on the health check port.
+//!
+//! Anything in the agent - paddington itself, templemeads, or an agent's
+//! own engine code - records into the one registry by metric name and
+//! label values. Families are created the first time they are recorded,
+//! so a metric that has never happened simply does not appear. Recording
+//! never fails and never blocks for long: a poisoned lock just loses the
+//! sample, as losing a sample is always better than failing the job or
+//! connection that was being measured.
+//!
+//! Label values must be bounded (a peer name, an instruction's command
+//! name, a job state) - never a job id or a full instruction, or the
+//! registry would grow without limit.
+
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// The content type to serve `render()`'s output with
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Histogram bucket upper bounds, in seconds. These span a local account
+/// change (milliseconds) to a slow FreeIPA or Slurm call (a minute).
+const BUCKETS: [f64; 14] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Histogram => "histogram",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// Non-cumulative count per bucket, with one extra for `+Inf`
+    buckets: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; BUCKETS.len() + 1];
+        }
+
+        let index = BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(BUCKETS.len());
+
+        if let Some(bucket) = self.buckets.get_mut(index) {
+            *bucket += 1;
+        }
+
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(f64),
+    Histogram(Histogram),
+}
+
+type Labels = Vec<(String, String)>;
+
+#[derive(Debug, Clone)]
+struct Family {
+    kind: Kind,
+    help: String,
+    series: BTreeMap<Labels, Value>,
+}
+
+static REGISTRY: Lazy<Mutex<BTreeMap<String, Family>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+///
+/// Apply `update` to the series of `name` with `labels`, creating the
+/// family and series as needed. A family recorded as one kind and then
+/// another is a programming error - the second kind is ignored.
+///
+fn update(
+    name: &str,
+    help: &str,
+    kind: Kind,
+    labels: &[(&str, &str)],
+    update: impl FnOnce(&mut Value),
+) {
+    let mut registry = match REGISTRY.lock() {
+        Ok(registry) => registry,
+        Err(e) => {
+            tracing::error!("Error getting metrics lock: {}", e);
+            return;
+        }
+    };
+
+    let family = registry.entry(name.to_string()).or_insert_with(|| Family {
+        kind,
+        help: help.to_string(),
+        series: BTreeMap::new(),
+    });
+
+    if family.kind != kind {
+        tracing::warn!(
+            "Metric {} is a {}, not a {} - ignoring.",
+            name,
+            family.kind.as_str(),
+            kind.as_str()
+        );
+        return;
+    }
+
+    let labels: Labels = labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    let value = family.series.entry(labels).or_insert_with(|| match kind {
+        Kind::Histogram => Value::Histogram(Histogram::default()),
+        _ => Value::Number(0.0),
+    });
+
+    update(value);
+}
+
+///
+/// Add one to the counter `name` with the passed labels. `name` is the
+/// family name, without the `_total` suffix that is added when rendered.
+///
+pub fn increment(name: &str, help: &str, labels: &[(&str, &str)]) {
+    add(name, help, labels, 1.0);
+}
+
+///
+/// Add `value` (which must not be negative) to the counter `name`
+///
+pub fn add(name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+    if value < 0.0 {
+        return;
+    }
+
+    update(name, help, Kind::Counter, labels, |v| {
+        if let Value::Number(n) = v {
+            *n += value;
+        }
+    });
+}
+
+///
+/// Set the gauge `name` with the passed labels to `value`
+///
+pub fn set(name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+    update(name, help, Kind::Gauge, labels, |v| {
+        if let Value::Number(n) = v {
+            *n = value;
+        }
+    });
+}
+
+///
+/// Record one observation of `duration` in the histogram `name`, in seconds
+///
+pub fn observe(name: &str, help: &str, labels: &[(&str, &str)], duration: std::time::Duration) {
+    let seconds = duration.as_secs_f64();
+
+    update(name, help, Kind::Histogram, labels, |v| {
+        if let Value::Histogram(h) = v {
+            h.observe(seconds);
+        }
+    });
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_labels(labels: &Labels, extra: Option<(&str, &str)>) -> String {
+    let mut all: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+        .collect();
+
+    if let Some((k, v)) = extra {
+        all.push(format!("{}=\"{}\"", k, escape(v)));
+    }
+
+    match all.is_empty() {
+        true => String::new(),
+        false => format!("{{{}}}", all.join(",")),
+    }
+}
+
+fn format_number(value: f64) -> String {
+    match value {
+        v if v == f64::INFINITY => "+Inf".to_string(),
+        v if v.fract() == 0.0 && v.abs() < 1e15 => format!("{}", v as i64),
+        v => format!("{}", v),
+    }
+}
+
+///
+/// Return every recorded metric in OpenMetrics text format
+///
+pub fn render() -> String {
+    let registry = match REGISTRY.lock() {
+        Ok(registry) => registry.clone(),
+        Err(e) => {
+            tracing::error!("Error getting metrics lock: {}", e);
+            BTreeMap::new()
+        }
+    };
+
+    let mut text = String::new();
+
+    for (name, family) in registry.iter() {
+        let _ = writeln!(text, "# TYPE {} {}", name, family.kind.as_str());
+        let _ = writeln!(text, "# HELP {} {}", name, escape(&family.help));
+
+        for (labels, value) in family.series.iter() {
+            match value {
+                Value::Number(n) => {
+                    let suffix = match family.kind {
+                        Kind::Counter => "_total",
+                        _ => "",
+                    };
+
+                    let _ = writeln!(
+                        text,
+                        "{}{}{} {}",
+                        name,
+                        suffix,
+                        format_labels(labels, None),
+                        format_number(*n)
+                    );
+                }
+                Value::Histogram(h) => {
+                    let mut cumulative = 0;
+
+                    for (i, bound) in BUCKETS.iter().chain([f64::INFINITY].iter()).enumerate() {
+                        cumulative += h.buckets.get(i).copied().unwrap_or(0);
+
+                        let _ = writeln!(
+                            text,
+                            "{}_bucket{} {}",
+                            name,
+                            format_labels(labels, Some(("le", &format_number(*bound)))),
+                            cumulative
+                        );
+                    }
+
+                    let _ = writeln!(
+                        text,
+                        "{}_count{} {}",
+                        name,
+                        format_labels(labels, None),
+                        h.count
+                    );
+                    let _ = writeln!(
+                        text,
+                        "{}_sum{} {}",
+                        name,
+                        format_labels(labels, None),
+                        format_number(h.sum)
+                    );
+                }
+            }
+        }
+    }
+
+    text.push_str("# EOF\n");
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_is_openmetrics() {
+        increment(
+            "test_render_jobs",
+            "Jobs run",
+            &[("instruction", "add_user"), ("state", "complete")],
+        );
+        increment(
+            "test_render_jobs",
+            "Jobs run",
+            &[("instruction", "add_user"), ("state", "complete")],
+        );
+        set(
+            "test_render_connected",
+            "Whether the peer is connected",
+            &[("peer", "say \"hi\"")],
+            1.0,
+        );
+        observe(
+            "test_render_seconds",
+            "How long it took",
+            &[("function", "user_add")],
+            std::time::Duration::from_millis(300),
+        );
+
+        // a counter can't become a gauge
+        set("test_render_jobs", "Jobs run", &[], 5.0);
+
+        let text = render();
+
+        assert!(text.contains("# TYPE test_render_jobs counter\n"));
+        assert!(text
+            .contains("test_render_jobs_total{instruction=\"add_user\",state=\"complete\"} 2\n"));
+        assert!(!text.contains("test_render_jobs_total 5"));
+        assert!(text.contains("test_render_connected{peer=\"say \\\"hi\\\"\"} 1\n"));
+        assert!(text.contains("test_render_seconds_bucket{function=\"user_add\",le=\"0.25\"} 0\n"));
+        assert!(text.contains("test_render_seconds_bucket{function=\"user_add\",le=\"0.5\"} 1\n"));
+        assert!(text.contains("test_render_seconds_bucket{function=\"user_add\",le=\"+Inf\"} 1\n"));
+        assert!(text.contains("test_render_seconds_count{function=\"user_add\"} 1\n"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+}
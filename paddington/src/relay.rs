@@ -1135,6 +1135,8 @@ async fn handle_incoming_envelope(
         return Ok(None);
     }
 
+    record_relay_bytes("received", &envelope.from, envelope.ciphertext.len());
+
     let bootstrap_salt = bootstrap_salt()?;
 
     // try the permanent pre-shared key first - only ever used for the two
@@ -1380,6 +1382,19 @@ pub fn relay_dispatch_handler(
     })
 }
 
+///
+/// Count the ciphertext bytes exchanged with the relayed peer `peer`, in
+/// the passed `direction` ("sent" or "received")
+///
+fn record_relay_bytes(direction: &str, peer: &str, size: usize) {
+    crate::metrics::add(
+        "paddington_relay_bytes",
+        "Bytes of ciphertext exchanged with a relayed peer through its proxy",
+        &[("peer", peer), ("direction", direction)],
+        size as f64,
+    );
+}
+
 ///
 /// Send `payload` to the relayed peer `to`, bootstrapping a fresh session
 /// first if none exists yet (relayed-client role only - a relayed server
@@ -1439,7 +1454,13 @@ pub async fn send(to: &str, payload: &str) -> Result<(), Error> {
         .with_context(|| "Could not serialise relay envelope")
         .map_err(Error::Any)?;
 
-    exchange::send(Message::send_to(&relay, &peer.relay_zone, &payload)).await
+    let size = envelope.ciphertext.len();
+
+    exchange::send(Message::send_to(&relay, &peer.relay_zone, &payload)).await?;
+
+    record_relay_bytes("sent", to, size);
+
+    Ok(())
 }
 
 ///
@@ -1601,13 +1622,21 @@ pub fn proxy_handler(message: Message) -> Pin<Box<dyn Future<Output = Result<(),
 
         let outgoing = Message::send_to(&envelope.to, &real_zone, message.payload());
 
-        if let Err(e) = exchange::send(outgoing).await {
-            tracing::warn!(
-                "Could not relay message from {} to {}: {:?}",
-                envelope.from,
-                envelope.to,
-                e
-            );
+        match exchange::send(outgoing).await {
+            Ok(_) => crate::metrics::add(
+                "paddington_relay_forwarded_bytes",
+                "Bytes of ciphertext this proxy has relayed between two peers",
+                &[("from", &envelope.from), ("to", &envelope.to)],
+                envelope.ciphertext.len() as f64,
+            ),
+            Err(e) => {
+                tracing::warn!(
+                    "Could not relay message from {} to {}: {:?}",
+                    envelope.from,
+                    envelope.to,
+                    e
+                );
+            }
         }
 
         Ok(())
@@ -0,0 +1,550 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! Scheduled, in-band rotation of the pre-shared peer keys.
+//!
+//! Rotating keys by hand means carrying a `rotate_*` invite from one
+//! operator to the other and restarting both agents. With `key_rotation`
+//! set on a service, it instead proposes fresh keys to each of its directly
+//! connected clients once their keys are older than the interval, as a
+//! `RotationCommand::Propose` control message sent over the connection
+//! itself (so protected by its session keys). The client writes the keys to
+//! its config file and answers `Accept`, and only then does the server write
+//! them to its own.
+//!
+//! Neither side changes the connection the keys were agreed on - the new
+//! keys are used from the next reconnect. The replaced keys are kept for
+//! the grace period, during which the server still accepts them and the
+//! client falls back to them if the new ones are refused, so a lost
+//! `Accept`, or a crash between the two writes, cannot lock a client out.
+//!
+//! Only direct peers with pre-shared keys are rotated. A peer with a
+//! public-key identity already has fresh session keys for every connection,
+//! and relayed peers keep the keys their operators exchanged.
+
+use crate::config::{self, HasServiceConfig, ServiceConfig};
+use crate::crypto::{random_bytes, Key, SecretKey};
+use crate::error::Error;
+use crate::exchange;
+use crate::message::Message;
+
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, RwLock};
+
+/// Marks a payload as a key rotation control message. These are handled by
+/// the connection itself, and never reach the message handler.
+const KEY_ROTATION_PREFIX: &str = "PADDINGTON_KEY_ROTATION:";
+
+/// How often the server looks for clients whose keys are due for rotation
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// How long the server waits for a client to accept proposed keys before
+/// proposing fresh ones
+const PROPOSAL_TIMEOUT_MINUTES: i64 = 60;
+
+/// The longest grace period a client will honour - a server cannot make a
+/// client keep accepting its replaced keys indefinitely
+const MAX_GRACE_HOURS: u64 = 30 * 24;
+
+#[derive(Serialize, Deserialize)]
+enum RotationCommand {
+    /// Server to client: switch to these keys from the next connection
+    Propose {
+        id: String,
+        inner_key: SecretKey,
+        outer_key: SecretKey,
+        grace_hours: u64,
+    },
+    /// Client to server: the proposed keys are saved
+    Accept { id: String },
+    /// Client to server: the proposed keys could not be used
+    Refuse { id: String, reason: String },
+}
+
+/// Keys proposed to a client that has not yet accepted them
+struct Proposal {
+    id: String,
+    inner_key: SecretKey,
+    outer_key: SecretKey,
+    grace: Duration,
+    proposed: DateTime<Utc>,
+}
+
+type ConfigChange<'a> = &'a dyn Fn(&mut ServiceConfig) -> Result<(), Error>;
+type ConfigUpdater = Box<dyn Fn(ConfigChange) -> Result<(), Error> + Send + Sync>;
+
+/// The running service's config, with any keys rotated since it started
+static LIVE_CONFIG: Lazy<RwLock<Option<ServiceConfig>>> = Lazy::new(|| RwLock::new(None));
+
+/// Writes a change to the config file the service was loaded from
+static UPDATER: Lazy<RwLock<Option<ConfigUpdater>>> = Lazy::new(|| RwLock::new(None));
+
+/// Outstanding proposals, keyed by `name@zone` of the client
+static PROPOSALS: Lazy<Mutex<HashMap<String, Proposal>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn peer_key(name: &str, zone: &str) -> String {
+    format!("{}@{}", name, zone)
+}
+
+///
+/// Save keys agreed by in-band rotation to `config_file`, which holds a
+/// `T` that was loaded with `load_service_config`. Without this, the service
+/// neither proposes nor accepts rotated keys, as it could not keep them
+/// across a restart.
+///
+pub fn persist_to<T>(config_file: &Path) -> Result<(), Error>
+where
+    T: serde::de::DeserializeOwned + serde::Serialize + HasServiceConfig + Clone + 'static,
+{
+    let config_file = config_file.to_path_buf();
+
+    let updater: ConfigUpdater = Box::new(move |change| {
+        let mut config = config::load_service_config::<T>(&config_file)?;
+        change(config.service_config_mut())?;
+        config::save_service_config(&config, &config_file)
+    });
+
+    *UPDATER
+        .write()
+        .map_err(|e| Error::Poison(format!("Error getting write lock: {}", e)))? = Some(updater);
+
+    Ok(())
+}
+
+fn can_persist() -> bool {
+    UPDATER.read().is_ok_and(|updater| updater.is_some())
+}
+
+///
+/// Record the config the service is running with - called once, when the
+/// event loop starts
+///
+pub(crate) fn set_live_config(config: &ServiceConfig) {
+    match LIVE_CONFIG.write() {
+        Ok(mut live) => *live = Some(config.clone()),
+        Err(e) => tracing::error!("Error getting write lock: {}", e),
+    }
+}
+
+///
+/// The config to use for a new connection - `config`, updated with any
+/// keys that have been rotated since the service started
+///
+pub(crate) fn live_config(config: &ServiceConfig) -> ServiceConfig {
+    match LIVE_CONFIG.read() {
+        Ok(live) => match live.as_ref() {
+            Some(live) if live.name() == config.name() => live.clone(),
+            _ => config.clone(),
+        },
+        Err(_) => config.clone(),
+    }
+}
+
+///
+/// Write `change` to the config file, and then apply it to the running
+/// config. The file comes first, so the running service never uses keys
+/// that a restart would lose.
+///
+fn persist_and_apply(change: ConfigChange) -> Result<(), Error> {
+    {
+        let updater = UPDATER
+            .read()
+            .map_err(|e| Error::Poison(format!("Error getting read lock: {}", e)))?;
+
+        let Some(updater) = updater.as_ref() else {
+            return Err(Error::Unavailable(
+                "This agent cannot save rotated keys to its config file.".to_string(),
+            ));
+        };
+
+        updater(change)?;
+    }
+
+    let mut live = LIVE_CONFIG
+        .write()
+        .map_err(|e| Error::Poison(format!("Error getting write lock: {}", e)))?;
+
+    if let Some(live) = live.as_mut() {
+        change(live)?;
+    }
+
+    Ok(())
+}
+
+///
+/// Whether `payload` is a key rotation control message, which the
+/// connection hands to `received_from_server` or `received_from_client`
+/// rather than to the message handler
+///
+pub(crate) fn is_key_rotation(payload: &str) -> bool {
+    payload.starts_with(KEY_ROTATION_PREFIX)
+}
+
+fn parse(payload: &str) -> Result<RotationCommand, Error> {
+    let json = payload
+        .strip_prefix(KEY_ROTATION_PREFIX)
+        .ok_or_else(|| Error::Parse("This is not a key rotation control message.".to_string()))?;
+
+    Ok(serde_json::from_str(json).with_context(|| "Could not parse key rotation message")?)
+}
+
+async fn send(name: &str, zone: &str, command: &RotationCommand) -> Result<(), Error> {
+    let json =
+        serde_json::to_string(command).with_context(|| "Could not serialise key rotation")?;
+
+    exchange::send(Message::send_to(
+        name,
+        zone,
+        &format!("{}{}", KEY_ROTATION_PREFIX, json),
+    ))
+    .await
+}
+
+///
+/// Start the task that proposes fresh keys to every connected client whose
+/// keys are older than the service's `key_rotation` interval
+///
+pub(crate) fn spawn_scheduler() {
+    if !can_persist() {
+        tracing::warn!(
+            "Key rotation is enabled, but this agent has no config file to save rotated keys \
+             to - keys will not be rotated."
+        );
+        return;
+    }
+
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            propose_due().await;
+        }
+    });
+}
+
+async fn propose_due() {
+    let config = match LIVE_CONFIG.read() {
+        Ok(live) => live.clone(),
+        Err(e) => {
+            tracing::error!("Error getting read lock: {}", e);
+            return;
+        }
+    };
+
+    let Some(config) = config else {
+        return;
+    };
+
+    let Some(key_rotation) = config.key_rotation() else {
+        return;
+    };
+
+    let now = Utc::now();
+
+    for client in config.clients() {
+        if client.is_relayed()
+            || client.public_key().is_some()
+            || client.inner_key().expose_secret().is_null()
+        {
+            continue;
+        }
+
+        if client
+            .keys_rotated()
+            .is_some_and(|rotated| rotated + key_rotation.interval() > now)
+        {
+            continue;
+        }
+
+        // keys are only ever proposed over a live connection
+        if !exchange::is_connected(&client.name(), &client.zone()) {
+            continue;
+        }
+
+        if let Err(e) = propose(&client.name(), &client.zone(), &key_rotation.grace()).await {
+            tracing::warn!(
+                "Could not propose new keys to client {}@{}: {}",
+                client.name(),
+                client.zone(),
+                e
+            );
+        }
+    }
+}
+
+///
+/// Propose fresh keys to the client `name` in `zone`, unless it is still
+/// considering an earlier proposal
+///
+async fn propose(name: &str, zone: &str, grace: &Duration) -> Result<(), Error> {
+    let proposal = Proposal {
+        id: hex::encode(random_bytes(16)?),
+        inner_key: Key::generate(),
+        outer_key: Key::generate(),
+        grace: *grace,
+        proposed: Utc::now(),
+    };
+
+    let command = RotationCommand::Propose {
+        id: proposal.id.clone(),
+        inner_key: proposal.inner_key.clone(),
+        outer_key: proposal.outer_key.clone(),
+        grace_hours: u64::try_from(grace.num_hours()).unwrap_or(0),
+    };
+
+    {
+        let mut proposals = PROPOSALS
+            .lock()
+            .map_err(|e| Error::Poison(format!("Error getting proposals lock: {}", e)))?;
+
+        if proposals
+            .get(&peer_key(name, zone))
+            .is_some_and(|p| p.proposed + Duration::minutes(PROPOSAL_TIMEOUT_MINUTES) > Utc::now())
+        {
+            return Ok(());
+        }
+
+        proposals.insert(peer_key(name, zone), proposal);
+    }
+
+    tracing::info!("Proposing new keys to client {}@{}", name, zone);
+
+    send(name, zone, &command).await
+}
+
+///
+/// Handle a key rotation control message that arrived from the server
+/// `name` in `zone`, over a connection this service made
+///
+pub(crate) async fn received_from_server(name: String, zone: String, payload: String) {
+    let reply = match parse(&payload) {
+        Ok(RotationCommand::Propose {
+            id,
+            inner_key,
+            outer_key,
+            grace_hours,
+        }) => match accept(&name, &zone, &inner_key, &outer_key, grace_hours) {
+            Ok(()) => {
+                tracing::info!(
+                    "Saved new keys proposed by server {}@{} - they will be used from the \
+                     next connection",
+                    name,
+                    zone
+                );
+                RotationCommand::Accept { id }
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Refusing new keys proposed by server {}@{}: {}",
+                    name,
+                    zone,
+                    e
+                );
+                RotationCommand::Refuse {
+                    id,
+                    reason: e.to_string(),
+                }
+            }
+        },
+        Ok(_) => {
+            tracing::warn!(
+                "Ignoring a key rotation reply from server {}@{} - only clients reply",
+                name,
+                zone
+            );
+            return;
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Ignoring a malformed key rotation message from server {}@{}: {}",
+                name,
+                zone,
+                e
+            );
+            return;
+        }
+    };
+
+    if let Err(e) = send(&name, &zone, &reply).await {
+        tracing::warn!(
+            "Could not answer the key rotation proposed by server {}@{}: {}",
+            name,
+            zone,
+            e
+        );
+    }
+}
+
+fn accept(
+    name: &str,
+    zone: &str,
+    inner_key: &SecretKey,
+    outer_key: &SecretKey,
+    grace_hours: u64,
+) -> Result<(), Error> {
+    let config = live_config_or_err()?;
+
+    let server = config
+        .servers()
+        .into_iter()
+        .find(|s| s.name() == name && s.zone() == zone)
+        .ok_or_else(|| Error::UnknownPeer(format!("No server {}@{} is configured.", name, zone)))?;
+
+    if server.proxy().is_some() || server.public_key().is_some() {
+        return Err(Error::Peer(
+            "Only the keys of a direct, pre-shared-key peer can be rotated.".to_string(),
+        ));
+    }
+
+    if inner_key.expose_secret().is_null() || outer_key.expose_secret().is_null() {
+        return Err(Error::Peer("The proposed keys are null.".to_string()));
+    }
+
+    let rotated = Utc::now();
+    let grace = Duration::hours(grace_hours.min(MAX_GRACE_HOURS) as i64);
+
+    persist_and_apply(&|service: &mut ServiceConfig| {
+        service.replace_server_keys(name, zone, inner_key, outer_key, &rotated, &grace)
+    })
+}
+
+fn live_config_or_err() -> Result<ServiceConfig, Error> {
+    LIVE_CONFIG
+        .read()
+        .map_err(|e| Error::Poison(format!("Error getting read lock: {}", e)))?
+        .clone()
+        .ok_or_else(|| Error::Null("The service is not running.".to_string()))
+}
+
+///
+/// Handle a key rotation control message that arrived from the client
+/// `name` in `zone`, over a connection this service accepted
+///
+pub(crate) async fn received_from_client(name: String, zone: String, payload: String) {
+    let (id, refused) = match parse(&payload) {
+        Ok(RotationCommand::Accept { id }) => (id, None),
+        Ok(RotationCommand::Refuse { id, reason }) => (id, Some(reason)),
+        Ok(RotationCommand::Propose { .. }) => {
+            tracing::warn!(
+                "Ignoring keys proposed by client {}@{} - only servers propose keys",
+                name,
+                zone
+            );
+            return;
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Ignoring a malformed key rotation message from client {}@{}: {}",
+                name,
+                zone,
+                e
+            );
+            return;
+        }
+    };
+
+    let proposal = match PROPOSALS.lock() {
+        Ok(mut proposals) => match proposals.get(&peer_key(&name, &zone)) {
+            Some(proposal) if proposal.id == id => proposals.remove(&peer_key(&name, &zone)),
+            _ => None,
+        },
+        Err(e) => {
+            tracing::error!("Error getting proposals lock: {}", e);
+            return;
+        }
+    };
+
+    let Some(proposal) = proposal else {
+        tracing::warn!(
+            "Ignoring a key rotation reply from client {}@{} that matches no proposal",
+            name,
+            zone
+        );
+        return;
+    };
+
+    if let Some(reason) = refused {
+        tracing::warn!(
+            "Client {}@{} refused the proposed keys: {}",
+            name,
+            zone,
+            reason
+        );
+        return;
+    }
+
+    let rotated = Utc::now();
+
+    match persist_and_apply(&|service: &mut ServiceConfig| {
+        service.replace_client_keys(
+            &name,
+            &zone,
+            &proposal.inner_key,
+            &proposal.outer_key,
+            &rotated,
+            &proposal.grace,
+        )
+    }) {
+        Ok(()) => tracing::info!(
+            "Rotated the keys of client {}@{} - they will be used from its next connection",
+            name,
+            zone
+        ),
+        // the client already uses the new keys, and falls back to the old
+        // ones, which we still have, until the grace period ends
+        Err(e) => tracing::error!(
+            "Client {}@{} accepted new keys, but they could not be saved: {}. It will fall \
+             back to its previous keys, which stop working when the grace period ends.",
+            name,
+            zone,
+            e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_commands_round_trip_with_the_prefix() {
+        let command = RotationCommand::Propose {
+            id: "abc".to_string(),
+            inner_key: Key::generate(),
+            outer_key: Key::generate(),
+            grace_hours: 24,
+        };
+
+        let payload = format!(
+            "{}{}",
+            KEY_ROTATION_PREFIX,
+            serde_json::to_string(&command).unwrap_or_else(|e| unreachable!("{:?}", e))
+        );
+
+        assert!(is_key_rotation(&payload));
+        assert!(!is_key_rotation("KEEPALIVE"));
+
+        match parse(&payload).unwrap_or_else(|e| unreachable!("{:?}", e)) {
+            RotationCommand::Propose {
+                id,
+                inner_key,
+                grace_hours,
+                ..
+            } => {
+                assert_eq!(id, "abc");
+                assert_eq!(grace_hours, 24);
+                assert!(!inner_key.expose_secret().is_null());
+            }
+            _ => unreachable!("wrong command"),
+        }
+
+        assert!(parse("KEEPALIVE").is_err());
+    }
+}
@@ -0,0 +1,279 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! External secret backends for `EncryptionScheme`.
+//!
+//! Every backend is driven through a command - the HashiCorp `vault` CLI, or
+//! a site's own helper - rather than a client library. That keeps secret
+//! handling synchronous (as `ServiceConfig::encrypt`/`decrypt` are), lets the
+//! operator authenticate the command however their site already does (a
+//! `VAULT_TOKEN` in the agent's environment, a Vault agent sidecar, a
+//! Kerberos ticket for the helper, ...), and means a small local script can
+//! stand in for any of them in testing.
+//!
+//! Secrets are always passed to and from the command on stdin/stdout, never
+//! as arguments, so they do not appear in `ps` output.
+
+use crate::error::Error;
+
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use secrecy::zeroize::Zeroizing;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+///
+/// Run `program` with `args` (and any extra environment variables), writing
+/// `input` to its stdin, and return what it printed to stdout. The output may
+/// be a secret, so it is zeroized on drop, and it never appears in an error -
+/// only stderr does.
+///
+/// Exactly one trailing newline (`\n` or `\r\n`) is removed, because
+/// `vault kv get -field=...` and most shell helpers end what they print with
+/// one. Anything else - leading or inner whitespace, or a second trailing
+/// newline - is part of the secret and is kept as printed. A secret that
+/// really ends in a newline must therefore be printed with an extra one.
+///
+fn run(
+    program: &str,
+    args: &[&str],
+    envs: &[(&str, &str)],
+    input: Option<&str>,
+) -> Result<Zeroizing<String>, Error> {
+    let mut child = Command::new(program)
+        .args(args)
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run the secret backend command '{}'", program))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Some(input) = input {
+            stdin
+                .write_all(input.as_bytes())
+                .with_context(|| format!("Could not write to '{}'", program))?;
+        }
+        // dropping stdin closes it, so the command sees end-of-input
+    }
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Could not wait for '{}'", program))?;
+
+    let stdout = Zeroizing::new(output.stdout);
+
+    if !output.status.success() {
+        return Err(Error::Unavailable(format!(
+            "The secret backend command '{} {}' failed ({}): {}",
+            program,
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let text = std::str::from_utf8(&stdout).map_err(|_| {
+        Error::Parse(format!(
+            "The secret backend command '{}' did not print valid UTF-8",
+            program
+        ))
+    })?;
+
+    Ok(Zeroizing::new(
+        text.strip_suffix('\n')
+            .map(|t| t.strip_suffix('\r').unwrap_or(t))
+            .unwrap_or(text)
+            .to_string(),
+    ))
+}
+
+///
+/// Store `value` at `<path>/<name>` in a Vault KV (v2) engine mounted at
+/// `mount`
+///
+pub(crate) fn vault_kv_put(
+    address: &str,
+    mount: &str,
+    path: &str,
+    name: &str,
+    value: &str,
+) -> Result<(), Error> {
+    let mount = format!("-mount={}", mount);
+    let location = format!("{}/{}", path.trim_end_matches('/'), name);
+
+    // `value=-` reads the value from stdin
+    run(
+        "vault",
+        &["kv", "put", &mount, &location, "value=-"],
+        &[("VAULT_ADDR", address)],
+        Some(value),
+    )?;
+
+    Ok(())
+}
+
+///
+/// Read the value stored at `<path>/<name>` in a Vault KV (v2) engine
+///
+pub(crate) fn vault_kv_get(
+    address: &str,
+    mount: &str,
+    path: &str,
+    name: &str,
+) -> Result<Zeroizing<String>, Error> {
+    let mount = format!("-mount={}", mount);
+    let location = format!("{}/{}", path.trim_end_matches('/'), name);
+
+    run(
+        "vault",
+        &["kv", "get", &mount, "-field=value", &location],
+        &[("VAULT_ADDR", address)],
+        None,
+    )
+}
+
+///
+/// Encrypt `value` with the named key of a Vault transit engine, returning
+/// Vault's ciphertext (`vault:v1:...`). The key never leaves Vault.
+///
+pub(crate) fn vault_transit_encrypt(
+    address: &str,
+    mount: &str,
+    key: &str,
+    value: &str,
+) -> Result<String, Error> {
+    let endpoint = format!("{}/encrypt/{}", mount.trim_end_matches('/'), key);
+
+    let ciphertext = run(
+        "vault",
+        &["write", "-field=ciphertext", &endpoint, "plaintext=-"],
+        &[("VAULT_ADDR", address)],
+        Some(&BASE64.encode(value.as_bytes())),
+    )?;
+
+    Ok(ciphertext.to_string())
+}
+
+///
+/// Decrypt Vault transit `ciphertext` with the named key
+///
+pub(crate) fn vault_transit_decrypt(
+    address: &str,
+    mount: &str,
+    key: &str,
+    ciphertext: &str,
+) -> Result<Zeroizing<String>, Error> {
+    let endpoint = format!("{}/decrypt/{}", mount.trim_end_matches('/'), key);
+
+    let plaintext = run(
+        "vault",
+        &["write", "-field=plaintext", &endpoint, "ciphertext=-"],
+        &[("VAULT_ADDR", address)],
+        Some(ciphertext),
+    )?;
+
+    let bytes = Zeroizing::new(
+        BASE64
+            .decode(plaintext.trim())
+            .map_err(|_| Error::Parse("Invalid base64 from the secret backend".to_string()))?,
+    );
+
+    Ok(Zeroizing::new(String::from_utf8(bytes.to_vec()).map_err(
+        |_| Error::Parse("Vault returned a non-UTF-8 secret".to_string()),
+    )?))
+}
+
+///
+/// Ask the helper `command` to store `value` (given on stdin) under `name`:
+/// `<command...> store <name>`
+///
+pub(crate) fn exec_store(command: &[String], name: &str, value: &str) -> Result<(), Error> {
+    let Some((program, args)) = command.split_first() else {
+        return Err(Error::Null(
+            "No secret helper command is configured".to_string(),
+        ));
+    };
+
+    let mut args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    args.extend(["store", name]);
+
+    run(program, &args, &[], Some(value))?;
+
+    Ok(())
+}
+
+///
+/// Ask the helper `command` for the secret stored under `name`, which it
+/// prints to stdout: `<command...> fetch <name>`
+///
+pub(crate) fn exec_fetch(command: &[String], name: &str) -> Result<Zeroizing<String>, Error> {
+    let Some((program, args)) = command.split_first() else {
+        return Err(Error::Null(
+            "No secret helper command is configured".to_string(),
+        ));
+    };
+
+    let mut args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    args.extend(["fetch", name]);
+
+    run(program, &args, &[], None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_strips_exactly_one_trailing_newline() {
+        for (printed, expected) in [
+            ("hunter2", "hunter2"),
+            ("hunter2\\n", "hunter2"),
+            ("hunter2\\r\\n", "hunter2"),
+            ("hunter2\\n\\n", "hunter2\n"),
+            ("  hunter 2\\t\\n", "  hunter 2\t"),
+            ("\\n", ""),
+        ] {
+            let output =
+                run("printf", &[printed], &[], None).unwrap_or_else(|e| unreachable!("{:?}", e));
+            assert_eq!(output.as_str(), expected, "printf '{}'", printed);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_helper_receives_the_secret_on_stdin() {
+        // a stand-in helper that keeps secrets as files in a directory
+        let dir = std::env::temp_dir().join(format!(
+            "secretstore-{}",
+            hex::encode(crate::crypto::random_bytes(8).unwrap_or_default())
+        ));
+        std::fs::create_dir_all(&dir).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let script = format!(
+            "case \"$1\" in store) cat > '{dir}'/\"$2\";; fetch) cat '{dir}'/\"$2\";; *) exit 2;; esac",
+            dir = dir.display()
+        );
+
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            script,
+            "helper".to_string(),
+        ];
+
+        exec_store(&command, "freeipa-password", "hunter2\n")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let secret =
+            exec_fetch(&command, "freeipa-password").unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(secret.as_str(), "hunter2");
+
+        assert!(exec_fetch(&command, "missing").is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
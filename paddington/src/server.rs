@@ -143,8 +143,14 @@ pub async fn run_once(config: ServiceConfig) -> Result<(), Error> {
                 // never to an established connection.
                 let (auth_tx, auth_rx) = tokio::sync::oneshot::channel::<()>();
 
-                let handle =
-                    tokio::spawn(handle_connection(stream, config.clone(), permit, auth_tx));
+                // the latest config, so that keys rotated in-band since the
+                // service started are used
+                let handle = tokio::spawn(handle_connection(
+                    stream,
+                    crate::rotation::live_config(&config),
+                    permit,
+                    auth_tx,
+                ));
 
                 tokio::spawn(async move {
                     tokio::select! {
@@ -0,0 +1,458 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+use greatwestern::grammar::{Date, ProjectIdentifier};
+use greatwestern::lifecycle::{LifecycleAction, LifecyclePolicy, LifecycleTransition};
+use greatwestern::{Hpc, NotificationEvent};
+use templemeads::agent;
+use templemeads::destination::Destination;
+use templemeads::notification;
+use templemeads::portal_identifier::PortalIdentifier;
+use templemeads::Error;
+
+use crate::{
+    block_project, get_award, get_offerings, get_projects, remove_project, unblock_project,
+    BRIDGE_WAIT_TIME,
+};
+
+/// The policy that turns the end dates of awards into transitions
+static POLICY: RwLock<Option<LifecyclePolicy>> = RwLock::new(None);
+
+/// The last transition carried out for each project, keyed by the
+/// resource and project, so that each is only carried out once
+static DONE: Mutex<BTreeMap<String, LifecycleTransition>> = Mutex::new(BTreeMap::new());
+
+/// The file in which `DONE` is kept between restarts, if any
+static DONE_FILE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+pub fn set_policy(policy: LifecyclePolicy) -> Result<(), Error> {
+    match POLICY.write() {
+        Ok(mut guard) => {
+            *guard = Some(policy);
+            Ok(())
+        }
+        Err(e) => Err(Error::Misconfigured(format!(
+            "Could not store the lifecycle policy: {}",
+            e
+        ))),
+    }
+}
+
+fn policy() -> Result<LifecyclePolicy, Error> {
+    match POLICY.read() {
+        Ok(guard) => guard.clone().ok_or_else(|| {
+            Error::Misconfigured("No lifecycle policy has been configured".to_string())
+        }),
+        Err(e) => Err(Error::Misconfigured(format!(
+            "Could not read the lifecycle policy: {}",
+            e
+        ))),
+    }
+}
+
+fn key(resource: &str, project: &ProjectIdentifier) -> String {
+    format!("{}/{}", resource, project)
+}
+
+fn get_done(resource: &str, project: &ProjectIdentifier) -> Option<LifecycleTransition> {
+    match DONE.lock() {
+        Ok(done) => done.get(&key(resource, project)).cloned(),
+        Err(e) => {
+            tracing::error!("Could not read the lifecycle transitions: {}", e);
+            None
+        }
+    }
+}
+
+fn set_done(resource: &str, project: &ProjectIdentifier, transition: Option<LifecycleTransition>) {
+    match DONE.lock() {
+        Ok(mut done) => match transition {
+            Some(transition) => {
+                done.insert(key(resource, project), transition);
+            }
+            None => {
+                done.remove(&key(resource, project));
+            }
+        },
+        Err(e) => tracing::error!("Could not record the lifecycle transition: {}", e),
+    }
+}
+
+fn load_done(path: &Path) -> Result<BTreeMap<String, LifecycleTransition>, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| {
+            Error::Parse(format!(
+                "Could not read the lifecycle transitions in {}: {}",
+                path.display(),
+                e
+            ))
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(Error::IO(e)),
+    }
+}
+
+async fn save_done(path: &Path, done: &BTreeMap<String, LifecycleTransition>) -> Result<(), Error> {
+    let json = serde_json::to_string_pretty(done).map_err(|e| {
+        Error::Parse(format!(
+            "Could not serialise the lifecycle transitions: {}",
+            e
+        ))
+    })?;
+
+    // write then rename, so a crash never leaves a half-written file
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, json).await?;
+    tokio::fs::rename(&tmp, path).await?;
+
+    Ok(())
+}
+
+///
+/// Write the transitions carried out to the state file, if there is one
+///
+async fn persist() {
+    let file = match DONE_FILE.read() {
+        Ok(file) => file.clone(),
+        Err(e) => {
+            tracing::error!("Could not read the lifecycle state file: {}", e);
+            return;
+        }
+    };
+
+    let Some(file) = file else {
+        return;
+    };
+
+    let done = match DONE.lock() {
+        Ok(done) => done.clone(),
+        Err(e) => {
+            tracing::error!("Could not read the lifecycle transitions: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = save_done(&file, &done).await {
+        tracing::error!(
+            "Could not save the lifecycle transitions to {}: {}",
+            file.display(),
+            e
+        );
+    }
+}
+
+///
+/// Return the projects on `offering` (a "resource.me.portal" offering)
+/// together with the end dates of their awards. Projects whose awards
+/// have no end date have no lifecycle, so are skipped.
+///
+async fn get_end_dates(
+    me: &str,
+    offering: &Destination,
+) -> Result<Vec<(ProjectIdentifier, Date)>, Error> {
+    let resource = offering.first();
+    let portal = PortalIdentifier::parse(&offering.last())?;
+    let forwarded_for = offering.reverse();
+
+    let mut end_dates = Vec::new();
+
+    for mapping in get_projects(me, &resource, &portal, &forwarded_for).await? {
+        match get_award(me, &resource, mapping.project(), &forwarded_for).await {
+            Ok(award) => {
+                if let Some(end_date) = award.end_date() {
+                    end_dates.push((mapping.project().clone(), end_date));
+                }
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Could not get the award of {} on {}: {}",
+                    mapping.project(),
+                    resource,
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(end_dates)
+}
+
+///
+/// Return the lifecycle transitions of the projects of all of this
+/// portal's offerings that have not yet been carried out, in date
+/// order. This is a dry run - nothing is changed.
+///
+pub async fn get_transitions() -> Result<Vec<LifecycleTransition>, Error> {
+    let me = agent::name().await;
+    let policy = policy()?;
+    let today = Date::today();
+
+    let mut transitions = Vec::new();
+
+    for offering in Vec::<Destination>::from(get_offerings().await?) {
+        let resource = offering.first();
+
+        for (project, end_date) in get_end_dates(&me, &offering).await? {
+            transitions.extend(policy.upcoming(
+                &project,
+                &resource,
+                &end_date,
+                &today,
+                get_done(&resource, &project).as_ref(),
+            ));
+        }
+    }
+
+    transitions.sort_by(|a, b| a.date().cmp(b.date()));
+
+    Ok(transitions)
+}
+
+///
+/// Tell both the awarding portal (back along the offering) and our own
+/// portal software (via the bridge) about a lifecycle event
+///
+async fn notify(me: &str, offering: &Destination, event: NotificationEvent) {
+    notification::send::<Hpc>(offering, event.clone()).await;
+
+    match agent::bridge(BRIDGE_WAIT_TIME).await {
+        Some(bridge) => match Destination::parse(&format!("{}.{}", me, bridge.name())) {
+            Ok(destination) => notification::send::<Hpc>(&destination, event).await,
+            Err(e) => tracing::warn!("Could not notify the bridge of {}: {}", event, e),
+        },
+        None => tracing::warn!("No bridge agent to notify of {}", event),
+    }
+}
+
+async fn check_project(
+    me: &str,
+    policy: &LifecyclePolicy,
+    offering: &Destination,
+    project: &ProjectIdentifier,
+    end_date: &Date,
+) -> Result<(), Error> {
+    let resource = offering.first();
+    let forwarded_for = offering.reverse();
+
+    let due = policy.due(project, &resource, end_date, &Date::today());
+    let done = get_done(&resource, project);
+
+    if due == done {
+        return Ok(());
+    }
+
+    let due_action = due.as_ref().map(|t| t.action());
+
+    // the end date was moved later, so undo the block
+    if done.map(|t| t.action()) == Some(LifecycleAction::Block)
+        && due_action != Some(LifecycleAction::Block)
+        && due_action != Some(LifecycleAction::Remove)
+    {
+        tracing::info!("Award of {} extended - unblocking on {}", project, resource);
+        unblock_project(me, &resource, project, &forwarded_for).await?;
+    }
+
+    match due_action {
+        Some(LifecycleAction::Warn(days)) => {
+            tracing::info!("Award of {} on {} ends in {} days", project, resource, days);
+            notify(
+                me,
+                offering,
+                NotificationEvent::AwardEnding(project.clone(), days),
+            )
+            .await;
+        }
+        Some(LifecycleAction::Block) => {
+            tracing::info!(
+                "Award of {} ended on {} - blocking on {}",
+                project,
+                end_date,
+                resource
+            );
+            block_project(me, &resource, project, &forwarded_for).await?;
+            notify(
+                me,
+                offering,
+                NotificationEvent::AwardExpired(project.clone()),
+            )
+            .await;
+        }
+        Some(LifecycleAction::Remove) => {
+            tracing::info!(
+                "Award of {} ended on {} - removing from {}",
+                project,
+                end_date,
+                resource
+            );
+            remove_project(me, &resource, project, &forwarded_for).await?;
+            notify(
+                me,
+                offering,
+                NotificationEvent::AwardRemoved(project.clone()),
+            )
+            .await;
+        }
+        None => {}
+    }
+
+    set_done(&resource, project, due);
+    persist().await;
+
+    Ok(())
+}
+
+async fn check_all(me: &str) {
+    let policy = match policy() {
+        Ok(policy) => policy,
+        Err(e) => {
+            tracing::error!("Could not check project lifecycles: {}", e);
+            return;
+        }
+    };
+
+    let offerings = match get_offerings().await {
+        Ok(offerings) => offerings,
+        Err(e) => {
+            tracing::error!("Could not get the offerings: {}", e);
+            return;
+        }
+    };
+
+    for offering in Vec::<Destination>::from(offerings) {
+        let end_dates = match get_end_dates(me, &offering).await {
+            Ok(end_dates) => end_dates,
+            Err(e) => {
+                tracing::warn!("Could not get the awards of {}: {}", offering, e);
+                continue;
+            }
+        };
+
+        for (project, end_date) in end_dates {
+            if let Err(e) = check_project(me, &policy, &offering, &project, &end_date).await {
+                tracing::warn!("Could not update the lifecycle of {}: {}", project, e);
+            }
+        }
+    }
+}
+
+///
+/// Start the task that, every `interval`, carries out the lifecycle
+/// transitions that have fallen due for the projects of this portal's
+/// offerings - warning before their awards end, then blocking and
+/// (optionally) removing them afterwards. Transitions are asked of the
+/// portal software via the bridge. The record of what has been done is
+/// kept in `state_file` (if given), so that a restart does not carry out
+/// the same transition again. `me` is this agent's name.
+///
+pub fn enable(me: &str, interval: Duration, state_file: Option<&Path>) -> Result<(), Error> {
+    if let Some(file) = state_file {
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let loaded = load_done(file)?;
+
+        match DONE.lock() {
+            Ok(mut done) => *done = loaded,
+            Err(e) => {
+                return Err(Error::Bug(format!(
+                    "Could not read the lifecycle transitions: {}",
+                    e
+                )))
+            }
+        }
+
+        match DONE_FILE.write() {
+            Ok(mut guard) => *guard = Some(file.to_path_buf()),
+            Err(e) => {
+                return Err(Error::Bug(format!(
+                    "Could not store the lifecycle state file: {}",
+                    e
+                )))
+            }
+        }
+
+        tracing::info!("Keeping the lifecycle transitions in {}", file.display());
+    }
+
+    let me = me.to_string();
+
+    tracing::info!(
+        "Checking project lifecycles every {} seconds",
+        interval.as_secs()
+    );
+
+    tokio::spawn(async move {
+        // give the bridge time to connect first
+        let mut interval =
+            tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+
+        loop {
+            interval.tick().await;
+            check_all(&me).await;
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_transitions_done_survive_a_reload() {
+        let file = std::env::temp_dir().join(format!("op-lifecycle-{}.json", std::process::id()));
+
+        // nothing has been done yet
+        assert!(load_done(&file)
+            .unwrap_or_else(|e| unreachable!("{:?}", e))
+            .is_empty());
+
+        let policy =
+            LifecyclePolicy::new(&[7], 0, None).unwrap_or_else(|e| unreachable!("{:?}", e));
+        let project =
+            ProjectIdentifier::parse("proj.brics").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let today = Date::today();
+        let end_date = today.prev();
+
+        // the award has ended, so the project is due to be blocked
+        let due = policy.due(&project, "gpu", &end_date, &today);
+        assert_eq!(
+            due.as_ref().map(|t| t.action()),
+            Some(LifecycleAction::Block)
+        );
+
+        let mut done = BTreeMap::new();
+
+        if let Some(due) = &due {
+            done.insert(key("gpu", &project), due.clone());
+        }
+
+        save_done(&file, &done)
+            .await
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        // after a restart, the block is already done so is not carried
+        // out again, and nothing is left to come
+        let reloaded = load_done(&file).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(reloaded, done);
+        assert_eq!(reloaded.get(&key("gpu", &project)).cloned(), due);
+        assert!(policy
+            .upcoming(
+                &project,
+                "gpu",
+                &end_date,
+                &today,
+                reloaded.get(&key("gpu", &project))
+            )
+            .is_empty());
+
+        let _ = std::fs::remove_file(&file);
+    }
+}
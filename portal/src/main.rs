@@ -3,14 +3,19 @@
 
 use anyhow::Result;
 
+mod lifecycle;
+
 use greatwestern::grammar::Instruction::{
-    AddOfferings, CreateProject, GetAward, GetAwards, GetOfferings, GetProject, GetProjectMapping,
-    GetProjects, GetStorageReport, GetStorageReports, GetUsageReport, GetUsageReports, GetUsers,
-    RemoveOfferings, RemoveProject, Submit, SyncOfferings, UpdateProject,
+    AddOfferings, CreateProject, GetAward, GetAwards, GetCostReport, GetLifecycleTransitions,
+    GetOfferings, GetProject, GetProjectMapping, GetProjects, GetStorageReport, GetStorageReports,
+    GetUsageReport, GetUsageReports, GetUsers, RemoveOfferings, RemoveProject, Submit,
+    SyncOfferings, UpdateProject,
 };
 use greatwestern::grammar::{
     DateRange, ProjectDetails, ProjectIdentifier, ProjectMapping, UserMapping,
 };
+use greatwestern::lifecycle::LifecyclePolicy;
+use greatwestern::pricing::{ProjectCostReport, RateCard};
 use greatwestern::storagereport::{ProjectStorageReport, StorageReport};
 use greatwestern::usagereport::{ProjectUsageReport, UsageReport};
 use greatwestern::{Hpc, NotificationEvent};
@@ -33,6 +38,12 @@ type Envelope = templemeads::job::Envelope<Hpc>;
 type Job = templemeads::job::Job<Hpc>;
 type NotificationEnvelope = templemeads::notification::NotificationEnvelope<Hpc>;
 
+///
+/// The rates used to cost the usage of the virtual resources managed
+/// by this portal. This is None if no rates file is configured.
+///
+static RATES: std::sync::RwLock<Option<RateCard>> = std::sync::RwLock::new(None);
+
 ///
 /// Main function for the portal instance agent
 ///
@@ -79,6 +90,70 @@ async fn main() -> Result<()> {
         }
     };
 
+    // get the (optional) rates file used to turn usage into costs
+    let rates_file = config.option("rates-file", "");
+
+    if !rates_file.is_empty() {
+        let rates = RateCard::load(std::path::Path::new(&rates_file))?;
+
+        match RATES.write() {
+            Ok(mut guard) => *guard = Some(rates),
+            Err(e) => {
+                return Err(anyhow::anyhow!(format!(
+                    "Could not store the rates from {}: {}",
+                    rates_file, e
+                )));
+            }
+        }
+
+        tracing::info!("Costing usage with the rates in {}", rates_file);
+    }
+
+    // the lifecycle policy applied to projects as their awards end. This is
+    // always set, so that the transitions can be listed before the engine
+    // that carries them out is enabled
+    lifecycle::set_policy(LifecyclePolicy::parse(
+        &config.option("lifecycle-warning-days", "30,7,1"),
+        &config.option("lifecycle-grace-days", "0"),
+        &config.option("lifecycle-retention-days", ""),
+    )?)?;
+
+    // get how often (in seconds) to carry out the lifecycle transitions
+    // that have fallen due (0, the default, disables this)
+    let lifecycle_interval: u64 = match config.option("lifecycle-check-interval", "0").parse() {
+        Ok(interval) => interval,
+        Err(_) => {
+            return Err(anyhow::anyhow!(
+                "Invalid lifecycle check interval provided. This should be a number of seconds."
+                    .to_owned(),
+            ));
+        }
+    };
+
+    if lifecycle_interval > 0 {
+        // where the transitions already carried out are kept, so that a
+        // restart does not repeat them (an empty string keeps them in memory)
+        let lifecycle_state_file = config.option(
+            "lifecycle-state-file",
+            &dirs::data_local_dir()
+                .unwrap_or(".".into())
+                .join("openportal")
+                .join("portal-lifecycle.json")
+                .to_string_lossy(),
+        );
+
+        let lifecycle_state_file = match lifecycle_state_file.trim().is_empty() {
+            true => None,
+            false => Some(std::path::PathBuf::from(lifecycle_state_file.trim())),
+        };
+
+        lifecycle::enable(
+            &config.service().name(),
+            std::time::Duration::from_secs(lifecycle_interval),
+            lifecycle_state_file.as_deref(),
+        )?;
+    }
+
     async_runnable! {
         pub async fn virtual_resource_runner(envelope: Envelope) -> Result<Job, Error>
         {
@@ -164,6 +239,12 @@ async fn main() -> Result<()> {
                     job.completed(
                         get_usage_reports(&me, &resource, &portal, &dates, &job.destination()).await?)
                 }
+                GetCostReport(project, dates) => {
+                    tracing::debug!("Getting cost report for {} for dates {}", project, dates);
+
+                    job.completed(
+                        get_cost_report(&me, &resource, &project, &dates, &job.destination()).await?)
+                }
                 GetStorageReport(project, dates) => {
                     tracing::debug!("Getting storage report for {}", project);
 
@@ -357,6 +438,13 @@ async fn main() -> Result<()> {
 
                             job.completed(sync_offerings(&existing_offerings.remove(offerings)).await?)
                         }
+                        GetLifecycleTransitions() => {
+                            // This is a dry run that lists the lifecycle
+                            // transitions that are due to the projects of
+                            // the offerings, without carrying them out
+                            tracing::info!("Getting lifecycle transitions");
+                            job.completed(lifecycle::get_transitions().await?)
+                        }
                         _ => {
                             Err(Error::InvalidInstruction(
                                 format!("Invalid instruction: {}. Only bridge agents can send instructions to the portal", job.instruction()),
@@ -900,6 +988,78 @@ pub async fn remove_project(
     }
 }
 
+///
+/// Ask the connected portal software to block (or, if `block` is false,
+/// unblock) a project on a resource, e.g. because its award has ended
+///
+async fn set_project_blocked(
+    me: &str,
+    resource: &str,
+    project: &ProjectIdentifier,
+    block: bool,
+    forwarded_for: &Destination,
+) -> Result<Vec<UserMapping>, Error> {
+    let command = match block {
+        true => "block_project",
+        false => "unblock_project",
+    };
+
+    match agent::bridge(BRIDGE_WAIT_TIME).await {
+        Some(bridge) => {
+            let job = Job::parse(
+                &format!(
+                    "{}.{}.{} {} {}",
+                    me,
+                    bridge.name(),
+                    resource,
+                    command,
+                    project
+                ),
+                false,
+            )?
+            .with_forwarded_for(forwarded_for.clone())
+            .put(&bridge)
+            .await?;
+
+            let result = job.wait().await?.result::<Vec<UserMapping>>()?;
+
+            tracing::debug!("{} {} by bridge agent: {:?}", command, project, result);
+
+            Ok(result.unwrap_or_default())
+        }
+        None => {
+            tracing::error!("No bridge agent found");
+            Err(Error::MissingAgent(
+                "Cannot run the job because there is no bridge agent".to_string(),
+            ))
+        }
+    }
+}
+
+///
+/// Block a project
+///
+pub async fn block_project(
+    me: &str,
+    resource: &str,
+    project: &ProjectIdentifier,
+    forwarded_for: &Destination,
+) -> Result<Vec<UserMapping>, Error> {
+    set_project_blocked(me, resource, project, true, forwarded_for).await
+}
+
+///
+/// Unblock a project
+///
+pub async fn unblock_project(
+    me: &str,
+    resource: &str,
+    project: &ProjectIdentifier,
+    forwarded_for: &Destination,
+) -> Result<Vec<UserMapping>, Error> {
+    set_project_blocked(me, resource, project, false, forwarded_for).await
+}
+
 ///
 /// Get an existing project
 ///
@@ -1280,6 +1440,35 @@ pub async fn get_usage_report(
     }
 }
 
+///
+/// Get the cost of the usage of an existing project. The usage is costed
+/// here, with the resource used as the node type of any usage that is not
+/// broken down by partition
+///
+pub async fn get_cost_report(
+    me: &str,
+    resource: &str,
+    project: &ProjectIdentifier,
+    dates: &DateRange,
+    forwarded_for: &Destination,
+) -> Result<ProjectCostReport, Error> {
+    // copy the rates so that the lock is not held while waiting for the report
+    let rates = match RATES.read() {
+        Ok(guard) => guard.clone(),
+        Err(e) => return Err(Error::Bug(format!("Could not read the rates: {}", e))),
+    };
+
+    let rates = rates.ok_or_else(|| {
+        Error::Misconfigured(
+            "Cannot cost usage because no rates-file has been configured".to_string(),
+        )
+    })?;
+
+    let report = get_usage_report(me, resource, project, dates, forwarded_for).await?;
+
+    Ok(rates.cost(&report, Some(resource)))
+}
+
 ///
 /// Get the storage report for an existing project (reflects current state)
 ///
@@ -4,6 +4,10 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
 use greatwestern::grammar;
+use greatwestern::jobrecords;
+use greatwestern::lifecycle;
+use greatwestern::pricing;
+use greatwestern::scheduling;
 use greatwestern::storagereport;
 use greatwestern::usagereport;
 use once_cell::sync::Lazy;
@@ -23,6 +27,7 @@ use templemeads::destination;
 use templemeads::diagnostics as mod_diagnostics;
 use templemeads::health as mod_health;
 use templemeads::job;
+use templemeads::jobhistory as mod_jobhistory;
 use templemeads::notification as mod_notification;
 use templemeads::portal_identifier;
 use templemeads::server::{sign_api_call, SignatureVersion, SIGNATURE_VERSION_HEADER};
@@ -103,6 +108,28 @@ fn call_get<T>(function: &str) -> Result<T, Error>
 where
     T: DeserializeOwned,
 {
+    call_get_with_query(function, &[])
+}
+
+///
+/// Call a GET function with the passed query parameters. A GET has no body,
+/// so the bridge expects the signature to cover the query instead - it is
+/// signed as part of the function name, e.g. `jobs/history?state=error`.
+///
+fn call_get_with_query<T>(function: &str, query: &[(&str, String)]) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(query)
+        .finish();
+
+    let function = match query.is_empty() {
+        true => function.to_string(),
+        false => format!("{}?{}", function, query),
+    };
+    let function = function.as_str();
+
     tracing::debug!("Calling get /{}", function);
 
     let config = get_config()?;
@@ -2064,6 +2091,28 @@ impl Job {
                     None => Ok(py.None().into_bound(py)),
                 }
             }
+            "Vec<RecycledDir>" => {
+                let result = match self.0.result::<Vec<greatwestern::storage::RecycledDir>>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let list = PyList::empty(py);
+                        for item in result {
+                            let dict = pyo3::types::PyDict::new(py);
+                            dict.set_item("volume", item.volume().to_string())?;
+                            dict.set_item("path", item.path())?;
+                            dict.set_item("version", item.version().to_string())?;
+                            dict.set_item("recycled", item.recycled().to_rfc3339())?;
+                            list.append(dict)?;
+                        }
+                        Ok(list.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
             "HashMap<Volume, Quota>" => {
                 let result = match self.0.result::<std::collections::HashMap<
                     greatwestern::storage::Volume,
@@ -2087,6 +2136,232 @@ impl Job {
                     None => Ok(py.None().into_bound(py)),
                 }
             }
+            "UserProfile" => {
+                let result = match self.0.result::<grammar::UserProfile>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let dict = pyo3::types::PyDict::new(py);
+                        dict.set_item("display_name", result.display_name())?;
+                        dict.set_item("email", result.email())?;
+                        dict.set_item("ssh_keys", result.ssh_keys())?;
+                        dict.set_item("shell", result.shell())?;
+                        Ok(dict.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
+            "ProjectCostReport" => {
+                let result = match self.0.result::<pricing::ProjectCostReport>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let daily = pyo3::types::PyDict::new(py);
+                        for (date, cost) in result.daily_costs() {
+                            daily.set_item(date.to_string(), cost)?;
+                        }
+
+                        let users = pyo3::types::PyDict::new(py);
+                        for (user, cost) in result.user_costs() {
+                            users.set_item(user.to_string(), cost)?;
+                        }
+
+                        let dict = pyo3::types::PyDict::new(py);
+                        dict.set_item("project", result.project().to_string())?;
+                        dict.set_item("currency", result.currency())?;
+                        dict.set_item("total", result.total_cost())?;
+                        dict.set_item("unmapped", result.unmapped_cost())?;
+                        dict.set_item("daily", daily)?;
+                        dict.set_item("users", users)?;
+                        dict.set_item("components", result.component_costs())?;
+                        Ok(dict.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
+            "SchedulingPolicy" => {
+                let result = match self.0.result::<scheduling::SchedulingPolicy>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let dict = pyo3::types::PyDict::new(py);
+                        dict.set_item("qos", result.qos())?;
+                        dict.set_item("default_qos", result.default_qos())?;
+                        dict.set_item("partitions", result.partitions())?;
+                        Ok(dict.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
+            "UserLimit" => {
+                let result = match self.0.result::<scheduling::UserLimit>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let dict = pyo3::types::PyDict::new(py);
+                        dict.set_item("usage", result.usage().map(Usage::from))?;
+                        dict.set_item("max_jobs", result.max_jobs())?;
+                        dict.set_item("max_submit_jobs", result.max_submit_jobs())?;
+                        dict.set_item("grp_tres", result.grp_tres())?;
+                        Ok(dict.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
+            "ProjectLimit" => {
+                let result = match self.0.result::<scheduling::ProjectLimit>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let users = pyo3::types::PyDict::new(py);
+                        for (user, limit) in result.users() {
+                            let dict = pyo3::types::PyDict::new(py);
+                            dict.set_item("usage", limit.usage().map(Usage::from))?;
+                            dict.set_item("max_jobs", limit.max_jobs())?;
+                            dict.set_item("max_submit_jobs", limit.max_submit_jobs())?;
+                            dict.set_item("grp_tres", limit.grp_tres())?;
+                            users.set_item(user, dict)?;
+                        }
+
+                        let dict = pyo3::types::PyDict::new(py);
+                        dict.set_item("limit", Usage::from(*result.limit()))?;
+                        dict.set_item("users", users)?;
+                        Ok(dict.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
+            "JobRecords" => {
+                let result = match self.0.result::<jobrecords::JobRecords>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let records = PyList::empty(py);
+                        for record in result.records() {
+                            let dict = pyo3::types::PyDict::new(py);
+                            dict.set_item("id", record.id())?;
+                            dict.set_item("user", record.user().map(|u| u.to_string()))?;
+                            dict.set_item("local_user", record.local_user())?;
+                            dict.set_item("state", record.state())?;
+                            dict.set_item("qos", record.qos())?;
+                            dict.set_item("eligible_time", record.eligible_time().to_rfc3339())?;
+                            dict.set_item("start_time", record.start_time().to_rfc3339())?;
+                            dict.set_item("end_time", record.end_time().to_rfc3339())?;
+                            dict.set_item("wait_seconds", record.wait_seconds())?;
+                            dict.set_item("nodes", record.nodes())?;
+                            dict.set_item("cpus", record.cpus())?;
+                            dict.set_item("gpus", record.gpus())?;
+                            dict.set_item("memory", record.memory())?;
+                            dict.set_item("billing", record.billing())?;
+                            dict.set_item("usage", Usage::from(*record.usage()))?;
+                            records.append(dict)?;
+                        }
+
+                        let dict = pyo3::types::PyDict::new(py);
+                        dict.set_item("project", result.project().to_string())?;
+                        dict.set_item("dates", result.dates().to_string())?;
+                        dict.set_item("page", result.page())?;
+                        dict.set_item("page_size", result.page_size())?;
+                        dict.set_item("num_pages", result.num_pages())?;
+                        dict.set_item("total", result.total())?;
+                        dict.set_item("has_more", result.has_more())?;
+                        dict.set_item("records", records)?;
+                        Ok(dict.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
+            "Reservation" => {
+                let result = match self.0.result::<scheduling::Reservation>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let dict = pyo3::types::PyDict::new(py);
+                        dict.set_item("name", result.name())?;
+                        dict.set_item("nodes", result.nodes())?;
+                        dict.set_item("node_count", result.node_count())?;
+                        dict.set_item("start_time", result.start_time().to_rfc3339())?;
+                        dict.set_item("end_time", result.end_time().to_rfc3339())?;
+                        dict.set_item("partition", result.partition())?;
+                        Ok(dict.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
+            "Vec<Reservation>" => {
+                let result = match self.0.result::<Vec<scheduling::Reservation>>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let list = PyList::empty(py);
+                        for item in result {
+                            let dict = pyo3::types::PyDict::new(py);
+                            dict.set_item("name", item.name())?;
+                            dict.set_item("nodes", item.nodes())?;
+                            dict.set_item("node_count", item.node_count())?;
+                            dict.set_item("start_time", item.start_time().to_rfc3339())?;
+                            dict.set_item("end_time", item.end_time().to_rfc3339())?;
+                            dict.set_item("partition", item.partition())?;
+                            list.append(dict)?;
+                        }
+                        Ok(list.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
+            "Vec<LifecycleTransition>" => {
+                let result = match self.0.result::<Vec<lifecycle::LifecycleTransition>>() {
+                    Ok(result) => result,
+                    Err(e) => return Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+                };
+
+                match result {
+                    Some(result) => {
+                        let list = PyList::empty(py);
+                        for item in result {
+                            let (action, days) = match item.action() {
+                                lifecycle::LifecycleAction::Warn(days) => ("warn", Some(days)),
+                                lifecycle::LifecycleAction::Block => ("block", None),
+                                lifecycle::LifecycleAction::Remove => ("remove", None),
+                            };
+
+                            let dict = pyo3::types::PyDict::new(py);
+                            dict.set_item("project", item.project().to_string())?;
+                            dict.set_item("resource", item.resource())?;
+                            dict.set_item("date", item.date().to_string())?;
+                            dict.set_item("action", action)?;
+                            dict.set_item("days_left", days)?;
+                            list.append(dict)?;
+                        }
+                        Ok(list.into_any())
+                    }
+                    None => Ok(py.None().into_bound(py)),
+                }
+            }
             _ => Err(PyErr::new::<PyOSError, _>(format!(
                 "Unknown result type: {}",
                 result_type
@@ -5461,6 +5736,34 @@ fn status(job: Job) -> PyResult<Job> {
     }
 }
 
+///
+/// Export the result of the passed (completed) job in the passed
+/// format - "json", "csv", "xdmod", "apel" or "apel-billed" - returning
+/// the exported text, e.g. to save to a file. Usage reports can be
+/// exported as csv, apel or apel-billed (which gives the usage charged
+/// as the APEL durations), and job records as csv or xdmod. The site names the
+/// site (or cluster) in formats that need one, defaulting to the
+/// name of the portal.
+///
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[pyo3(signature = (job, format, site=None))]
+fn export_report(job: Job, format: &str, site: Option<String>) -> PyResult<String> {
+    match call_post::<serde_json::Value>(
+        "export",
+        serde_json::json!({"job": job.0.id().to_string(), "format": format, "site": site}),
+    ) {
+        Ok(response) => match response.get("content").and_then(|c| c.as_str()) {
+            Some(content) => Ok(content.to_string()),
+            None => Err(PyErr::new::<PyOSError, _>(format!(
+                "No content in the export of job {}",
+                job.0.id()
+            ))),
+        },
+        Err(e) => Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+    }
+}
+
 ///
 /// Return the Job with the specified ID. Raises an error if the
 /// job does not exist.
@@ -5563,6 +5866,160 @@ fn fetch_notification(py: Python<'_>, notification_id: Py<PyAny>) -> PyResult<No
     }
 }
 
+///
+/// A summary of a finished job, kept in the bridge's job history
+///
+#[gen_stub_pyclass]
+#[pyclass(module = "openportal")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord(mod_jobhistory::JobRecord);
+
+#[gen_stub_pymethods]
+#[pymethods]
+impl JobRecord {
+    #[getter]
+    fn id(&self) -> PyResult<Uuid> {
+        Ok(self.0.id.into())
+    }
+
+    #[getter]
+    fn destination(&self) -> PyResult<String> {
+        Ok(self.0.destination.clone())
+    }
+
+    #[getter]
+    fn instruction(&self) -> PyResult<String> {
+        Ok(self.0.instruction.clone())
+    }
+
+    #[getter]
+    fn state(&self) -> PyResult<String> {
+        Ok(self.0.state.clone())
+    }
+
+    #[getter]
+    fn error_kind(&self) -> PyResult<Option<String>> {
+        Ok(self.0.error_kind.clone())
+    }
+
+    #[getter]
+    fn error_message(&self) -> PyResult<Option<String>> {
+        Ok(self.0.error_message.clone())
+    }
+
+    #[getter]
+    fn created<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDateTime>> {
+        PyDateTime::from_timestamp(
+            py,
+            self.0.created.timestamp() as f64,
+            PyTzInfo::utc(py).ok().as_deref(),
+        )
+    }
+
+    #[getter]
+    fn finished<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDateTime>> {
+        PyDateTime::from_timestamp(
+            py,
+            self.0.finished.timestamp() as f64,
+            PyTzInfo::utc(py).ok().as_deref(),
+        )
+    }
+
+    #[getter]
+    fn duration_ms(&self) -> PyResult<i64> {
+        Ok(self.0.duration_ms)
+    }
+
+    fn is_error(&self) -> PyResult<bool> {
+        Ok(self.0.error_kind.is_some())
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        let mut s = format!(
+            "JobRecord( {} {} {}: {} ({}ms)",
+            self.0.finished.to_rfc3339(),
+            self.0.destination,
+            self.0.instruction,
+            self.0.state,
+            self.0.duration_ms
+        );
+
+        if let Some(kind) = &self.0.error_kind {
+            s.push_str(&format!(
+                ", error[{}]: {}",
+                kind,
+                self.0.error_message.as_deref().unwrap_or_default()
+            ));
+        }
+
+        s.push_str(" )");
+        Ok(s)
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        self.__str__()
+    }
+
+    fn __copy__(&self) -> PyResult<JobRecord> {
+        Ok(self.clone())
+    }
+
+    fn __deepcopy__(&self, _memo: Py<PyAny>) -> PyResult<JobRecord> {
+        Ok(self.clone())
+    }
+}
+
+impl From<mod_jobhistory::JobRecord> for JobRecord {
+    fn from(record: mod_jobhistory::JobRecord) -> Self {
+        JobRecord(record)
+    }
+}
+
+///
+/// Return the history of finished jobs kept by the bridge, newest first.
+///
+/// Parameters:
+/// - since: only jobs that finished at or after this (UTC) datetime
+/// - project: only jobs whose instruction names this project
+///            (e.g. "myproject.myportal"), including its users
+/// - state: only jobs that ended in this state (e.g. "error")
+/// - limit: return at most this many jobs (the bridge caps this at 1000)
+///
+/// Raises an error if the bridge is not keeping a job history.
+///
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[pyo3(signature = (since=None, project=None, state=None, limit=None))]
+fn job_history(
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    project: Option<String>,
+    state: Option<String>,
+    limit: Option<usize>,
+) -> PyResult<Vec<JobRecord>> {
+    let mut query = Vec::new();
+
+    if let Some(since) = since {
+        query.push(("since", since.to_rfc3339()));
+    }
+
+    if let Some(project) = project {
+        query.push(("project", project));
+    }
+
+    if let Some(state) = state {
+        query.push(("state", state));
+    }
+
+    if let Some(limit) = limit {
+        query.push(("limit", limit.to_string()));
+    }
+
+    match call_get_with_query::<Vec<mod_jobhistory::JobRecord>>("jobs/history", &query) {
+        Ok(records) => Ok(records.into_iter().map(Into::into).collect()),
+        Err(e) => Err(PyErr::new::<PyOSError, _>(format!("{:?}", e))),
+    }
+}
+
 #[gen_stub_pyfunction]
 #[pyfunction]
 fn add_offerings(offerings: Vec<Destination>) -> PyResult<Vec<Destination>> {
@@ -5850,12 +6307,16 @@ struct QuotaLimit(greatwestern::storage::QuotaLimit);
 impl QuotaLimit {
     #[staticmethod]
     fn limited(size: &StorageSize) -> PyResult<Self> {
-        Ok(Self(greatwestern::storage::QuotaLimit::Limited(size.0)))
+        Ok(Self(greatwestern::storage::QuotaLimit::limited(size.0)))
     }
 
     #[staticmethod]
     fn unlimited() -> PyResult<Self> {
-        Ok(Self(greatwestern::storage::QuotaLimit::Unlimited))
+        Ok(Self(greatwestern::storage::QuotaLimit::unlimited()))
+    }
+
+    fn with_inodes(&self, inodes: u64) -> PyResult<Self> {
+        Ok(Self(self.0.with_inodes(inodes)))
     }
 
     #[staticmethod]
@@ -5904,6 +6365,11 @@ impl QuotaLimit {
         Ok(self.0.size().map(|s| s.into()))
     }
 
+    #[getter]
+    fn inodes(&self) -> PyResult<Option<u64>> {
+        Ok(self.0.inodes())
+    }
+
     fn __str__(&self) -> PyResult<String> {
         Ok(self.0.to_string())
     }
@@ -5971,7 +6437,7 @@ impl Quota {
 
     #[getter]
     fn limit(&self) -> PyResult<QuotaLimit> {
-        Ok(QuotaLimit(self.0.limit().clone()))
+        Ok(QuotaLimit(self.0.limit()))
     }
 
     #[setter]
@@ -6003,6 +6469,21 @@ impl Quota {
         Ok(self.0.percentage_used())
     }
 
+    #[getter]
+    fn inode_usage(&self) -> PyResult<Option<u64>> {
+        Ok(self.0.inode_usage())
+    }
+
+    #[setter]
+    fn set_inode_usage(&mut self, inode_usage: u64) -> PyResult<()> {
+        self.0.set_inode_usage(inode_usage);
+        Ok(())
+    }
+
+    fn percentage_inodes_used(&self) -> PyResult<Option<f64>> {
+        Ok(self.0.percentage_inodes_used())
+    }
+
     fn __str__(&self) -> PyResult<String> {
         Ok(self.0.to_string())
     }
@@ -6138,10 +6619,12 @@ fn openportal(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(get, m)?)?;
     m.add_function(wrap_pyfunction!(get_offerings, m)?)?;
     m.add_function(wrap_pyfunction!(error_from_message, m)?)?;
+    m.add_function(wrap_pyfunction!(export_report, m)?)?;
     m.add_function(wrap_pyfunction!(get_portal, m)?)?;
     m.add_function(wrap_pyfunction!(diagnostics, m)?)?;
     m.add_function(wrap_pyfunction!(health, m)?)?;
     m.add_function(wrap_pyfunction!(is_config_loaded, m)?)?;
+    m.add_function(wrap_pyfunction!(job_history, m)?)?;
     m.add_function(wrap_pyfunction!(initialize_tracing, m)?)?;
     m.add_function(wrap_pyfunction!(remove_offerings, m)?)?;
     m.add_function(wrap_pyfunction!(restart, m)?)?;
@@ -6163,6 +6646,7 @@ fn openportal(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<ExpiredJobEntry>()?;
     m.add_class::<RunningJobEntry>()?;
     m.add_class::<Job>()?;
+    m.add_class::<JobRecord>()?;
     m.add_class::<Notification>()?;
     m.add_class::<UserIdentifier>()?;
     m.add_class::<ProjectIdentifier>()?;
@@ -2,7 +2,8 @@
 // SPDX-License-Identifier: MIT
 
 use anyhow::Result;
-use greatwestern::grammar::{Date, Hour, ProjectIdentifier, UserIdentifier};
+use greatwestern::grammar::{Date, Hour, ProjectIdentifier, ProjectTemplate, UserIdentifier};
+use greatwestern::scheduling::SchedulingPolicy;
 use greatwestern::usagereport::DailyProjectUsageReport;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
@@ -16,6 +17,7 @@ use crate::slurm::{SlurmAccount, SlurmJob, SlurmNode, SlurmNodes, SlurmUser};
 struct UsageDatabase {
     reports: HashMap<Date, DailyProjectUsageReport>,
     hourly_reports: HashMap<Date, HashMap<Hour, Vec<SlurmJob>>>,
+    daily_jobs: HashMap<Date, Vec<SlurmJob>>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -23,6 +25,8 @@ struct Database {
     cluster: Option<String>,
     partition: Option<String>,
     parent_account: String,
+    manager_roles: Vec<String>,
+    template_policies: HashMap<String, SchedulingPolicy>,
     accounts: HashMap<String, SlurmAccount>,
     users: HashMap<String, SlurmUser>,
     nodes: Option<SlurmNodes>,
@@ -90,6 +94,11 @@ fn enforce_cache_bounds(cache: &mut Database) {
             MAX_CACHED_DATES_PER_PROJECT,
             &format!("hourly usage for {}", project),
         );
+        evict_oldest_until(
+            &mut usage.daily_jobs,
+            MAX_CACHED_DATES_PER_PROJECT,
+            &format!("daily jobs for {}", project),
+        );
     }
 
     retain_held_mutexes(&mut cache.user_mutexes, MAX_CACHED_MUTEXES, "user");
@@ -249,6 +258,48 @@ pub async fn set_parent_account(parent_account: &str) -> Result<(), Error> {
     Ok(())
 }
 
+///
+/// Set the project roles (e.g. "pi", "manager") whose members are
+/// made coordinators of their project's account
+///
+pub async fn set_manager_roles(roles: &[String]) -> Result<(), Error> {
+    let mut cache = CACHE.write().await;
+    cache.manager_roles = roles.to_vec();
+    Ok(())
+}
+
+///
+/// Return the project roles whose members are made coordinators
+/// of their project's account
+///
+pub async fn get_manager_roles() -> Result<Vec<String>, Error> {
+    let cache = CACHE.read().await;
+    Ok(cache.manager_roles.clone())
+}
+
+///
+/// Set the scheduling policies that are applied to projects created
+/// from each project template
+///
+pub async fn set_template_policies(
+    policies: &HashMap<String, SchedulingPolicy>,
+) -> Result<(), Error> {
+    let mut cache = CACHE.write().await;
+    cache.template_policies = policies.clone();
+    Ok(())
+}
+
+///
+/// Return the scheduling policy for projects created from the passed
+/// template, or None if no policy has been configured for it
+///
+pub async fn get_template_policy(
+    template: &ProjectTemplate,
+) -> Result<Option<SchedulingPolicy>, Error> {
+    let cache = CACHE.read().await;
+    Ok(cache.template_policies.get(&template.to_string()).cloned())
+}
+
 ///
 /// Return the name of the parent account
 ///
@@ -514,6 +565,52 @@ pub async fn get_hourly_report(
     }
 }
 
+///
+/// Set the jobs of this project that ran on this (finished) date, so
+/// that the job records of later pages don't read them again
+///
+pub async fn set_daily_jobs(
+    project: &ProjectIdentifier,
+    date: &Date,
+    jobs: &[SlurmJob],
+) -> Result<(), Error> {
+    if date >= &Date::today() {
+        return Err(Error::Bug(format!(
+            "Cannot cache the jobs of project '{}' for unfinished date: {} - {} jobs",
+            project,
+            date,
+            jobs.len()
+        )));
+    }
+
+    let mut cache = CACHE.write().await;
+    enforce_cache_bounds(&mut cache);
+
+    cache
+        .reports
+        .entry(project.clone())
+        .or_default()
+        .daily_jobs
+        .insert(date.clone(), jobs.to_vec());
+
+    Ok(())
+}
+
+///
+/// Get the cached jobs of this project that ran on this date
+///
+pub async fn get_daily_jobs(
+    project: &ProjectIdentifier,
+    date: &Date,
+) -> Result<Option<Vec<SlurmJob>>, Error> {
+    let cache = CACHE.read().await;
+
+    match cache.reports.get(project) {
+        Some(usage) => Ok(usage.daily_jobs.get(date).cloned()),
+        None => Ok(None),
+    }
+}
+
 ///
 /// Clear the cache - we need to do this if Slurm is changed behine
 /// our back
@@ -3,11 +3,17 @@
 
 use anyhow::Result;
 
+use greatwestern::grammar::validate_role;
 use greatwestern::grammar::Instruction::{
-    AddLocalProject, AddLocalUser, GetLocalLimit, GetLocalUsageReport, RemoveLocalProject,
-    RemoveLocalUser, SetLocalLimit,
+    AddLocalProject, AddLocalUser, CreateLocalReservation, DeleteLocalReservation,
+    GetLocalJobRecords, GetLocalLimit, GetLocalMemberLimits, GetLocalReservations,
+    GetLocalSchedulingPolicy, GetLocalUsageReport, GetLocalUserLimit, RemoveLocalProject,
+    RemoveLocalUser, SetLocalLimit, SetLocalProjectTemplate, SetLocalSchedulingPolicy,
+    SetLocalUserLimit, SetLocalUserRole, UpdateLocalReservation,
 };
+use greatwestern::scheduling::SchedulingPolicy;
 use greatwestern::Hpc;
+use std::collections::HashMap;
 use templemeads::agent::scheduler::{process_args, run, Defaults};
 use templemeads::agent::Type as AgentType;
 use templemeads::async_runnable;
@@ -19,6 +25,7 @@ type Envelope = templemeads::job::Envelope<Hpc>;
 type Job = templemeads::job::Job<Hpc>;
 
 mod cache;
+mod reports;
 mod sacctmgr;
 mod slurm;
 
@@ -107,6 +114,42 @@ async fn main() -> Result<()> {
     let parent_account = config.option("parent-account", "root");
     cache::set_parent_account(&parent_account).await?;
 
+    // get the project roles whose members are made coordinators of
+    // their project's account, as a comma-separated list
+    let manager_roles: Vec<String> = config
+        .option("manager-roles", "pi,manager")
+        .split(',')
+        .filter(|role| !role.trim().is_empty())
+        .map(validate_role)
+        .collect::<Result<Vec<_>, _>>()?;
+    cache::set_manager_roles(&manager_roles).await?;
+
+    // get the (optional) scheduling policies to apply to projects created
+    // from each template, as a JSON object of template name to policy
+    let template_policies = config.option("template-policies", "");
+
+    if !template_policies.is_empty() {
+        let policies: HashMap<String, serde_json::Value> = match serde_json::from_str(
+            &template_policies,
+        ) {
+            Ok(policies) => policies,
+            Err(e) => {
+                return Err(anyhow::anyhow!(format!(
+                        "Invalid template policies provided. This should be a JSON object of template names to scheduling policies. Set this in the template-policies option: {}",
+                        e)));
+            }
+        };
+
+        let policies = policies
+            .into_iter()
+            .map(|(template, policy)| {
+                SchedulingPolicy::parse(&policy.to_string()).map(|policy| (template, policy))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        cache::set_template_policies(&policies).await?;
+    }
+
     let slurm_server = config.option("slurm-server", "");
 
     // get the sacct, sacctmgr, scontrol and scancel commands - we may need these even if
@@ -174,14 +217,72 @@ async fn main() -> Result<()> {
                         let report = sacctmgr::get_usage_report(&mapping, &dates, job.expires()).await?;
                         job.completed(report)
                     }
+                    GetLocalJobRecords(mapping, dates, page) => {
+                        let records = sacctmgr::get_job_records(&mapping, &dates, &page, job.expires()).await?;
+                        job.completed(records)
+                    }
                     GetLocalLimit(mapping) => {
                         let limit = sacctmgr::get_limit(&mapping, job.expires()).await?;
                         job.completed(limit)
                     }
+                    GetLocalMemberLimits(mapping) => {
+                        let limits = sacctmgr::get_member_limits(&mapping, job.expires()).await?;
+                        job.completed(limits)
+                    }
                     SetLocalLimit(mapping, limit) => {
                         let limit = sacctmgr::set_limit(&mapping, &limit, job.expires()).await?;
                         job.completed(limit)
                     }
+                    GetLocalUserLimit(mapping) => {
+                        let limit = sacctmgr::get_user_limit(&mapping, job.expires()).await?;
+                        job.completed(limit)
+                    }
+                    SetLocalUserLimit(mapping, limit) => {
+                        let limit = sacctmgr::set_user_limit(&mapping, &limit, job.expires()).await?;
+                        job.completed(limit)
+                    }
+                    SetLocalUserRole(mapping, role) => {
+                        sacctmgr::set_user_role(&mapping, &role, job.expires()).await?;
+                        job.completed_none()
+                    }
+                    GetLocalSchedulingPolicy(mapping) => {
+                        let policy = sacctmgr::get_scheduling_policy(&mapping, job.expires()).await?;
+                        job.completed(policy)
+                    }
+                    SetLocalSchedulingPolicy(mapping, policy) => {
+                        let policy = sacctmgr::set_scheduling_policy(&mapping, &policy, job.expires()).await?;
+                        job.completed(policy)
+                    }
+                    SetLocalProjectTemplate(mapping, template) => {
+                        let policy = match cache::get_template_policy(&template).await? {
+                            Some(policy) => policy,
+                            None => {
+                                return Err(Error::NotFound(format!(
+                                    "No scheduling policy is configured for template {}",
+                                    template
+                                )));
+                            }
+                        };
+
+                        let policy = sacctmgr::set_scheduling_policy(&mapping, &policy, job.expires()).await?;
+                        job.completed(policy)
+                    }
+                    GetLocalReservations(mapping) => {
+                        let reservations = sacctmgr::get_reservations(&mapping, job.expires()).await?;
+                        job.completed(reservations)
+                    }
+                    CreateLocalReservation(mapping, reservation) => {
+                        let reservation = sacctmgr::create_reservation(&mapping, &reservation, job.expires()).await?;
+                        job.completed(reservation)
+                    }
+                    UpdateLocalReservation(mapping, reservation) => {
+                        let reservation = sacctmgr::update_reservation(&mapping, &reservation, job.expires()).await?;
+                        job.completed(reservation)
+                    }
+                    DeleteLocalReservation(mapping, name) => {
+                        let reservation = sacctmgr::delete_reservation(&mapping, &name, job.expires()).await?;
+                        job.completed(reservation)
+                    }
                     _ => {
                         Err(Error::InvalidInstruction(
                             format!("Invalid instruction: {}. Slurm agents do not support this instruction", job.instruction()),
@@ -275,18 +376,75 @@ async fn main() -> Result<()> {
                         job.completed_none()
                     },
                     GetLocalUsageReport(mapping, dates) => {
-                        // use sacctmgr for now, as we need to validate the API response
                         let report = slurm::get_usage_report(&mapping, &dates, job.expires()).await?;
                         job.completed(report)
                     }
+                    GetLocalJobRecords(mapping, dates, page) => {
+                        let records = slurm::get_job_records(&mapping, &dates, &page, job.expires()).await?;
+                        job.completed(records)
+                    }
                     GetLocalLimit(mapping) => {
                         let limit = slurm::get_limit(&mapping, job.expires()).await?;
                         job.completed(limit)
                     }
+                    GetLocalMemberLimits(mapping) => {
+                        let limits = slurm::get_member_limits(&mapping, job.expires()).await?;
+                        job.completed(limits)
+                    }
                     SetLocalLimit(mapping, limit) => {
                         let limit = slurm::set_limit(&mapping, &limit, job.expires()).await?;
                         job.completed(limit)
                     }
+                    GetLocalUserLimit(mapping) => {
+                        let limit = slurm::get_user_limit(&mapping, job.expires()).await?;
+                        job.completed(limit)
+                    }
+                    SetLocalUserLimit(mapping, limit) => {
+                        let limit = slurm::set_user_limit(&mapping, &limit, job.expires()).await?;
+                        job.completed(limit)
+                    }
+                    SetLocalUserRole(mapping, role) => {
+                        sacctmgr::set_user_role(&mapping, &role, job.expires()).await?;
+                        job.completed_none()
+                    }
+                    GetLocalSchedulingPolicy(mapping) => {
+                        let policy = slurm::get_scheduling_policy(&mapping, job.expires()).await?;
+                        job.completed(policy)
+                    }
+                    SetLocalSchedulingPolicy(mapping, policy) => {
+                        let policy = slurm::set_scheduling_policy(&mapping, &policy, job.expires()).await?;
+                        job.completed(policy)
+                    }
+                    SetLocalProjectTemplate(mapping, template) => {
+                        let policy = match cache::get_template_policy(&template).await? {
+                            Some(policy) => policy,
+                            None => {
+                                return Err(Error::NotFound(format!(
+                                    "No scheduling policy is configured for template {}",
+                                    template
+                                )));
+                            }
+                        };
+
+                        let policy = slurm::set_scheduling_policy(&mapping, &policy, job.expires()).await?;
+                        job.completed(policy)
+                    }
+                    GetLocalReservations(mapping) => {
+                        let reservations = slurm::get_reservations(&mapping, job.expires()).await?;
+                        job.completed(reservations)
+                    }
+                    CreateLocalReservation(mapping, reservation) => {
+                        let reservation = slurm::create_reservation(&mapping, &reservation, job.expires()).await?;
+                        job.completed(reservation)
+                    }
+                    UpdateLocalReservation(mapping, reservation) => {
+                        let reservation = slurm::update_reservation(&mapping, &reservation, job.expires()).await?;
+                        job.completed(reservation)
+                    }
+                    DeleteLocalReservation(mapping, name) => {
+                        let reservation = slurm::delete_reservation(&mapping, &name, job.expires()).await?;
+                        job.completed(reservation)
+                    }
                     _ => {
                         Err(Error::InvalidInstruction(
                             format!("Invalid instruction: {}. Slurm agents do not support this instruction", job.instruction()),
@@ -0,0 +1,694 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! Usage reports and job records, built from the jobs charged to a
+//! project's account. These are the same whether the jobs are read with
+//! `sacct` or from slurmrestd, so both backends share this code, and only
+//! provide a `JobSource` that reads the jobs in a window of time.
+
+use chrono::Utc;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use greatwestern::grammar::{Date, DateRange, Hour, ProjectMapping};
+use greatwestern::jobrecords::{JobRecord, JobRecords, Page};
+use greatwestern::pricing::USAGE_COMPONENT;
+use greatwestern::usagereport::{
+    partition_component, DailyProjectUsageReport, ProjectUsageReport, Usage,
+};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+use templemeads::job::assert_not_expired;
+use templemeads::Error;
+
+use crate::cache;
+use crate::slurm::SlurmJob;
+
+/// How long to wait for the jobs of a whole day, before falling back
+/// to reading them hour by hour
+const DAILY_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// How long to wait for the jobs of an hour
+const HOURLY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The most days whose jobs are read at once for the job records
+const MAX_CONCURRENT_DAYS: usize = 8;
+
+///
+/// Where the jobs charged to a project's account are read from
+///
+pub trait JobSource: Clone + Send + Sync + 'static {
+    ///
+    /// Get the allocations charged to the account that were running at
+    /// some point between `start_time` and `end_time`, clipped to that
+    /// window. This should fail with `Error::Timeout` if the query takes
+    /// longer than `timeout`.
+    ///
+    fn get_jobs(
+        &self,
+        start_time: &chrono::DateTime<Utc>,
+        end_time: &chrono::DateTime<Utc>,
+        timeout: Duration,
+        expires: &chrono::DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<SlurmJob>, Error>> + Send;
+}
+
+///
+/// Add the usage of `jobs` to `daily_report`, counting the job itself (and
+/// its wait time) only if it started at or after `counted_from`, so that a
+/// job spanning several periods is only counted once. Returns the totals
+/// added, as (usage seconds, jobs, wait seconds), to check the report against.
+///
+fn add_jobs_to_report(
+    daily_report: &mut DailyProjectUsageReport,
+    jobs: &[SlurmJob],
+    counted_from: &chrono::DateTime<Utc>,
+) -> (u64, u64, u64) {
+    let mut total_usage: u64 = 0;
+    let mut num_jobs: u64 = 0;
+    let mut total_wait_seconds: u64 = 0;
+
+    for job in jobs {
+        total_usage += job.billed_node_seconds();
+        daily_report.add_usage(job.user(), Usage::new(job.billed_node_seconds()));
+
+        let components = [
+            ("cpu", job.cpu_seconds()),
+            ("memory", job.memory_seconds()),
+            ("gpu", job.gpu_seconds()),
+            ("billing", job.billing_seconds()),
+        ];
+
+        for (component, seconds) in components {
+            daily_report.add_component_usage(component, job.user(), Usage::new(seconds));
+        }
+
+        // the same usage again, broken down by partition so that it can be
+        // priced at the rates of the partition it was used on
+        if !job.partition().is_empty() {
+            for (component, seconds) in
+                std::iter::once((USAGE_COMPONENT, job.billed_node_seconds())).chain(components)
+            {
+                daily_report.add_component_usage(
+                    &partition_component(component, job.partition()),
+                    job.user(),
+                    Usage::new(seconds),
+                );
+            }
+        }
+
+        if job.original_start_time() >= counted_from {
+            num_jobs += 1;
+            total_wait_seconds += job.wait_time().num_seconds() as u64;
+            daily_report.add_jobs(job.user(), 1);
+            daily_report.add_wait_seconds(job.user(), job.wait_time().num_seconds() as u64);
+        }
+    }
+
+    (total_usage, num_jobs, total_wait_seconds)
+}
+
+///
+/// Add `jobs` to the records, keyed by job ID. Jobs that are read in
+/// several windows of time (e.g. hour by hour) are clipped to each
+/// window, so the usage of each window is added to a single record.
+///
+pub fn add_jobs_to_records(records: &mut BTreeMap<u64, JobRecord>, jobs: &[SlurmJob]) {
+    for job in jobs {
+        match records.get_mut(&job.id()) {
+            Some(record) => {
+                record.add_usage(Usage::new(job.billed_node_seconds()), job.end_time());
+            }
+            None => {
+                records.insert(job.id(), job.to_record());
+            }
+        }
+    }
+}
+
+///
+/// Check `daily_report` against the totals added to it, and if they agree
+/// and the day is over, mark it as complete and cache it.
+///
+async fn finish_daily_report(
+    project: &ProjectMapping,
+    day: &Date,
+    mut daily_report: DailyProjectUsageReport,
+    (total_usage, num_jobs, total_wait_seconds): (u64, u64, u64),
+) -> DailyProjectUsageReport {
+    // runtime consistency check: local shadow counters must match the report's scalar totals
+    if daily_report.num_jobs() != num_jobs
+        || daily_report.total_wait_seconds() != total_wait_seconds
+    {
+        tracing::warn!(
+            "Job count/wait time inconsistency for project {} on {}: \
+             local counters ({} jobs, {}s wait) differ from report totals ({} jobs, {}s wait). \
+             This may indicate a bug.",
+            project.project(),
+            day,
+            num_jobs,
+            total_wait_seconds,
+            daily_report.num_jobs(),
+            daily_report.total_wait_seconds()
+        );
+    }
+
+    if daily_report.total_usage().seconds() != total_usage {
+        // don't mark this as complete or cache it, because this points
+        // to some error when generating the values...
+        tracing::error!(
+            "Total usage in daily report does not match total usage calculated manually: {} != {}",
+            daily_report.total_usage().seconds(),
+            total_usage
+        );
+    } else if day.day().end_time().and_utc() < Utc::now() {
+        daily_report.set_complete();
+
+        if let Err(e) = cache::set_report(project.project(), day, &daily_report).await {
+            tracing::error!("Could not cache report for {}: {}", day, e);
+        }
+    }
+
+    daily_report
+}
+
+///
+/// Get the jobs of the project that ran in `hour`, using the cached
+/// jobs if this hour has been read before. Hours that have not yet
+/// started have no jobs.
+///
+async fn get_hourly_jobs<S: JobSource>(
+    source: &S,
+    expires: &chrono::DateTime<Utc>,
+    project: &ProjectMapping,
+    hour: &Hour,
+    now: &chrono::DateTime<Utc>,
+) -> Result<Vec<SlurmJob>, Error> {
+    if let Some(jobs) = cache::get_hourly_report(project.project(), hour).await? {
+        tracing::debug!(
+            "Using cached hourly report for {}. Number of jobs = {}",
+            hour,
+            jobs.len()
+        );
+        return Ok(jobs);
+    }
+
+    assert_not_expired(expires)?;
+
+    let start_time = hour.start_time().and_utc();
+
+    if start_time > *now {
+        // we can't get the usage for this hour yet as it is in the future
+        return Ok(Vec::new());
+    }
+
+    let end_time = hour.end_time().and_utc().min(*now);
+
+    let jobs = source
+        .get_jobs(&start_time, &end_time, HOURLY_TIMEOUT, expires)
+        .await?;
+
+    tracing::debug!(
+        "Got {} jobs for project {} on {}",
+        jobs.len(),
+        project.project(),
+        hour
+    );
+
+    // cache this hourly report if it is in the past
+    if hour.end_time().and_utc() < *now {
+        if let Err(e) = cache::set_hourly_report(project.project(), hour, &jobs).await {
+            tracing::error!("Could not cache hourly report for {}: {}", hour, e);
+        }
+    }
+
+    Ok(jobs)
+}
+
+async fn get_hourly_report<S: JobSource>(
+    source: &S,
+    expires: &chrono::DateTime<Utc>,
+    project: &ProjectMapping,
+    day: &Date,
+) -> Result<DailyProjectUsageReport, Error> {
+    let now = Utc::now();
+    let mut daily_report = DailyProjectUsageReport::default();
+    let mut totals = (0, 0, 0);
+
+    // get the report hour by hour, as users may have run very large
+    // numbers of jobs in a day, and the daily query may time out
+    for hour in day.hours() {
+        let jobs = get_hourly_jobs(source, expires, project, &hour, &now).await?;
+
+        let (usage, num_jobs, wait_seconds) =
+            add_jobs_to_report(&mut daily_report, &jobs, &hour.start_time().and_utc());
+
+        totals = (
+            totals.0 + usage,
+            totals.1 + num_jobs,
+            totals.2 + wait_seconds,
+        );
+    }
+
+    Ok(finish_daily_report(project, day, daily_report, totals).await)
+}
+
+async fn get_daily_report<S: JobSource>(
+    source: &S,
+    expires: &chrono::DateTime<Utc>,
+    project: &ProjectMapping,
+    day: &Date,
+) -> Result<DailyProjectUsageReport, Error> {
+    // see if we have this report in the cache
+    if let Some(report) = cache::get_report(project.project(), day).await? {
+        return Ok(report);
+    }
+
+    assert_not_expired(expires)?;
+
+    if cache::compute_via_hourly_reports(project.project(), day).await? {
+        return get_hourly_report(source, expires, project, day).await;
+    }
+
+    let now = Utc::now();
+    let start_time = day.day().start_time().and_utc();
+
+    if start_time > now {
+        // we can't get the usage for this day yet as it is in the future
+        return Ok(DailyProjectUsageReport::default());
+    }
+
+    let end_time = day.day().end_time().and_utc().min(now);
+
+    // use a short timeout, as we fall back to hourly reports if this fails
+    match source
+        .get_jobs(&start_time, &end_time, DAILY_TIMEOUT, expires)
+        .await
+    {
+        Ok(jobs) => {
+            tracing::debug!(
+                "Got {} jobs for project {} on {}",
+                jobs.len(),
+                project.project(),
+                day
+            );
+
+            let mut daily_report = DailyProjectUsageReport::default();
+            let totals = add_jobs_to_report(&mut daily_report, &jobs, &start_time);
+
+            Ok(finish_daily_report(project, day, daily_report, totals).await)
+        }
+        Err(Error::Timeout(_)) => {
+            tracing::warn!(
+                "Timed out getting usage for project {} on {}. Switching to hourly reporting.",
+                project.project(),
+                day
+            );
+
+            get_hourly_report(source, expires, project, day).await
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Could not get usage for project {} on {}: {}",
+                project.project(),
+                day,
+                e
+            );
+
+            // return an empty report - this will not be complete
+            // and will not be cached
+            Ok(DailyProjectUsageReport::default())
+        }
+    }
+}
+
+///
+/// Get the usage report of the project for `dates`, reading the jobs
+/// of each day (in parallel) from `source`
+///
+pub async fn get_usage_report<S: JobSource>(
+    source: &S,
+    project: &ProjectMapping,
+    dates: &DateRange,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<ProjectUsageReport, Error> {
+    let mut report = ProjectUsageReport::new(project.project());
+    let now = Utc::now();
+
+    // request the data day by day - do this in parallel
+    let mut tasks = Vec::new();
+
+    for day in dates.days() {
+        if day.day().start_time().and_utc() > now {
+            // we can't get the usage for this day yet as it is in the future
+            continue;
+        }
+
+        let source = source.clone();
+        let expires = *expires;
+        let project = project.clone();
+        let day2 = day.clone();
+
+        tasks.push((
+            tokio::spawn(async move { get_daily_report(&source, &expires, &project, &day).await }),
+            day2,
+        ));
+    }
+
+    for (task, day) in tasks {
+        let daily_report = match task.await {
+            Ok(Ok(report)) => report,
+            Ok(Err(e)) => {
+                tracing::warn!("Could not get daily report: {}", e);
+                DailyProjectUsageReport::default()
+            }
+            Err(e) => {
+                tracing::warn!("Could not get daily report: {}", e);
+                DailyProjectUsageReport::default()
+            }
+        };
+
+        report.set_report(&day, &daily_report);
+    }
+
+    Ok(report)
+}
+
+///
+/// Get the jobs of the project that ran on `day`, falling back to
+/// reading them hour by hour if the query for the whole day times out.
+/// The jobs of finished days are cached, so that paging through the
+/// job records doesn't read them again.
+///
+async fn get_daily_jobs<S: JobSource>(
+    source: &S,
+    expires: &chrono::DateTime<Utc>,
+    project: &ProjectMapping,
+    day: &Date,
+) -> Result<Vec<SlurmJob>, Error> {
+    if let Some(jobs) = cache::get_daily_jobs(project.project(), day).await? {
+        return Ok(jobs);
+    }
+
+    assert_not_expired(expires)?;
+
+    let now = Utc::now();
+    let start_time = day.day().start_time().and_utc();
+
+    if start_time > now {
+        return Ok(Vec::new());
+    }
+
+    let end_time = day.day().end_time().and_utc().min(now);
+
+    let jobs = match source
+        .get_jobs(&start_time, &end_time, DAILY_TIMEOUT, expires)
+        .await
+    {
+        Ok(jobs) => jobs,
+        Err(Error::Timeout(_)) => {
+            tracing::warn!(
+                "Timed out getting jobs for project {} on {}. Switching to hourly reading.",
+                project.project(),
+                day
+            );
+
+            let mut jobs = Vec::new();
+
+            for hour in day.hours() {
+                jobs.extend(get_hourly_jobs(source, expires, project, &hour, &now).await?);
+            }
+
+            jobs
+        }
+        Err(e) => return Err(e),
+    };
+
+    if day.day().end_time().and_utc() < now {
+        if let Err(e) = cache::set_daily_jobs(project.project(), day, &jobs).await {
+            tracing::error!("Could not cache the jobs for {}: {}", day, e);
+        }
+    }
+
+    Ok(jobs)
+}
+
+///
+/// Get the requested page of the records of the jobs charged to the
+/// project's account in `dates`. All of the jobs in the dates are
+/// needed to put them in order, and to add up the usage of jobs that
+/// ran over several days, so these are read from `source` day by day,
+/// with at most `MAX_CONCURRENT_DAYS` days at once. Finished days are
+/// cached, so later pages only read the days that are still running.
+///
+pub async fn get_job_records<S: JobSource>(
+    source: &S,
+    project: &ProjectMapping,
+    dates: &DateRange,
+    page: &Page,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<JobRecords, Error> {
+    // a missing day would silently drop jobs, so any failure is an error
+    let days: Vec<(Date, Vec<SlurmJob>)> = stream::iter(dates.days())
+        .map(|day| async move {
+            let jobs = get_daily_jobs(source, expires, project, &day).await?;
+            Ok::<_, Error>((day, jobs))
+        })
+        .buffer_unordered(MAX_CONCURRENT_DAYS)
+        .try_collect()
+        .await?;
+
+    let days: BTreeMap<Date, Vec<SlurmJob>> = days.into_iter().collect();
+
+    let mut records = BTreeMap::new();
+
+    for jobs in days.values() {
+        add_jobs_to_records(&mut records, jobs);
+    }
+
+    Ok(JobRecords::paginate(
+        project.project(),
+        dates,
+        page,
+        records.into_values().collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slurm::{SlurmNode, SlurmNodes};
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A source with one job an hour into each day, which counts the
+    /// queries and the most that were running at once
+    #[derive(Clone, Default)]
+    struct CountedJobs {
+        queries: Arc<AtomicUsize>,
+        running: Arc<AtomicUsize>,
+        most_running: Arc<AtomicUsize>,
+    }
+
+    impl JobSource for CountedJobs {
+        async fn get_jobs(
+            &self,
+            start_time: &chrono::DateTime<Utc>,
+            end_time: &chrono::DateTime<Utc>,
+            _timeout: Duration,
+            _expires: &chrono::DateTime<Utc>,
+        ) -> Result<Vec<SlurmJob>, Error> {
+            self.queries.fetch_add(1, Ordering::SeqCst);
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most_running.fetch_max(running, Ordering::SeqCst);
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+
+            let start = start_time.timestamp() + 3600;
+
+            let response = serde_json::json!({"jobs": [{
+                "job_id": start,
+                "user": "alice",
+                "account": "someproject",
+                "cluster": "cluster1",
+                "nodes": "node01",
+                "time": {
+                    "start": start,
+                    "end": start + 3600,
+                    "eligible": start,
+                    "elapsed": 3600,
+                },
+                "state": {"current": ["COMPLETED"]},
+                "qos": "normal",
+                "tres": {
+                    "allocated": [{"type": "cpu", "name": "", "count": 1}],
+                    "requested": [{"type": "cpu", "name": "", "count": 1}],
+                },
+            }]});
+
+            let nodes = SlurmNodes::new(&SlurmNode::new(1, 0, 0, 0));
+            let jobs = SlurmJob::get_consumers(&response, start_time, end_time, &nodes);
+
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            jobs
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_records_read_a_bounded_number_of_days_once() {
+        let source = CountedJobs::default();
+        let project = ProjectMapping::parse("records.portal:records")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        let dates =
+            DateRange::parse("2026-01-01:2026-01-20").unwrap_or_else(|e| unreachable!("{:?}", e));
+        let expires = Utc::now() + chrono::Duration::minutes(5);
+
+        let first = get_job_records(
+            &source,
+            &project,
+            &dates,
+            &Page::new(1, 15).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            &expires,
+        )
+        .await
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(first.total(), 20);
+        assert_eq!(first.records().len(), 15);
+        assert_eq!(source.queries.load(Ordering::SeqCst), 20);
+        assert!(source.most_running.load(Ordering::SeqCst) <= MAX_CONCURRENT_DAYS);
+
+        // the days are over, so the next page is read from the cache
+        let second = get_job_records(
+            &source,
+            &project,
+            &dates,
+            &Page::new(2, 15).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            &expires,
+        )
+        .await
+        .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(second.records().len(), 5);
+        assert_eq!(source.queries.load(Ordering::SeqCst), 20);
+    }
+
+    #[test]
+    fn test_jobs_spanning_several_hours_are_counted_once() {
+        let at = |hour: u32| {
+            Utc.with_ymd_and_hms(2026, 3, 2, hour, 0, 0)
+                .single()
+                .unwrap_or_else(|| unreachable!("time"))
+        };
+
+        let response = serde_json::json!({"jobs": [{
+            "job_id": 7,
+            "user": "alice",
+            "account": "someproject",
+            "cluster": "cluster1",
+            "nodes": "node01",
+            "time": {
+                "start": at(9).timestamp(),
+                "end": at(11).timestamp(),
+                "eligible": at(9).timestamp() - 300,
+                "elapsed": 7200,
+            },
+            "state": {"current": ["COMPLETED"]},
+            "qos": "normal",
+            "tres": {
+                "allocated": [{"type": "cpu", "name": "", "count": 128}],
+                "requested": [{"type": "cpu", "name": "", "count": 128}],
+            },
+        }]});
+
+        let nodes = SlurmNodes::new(&SlurmNode::new(128, 0, 0, 0));
+        let mut daily_report = DailyProjectUsageReport::default();
+        let mut totals = (0, 0, 0);
+
+        // read hour by hour, as when a daily query times out
+        for hour in [9, 10] {
+            let jobs = SlurmJob::get_consumers(&response, &at(hour), &at(hour + 1), &nodes)
+                .unwrap_or_else(|e| unreachable!("get jobs: {:?}", e));
+
+            let (usage, jobs, waits) = add_jobs_to_report(&mut daily_report, &jobs, &at(hour));
+            totals = (totals.0 + usage, totals.1 + jobs, totals.2 + waits);
+        }
+
+        // the usage of both hours, but the job and its wait only once
+        assert_eq!(totals, (7200, 1, 300));
+        assert_eq!(daily_report.usage("alice"), Usage::new(7200));
+        assert_eq!(daily_report.num_jobs(), 1);
+        assert_eq!(daily_report.total_wait_seconds(), 300);
+        // while its components are in their own units, e.g. cpu-seconds
+        assert_eq!(
+            daily_report.get_component("cpu").usage("alice"),
+            Usage::new(128 * 7200)
+        );
+    }
+
+    #[test]
+    fn test_usage_is_broken_down_by_partition() {
+        let at = |hour: u32| {
+            Utc.with_ymd_and_hms(2026, 3, 2, hour, 0, 0)
+                .single()
+                .unwrap_or_else(|| unreachable!("time"))
+        };
+
+        let job = |id: u64, partition: Option<&str>| {
+            let mut job = serde_json::json!({
+                "job_id": id,
+                "user": "alice",
+                "account": "someproject",
+                "cluster": "cluster1",
+                "nodes": "node01",
+                "time": {
+                    "start": at(9).timestamp(),
+                    "end": at(10).timestamp(),
+                    "eligible": at(9).timestamp(),
+                    "elapsed": 3600,
+                },
+                "state": {"current": ["COMPLETED"]},
+                "qos": "normal",
+                "tres": {
+                    "allocated": [{"type": "cpu", "name": "", "count": 128}],
+                    "requested": [{"type": "cpu", "name": "", "count": 128}],
+                },
+            });
+
+            if let (Some(partition), Some(job)) = (partition, job.as_object_mut()) {
+                job.insert("partition".to_string(), partition.into());
+            }
+
+            job
+        };
+
+        // an older slurm may not say which partition a job ran on
+        let response = serde_json::json!({"jobs": [job(1, Some("gpu")), job(2, None)]});
+
+        let nodes = SlurmNodes::new(&SlurmNode::new(128, 0, 0, 0));
+        let jobs = SlurmJob::get_consumers(&response, &at(9), &at(10), &nodes)
+            .unwrap_or_else(|e| unreachable!("get jobs: {:?}", e));
+
+        let mut daily_report = DailyProjectUsageReport::default();
+        add_jobs_to_report(&mut daily_report, &jobs, &at(9));
+
+        assert_eq!(daily_report.usage("alice"), Usage::new(2 * 3600));
+        assert_eq!(
+            daily_report
+                .get_component(&partition_component(USAGE_COMPONENT, "gpu"))
+                .usage("alice"),
+            Usage::new(3600)
+        );
+        assert_eq!(
+            daily_report
+                .get_component(&partition_component("cpu", "gpu"))
+                .usage("alice"),
+            Usage::new(128 * 3600)
+        );
+        assert_eq!(
+            daily_report.components(),
+            vec!["cpu", "cpu@gpu", "usage@gpu"]
+        );
+    }
+}
@@ -4,20 +4,27 @@
 use anyhow::Context;
 use anyhow::Result;
 use chrono::Utc;
-use greatwestern::grammar::{DateRange, ProjectMapping, UserMapping};
-use greatwestern::usagereport::{DailyProjectUsageReport, ProjectUsageReport, Usage};
+use greatwestern::grammar::{validate_role, DateRange, ProjectMapping, UserMapping};
+use greatwestern::jobrecords::{JobRecords, Page};
+use greatwestern::scheduling::{ProjectLimit, Reservation, SchedulingPolicy, UserLimit};
+use greatwestern::usagereport::{ProjectUsageReport, Usage};
 use once_cell::sync::Lazy;
 use rand::seq::IteratorRandom;
 use rand::SeedableRng;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use templemeads::job::assert_not_expired;
+use templemeads::metrics;
 use templemeads::Error;
 use tokio::sync::Mutex;
 
 use crate::cache;
+use crate::reports::{self, JobSource};
 use crate::slurm::{
-    clean_account_name, clean_user_name, get_managed_organization, SlurmAccount, SlurmLimit,
-    SlurmUser,
+    clean_account_name, clean_user_name, get_managed_organization, parse_tres,
+    policy_from_associations, reservations_from_json, slurm_reservation_name,
+    user_limit_from_fields, user_limit_tres, user_limits_from_associations, SlurmAccount,
+    SlurmAssociationPolicy, SlurmLimit, SlurmUser,
 };
 use crate::slurm::{SlurmJob, SlurmNodes};
 
@@ -149,6 +156,18 @@ impl LockedRunner {
 
         let duration_ms = (end_time - start_time).num_milliseconds();
 
+        let command = std::path::Path::new(program)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| program.clone());
+
+        metrics::observe(
+            "slurm_command_duration_seconds",
+            "How long Slurm commands (sacctmgr, sacct, ...) took to run",
+            &[("command", &command)],
+            std::time::Duration::from_millis(duration_ms.max(0) as u64),
+        );
+
         if duration_ms > 5000 {
             tracing::warn!(
                 "Running command {:?} took {} seconds",
@@ -1018,96 +1037,63 @@ pub async fn add_project(
 pub async fn add_user(user: &UserMapping, expires: &chrono::DateTime<Utc>) -> Result<(), Error> {
     assert_not_expired(expires)?;
 
+    let account = SlurmAccount::from_mapping(&user.clone().into())?;
     let user: SlurmUser = get_user_create_if_not_exists(user, expires).await?;
 
-    tracing::info!("Added user: {}", user);
-
-    Ok(())
-}
-
-async fn get_hourly_report(
-    expires: &chrono::DateTime<Utc>,
-    project: &ProjectMapping,
-    day: &greatwestern::grammar::Date,
-    account: &SlurmAccount,
-    slurm_nodes: &SlurmNodes,
-    cluster: &str,
-    partition_command: &str,
-) -> Result<DailyProjectUsageReport, Error> {
-    let now = chrono::Utc::now();
-    let mut daily_report = DailyProjectUsageReport::default();
-    let mut total_usage: u64 = 0;
-    let mut num_jobs: u64 = 0;
-    let mut total_wait_seconds: u64 = 0;
-
-    // we need to get the report hour by hour from slurm, as users may have
-    // run very large numbers of jobs in a day, and sacct may time out
-    for hour in day.hours() {
-        if let Some(hourly_report) = cache::get_hourly_report(project.project(), &hour).await? {
-            // we have this hour in the cache, so use it
-            tracing::debug!(
-                "Using cached hourly report for {}. Number of jobs = {}",
-                hour,
-                hourly_report.len()
-            );
-
-            let hour_start_time = hour.start_time().and_utc();
-
-            for job in hourly_report {
-                total_usage += job.billed_node_seconds();
-                daily_report.add_usage(job.user(), Usage::new(job.billed_node_seconds()));
-
-                daily_report.add_component_usage("cpu", job.user(), Usage::new(job.cpu_seconds()));
-                daily_report.add_component_usage(
-                    "memory",
-                    job.user(),
-                    Usage::new(job.memory_seconds()),
-                );
-                daily_report.add_component_usage("gpu", job.user(), Usage::new(job.gpu_seconds()));
-                daily_report.add_component_usage(
-                    "billing",
-                    job.user(),
-                    Usage::new(job.billing_seconds()),
-                );
-
-                if job.original_start_time() >= &hour_start_time {
-                    num_jobs += 1;
-                    total_wait_seconds += job.wait_time().num_seconds() as u64;
-                    daily_report.add_jobs(job.user(), 1);
-                    daily_report.add_wait_seconds(job.user(), job.wait_time().num_seconds() as u64);
-                }
-            }
+    // give the user an association on each partition of the account's
+    // scheduling policy, as set_scheduling_policy did for existing users
+    let associations = get_association_policies(account.name(), expires).await?;
+    let policy = policy_from_associations(&associations);
 
-            continue;
+    for partition in policy.partitions().unwrap_or_default() {
+        if !associations
+            .iter()
+            .any(|a| a.user() == user.name() && a.partition() == partition)
+        {
+            add_partition_association(user.name(), account.name(), &partition, expires).await?;
         }
+    }
 
-        assert_not_expired(expires)?;
+    tracing::info!("Added user: {}", user);
 
-        let start_time = hour.start_time().and_utc();
-        let end_time = hour.end_time().and_utc();
+    Ok(())
+}
 
-        if start_time > now {
-            // we can't get the usage for this hour yet as it is in the future
-            continue;
-        }
+///
+/// Reads the jobs charged to an account with `sacct`
+///
+#[derive(Debug, Clone)]
+struct SacctJobs {
+    account: SlurmAccount,
+    cluster: String,
+    partition_command: String,
+    slurm_nodes: SlurmNodes,
+}
 
-        let end_time = match now < end_time {
-            true => now,
-            false => end_time,
+impl SacctJobs {
+    async fn new(account: SlurmAccount) -> Result<Self, Error> {
+        let partition_command = match cache::get_partition().await? {
+            Some(partition) => format!("--partition={}", partition),
+            None => "".to_string(),
         };
 
-        // check that the hour contains <= 3600 seconds
-        if end_time.timestamp() - start_time.timestamp() > 3600 {
-            tracing::warn!(
-                "Hour {} contains more than 1 hour - check this! {} : {}",
-                hour,
-                start_time,
-                end_time
-            );
-        }
+        Ok(Self {
+            account,
+            cluster: cache::get_cluster().await?,
+            partition_command,
+            slurm_nodes: cache::get_nodes().await?,
+        })
+    }
+}
 
-        // now try to get the report for this hour - we use a much longer
-        // timeout here as we may be getting a lot of jobs
+impl JobSource for SacctJobs {
+    async fn get_jobs(
+        &self,
+        start_time: &chrono::DateTime<Utc>,
+        end_time: &chrono::DateTime<Utc>,
+        timeout: std::time::Duration,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Vec<SlurmJob>, Error> {
         let cmd = runner(expires).await?.build_command(
             "SACCT",
             vec![
@@ -1116,301 +1102,16 @@ async fn get_hourly_report(
                 "--allusers".to_string(),
                 format!("--starttime={}", start_time.format("%Y-%m-%dT%H:%M:%S")),
                 format!("--endtime={}", end_time.format("%Y-%m-%dT%H:%M:%S")),
-                format!("--account={}", account.name()),
-                format!("--cluster={}", cluster),
-                partition_command.to_string(),
+                format!("--account={}", self.account.name()),
+                format!("--cluster={}", self.cluster),
+                self.partition_command.clone(),
                 "--json".to_string(),
             ],
         )?;
 
-        let response = runner(expires)
-            .await?
-            .run_json(&cmd, std::time::Duration::from_secs(120))
-            .await?;
-
-        let jobs = SlurmJob::get_consumers(&response, &start_time, &end_time, slurm_nodes)?;
-
-        tracing::debug!(
-            "Got {} jobs for project {} on {}",
-            jobs.len(),
-            project.project(),
-            hour
-        );
-
-        // cache this hourly report if it is in the past
-        if hour.end_time().and_utc() < now {
-            match cache::set_hourly_report(project.project(), &hour, &jobs).await {
-                Ok(_) => (),
-                Err(e) => {
-                    tracing::error!("Could not cache hourly report for {}: {}", hour, e);
-                }
-            }
-        }
-
-        for job in jobs {
-            total_usage += job.billed_node_seconds();
-            daily_report.add_usage(job.user(), Usage::new(job.billed_node_seconds()));
-
-            daily_report.add_component_usage("cpu", job.user(), Usage::new(job.cpu_seconds()));
-            daily_report.add_component_usage(
-                "memory",
-                job.user(),
-                Usage::new(job.memory_seconds()),
-            );
-            daily_report.add_component_usage("gpu", job.user(), Usage::new(job.gpu_seconds()));
-            daily_report.add_component_usage(
-                "billing",
-                job.user(),
-                Usage::new(job.billing_seconds()),
-            );
-
-            // only count wait time for jobs that started in this hour
-            if job.original_start_time() >= &start_time {
-                num_jobs += 1;
-                total_wait_seconds += job.wait_time().num_seconds() as u64;
-                daily_report.add_jobs(job.user(), 1);
-                daily_report.add_wait_seconds(job.user(), job.wait_time().num_seconds() as u64);
-            }
-        }
-    }
-
-    tracing::debug!(
-        "Got {} jobs consuming {} seconds for project {} on {}",
-        num_jobs,
-        total_usage,
-        project.project(),
-        day
-    );
-
-    // runtime consistency check: local shadow counters must match the report's scalar totals
-    if daily_report.num_jobs() != num_jobs
-        || daily_report.total_wait_seconds() != total_wait_seconds
-    {
-        tracing::warn!(
-            "Job count/wait time inconsistency for project {} on {}: \
-             local counters ({} jobs, {}s wait) differ from report totals ({} jobs, {}s wait). \
-             This may indicate a bug.",
-            project.project(),
-            day,
-            num_jobs,
-            total_wait_seconds,
-            daily_report.num_jobs(),
-            daily_report.total_wait_seconds()
-        );
-    }
-
-    // check that the total usage in the daily report matches the total usage calculated manually
-    if daily_report.total_usage().seconds() != total_usage {
-        // it doesn't - we don't want to mark this as complete or cache it, because
-        // this points to some error when generating the values...
-        tracing::error!(
-            "Total usage in daily report does not match total usage calculated manually: {} != {}",
-            daily_report.total_usage().seconds(),
-            total_usage
-        );
-    } else if day.day().end_time().and_utc() < now {
-        // we can set this day as completed if it is in the past
-        daily_report.set_complete();
-
-        match cache::set_report(project.project(), day, &daily_report).await {
-            Ok(_) => (),
-            Err(e) => {
-                tracing::error!("Could not cache report for {}: {}", day, e);
-            }
-        }
-    }
-
-    Ok(daily_report)
-}
-
-async fn get_daily_report(
-    expires: &chrono::DateTime<Utc>,
-    project: &ProjectMapping,
-    day: &greatwestern::grammar::Date,
-    account: &SlurmAccount,
-    slurm_nodes: &SlurmNodes,
-    cluster: &str,
-    partition_command: &str,
-) -> Result<DailyProjectUsageReport, Error> {
-    // see if we have this report in the cache
-    if let Some(report) = cache::get_report(project.project(), day).await? {
-        return Ok(report);
-    }
-
-    assert_not_expired(expires)?;
-
-    if cache::compute_via_hourly_reports(project.project(), day).await? {
-        return get_hourly_report(
-            expires,
-            project,
-            day,
-            account,
-            slurm_nodes,
-            cluster,
-            partition_command,
-        )
-        .await;
-    }
-
-    let now = chrono::Utc::now();
-    let start_time = day.day().start_time().and_utc();
-    let end_time = day.day().end_time().and_utc();
-
-    if start_time > now {
-        // we can't get the usage for this day yet as it is in the future
-        return Ok(DailyProjectUsageReport::default());
-    }
-
-    let end_time = match now < end_time {
-        true => now,
-        false => end_time,
-    };
-
-    // check that the day contains <= 24 hours (86400 seconds)
-    if end_time.timestamp() - start_time.timestamp() > 86400 {
-        tracing::warn!(
-            "Day {} contains more than 24 hours - check this! {} : {}",
-            day,
-            start_time,
-            end_time
-        );
-    }
-
-    // try to get the daily report from slurm - use a shorter 20 second
-    // timeout as we will fall back to hourly reports if this fails
-    let cmd = runner(expires).await?.build_command(
-        "SACCT",
-        vec![
-            "--noconvert".to_string(),
-            "--allocations".to_string(),
-            "--allusers".to_string(),
-            format!("--starttime={}", start_time.format("%Y-%m-%dT%H:%M:%S")),
-            format!("--endtime={}", end_time.format("%Y-%m-%dT%H:%M:%S")),
-            format!("--account={}", account.name()),
-            format!("--cluster={}", cluster),
-            partition_command.to_string(),
-            "--json".to_string(),
-        ],
-    )?;
-
-    let response = runner(expires)
-        .await?
-        .run_json(&cmd, std::time::Duration::from_secs(20))
-        .await;
-
-    match response {
-        Ok(response) => {
-            let jobs = SlurmJob::get_consumers(&response, &start_time, &end_time, slurm_nodes)?;
-
-            tracing::debug!(
-                "Got {} jobs for project {} on {}",
-                jobs.len(),
-                project.project(),
-                day
-            );
-
-            let mut daily_report = DailyProjectUsageReport::default();
-            let mut total_usage: u64 = 0;
-            let mut num_jobs_started: u64 = 0;
-            let mut total_wait_seconds: u64 = 0;
-
-            for job in jobs {
-                total_usage += job.billed_node_seconds();
-                daily_report.add_usage(job.user(), Usage::new(job.billed_node_seconds()));
-
-                // only count jobs and wait time for jobs that started in this day
-                if job.original_start_time() >= &start_time {
-                    num_jobs_started += 1;
-                    total_wait_seconds += job.wait_time().num_seconds() as u64;
-                    daily_report.add_jobs(job.user(), 1);
-                    daily_report.add_wait_seconds(job.user(), job.wait_time().num_seconds() as u64);
-                }
-
-                // also add in all of the components
-                daily_report.add_component_usage("cpu", job.user(), Usage::new(job.cpu_seconds()));
-                daily_report.add_component_usage(
-                    "memory",
-                    job.user(),
-                    Usage::new(job.memory_seconds()),
-                );
-                daily_report.add_component_usage("gpu", job.user(), Usage::new(job.gpu_seconds()));
-                daily_report.add_component_usage(
-                    "billing",
-                    job.user(),
-                    Usage::new(job.billing_seconds()),
-                );
-            }
-
-            // runtime consistency check
-            if daily_report.num_jobs() != num_jobs_started
-                || daily_report.total_wait_seconds() != total_wait_seconds
-            {
-                tracing::warn!(
-                    "Job count/wait time inconsistency for project {} on {}: \
-                     local counters ({} jobs, {}s wait) differ from report totals ({} jobs, {}s wait). \
-                     This may indicate a bug.",
-                    project.project(),
-                    day,
-                    num_jobs_started,
-                    total_wait_seconds,
-                    daily_report.num_jobs(),
-                    daily_report.total_wait_seconds()
-                );
-            }
-
-            // check that the total usage in the daily report matches the total usage calculated manually
-            if daily_report.total_usage().seconds() != total_usage {
-                // it doesn't - we don't want to mark this as complete or cache it, because
-                // this points to some error when generating the values...
-                tracing::error!(
-                    "Total usage in daily report does not match total usage calculated manually: {} != {}",
-                    daily_report.total_usage().seconds(),
-                    total_usage
-                );
-            } else if day.day().end_time().and_utc() < now {
-                // we can set this day as completed if it is in the past
-                daily_report.set_complete();
-
-                match cache::set_report(project.project(), day, &daily_report).await {
-                    Ok(_) => (),
-                    Err(e) => {
-                        tracing::error!("Could not cache report for {}: {}", day, e);
-                    }
-                }
-            }
-            Ok(daily_report)
-        }
-        Err(Error::Timeout(_)) => {
-            tracing::warn!(
-                "Timed out getting usage for project {} on {}. Switching to hourly reporting.",
-                project.project(),
-                day
-            );
-
-            // we need to switch to getting an hourly report for this date
-            return get_hourly_report(
-                expires,
-                project,
-                day,
-                account,
-                slurm_nodes,
-                cluster,
-                partition_command,
-            )
-            .await;
-        }
-        Err(e) => {
-            tracing::warn!(
-                "Could not get usage for project {} on {}: {}",
-                project.project(),
-                day,
-                e
-            );
+        let response = runner(expires).await?.run_json(&cmd, timeout).await?;
 
-            // we will return an empty report - this will not be complete
-            // and will not be cached
-            Ok(DailyProjectUsageReport::default())
-        }
+        SlurmJob::get_consumers(&response, start_time, end_time, &self.slurm_nodes)
     }
 }
 
@@ -1435,74 +1136,44 @@ pub async fn get_usage_report(
         }
     };
 
-    let mut report = ProjectUsageReport::new(project.project());
-    let slurm_nodes = cache::get_nodes().await?;
-    let now = chrono::Utc::now();
-    let cluster = cache::get_cluster().await?;
-    let partition = cache::get_partition().await?;
+    reports::get_usage_report(&SacctJobs::new(account).await?, project, dates, expires).await
+}
 
-    let partition_command = match partition {
-        Some(partition) => format!("--partition={}", partition),
-        None => "".to_string(),
-    };
+///
+/// Get the requested page of the records of the jobs charged to the
+/// project's account in `dates`, read with sacct
+///
+pub async fn get_job_records(
+    project: &ProjectMapping,
+    dates: &DateRange,
+    page: &Page,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<JobRecords, Error> {
+    assert_not_expired(expires)?;
 
-    // we now request the data day by day - do this in parallel
-    let mut tasks = Vec::new();
+    let account = SlurmAccount::from_mapping(project)?;
 
-    for day in dates.days() {
-        if day.day().start_time().and_utc() > now {
-            // we can't get the usage for this day yet as it is in the future
-            continue;
+    let account = match get_account(account.name(), expires).await? {
+        Some(account) => account,
+        None => {
+            tracing::warn!("Could not get account {}", account.name());
+            return Ok(JobRecords::paginate(
+                project.project(),
+                dates,
+                page,
+                Vec::new(),
+            ));
         }
+    };
 
-        let expires = *expires;
-        let project = project.clone();
-        let account = account.clone();
-        let slurm_nodes = slurm_nodes.clone();
-        let cluster = cluster.clone();
-        let partition_command = partition_command.clone();
-        let day = day.clone();
-        let day2 = day.clone();
-
-        tasks.push((
-            tokio::spawn(async move {
-                get_daily_report(
-                    &expires,
-                    &project,
-                    &day,
-                    &account,
-                    &slurm_nodes,
-                    &cluster,
-                    &partition_command,
-                )
-                .await
-            }),
-            day2,
-        ));
-    }
-
-    for (task, day) in tasks {
-        let daily_report = match task.await {
-            Ok(report) => match report {
-                Ok(report) => report,
-                Err(e) => {
-                    tracing::warn!("Could not get daily report: {}", e);
-                    // we will return an empty report for this day
-                    DailyProjectUsageReport::default()
-                }
-            },
-            Err(e) => {
-                tracing::warn!("Could not get daily report: {}", e);
-                // we will return an empty report for this day
-                DailyProjectUsageReport::default()
-            }
-        };
-
-        // now save this to the overall report
-        report.set_report(&day, &daily_report);
-    }
-
-    Ok(report)
+    reports::get_job_records(
+        &SacctJobs::new(account).await?,
+        project,
+        dates,
+        page,
+        expires,
+    )
+    .await
 }
 
 pub async fn get_limit(
@@ -1560,8 +1231,6 @@ pub async fn get_limit(
 
     let cluster = cache::get_cluster().await?;
 
-    let project_limit = account.limit();
-
     let slurm_limit = match limits
         .iter()
         .find(|l| l.account() == account.name() && l.cluster() == cluster)
@@ -1581,85 +1250,7 @@ pub async fn get_limit(
 
     let node = cache::get_default_node().await?;
 
-    let mut actual_slurm_limit: Option<Usage> = None;
-
-    if node.has_cpus() && node.cpus() > 0 {
-        if let Some(cpu_limit) = slurm_limit.cpu_limit() {
-            let check = node.cpus() * project_limit.seconds();
-            if check != cpu_limit.seconds() {
-                if check != 0 {
-                    tracing::warn!(
-                        "CPU limit for account {} does not match: {} != {}",
-                        account.name(),
-                        check,
-                        cpu_limit.seconds()
-                    );
-                }
-
-                actual_slurm_limit = Some(Usage::new(cpu_limit.seconds() / node.cpus()));
-            }
-        }
-    }
-
-    if node.has_gpus() && node.gpus() > 0 {
-        if let Some(gpu_limit) = slurm_limit.gpu_limit() {
-            let check = node.gpus() * project_limit.seconds();
-            if check != gpu_limit.seconds() {
-                if check != 0 {
-                    tracing::warn!(
-                        "GPU limit for account {} does not match: {} != {}",
-                        account.name(),
-                        check,
-                        gpu_limit.seconds()
-                    );
-                }
-
-                if actual_slurm_limit.is_none() {
-                    actual_slurm_limit = Some(Usage::new(gpu_limit.seconds() / node.gpus()));
-                }
-            }
-        }
-    }
-
-    if node.has_mem() && node.mem() > 0 {
-        if let Some(mem_limit) = slurm_limit.mem_limit() {
-            let check = node.mem() * project_limit.seconds();
-            if check != mem_limit.seconds() {
-                if check != 0 {
-                    tracing::warn!(
-                        "Memory limit for account {} does not match: {} != {}",
-                        account.name(),
-                        check,
-                        mem_limit.seconds()
-                    );
-                }
-
-                if actual_slurm_limit.is_none() {
-                    actual_slurm_limit = Some(Usage::new(mem_limit.seconds() / node.mem()));
-                }
-            }
-        }
-    }
-
-    if node.has_billing() && node.billing() > 0 {
-        if let Some(billing_limit) = slurm_limit.billing_limit() {
-            let check = node.billing() * project_limit.seconds();
-            if check != billing_limit.seconds() {
-                if check != 0 {
-                    tracing::warn!(
-                        "Billing limit for account {} does not match: {} != {}",
-                        account.name(),
-                        check,
-                        billing_limit.seconds()
-                    );
-                }
-
-                if actual_slurm_limit.is_none() {
-                    actual_slurm_limit = Some(Usage::new(billing_limit.seconds() / node.billing()));
-                }
-            }
-        }
-    }
+    let actual_slurm_limit = slurm_limit.enforced_limit(&account, &node);
 
     if let Some(actual_slurm_limit) = actual_slurm_limit {
         // we need to set this to the actual slurm limit
@@ -1782,6 +1373,804 @@ pub async fn set_limit(
     }
 }
 
+///
+/// Return the limits of each of the users of the account in the
+/// managed cluster
+///
+async fn get_user_limits(
+    account: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<BTreeMap<String, UserLimit>, Error> {
+    let cluster = cache::get_cluster().await?;
+    let node = cache::get_default_node().await?;
+
+    let cmd = priority_runner(expires).await?.build_command(
+        "SACCTMGR",
+        vec![
+            "--noheader".to_string(),
+            "--parsable2".to_string(),
+            "show".to_string(),
+            "association".to_string(),
+            "where".to_string(),
+            format!("account={}", clean_account_name(account)?),
+            format!("cluster={}", cluster),
+            "format=User,Partition,GrpTRESMins,MaxJobs,MaxSubmitJobs,GrpTRES".to_string(),
+        ],
+    )?;
+
+    let output = priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    let associations = output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split('|').map(|field| field.trim().to_string());
+
+            let user = fields.next().unwrap_or_default();
+            let partition = fields.next().unwrap_or_default();
+            let grp_tres_mins = parse_tres(&fields.next().unwrap_or_default());
+            let max_jobs = fields.next().and_then(|m| m.parse::<i64>().ok());
+            let max_submit_jobs = fields.next().and_then(|m| m.parse::<i64>().ok());
+            let grp_tres = parse_tres(&fields.next().unwrap_or_default());
+
+            (
+                user,
+                partition,
+                user_limit_from_fields(&grp_tres_mins, max_jobs, max_submit_jobs, grp_tres, &node),
+            )
+        })
+        .collect();
+
+    Ok(user_limits_from_associations(associations))
+}
+
+///
+/// Return the limit of the project's account, together with the
+/// limits of those of its users that have their own
+///
+pub async fn get_member_limits(
+    project: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<ProjectLimit, Error> {
+    let mut limit = ProjectLimit::new(&get_limit(project, expires).await?);
+
+    let account = SlurmAccount::from_mapping(project)?;
+
+    for (user, user_limit) in get_user_limits(account.name(), expires).await? {
+        limit.set_user_limit(&user, &user_limit);
+    }
+
+    Ok(limit)
+}
+
+///
+/// Return the limits of the user within their project's account
+///
+pub async fn get_user_limit(
+    user: &UserMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<UserLimit, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(&user.project(), expires).await?;
+    let local_user = clean_user_name(user.local_user().unix()?)?;
+
+    match get_user_limits(account.name(), expires)
+        .await?
+        .remove(&local_user)
+    {
+        Some(limit) => Ok(limit),
+        None => Err(Error::NotFound(format!(
+            "User {} has no association with account {}",
+            local_user,
+            account.name()
+        ))),
+    }
+}
+
+///
+/// Set the limits of the user within their project's account. The
+/// usage is set as the GrpTRESMins of the user's associations with the
+/// account (in the same way as the account's limit), with MaxJobs,
+/// MaxSubmitJobs and GrpTRES set as given. Limits that are not in
+/// `limit` are cleared. This returns the limits that the user now has.
+///
+pub async fn set_user_limit(
+    user: &UserMapping,
+    limit: &UserLimit,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<UserLimit, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(&user.project(), expires).await?;
+    let local_user = clean_user_name(user.local_user().unix()?)?;
+    let cluster = cache::get_cluster().await?;
+    let node = cache::get_default_node().await?;
+
+    let current = get_user_limit(user, expires).await?;
+
+    let (minutes, total) = user_limit_tres(limit, &current, &node);
+
+    let tres = |tres: BTreeMap<String, i64>| {
+        tres.iter()
+            .map(|(tres, count)| format!("{}={}", tres, count))
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+
+    let number = |n: Option<u32>| match n {
+        Some(n) => n.to_string(),
+        None => "-1".to_string(),
+    };
+
+    let mut cmd = vec![
+        "--immediate".to_string(),
+        "modify".to_string(),
+        "user".to_string(),
+        "where".to_string(),
+        format!("name={}", local_user),
+        format!("account={}", account.name()),
+        format!("cluster={}", cluster),
+        "set".to_string(),
+        format!("MaxJobs={}", number(limit.max_jobs())),
+        format!("MaxSubmitJobs={}", number(limit.max_submit_jobs())),
+    ];
+
+    if !minutes.is_empty() {
+        cmd.push(format!("GrpTRESMins={}", tres(minutes)));
+    }
+
+    if !total.is_empty() {
+        cmd.push(format!("GrpTRES={}", tres(total)));
+    }
+
+    let cmd = priority_runner(expires)
+        .await?
+        .build_command("SACCTMGR", cmd)?;
+
+    priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    tracing::info!(
+        "Set the limits of {} in {} to {}",
+        local_user,
+        account.name(),
+        limit
+    );
+
+    get_user_limit(user, expires).await
+}
+
+///
+/// Set the role of the user within their project's account. Users whose
+/// role is one of the configured manager roles are made coordinators of
+/// the account, so that they can manage its jobs and members. Everyone
+/// else is removed as a coordinator. This is used by both the sacctmgr
+/// and the slurmrestd runners, as slurmrestd cannot remove coordinators.
+///
+pub async fn set_user_role(
+    user: &UserMapping,
+    role: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<(), Error> {
+    assert_not_expired(expires)?;
+
+    let role = validate_role(role)?;
+    let is_coordinator = cache::get_manager_roles().await?.contains(&role);
+
+    let account = SlurmAccount::from_mapping(&user.clone().into())?;
+    let username = clean_user_name(user.local_user().unix()?)?;
+
+    // as for `set_limit`, refuse to change an account that this agent
+    // does not manage, as `local_group` is chosen by the peer
+    match get_account(account.name(), expires).await? {
+        Some(existing) if existing.is_managed() => {}
+        Some(existing) => {
+            tracing::warn!(
+                "Refusing to set coordinators of Slurm account '{}': it is in \
+                 organization '{}', not the OpenPortal-managed '{}'.",
+                account.name(),
+                existing.organization(),
+                get_managed_organization()
+            );
+            return Err(Error::UnmanagedGroup(format!(
+                "Cannot set coordinators of Slurm account '{}' - it is not managed by OpenPortal",
+                account.name()
+            )));
+        }
+        None => {
+            tracing::warn!("Could not get account {}", account.name());
+            return Err(Error::NotFound(account.name().to_string()));
+        }
+    }
+
+    // sacctmgr fails if asked to remove someone who isn't a coordinator,
+    // so only make the change if it is needed
+    let cmd = priority_runner(expires).await?.build_command(
+        "SACCTMGR",
+        vec![
+            "--noheader".to_string(),
+            "--parsable2".to_string(),
+            "show".to_string(),
+            "account".to_string(),
+            account.name().to_string(),
+            "withcoordinator".to_string(),
+            "format=Coordinators".to_string(),
+        ],
+    )?;
+
+    let coordinators = priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    let is_already_coordinator = coordinators
+        .lines()
+        .flat_map(|line| line.split(','))
+        .any(|name| name.trim() == username);
+
+    if is_coordinator == is_already_coordinator {
+        tracing::info!(
+            "User {} role '{}' needs no change to the coordinators of {}",
+            username,
+            role,
+            account.name()
+        );
+        return Ok(());
+    }
+
+    let cmd = priority_runner(expires).await?.build_command(
+        "SACCTMGR",
+        vec![
+            "--immediate".to_string(),
+            match is_coordinator {
+                true => "add".to_string(),
+                false => "remove".to_string(),
+            },
+            "coordinator".to_string(),
+            format!("account={}", account.name()),
+            format!("names={}", username),
+        ],
+    )?;
+
+    priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    match is_coordinator {
+        true => tracing::info!(
+            "Added {} as a coordinator of {} as their role is '{}'",
+            username,
+            account.name(),
+            role
+        ),
+        false => tracing::info!(
+            "Removed {} as a coordinator of {} as their role is '{}'",
+            username,
+            account.name(),
+            role
+        ),
+    }
+
+    Ok(())
+}
+
+///
+/// Return the account for the passed project, refusing (as for `set_limit`)
+/// if it does not exist or is not managed by OpenPortal, as `local_group`
+/// is chosen by the peer
+///
+async fn get_managed_account(
+    project: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<SlurmAccount, Error> {
+    let account = SlurmAccount::from_mapping(project)?;
+
+    match get_account(account.name(), expires).await? {
+        Some(existing) if existing.is_managed() => Ok(existing),
+        Some(existing) => {
+            tracing::warn!(
+                "Refusing to change Slurm account '{}': it is in \
+                 organization '{}', not the OpenPortal-managed '{}'.",
+                account.name(),
+                existing.organization(),
+                get_managed_organization()
+            );
+            Err(Error::UnmanagedGroup(format!(
+                "Cannot change Slurm account '{}' - it is not managed by OpenPortal",
+                account.name()
+            )))
+        }
+        None => {
+            tracing::warn!("Could not get account {}", account.name());
+            Err(Error::NotFound(account.name().to_string()))
+        }
+    }
+}
+
+///
+/// Parse the `--parsable2` output of `sacctmgr show association` run
+/// with `format=User,Partition,QOS,DefaultQOS`
+///
+fn parse_association_policies(output: &str) -> Vec<SlurmAssociationPolicy> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split('|').map(|field| field.trim().to_string());
+
+            let user = fields.next().unwrap_or_default();
+            let partition = fields.next().unwrap_or_default();
+            let qos = fields
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .map(|q| q.trim().to_string())
+                .filter(|q| !q.is_empty())
+                .collect();
+            let default_qos = fields.next().filter(|q| !q.is_empty());
+
+            SlurmAssociationPolicy::new(&user, &partition, qos, default_qos)
+        })
+        .collect()
+}
+
+///
+/// Return the QoS, default QoS and partitions of every association
+/// of the passed account in the managed cluster
+///
+async fn get_association_policies(
+    account: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Vec<SlurmAssociationPolicy>, Error> {
+    let cluster = cache::get_cluster().await?;
+
+    let cmd = priority_runner(expires).await?.build_command(
+        "SACCTMGR",
+        vec![
+            "--noheader".to_string(),
+            "--parsable2".to_string(),
+            "show".to_string(),
+            "association".to_string(),
+            "where".to_string(),
+            format!("account={}", clean_account_name(account)?),
+            format!("cluster={}", cluster),
+            "format=User,Partition,QOS,DefaultQOS".to_string(),
+        ],
+    )?;
+
+    let output = priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    Ok(parse_association_policies(&output))
+}
+
+///
+/// Return the scheduling policy (QoS, default QoS and partitions) of
+/// the project's account
+///
+pub async fn get_scheduling_policy(
+    project: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<SchedulingPolicy, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    Ok(policy_from_associations(
+        &get_association_policies(account.name(), expires).await?,
+    ))
+}
+
+///
+/// Set the scheduling policy of the project's account. The QoS are set
+/// on the account and all of its users' associations - if the policy
+/// has none then those of the parent account are used. Each user of
+/// the account is given an association on each of the policy's
+/// partitions, and their associations on any other partition are
+/// deleted. This returns the policy that the account now has.
+///
+pub async fn set_scheduling_policy(
+    project: &ProjectMapping,
+    policy: &SchedulingPolicy,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<SchedulingPolicy, Error> {
+    assert_not_expired(expires)?;
+
+    policy.validate()?;
+
+    let account = get_managed_account(project, expires).await?;
+    let cluster = cache::get_cluster().await?;
+
+    // a cleared QoS (or default QoS) goes back to that of the parent
+    let parent = policy_from_associations(
+        &get_association_policies(&cache::get_parent_account().await?, expires).await?,
+    );
+
+    let qos = policy.qos().or(parent.qos()).unwrap_or_default();
+    let default_qos = policy.default_qos().or(parent.default_qos());
+
+    let associations = get_association_policies(account.name(), expires).await?;
+
+    let mut set_qos = vec![
+        "set".to_string(),
+        format!("QOS={}", qos.join(",")),
+        format!(
+            "DefaultQOS={}",
+            default_qos.unwrap_or_else(|| "-1".to_string())
+        ),
+    ];
+
+    let cmd = priority_runner(expires).await?.build_command(
+        "SACCTMGR",
+        [
+            vec![
+                "--immediate".to_string(),
+                "modify".to_string(),
+                "account".to_string(),
+                "where".to_string(),
+                format!("name={}", account.name()),
+                format!("cluster={}", cluster),
+            ],
+            set_qos.clone(),
+        ]
+        .concat(),
+    )?;
+
+    priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    let mut users: Vec<String> = associations
+        .iter()
+        .filter(|a| !a.user().is_empty())
+        .map(|a| a.user().to_string())
+        .collect();
+
+    users.sort();
+    users.dedup();
+
+    if !users.is_empty() {
+        let mut cmd = vec![
+            "--immediate".to_string(),
+            "modify".to_string(),
+            "user".to_string(),
+            "where".to_string(),
+            format!("account={}", account.name()),
+            format!("cluster={}", cluster),
+        ];
+        cmd.append(&mut set_qos);
+
+        let cmd = priority_runner(expires)
+            .await?
+            .build_command("SACCTMGR", cmd)?;
+
+        priority_runner(expires)
+            .await?
+            .run(&cmd, DEFAULT_TIMEOUT)
+            .await?;
+    }
+
+    let partitions = policy.partitions().unwrap_or_default();
+
+    for user in users {
+        let existing: Vec<String> = associations
+            .iter()
+            .filter(|a| a.user() == user && !a.partition().is_empty())
+            .map(|a| a.partition().to_string())
+            .collect();
+
+        for partition in partitions.iter().filter(|p| !existing.contains(p)) {
+            add_partition_association(&user, account.name(), partition, expires).await?;
+        }
+
+        for partition in existing.iter().filter(|p| !partitions.contains(p)) {
+            let cmd = priority_runner(expires).await?.build_command(
+                "SACCTMGR",
+                vec![
+                    "--immediate".to_string(),
+                    "delete".to_string(),
+                    "user".to_string(),
+                    "where".to_string(),
+                    format!("name={}", clean_user_name(&user)?),
+                    format!("account={}", account.name()),
+                    format!("cluster={}", cluster),
+                    format!("partition={}", partition),
+                ],
+            )?;
+
+            priority_runner(expires)
+                .await?
+                .run(&cmd, DEFAULT_TIMEOUT)
+                .await?;
+        }
+    }
+
+    tracing::info!(
+        "Set the scheduling policy of {} to {}",
+        account.name(),
+        policy
+    );
+
+    get_scheduling_policy(project, expires).await
+}
+
+///
+/// Add an association for the user to the account on the passed
+/// partition. This inherits the QoS of the account.
+///
+async fn add_partition_association(
+    user: &str,
+    account: &str,
+    partition: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<(), Error> {
+    let cluster = cache::get_cluster().await?;
+
+    let cmd = priority_runner(expires).await?.build_command(
+        "SACCTMGR",
+        vec![
+            "--immediate".to_string(),
+            "add".to_string(),
+            "user".to_string(),
+            format!("name={}", clean_user_name(user)?),
+            format!("account={}", clean_account_name(account)?),
+            format!("cluster={}", cluster),
+            format!("partition={}", partition),
+        ],
+    )?;
+
+    priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    Ok(())
+}
+
+///
+/// Return the node reservations of the passed account
+///
+async fn show_reservations(
+    account: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Vec<Reservation>, Error> {
+    let cmd = priority_runner(expires).await?.build_command(
+        "SCONTROL",
+        vec![
+            "--json".to_string(),
+            "show".to_string(),
+            "reservation".to_string(),
+        ],
+    )?;
+
+    let response = priority_runner(expires)
+        .await?
+        .run_json(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    Ok(reservations_from_json(&response, account))
+}
+
+///
+/// Return the reservation of the account called `name`, if it exists
+///
+async fn get_reservation(
+    account: &str,
+    name: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Option<Reservation>, Error> {
+    Ok(show_reservations(account, expires)
+        .await?
+        .into_iter()
+        .find(|r| r.name() == name))
+}
+
+///
+/// Return the scontrol arguments that set the times, nodes and
+/// partition of the reservation. Times are given to scontrol in the
+/// local time of the cluster.
+///
+fn reservation_arguments(reservation: &Reservation) -> Vec<String> {
+    let time = |t: &chrono::DateTime<Utc>| {
+        t.with_timezone(&chrono::Local)
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string()
+    };
+
+    let mut args = vec![
+        format!("StartTime={}", time(reservation.start_time())),
+        format!("EndTime={}", time(reservation.end_time())),
+    ];
+
+    match (reservation.nodes(), reservation.node_count()) {
+        (Some(nodes), _) => args.push(format!("Nodes={}", nodes.join(","))),
+        (None, Some(count)) => args.push(format!("NodeCnt={}", count)),
+        (None, None) => {}
+    }
+
+    if let Some(partition) = reservation.partition() {
+        args.push(format!("PartitionName={}", partition));
+    }
+
+    args
+}
+
+///
+/// Return the node reservations of the project's account
+///
+pub async fn get_reservations(
+    project: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Vec<Reservation>, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    show_reservations(account.name(), expires).await
+}
+
+///
+/// Create a node reservation for the project's account. The Slurm
+/// reservation is named with the account as a prefix, and only the
+/// account can use it. This returns the reservation as Slurm made it,
+/// e.g. with the nodes that it chose.
+///
+pub async fn create_reservation(
+    project: &ProjectMapping,
+    reservation: &Reservation,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Reservation, Error> {
+    assert_not_expired(expires)?;
+
+    reservation.validate()?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    if get_reservation(account.name(), reservation.name(), expires)
+        .await?
+        .is_some()
+    {
+        return Err(Error::Duplicate(format!(
+            "Project {} already has a reservation called '{}'",
+            project.project(),
+            reservation.name()
+        )));
+    }
+
+    let cmd = priority_runner(expires).await?.build_command(
+        "SCONTROL",
+        [
+            vec![
+                "create".to_string(),
+                "reservation".to_string(),
+                format!(
+                    "ReservationName={}",
+                    slurm_reservation_name(account.name(), reservation.name())
+                ),
+                format!("Accounts={}", account.name()),
+            ],
+            reservation_arguments(reservation),
+        ]
+        .concat(),
+    )?;
+
+    priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    tracing::info!(
+        "Created reservation {} for {}",
+        reservation.name(),
+        account.name()
+    );
+
+    get_reservation(account.name(), reservation.name(), expires)
+        .await?
+        .ok_or_else(|| Error::NotFound(reservation.name().to_string()))
+}
+
+///
+/// Update the times, nodes and partition of a node reservation of the
+/// project's account, returning the reservation as Slurm now has it
+///
+pub async fn update_reservation(
+    project: &ProjectMapping,
+    reservation: &Reservation,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Reservation, Error> {
+    assert_not_expired(expires)?;
+
+    reservation.validate()?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    if get_reservation(account.name(), reservation.name(), expires)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound(reservation.name().to_string()));
+    }
+
+    let cmd = priority_runner(expires).await?.build_command(
+        "SCONTROL",
+        [
+            vec![
+                "update".to_string(),
+                format!(
+                    "ReservationName={}",
+                    slurm_reservation_name(account.name(), reservation.name())
+                ),
+            ],
+            reservation_arguments(reservation),
+        ]
+        .concat(),
+    )?;
+
+    priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    tracing::info!(
+        "Updated reservation {} for {}",
+        reservation.name(),
+        account.name()
+    );
+
+    get_reservation(account.name(), reservation.name(), expires)
+        .await?
+        .ok_or_else(|| Error::NotFound(reservation.name().to_string()))
+}
+
+///
+/// Delete the node reservation of the project's account called `name`,
+/// returning the reservation that was deleted
+///
+pub async fn delete_reservation(
+    project: &ProjectMapping,
+    name: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Reservation, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    let Some(reservation) = get_reservation(account.name(), name, expires).await? else {
+        return Err(Error::NotFound(name.to_string()));
+    };
+
+    let cmd = priority_runner(expires).await?.build_command(
+        "SCONTROL",
+        vec![
+            "delete".to_string(),
+            format!(
+                "ReservationName={}",
+                slurm_reservation_name(account.name(), name)
+            ),
+        ],
+    )?;
+
+    priority_runner(expires)
+        .await?
+        .run(&cmd, DEFAULT_TIMEOUT)
+        .await?;
+
+    tracing::info!("Deleted reservation {} for {}", name, account.name());
+
+    Ok(reservation)
+}
+
 pub async fn cancel_pending_user_jobs(
     user: &str,
     expires: &chrono::DateTime<Utc>,
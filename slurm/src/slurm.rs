@@ -5,24 +5,27 @@ use anyhow::Context;
 use anyhow::Result;
 use chrono::{TimeZone, Utc};
 use greatwestern::grammar::{DateRange, ProjectMapping, UserMapping};
+use greatwestern::jobrecords::{JobRecord, JobRecords, Page};
+use greatwestern::scheduling::{ProjectLimit, Reservation, SchedulingPolicy, UserLimit};
 use greatwestern::usagereport::{ProjectUsageReport, Usage};
 use once_cell::sync::Lazy;
 use rand::seq::IteratorRandom;
 use rand::SeedableRng;
-use reqwest::{Client, Url};
+use reqwest::{Client, Method, Url};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::fmt::Display;
 use std::sync::Arc;
 use std::time::Duration;
 use templemeads::job::assert_not_expired;
+use templemeads::metrics;
 use templemeads::Error;
 use tokio::sync::Mutex;
 
 use crate::cache;
-use crate::sacctmgr;
+use crate::reports::{self, JobSource};
 
 #[derive(Debug, Clone)]
 struct SlurmServer {
@@ -593,6 +596,21 @@ async fn get_connected_server(expires: &chrono::DateTime<Utc>) -> Result<LockedS
     }
 }
 
+///
+/// The time allowed for a call to slurmrestd, unless the caller asks for
+/// longer
+///
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn timeout_or_call_error(e: reqwest::Error, url: &Url) -> Error {
+    match e.is_timeout() {
+        true => Error::Timeout(format!("Timed out calling function: {}", url)),
+        false => anyhow::Error::new(e)
+            .context(format!("Could not call function: {}", url))
+            .into(),
+    }
+}
+
 ///
 /// Call a get URL on the slurmrestd server described in 'auth'.
 ///
@@ -601,6 +619,93 @@ async fn call_get(
     function: &str,
     query_params: &Vec<(&str, &str)>,
     expires: &chrono::DateTime<Utc>,
+) -> Result<serde_json::Value, Error> {
+    call_get_with_timeout(backend, function, query_params, DEFAULT_TIMEOUT, expires).await
+}
+
+///
+/// Record how long a call to slurmrestd took in `/metrics`. Only the
+/// endpoint is used as the label (`user`, not `user/alice`), so that the
+/// number of series stays bounded.
+///
+fn record_call_metric<T>(
+    function: &str,
+    result: &Result<T, Error>,
+    start_time: std::time::Instant,
+) {
+    let endpoint = function.split('/').next().unwrap_or(function);
+
+    metrics::observe(
+        "slurm_call_duration_seconds",
+        "How long calls to slurmrestd took, by endpoint and outcome",
+        &[
+            ("endpoint", endpoint),
+            ("outcome", if result.is_ok() { "ok" } else { "error" }),
+        ],
+        start_time.elapsed(),
+    );
+}
+
+///
+/// Call a get URL on the slurmrestd server, giving up with `Error::Timeout`
+/// if it has not answered within `timeout` (or before the job expires).
+///
+async fn call_get_with_timeout(
+    backend: &str,
+    function: &str,
+    query_params: &Vec<(&str, &str)>,
+    timeout: Duration,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<serde_json::Value, Error> {
+    let start_time = std::time::Instant::now();
+    let result = call_from_server(
+        Method::GET,
+        backend,
+        function,
+        query_params,
+        timeout,
+        expires,
+    )
+    .await;
+    record_call_metric(function, &result, start_time);
+    result
+}
+
+///
+/// Call a delete URL on the slurmrestd server, e.g. to delete an
+/// association that is described by the query parameters
+///
+async fn call_delete(
+    backend: &str,
+    function: &str,
+    query_params: &Vec<(&str, &str)>,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<serde_json::Value, Error> {
+    let start_time = std::time::Instant::now();
+    let result = call_from_server(
+        Method::DELETE,
+        backend,
+        function,
+        query_params,
+        DEFAULT_TIMEOUT,
+        expires,
+    )
+    .await;
+    record_call_metric(function, &result, start_time);
+    result
+}
+
+///
+/// Call the URL for `function` on the slurmrestd server with `method`
+/// (GET or DELETE), passing the arguments as query parameters
+///
+async fn call_from_server(
+    method: Method,
+    backend: &str,
+    function: &str,
+    query_params: &Vec<(&str, &str)>,
+    timeout: Duration,
+    expires: &chrono::DateTime<Utc>,
 ) -> Result<serde_json::Value, Error> {
     // get a connected server
     tracing::debug!("Getting a connected server...");
@@ -646,12 +751,12 @@ async fn call_get(
     tracing::debug!("Calling function {}", url);
 
     let client = Client::builder()
-        .timeout(Duration::from_secs(time_left.min(60) as u64))
+        .timeout(Duration::from_secs(time_left as u64).min(timeout))
         .build()
         .context("Could not build client")?;
 
     let mut result = client
-        .get(url.clone())
+        .request(method.clone(), url.clone())
         .header("Referer", format!("{}/ipa", lock.server()))
         .header("Content-Type", "application/json")
         .header("Accept", "application/json")
@@ -659,7 +764,7 @@ async fn call_get(
         .header("X-SLURM-USER-TOKEN", lock.jwt().expose_secret().to_string())
         .send()
         .await
-        .with_context(|| format!("Could not call function: {}", url))?;
+        .map_err(|e| timeout_or_call_error(e, &url))?;
 
     // write a warning if this took a long time
     if (Utc::now() - start_time).num_seconds() > 5 {
@@ -709,13 +814,13 @@ async fn call_get(
         }
 
         let client = Client::builder()
-            .timeout(Duration::from_secs(time_left.min(60) as u64))
+            .timeout(Duration::from_secs(time_left as u64).min(timeout))
             .build()
             .context("Could not build client")?;
 
         // retry the call
         result = client
-            .get(url.clone())
+            .request(method.clone(), url.clone())
             .header("Referer", format!("{}/ipa", lock.server()))
             .header("Content-Type", "application/json")
             .header("Accept", "application/json")
@@ -723,7 +828,7 @@ async fn call_get(
             .header("X-SLURM-USER-TOKEN", lock.jwt().expose_secret().to_string())
             .send()
             .await
-            .with_context(|| format!("Could not call function: {}", url))?;
+            .map_err(|e| timeout_or_call_error(e, &url))?;
 
         if Utc::now().signed_duration_since(start_time).num_seconds() > 10 {
             tracing::error!(
@@ -808,6 +913,18 @@ async fn call_post(
     function: &str,
     payload: &serde_json::Value,
     expires: &chrono::DateTime<Utc>,
+) -> Result<(), Error> {
+    let start_time = std::time::Instant::now();
+    let result = call_post_to_server(backend, function, payload, expires).await;
+    record_call_metric(function, &result, start_time);
+    result
+}
+
+async fn call_post_to_server(
+    backend: &str,
+    function: &str,
+    payload: &serde_json::Value,
+    expires: &chrono::DateTime<Utc>,
 ) -> Result<(), Error> {
     // get a connected server
     tracing::debug!("Getting a connected server...");
@@ -2107,6 +2224,96 @@ impl SlurmLimit {
         &self.cluster
     }
 
+    ///
+    /// Return the limit, in node-seconds of `node`, that Slurm is actually
+    /// enforcing for `account`, if this is not the limit recorded for the
+    /// account - e.g. because an administrator changed it directly in Slurm.
+    ///
+    pub fn enforced_limit(&self, account: &SlurmAccount, node: &SlurmNode) -> Option<Usage> {
+        let mut actual_slurm_limit: Option<Usage> = None;
+
+        if node.has_cpus() && node.cpus() > 0 {
+            if let Some(cpu_limit) = self.cpu_limit() {
+                let check = node.cpus() * account.limit().seconds();
+                if check != cpu_limit.seconds() {
+                    if check != 0 {
+                        tracing::warn!(
+                            "CPU limit for account {} does not match: {} != {}",
+                            account.name(),
+                            check,
+                            cpu_limit.seconds()
+                        );
+                    }
+
+                    actual_slurm_limit = Some(Usage::new(cpu_limit.seconds() / node.cpus()));
+                }
+            }
+        }
+
+        if node.has_gpus() && node.gpus() > 0 {
+            if let Some(gpu_limit) = self.gpu_limit() {
+                let check = node.gpus() * account.limit().seconds();
+                if check != gpu_limit.seconds() {
+                    if check != 0 {
+                        tracing::warn!(
+                            "GPU limit for account {} does not match: {} != {}",
+                            account.name(),
+                            check,
+                            gpu_limit.seconds()
+                        );
+                    }
+
+                    if actual_slurm_limit.is_none() {
+                        actual_slurm_limit = Some(Usage::new(gpu_limit.seconds() / node.gpus()));
+                    }
+                }
+            }
+        }
+
+        if node.has_mem() && node.mem() > 0 {
+            if let Some(mem_limit) = self.mem_limit() {
+                let check = node.mem() * account.limit().seconds();
+                if check != mem_limit.seconds() {
+                    if check != 0 {
+                        tracing::warn!(
+                            "Memory limit for account {} does not match: {} != {}",
+                            account.name(),
+                            check,
+                            mem_limit.seconds()
+                        );
+                    }
+
+                    if actual_slurm_limit.is_none() {
+                        actual_slurm_limit = Some(Usage::new(mem_limit.seconds() / node.mem()));
+                    }
+                }
+            }
+        }
+
+        if node.has_billing() && node.billing() > 0 {
+            if let Some(billing_limit) = self.billing_limit() {
+                let check = node.billing() * account.limit().seconds();
+                if check != billing_limit.seconds() {
+                    if check != 0 {
+                        tracing::warn!(
+                            "Billing limit for account {} does not match: {} != {}",
+                            account.name(),
+                            check,
+                            billing_limit.seconds()
+                        );
+                    }
+
+                    if actual_slurm_limit.is_none() {
+                        actual_slurm_limit =
+                            Some(Usage::new(billing_limit.seconds() / node.billing()));
+                    }
+                }
+            }
+        }
+
+        actual_slurm_limit
+    }
+
     pub fn cpu_limit(&self) -> Option<Usage> {
         self.cpu_limit
     }
@@ -2249,6 +2456,120 @@ impl SlurmAssociation {
     }
 }
 
+///
+/// The scheduling policy of a single association - either that of an
+/// account (which has no user) or of one of its users, optionally on
+/// a partition
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlurmAssociationPolicy {
+    user: String,
+    partition: String,
+    qos: Vec<String>,
+    default_qos: Option<String>,
+}
+
+impl SlurmAssociationPolicy {
+    pub fn new(user: &str, partition: &str, qos: Vec<String>, default_qos: Option<String>) -> Self {
+        SlurmAssociationPolicy {
+            user: user.trim().to_string(),
+            partition: partition.trim().to_string(),
+            qos,
+            default_qos,
+        }
+    }
+
+    ///
+    /// Construct from an association returned by slurmrestd, which has
+    /// `user`, `partition`, `qos` (a list) and `default.qos`
+    ///
+    pub fn construct(association: &serde_json::Value) -> Self {
+        let field = |name: &str| {
+            association
+                .get(name)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let qos = association
+            .get("qos")
+            .and_then(|v| v.as_array())
+            .map(|qos| {
+                qos.iter()
+                    .filter_map(|q| q.as_str())
+                    .map(|q| q.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let default_qos = association
+            .get("default")
+            .and_then(|d| d.get("qos"))
+            .and_then(|q| q.as_str())
+            .filter(|q| !q.is_empty())
+            .map(|q| q.to_string());
+
+        SlurmAssociationPolicy::new(&field("user"), &field("partition"), qos, default_qos)
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn partition(&self) -> &str {
+        &self.partition
+    }
+
+    pub fn qos(&self) -> &Vec<String> {
+        &self.qos
+    }
+
+    pub fn default_qos(&self) -> &Option<String> {
+        &self.default_qos
+    }
+}
+
+///
+/// Build the scheduling policy of an account from its associations -
+/// the QoS are those of the account's own association, and the
+/// partitions are those that its users have associations on
+///
+pub fn policy_from_associations(associations: &[SlurmAssociationPolicy]) -> SchedulingPolicy {
+    let mut policy = SchedulingPolicy::new();
+
+    if let Some(account) = associations.iter().find(|a| a.user().is_empty()) {
+        if !account.qos().is_empty() {
+            if let Err(e) = policy.set_qos(account.qos().clone()) {
+                tracing::warn!("Ignoring the QoS of {:?}: {}", account, e);
+            }
+        }
+
+        if let Some(default_qos) = account.default_qos() {
+            if let Err(e) = policy.set_default_qos(default_qos) {
+                tracing::warn!("Ignoring the default QoS of {:?}: {}", account, e);
+            }
+        }
+    }
+
+    let mut partitions: Vec<String> = associations
+        .iter()
+        .filter(|a| !a.user().is_empty() && !a.partition().is_empty())
+        .map(|a| a.partition().to_string())
+        .collect();
+
+    partitions.sort();
+    partitions.dedup();
+
+    if !partitions.is_empty() {
+        if let Err(e) = policy.set_partitions(partitions) {
+            tracing::warn!("Ignoring the partitions of the account: {}", e);
+        }
+    }
+
+    policy
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SlurmUser {
     name: String,
@@ -2397,7 +2718,7 @@ impl Display for SlurmNode {
 }
 
 impl SlurmNode {
-    fn new(cpus: u64, gpus: u64, mem: u64, billing: u64) -> Self {
+    pub fn new(cpus: u64, gpus: u64, mem: u64, billing: u64) -> Self {
         SlurmNode {
             cpus,
             gpus,
@@ -2493,6 +2814,61 @@ impl SlurmNode {
     pub fn has_billing(&self) -> bool {
         self.billing > 0
     }
+
+    ///
+    /// Return the TRES (e.g. "cpu" and "gres/gpu") of this node that
+    /// usage limits are given to Slurm in
+    ///
+    pub fn tres_names(&self) -> Vec<String> {
+        [
+            ("cpu", self.has_cpus()),
+            ("gres/gpu", self.has_gpus()),
+            ("mem", self.has_mem()),
+            ("billing", self.has_billing()),
+        ]
+        .iter()
+        .filter(|(_, has)| *has)
+        .map(|(name, _)| name.to_string())
+        .collect()
+    }
+
+    ///
+    /// Return the TRES-minutes that `usage`, in node-seconds of this
+    /// node, is equivalent to - this is how usage limits are given to
+    /// Slurm as GrpTRESMins
+    ///
+    pub fn tres_minutes(&self, usage: &Usage) -> BTreeMap<String, u64> {
+        [
+            ("cpu", self.cpus()),
+            ("gres/gpu", self.gpus()),
+            ("mem", self.mem()),
+            ("billing", self.billing()),
+        ]
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(name, count)| (name.to_string(), (*count as f64 * usage.minutes()) as u64))
+        .collect()
+    }
+
+    ///
+    /// Return the usage, in node-seconds of this node, of the passed
+    /// TRES-minutes, or None if they have none of this node's TRES.
+    /// This is the reverse of `tres_minutes`.
+    ///
+    pub fn usage_from_tres_minutes(&self, tres: &BTreeMap<String, u64>) -> Option<Usage> {
+        [
+            ("cpu", self.cpus()),
+            ("gres/gpu", self.gpus()),
+            ("mem", self.mem()),
+            ("billing", self.billing()),
+        ]
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .find_map(|(name, count)| {
+            tres.get(*name)
+                .map(|minutes| Usage::new(minutes.saturating_mul(60) / count))
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -2547,6 +2923,7 @@ pub struct SlurmJob {
     duration: u64,
     state: String,
     qos: String,
+    partition: String,
     nodes: u64,
     cpus: u64,
     gpus: u64,
@@ -2564,7 +2941,7 @@ impl Display for SlurmJob {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "SlurmJob {{ id: {}, user: {}, account: {}, cluster: {}, node_info: {}, start: {}, end: {}, duration: {}s, total_duration: {}s state: {}, qos: {}, nodes: {}, cpus: {}, gpus: {}, memory: {}, requested_nodes: {}, requested_cpus: {}, requested_gpus: {}, requested_memory: {}, energy: {}, billing: {}, requested_billing: {}, node_fraction: {}, billed_node_seconds: {} }}",
+            "SlurmJob {{ id: {}, user: {}, account: {}, cluster: {}, node_info: {}, start: {}, end: {}, duration: {}s, total_duration: {}s state: {}, qos: {}, partition: {}, nodes: {}, cpus: {}, gpus: {}, memory: {}, requested_nodes: {}, requested_cpus: {}, requested_gpus: {}, requested_memory: {}, energy: {}, billing: {}, requested_billing: {}, node_fraction: {}, billed_node_seconds: {} }}",
             self.id(),
             self.user(),
             self.account(),
@@ -2576,6 +2953,7 @@ impl Display for SlurmJob {
             self.total_duration().num_seconds(),
             self.state(),
             self.qos(),
+            self.partition(),
             self.nodes(),
             self.cpus(),
             self.gpus(),
@@ -2840,6 +3218,15 @@ impl SlurmJob {
             }
         };
 
+        // the partition the job ran on, used to price its usage - this is
+        // missing from the records of some older versions of slurm
+        let partition = value
+            .get("partition")
+            .and_then(|partition| partition.as_str())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+
         let tres = match value.get("tres") {
             Some(tres) => tres,
             None => {
@@ -3051,6 +3438,7 @@ impl SlurmJob {
             duration,
             state,
             qos,
+            partition,
             nodes,
             cpus,
             gpus,
@@ -3197,6 +3585,13 @@ impl SlurmJob {
         &self.qos
     }
 
+    ///
+    /// The partition the job ran on, or an empty string if it is not known
+    ///
+    pub fn partition(&self) -> &str {
+        &self.partition
+    }
+
     pub fn nodes(&self) -> u64 {
         self.nodes
     }
@@ -3353,6 +3748,30 @@ impl SlurmJob {
         }
     }
 
+    ///
+    /// Return the accounting record of this job, charged with the
+    /// usage of the job in the window of time it was read in
+    ///
+    pub fn to_record(&self) -> JobRecord {
+        let mut record = JobRecord::new(self.id(), self.user(), self.state(), self.qos());
+
+        record.set_times(
+            self.eligible_time(),
+            self.original_start_time(),
+            self.end_time(),
+        );
+        record.set_resources(
+            self.nodes(),
+            self.cpus(),
+            self.gpus(),
+            self.memory(),
+            self.billing(),
+        );
+        record.add_usage(Usage::new(self.billed_node_seconds()), self.end_time());
+
+        record
+    }
+
     pub fn billing_seconds(&self) -> u64 {
         let billing_seconds = self.billing * self.duration().num_seconds() as u64;
 
@@ -3431,8 +3850,23 @@ pub async fn add_user(user: &UserMapping, expires: &chrono::DateTime<Utc>) -> Re
         };
     };
 
+    let account = SlurmAccount::from_mapping(&user.clone().into())?;
     let user: SlurmUser = get_user_create_if_not_exists(user, expires).await?;
 
+    // give the user an association on each partition of the account's
+    // scheduling policy, as set_scheduling_policy did for existing users
+    let associations = get_association_policies(account.name(), expires).await?;
+    let policy = policy_from_associations(&associations);
+
+    for partition in policy.partitions().unwrap_or_default() {
+        if !associations
+            .iter()
+            .any(|a| a.user() == user.name() && a.partition() == partition)
+        {
+            add_partition_association(user.name(), account.name(), &partition, expires).await?;
+        }
+    }
+
     tracing::info!("Added user: {}", user);
 
     Ok(())
@@ -3481,6 +3915,60 @@ pub async fn add_project(
     Ok(())
 }
 
+///
+/// Reads the jobs charged to an account from the slurmrestd `jobs`
+/// endpoint - the equivalent of `sacct --allocations --allusers`
+///
+#[derive(Debug, Clone)]
+struct RestJobs {
+    account: SlurmAccount,
+    cluster: String,
+    partition: Option<String>,
+    slurm_nodes: SlurmNodes,
+}
+
+impl RestJobs {
+    async fn new(account: SlurmAccount) -> Result<Self, Error> {
+        Ok(Self {
+            account,
+            cluster: cache::get_cluster().await?,
+            partition: cache::get_partition().await?,
+            slurm_nodes: cache::get_nodes().await?,
+        })
+    }
+}
+
+impl JobSource for RestJobs {
+    async fn get_jobs(
+        &self,
+        start_time: &chrono::DateTime<Utc>,
+        end_time: &chrono::DateTime<Utc>,
+        timeout: Duration,
+        expires: &chrono::DateTime<Utc>,
+    ) -> Result<Vec<SlurmJob>, Error> {
+        let start = start_time.timestamp().to_string();
+        let end = end_time.timestamp().to_string();
+
+        let mut query_params = vec![
+            ("account", self.account.name()),
+            ("cluster", self.cluster.as_str()),
+            ("start_time", start.as_str()),
+            ("end_time", end.as_str()),
+            ("skip_steps", "true"),
+        ];
+
+        if let Some(partition) = &self.partition {
+            query_params.push(("partition", partition.as_str()));
+        }
+
+        let response =
+            call_get_with_timeout("slurmdb", "jobs", &query_params, timeout, expires).await?;
+
+        // each job is clipped to the window, as for `sacct`
+        SlurmJob::get_consumers(&response, start_time, end_time, &self.slurm_nodes)
+    }
+}
+
 pub async fn get_usage_report(
     project: &ProjectMapping,
     dates: &DateRange,
@@ -3488,37 +3976,1154 @@ pub async fn get_usage_report(
 ) -> Result<ProjectUsageReport, Error> {
     assert_not_expired(expires)?;
 
-    // Call the sacctmgr version
-    sacctmgr::get_usage_report(project, dates, expires).await
+    let account = SlurmAccount::from_mapping(project)?;
+
+    let account = match get_account(account.name(), expires).await {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            tracing::warn!("Could not get account {}", account.name());
+            return Ok(ProjectUsageReport::new(project.project()));
+        }
+        Err(e) => {
+            tracing::warn!("Could not get account {}: {}", account.name(), e);
+            return Ok(ProjectUsageReport::new(project.project()));
+        }
+    };
+
+    reports::get_usage_report(&RestJobs::new(account).await?, project, dates, expires).await
 }
 
-pub async fn get_limit(
+///
+/// Get the requested page of the records of the jobs charged to the
+/// project's account in `dates`, read from slurmrestd
+///
+pub async fn get_job_records(
     project: &ProjectMapping,
+    dates: &DateRange,
+    page: &Page,
     expires: &chrono::DateTime<Utc>,
-) -> Result<Usage, Error> {
+) -> Result<JobRecords, Error> {
     assert_not_expired(expires)?;
 
-    // Call the sacctmgr version
-    sacctmgr::get_limit(project, expires).await
+    let account = SlurmAccount::from_mapping(project)?;
+
+    let account = match get_account(account.name(), expires).await? {
+        Some(account) => account,
+        None => {
+            tracing::warn!("Could not get account {}", account.name());
+            return Ok(JobRecords::paginate(
+                project.project(),
+                dates,
+                page,
+                Vec::new(),
+            ));
+        }
+    };
+
+    reports::get_job_records(
+        &RestJobs::new(account).await?,
+        project,
+        dates,
+        page,
+        expires,
+    )
+    .await
 }
 
-pub async fn set_limit(
-    project: &ProjectMapping,
-    limit: &Usage,
+///
+/// Get the account-level association of `account` on `cluster` - the one
+/// that carries the account's `GrpTRESMins` - from slurmrestd.
+///
+async fn get_account_limit(
+    account: &SlurmAccount,
+    cluster: &str,
     expires: &chrono::DateTime<Utc>,
-) -> Result<Usage, Error> {
-    assert_not_expired(expires)?;
+) -> Result<Option<SlurmLimit>, Error> {
+    let response = call_get(
+        "slurmdb",
+        "associations",
+        &vec![("account", account.name()), ("cluster", cluster)],
+        expires,
+    )
+    .await?;
 
-    // Call the sacctmgr version
-    sacctmgr::set_limit(project, limit, expires).await
-}
+    let associations = match response.get("associations") {
+        Some(associations) => match associations.as_array() {
+            Some(associations) => associations,
+            None => {
+                tracing::warn!("Associations is not an array: {:?}", associations);
+                return Err(Error::Call("Associations is not an array".to_string()));
+            }
+        },
+        None => return Ok(None),
+    };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // user associations of the account are returned too - the limit is
+    // held on the association that has no user
+    for association in associations {
+        let user = association
+            .get("user")
+            .and_then(|u| u.as_str())
+            .unwrap_or_default();
 
-    #[test]
-    fn test_only_accounts_in_the_managed_organization_are_managed() {
+        if !user.is_empty() {
+            continue;
+        }
+
+        let limit = SlurmLimit::construct(association)?;
+
+        if limit.account() == account.name() && limit.cluster() == cluster {
+            return Ok(Some(limit));
+        }
+    }
+
+    Ok(None)
+}
+
+pub async fn get_limit(
+    project: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Usage, Error> {
+    assert_not_expired(expires)?;
+
+    let account = SlurmAccount::from_mapping(project)?;
+
+    let account = match get_account(account.name(), expires).await? {
+        Some(account) => account,
+        None => {
+            tracing::warn!("Could not get account {}", account.name());
+            return Err(Error::NotFound(account.name().to_string()));
+        }
+    };
+
+    let cluster = cache::get_cluster().await?;
+
+    let slurm_limit = match get_account_limit(&account, &cluster, expires).await? {
+        Some(slurm_limit) => slurm_limit,
+        None => {
+            tracing::warn!("Could not find limit for account {}", account.name());
+            return Err(Error::NotFound(account.name().to_string()));
+        }
+    };
+
+    tracing::debug!(
+        "Found limit for account {}: {}",
+        account.name(),
+        slurm_limit
+    );
+
+    let node = cache::get_default_node().await?;
+
+    if let Some(actual_slurm_limit) = slurm_limit.enforced_limit(&account, &node) {
+        // we need to set this to the actual slurm limit
+        let mut account = account.clone();
+        account.set_limit(&actual_slurm_limit);
+
+        cache::add_account(&account).await?;
+
+        tracing::info!("Updated account limit to {}", actual_slurm_limit);
+        return Ok(actual_slurm_limit);
+    }
+
+    Ok(*account.limit())
+}
+
+///
+/// Return the payload that sets the `GrpTRESMins` of the account-level
+/// association of `account` to `limit`, in terms of the CPUs, GPUs, memory
+/// and billing of `node`. This is None if the node has none of these.
+///
+fn account_limit_payload(
+    account: &str,
+    cluster: &str,
+    node: &SlurmNode,
+    limit: &Usage,
+) -> Option<serde_json::Value> {
+    let mut tres: Vec<serde_json::Value> = Vec::new();
+
+    if node.has_cpus() {
+        tres.push(serde_json::json!({
+            "type": "cpu",
+            "count": (node.cpus() as f64 * limit.minutes()) as u64
+        }));
+    }
+
+    if node.has_gpus() {
+        tres.push(serde_json::json!({
+            "type": "gres",
+            "name": "gpu",
+            "count": (node.gpus() as f64 * limit.minutes()) as u64
+        }));
+    }
+
+    if node.has_mem() {
+        tres.push(serde_json::json!({
+            "type": "mem",
+            "count": (node.mem() as f64 * limit.minutes()) as u64
+        }));
+    }
+
+    if node.has_billing() {
+        tres.push(serde_json::json!({
+            "type": "billing",
+            "count": (node.billing() as f64 * limit.minutes()) as u64
+        }));
+    }
+
+    if tres.is_empty() {
+        return None;
+    }
+
+    // POSTing an association that already exists updates it - this
+    // is the account-level association, so it has no user
+    Some(serde_json::json!({
+        "associations": [
+            {
+                "account": account,
+                "cluster": cluster,
+                "user": "",
+                "max": {
+                    "tres": {
+                        "group": {
+                            "minutes": tres
+                        }
+                    }
+                }
+            }
+        ]
+    }))
+}
+
+pub async fn set_limit(
+    project: &ProjectMapping,
+    limit: &Usage,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Usage, Error> {
+    assert_not_expired(expires)?;
+
+    let account = SlurmAccount::from_mapping(project)?;
+
+    let account = match get_account(account.name(), expires).await? {
+        Some(account) => account,
+        None => {
+            tracing::warn!("Could not get account {}", account.name());
+            return Err(Error::NotFound(account.name().to_string()));
+        }
+    };
+
+    // Refuse to modify an account this agent does not manage - see
+    // `docs/specifications/security-review-2.md` (finding R5).
+    if !account.is_managed() {
+        tracing::warn!(
+            "Refusing to set a limit on Slurm account '{}': it is in \
+             organization '{}', not the OpenPortal-managed '{}'.",
+            account.name(),
+            account.organization(),
+            get_managed_organization()
+        );
+        return Err(Error::UnmanagedGroup(format!(
+            "Cannot set a limit on Slurm account '{}' - it is not managed by OpenPortal",
+            account.name()
+        )));
+    }
+
+    let mut account = account.clone();
+    account.set_limit(limit);
+
+    let cluster = cache::get_cluster().await?;
+
+    // calculate the GrpTRESMins limits in terms of CPU, GPU, memory and billing
+    let node = cache::get_default_node().await?;
+
+    if let Some(payload) = account_limit_payload(account.name(), &cluster, &node, limit) {
+        call_post("slurmdb", "associations", &payload, expires).await?;
+    }
+
+    // now we've made the change, save the account to the cache
+    cache::add_account(&account).await?;
+
+    Ok(*account.limit())
+}
+
+///
+/// Return the account for the passed project, refusing (as for `set_limit`)
+/// if it does not exist or is not managed by OpenPortal
+///
+async fn get_managed_account(
+    project: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<SlurmAccount, Error> {
+    let account = SlurmAccount::from_mapping(project)?;
+
+    match get_account(account.name(), expires).await? {
+        Some(existing) if existing.is_managed() => Ok(existing),
+        Some(existing) => {
+            tracing::warn!(
+                "Refusing to change Slurm account '{}': it is in \
+                 organization '{}', not the OpenPortal-managed '{}'.",
+                account.name(),
+                existing.organization(),
+                get_managed_organization()
+            );
+            Err(Error::UnmanagedGroup(format!(
+                "Cannot change Slurm account '{}' - it is not managed by OpenPortal",
+                account.name()
+            )))
+        }
+        None => {
+            tracing::warn!("Could not get account {}", account.name());
+            Err(Error::NotFound(account.name().to_string()))
+        }
+    }
+}
+
+///
+/// Return the QoS, default QoS and partitions of every association
+/// of the passed account in the managed cluster
+///
+async fn get_association_policies(
+    account: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Vec<SlurmAssociationPolicy>, Error> {
+    let cluster = cache::get_cluster().await?;
+
+    let response = call_get(
+        "slurmdb",
+        "associations",
+        &vec![("account", account), ("cluster", &cluster)],
+        expires,
+    )
+    .await?;
+
+    match response.get("associations").and_then(|a| a.as_array()) {
+        Some(associations) => Ok(associations
+            .iter()
+            .filter(|a| a.get("account").and_then(|v| v.as_str()) == Some(account))
+            .map(SlurmAssociationPolicy::construct)
+            .collect()),
+        None => Ok(Vec::new()),
+    }
+}
+
+///
+/// Add an association for the user to the account on the passed
+/// partition. This inherits the QoS of the account.
+///
+async fn add_partition_association(
+    user: &str,
+    account: &str,
+    partition: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<(), Error> {
+    let cluster = cache::get_cluster().await?;
+
+    let payload = serde_json::json!({
+        "associations": [
+            {
+                "user": clean_user_name(user)?,
+                "account": clean_account_name(account)?,
+                "cluster": cluster,
+                "partition": partition,
+                "comment": format!("Association added by OpenPortal for partition {}", partition),
+            }
+        ]
+    });
+
+    call_post("slurmdb", "associations", &payload, expires).await
+}
+
+///
+/// Return the scheduling policy (QoS, default QoS and partitions) of
+/// the project's account
+///
+pub async fn get_scheduling_policy(
+    project: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<SchedulingPolicy, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    Ok(policy_from_associations(
+        &get_association_policies(account.name(), expires).await?,
+    ))
+}
+
+///
+/// Set the scheduling policy of the project's account, as
+/// `sacctmgr::set_scheduling_policy` does but through slurmrestd
+///
+pub async fn set_scheduling_policy(
+    project: &ProjectMapping,
+    policy: &SchedulingPolicy,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<SchedulingPolicy, Error> {
+    assert_not_expired(expires)?;
+
+    policy.validate()?;
+
+    let account = get_managed_account(project, expires).await?;
+    let cluster = cache::get_cluster().await?;
+
+    // a cleared QoS (or default QoS) goes back to that of the parent
+    let parent = policy_from_associations(
+        &get_association_policies(&cache::get_parent_account().await?, expires).await?,
+    );
+
+    let qos = policy.qos().or(parent.qos()).unwrap_or_default();
+    let default_qos = policy
+        .default_qos()
+        .or(parent.default_qos())
+        .unwrap_or_default();
+
+    let associations = get_association_policies(account.name(), expires).await?;
+
+    // POSTing an association that already exists updates it, so set the
+    // QoS on the account's association and all of its users' associations
+    let updates: Vec<serde_json::Value> = associations
+        .iter()
+        .map(|a| {
+            serde_json::json!({
+                "account": account.name(),
+                "cluster": cluster,
+                "user": a.user(),
+                "partition": a.partition(),
+                "qos": qos,
+                "default": {
+                    "qos": default_qos
+                }
+            })
+        })
+        .collect();
+
+    if !updates.is_empty() {
+        call_post(
+            "slurmdb",
+            "associations",
+            &serde_json::json!({ "associations": updates }),
+            expires,
+        )
+        .await?;
+    }
+
+    let partitions = policy.partitions().unwrap_or_default();
+
+    let mut users: Vec<String> = associations
+        .iter()
+        .filter(|a| !a.user().is_empty())
+        .map(|a| a.user().to_string())
+        .collect();
+
+    users.sort();
+    users.dedup();
+
+    for user in users {
+        let existing: Vec<String> = associations
+            .iter()
+            .filter(|a| a.user() == user && !a.partition().is_empty())
+            .map(|a| a.partition().to_string())
+            .collect();
+
+        for partition in partitions.iter().filter(|p| !existing.contains(p)) {
+            add_partition_association(&user, account.name(), partition, expires).await?;
+        }
+
+        for partition in existing.iter().filter(|p| !partitions.contains(p)) {
+            call_delete(
+                "slurmdb",
+                "association",
+                &vec![
+                    ("account", account.name()),
+                    ("cluster", &cluster),
+                    ("user", &user),
+                    ("partition", partition),
+                ],
+                expires,
+            )
+            .await?;
+        }
+    }
+
+    tracing::info!(
+        "Set the scheduling policy of {} to {}",
+        account.name(),
+        policy
+    );
+
+    get_scheduling_policy(project, expires).await
+}
+
+///
+/// Parse a TRES list as printed by sacctmgr (e.g. "cpu=128,gres/gpu=2"),
+/// skipping any entry that does not have a whole number
+///
+pub fn parse_tres(tres: &str) -> BTreeMap<String, u64> {
+    tres.split(',')
+        .filter_map(|entry| {
+            let (name, count) = entry.split_once('=')?;
+
+            match count.trim().parse::<u64>() {
+                Ok(count) => Some((name.trim().to_ascii_lowercase(), count)),
+                Err(_) => {
+                    tracing::warn!("Ignoring TRES '{}' that is not a whole number", entry);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+///
+/// Return the TRES in a list from slurmrestd, where each is an object
+/// with a "type", "name" and "count" (e.g. gres, gpu and 2)
+///
+pub fn tres_from_json(tres: Option<&serde_json::Value>) -> BTreeMap<String, u64> {
+    let Some(tres) = tres.and_then(|t| t.as_array()) else {
+        return BTreeMap::new();
+    };
+
+    tres.iter()
+        .filter_map(|t| {
+            let typ = t.get("type").and_then(|t| t.as_str())?.to_ascii_lowercase();
+            let name = t.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+            let count = t.get("count").and_then(|c| c.as_u64())?;
+
+            match name.is_empty() {
+                true => Some((typ, count)),
+                false => Some((format!("{}/{}", typ, name), count)),
+            }
+        })
+        .collect()
+}
+
+///
+/// Return the TRES as a list for slurmrestd - the reverse of `tres_from_json`
+///
+fn tres_to_json(tres: &BTreeMap<String, i64>) -> serde_json::Value {
+    serde_json::Value::Array(
+        tres.iter()
+            .map(|(tres, count)| match tres.split_once('/') {
+                Some((typ, name)) => serde_json::json!({"type": typ, "name": name, "count": count}),
+                None => serde_json::json!({"type": tres, "count": count}),
+            })
+            .collect(),
+    )
+}
+
+///
+/// Return the UserLimit of a user association from its GrpTRESMins,
+/// MaxJobs, MaxSubmitJobs and GrpTRES. The GrpTRESMins are turned into
+/// usage in node-seconds of `node`, as for the limit of the account.
+///
+pub fn user_limit_from_fields(
+    grp_tres_mins: &BTreeMap<String, u64>,
+    max_jobs: Option<i64>,
+    max_submit_jobs: Option<i64>,
+    grp_tres: BTreeMap<String, u64>,
+    node: &SlurmNode,
+) -> UserLimit {
+    let mut limit = UserLimit::new();
+
+    if let Some(usage) = node.usage_from_tres_minutes(grp_tres_mins) {
+        limit.set_usage(usage);
+    }
+
+    if let Some(max_jobs) = max_jobs.and_then(|m| u32::try_from(m).ok()) {
+        limit.set_max_jobs(max_jobs);
+    }
+
+    if let Some(max_submit_jobs) = max_submit_jobs.and_then(|m| u32::try_from(m).ok()) {
+        limit.set_max_submit_jobs(max_submit_jobs);
+    }
+
+    if let Err(e) = limit.set_grp_tres(grp_tres) {
+        tracing::warn!("Ignoring the GrpTRES of a user association: {}", e);
+    }
+
+    limit
+}
+
+///
+/// Return the UserLimit of a user association returned by slurmrestd
+///
+pub fn user_limit_from_association(association: &serde_json::Value, node: &SlurmNode) -> UserLimit {
+    let max = association.get("max");
+    let jobs = max.and_then(|m| m.get("jobs")).and_then(|j| j.get("per"));
+    let tres = max.and_then(|m| m.get("tres"));
+
+    user_limit_from_fields(
+        &tres_from_json(
+            tres.and_then(|t| t.get("group"))
+                .and_then(|g| g.get("minutes")),
+        ),
+        get_json_number(jobs.and_then(|j| j.get("count"))),
+        get_json_number(jobs.and_then(|j| j.get("submitted"))),
+        tres_from_json(tres.and_then(|t| t.get("total"))),
+        node,
+    )
+}
+
+///
+/// Return the limit of each user from the (user, partition, limit) of
+/// each of the user associations of an account. Users can have an
+/// association per partition, which all have the same limits, so the
+/// one without a partition is used if there is one.
+///
+pub fn user_limits_from_associations(
+    associations: Vec<(String, String, UserLimit)>,
+) -> BTreeMap<String, UserLimit> {
+    let mut limits = BTreeMap::new();
+
+    for (user, partition, limit) in associations {
+        if user.is_empty() {
+            continue;
+        }
+
+        if partition.is_empty() || !limits.contains_key(&user) {
+            limits.insert(user, limit);
+        }
+    }
+
+    limits
+}
+
+///
+/// Return the GrpTRESMins and GrpTRES that give a user the passed
+/// `limit`, when they currently have `current`. Any TRES that should
+/// no longer be limited is set to -1, which is how Slurm clears them.
+///
+pub fn user_limit_tres(
+    limit: &UserLimit,
+    current: &UserLimit,
+    node: &SlurmNode,
+) -> (BTreeMap<String, i64>, BTreeMap<String, i64>) {
+    let mut minutes: BTreeMap<String, i64> =
+        node.tres_names().into_iter().map(|t| (t, -1)).collect();
+
+    if let Some(usage) = limit.usage() {
+        for (tres, count) in node.tres_minutes(&usage) {
+            minutes.insert(tres, i64::try_from(count).unwrap_or(i64::MAX));
+        }
+    }
+
+    let mut total: BTreeMap<String, i64> = current
+        .grp_tres()
+        .unwrap_or_default()
+        .into_keys()
+        .map(|t| (t, -1))
+        .collect();
+
+    for (tres, count) in limit.grp_tres().unwrap_or_default() {
+        total.insert(tres, i64::try_from(count).unwrap_or(i64::MAX));
+    }
+
+    (minutes, total)
+}
+
+///
+/// Return the limits of each of the users of the account in the
+/// managed cluster
+///
+async fn get_user_limits(
+    account: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<BTreeMap<String, UserLimit>, Error> {
+    let cluster = cache::get_cluster().await?;
+    let node = cache::get_default_node().await?;
+
+    let response = call_get(
+        "slurmdb",
+        "associations",
+        &vec![("account", account), ("cluster", &cluster)],
+        expires,
+    )
+    .await?;
+
+    let associations = match response.get("associations").and_then(|a| a.as_array()) {
+        Some(associations) => associations
+            .iter()
+            .filter(|a| a.get("account").and_then(|v| v.as_str()) == Some(account))
+            .map(|a| {
+                (
+                    a.get("user")
+                        .and_then(|u| u.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    a.get("partition")
+                        .and_then(|p| p.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    user_limit_from_association(a, &node),
+                )
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(user_limits_from_associations(associations))
+}
+
+///
+/// Return the limit of the project's account, together with the
+/// limits of those of its users that have their own
+///
+pub async fn get_member_limits(
+    project: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<ProjectLimit, Error> {
+    let mut limit = ProjectLimit::new(&get_limit(project, expires).await?);
+
+    let account = SlurmAccount::from_mapping(project)?;
+
+    for (user, user_limit) in get_user_limits(account.name(), expires).await? {
+        limit.set_user_limit(&user, &user_limit);
+    }
+
+    Ok(limit)
+}
+
+///
+/// Return the limits of the user within their project's account
+///
+pub async fn get_user_limit(
+    user: &UserMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<UserLimit, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(&user.project(), expires).await?;
+    let local_user = clean_user_name(user.local_user().unix()?)?;
+
+    match get_user_limits(account.name(), expires)
+        .await?
+        .remove(&local_user)
+    {
+        Some(limit) => Ok(limit),
+        None => Err(Error::NotFound(format!(
+            "User {} has no association with account {}",
+            local_user,
+            account.name()
+        ))),
+    }
+}
+
+///
+/// Set the limits of the user within their project's account, as
+/// `sacctmgr::set_user_limit` does but through slurmrestd
+///
+pub async fn set_user_limit(
+    user: &UserMapping,
+    limit: &UserLimit,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<UserLimit, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(&user.project(), expires).await?;
+    let local_user = clean_user_name(user.local_user().unix()?)?;
+    let cluster = cache::get_cluster().await?;
+    let node = cache::get_default_node().await?;
+
+    let current = get_user_limit(user, expires).await?;
+
+    let (minutes, total) = user_limit_tres(limit, &current, &node);
+
+    let number = |n: Option<u32>| match n {
+        Some(n) => serde_json::json!({"set": true, "infinite": false, "number": n}),
+        None => serde_json::json!({"set": true, "infinite": true, "number": 0}),
+    };
+
+    let max = serde_json::json!({
+        "jobs": {
+            "per": {
+                "count": number(limit.max_jobs()),
+                "submitted": number(limit.max_submit_jobs()),
+            }
+        },
+        "tres": {
+            "total": tres_to_json(&total),
+            "group": {
+                "minutes": tres_to_json(&minutes),
+            }
+        }
+    });
+
+    // POSTing an association that already exists updates it, so set the
+    // limits on each of the user's associations with the account
+    let updates: Vec<serde_json::Value> = get_association_policies(account.name(), expires)
+        .await?
+        .iter()
+        .filter(|a| a.user() == local_user)
+        .map(|a| {
+            serde_json::json!({
+                "account": account.name(),
+                "cluster": cluster,
+                "user": local_user,
+                "partition": a.partition(),
+                "max": max,
+            })
+        })
+        .collect();
+
+    call_post(
+        "slurmdb",
+        "associations",
+        &serde_json::json!({ "associations": updates }),
+        expires,
+    )
+    .await?;
+
+    tracing::info!(
+        "Set the limits of {} in {} to {}",
+        local_user,
+        account.name(),
+        limit
+    );
+
+    get_user_limit(user, expires).await
+}
+
+///
+/// Return the name of the Slurm reservation for the project's reservation
+/// called `name`. The account prefix keeps the reservations of different
+/// projects apart.
+///
+pub fn slurm_reservation_name(account: &str, name: &str) -> String {
+    format!("{}_{}", account, name)
+}
+
+///
+/// Return a number from Slurm's JSON, which is either a plain number or
+/// (from v0.0.40) an object with "set", "infinite" and "number" fields.
+/// Numbers that are not set, or are infinite, are None.
+///
+fn get_json_number(value: Option<&serde_json::Value>) -> Option<i64> {
+    let value = value?;
+
+    match value.get("number") {
+        Some(number) => match (
+            value.get("set").and_then(|s| s.as_bool()),
+            value.get("infinite").and_then(|i| i.as_bool()),
+        ) {
+            (Some(false), _) | (_, Some(true)) => None,
+            _ => number.as_i64(),
+        },
+        None => value.as_i64(),
+    }
+}
+
+///
+/// Split a Slurm hostlist (e.g. "gpu[01-04,07],login1") into its
+/// comma-separated parts, leaving the commas inside brackets alone
+///
+fn split_hostlist(hostlist: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut depth = 0;
+
+    for c in hostlist.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+
+        if c == ',' && depth <= 0 {
+            parts.push(std::mem::take(&mut part));
+        } else {
+            part.push(c);
+        }
+    }
+
+    parts.push(part);
+
+    parts
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+///
+/// Return the reservations of `account` from the "reservations" in
+/// `response`, which is the output of both slurmrestd's `reservations`
+/// endpoint and `scontrol --json show reservation`. Only reservations
+/// that are for this account alone, and are named with its prefix, are
+/// returned - with the prefix removed.
+///
+pub fn reservations_from_json(response: &serde_json::Value, account: &str) -> Vec<Reservation> {
+    let prefix = slurm_reservation_name(account, "");
+
+    let Some(reservations) = response.get("reservations").and_then(|r| r.as_array()) else {
+        return Vec::new();
+    };
+
+    reservations
+        .iter()
+        .filter_map(|r| {
+            let name = r
+                .get("name")
+                .and_then(|n| n.as_str())?
+                .strip_prefix(&prefix)?;
+
+            let accounts: Vec<&str> = match r.get("accounts") {
+                Some(serde_json::Value::Array(accounts)) => {
+                    accounts.iter().filter_map(|a| a.as_str()).collect()
+                }
+                Some(serde_json::Value::String(accounts)) => accounts.split(',').collect(),
+                _ => Vec::new(),
+            };
+
+            if accounts != vec![account] {
+                tracing::warn!(
+                    "Ignoring reservation {}{} as it is not for {} alone",
+                    prefix,
+                    name,
+                    account
+                );
+                return None;
+            }
+
+            let time = |key: &str| {
+                get_json_number(r.get(key))
+                    .and_then(|t| Utc.timestamp_opt(t, 0).single())
+                    .map(|t| t.to_rfc3339())
+            };
+
+            let mut reservation = serde_json::Map::new();
+            reservation.insert("name".to_string(), name.into());
+            reservation.insert("start_time".to_string(), time("start_time")?.into());
+            reservation.insert("end_time".to_string(), time("end_time")?.into());
+
+            if let Some(nodes) = r.get("node_list").and_then(|n| n.as_str()) {
+                let nodes = split_hostlist(nodes);
+
+                if !nodes.is_empty() {
+                    reservation.insert("nodes".to_string(), nodes.into());
+                }
+            }
+
+            if let Some(count) = get_json_number(r.get("node_count")) {
+                reservation.insert("node_count".to_string(), count.into());
+            }
+
+            if let Some(partition) = r.get("partition").and_then(|p| p.as_str()) {
+                if !partition.is_empty() {
+                    reservation.insert("partition".to_string(), partition.into());
+                }
+            }
+
+            match Reservation::from_json(&serde_json::Value::Object(reservation).to_string()) {
+                Ok(reservation) => Some(reservation),
+                Err(e) => {
+                    tracing::warn!("Ignoring reservation {}{}: {}", prefix, name, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+///
+/// Return the reservation of the account called `name`, if it exists
+///
+async fn get_reservation(
+    account: &str,
+    name: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Option<Reservation>, Error> {
+    let response = call_get("slurm", "reservations", &vec![], expires).await?;
+
+    Ok(reservations_from_json(&response, account)
+        .into_iter()
+        .find(|r| r.name() == name))
+}
+
+///
+/// Return the slurmrestd description of the reservation for `account`
+///
+fn reservation_payload(account: &str, reservation: &Reservation) -> serde_json::Value {
+    let mut payload = serde_json::Map::new();
+
+    payload.insert(
+        "name".to_string(),
+        slurm_reservation_name(account, reservation.name()).into(),
+    );
+    payload.insert("accounts".to_string(), serde_json::json!([account]));
+    payload.insert(
+        "start_time".to_string(),
+        serde_json::json!({"set": true, "number": reservation.start_time().timestamp()}),
+    );
+    payload.insert(
+        "end_time".to_string(),
+        serde_json::json!({"set": true, "number": reservation.end_time().timestamp()}),
+    );
+
+    match (reservation.nodes(), reservation.node_count()) {
+        (Some(nodes), _) => {
+            payload.insert("node_list".to_string(), nodes.join(",").into());
+        }
+        (None, Some(count)) => {
+            payload.insert(
+                "node_count".to_string(),
+                serde_json::json!({"set": true, "number": count}),
+            );
+        }
+        (None, None) => {}
+    }
+
+    if let Some(partition) = reservation.partition() {
+        payload.insert("partition".to_string(), partition.into());
+    }
+
+    serde_json::Value::Object(payload)
+}
+
+///
+/// Return the node reservations of the project's account. The
+/// reservation endpoints need slurmrestd v0.0.43 (Slurm 25.05) or later.
+///
+pub async fn get_reservations(
+    project: &ProjectMapping,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Vec<Reservation>, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    let response = call_get("slurm", "reservations", &vec![], expires).await?;
+
+    Ok(reservations_from_json(&response, account.name()))
+}
+
+///
+/// Create a node reservation for the project's account, as
+/// `sacctmgr::create_reservation` does but through slurmrestd
+///
+pub async fn create_reservation(
+    project: &ProjectMapping,
+    reservation: &Reservation,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Reservation, Error> {
+    assert_not_expired(expires)?;
+
+    reservation.validate()?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    if get_reservation(account.name(), reservation.name(), expires)
+        .await?
+        .is_some()
+    {
+        return Err(Error::Duplicate(format!(
+            "Project {} already has a reservation called '{}'",
+            project.project(),
+            reservation.name()
+        )));
+    }
+
+    call_post(
+        "slurm",
+        "reservation",
+        &reservation_payload(account.name(), reservation),
+        expires,
+    )
+    .await?;
+
+    tracing::info!(
+        "Created reservation {} for {}",
+        reservation.name(),
+        account.name()
+    );
+
+    get_reservation(account.name(), reservation.name(), expires)
+        .await?
+        .ok_or_else(|| Error::NotFound(reservation.name().to_string()))
+}
+
+///
+/// Update a node reservation of the project's account, as
+/// `sacctmgr::update_reservation` does but through slurmrestd
+///
+pub async fn update_reservation(
+    project: &ProjectMapping,
+    reservation: &Reservation,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Reservation, Error> {
+    assert_not_expired(expires)?;
+
+    reservation.validate()?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    if get_reservation(account.name(), reservation.name(), expires)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound(reservation.name().to_string()));
+    }
+
+    // POSTing a reservation that already exists updates it
+    call_post(
+        "slurm",
+        "reservations",
+        &serde_json::json!({
+            "reservations": [reservation_payload(account.name(), reservation)]
+        }),
+        expires,
+    )
+    .await?;
+
+    tracing::info!(
+        "Updated reservation {} for {}",
+        reservation.name(),
+        account.name()
+    );
+
+    get_reservation(account.name(), reservation.name(), expires)
+        .await?
+        .ok_or_else(|| Error::NotFound(reservation.name().to_string()))
+}
+
+///
+/// Delete the node reservation of the project's account called `name`,
+/// returning the reservation that was deleted
+///
+pub async fn delete_reservation(
+    project: &ProjectMapping,
+    name: &str,
+    expires: &chrono::DateTime<Utc>,
+) -> Result<Reservation, Error> {
+    assert_not_expired(expires)?;
+
+    let account = get_managed_account(project, expires).await?;
+
+    let Some(reservation) = get_reservation(account.name(), name, expires).await? else {
+        return Err(Error::NotFound(name.to_string()));
+    };
+
+    call_delete(
+        "slurm",
+        &format!(
+            "reservation/{}",
+            encode_path_segment(&slurm_reservation_name(account.name(), name))
+        ),
+        &vec![],
+        expires,
+    )
+    .await?;
+
+    tracing::info!("Deleted reservation {} for {}", name, account.name());
+
+    Ok(reservation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reports::add_jobs_to_records;
+
+    #[test]
+    fn test_only_accounts_in_the_managed_organization_are_managed() {
         // Every mutation path (`set_limit`, `cancel_pending_*_jobs`) gates on
         // `is_managed()` applied to the account *fetched from Slurm*. Before
         // finding R5 the only check was on a locally-constructed account whose
@@ -3555,6 +5160,282 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_enforced_limit_is_read_from_the_account_association() {
+        // the account-level association, as returned by both slurmrestd's
+        // `associations` endpoint and `sacctmgr --json show association`
+        let association = serde_json::json!({
+            "account": "someproject",
+            "cluster": "cluster1",
+            "user": "",
+            "max": {"tres": {"group": {"minutes": [
+                {"type": "cpu", "name": "", "count": 7680},
+            ]}}},
+        });
+
+        let limit = SlurmLimit::construct(&association)
+            .unwrap_or_else(|e| unreachable!("construct limit: {:?}", e));
+
+        let mut account = SlurmAccount::construct(&serde_json::json!({
+            "name": "someproject",
+            "description": "a project",
+            "organization": get_managed_organization(),
+            "associations": [{"cluster": "cluster1"}],
+        }))
+        .unwrap_or_else(|e| unreachable!("construct account: {:?}", e));
+
+        let node = SlurmNode::new(128, 0, 0, 0);
+
+        // 7680 cpu-minutes on 128-cpu nodes is one node-hour
+        assert_eq!(
+            limit.enforced_limit(&account, &node),
+            Some(Usage::new(3600))
+        );
+
+        // nothing to correct once the account records the same limit
+        account.set_limit(&Usage::new(3600));
+        assert_eq!(limit.enforced_limit(&account, &node), None);
+    }
+
+    #[test]
+    fn test_jobs_read_in_several_windows_make_one_record() {
+        let start = chrono::Utc
+            .with_ymd_and_hms(2026, 3, 2, 9, 30, 0)
+            .single()
+            .unwrap_or_else(|| unreachable!("start time"));
+        let end = start + chrono::Duration::minutes(90);
+
+        let response = serde_json::json!({"jobs": [{
+            "job_id": 42,
+            "user": "alice",
+            "account": "someproject",
+            "cluster": "cluster1",
+            "nodes": "node01",
+            "time": {
+                "start": start.timestamp(),
+                "end": end.timestamp(),
+                "eligible": start.timestamp() - 600,
+                "elapsed": 5400,
+            },
+            "state": {"current": ["COMPLETED"]},
+            "qos": "normal",
+            "tres": {
+                "allocated": [
+                    {"type": "cpu", "name": "", "count": 64},
+                    {"type": "node", "name": "", "count": 1},
+                ],
+                "requested": [
+                    {"type": "cpu", "name": "", "count": 64},
+                    {"type": "node", "name": "", "count": 1},
+                ],
+            },
+        }]});
+
+        let nodes = SlurmNodes::new(&SlurmNode::new(128, 0, 0, 0));
+        let mut records = BTreeMap::new();
+
+        // the job is read in the two hours that it ran in, so is clipped to each
+        for hour in [9, 10] {
+            let window_start = chrono::Utc
+                .with_ymd_and_hms(2026, 3, 2, hour, 0, 0)
+                .single()
+                .unwrap_or_else(|| unreachable!("window start"));
+            let window_end = window_start + chrono::Duration::hours(1);
+
+            let jobs = SlurmJob::get_consumers(&response, &window_start, &window_end, &nodes)
+                .unwrap_or_else(|e| unreachable!("get jobs: {:?}", e));
+
+            add_jobs_to_records(&mut records, &jobs);
+        }
+
+        assert_eq!(records.len(), 1);
+
+        let record = records
+            .get(&42)
+            .unwrap_or_else(|| unreachable!("no record of the job"));
+
+        // half a node for 30 minutes then for an hour
+        assert_eq!(record.usage(), &Usage::new(2700));
+        assert_eq!(record.start_time(), &start);
+        assert_eq!(record.end_time(), &end);
+        assert_eq!(record.wait_seconds(), 600);
+        assert_eq!(record.local_user(), "alice");
+        assert_eq!(record.cpus(), 64);
+    }
+
+    #[test]
+    fn test_jobs_are_read_from_the_slurmrestd_jobs_response() {
+        let at = |hour: u32, minute: u32| {
+            chrono::Utc
+                .with_ymd_and_hms(2026, 3, 2, hour, minute, 0)
+                .single()
+                .unwrap_or_else(|| unreachable!("time"))
+        };
+
+        let tres = |cpus: u64, gpus: u64| {
+            serde_json::json!([
+                {"type": "cpu", "name": "", "id": 1, "count": cpus},
+                {"type": "mem", "name": "", "id": 2, "count": 1024},
+                {"type": "node", "name": "", "id": 4, "count": 1},
+                {"type": "gres", "name": "gpu", "id": 1001, "count": gpus},
+            ])
+        };
+
+        // as returned by `GET /slurmdb/v0.0.40/jobs` with `skip_steps`
+        let response = serde_json::json!({
+            "jobs": [
+                {
+                    "job_id": 101,
+                    "user": "alice",
+                    "account": "someproject",
+                    "cluster": "cluster1",
+                    "nodes": "gpu01",
+                    "partition": "gpu",
+                    "qos": "normal",
+                    "state": {"current": ["COMPLETED"], "reason": "None"},
+                    "time": {
+                        "submission": at(9, 50).timestamp(),
+                        "eligible": at(9, 50).timestamp(),
+                        "start": at(10, 0).timestamp(),
+                        "end": at(10, 30).timestamp(),
+                        "elapsed": 1800,
+                    },
+                    "tres": {"allocated": tres(32, 2), "requested": tres(32, 2)},
+                    "steps": [],
+                },
+                {
+                    "job_id": 102,
+                    "user": "bob",
+                    "account": "someproject",
+                    "cluster": "cluster1",
+                    "nodes": "gpu02",
+                    "partition": "gpu",
+                    "qos": "normal",
+                    "state": {"current": ["RUNNING"], "reason": "None"},
+                    "time": {
+                        "submission": at(8, 0).timestamp(),
+                        "eligible": at(8, 0).timestamp(),
+                        "start": at(9, 0).timestamp(),
+                        "end": at(12, 0).timestamp(),
+                        "elapsed": 10800,
+                    },
+                    "tres": {"allocated": tres(64, 0), "requested": tres(64, 0)},
+                    "steps": [],
+                },
+                {
+                    // a job that cannot be read is skipped, not fatal
+                    "job_id": "not-a-number",
+                    "user": "mallory",
+                },
+            ],
+            "warnings": [],
+            "errors": [],
+        });
+
+        let nodes = SlurmNodes::new(&SlurmNode::new(128, 4, 0, 0));
+
+        let jobs = SlurmJob::get_consumers(&response, &at(10, 0), &at(11, 0), &nodes)
+            .unwrap_or_else(|e| unreachable!("get jobs: {:?}", e));
+
+        assert_eq!(jobs.len(), 2);
+
+        let alice = jobs
+            .iter()
+            .find(|job| job.id() == 101)
+            .unwrap_or_else(|| unreachable!("no job 101"));
+
+        // half of the GPUs for 30 minutes
+        assert_eq!(alice.user(), "alice");
+        assert_eq!(alice.gpus(), 2);
+        assert_eq!(alice.billed_node_seconds(), 900);
+        assert_eq!(alice.wait_time().num_seconds(), 600);
+
+        // bob's job ran for the whole window, and is clipped to it,
+        // while still recording when it really started
+        let bob = jobs
+            .iter()
+            .find(|job| job.id() == 102)
+            .unwrap_or_else(|| unreachable!("no job 102"));
+
+        assert_eq!(bob.start_time(), &at(10, 0));
+        assert_eq!(bob.end_time(), &at(11, 0));
+        assert_eq!(bob.original_start_time(), &at(9, 0));
+        assert_eq!(bob.billed_node_seconds(), 1800);
+
+        // an empty response has no jobs, but a malformed one is an error
+        assert!(SlurmJob::get_consumers(
+            &serde_json::json!({"jobs": [], "errors": []}),
+            &at(10, 0),
+            &at(11, 0),
+            &nodes
+        )
+        .unwrap_or_else(|e| unreachable!("get jobs: {:?}", e))
+        .is_empty());
+
+        assert!(SlurmJob::get_consumers(
+            &serde_json::json!({"jobs": {}}),
+            &at(10, 0),
+            &at(11, 0),
+            &nodes
+        )
+        .is_err());
+
+        assert!(SlurmJob::get_consumers(&response, &at(11, 0), &at(10, 0), &nodes).is_err());
+    }
+
+    #[test]
+    fn test_set_limit_upserts_the_account_association() {
+        let node = SlurmNode::new(128, 4, 256000, 0);
+
+        let payload = account_limit_payload("someproject", "cluster1", &node, &Usage::new(3600))
+            .unwrap_or_else(|| unreachable!("no payload"));
+
+        // one node-hour, in the minutes of each resource of the node, on the
+        // account-level association - the one with an empty user
+        assert_eq!(
+            payload,
+            serde_json::json!({"associations": [{
+                "account": "someproject",
+                "cluster": "cluster1",
+                "user": "",
+                "max": {"tres": {"group": {"minutes": [
+                    {"type": "cpu", "count": 7680},
+                    {"type": "gres", "name": "gpu", "count": 240},
+                    {"type": "mem", "count": 15360000},
+                ]}}},
+            }]})
+        );
+
+        // the limit that is set is the one that is read back
+        let association = payload
+            .get("associations")
+            .and_then(|a| a.get(0))
+            .unwrap_or_else(|| unreachable!("no association"));
+
+        let limit = SlurmLimit::construct(association)
+            .unwrap_or_else(|e| unreachable!("construct limit: {:?}", e));
+
+        let mut account = SlurmAccount::construct(&serde_json::json!({
+            "name": "someproject",
+            "description": "a project",
+            "organization": get_managed_organization(),
+            "associations": [{"cluster": "cluster1"}],
+        }))
+        .unwrap_or_else(|e| unreachable!("construct account: {:?}", e));
+
+        account.set_limit(&Usage::new(3600));
+        assert_eq!(limit.enforced_limit(&account, &node), None);
+
+        // a node with nothing to limit has nothing to send
+        assert!(account_limit_payload(
+            "someproject",
+            "cluster1",
+            &SlurmNode::new(0, 0, 0, 0),
+            &Usage::new(3600)
+        )
+        .is_none());
+    }
+
     #[test]
     fn test_api_version_parsing_tolerates_a_hostile_version_string() {
         // The version comes from the server's openapi.json and used to be
@@ -3641,4 +5522,176 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_scheduling_policy_from_associations() {
+        let associations: Vec<SlurmAssociationPolicy> = [
+            serde_json::json!({
+                "account": "someproject",
+                "user": "",
+                "qos": ["normal", "gpu"],
+                "default": {"qos": "normal"},
+            }),
+            serde_json::json!({"account": "someproject", "user": "alice", "qos": ["normal", "gpu"]}),
+            serde_json::json!({"account": "someproject", "user": "alice", "partition": "gpu-a100"}),
+            serde_json::json!({"account": "someproject", "user": "bob", "partition": "gpu-a100"}),
+            serde_json::json!({"account": "someproject", "user": "bob", "partition": "cpu"}),
+        ]
+        .iter()
+        .map(SlurmAssociationPolicy::construct)
+        .collect();
+
+        let policy = policy_from_associations(&associations);
+
+        assert_eq!(
+            policy.qos(),
+            Some(vec!["normal".to_string(), "gpu".to_string()])
+        );
+        assert_eq!(policy.default_qos(), Some("normal".to_string()));
+        assert_eq!(
+            policy.partitions(),
+            Some(vec!["cpu".to_string(), "gpu-a100".to_string()])
+        );
+
+        // an account without its own QoS or partitions has an empty policy
+        assert!(policy_from_associations(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_reservations_are_read_for_their_account_only() {
+        // scontrol and slurmrestd before v0.0.40 give plain numbers, and
+        // a comma-separated string of accounts
+        let response = serde_json::json!({"reservations": [
+            {
+                "name": "someproject_workshop",
+                "accounts": "someproject",
+                "node_list": "gpu[01-04,07],login1",
+                "node_count": 6,
+                "partition": "gpu",
+                "start_time": 1793610000,
+                "end_time": {"set": true, "infinite": false, "number": 1793638800},
+            },
+            {
+                "name": "someproject_shared",
+                "accounts": ["someproject", "otherproject"],
+                "node_count": {"set": true, "number": 1},
+                "start_time": 1793610000,
+                "end_time": 1793638800,
+            },
+            {
+                "name": "maintenance",
+                "accounts": "someproject",
+                "node_count": 10,
+                "start_time": 1793610000,
+                "end_time": 1793638800,
+            },
+        ]});
+
+        let reservations = reservations_from_json(&response, "someproject");
+
+        assert_eq!(reservations.len(), 1);
+
+        let reservation = reservations
+            .first()
+            .unwrap_or_else(|| unreachable!("no reservation"));
+
+        assert_eq!(reservation.name(), "workshop");
+        assert_eq!(
+            reservation.nodes(),
+            Some(vec!["gpu[01-04,07]".to_string(), "login1".to_string()])
+        );
+        assert_eq!(reservation.node_count(), Some(6));
+        assert_eq!(reservation.partition(), Some("gpu".to_string()));
+        assert_eq!(reservation.start_time().timestamp(), 1793610000);
+        assert_eq!(reservation.end_time().timestamp(), 1793638800);
+
+        // the named nodes are reserved, rather than the count
+        let payload = reservation_payload("someproject", reservation);
+        assert_eq!(payload["name"], "someproject_workshop");
+        assert!(payload.get("node_count").is_none());
+    }
+
+    #[test]
+    fn test_user_limits_are_read_from_their_associations() {
+        let node = SlurmNode::new(128, 0, 0, 0);
+
+        // 7680 cpu-minutes on 128-cpu nodes is one node-hour
+        assert_eq!(
+            node.tres_minutes(&Usage::new(3600)),
+            BTreeMap::from([("cpu".to_string(), 7680)])
+        );
+        assert_eq!(
+            node.usage_from_tres_minutes(&parse_tres("cpu=7680,mem=100")),
+            Some(Usage::new(3600))
+        );
+
+        let association = |user: &str, partition: &str, max_jobs: serde_json::Value| {
+            serde_json::json!({
+                "account": "someproject",
+                "user": user,
+                "partition": partition,
+                "max": {
+                    "jobs": {"per": {
+                        "count": max_jobs,
+                        "submitted": {"set": false, "infinite": false, "number": 0},
+                    }},
+                    "tres": {
+                        "total": [{"type": "gres", "name": "gpu", "count": 2}],
+                        "group": {"minutes": [{"type": "cpu", "name": "", "count": 7680}]},
+                    },
+                },
+            })
+        };
+
+        let limits = user_limits_from_associations(
+            [
+                association("", "", serde_json::json!(10)),
+                association("alice", "gpu", serde_json::json!(8)),
+                association(
+                    "alice",
+                    "",
+                    serde_json::json!({"set": true, "infinite": false, "number": 4}),
+                ),
+                association(
+                    "bob",
+                    "",
+                    serde_json::json!({"set": true, "infinite": true, "number": 0}),
+                ),
+            ]
+            .iter()
+            .map(|a| {
+                (
+                    a["user"].as_str().unwrap_or_default().to_string(),
+                    a["partition"].as_str().unwrap_or_default().to_string(),
+                    user_limit_from_association(a, &node),
+                )
+            })
+            .collect(),
+        );
+
+        // the account's own association is not a user's
+        assert_eq!(limits.len(), 2);
+
+        let alice = limits
+            .get("alice")
+            .unwrap_or_else(|| unreachable!("no limit for alice"));
+
+        assert_eq!(alice.usage(), Some(Usage::new(3600)));
+        assert_eq!(alice.max_jobs(), Some(4));
+        assert_eq!(alice.max_submit_jobs(), None);
+        assert_eq!(
+            alice.grp_tres(),
+            Some(BTreeMap::from([("gres/gpu".to_string(), 2)]))
+        );
+
+        let bob = limits
+            .get("bob")
+            .unwrap_or_else(|| unreachable!("no limit for bob"));
+        assert_eq!(bob.max_jobs(), None);
+
+        // clearing the limits sets each TRES that was limited to -1
+        let (minutes, total) = user_limit_tres(&UserLimit::new(), alice, &node);
+        assert_eq!(minutes, BTreeMap::from([("cpu".to_string(), -1)]));
+        assert_eq!(total, BTreeMap::from([("gres/gpu".to_string(), -1)]));
+    }
 }
@@ -0,0 +1,42 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Summary of a job that has passed through the bridge
+ */
+export type JobRecord = { 
+/**
+ * Job ID
+ */
+id: string, 
+/**
+ * Job destination
+ */
+destination: string, 
+/**
+ * Job instruction
+ */
+instruction: string, 
+/**
+ * Final state of the job (e.g. "complete" or "error")
+ */
+state: string, 
+/**
+ * Stable kind of the failure, if the job errored
+ */
+error_kind: string | null, 
+/**
+ * Failure message, if the job errored
+ */
+error_message: string | null, 
+/**
+ * When the job was created
+ */
+created: string, 
+/**
+ * When the job last changed (i.e. when it finished)
+ */
+finished: string, 
+/**
+ * How long the job took, in milliseconds
+ */
+duration_ms: bigint, };
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT
 
 use crate::agent::Type as AgentType;
+use crate::agent_core::DEFAULT_KEY_ROTATION_GRACE_HOURS;
 use crate::bridge_server::{
     save as save_bridge_invite, spawn, Config as BridgeConfig, Defaults as BridgeDefaults,
     Invite as BridgeInvite,
@@ -9,16 +10,18 @@ use crate::bridge_server::{
 use crate::domain::Domain;
 use crate::error::Error;
 use crate::handler::{process_message, set_my_service_details};
+use crate::jobhistory::Config as HistoryConfig;
 use crate::runnable::AsyncRunnable;
 
 use anyhow::Context;
 use anyhow::Result;
 use clap::{CommandFactory, Parser, Subcommand};
 use paddington::config::{
-    load as load_config, save as save_config, Defaults as ServiceDefaults, ServiceConfig,
+    load_service_config as load_config, save_service_config as save_config,
+    Defaults as ServiceDefaults, HasServiceConfig, KeyRotation, ServiceConfig,
 };
 use paddington::invite::{load as load_invite, save as save_invite};
-use paddington::Key;
+use paddington::{Key, PublicKey};
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use std::path::PathBuf;
@@ -42,6 +45,21 @@ pub async fn run<L: Domain>(config: Config, runner: AsyncRunnable<L>) -> Result<
     // pass the service details onto the handler
     set_my_service_details(&config.service.name(), &config.agent, Some(runner), true).await?;
 
+    // restore the journalled boards before the bridge server can accept
+    // jobs, or any peer can connect
+    if let Some(dir) = &config.journal_dir {
+        crate::state::enable_journal::<L>(&dir.join("boards")).await?;
+        crate::bridgestate::enable_journal::<L>(&dir.join("bridge")).await?;
+    }
+
+    if let Some(history) = &config.history {
+        crate::jobhistory::enable(history, &config.service.name())?;
+    }
+
+    if let Some(audit_log) = &config.audit_log {
+        crate::audit::enable(audit_log, &config.service.name())?;
+    }
+
     // spawn the bridge server
     spawn::<L>(config.bridge).await?;
 
@@ -59,6 +77,31 @@ pub struct Config {
     pub service: ServiceConfig,
     pub bridge: BridgeConfig,
     pub agent: AgentType,
+
+    /// Directory in which to journal the job boards, so that they survive
+    /// a restart. Boards are held only in memory if this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal_dir: Option<PathBuf>,
+
+    /// Where and how much job history to keep, for `GET /jobs/history`.
+    /// No history is kept if this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<HistoryConfig>,
+
+    /// File to which to append the hash-chained audit log of every job the
+    /// bridge submits or handles. No audit log is kept if this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_log: Option<PathBuf>,
+}
+
+impl HasServiceConfig for Config {
+    fn service_config(&self) -> &ServiceConfig {
+        &self.service
+    }
+
+    fn service_config_mut(&mut self) -> &mut ServiceConfig {
+        &mut self.service
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -183,6 +226,9 @@ pub async fn process_args(defaults: &Defaults) -> Result<Option<Config>, Error>
                         .unwrap_or_else(|| defaults.bridge.notification_url()),
                 ),
                 agent: AgentType::Bridge,
+                journal_dir: None,
+                audit_log: None,
+                history: None,
             };
 
             // Apply the trusted-proxy allow-list to both the agent (paddington,
@@ -220,6 +266,7 @@ pub async fn process_args(defaults: &Defaults) -> Result<Option<Config>, Error>
             zone,
             rotate,
             r#type,
+            public_key,
         }) => {
             if *list {
                 let config = load_config::<Config>(&config_file)?;
@@ -239,19 +286,32 @@ pub async fn process_args(defaults: &Defaults) -> Result<Option<Config>, Error>
                     )));
                 }
 
+                let public_key = public_key
+                    .as_deref()
+                    .map(|key| key.parse::<PublicKey>())
+                    .transpose()?;
+
                 let mut config = load_config::<Config>(&config_file)?;
 
-                let invite = config
-                    .service
-                    .add_client(
+                let invite = match &public_key {
+                    Some(public_key) => config.service.add_client_with_public_key(
                         client,
                         &ip.clone().unwrap_or_else(|| "".to_string()),
                         zone,
                         &agent_type,
-                    )?
-                    // A bridge is always a Bridge - tell the client so, so its
-                    // side of the expectation needs no hand-editing.
-                    .with_agent_type(Some(AgentType::Bridge.to_string()));
+                        public_key,
+                    )?,
+                    None => config.service.add_client(
+                        client,
+                        &ip.clone().unwrap_or_else(|| "".to_string()),
+                        zone,
+                        &agent_type,
+                    )?,
+                };
+
+                // A bridge is always a Bridge - tell the client so, so its
+                // side of the expectation needs no hand-editing.
+                let invite = invite.with_agent_type(Some(AgentType::Bridge.to_string()));
 
                 save_config(&config, &config_file)?;
                 save_invite(
@@ -292,6 +352,58 @@ pub async fn process_args(defaults: &Defaults) -> Result<Option<Config>, Error>
 
             return Ok(None);
         }
+        Some(Commands::KeyRotation {
+            interval,
+            grace,
+            disable,
+        }) => {
+            let mut config = load_config::<Config>(&config_file)?;
+
+            if *disable {
+                config.service.set_key_rotation(None);
+                save_config(&config, &config_file)?;
+                tracing::info!("Automatic key rotation disabled.");
+            } else if let Some(interval) = interval {
+                let key_rotation =
+                    KeyRotation::new(*interval, grace.unwrap_or(DEFAULT_KEY_ROTATION_GRACE_HOURS))?;
+
+                config.service.set_key_rotation(Some(key_rotation.clone()));
+                save_config(&config, &config_file)?;
+                tracing::info!(
+                    "Client keys will be rotated every {} hours, and replaced keys accepted for \
+                     {} hours. Restart the agent for this to take effect.",
+                    key_rotation.interval_hours(),
+                    key_rotation.grace_hours()
+                );
+            } else {
+                match config.service.key_rotation() {
+                    Some(key_rotation) => println!(
+                        "Client keys are rotated every {} hours, and replaced keys accepted for \
+                         {} hours.",
+                        key_rotation.interval_hours(),
+                        key_rotation.grace_hours()
+                    ),
+                    None => println!("Automatic key rotation is disabled."),
+                }
+            }
+
+            return Ok(None);
+        }
+        Some(Commands::Identity {}) => {
+            let mut config = load_config::<Config>(&config_file)?;
+
+            let had_identity = config.service.identity_public_key()?.is_some();
+            let public_key = config.service.ensure_identity()?;
+
+            if !had_identity {
+                save_config(&config, &config_file)?;
+                tracing::info!("Generated a new identity keypair for this service.");
+            }
+
+            println!("public key:  {}", public_key);
+            println!("fingerprint: {}", public_key.fingerprint());
+            return Ok(None);
+        }
         Some(Commands::Server {
             add,
             list,
@@ -323,6 +435,14 @@ pub async fn process_args(defaults: &Defaults) -> Result<Option<Config>, Error>
 
                 let mut config = load_config::<Config>(&config_file)?;
                 config.service.add_server(&invite)?;
+
+                if let Some(fingerprint) = invite.fingerprint() {
+                    tracing::info!(
+                        "The server's identity fingerprint is {} - check this with its operator.",
+                        fingerprint
+                    );
+                }
+
                 save_config(&config, &config_file)?;
                 tracing::info!("Server '{}' added.", server.display());
                 return Ok(None);
@@ -351,7 +471,15 @@ pub async fn process_args(defaults: &Defaults) -> Result<Option<Config>, Error>
 
             return Ok(None);
         }
-        Some(Commands::Bridge { config, regenerate }) => {
+        Some(Commands::Bridge {
+            config,
+            regenerate,
+            journal_dir,
+            history_dir,
+            history_max_records,
+            history_max_days,
+            audit_log,
+        }) => {
             if let Some(py_config_file) = config {
                 let config = load_config::<Config>(&config_file)?;
                 let py_config = BridgeInvite::parse(&config.bridge.url, &config.bridge.key);
@@ -371,13 +499,106 @@ pub async fn process_args(defaults: &Defaults) -> Result<Option<Config>, Error>
                 return Ok(None);
             }
 
+            if let Some(journal_dir) = journal_dir {
+                let mut config = load_config::<Config>(&config_file)?;
+
+                if journal_dir.as_os_str().is_empty() {
+                    config.journal_dir = None;
+                    tracing::info!("Board journals disabled.");
+                } else {
+                    config.journal_dir = Some(journal_dir.clone());
+                    tracing::info!(
+                        "Board journals will be written to {}",
+                        journal_dir.display()
+                    );
+                }
+
+                save_config(&config, &config_file)?;
+                return Ok(None);
+            }
+
+            if let Some(audit_log) = audit_log {
+                let mut config = load_config::<Config>(&config_file)?;
+
+                if audit_log.as_os_str().is_empty() {
+                    config.audit_log = None;
+                    tracing::info!("Audit log disabled.");
+                } else {
+                    config.audit_log = Some(audit_log.clone());
+                    tracing::info!("Audit log will be written to {}", audit_log.display());
+                }
+
+                save_config(&config, &config_file)?;
+                return Ok(None);
+            }
+
+            if history_dir.is_some() || history_max_records.is_some() || history_max_days.is_some()
+            {
+                let mut config = load_config::<Config>(&config_file)?;
+
+                let history = match (history_dir, config.history.take()) {
+                    (Some(dir), _) if dir.as_os_str().is_empty() => None,
+                    (Some(dir), Some(history)) => Some(HistoryConfig {
+                        dir: dir.clone(),
+                        ..history
+                    }),
+                    (Some(dir), None) => Some(HistoryConfig::new(dir)),
+                    (None, Some(history)) => Some(history),
+                    (None, None) => {
+                        return Err(Error::InvalidConfig(
+                            "Set a job history directory with --history-dir first.".to_string(),
+                        ));
+                    }
+                };
+
+                config.history = history.map(|history| HistoryConfig {
+                    max_records: history_max_records.unwrap_or(history.max_records),
+                    max_age_days: history_max_days.unwrap_or(history.max_age_days),
+                    ..history
+                });
+
+                match &config.history {
+                    Some(history) => tracing::info!(
+                        "Job history will be kept in {} (at most {} records, {} days).",
+                        history.dir.display(),
+                        history.max_records,
+                        history.max_age_days
+                    ),
+                    None => tracing::info!("Job history disabled."),
+                }
+
+                save_config(&config, &config_file)?;
+                return Ok(None);
+            }
+
             let _ = Args::command().print_help();
 
             return Ok(None);
         }
+        Some(Commands::VerifyAudit { file }) => {
+            let path = match file {
+                Some(file) => file.clone(),
+                None => load_config::<Config>(&config_file)?
+                    .audit_log
+                    .ok_or_else(|| {
+                        Error::InvalidConfig(
+                            "No audit log is configured - set one with `bridge --audit-log`, \
+                             or pass --file"
+                                .to_string(),
+                        )
+                    })?,
+            };
+
+            crate::audit::verify_and_report(&path)?;
+            return Ok(None);
+        }
         Some(Commands::Run {}) => {
             let config = load_config::<Config>(&config_file)?;
             tracing::info!("Loaded config from {}", &config_file.display());
+
+            // keys rotated in-band while the agent runs are saved back here
+            paddington::rotation::persist_to::<Config>(&config_file)?;
+
             return Ok(Some(config));
         }
         _ => {
@@ -446,6 +667,46 @@ enum Commands {
                     bridge, account, filesystem, scheduler, virtual"
         )]
         r#type: Option<String>,
+
+        #[arg(
+            long,
+            short = 'k',
+            help = "The client's public key, as printed by its `identity` command. The \
+                    client then authenticates with its keypair rather than pre-shared keys, \
+                    and the invite carries only this service's public key"
+        )]
+        public_key: Option<String>,
+    },
+
+    /// Print this service's public key and fingerprint, generating its
+    /// keypair if it has none
+    Identity {},
+
+    /// Rotate the keys of this service's clients automatically, over their
+    /// existing connections
+    KeyRotation {
+        #[arg(
+            long,
+            short = 'i',
+            help = "Propose fresh keys to each client once its keys are this many hours old"
+        )]
+        interval: Option<u64>,
+
+        #[arg(
+            long,
+            short = 'g',
+            requires = "interval",
+            help = "Hours for which a client's replaced keys are still accepted (default: 24)"
+        )]
+        grace: Option<u64>,
+
+        #[arg(
+            long,
+            short = 'd',
+            conflicts_with = "interval",
+            help = "Stop rotating keys automatically"
+        )]
+        disable: bool,
     },
 
     /// Adding and removing servers
@@ -585,6 +846,47 @@ enum Commands {
             help = "Re-generate the API key used by bridge clients to connect to the service. Note you will need to generate a new configuration file for any Python clients."
         )]
         regenerate: bool,
+
+        #[arg(
+            long,
+            short = 'j',
+            help = "Directory in which to journal the job boards so that they survive a restart. Pass an empty string to go back to in-memory boards."
+        )]
+        journal_dir: Option<std::path::PathBuf>,
+
+        #[arg(
+            long,
+            help = "Directory in which to keep the history of finished jobs, served from /jobs/history. Pass an empty string to stop keeping history."
+        )]
+        history_dir: Option<std::path::PathBuf>,
+
+        #[arg(
+            long,
+            help = "Maximum number of finished jobs to keep in the history (default 100000)."
+        )]
+        history_max_records: Option<usize>,
+
+        #[arg(
+            long,
+            help = "Maximum age, in days, of a finished job kept in the history (default 90)."
+        )]
+        history_max_days: Option<u32>,
+
+        #[arg(
+            long,
+            help = "File to which to append the hash-chained audit log of every job the bridge submits or handles. Pass an empty string to stop keeping an audit log."
+        )]
+        audit_log: Option<std::path::PathBuf>,
+    },
+
+    /// Verify the hash chain of the audit log
+    VerifyAudit {
+        #[arg(
+            long,
+            short = 'f',
+            help = "Audit log to verify (default: the audit log in this bridge's config)"
+        )]
+        file: Option<std::path::PathBuf>,
     },
 
     /// Run the service
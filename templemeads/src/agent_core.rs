@@ -2,21 +2,28 @@
 // SPDX-License-Identifier: MIT
 
 use crate::agent::Type as AgentType;
+use crate::domain::Domain;
 use crate::error::Error;
 
 use anyhow::Context;
 use anyhow::Result;
 use clap::{CommandFactory, Parser, Subcommand};
 use paddington::config::{
-    load as load_config, save as save_config, Defaults as ServiceDefaults, ServiceConfig,
+    is_stored_secret, load_service_config as load_config, save_service_config as save_config,
+    Defaults as ServiceDefaults, HasServiceConfig, KeyRotation, ServiceConfig,
 };
 use paddington::invite::{load as load_invite, save as save_invite};
+use paddington::PublicKey;
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 
+/// How long a client's replaced keys are accepted after an automatic key
+/// rotation, unless `key-rotation --grace` says otherwise
+pub(crate) const DEFAULT_KEY_ROTATION_GRACE_HOURS: u64 = 24;
+
 // Configuration
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -44,6 +51,19 @@ where
     one_shot_zone: Option<String>,
 }
 
+impl<T> HasServiceConfig for Config<T>
+where
+    T: Serialize + Clone + std::fmt::Debug,
+{
+    fn service_config(&self) -> &ServiceConfig {
+        &self.service
+    }
+
+    fn service_config_mut(&mut self) -> &mut ServiceConfig {
+        &mut self.service
+    }
+}
+
 impl<T> Config<T>
 where
     T: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug + Default,
@@ -88,6 +108,39 @@ where
         }
     }
 
+    ///
+    /// Make this agent's job boards durable if the `board-journal-dir`
+    /// extra is set, restoring any boards journalled there by a previous
+    /// run. Does nothing if the extra is not set.
+    ///
+    pub async fn enable_board_journal<L: Domain>(&self) -> Result<(), Error> {
+        match self.extras.get("board-journal-dir") {
+            Some(dir) if !dir.trim().is_empty() => {
+                crate::state::enable_journal::<L>(Path::new(dir.trim())).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// Start this agent's tamper-evident audit log if the `audit-log` extra
+    /// is set, continuing any hash chain already in that file. Does nothing
+    /// if the extra is not set. See [`crate::audit`].
+    ///
+    pub fn enable_audit_log(&self) -> Result<(), Error> {
+        match self.audit_log() {
+            Some(path) => crate::audit::enable(&path, &self.service.name()),
+            None => Ok(()),
+        }
+    }
+
+    fn audit_log(&self) -> Option<PathBuf> {
+        match self.extras.get("audit-log") {
+            Some(path) if !path.trim().is_empty() => Some(PathBuf::from(path.trim())),
+            _ => None,
+        }
+    }
+
     pub fn one_shot_commands(&self) -> &Option<Vec<String>> {
         &self.one_shot_commands
     }
@@ -262,7 +315,7 @@ pub(crate) fn validate_agent_type(agent_type: &Option<String>) -> Result<Option<
 
 pub async fn process_args<T>(defaults: &Defaults<T>) -> Result<Option<Config<T>>, Error>
 where
-    T: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug + Default,
+    T: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug + Default + 'static,
 {
     let args = Args::parse();
     let defaults = defaults.clone();
@@ -347,6 +400,7 @@ where
             rotate,
             proxy,
             r#type,
+            public_key,
         }) => {
             if *list {
                 let config = load_config::<Config<T>>(&config_file)?;
@@ -361,7 +415,18 @@ where
 
                 let mut config = load_config::<Config<T>>(&config_file)?;
 
+                let public_key = public_key
+                    .as_deref()
+                    .map(|key| key.parse::<PublicKey>())
+                    .transpose()?;
+
                 let invite = match proxy {
+                    Some(_) if public_key.is_some() => {
+                        return Err(Error::PeerEdit(format!(
+                            "Client {} cannot use a public key, as it is reached through a relay.",
+                            client
+                        )));
+                    }
                     Some(relay) => {
                         config
                             .service
@@ -375,12 +440,21 @@ where
                             )));
                         }
 
-                        config.service.add_client(
-                            client,
-                            &ip.clone().unwrap_or_else(|| "".to_string()),
-                            zone,
-                            &agent_type,
-                        )?
+                        match &public_key {
+                            Some(public_key) => config.service.add_client_with_public_key(
+                                client,
+                                &ip.clone().unwrap_or_else(|| "".to_string()),
+                                zone,
+                                &agent_type,
+                                public_key,
+                            )?,
+                            None => config.service.add_client(
+                                client,
+                                &ip.clone().unwrap_or_else(|| "".to_string()),
+                                zone,
+                                &agent_type,
+                            )?,
+                        }
                     }
                 };
 
@@ -439,6 +513,58 @@ where
 
             return Ok(None);
         }
+        Some(Commands::KeyRotation {
+            interval,
+            grace,
+            disable,
+        }) => {
+            let mut config = load_config::<Config<T>>(&config_file)?;
+
+            if *disable {
+                config.service.set_key_rotation(None);
+                save_config(&config, &config_file)?;
+                tracing::info!("Automatic key rotation disabled.");
+            } else if let Some(interval) = interval {
+                let key_rotation =
+                    KeyRotation::new(*interval, grace.unwrap_or(DEFAULT_KEY_ROTATION_GRACE_HOURS))?;
+
+                config.service.set_key_rotation(Some(key_rotation.clone()));
+                save_config(&config, &config_file)?;
+                tracing::info!(
+                    "Client keys will be rotated every {} hours, and replaced keys accepted for \
+                     {} hours. Restart the agent for this to take effect.",
+                    key_rotation.interval_hours(),
+                    key_rotation.grace_hours()
+                );
+            } else {
+                match config.service.key_rotation() {
+                    Some(key_rotation) => println!(
+                        "Client keys are rotated every {} hours, and replaced keys accepted for \
+                         {} hours.",
+                        key_rotation.interval_hours(),
+                        key_rotation.grace_hours()
+                    ),
+                    None => println!("Automatic key rotation is disabled."),
+                }
+            }
+
+            return Ok(None);
+        }
+        Some(Commands::Identity {}) => {
+            let mut config = load_config::<Config<T>>(&config_file)?;
+
+            let had_identity = config.service.identity_public_key()?.is_some();
+            let public_key = config.service.ensure_identity()?;
+
+            if !had_identity {
+                save_config(&config, &config_file)?;
+                tracing::info!("Generated a new identity keypair for this service.");
+            }
+
+            println!("public key:  {}", public_key);
+            println!("fingerprint: {}", public_key.fingerprint());
+            return Ok(None);
+        }
         Some(Commands::Server {
             add,
             list,
@@ -476,6 +602,21 @@ where
                 // else needs to be passed in here.
                 config.service.add_server(&invite)?;
 
+                if let Some(fingerprint) = invite.fingerprint() {
+                    if config.service.identity_public_key()?.is_none() {
+                        tracing::warn!(
+                            "This server expects a public-key identity, but this service has \
+                             none - run the `identity` command, and send the key it prints to \
+                             the server's operator."
+                        );
+                    }
+
+                    tracing::info!(
+                        "The server's identity fingerprint is {} - check this with its operator.",
+                        fingerprint
+                    );
+                }
+
                 save_config(&config, &config_file)?;
                 tracing::info!("Server '{}' added.", server.display());
                 return Ok(None);
@@ -507,6 +648,11 @@ where
         Some(Commands::Encryption {
             simple,
             environment,
+            vault,
+            vault_mount,
+            vault_path,
+            vault_transit_key,
+            exec,
         }) => {
             let mut config = load_config::<Config<T>>(&config_file)?;
 
@@ -518,15 +664,35 @@ where
             let stored_secrets: Vec<String> = config
                 .extras
                 .iter()
-                .filter(|(_, value)| value.starts_with("op-secret-v1:"))
+                .filter(|(_, value)| is_stored_secret(value))
                 .map(|(key, _)| key.clone())
                 .collect();
 
-            match environment {
-                Some(env) => {
+            // peer keys need no such care - they were fetched from the old
+            // scheme's backend on load, and `save_config` puts them wherever
+            // the new one keeps them
+            match (environment, vault, vault_transit_key, exec) {
+                (Some(env), _, _, _) => {
                     config.service.set_environment_encryption(env)?;
                 }
-                None => {
+                (None, Some(address), Some(key), _) => {
+                    config.service.set_vault_transit_encryption(
+                        address,
+                        vault_mount.as_deref().unwrap_or("transit"),
+                        key,
+                    )?;
+                }
+                (None, Some(address), None, _) => {
+                    config.service.set_vault_kv_encryption(
+                        address,
+                        vault_mount.as_deref().unwrap_or("secret"),
+                        vault_path.as_deref().unwrap_or("openportal"),
+                    )?;
+                }
+                (None, None, _, Some(command)) => {
+                    config.service.set_exec_encryption(command)?;
+                }
+                (None, None, _, None) => {
                     if *simple {
                         config.service.set_simple_encryption()?;
                     }
@@ -555,7 +721,7 @@ where
             let secret = read_secret_value(value.as_deref(), value_file.as_deref())?;
 
             let mut config = load_config::<Config<T>>(&config_file)?;
-            let value = config.service().encrypt(&secret)?;
+            let value = config.service().store_secret(key, &secret)?;
             config.extras.insert(key.clone(), value.clone());
             save_config(&config, &config_file)?;
             return Ok(None);
@@ -566,6 +732,23 @@ where
             save_config(&config, &config_file)?;
             return Ok(None);
         }
+        Some(Commands::VerifyAudit { file }) => {
+            let path = match file {
+                Some(file) => file.clone(),
+                None => load_config::<Config<T>>(&config_file)?
+                    .audit_log()
+                    .ok_or_else(|| {
+                        Error::InvalidConfig(
+                            "No audit log is configured - set the `audit-log` extra, or pass \
+                             --file"
+                                .to_string(),
+                        )
+                    })?,
+            };
+
+            crate::audit::verify_and_report(&path)?;
+            return Ok(None);
+        }
         Some(Commands::Run {
             one_shot_commands,
             repeat,
@@ -575,6 +758,9 @@ where
             let mut config = load_config::<Config<T>>(&config_file)?;
             tracing::info!("Loaded config from {}", &config_file.display());
 
+            // keys rotated in-band while the agent runs are saved back here
+            paddington::rotation::persist_to::<Config<T>>(&config_file)?;
+
             if let Some(one_shot_commands) = one_shot_commands {
                 let repeat = repeat.unwrap_or(1);
                 let mut one_shot_commands = one_shot_commands.clone();
@@ -665,6 +851,46 @@ enum Commands {
                     bridge, account, filesystem, scheduler, virtual"
         )]
         r#type: Option<String>,
+
+        #[arg(
+            long,
+            short = 'k',
+            help = "The client's public key, as printed by its `identity` command. The \
+                    client then authenticates with its keypair rather than pre-shared keys, \
+                    and the invite carries only this service's public key"
+        )]
+        public_key: Option<String>,
+    },
+
+    /// Print this service's public key and fingerprint, generating its
+    /// keypair if it has none
+    Identity {},
+
+    /// Rotate the keys of this service's clients automatically, over their
+    /// existing connections
+    KeyRotation {
+        #[arg(
+            long,
+            short = 'i',
+            help = "Propose fresh keys to each client once its keys are this many hours old"
+        )]
+        interval: Option<u64>,
+
+        #[arg(
+            long,
+            short = 'g',
+            requires = "interval",
+            help = "Hours for which a client's replaced keys are still accepted (default: 24)"
+        )]
+        grace: Option<u64>,
+
+        #[arg(
+            long,
+            short = 'd',
+            conflicts_with = "interval",
+            help = "Stop rotating keys automatically"
+        )]
+        disable: bool,
     },
 
     /// Adding and removing servers
@@ -801,6 +1027,60 @@ enum Commands {
             help = "Use the value of the specified environment variable as the encryption password."
         )]
         environment: Option<String>,
+
+        #[arg(
+            long,
+            help = "Keep secrets in the HashiCorp Vault at this address - in a KV engine, or \
+                    encrypted by a transit key if --vault-transit-key is given. The `vault` \
+                    CLI must be installed, and authenticated (e.g. VAULT_TOKEN) when the \
+                    agent runs."
+        )]
+        vault: Option<String>,
+
+        #[arg(
+            long,
+            requires = "vault",
+            help = "Mount point of the Vault engine (default: 'secret' for KV, 'transit' \
+                    for transit)"
+        )]
+        vault_mount: Option<String>,
+
+        #[arg(
+            long,
+            requires = "vault",
+            conflicts_with = "vault_transit_key",
+            help = "Path under the KV mount at which to keep secrets (default: 'openportal')"
+        )]
+        vault_path: Option<String>,
+
+        #[arg(
+            long,
+            requires = "vault",
+            help = "Encrypt secrets with this Vault transit key, keeping only the \
+                    ciphertext in the config"
+        )]
+        vault_transit_key: Option<String>,
+
+        #[arg(
+            long,
+            num_args = 1..,
+            allow_hyphen_values = true,
+            conflicts_with = "vault",
+            help = "Keep secrets with this helper command (and arguments), which is run as \
+                    `<command> store <name>` with the secret on stdin, and \
+                    `<command> fetch <name>` printing it to stdout. Must be the last option."
+        )]
+        exec: Option<Vec<String>>,
+    },
+
+    /// Verify the hash chain of the audit log
+    VerifyAudit {
+        #[arg(
+            long,
+            short = 'f',
+            help = "Audit log to verify (default: the `audit-log` extra of this agent's config)"
+        )]
+        file: Option<PathBuf>,
     },
 
     /// Run the service
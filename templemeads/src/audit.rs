@@ -0,0 +1,530 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! A tamper-evident audit log of the jobs an agent handles.
+//!
+//! `tracing` output and the diagnostics ring buffers say what an agent did,
+//! but nothing about them would show if a line were later edited or removed.
+//! When an audit log is configured, every job this agent receives, forwards,
+//! refuses or completes is appended to it as one JSON line naming the peer and
+//! zone it came from, the instruction, and the result or error. Each record
+//! carries the hash of the record before it, and its own hash over both, so
+//! changing, inserting or deleting any record breaks the chain from that point
+//! on - which `verify` (the `verify-audit` subcommand of every agent) reports.
+//!
+//! A hash chain proves the log is internally consistent, not that the whole
+//! file was not regenerated from scratch. The head of the chain is logged at
+//! `info` level every time the log is opened, so a site that ships its logs
+//! elsewhere holds an independent anchor to check the file against.
+//!
+//! Unlike the board journals, the audit log is not a cache: if it cannot be
+//! opened the agent refuses to start, rather than run unaudited.
+//!
+//! The chain is built under a lock, but each record is written and synced
+//! by a [`DiskWriter`] thread, so recording an event never blocks the task
+//! that handles the job on disk I/O.
+
+use crate::agent::Peer;
+use crate::diskwriter::DiskWriter;
+use crate::domain::Domain;
+use crate::error::Error;
+use crate::job::{Job, Status};
+use crate::joberror::JobError;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use uuid::Uuid;
+
+/// The `prev` of the first record in a log
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Results are recorded up to this many bytes. A usage report can run to
+/// megabytes, and the audit question is which change was made, not the
+/// whole of every report that was read - so a longer result is truncated,
+/// and its full digest kept in `result_digest`.
+const MAX_RESULT_BYTES: usize = 4096;
+
+///
+/// What happened to the job
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// The audit log was opened by a (re)started agent
+    Started,
+    /// The job arrived from `peer`
+    Received,
+    /// The job was passed on to `peer`
+    Forwarded,
+    /// The job was refused before it was acted on
+    Refused,
+    /// This agent ran the job, for `peer`, and this was the outcome
+    Completed,
+}
+
+impl std::fmt::Display for AuditEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditEvent::Started => write!(f, "started"),
+            AuditEvent::Received => write!(f, "received"),
+            AuditEvent::Forwarded => write!(f, "forwarded"),
+            AuditEvent::Refused => write!(f, "refused"),
+            AuditEvent::Completed => write!(f, "completed"),
+        }
+    }
+}
+
+///
+/// One line of the audit log
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the chain, counting from 0
+    pub seq: u64,
+    /// When this was recorded
+    pub time: DateTime<Utc>,
+    /// The agent writing the log
+    pub agent: String,
+    /// What happened
+    pub event: AuditEvent,
+    /// The peer the job came from or went to
+    #[serde(default)]
+    pub peer: Option<String>,
+    /// The zone of that peer
+    #[serde(default)]
+    pub zone: Option<String>,
+    #[serde(default)]
+    pub job: Option<Uuid>,
+    #[serde(default)]
+    pub destination: Option<String>,
+    #[serde(default)]
+    pub instruction: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    /// The result of a completed job, truncated to a few KB
+    #[serde(default)]
+    pub result: Option<String>,
+    /// Digest of the whole result, however long
+    #[serde(default)]
+    pub result_digest: Option<String>,
+    #[serde(default)]
+    pub error: Option<JobError>,
+    /// Hash of the previous record ([`GENESIS`] for the first)
+    pub prev: String,
+    /// Hash of this record, computed with this field empty
+    pub hash: String,
+}
+
+impl AuditRecord {
+    fn compute_hash(&self) -> Result<String, Error> {
+        let mut unhashed = self.clone();
+        unhashed.hash = String::new();
+        Ok(paddington::digest(
+            serde_json::to_string(&unhashed)?.as_bytes(),
+        )?)
+    }
+}
+
+///
+/// The result of verifying an audit log
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    /// Number of records that verified, before any break
+    pub records: u64,
+    /// Hash of the last record that verified (the head of the chain)
+    pub head: String,
+    /// The line number (from 1) and reason of the first break, if any
+    pub error: Option<(usize, String)>,
+}
+
+impl Verification {
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug)]
+struct AuditLog {
+    path: PathBuf,
+    writer: DiskWriter,
+    agent: String,
+    seq: u64,
+    head: String,
+}
+
+static AUDIT: OnceLock<Mutex<AuditLog>> = OnceLock::new();
+
+impl AuditLog {
+    fn open(path: &Path, agent: &str) -> Result<Self, Error> {
+        // continue the chain from the last record already in the file
+        let verification = verify(path)?;
+
+        if let Some((line, reason)) = &verification.error {
+            // Carry on from the last good record rather than refuse to start -
+            // the break stays in the file, where `verify` will keep reporting it.
+            tracing::error!(
+                "The audit log {} is broken at line {}: {}. Continuing the chain from \
+                 the last good record.",
+                path.display(),
+                line,
+                reason
+            );
+        }
+
+        let mut options = std::fs::OpenOptions::new();
+        options.create(true).append(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let file = options.open(path).map_err(|e| {
+            Error::InvalidConfig(format!(
+                "Could not open the audit log {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: DiskWriter::start(path, file, "audit log", true)?,
+            agent: agent.to_string(),
+            seq: verification.records,
+            head: verification.head,
+        })
+    }
+
+    fn append(&mut self, mut record: AuditRecord) -> Result<(), Error> {
+        record.seq = self.seq;
+        record.agent = self.agent.clone();
+        record.prev = self.head.clone();
+        record.hash = record.compute_hash()?;
+
+        let line = serde_json::to_string(&record)?;
+
+        self.writer.append(line + "\n");
+
+        self.seq += 1;
+        self.head = record.hash;
+
+        Ok(())
+    }
+}
+
+fn truncate(s: &str, max_bytes: usize) -> String {
+    if s.len() <= max_bytes {
+        return s.to_string();
+    }
+
+    let mut end = max_bytes;
+
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    format!("{}…", s.get(..end).unwrap_or_default())
+}
+
+///
+/// Start writing the audit log to `path`, continuing any chain already
+/// there. `agent` is this agent's name. Call this once, at startup.
+///
+pub(crate) fn enable(path: &Path, agent: &str) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+
+    let mut log = AuditLog::open(path, agent)?;
+
+    log.append(AuditRecord {
+        seq: 0,
+        time: Utc::now(),
+        agent: String::new(),
+        event: AuditEvent::Started,
+        peer: None,
+        zone: None,
+        job: None,
+        destination: None,
+        instruction: None,
+        state: None,
+        result: None,
+        result_digest: None,
+        error: None,
+        prev: String::new(),
+        hash: String::new(),
+    })?;
+
+    tracing::info!(
+        "Audit log {} opened at record {} - chain head {}",
+        log.path.display(),
+        log.seq,
+        log.head
+    );
+
+    if AUDIT.set(Mutex::new(log)).is_err() {
+        return Err(Error::InvalidState(
+            "The audit log has already been enabled".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+///
+/// Append a record of `event` happening to `job`, involving `peer`, to the
+/// audit log. Does nothing if there is no audit log.
+///
+pub(crate) fn record<L: Domain>(event: AuditEvent, peer: &Peer, job: &Job<L>) {
+    let Some(log) = AUDIT.get() else {
+        return;
+    };
+
+    let (result, result_digest) = match job.state() {
+        Status::Complete => match job.result_json() {
+            Ok(result) => (
+                Some(truncate(&result, MAX_RESULT_BYTES)),
+                paddington::digest(result.as_bytes()).ok(),
+            ),
+            Err(_) => (None, None),
+        },
+        _ => (None, None),
+    };
+
+    let record = AuditRecord {
+        seq: 0,
+        time: Utc::now(),
+        agent: String::new(),
+        event,
+        peer: Some(peer.name().to_string()),
+        zone: Some(peer.zone().to_string()),
+        job: Some(job.id()),
+        destination: Some(job.destination().to_string()),
+        instruction: Some(job.instruction().to_string()),
+        state: Some(job.state().to_string()),
+        result,
+        result_digest,
+        error: job.error_or_infer(),
+        prev: String::new(),
+        hash: String::new(),
+    };
+
+    match log.lock() {
+        Ok(mut log) => {
+            if let Err(e) = log.append(record) {
+                tracing::error!(
+                    "Could not write to the audit log {}: {}",
+                    log.path.display(),
+                    e
+                );
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to lock the audit log: {}", e);
+        }
+    }
+}
+
+///
+/// Verify the hash chain of the audit log at `path`, stopping at the first
+/// record that does not follow from the one before it. A missing file is an
+/// empty (and so valid) log.
+///
+pub fn verify(path: &Path) -> Result<Verification, Error> {
+    let mut verification = Verification {
+        records: 0,
+        head: GENESIS.to_string(),
+        error: None,
+    };
+
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(verification),
+        Err(e) => return Err(Error::IO(e)),
+    };
+
+    for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line_number = index + 1;
+        let line = line?;
+
+        let fail = |reason: String| Some((line_number, reason));
+
+        let record = match serde_json::from_str::<AuditRecord>(&line) {
+            Ok(record) => record,
+            Err(e) => {
+                verification.error = fail(format!("unreadable record: {}", e));
+                break;
+            }
+        };
+
+        if record.seq != verification.records {
+            verification.error = fail(format!(
+                "expected record {}, found record {}",
+                verification.records, record.seq
+            ));
+            break;
+        }
+
+        if record.prev != verification.head {
+            verification.error = fail(format!(
+                "record {} does not follow from the record before it",
+                record.seq
+            ));
+            break;
+        }
+
+        if record.compute_hash()? != record.hash {
+            verification.error = fail(format!(
+                "record {} has been altered - its hash does not match",
+                record.seq
+            ));
+            break;
+        }
+
+        verification.records += 1;
+        verification.head = record.hash;
+    }
+
+    Ok(verification)
+}
+
+///
+/// Verify the audit log at `path` and print the outcome, returning an error
+/// if the chain is broken. This is the `verify-audit` subcommand shared by
+/// every agent.
+///
+pub(crate) fn verify_and_report(path: &Path) -> Result<(), Error> {
+    let verification = verify(path)?;
+
+    match &verification.error {
+        None => {
+            println!(
+                "{}: OK - {} record(s), chain head {}",
+                path.display(),
+                verification.records,
+                verification.head
+            );
+            Ok(())
+        }
+        Some((line, reason)) => {
+            println!(
+                "{}: BROKEN at line {} - {}. The {} record(s) before it verify, \
+                 ending at chain head {}",
+                path.display(),
+                line,
+                reason,
+                verification.records,
+                verification.head
+            );
+            Err(Error::Failed(format!(
+                "The audit log {} failed verification at line {}",
+                path.display(),
+                line
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_domain::TestDomain;
+
+    fn temp_log(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("audit-{}-{}.jsonl", name, Uuid::new_v4()))
+    }
+
+    fn write_chain(path: &Path, n: usize) {
+        let mut log = AuditLog::open(path, "cluster").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let job = Job::<TestDomain>::parse("portal.cluster add_user alice.proj.portal", false)
+            .unwrap_or_else(|e| unreachable!("parse: {:?}", e));
+
+        for _ in 0..n {
+            let peer = Peer::new("portal", "default");
+            let record = AuditRecord {
+                seq: 0,
+                time: Utc::now(),
+                agent: String::new(),
+                event: AuditEvent::Received,
+                peer: Some(peer.name().to_string()),
+                zone: Some(peer.zone().to_string()),
+                job: Some(job.id()),
+                destination: Some(job.destination().to_string()),
+                instruction: Some(job.instruction().to_string()),
+                state: Some(job.state().to_string()),
+                result: None,
+                result_digest: None,
+                error: None,
+                prev: String::new(),
+                hash: String::new(),
+            };
+            log.append(record)
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+        }
+
+        log.writer.synced().wait_blocking();
+    }
+
+    #[test]
+    fn test_an_intact_chain_verifies_and_continues_across_reopen() {
+        let path = temp_log("intact");
+
+        write_chain(&path, 3);
+        write_chain(&path, 2);
+
+        let verification = verify(&path).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert!(verification.is_valid(), "{:?}", verification);
+        assert_eq!(verification.records, 5);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_an_edited_or_deleted_record_breaks_the_chain() {
+        let path = temp_log("edited");
+        write_chain(&path, 4);
+
+        let original = std::fs::read_to_string(&path).unwrap_or_else(|e| unreachable!("{:?}", e));
+        let lines: Vec<&str> = original.lines().collect();
+
+        // change which peer asked for the second record
+        let edited: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, l)| match i {
+                1 => l.replace("\"peer\":\"portal\"", "\"peer\":\"other\""),
+                _ => l.to_string(),
+            })
+            .collect();
+        std::fs::write(&path, edited.join("\n") + "\n").unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let verification = verify(&path).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(verification.records, 1);
+        assert_eq!(verification.error.map(|(line, _)| line), Some(2));
+
+        // delete the third record
+        let deleted: Vec<&str> = lines
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 2)
+            .map(|(_, l)| *l)
+            .collect();
+        std::fs::write(&path, deleted.join("\n") + "\n")
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let verification = verify(&path).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(verification.records, 2);
+        assert_eq!(verification.error.map(|(line, _)| line), Some(3));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
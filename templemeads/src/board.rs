@@ -3,14 +3,19 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 use tokio::sync::oneshot;
 use uuid::Uuid;
 
 use crate::agent::Peer;
 use crate::command::Command as ControlCommand;
+use crate::destination::Position;
+use crate::diskwriter::Synced;
 use crate::domain::Domain;
 use crate::error::Error;
 use crate::job::Job;
+use crate::joberror::{kind, JobError};
+use crate::journal::{journal_path, Journal};
 
 /// Largest `version` a `Job` arriving from a peer may plausibly carry.
 ///
@@ -108,6 +113,11 @@ pub struct Board<L: Domain> {
     // do not serialise the duplicates
     #[serde(skip)]
     duplicates: HashMap<Uuid, Vec<Uuid>>,
+
+    // the on-disk journal, if durable boards are enabled - never
+    // serialised or cloned, as only one board may write to it
+    #[serde(skip)]
+    journal: Option<Journal>,
 }
 
 impl<L: Domain> Default for Board<L> {
@@ -118,12 +128,13 @@ impl<L: Domain> Default for Board<L> {
             queued_commands: Vec::new(),
             waiters: HashMap::new(),
             duplicates: HashMap::new(),
+            journal: None,
         }
     }
 }
 
 impl<L: Domain> Clone for Board<L> {
-    /// Clone the board, but do not clone the waiters
+    /// Clone the board, but do not clone the waiters or the journal
     fn clone(&self) -> Self {
         Self {
             peer: self.peer.clone(),
@@ -131,6 +142,7 @@ impl<L: Domain> Clone for Board<L> {
             queued_commands: self.queued_commands.clone(),
             waiters: HashMap::new(),
             duplicates: self.duplicates.clone(),
+            journal: None,
         }
     }
 }
@@ -143,6 +155,93 @@ impl<L: Domain> Board<L> {
             queued_commands: Vec::new(),
             waiters: HashMap::new(),
             duplicates: HashMap::new(),
+            journal: None,
+        }
+    }
+
+    ///
+    /// Make this board durable by attaching the journal for its peer in
+    /// `dir`, replaying whatever that journal already holds onto the board.
+    ///
+    /// `my_name` is the name of this agent. Any unfinished job that this agent
+    /// was running, or was relaying further down its path, cannot be resumed -
+    /// the task that was doing so died with the old process - so it is marked
+    /// as errored here. The next `sync_board` then reports that upstream
+    /// straight away, rather than leaving the requester to wait for it to
+    /// expire. Jobs this agent sent to the peer are kept as they were, and are
+    /// reconciled by the peer's own sync.
+    ///
+    /// Returns the number of jobs restored.
+    ///
+    pub(crate) fn attach_journal(&mut self, dir: &Path, my_name: &str) -> Result<usize, Error> {
+        let (mut journal, replayed) =
+            Journal::open::<L>(&journal_path(dir, &self.peer)?, &self.peer)?;
+
+        let num_jobs = replayed.jobs.len();
+
+        for job in replayed.jobs {
+            let job = job.on_board(&self.peer);
+
+            let interrupted = !job.is_finished()
+                && (job.is_duplicate()
+                    || matches!(
+                        job.destination().position(my_name, self.peer.name()),
+                        Position::Destination | Position::Downstream
+                    ));
+
+            let job = match interrupted {
+                true => match job.errored_with(JobError::new(
+                    kind::INTERRUPTED,
+                    "The agent handling this job restarted before it finished. It may \
+                     or may not have taken effect - check, then retry.",
+                )) {
+                    Ok(errored) => {
+                        journal.put(&errored);
+                        errored
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to mark job {} as interrupted: {}", job.id(), e);
+                        job
+                    }
+                },
+                false => job,
+            };
+
+            self.jobs.insert(job.id(), job);
+        }
+
+        for command in replayed.queued {
+            if self.queued_commands.len() < MAX_QUEUED_COMMANDS {
+                self.queued_commands.push(command);
+            }
+        }
+
+        self.journal = Some(journal);
+
+        Ok(num_jobs)
+    }
+
+    fn journal_put(&mut self, job: &Job<L>) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.put(job);
+        }
+    }
+
+    fn journal_remove(&mut self, id: &Uuid) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.remove::<L>(id);
+        }
+    }
+
+    ///
+    /// Return a `Synced` that resolves once every change made to this board
+    /// so far is in its journal on disk. Take this while holding the board
+    /// lock, but wait on it after releasing the lock.
+    ///
+    pub(crate) fn journal_synced(&self) -> Synced {
+        match self.journal.as_ref() {
+            Some(journal) => journal.synced(),
+            None => Synced::none(),
         }
     }
 
@@ -360,6 +459,14 @@ impl<L: Domain> Board<L> {
             }
         }
 
+        if state != JobAddState::Unchanged {
+            self.journal_put(&job);
+
+            if job.is_finished() {
+                crate::jobhistory::record_if_sent(&job);
+            }
+        }
+
         // if this is a new job then check for any duplicates
         if state == JobAddState::Added && job.is_pending() {
             // do through all of the existing jobs to see if there are
@@ -404,6 +511,7 @@ impl<L: Domain> Board<L> {
                         };
 
                         self.jobs.insert(errored_job.id(), errored_job.clone());
+                        self.journal_put(&errored_job);
                         return Ok((errored_job, JobAddState::Added));
                     }
 
@@ -429,6 +537,7 @@ impl<L: Domain> Board<L> {
                         };
 
                         self.jobs.insert(errored_job.id(), errored_job.clone());
+                        self.journal_put(&errored_job);
                         return Ok((errored_job, JobAddState::Added));
                     }
 
@@ -445,6 +554,7 @@ impl<L: Domain> Board<L> {
 
                     // we now need to update this job to be a duplicate
                     self.jobs.insert(duplicate.id(), duplicate.clone());
+                    self.journal_put(&duplicate);
 
                     // now record this as a duplicate for the original's ID
                     self.duplicates.entry(*id).or_default().push(duplicate.id());
@@ -504,6 +614,10 @@ impl<L: Domain> Board<L> {
                                     }
                                 }
                             };
+
+                            if let Some(journal) = self.journal.as_mut() {
+                                journal.put(duplicate_job);
+                            }
                         }
 
                         // notify any listeners for the duplicate job
@@ -545,6 +659,10 @@ impl<L: Domain> Board<L> {
 
         let removed = self.jobs.remove(&job.id()).is_some();
 
+        if removed {
+            self.journal_remove(&job.id());
+        }
+
         // we also need to wake up any waiters for this job and
         // remove any duplicates
         if let Some(listeners) = self.waiters.remove(&job.id()) {
@@ -556,6 +674,8 @@ impl<L: Domain> Board<L> {
         if let Some(duplicate_ids) = self.duplicates.remove(&job.id()) {
             for duplicate_id in duplicate_ids {
                 if let Some(mut duplicate_job) = self.jobs.remove(&duplicate_id) {
+                    self.journal_remove(&duplicate_id);
+
                     if !duplicate_job.is_finished() {
                         duplicate_job = match duplicate_job.copy_result_from(job) {
                             Ok(dup) => dup,
@@ -626,7 +746,10 @@ impl<L: Domain> Board<L> {
         // remove the job from the main board as it never made it
         // to the destination
         if let Some(job_id) = command.job_id() {
-            self.jobs.remove(&job_id);
+            if self.jobs.remove(&job_id).is_some() {
+                self.journal_remove(&job_id);
+            }
+
             if self.queued_commands.len() >= MAX_QUEUED_COMMANDS {
                 tracing::warn!(
                     "Not queueing another command for agent {}: {} are already queued \
@@ -635,6 +758,10 @@ impl<L: Domain> Board<L> {
                     self.queued_commands.len()
                 );
             } else {
+                if let Some(journal) = self.journal.as_mut() {
+                    journal.queue(&command);
+                }
+
                 self.queued_commands.push(command);
             }
         } else {
@@ -649,6 +776,13 @@ impl<L: Domain> Board<L> {
     pub fn take_queued(&mut self) -> Vec<ControlCommand<L>> {
         let mut queued_commands = Vec::new();
         std::mem::swap(&mut queued_commands, &mut self.queued_commands);
+
+        if !queued_commands.is_empty() {
+            if let Some(journal) = self.journal.as_mut() {
+                journal.take_queued::<L>();
+            }
+        }
+
         queued_commands
     }
 
@@ -720,6 +854,8 @@ impl<L: Domain> Board<L> {
                 },
             };
 
+            crate::jobhistory::record_if_sent(&job);
+
             // Add to the list of errored jobs to return
             errored_jobs.push(job.clone());
 
@@ -762,11 +898,23 @@ impl<L: Domain> Board<L> {
                         }
                     }
 
-                    self.jobs.remove(&duplicate_id);
+                    if self.jobs.remove(&duplicate_id).is_some() {
+                        self.journal_remove(&duplicate_id);
+                    }
                 }
             }
 
-            self.jobs.remove(&job.id());
+            if self.jobs.remove(&job.id()).is_some() {
+                self.journal_remove(&job.id());
+            }
+        }
+
+        // Expired queued commands are not journalled one by one, so this is
+        // also where the journal is brought back down to the size of the board
+        if let Some(journal) = self.journal.as_mut() {
+            if journal.needs_compaction(self.jobs.len() + self.queued_commands.len()) {
+                journal.compact(self.jobs.values(), self.queued_commands.iter());
+            }
         }
 
         // Return the errored jobs so the caller can send updates back upstream
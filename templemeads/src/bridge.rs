@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT
 
 use crate::agent;
+use crate::audit::{self, AuditEvent};
 use crate::command::Command;
 use crate::destination::Destination;
 use crate::domain::Domain;
@@ -126,7 +127,9 @@ pub async fn run<L: Domain>(command: &str) -> Result<Job<L>, Error> {
                 }
 
                 // send the job straight to the portal
-                return job.put(&portal).await;
+                let job = job.put(&portal).await?;
+                audit::record(AuditEvent::Forwarded, &portal, &job);
+                return Ok(job);
             } else if job.destination().first() != portal.name() {
                 tracing::error!(
                     "Job destination does not match portal name: {} != {}",
@@ -151,7 +154,10 @@ pub async fn run<L: Domain>(command: &str) -> Result<Job<L>, Error> {
             // e.g. 1 minute
             let job = job.set_lifetime(chrono::Duration::minutes(5));
 
-            Ok(job.put(&portal).await?)
+            let job = job.put(&portal).await?;
+            audit::record(AuditEvent::Forwarded, &portal, &job);
+
+            Ok(job)
         }
         None => {
             tracing::error!("No portal agent found");
@@ -11,6 +11,7 @@ use crate::domain::Domain;
 use crate::error::Error;
 use crate::health::collect_health;
 use crate::job::Job;
+use crate::jobhistory::{self, HistoryQuery, JobRecord};
 use crate::notification::Notification;
 use crate::notificationstate;
 use crate::portal_identifier::PortalIdentifier;
@@ -18,9 +19,9 @@ use crate::portal_identifier::PortalIdentifier;
 use anyhow::{Context, Result};
 use axum::{
     body::Bytes,
-    extract::{ConnectInfo, Json, Request, State},
+    extract::{ConnectInfo, Json, Query, Request, State},
     http::header::HeaderMap,
-    http::{HeaderValue, StatusCode},
+    http::{HeaderValue, StatusCode, Uri},
     middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::{get, post},
@@ -1261,6 +1262,68 @@ async fn status<L: Domain>(
     }
 }
 
+//
+// Struct to represent the requests to the 'export' endpoint
+//
+#[derive(Deserialize, Debug)]
+struct ExportRequest {
+    job: Uuid,
+    format: String,
+    #[serde(default)]
+    site: Option<String>,
+}
+
+///
+/// The 'export' endpoint for the web API. This will return the result
+/// of the requested (completed) Job exported in the requested format,
+/// e.g. a usage report as CSV, for systems that cannot read the result
+/// as OpenPortal JSON
+///
+#[tracing::instrument(skip_all)]
+async fn export<L: Domain>(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, AppError> {
+    verify_headers(&state, &headers, "post", "export", &body).await?;
+
+    let payload: ExportRequest = serde_json::from_slice(&body)?;
+
+    tracing::debug!("Export request for job: {:?}", payload);
+
+    let job = match bridge_status::<L>(&payload.job).await {
+        Ok(job) => job,
+        Err(e) => {
+            tracing::error!("Error getting status: {:?}", e);
+            return Err(AppError(e.into(), None));
+        }
+    };
+
+    if !job.is_finished() || job.is_error() {
+        return Err(AppError(
+            anyhow::anyhow!("Job {} is not complete, so cannot be exported", job.id()),
+            Some(StatusCode::BAD_REQUEST),
+        ));
+    }
+
+    let result_type = job.result_type()?;
+    let result = job.result_json()?;
+
+    match L::export_result(
+        &result_type,
+        &result,
+        &payload.format,
+        payload.site.as_deref(),
+    ) {
+        Ok((media_type, content)) => Ok(Json(json!({
+            "format": payload.format,
+            "media_type": media_type,
+            "content": content,
+        }))),
+        Err(e) => Err(AppError(e.into(), Some(StatusCode::BAD_REQUEST))),
+    }
+}
+
 ///
 /// The 'fetch_jobs' endpoint for the web API. This will return a list
 /// of all of the jobs that OpenPortal has sent to us that we need
@@ -1389,6 +1452,61 @@ async fn send_result<L: Domain>(
     }
 }
 
+///
+/// Return the function name that a GET request with the passed query string
+/// must have been signed over: `function?query`, with the query exactly as it
+/// was sent, less the `openportal-version` parameter every client appends.
+///
+/// A GET has no body, so this is what puts its filters under the signature -
+/// otherwise anyone able to alter the request in flight could change what a
+/// correctly-signed query asks for.
+///
+fn signed_get_function(function: &str, query: Option<&str>) -> String {
+    let query = query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !pair.starts_with("openportal-version="))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    match query.is_empty() {
+        true => function.to_string(),
+        false => format!("{}?{}", function, query),
+    }
+}
+
+///
+/// The 'jobs/history' endpoint for the web API. This returns the archived
+/// summaries of finished jobs, newest first, optionally filtered by
+/// `since` (RFC 3339), `project` (`project.portal`), `state` and `limit`.
+///
+#[tracing::instrument(skip_all)]
+async fn job_history(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    uri: Uri,
+) -> Result<Json<Vec<JobRecord>>, AppError> {
+    let function = signed_get_function("jobs/history", uri.query());
+    verify_headers(&state, &headers, "get", &function, &[]).await?;
+
+    let Query(query) = Query::<HistoryQuery>::try_from_uri(&uri).map_err(|e| {
+        AppError(
+            anyhow::anyhow!("Invalid job history query: {}", e),
+            Some(StatusCode::BAD_REQUEST),
+        )
+    })?;
+
+    tracing::debug!("Job history request: {:?}", query);
+
+    match jobhistory::query(&query) {
+        Ok(records) => Ok(Json(records)),
+        Err(e) => {
+            tracing::error!("Error querying job history: {:?}", e);
+            Err(AppError(e.into(), Some(StatusCode::SERVICE_UNAVAILABLE)))
+        }
+    }
+}
+
 #[allow(dead_code)]
 const PORTAL_WAIT_TIME: u64 = 5; // seconds
 
@@ -1683,9 +1801,11 @@ pub async fn spawn<L: Domain>(config: Config) -> Result<(), Error> {
         .route("/run", post(run::<L>))
         .route("/notify", post(notify::<L>))
         .route("/status", post(status::<L>))
+        .route("/export", post(export::<L>))
         .route("/fetch_job", post(fetch_job::<L>))
         .route("/fetch_jobs", get(fetch_jobs::<L>))
         .route("/fetch_notification", post(fetch_notification::<L>))
+        .route("/jobs/history", get(job_history))
         .route("/get_portal", get(get_portal))
         .route("/send_result", post(send_result::<L>))
         .route("/sync_offerings", post(sync_offerings::<L>))
@@ -1760,6 +1880,24 @@ where
 mod tests {
     use super::*;
 
+    /// The filters of a GET are signed as part of the function name, less
+    /// the version parameter that every client appends.
+    #[test]
+    fn test_signed_get_function_covers_the_query() {
+        assert_eq!(signed_get_function("jobs/history", None), "jobs/history");
+        assert_eq!(
+            signed_get_function("jobs/history", Some("openportal-version=0.1")),
+            "jobs/history"
+        );
+        assert_eq!(
+            signed_get_function(
+                "jobs/history",
+                Some("project=proj.portal&state=error&openportal-version=0.1")
+            ),
+            "jobs/history?project=proj.portal&state=error"
+        );
+    }
+
     /// `origin` names an agent inside the network. Every job served to the
     /// connected portal goes through `outbound`, which must strip it - while
     /// leaving the kind and message, which are what the portal acts on.
@@ -3,14 +3,18 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 use tokio::sync::oneshot;
 use url::Url;
 use uuid::Uuid;
 
+use crate::agent::Peer;
 use crate::board::{Listener, Waiter};
 use crate::domain::Domain;
 use crate::error::Error;
 use crate::job::Job;
+use crate::joberror::{kind, JobError};
+use crate::journal::{journal_path, Journal};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(bound = "")]
@@ -24,6 +28,10 @@ pub struct BridgeBoard<L: Domain> {
     // do not serialise or clone the waiters
     #[serde(skip)]
     waiters: HashMap<Uuid, Vec<Listener<L>>>,
+
+    // the on-disk journal of this board, if it is durable
+    #[serde(skip)]
+    journal: Option<Journal>,
 }
 
 impl<L: Domain> Clone for BridgeBoard<L> {
@@ -34,6 +42,7 @@ impl<L: Domain> Clone for BridgeBoard<L> {
             signal_url: self.signal_url.clone(),
             notification_url: self.notification_url.clone(),
             waiters: HashMap::new(),
+            journal: None,
         }
     }
 }
@@ -51,7 +60,51 @@ impl<L: Domain> BridgeBoard<L> {
             signal_url: None,
             notification_url: None,
             waiters: HashMap::new(),
+            journal: None,
+        }
+    }
+
+    ///
+    /// Rebuild this board from its journal in `dir`, and journal every
+    /// change from now on. Returns the number of jobs restored.
+    ///
+    /// Finished jobs are restored as they were, so the portal can still
+    /// fetch their results. Unfinished jobs are errored as interrupted - the
+    /// call from the portal that was waiting on each of them did not survive
+    /// the restart, so nothing else would ever finish them.
+    ///
+    pub(crate) fn attach_journal(&mut self, dir: &Path) -> Result<usize, Error> {
+        let peer = Peer::new("bridge", "bridge");
+
+        let (mut journal, replayed) = Journal::open::<L>(&journal_path(dir, &peer)?, &peer)?;
+
+        let num_jobs = replayed.jobs.len();
+
+        for job in replayed.jobs {
+            let job = match job.is_finished() {
+                true => job,
+                false => match job.errored_with(JobError::new(
+                    kind::INTERRUPTED,
+                    "The bridge restarted before this job finished. It may or may \
+                     not have taken effect - check, then retry.",
+                )) {
+                    Ok(errored) => {
+                        journal.put(&errored);
+                        errored
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to mark job {} as interrupted: {}", job.id(), e);
+                        continue;
+                    }
+                },
+            };
+
+            self.jobs.insert(job.id(), job);
         }
+
+        self.journal = Some(journal);
+
+        Ok(num_jobs)
     }
 
     ///
@@ -124,6 +177,10 @@ impl<L: Domain> BridgeBoard<L> {
             None => {
                 // add the job to the board
                 self.jobs.insert(job.id(), job.clone());
+
+                if let Some(journal) = self.journal.as_mut() {
+                    journal.put(job);
+                }
             }
         }
 
@@ -143,6 +200,14 @@ impl<L: Domain> BridgeBoard<L> {
                 if job.version() > j.version() {
                     *j = job.clone();
 
+                    if let Some(journal) = self.journal.as_mut() {
+                        journal.put(job);
+                    }
+
+                    if job.is_finished() {
+                        crate::jobhistory::record(job);
+                    }
+
                     // notify any listeners that the job has been updated
                     if job.is_finished() {
                         if let Some(listeners) = self.waiters.remove(&job.id()) {
@@ -180,7 +245,24 @@ impl<L: Domain> BridgeBoard<L> {
             }
         }
 
-        let removed = self.jobs.remove(&job.id()).is_some();
+        let removed = match self.jobs.remove(&job.id()) {
+            Some(removed) => {
+                if let Some(journal) = self.journal.as_mut() {
+                    journal.remove::<L>(&job.id());
+                }
+
+                match removed.is_finished() {
+                    true => crate::jobhistory::record(&removed),
+                    false => match removed.errored("Job removed from board") {
+                        Ok(removed) => crate::jobhistory::record(&removed),
+                        Err(e) => tracing::error!("Failed to mark job as errored: {}", e),
+                    },
+                }
+
+                true
+            }
+            None => false,
+        };
 
         Ok(removed)
     }
@@ -238,7 +320,25 @@ impl<L: Domain> BridgeBoard<L> {
             .collect();
 
         for job_id in expired_jobs.iter() {
-            let _ = self.jobs.remove(job_id);
+            if let Some(job) = self.jobs.remove(job_id) {
+                match job.is_finished() {
+                    true => crate::jobhistory::record(&job),
+                    false => match job.errored("Job expired") {
+                        Ok(job) => crate::jobhistory::record(&job),
+                        Err(e) => tracing::error!("Failed to mark job as errored: {}", e),
+                    },
+                }
+            }
+        }
+
+        if let Some(journal) = self.journal.as_mut() {
+            for job_id in expired_jobs.iter() {
+                journal.remove::<L>(job_id);
+            }
+
+            if journal.needs_compaction(self.jobs.len()) {
+                journal.compact(self.jobs.values(), std::iter::empty());
+            }
         }
     }
 
@@ -8,6 +8,7 @@ use crate::error::Error;
 
 use anyhow::Result;
 use std::any::Any;
+use std::path::Path;
 use std::sync::{Arc, OnceLock};
 use tokio::sync::RwLock;
 
@@ -40,6 +41,38 @@ pub async fn get<L: Domain>() -> Result<Arc<RwLock<BridgeBoard<L>>>, Error> {
     Ok(state.board.clone())
 }
 
+///
+/// Make the bridge board durable, journalling it to `dir`, and restore
+/// it from any journal already there. Call this once at startup, before
+/// the bridge server starts accepting jobs.
+///
+pub(crate) async fn enable_journal<L: Domain>(dir: &Path) -> Result<(), Error> {
+    std::fs::create_dir_all(dir).map_err(|e| {
+        Error::InvalidConfig(format!(
+            "Could not create the bridge journal directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+
+    let board = get::<L>().await?;
+    let num_jobs = board.write().await.attach_journal(dir)?;
+
+    tracing::info!(
+        "Bridge board journal enabled in {} - restored {} job(s)",
+        dir.display(),
+        num_jobs
+    );
+
+    Ok(())
+}
+
 ///
 /// Function called in a tokio task to clean up the board
 ///
@@ -27,4 +27,7 @@ pub fn initialise_tracing() {
         "pretty" => base.with(tracing_subscriber::fmt::layer().pretty()).init(),
         _ => base.with(tracing_subscriber::fmt::layer()).init(),
     }
+
+    // export trace spans too, if asked to in the environment
+    crate::telemetry::initialise_from_env();
 }
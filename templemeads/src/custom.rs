@@ -24,6 +24,10 @@ pub async fn run<L: Domain>(config: Config, runner: AsyncRunnable<L>) -> Result<
     )
     .await?;
 
+    // restore any journalled job boards before peers can connect
+    config.enable_board_journal::<L>().await?;
+    config.enable_audit_log()?;
+
     // run the Provider OpenPortal agent
     run_with_relay::<L>(config.service()).await?;
 
@@ -661,21 +661,35 @@ pub async fn get_job_statistics() -> JobStatistics {
 /// Record a notification received by this agent (inbound from the network)
 pub async fn increment_notification_received() {
     DIAGNOSTICS.write().await.total_notifications_received += 1;
+    record_notification_metric("received", 1);
 }
 
 /// Record a notification successfully sent (delivered to next hop or web portal)
 pub async fn increment_notification_sent() {
     DIAGNOSTICS.write().await.total_notifications_sent += 1;
+    record_notification_metric("sent", 1);
 }
 
 /// Record a notification that failed to deliver after all retries
 pub async fn increment_notification_failed() {
     DIAGNOSTICS.write().await.total_notifications_failed += 1;
+    record_notification_metric("failed", 1);
 }
 
 /// Bulk-increment the failed notification counter (e.g. when clearing a full queue)
 pub async fn add_notifications_failed(count: usize) {
     DIAGNOSTICS.write().await.total_notifications_failed += count;
+    record_notification_metric("failed", count);
+}
+
+/// Mirror the notification counters into `/metrics`
+fn record_notification_metric(outcome: &str, count: usize) {
+    paddington::metrics::add(
+        "templemeads_notifications",
+        "Notifications received by, sent by, or that failed to send from this agent",
+        &[("outcome", outcome)],
+        count as f64,
+    );
 }
 
 /// Clear all diagnostics data (used during soft restart)
@@ -0,0 +1,266 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! A background thread that owns one append-only file.
+//!
+//! The board journals, the audit log and the job history are all written
+//! from async tasks, and a `write` followed by an `fsync` can block for tens
+//! of milliseconds on a busy disk - long enough to stall every other task on
+//! that runtime worker, and worse if a lock is held while it happens. Each of
+//! these files is instead handed to a `DiskWriter`, which queues lines to a
+//! dedicated thread. The thread writes whatever has queued up since its last
+//! pass, syncs once for the whole batch, and then tells anyone waiting via
+//! [`DiskWriter::synced`] that their lines are on disk.
+
+use crate::error::Error;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::sync::oneshot;
+
+/// The most operations written between two syncs, so a flood of lines
+/// still reaches the disk in bounded steps.
+const MAX_BATCH: usize = 256;
+
+#[derive(Debug)]
+enum Op {
+    /// Append this (already newline-terminated) text to the file
+    Append(String),
+
+    /// Atomically replace the whole file with this text
+    Replace(String),
+
+    /// Tell the sender once everything queued before this is on disk
+    Synced(oneshot::Sender<()>),
+}
+
+///
+/// A handle to the thread writing one file. Dropping the handle lets the
+/// thread finish whatever is queued and then exit.
+///
+#[derive(Debug)]
+pub(crate) struct DiskWriter {
+    tx: std::sync::mpsc::Sender<Op>,
+}
+
+///
+/// Resolves once the lines queued before it was created are on disk (or
+/// have failed to be written, which the writer has already logged).
+///
+#[derive(Debug)]
+pub(crate) struct Synced(Option<oneshot::Receiver<()>>);
+
+impl Synced {
+    ///
+    /// A `Synced` for when nothing was written, which resolves immediately
+    ///
+    pub(crate) fn none() -> Self {
+        Self(None)
+    }
+
+    pub(crate) async fn wait(self) {
+        if let Some(rx) = self.0 {
+            // an error only means the writer has gone, so there is
+            // nothing left to wait for
+            let _ = rx.await;
+        }
+    }
+
+    ///
+    /// Wait from outside the async runtime, e.g. in a test
+    ///
+    #[cfg(test)]
+    pub(crate) fn wait_blocking(self) {
+        if let Some(rx) = self.0 {
+            let _ = rx.blocking_recv();
+        }
+    }
+}
+
+impl DiskWriter {
+    ///
+    /// Start a thread appending to `file`, which is open at `path`. `what`
+    /// names the file in errors, e.g. "board journal". If `sync` is set,
+    /// each batch is synced to disk before waiters are told.
+    ///
+    pub(crate) fn start(
+        path: &Path,
+        file: std::fs::File,
+        what: &'static str,
+        sync: bool,
+    ) -> Result<Self, Error> {
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let mut thread = WriterThread {
+            path: path.to_path_buf(),
+            file: Some(file),
+            what,
+            sync,
+        };
+
+        std::thread::Builder::new()
+            .name(format!("{} writer", what))
+            .spawn(move || thread.run(rx))?;
+
+        Ok(Self { tx })
+    }
+
+    ///
+    /// Queue `text` to be appended to the file. Each call is one `write_all`,
+    /// so a line is either wholly in the file or (after a crash) torn at
+    /// the end of it.
+    ///
+    pub(crate) fn append(&self, text: String) {
+        self.send(Op::Append(text));
+    }
+
+    ///
+    /// Queue the file to be replaced by `contents`. This is written through
+    /// a rename, so a crash leaves either the old file or the new one.
+    ///
+    pub(crate) fn replace(&self, contents: String) {
+        self.send(Op::Replace(contents));
+    }
+
+    ///
+    /// Return a `Synced` that resolves once everything queued so far is
+    /// on disk
+    ///
+    pub(crate) fn synced(&self) -> Synced {
+        let (tx, rx) = oneshot::channel();
+        self.send(Op::Synced(tx));
+        Synced(Some(rx))
+    }
+
+    fn send(&self, op: Op) {
+        if self.tx.send(op).is_err() {
+            tracing::error!("The disk writer thread has stopped - this change is not saved");
+        }
+    }
+}
+
+struct WriterThread {
+    path: PathBuf,
+    file: Option<std::fs::File>,
+    what: &'static str,
+    sync: bool,
+}
+
+impl WriterThread {
+    fn run(&mut self, rx: std::sync::mpsc::Receiver<Op>) {
+        while let Ok(first) = rx.recv() {
+            let mut waiters = Vec::new();
+            let mut written = false;
+
+            for op in std::iter::once(first).chain(rx.try_iter().take(MAX_BATCH - 1)) {
+                match op {
+                    Op::Append(text) => {
+                        self.write(&text);
+                        written = true;
+                    }
+                    Op::Replace(contents) => {
+                        self.replace(&contents);
+                        written = true;
+                    }
+                    Op::Synced(waiter) => waiters.push(waiter),
+                }
+            }
+
+            if written && self.sync {
+                if let Some(file) = self.file.as_ref() {
+                    if let Err(e) = file.sync_data() {
+                        tracing::error!(
+                            "Could not sync the {} {}: {}",
+                            self.what,
+                            self.path.display(),
+                            e
+                        );
+                    }
+                }
+            }
+
+            for waiter in waiters {
+                let _ = waiter.send(());
+            }
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+
+        if let Err(e) = file.write_all(text.as_bytes()) {
+            tracing::error!(
+                "Could not write to the {} {}: {}",
+                self.what,
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    fn replace(&mut self, contents: &str) {
+        self.file = None;
+
+        if let Err(e) = paddington::config::write_secret_file(&self.path, contents) {
+            tracing::error!(
+                "Could not rewrite the {} {}: {}",
+                self.what,
+                self.path.display(),
+                e
+            );
+        }
+
+        match std::fs::OpenOptions::new().append(true).open(&self.path) {
+            Ok(file) => self.file = Some(file),
+            Err(e) => {
+                tracing::error!(
+                    "Could not reopen the {} {}: {}. Nothing more will be written to it.",
+                    self.what,
+                    self.path.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_are_written_in_order_and_replaced() {
+        let path = std::env::temp_dir().join(format!("diskwriter-{}.txt", uuid::Uuid::new_v4()));
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let writer = DiskWriter::start(&path, file, "test file", true)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        writer.append("one\n".to_string());
+        writer.append("two\n".to_string());
+        writer.synced().wait_blocking();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            "one\ntwo\n"
+        );
+
+        writer.replace("three\n".to_string());
+        writer.append("four\n".to_string());
+        writer.synced().wait_blocking();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            "three\nfour\n"
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -106,6 +106,28 @@ pub trait Domain: Clone + std::fmt::Debug + 'static {
         None
     }
 
+    /// Export the result of a finished job - `result` is the JSON of a
+    /// value whose type is named `result_type` - in another `format`
+    /// (e.g. `"csv"`), for systems outside the network that cannot read
+    /// this domain's JSON. `site` names the site the result describes,
+    /// for formats that need one. Returns the media type of the export
+    /// and its content.
+    ///
+    /// Default: nothing can be exported. A domain overrides this for the
+    /// result types it knows how to render - see `greatwestern`, which
+    /// exports usage reports and job records for accounting systems.
+    fn export_result(
+        result_type: &str,
+        _result: &str,
+        format: &str,
+        _site: Option<&str>,
+    ) -> Result<(String, String), Error> {
+        Err(Error::Incompatible(format!(
+            "Results of type {} cannot be exported as {}",
+            result_type, format
+        )))
+    }
+
     /// Wrap an inner `Notification` for southbound forwarding: used by a
     /// bridge agent to ask the portal to forward a notification, stripping
     /// the bridge from the path (analogous to `Job`'s `submit` instruction).
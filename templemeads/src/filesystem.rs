@@ -84,6 +84,10 @@ where
         return Ok(());
     }
 
+    // restore any journalled job boards before peers can connect
+    config.enable_board_journal::<L>().await?;
+    config.enable_audit_log()?;
+
     // run the Provider OpenPortal agent
     run_with_relay::<L>(config.service()).await?;
 
@@ -3,6 +3,7 @@
 
 use crate::agent;
 use crate::agent::{Peer, Type as AgentType};
+use crate::audit::{self, AuditEvent};
 use crate::command::Command;
 use crate::control_message::process_control_message;
 use crate::destination::Position;
@@ -18,6 +19,7 @@ use crate::portal_identifier::PortalIdentifier;
 use crate::portalroutes;
 use crate::restart;
 use crate::runnable::{default_runner, AsyncRunnable};
+use crate::telemetry;
 
 use anyhow::Result;
 use paddington::config::ServiceConfig;
@@ -29,6 +31,7 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex, OnceLock};
 use tokio::sync::RwLock;
+use tracing::Instrument;
 
 #[derive(Debug, Clone)]
 struct ServiceDetails<L: Domain> {
@@ -679,6 +682,8 @@ async fn process_command<L: Domain>(
             {
                 tracing::error!("Refusing job {}: {}", job.id(), e);
                 let job = job.errored(&e.to_string())?;
+                audit::record(AuditEvent::Refused, &peer, &job);
+                jobtiming::record_job_metrics(&job.command_name(), "refused", None);
                 let _ = job.update(&Peer::new(sender, zone)).await?;
                 return Ok(());
             }
@@ -694,6 +699,8 @@ async fn process_command<L: Domain>(
                 }
             };
 
+            audit::record(AuditEvent::Received, &peer, &job);
+
             // Keep a copy of the original job to detect if it changed
             let original_version = job.version();
 
@@ -720,7 +727,10 @@ async fn process_command<L: Domain>(
                             let peer = Peer::new(&agent, zone);
 
                             job = match job.put(&peer).await {
-                                Ok(job) => job,
+                                Ok(job) => {
+                                    audit::record(AuditEvent::Forwarded, &peer, &job);
+                                    job
+                                }
                                 Err(e) => {
                                     tracing::error!("Error putting job: {}", e);
                                     job.errored(&e.to_string())?
@@ -756,9 +766,28 @@ async fn process_command<L: Domain>(
                                 // Record job started for diagnostics
                                 diagnostics::record_job_started(&job).await;
 
-                                job = match runner(Envelope::new(recipient, sender, zone, &job))
-                                    .await
-                                {
+                                // Run the job in a span that continues the
+                                // sender's trace, and is current for the
+                                // runnable, so any jobs it puts join it too
+                                let mut span = telemetry::Span::start(
+                                    &format!("run {}", job.command_name()),
+                                    telemetry::parse_from_peer(job.traceparent()),
+                                );
+                                span.set_attribute("openportal.job.id", &job.id().to_string());
+                                span.set_attribute("openportal.agent", recipient);
+                                span.set_attribute("openportal.peer", &peer.to_string());
+
+                                let context = span.context();
+
+                                let run = runner(Envelope::new(recipient, sender, zone, &job))
+                                    .instrument(tracing::info_span!(
+                                        "job",
+                                        id = %job.id(),
+                                        trace_id = %context.trace_id(),
+                                        span_id = %context.span_id()
+                                    ));
+
+                                job = match telemetry::scope(context, run).await {
                                     Ok(job) => job,
                                     Err(e) => {
                                         tracing::error!("Error running job: {}", e);
@@ -770,6 +799,22 @@ async fn process_command<L: Domain>(
                                 let duration = start_time.elapsed();
                                 let duration_ms = duration.as_secs_f64() * 1000.0;
                                 jobtiming::record_job_time(duration_ms);
+                                jobtiming::record_job_metrics(
+                                    &job.command_name(),
+                                    &match job.is_expired() {
+                                        true => "expired".to_string(),
+                                        false => job.state().to_string(),
+                                    },
+                                    Some(duration),
+                                );
+
+                                audit::record(AuditEvent::Completed, &peer, &job);
+
+                                span.set_attribute(
+                                    "openportal.job.state",
+                                    &job.state().to_string(),
+                                );
+                                span.end(job.error_message().as_deref());
 
                                 // Record job finished for diagnostics
                                 diagnostics::record_job_finished(&job).await;
@@ -1035,7 +1080,30 @@ async fn process_command<L: Domain>(
                         notification.id()
                     );
                     let envelope = NotificationEnvelope::new(recipient, sender, zone, notification);
-                    if let Err(e) = notify_runner(envelope).await {
+
+                    let mut span = telemetry::Span::start(
+                        &format!(
+                            "notify {}",
+                            notification
+                                .event()
+                                .to_string()
+                                .split_whitespace()
+                                .next()
+                                .unwrap_or("unknown")
+                        ),
+                        telemetry::parse_from_peer(notification.traceparent()),
+                    );
+                    span.set_attribute(
+                        "openportal.notification.id",
+                        &notification.id().to_string(),
+                    );
+                    span.set_attribute("openportal.agent", recipient);
+
+                    let result = telemetry::scope(span.context(), notify_runner(envelope)).await;
+
+                    span.end(result.as_ref().err().map(|e| e.to_string()).as_deref());
+
+                    if let Err(e) = result {
                         tracing::warn!("Error in notify runner for [{}]: {}", notification.id(), e);
                     }
                 }
@@ -78,6 +78,10 @@ async fn run_instance<L: Domain>(
 
     set_verify_portal_ownership::<L>(verify_portal_ownership).await?;
 
+    // restore any journalled job boards before peers can connect
+    config.enable_board_journal::<L>().await?;
+    config.enable_audit_log()?;
+
     // run the Provider OpenPortal agent
     run_with_relay::<L>(config.service()).await?;
 
@@ -10,6 +10,7 @@ use crate::error::Error;
 use crate::joberror::JobError;
 use crate::named::NamedType;
 use crate::state;
+use crate::telemetry;
 
 use anyhow::Result;
 use chrono::serde::ts_seconds;
@@ -244,6 +245,12 @@ pub struct Job<L: Domain> {
     /// The domain's version, alongside `domain`.
     #[serde(default)]
     domain_version: Option<String>,
+    /// The W3C `traceparent` of the span that sent this Job on, so that
+    /// the agent receiving it continues the same trace - see `telemetry`.
+    /// `None` from a peer running templemeads from before this field
+    /// existed, and for a Job that was not created within a trace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    traceparent: Option<String>,
     #[serde(skip)]
     board: Option<Peer>,
 }
@@ -292,6 +299,8 @@ impl<L: Domain> Job<L> {
             forwarded_for: None,
             domain: Some(L::name().to_string()),
             domain_version: Some(L::version().to_string()),
+            // a Job created while running another continues its trace
+            traceparent: telemetry::current().map(|context| context.traceparent()),
             board: None,
         })
     }
@@ -307,6 +316,12 @@ impl<L: Domain> Job<L> {
         self.domain_version.as_deref()
     }
 
+    /// The W3C `traceparent` of the span that sent this Job on, if any -
+    /// see the field doc comment on `Job`.
+    pub fn traceparent(&self) -> Option<&str> {
+        self.traceparent.as_deref()
+    }
+
     pub fn to_json(&self) -> Result<String, Error> {
         serde_json::to_string(self).map_err(Error::SerdeJson)
     }
@@ -327,6 +342,19 @@ impl<L: Domain> Job<L> {
         self.command.instruction()
     }
 
+    ///
+    /// The command name of this Job's instruction (e.g. `add_user`),
+    /// without its arguments - a bounded label for metrics and spans
+    ///
+    pub fn command_name(&self) -> String {
+        self.instruction()
+            .to_string()
+            .split_whitespace()
+            .next()
+            .unwrap_or("unknown")
+            .to_string()
+    }
+
     pub fn expires(&self) -> &chrono::DateTime<Utc> {
         &self.expires
     }
@@ -391,6 +419,7 @@ impl<L: Domain> Job<L> {
             forwarded_for: self.forwarded_for.clone(),
             domain: self.domain.clone(),
             domain_version: self.domain_version.clone(),
+            traceparent: self.traceparent.clone(),
             board: self.board.clone(),
         }
     }
@@ -473,10 +502,21 @@ impl<L: Domain> Job<L> {
             forwarded_for: self.forwarded_for.clone(),
             domain: self.domain.clone(),
             domain_version: self.domain_version.clone(),
+            traceparent: self.traceparent.clone(),
             board: self.board.clone(),
         }
     }
 
+    ///
+    /// Return this job placed on the board for `agent`. The board is not
+    /// serialised, so this is how a job read back from a board's journal
+    /// is put back where it was.
+    ///
+    pub(crate) fn on_board(mut self, agent: &Peer) -> Self {
+        self.board = Some(agent.clone());
+        self
+    }
+
     pub fn assert_is_for_board(&self, agent: &Peer) -> Result<(), Error> {
         if self.is_expired() {
             return Err(Error::Expired(
@@ -531,6 +571,7 @@ impl<L: Domain> Job<L> {
                 forwarded_for: self.forwarded_for.clone(),
                 domain: self.domain.clone(),
                 domain_version: self.domain_version.clone(),
+                traceparent: self.traceparent.clone(),
                 board: self.board.clone(),
             }),
             Status::Pending => Ok(self.clone()),
@@ -577,6 +618,7 @@ impl<L: Domain> Job<L> {
                 forwarded_for: self.forwarded_for.clone(),
                 domain: self.domain.clone(),
                 domain_version: self.domain_version.clone(),
+                traceparent: self.traceparent.clone(),
                 board: self.board.clone(),
             }),
             _ => Err(Error::InvalidState(
@@ -601,6 +643,7 @@ impl<L: Domain> Job<L> {
                 forwarded_for: self.forwarded_for.clone(),
                 domain: self.domain.clone(),
                 domain_version: self.domain_version.clone(),
+                traceparent: self.traceparent.clone(),
                 board: self.board.clone(),
             }),
             _ => Err(Error::InvalidState(
@@ -632,6 +675,7 @@ impl<L: Domain> Job<L> {
                 forwarded_for: self.forwarded_for.clone(),
                 domain: self.domain.clone(),
                 domain_version: self.domain_version.clone(),
+                traceparent: self.traceparent.clone(),
                 board: self.board.clone(),
             }),
             _ => Err(Error::InvalidState(
@@ -656,6 +700,7 @@ impl<L: Domain> Job<L> {
                 forwarded_for: self.forwarded_for.clone(),
                 domain: self.domain.clone(),
                 domain_version: self.domain_version.clone(),
+                traceparent: self.traceparent.clone(),
                 board: self.board.clone(),
             }),
             _ => Err(Error::InvalidState(
@@ -684,6 +729,7 @@ impl<L: Domain> Job<L> {
                 forwarded_for: self.forwarded_for.clone(),
                 domain: self.domain.clone(),
                 domain_version: self.domain_version.clone(),
+                traceparent: self.traceparent.clone(),
                 board: self.board.clone(),
             }),
             _ => Err(Error::InvalidState(
@@ -726,6 +772,7 @@ impl<L: Domain> Job<L> {
                 forwarded_for: self.forwarded_for.clone(),
                 domain: self.domain.clone(),
                 domain_version: self.domain_version.clone(),
+                traceparent: self.traceparent.clone(),
                 board: self.board.clone(),
             }),
             _ => Err(Error::InvalidState(
@@ -917,7 +964,7 @@ impl<L: Domain> Job<L> {
         };
 
         // in a scope so we drop the lock asap
-        {
+        let synced = {
             // get the mutable board from the Arc<RwLock> board - this is the
             // blocking operation
             let mut board = board.write().await;
@@ -927,18 +974,60 @@ impl<L: Domain> Job<L> {
             job.board = Some(peer.clone());
 
             (job, _) = board.add(&job)?;
-        }
+
+            board.journal_synced()
+        };
+
+        // the journal is synced outside the lock, so other jobs can use the
+        // board while this one waits for the disk
+        synced.wait().await;
 
         Ok(job)
     }
 
+    ///
+    /// The span that an operation on this Job belongs in - a child of the
+    /// task's current span, or else of the span that sent this Job to us
+    ///
+    fn start_span(&self, operation: &str, peer: Option<&Peer>) -> telemetry::Span {
+        let parent = telemetry::current()
+            .or_else(|| telemetry::parse_from_peer(self.traceparent.as_deref()));
+
+        let mut span =
+            telemetry::Span::start(&format!("{} {}", operation, self.command_name()), parent);
+
+        span.set_attribute("openportal.job.id", &self.id.to_string());
+
+        if let Some(peer) = peer {
+            span.set_attribute("openportal.peer", &peer.to_string());
+        }
+
+        span
+    }
+
     pub async fn put(&self, peer: &Peer) -> Result<Job<L>, Error> {
+        let span = self.start_span("put", Some(peer));
+
+        let result = self.put_in_span(peer, span.context()).await;
+
+        span.end(result.as_ref().err().map(|e| e.to_string()).as_deref());
+
+        result
+    }
+
+    async fn put_in_span(
+        &self,
+        peer: &Peer,
+        context: telemetry::TraceContext,
+    ) -> Result<Job<L>, Error> {
         tracing::debug!("Put {} : {}", self.destination(), self.instruction());
 
         self.assert_is_not_expired()?;
 
-        // transition the job to pending, recording where it was sent
+        // transition the job to pending, recording where it was sent, and
+        // the span that sent it so that the peer's spans join this trace
         let mut job = self.pending()?;
+        job.traceparent = Some(context.traceparent());
 
         // get a RwLock to the board from the shared state
         let board = match state::get::<L>(peer).await {
@@ -953,7 +1042,7 @@ impl<L: Domain> Job<L> {
         };
 
         // in a scope so we drop the lock asap
-        {
+        let synced = {
             // get the mutable board from the Arc<RwLock> board - this is the
             // blocking operation
             let mut board = board.write().await;
@@ -977,7 +1066,13 @@ impl<L: Domain> Job<L> {
 
                 return Ok(job);
             }
-        }
+
+            board.journal_synced()
+        };
+
+        // make sure the job is in the journal before the peer hears of it,
+        // waiting outside the lock so the board is not held over disk I/O
+        synced.wait().await;
 
         // now send it to the agent for processing
         match ControlCommand::put(&job).send_to(peer).await {
@@ -1057,7 +1152,7 @@ impl<L: Domain> Job<L> {
         };
 
         // in a scope so we drop the lock asap
-        {
+        let synced = {
             // get the mutable board from the Arc<RwLock> board - this is the
             // blocking operation
             let mut board = board.write().await;
@@ -1076,7 +1171,13 @@ impl<L: Domain> Job<L> {
                 // (the job has already been sent)
                 return Ok(job);
             }
-        }
+
+            board.journal_synced()
+        };
+
+        // make sure the job is in the journal before the peer hears of it,
+        // waiting outside the lock so the board is not held over disk I/O
+        synced.wait().await;
 
         // now send it to the agent for processing
         match ControlCommand::update(&job).send_to(peer).await {
@@ -1192,7 +1293,7 @@ impl<L: Domain> Job<L> {
                 }
             };
 
-            {
+            let synced = {
                 let mut board = board.write().await;
                 job.board = Some(upstream_peer.clone());
 
@@ -1207,7 +1308,11 @@ impl<L: Domain> Job<L> {
                         );
                     }
                 };
-            }
+
+                board.journal_synced()
+            };
+
+            synced.wait().await;
 
             // Send the update to the upstream agent
             // The message should appear to come from the hosting agent
@@ -1289,7 +1394,7 @@ impl<L: Domain> Job<L> {
         };
 
         // in a scope so we drop the lock asap
-        {
+        let synced = {
             // get the mutable board from the Arc<RwLock> board - this is the
             // blocking operation
             let mut board = board.write().await;
@@ -1305,7 +1410,13 @@ impl<L: Domain> Job<L> {
                 // (the job has already been sent)
                 return Ok(job);
             }
-        }
+
+            board.journal_synced()
+        };
+
+        // make sure the removal is in the journal before the peer hears of
+        // it, waiting outside the lock so the board is not held over disk I/O
+        synced.wait().await;
 
         // now send it to the agent for processing
         match ControlCommand::delete(&job).send_to(peer).await {
@@ -1367,6 +1478,16 @@ impl<L: Domain> Job<L> {
     }
 
     pub async fn wait(&self) -> Result<Job<L>, Error> {
+        let span = self.start_span("wait", self.board.as_ref());
+
+        let result = self.wait_in_span().await;
+
+        span.end(result.as_ref().err().map(|e| e.to_string()).as_deref());
+
+        result
+    }
+
+    async fn wait_in_span(&self) -> Result<Job<L>, Error> {
         let mut job = self._wait().await?;
 
         // if the job is still running, then we need to wait for it to finish
@@ -46,6 +46,11 @@ pub mod kind {
     /// more specific kind.
     pub const RUN: &str = "run";
 
+    /// The agent handling the job restarted before the job finished, so it
+    /// cannot say whether it took effect. Only raised when boards are journalled
+    /// and replayed - see `crate::journal`.
+    pub const INTERRUPTED: &str = "interrupted";
+
     /// A failure with no information about it at all. The honest answer when
     /// an older peer sent prose that nothing recognises.
     pub const UNKNOWN: &str = "unknown";
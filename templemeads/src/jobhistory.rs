@@ -0,0 +1,537 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! A bounded, on-disk archive of the jobs that have passed through the bridge.
+//!
+//! A job leaves the `BridgeBoard` (and the bridge's board with the portal)
+//! once it expires, and after that the only trace of it used to be the
+//! diagnostics ring buffers - which keep failures and slow jobs, not what was
+//! asked and by whom. When an archive is configured, every job the bridge
+//! exchanges with the portal is recorded here once it finishes (or expires
+//! unfinished), so support staff can later ask "what happened to this user's
+//! account request last Tuesday?" via `GET /jobs/history`.
+//!
+//! Only a summary of each job is kept - never its result, which can carry
+//! whole usage reports. The archive is bounded both by number of records and
+//! by age, and, like the board journals, is a best-effort record: a failure to
+//! write it is logged and the bridge carries on. Records are kept in memory
+//! and written by a [`DiskWriter`] thread, so archiving a job never blocks
+//! the task that finished it on disk I/O.
+
+use crate::diskwriter::DiskWriter;
+use crate::domain::Domain;
+use crate::error::Error;
+use crate::job::Job;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Default maximum number of records kept in the archive
+pub const DEFAULT_MAX_RECORDS: usize = 100_000;
+
+/// Default maximum age (in days) of a record in the archive
+pub const DEFAULT_MAX_AGE_DAYS: u32 = 90;
+
+/// The most records a single query will return, however many match
+pub const MAX_QUERY_RESULTS: usize = 1_000;
+
+/// The archive file is rewritten down to its live records once it holds more
+/// than this many times as many lines as there are live records.
+const COMPACTION_RATIO: usize = 2;
+
+/// Name of the archive file within the configured directory
+const ARCHIVE_FILE: &str = "jobs.jsonl";
+
+///
+/// Configuration of the job history archive
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Directory holding the archive
+    pub dir: PathBuf,
+
+    /// Maximum number of records to keep - the oldest are dropped first
+    #[serde(default = "default_max_records")]
+    pub max_records: usize,
+
+    /// Maximum age of a record, in days
+    #[serde(default = "default_max_age_days")]
+    pub max_age_days: u32,
+}
+
+fn default_max_records() -> usize {
+    DEFAULT_MAX_RECORDS
+}
+
+fn default_max_age_days() -> u32 {
+    DEFAULT_MAX_AGE_DAYS
+}
+
+impl Config {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            max_records: DEFAULT_MAX_RECORDS,
+            max_age_days: DEFAULT_MAX_AGE_DAYS,
+        }
+    }
+}
+
+/// Summary of a job that has passed through the bridge
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct JobRecord {
+    /// Job ID
+    pub id: Uuid,
+    /// Job destination
+    pub destination: String,
+    /// Job instruction
+    pub instruction: String,
+    /// Final state of the job (e.g. "complete" or "error")
+    pub state: String,
+    /// Stable kind of the failure, if the job errored
+    pub error_kind: Option<String>,
+    /// Failure message, if the job errored
+    pub error_message: Option<String>,
+    /// When the job was created
+    pub created: DateTime<Utc>,
+    /// When the job last changed (i.e. when it finished)
+    pub finished: DateTime<Utc>,
+    /// How long the job took, in milliseconds
+    pub duration_ms: i64,
+}
+
+impl JobRecord {
+    pub fn from_job<L: Domain>(job: &Job<L>) -> Self {
+        let error = job.error_or_infer();
+
+        Self {
+            id: job.id(),
+            destination: job.destination().to_string(),
+            instruction: job.instruction().to_string(),
+            state: job.state().to_string(),
+            error_kind: error.as_ref().map(|e| e.kind().to_string()),
+            error_message: error.as_ref().map(|e| e.message().to_string()),
+            created: job.created(),
+            finished: job.changed(),
+            duration_ms: (job.changed() - job.created()).num_milliseconds(),
+        }
+    }
+
+    ///
+    /// Return whether this job's instruction names the passed project. This
+    /// matches the project identifier itself (`project.portal`) and any user
+    /// in that project (`user.project.portal`), wherever they appear in the
+    /// instruction - including inside a `submit` from the portal.
+    ///
+    pub fn mentions_project(&self, project: &str) -> bool {
+        let project = project.trim();
+
+        if project.is_empty() {
+            return false;
+        }
+
+        self.instruction
+            .split(|c: char| c.is_whitespace() || matches!(c, '"' | ',' | '[' | ']' | '{' | '}'))
+            .any(|token| {
+                token == project
+                    || token
+                        .strip_suffix(project)
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            })
+    }
+
+    fn matches(&self, query: &HistoryQuery) -> bool {
+        if let Some(since) = query.since {
+            if self.finished < since {
+                return false;
+            }
+        }
+
+        if let Some(state) = &query.state {
+            if !self.state.eq_ignore_ascii_case(state.trim()) {
+                return false;
+            }
+        }
+
+        if let Some(project) = &query.project {
+            if !self.mentions_project(project) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+///
+/// Filters for a query of the archive. Every filter is optional, and all
+/// of those given must match.
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistoryQuery {
+    /// Only jobs that finished at or after this time
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only jobs whose instruction names this project (`project.portal`)
+    #[serde(default)]
+    pub project: Option<String>,
+
+    /// Only jobs that ended in this state (e.g. `error`)
+    #[serde(default)]
+    pub state: Option<String>,
+
+    /// Return at most this many records (capped at [`MAX_QUERY_RESULTS`])
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Archive {
+    path: PathBuf,
+    writer: Option<DiskWriter>,
+    records: VecDeque<JobRecord>,
+    ids: HashSet<Uuid>,
+    lines: usize,
+    max_records: usize,
+    max_age: chrono::Duration,
+}
+
+static ARCHIVE: OnceLock<Mutex<Archive>> = OnceLock::new();
+
+/// The name of this agent, used to recognise the jobs it sent itself
+static MY_NAME: OnceLock<String> = OnceLock::new();
+
+impl Archive {
+    fn open(config: &Config) -> Result<Self, Error> {
+        let path = config.dir.join(ARCHIVE_FILE);
+
+        let mut archive = Self {
+            path,
+            writer: None,
+            records: VecDeque::new(),
+            ids: HashSet::new(),
+            lines: 0,
+            max_records: config.max_records.max(1),
+            max_age: chrono::Duration::days(i64::from(config.max_age_days.max(1))),
+        };
+
+        match std::fs::File::open(&archive.path) {
+            Ok(file) => {
+                for line in std::io::BufReader::new(file).lines() {
+                    let line = line?;
+
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+
+                    archive.lines += 1;
+
+                    // a torn final line from a crash is skipped, not fatal
+                    match serde_json::from_str::<JobRecord>(&line) {
+                        Ok(record) => {
+                            if archive.ids.insert(record.id) {
+                                archive.records.push_back(record);
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                "Skipping unreadable record in job history {}: {}",
+                                archive.path.display(),
+                                e
+                            );
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(Error::IO(e)),
+        }
+
+        archive.prune();
+
+        // Records name users and projects, so the archive is owner-only, and
+        // written through a rename so a crash leaves the old or the new file.
+        // The archive is not in use yet, so this first rewrite is done here.
+        paddington::config::write_secret_file(&archive.path, &archive.contents()?)?;
+        archive.lines = archive.records.len();
+
+        let file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&archive.path)?;
+
+        archive.writer = Some(DiskWriter::start(
+            &archive.path,
+            file,
+            "job history",
+            false,
+        )?);
+
+        Ok(archive)
+    }
+
+    /// Drop records beyond the size and age bounds, oldest first
+    fn prune(&mut self) {
+        let cutoff = Utc::now() - self.max_age;
+
+        while let Some(oldest) = self.records.front() {
+            if self.records.len() > self.max_records || oldest.finished < cutoff {
+                if let Some(oldest) = self.records.pop_front() {
+                    self.ids.remove(&oldest.id);
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn push(&mut self, record: JobRecord) {
+        if !self.ids.insert(record.id) {
+            return;
+        }
+
+        match serde_json::to_string(&record) {
+            Ok(line) => self.append(line),
+            Err(e) => tracing::error!("Could not serialise job record {}: {}", record.id, e),
+        }
+
+        self.records.push_back(record);
+        self.prune();
+
+        if self.lines > self.records.len().max(1).saturating_mul(COMPACTION_RATIO)
+            && self.lines > self.max_records
+        {
+            self.compact();
+        }
+    }
+
+    fn append(&mut self, line: String) {
+        let Some(writer) = self.writer.as_ref() else {
+            return;
+        };
+
+        writer.append(line + "\n");
+        self.lines += 1;
+    }
+
+    /// Replace the file with one holding exactly the live records
+    fn compact(&mut self) {
+        let Some(writer) = self.writer.as_ref() else {
+            return;
+        };
+
+        match self.contents() {
+            Ok(contents) => {
+                writer.replace(contents);
+                self.lines = self.records.len();
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Could not compact job history {}: {}",
+                    self.path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    fn contents(&self) -> Result<String, Error> {
+        let mut contents = String::new();
+
+        for record in self.records.iter() {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+
+        Ok(contents)
+    }
+
+    fn query(&self, query: &HistoryQuery) -> Vec<JobRecord> {
+        let limit = query
+            .limit
+            .unwrap_or(MAX_QUERY_RESULTS)
+            .clamp(1, MAX_QUERY_RESULTS);
+
+        // newest first
+        self.records
+            .iter()
+            .rev()
+            .filter(|record| record.matches(query))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+///
+/// Start archiving jobs to the directory in `config`, loading any records
+/// already there. `my_name` is the name of this (bridge) agent. Call this
+/// once, at startup.
+///
+pub(crate) fn enable(config: &Config, my_name: &str) -> Result<(), Error> {
+    std::fs::create_dir_all(&config.dir).map_err(|e| {
+        Error::InvalidConfig(format!(
+            "Could not create the job history directory {}: {}",
+            config.dir.display(),
+            e
+        ))
+    })?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&config.dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+
+    let archive = Archive::open(config)?;
+
+    tracing::info!(
+        "Job history enabled in {} - {} record(s) kept",
+        config.dir.display(),
+        archive.records.len()
+    );
+
+    if ARCHIVE.set(Mutex::new(archive)).is_err() || MY_NAME.set(my_name.to_string()).is_err() {
+        return Err(Error::InvalidState(
+            "The job history has already been enabled".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+///
+/// Return whether or not the archive has been enabled
+///
+pub fn is_enabled() -> bool {
+    ARCHIVE.get().is_some()
+}
+
+///
+/// Archive the passed job. Does nothing if the archive is not enabled, or
+/// the job has already been archived.
+///
+pub(crate) fn record<L: Domain>(job: &Job<L>) {
+    let Some(archive) = ARCHIVE.get() else {
+        return;
+    };
+
+    match archive.lock() {
+        Ok(mut archive) => archive.push(JobRecord::from_job(job)),
+        Err(e) => tracing::error!("Failed to lock the job history: {}", e),
+    }
+}
+
+///
+/// Archive the passed job if this agent sent it, i.e. it is the first hop
+/// of the job's destination. This is how the jobs the bridge submits to
+/// the portal are told apart from the ones the portal sends to the bridge,
+/// which are archived from the `BridgeBoard` instead.
+///
+pub(crate) fn record_if_sent<L: Domain>(job: &Job<L>) {
+    let Some(my_name) = MY_NAME.get() else {
+        return;
+    };
+
+    if job.destination().first() == *my_name {
+        record(job);
+    }
+}
+
+///
+/// Return the archived jobs that match the passed query, newest first
+///
+pub fn query(query: &HistoryQuery) -> Result<Vec<JobRecord>, Error> {
+    let Some(archive) = ARCHIVE.get() else {
+        return Err(Error::Unavailable(
+            "The job history is not enabled on this bridge".to_string(),
+        ));
+    };
+
+    match archive.lock() {
+        Ok(archive) => Ok(archive.query(query)),
+        Err(e) => Err(Error::Bug(format!("Failed to lock the job history: {}", e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_domain::TestDomain;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jobhistory-{}-{}", name, Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap_or_else(|e| unreachable!("mkdir: {:?}", e));
+        dir
+    }
+
+    fn finished(command: &str) -> Job<TestDomain> {
+        Job::<TestDomain>::parse(command, false)
+            .unwrap_or_else(|e| unreachable!("parse: {:?}", e))
+            .pending()
+            .unwrap_or_else(|e| unreachable!("pending: {:?}", e))
+            .errored("no")
+            .unwrap_or_else(|e| unreachable!("errored: {:?}", e))
+    }
+
+    #[test]
+    fn test_mentions_project() {
+        let job = finished("bridge.portal submit portal.cluster add_user alice.proj.portal");
+        let record = JobRecord::from_job(&job);
+
+        assert!(record.mentions_project("proj.portal"));
+        assert!(!record.mentions_project("roj.portal"));
+        assert!(!record.mentions_project("other.portal"));
+        assert!(!record.mentions_project(""));
+    }
+
+    #[test]
+    fn test_archive_is_bounded_and_survives_reopen() {
+        let dir = temp_dir("bounded");
+
+        let config = Config {
+            dir: dir.clone(),
+            max_records: 3,
+            max_age_days: 1,
+        };
+
+        let mut archive = Archive::open(&config).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let jobs: Vec<_> = (0..5)
+            .map(|i| finished(&format!("bridge.portal do_thing {}", i)))
+            .collect();
+
+        for job in jobs.iter() {
+            archive.push(JobRecord::from_job(job));
+            // archiving the same job twice keeps one record
+            archive.push(JobRecord::from_job(job));
+        }
+
+        let all = archive.query(&HistoryQuery::default());
+        assert_eq!(all.len(), 3);
+        assert_eq!(all.first().map(|r| r.id), jobs.last().map(|j| j.id()));
+
+        if let Some(writer) = archive.writer.as_ref() {
+            writer.synced().wait_blocking();
+        }
+        drop(archive);
+
+        let archive = Archive::open(&config).unwrap_or_else(|e| unreachable!("{:?}", e));
+        assert_eq!(archive.query(&HistoryQuery::default()), all);
+
+        let none = archive.query(&HistoryQuery {
+            state: Some("complete".to_string()),
+            ..Default::default()
+        });
+        assert!(none.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -104,3 +104,25 @@ pub fn get_stats() -> JobTimingStats {
         }
     }
 }
+
+/// Record a job that this agent has finished running (or refused to run)
+/// in the `/metrics` counters, and its run time in the latency histogram.
+///
+/// `instruction` is only the instruction's command name (e.g. `add_user`),
+/// never its arguments, so the number of series stays bounded.
+pub fn record_job_metrics(instruction: &str, state: &str, duration: Option<std::time::Duration>) {
+    paddington::metrics::increment(
+        "templemeads_jobs",
+        "Jobs this agent has run or refused, by instruction and final state",
+        &[("instruction", instruction), ("state", state)],
+    );
+
+    if let Some(duration) = duration {
+        paddington::metrics::observe(
+            "templemeads_job_duration_seconds",
+            "How long this agent took to run a job, by instruction",
+            &[("instruction", instruction)],
+            duration,
+        );
+    }
+}
@@ -0,0 +1,494 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! An append-only, on-disk journal behind a job board.
+//!
+//! Boards live in memory, so a crash or a hard restart used to lose every
+//! pending and running job, and the agent upstream only found out when each one
+//! timed out. When a journal directory is configured, every change a board makes
+//! to its jobs (and to its queue of commands waiting for a peer to reconnect) is
+//! appended here as one JSON line, and the board is rebuilt from it on startup -
+//! before the first `sync_board`, so the peer is told the true state of every
+//! job as soon as it reconnects.
+//!
+//! The journal is a cache of the board, never the other way round: a failure to
+//! write a record is logged and the board carries on in memory, exactly as it
+//! did before journals existed.
+//!
+//! Records are written and synced by a [`DiskWriter`] thread, so the board lock
+//! is never held across disk I/O. Anything that tells a peer about a change
+//! first waits on [`Journal::synced`] (after releasing the lock), so a peer is
+//! never told about a change that a crash could still lose.
+
+use crate::agent::Peer;
+use crate::command::Command as ControlCommand;
+use crate::diskwriter::{DiskWriter, Synced};
+use crate::domain::Domain;
+use crate::error::Error;
+use crate::job::Job;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// A journal is rewritten down to one record per live job once it holds more
+/// than this many records per live entry. Replay time is proportional to the
+/// file, not to the board, so without compaction a long-lived agent would
+/// replay every job it had ever seen.
+const COMPACTION_RATIO: usize = 4;
+
+/// ...but never compacted below this many records, so a nearly-empty board is
+/// not rewritten on every clean.
+const MIN_RECORDS_BEFORE_COMPACTION: usize = 1_000;
+
+///
+/// One line of a journal. Each is applied in order on replay.
+///
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+enum Record<L: Domain> {
+    /// Always the first line - names the board, so the journals in a directory
+    /// can be restored without having to decode the peer from a file name.
+    Board(Peer),
+
+    /// The job now looks like this (added, or replaced by a newer version)
+    Put(Job<L>),
+
+    /// The job with this id has left the board
+    Remove(Uuid),
+
+    /// A command was queued to be sent when the peer reconnects
+    Queue(ControlCommand<L>),
+
+    /// All queued commands were taken to be sent
+    TakeQueued,
+}
+
+/// Only the header of a journal, read without knowing the `Domain`.
+#[derive(Deserialize)]
+enum Header {
+    Board(Peer),
+}
+
+///
+/// The state recovered from a journal on replay.
+///
+#[derive(Debug)]
+pub(crate) struct Replayed<L: Domain> {
+    pub(crate) jobs: Vec<Job<L>>,
+    pub(crate) queued: Vec<ControlCommand<L>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Journal {
+    peer: Peer,
+    path: PathBuf,
+    writer: Option<DiskWriter>,
+    records: usize,
+}
+
+///
+/// Return the journal file for the board of `peer` in `dir`.
+///
+/// The name is built from the peer's name and zone, so both are checked to
+/// contain only characters that cannot escape `dir` - a peer name comes from
+/// config, but a board is created for whatever peer a job names.
+///
+pub(crate) fn journal_path(dir: &Path, peer: &Peer) -> Result<PathBuf, Error> {
+    let is_safe = |s: &str| {
+        !s.is_empty()
+            && !s.starts_with('.')
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    };
+
+    if !is_safe(peer.name()) || !is_safe(peer.zone()) {
+        return Err(Error::InvalidPeer(format!(
+            "Cannot journal the board for '{}' - its name or zone contains characters \
+             that are not safe in a file name",
+            peer
+        )));
+    }
+
+    Ok(dir.join(format!("{}@{}.jsonl", peer.name(), peer.zone())))
+}
+
+///
+/// Return the peer of every board journal found in `dir`, so that their boards
+/// can be restored at startup even if that peer never reconnects.
+///
+pub(crate) fn peers_in(dir: &Path) -> Result<Vec<Peer>, Error> {
+    let mut peers = Vec::new();
+
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(peers),
+        Err(e) => {
+            return Err(Error::IO(e));
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+            continue;
+        }
+
+        let Ok(file) = std::fs::File::open(&path) else {
+            tracing::warn!("Could not open board journal {}", path.display());
+            continue;
+        };
+
+        let mut first = String::new();
+
+        if std::io::BufReader::new(file).read_line(&mut first).is_err() {
+            continue;
+        }
+
+        match serde_json::from_str::<Header>(first.trim_end()) {
+            Ok(Header::Board(peer)) => peers.push(peer),
+            Err(_) => {
+                tracing::warn!(
+                    "Ignoring {} - it does not start with a board header",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    Ok(peers)
+}
+
+impl Journal {
+    ///
+    /// Open (creating if needed) the journal at `path` for the board of `peer`,
+    /// returning it together with the jobs and queued commands it held.
+    ///
+    /// The file is compacted as part of opening, so a replay never has to read
+    /// more than one restart's worth of history.
+    ///
+    pub(crate) fn open<L: Domain>(path: &Path, peer: &Peer) -> Result<(Self, Replayed<L>), Error> {
+        let mut jobs: HashMap<Uuid, Job<L>> = HashMap::new();
+        let mut order: Vec<Uuid> = Vec::new();
+        let mut queued: Vec<ControlCommand<L>> = Vec::new();
+
+        match std::fs::File::open(path) {
+            Ok(file) => {
+                for (number, line) in std::io::BufReader::new(file).lines().enumerate() {
+                    let line = line.with_context(|| {
+                        format!("Could not read board journal {}", path.display())
+                    })?;
+
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+
+                    // A process killed mid-write leaves a torn final line. That
+                    // record never reached the board anyone relied on, so it is
+                    // skipped rather than failing the whole replay.
+                    let record = match serde_json::from_str::<Record<L>>(&line) {
+                        Ok(record) => record,
+                        Err(e) => {
+                            tracing::warn!(
+                                "Skipping unreadable record on line {} of {}: {}",
+                                number + 1,
+                                path.display(),
+                                e
+                            );
+                            continue;
+                        }
+                    };
+
+                    match record {
+                        Record::Board(p) => {
+                            if p != *peer {
+                                return Err(Error::InvalidBoard(format!(
+                                    "Journal {} belongs to the board for {}, not {}",
+                                    path.display(),
+                                    p,
+                                    peer
+                                )));
+                            }
+                        }
+                        Record::Put(job) => {
+                            let id = job.id();
+
+                            if jobs.insert(id, job).is_none() {
+                                order.push(id);
+                            }
+                        }
+                        Record::Remove(id) => {
+                            jobs.remove(&id);
+                        }
+                        Record::Queue(command) => queued.push(command),
+                        Record::TakeQueued => queued.clear(),
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(Error::IO(e));
+            }
+        }
+
+        // keep the order the jobs first arrived in, so a replayed board syncs
+        // to its peer in the same order the original one would have
+        let jobs: Vec<Job<L>> = order.iter().filter_map(|id| jobs.remove(id)).collect();
+
+        let mut journal = Self {
+            peer: peer.clone(),
+            path: path.to_path_buf(),
+            writer: None,
+            records: 0,
+        };
+
+        // Jobs name users and projects, so the journal is kept owner-only, and
+        // written through a rename so a crash mid-compaction leaves either the
+        // old journal or the new one - never half of each. This first rewrite
+        // happens before the board is in use, so it is done here directly.
+        let contents = journal.contents(jobs.iter(), queued.iter())?;
+        paddington::config::write_secret_file(path, &contents)?;
+
+        let file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Could not open board journal {}", path.display()))?;
+
+        journal.writer = Some(DiskWriter::start(path, file, "board journal", true)?);
+
+        Ok((journal, Replayed { jobs, queued }))
+    }
+
+    ///
+    /// Record that `job` is now on the board in this state
+    ///
+    pub(crate) fn put<L: Domain>(&mut self, job: &Job<L>) {
+        self.append(&Record::Put(job.clone()));
+    }
+
+    ///
+    /// Record that the job with `id` has left the board
+    ///
+    pub(crate) fn remove<L: Domain>(&mut self, id: &Uuid) {
+        self.append(&Record::<L>::Remove(*id));
+    }
+
+    ///
+    /// Record that `command` has been queued for the peer
+    ///
+    pub(crate) fn queue<L: Domain>(&mut self, command: &ControlCommand<L>) {
+        self.append(&Record::Queue(command.clone()));
+    }
+
+    ///
+    /// Record that the queued commands have all been taken
+    ///
+    pub(crate) fn take_queued<L: Domain>(&mut self) {
+        self.append(&Record::<L>::TakeQueued);
+    }
+
+    ///
+    /// Return whether the journal has grown enough, relative to the `live`
+    /// number of jobs and queued commands on the board, to be worth compacting
+    ///
+    pub(crate) fn needs_compaction(&self, live: usize) -> bool {
+        self.records > MIN_RECORDS_BEFORE_COMPACTION.max(live.saturating_mul(COMPACTION_RATIO))
+    }
+
+    ///
+    /// Rewrite the journal so that it holds exactly the passed jobs and
+    /// queued commands
+    ///
+    pub(crate) fn compact<'a, L: Domain>(
+        &mut self,
+        jobs: impl Iterator<Item = &'a Job<L>>,
+        queued: impl Iterator<Item = &'a ControlCommand<L>>,
+    ) {
+        let Some(writer) = self.writer.as_ref() else {
+            return;
+        };
+
+        match self.contents(jobs, queued) {
+            Ok(contents) => {
+                self.records = contents.lines().count();
+                writer.replace(contents);
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Could not compact board journal {}: {}",
+                    self.path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    ///
+    /// Return a `Synced` that resolves once every record written so far
+    /// is on disk
+    ///
+    pub(crate) fn synced(&self) -> Synced {
+        match self.writer.as_ref() {
+            Some(writer) => writer.synced(),
+            None => Synced::none(),
+        }
+    }
+
+    fn contents<'a, L: Domain>(
+        &self,
+        jobs: impl Iterator<Item = &'a Job<L>>,
+        queued: impl Iterator<Item = &'a ControlCommand<L>>,
+    ) -> Result<String, Error> {
+        let mut contents = String::new();
+
+        let mut push = |record: &Record<L>| -> Result<(), Error> {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+            Ok(())
+        };
+
+        push(&Record::Board(self.peer.clone()))?;
+
+        for job in jobs {
+            push(&Record::Put(job.clone()))?;
+        }
+
+        for command in queued {
+            push(&Record::Queue(command.clone()))?;
+        }
+
+        Ok(contents)
+    }
+
+    fn append<L: Domain>(&mut self, record: &Record<L>) {
+        let Some(writer) = self.writer.as_ref() else {
+            return;
+        };
+
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line + "\n",
+            Err(e) => {
+                tracing::error!("Could not serialise a board journal record: {}", e);
+                return;
+            }
+        };
+
+        // One line per record, so a record is either wholly in the file or
+        // (after a crash) a torn final line that replay skips. The writer
+        // syncs because the point of the journal is to survive the machine
+        // going down too.
+        writer.append(line);
+        self.records += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_domain::TestDomain;
+    use std::io::Write;
+
+    fn job(command: &str) -> Job<TestDomain> {
+        Job::parse(command, false)
+            .and_then(|j| j.pending())
+            .unwrap_or_else(|e| unreachable!("job: {:?}", e))
+    }
+
+    // jobs are journaled with whole-second timestamps, so this is the
+    // job as it comes back from a replay
+    fn journaled(job: &Job<TestDomain>) -> Job<TestDomain> {
+        serde_json::to_string(job)
+            .and_then(|json| serde_json::from_str(&json))
+            .unwrap_or_else(|e| unreachable!("round-trip: {:?}", e))
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("op-journal-{}-{}", name, Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap_or_else(|e| unreachable!("temp dir: {:?}", e));
+        dir
+    }
+
+    #[test]
+    fn test_journal_replays_puts_and_removes() {
+        let dir = temp_dir("replay");
+        let peer = Peer::new("cluster", "default");
+        let path = journal_path(&dir, &peer).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let first = job("portal.cluster add_user a.b.portal");
+        let second = job("portal.cluster add_user c.d.portal");
+        let finished = second
+            .running(None)
+            .and_then(|j| j.completed_none())
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        {
+            let (mut journal, replayed) = Journal::open::<TestDomain>(&path, &peer)
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+            assert!(replayed.jobs.is_empty());
+
+            journal.put(&first);
+            journal.put(&second);
+            journal.put(&finished);
+            journal.remove::<TestDomain>(&first.id());
+            journal.synced().wait_blocking();
+        }
+
+        let (_, replayed) =
+            Journal::open::<TestDomain>(&path, &peer).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        // only the newest version of the surviving job comes back
+        assert_eq!(replayed.jobs, vec![journaled(&finished)]);
+
+        // ...and the header is what lets a directory be restored
+        assert_eq!(
+            peers_in(&dir).unwrap_or_else(|e| unreachable!("{:?}", e)),
+            vec![peer]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_journal_skips_a_torn_final_record() {
+        let dir = temp_dir("torn");
+        let peer = Peer::new("cluster", "default");
+        let path = journal_path(&dir, &peer).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        let kept = job("portal.cluster add_user a.b.portal");
+
+        {
+            let (mut journal, _) = Journal::open::<TestDomain>(&path, &peer)
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+            journal.put(&kept);
+            journal.synced().wait_blocking();
+        }
+
+        // simulate the process dying part-way through writing a record
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap_or_else(|e| unreachable!("{:?}", e));
+        let _ = file.write_all(b"{\"Put\":{\"id\":\"");
+
+        let (_, replayed) =
+            Journal::open::<TestDomain>(&path, &peer).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(replayed.jobs, vec![journaled(&kept)]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_journal_path_rejects_unsafe_peers() {
+        let dir = PathBuf::from("/tmp");
+
+        assert!(journal_path(&dir, &Peer::new("cluster", "default")).is_ok());
+        assert!(journal_path(&dir, &Peer::new("../etc", "default")).is_err());
+        assert!(journal_path(&dir, &Peer::new("cluster", "a/b")).is_err());
+        assert!(journal_path(&dir, &Peer::new("", "default")).is_err());
+    }
+}
@@ -10,11 +10,13 @@ mod bridgeboard;
 mod bridgestate;
 mod control_message;
 mod custom;
+mod diskwriter;
 mod error;
 mod filesystem;
 mod handler;
 mod instance;
 mod jobtiming;
+mod journal;
 mod notificationstate;
 mod platform;
 mod portal;
@@ -26,6 +28,7 @@ mod virtual_agent;
 
 // public API
 pub mod agent;
+pub mod audit;
 pub mod board;
 pub mod bridge;
 pub mod command;
@@ -39,12 +42,15 @@ pub mod erased;
 pub mod health;
 pub mod job;
 pub mod joberror;
+pub mod jobhistory;
+pub use paddington::metrics;
 pub mod named;
 pub mod notification;
 pub mod portal_identifier;
 pub mod portalroutes;
 pub mod runnable;
 pub mod state;
+pub mod telemetry;
 #[cfg(test)]
 mod test_domain;
 pub mod validate;
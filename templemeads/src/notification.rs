@@ -9,6 +9,7 @@ use crate::diagnostics;
 use crate::domain::Domain;
 use crate::error::Error;
 use crate::handler::invoke_notify_runner;
+use crate::telemetry;
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -31,6 +32,10 @@ pub struct Notification<L: Domain> {
     /// The domain's version, alongside `domain`.
     #[serde(default)]
     domain_version: Option<String>,
+    /// The W3C `traceparent` of the span this notification was sent from,
+    /// if any - see `Job::traceparent`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    traceparent: Option<String>,
 }
 
 impl<L: Domain> Notification<L> {
@@ -41,6 +46,7 @@ impl<L: Domain> Notification<L> {
             event,
             domain: Some(L::name().to_string()),
             domain_version: Some(L::version().to_string()),
+            traceparent: telemetry::current().map(|context| context.traceparent()),
         }
     }
 
@@ -59,6 +65,7 @@ impl<L: Domain> Notification<L> {
             event,
             domain: Some(L::name().to_string()),
             domain_version: Some(L::version().to_string()),
+            traceparent: telemetry::current().map(|context| context.traceparent()),
         })
     }
 
@@ -83,6 +90,12 @@ impl<L: Domain> Notification<L> {
     pub fn domain_version(&self) -> Option<&str> {
         self.domain_version.as_deref()
     }
+
+    /// The W3C `traceparent` of the span this notification was sent from,
+    /// if any.
+    pub fn traceparent(&self) -> Option<&str> {
+        self.traceparent.as_deref()
+    }
 }
 
 impl<L: Domain> fmt::Display for Notification<L> {
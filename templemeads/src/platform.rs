@@ -31,6 +31,10 @@ pub async fn run<L: Domain>(config: Config) -> Result<(), Error> {
     // - see docs/specifications/security-review-2.md (finding R34).
     set_verify_portal_ownership::<L>(true).await?;
 
+    // restore any journalled job boards before peers can connect
+    config.enable_board_journal::<L>().await?;
+    config.enable_audit_log()?;
+
     // run the Provider OpenPortal agent
     run_with_relay::<L>(config.service()).await?;
 
@@ -80,6 +80,10 @@ pub async fn run<L: Domain>(config: Config, runner: AsyncRunnable<L>) -> Result<
         return Ok(());
     }
 
+    // restore any journalled job boards before peers can connect
+    config.enable_board_journal::<L>().await?;
+    config.enable_audit_log()?;
+
     // run the Portal OpenPortal agent
     run_with_relay::<L>(config.service()).await?;
 
@@ -315,6 +315,18 @@ impl RouteTable {
             .collect()
     }
 
+    fn all_routes(&self) -> Vec<PortalRoute> {
+        self.routes
+            .iter()
+            .filter(|(key, _)| !self.collided.contains(*key))
+            .filter_map(|((_, portal), entry)| {
+                PortalIdentifier::parse(portal)
+                    .ok()
+                    .map(|portal| PortalRoute::new(&portal, &entry.route))
+            })
+            .collect()
+    }
+
     fn expected_route(&self, zone: &str, portal: &str) -> Option<Destination> {
         self.routes.get(&key(zone, portal)).map(|e| e.route.clone())
     }
@@ -373,6 +385,14 @@ pub async fn routes_for_peer(peer: &Peer) -> Vec<PortalRoute> {
     ROUTES.read().await.routes_for_peer(peer)
 }
 
+///
+/// The route by which every portal we know of reaches us, in any zone.
+/// Collided portals are left out, as we will not route for them.
+///
+pub async fn all_routes() -> Vec<PortalRoute> {
+    ROUTES.read().await.all_routes()
+}
+
 /// The route by which `portal` should reach us in `zone`, if we know one.
 pub async fn expected_route(zone: &str, portal: &str) -> Option<Destination> {
     ROUTES.read().await.expected_route(zone, portal)
@@ -539,6 +559,13 @@ mod tests {
             table.expected_route("default", "other"),
             Some(dest("other.aip1.clusters"))
         );
+
+        // ...and only the unaffected portal is offered to anything that
+        // wants to reach every portal we know of
+        assert_eq!(
+            table.all_routes(),
+            vec![advert("other", "other.aip1.clusters")]
+        );
     }
 
     #[test]
@@ -83,6 +83,10 @@ where
         return Ok(());
     }
 
+    // restore any journalled job boards before peers can connect
+    config.enable_board_journal::<L>().await?;
+    config.enable_audit_log()?;
+
     // run the OpenPortal agent
     run_with_relay::<L>(config.service()).await?;
 
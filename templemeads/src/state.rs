@@ -20,9 +20,14 @@ const MAX_BOARDS: usize = 1_000;
 use anyhow::Result;
 use std::any::Any;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock};
 use tokio::sync::RwLock;
 
+/// The directory holding each board's journal, if boards are durable. Set
+/// once at startup by [`enable_journal`], and read whenever a board is created.
+static JOURNAL_DIR: OnceLock<PathBuf> = OnceLock::new();
+
 struct States<L: Domain> {
     states: HashMap<agent::Peer, Arc<State<L>>>,
 }
@@ -149,6 +154,12 @@ async fn clean_boards<L: Domain>() {
 }
 
 async fn _force_get<L: Domain>(peer: agent::Peer) -> Result<Arc<State<L>>, Error> {
+    // read before taking the lock - replaying a journal needs our own name
+    let my_name = match JOURNAL_DIR.get() {
+        Some(_) => Some(agent::name().await),
+        None => None,
+    };
+
     let states = states::<L>()?;
     let mut states = states.write().await;
 
@@ -172,11 +183,14 @@ async fn _force_get<L: Domain>(peer: agent::Peer) -> Result<Arc<State<L>>, Error
         )));
     }
 
-    Ok(states
-        .states
-        .entry(peer.clone())
-        .or_insert(Arc::new(State::new(peer.clone())))
-        .clone())
+    if let Some(state) = states.states.get(&peer) {
+        return Ok(state.clone());
+    }
+
+    let state = Arc::new(State::open(peer.clone(), my_name.as_deref()));
+    states.states.insert(peer, state.clone());
+
+    Ok(state)
 }
 
 async fn _get<L: Domain>(peer: &agent::Peer) -> Result<Option<Arc<State<L>>>, Error> {
@@ -205,11 +219,88 @@ impl<L: Domain> State<L> {
         }
     }
 
+    ///
+    /// Create the state for `peer`, attaching its board to its journal if
+    /// boards are durable. A board whose journal cannot be opened still works,
+    /// in memory only, exactly as it would with journals disabled.
+    ///
+    fn open(peer: agent::Peer, my_name: Option<&str>) -> Self {
+        let mut board = board::Board::new(&peer);
+
+        if let (Some(dir), Some(my_name)) = (JOURNAL_DIR.get(), my_name) {
+            match board.attach_journal(dir, my_name) {
+                Ok(0) => {}
+                Ok(num_jobs) => {
+                    tracing::info!("Restored {} job(s) to the board for {}", num_jobs, peer);
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Could not open the journal for the board of {} - its jobs will \
+                         not survive a restart: {}",
+                        peer,
+                        e
+                    );
+                }
+            }
+        }
+
+        tracing::debug!("Creating new board for agent {}", peer);
+
+        Self {
+            board: Arc::new(RwLock::new(board)),
+        }
+    }
+
     pub async fn board(&self) -> Arc<RwLock<board::Board<L>>> {
         self.board.clone()
     }
 }
 
+///
+/// Make every board in this process durable, journalling it to `dir`, and
+/// restore every board that already has a journal there.
+///
+/// This must be called once at startup, after this agent's own name is known
+/// and before any peer connects - the restored boards are what the first
+/// `sync_board` with each peer sends, which is how a job that was in flight
+/// when the agent went down gets reported rather than silently forgotten.
+///
+pub(crate) async fn enable_journal<L: Domain>(dir: &Path) -> Result<(), Error> {
+    std::fs::create_dir_all(dir).map_err(|e| {
+        Error::InvalidConfig(format!(
+            "Could not create the board journal directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+
+    if JOURNAL_DIR.set(dir.to_path_buf()).is_err() {
+        return Err(Error::InvalidState(
+            "Board journals have already been enabled".to_string(),
+        ));
+    }
+
+    let peers = crate::journal::peers_in(dir)?;
+
+    tracing::info!(
+        "Board journals enabled in {} - restoring {} board(s)",
+        dir.display(),
+        peers.len()
+    );
+
+    for peer in peers {
+        get::<L>(&peer).await?;
+    }
+
+    Ok(())
+}
+
 ///
 /// Collect aggregate job statistics from all boards
 ///
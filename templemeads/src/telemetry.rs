@@ -0,0 +1,679 @@
+// SPDX-FileCopyrightText: © 2026 Christopher Woods <Christopher.Woods@bristol.ac.uk>
+// SPDX-License-Identifier: MIT
+
+//! W3C trace context propagation, and an optional OTLP span exporter, so a
+//! request can be followed through every agent it passes through.
+//!
+//! A `Job` or `Notification` carries the `traceparent` of the span that sent
+//! it. The handler runs each runnable inside a span that is a child of that
+//! one, and makes it the task's current context, so any `Job` created and
+//! `put` by the runnable continues the same trace on the next agent. Each
+//! agent only ever exports its own spans; they are stitched together by the
+//! collector from their shared trace id.
+//!
+//! Propagation always happens. Spans are only exported when an exporter is
+//! configured, either from the environment by `initialise_from_env` (which
+//! `config::initialise_tracing` calls) or explicitly:
+//!
+//! * `OTEL_EXPORTER_OTLP_ENDPOINT` - a local collector's OTLP/HTTP endpoint,
+//!   e.g. `http://localhost:4318`. Spans are POSTed as OTLP JSON to
+//!   `/v1/traces`, over `http` or `https`. This is meant for a collector on
+//!   the same host or a trusted network, which then forwards on however the
+//!   site wants.
+//! * `OPENPORTAL_OTLP_FILE` - a file to append OTLP JSON to, one export
+//!   request per line, as read by the collector's `otlpjsonfile` receiver.
+//! * `OTEL_SERVICE_NAME` - the service name to report, defaulting to the
+//!   name of the executable (e.g. `op-freeipa`).
+
+use crate::error::Error;
+
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Export as soon as this many spans are waiting
+const BATCH_SIZE: usize = 256;
+
+/// Export whatever is waiting at least this often
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Spans are dropped, not queued without limit, if they cannot be exported
+/// as fast as they are made
+const MAX_BUFFERED_SPANS: usize = 4096;
+
+/// How long to give the collector to accept a batch
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+///
+/// The W3C trace context of one span - the trace it belongs to and its own
+/// id within that trace
+///
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    sampled: bool,
+}
+
+impl std::fmt::Debug for TraceContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.traceparent())
+    }
+}
+
+impl std::fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.traceparent())
+    }
+}
+
+fn new_span_id() -> [u8; 8] {
+    // the version nibble of a v4 uuid sits in the first half, so this is
+    // never all zeroes (which W3C reserves as invalid)
+    Uuid::new_v4().as_u64_pair().0.to_be_bytes()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != 2 * N || hex.bytes().any(|c| !matches!(c, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+
+    let mut bytes = [0u8; N];
+
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+
+    Some(bytes)
+}
+
+impl TraceContext {
+    ///
+    /// The context of the first span of a new trace
+    ///
+    fn new_root() -> Self {
+        Self {
+            trace_id: *Uuid::new_v4().as_bytes(),
+            span_id: new_span_id(),
+            sampled: true,
+        }
+    }
+
+    ///
+    /// The context of a new span within the same trace as this one
+    ///
+    fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            span_id: new_span_id(),
+            sampled: self.sampled,
+        }
+    }
+
+    ///
+    /// Parse a `traceparent` header value, e.g.
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`
+    ///
+    pub fn parse(traceparent: &str) -> Result<Self, Error> {
+        let invalid = || Error::Parse(format!("Invalid traceparent: '{}'", traceparent));
+
+        let mut parts = traceparent.trim().split('-');
+
+        let version = parts.next().and_then(from_hex::<1>).ok_or_else(invalid)?;
+        let trace_id = parts.next().and_then(from_hex::<16>).ok_or_else(invalid)?;
+        let span_id = parts.next().and_then(from_hex::<8>).ok_or_else(invalid)?;
+        let flags = parts.next().and_then(from_hex::<1>).ok_or_else(invalid)?;
+
+        // version 00 has exactly four fields, later versions may add more,
+        // and ff is forbidden
+        if version == [0xff]
+            || (version == [0x00] && parts.next().is_some())
+            || trace_id == [0; 16]
+            || span_id == [0; 8]
+        {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            trace_id,
+            span_id,
+            sampled: flags.first().is_some_and(|f| f & 0x01 == 0x01),
+        })
+    }
+
+    ///
+    /// This context as a version 00 `traceparent` value
+    ///
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{}",
+            to_hex(&self.trace_id),
+            to_hex(&self.span_id),
+            if self.sampled { "01" } else { "00" }
+        )
+    }
+
+    pub fn trace_id(&self) -> String {
+        to_hex(&self.trace_id)
+    }
+
+    pub fn span_id(&self) -> String {
+        to_hex(&self.span_id)
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+///
+/// The context of the span the current task is running in, if any
+///
+pub fn current() -> Option<TraceContext> {
+    CURRENT.try_with(|context| *context).ok()
+}
+
+///
+/// Run `future` with `context` as its current trace context
+///
+pub async fn scope<F: Future>(context: TraceContext, future: F) -> F::Output {
+    CURRENT.scope(context, future).await
+}
+
+///
+/// Parse a `traceparent` that arrived from a peer, ignoring (with a debug
+/// message) one that is malformed rather than failing what it came with
+///
+pub fn parse_from_peer(traceparent: Option<&str>) -> Option<TraceContext> {
+    traceparent.and_then(|t| match TraceContext::parse(t) {
+        Ok(context) => Some(context),
+        Err(e) => {
+            tracing::debug!("Ignoring trace context from peer: {}", e);
+            None
+        }
+    })
+}
+
+fn unix_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+///
+/// One unit of work within a trace. Dropping a span without calling `end`
+/// simply does not export it.
+///
+#[derive(Debug)]
+pub struct Span {
+    name: String,
+    context: TraceContext,
+    parent_span_id: Option<[u8; 8]>,
+    start: u128,
+    attributes: Vec<(String, String)>,
+}
+
+impl Span {
+    ///
+    /// Start a span called `name`, as a child of `parent`, or as the first
+    /// span of a new trace if there is no parent
+    ///
+    pub fn start(name: &str, parent: Option<TraceContext>) -> Self {
+        let (context, parent_span_id) = match parent {
+            Some(parent) => (parent.child(), Some(parent.span_id)),
+            None => (TraceContext::new_root(), None),
+        };
+
+        Self {
+            name: name.to_string(),
+            context,
+            parent_span_id,
+            start: unix_nanos(),
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn context(&self) -> TraceContext {
+        self.context
+    }
+
+    pub fn set_attribute(&mut self, key: &str, value: &str) {
+        self.attributes.push((key.to_string(), value.to_string()));
+    }
+
+    ///
+    /// Finish the span, recording `error` as its status if it failed, and
+    /// queue it for export
+    ///
+    pub fn end(self, error: Option<&str>) {
+        if !self.context.sampled || !is_enabled() {
+            return;
+        }
+
+        let mut span = json!({
+            "traceId": to_hex(&self.context.trace_id),
+            "spanId": to_hex(&self.context.span_id),
+            "name": self.name,
+            // SPAN_KIND_INTERNAL
+            "kind": 1,
+            "startTimeUnixNano": self.start.to_string(),
+            "endTimeUnixNano": unix_nanos().to_string(),
+            "attributes": self.attributes.iter().map(|(key, value)| json!({
+                "key": key,
+                "value": { "stringValue": value },
+            })).collect::<Vec<_>>(),
+            "status": match error {
+                // STATUS_CODE_ERROR
+                Some(message) => json!({ "code": 2, "message": message }),
+                // STATUS_CODE_OK
+                None => json!({ "code": 1 }),
+            },
+        });
+
+        if let (Some(parent), Some(object)) = (self.parent_span_id, span.as_object_mut()) {
+            object.insert("parentSpanId".to_string(), json!(to_hex(&parent)));
+        }
+
+        record(span);
+    }
+}
+
+///
+/// Where finished spans are sent
+///
+#[derive(Debug, Clone)]
+enum Exporter {
+    File(PathBuf),
+    Collector(url::Url),
+}
+
+#[derive(Debug, Default)]
+struct ExportState {
+    exporter: Option<Exporter>,
+    service_name: String,
+    buffer: Vec<serde_json::Value>,
+    flusher_started: bool,
+}
+
+static EXPORT: Lazy<Mutex<ExportState>> = Lazy::new(|| Mutex::new(ExportState::default()));
+
+fn default_service_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "openportal".to_string())
+}
+
+fn set_exporter(exporter: Exporter) -> Result<(), Error> {
+    let mut state = EXPORT
+        .lock()
+        .map_err(|e| Error::Bug(format!("Error getting telemetry lock: {}", e)))?;
+
+    state.exporter = Some(exporter);
+
+    if state.service_name.is_empty() {
+        state.service_name =
+            std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| default_service_name());
+    }
+
+    Ok(())
+}
+
+///
+/// Append finished spans, as OTLP JSON, to the file at `path`
+///
+pub fn export_to_file(path: &Path) -> Result<(), Error> {
+    // fail now, rather than when the first span is exported
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    tracing::info!("Exporting trace spans to {}", path.display());
+
+    set_exporter(Exporter::File(path.to_path_buf()))
+}
+
+///
+/// Send finished spans to the OTLP/HTTP collector at `endpoint` (e.g.
+/// `http://localhost:4318`)
+///
+pub fn export_to_collector(endpoint: &str) -> Result<(), Error> {
+    let url = collector_url(endpoint)?;
+
+    tracing::info!(
+        "Exporting trace spans to the OTLP collector at {}",
+        endpoint
+    );
+
+    set_exporter(Exporter::Collector(url))
+}
+
+///
+/// The URL that spans are POSTed to for the collector at `endpoint`
+///
+fn collector_url(endpoint: &str) -> Result<url::Url, Error> {
+    let mut url = url::Url::parse(endpoint)?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(Error::Misconfigured(format!(
+            "Only an http:// or https:// OTLP collector endpoint is supported, not '{}'",
+            endpoint
+        )));
+    }
+
+    if url.host_str().is_none() {
+        return Err(Error::Misconfigured(format!("No host in '{}'", endpoint)));
+    }
+
+    if url.port().is_none() && url.scheme() == "http" {
+        url.set_port(Some(4318))
+            .map_err(|_| Error::Misconfigured(format!("Invalid endpoint '{}'", endpoint)))?;
+    }
+
+    let path = format!("{}/v1/traces", url.path().trim_end_matches('/'));
+    url.set_path(&path);
+
+    Ok(url)
+}
+
+///
+/// Configure the span exporter from the environment (see the module
+/// documentation). Exporting is off if neither variable is set.
+///
+pub fn initialise_from_env() {
+    let result = match (
+        std::env::var("OPENPORTAL_OTLP_FILE"),
+        std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT"),
+    ) {
+        (Ok(file), _) => export_to_file(Path::new(&file)),
+        (_, Ok(endpoint)) => export_to_collector(&endpoint),
+        _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Trace spans will not be exported: {}", e);
+    }
+}
+
+fn is_enabled() -> bool {
+    EXPORT
+        .lock()
+        .map(|state| state.exporter.is_some())
+        .unwrap_or(false)
+}
+
+fn record(span: serde_json::Value) {
+    let mut state = match EXPORT.lock() {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::error!("Error getting telemetry lock: {}", e);
+            return;
+        }
+    };
+
+    if state.buffer.len() >= MAX_BUFFERED_SPANS {
+        tracing::warn!("Dropping a trace span - too many are waiting to be exported");
+        return;
+    }
+
+    state.buffer.push(span);
+
+    if !state.flusher_started && tokio::runtime::Handle::try_current().is_ok() {
+        state.flusher_started = true;
+
+        tokio::spawn(async {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+
+            loop {
+                interval.tick().await;
+                flush().await;
+            }
+        });
+    }
+
+    if state.buffer.len() >= BATCH_SIZE && tokio::runtime::Handle::try_current().is_ok() {
+        drop(state);
+        tokio::spawn(flush());
+    }
+}
+
+///
+/// Export every span that is waiting
+///
+pub async fn flush() {
+    let (exporter, request) = {
+        let mut state = match EXPORT.lock() {
+            Ok(state) => state,
+            Err(e) => {
+                tracing::error!("Error getting telemetry lock: {}", e);
+                return;
+            }
+        };
+
+        let Some(exporter) = state.exporter.clone() else {
+            return;
+        };
+
+        if state.buffer.is_empty() {
+            return;
+        }
+
+        let spans = std::mem::take(&mut state.buffer);
+
+        (exporter, export_request(&state.service_name, spans))
+    };
+
+    let result = match &exporter {
+        Exporter::File(path) => append_line(path, &request),
+        Exporter::Collector(url) => {
+            match tokio::time::timeout(EXPORT_TIMEOUT, post(url, request)).await {
+                Ok(result) => result,
+                Err(_) => Err(Error::Timeout(format!(
+                    "Timed out exporting spans to {}",
+                    url
+                ))),
+            }
+        }
+    };
+
+    if let Err(e) = result {
+        tracing::warn!("Could not export trace spans: {}", e);
+    }
+}
+
+///
+/// An OTLP `ExportTraceServiceRequest`, in its JSON encoding
+///
+fn export_request(service_name: &str, spans: Vec<serde_json::Value>) -> String {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [{
+                    "key": "service.name",
+                    "value": { "stringValue": service_name },
+                }],
+            },
+            "scopeSpans": [{
+                "scope": {
+                    "name": "templemeads",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "spans": spans,
+            }],
+        }],
+    })
+    .to_string()
+}
+
+fn append_line(path: &Path, line: &str) -> Result<(), Error> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    writeln!(file, "{}", line)?;
+
+    Ok(())
+}
+
+///
+/// POST `body` to the collector at `url`
+///
+async fn post(url: &url::Url, body: String) -> Result<(), Error> {
+    let response = reqwest::Client::builder()
+        .timeout(EXPORT_TIMEOUT)
+        .build()
+        .map_err(|e| Error::Call(format!("Could not create an HTTP client: {}", e)))?
+        .post(url.clone())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| {
+            Error::Call(format!(
+                "Could not send spans to the OTLP collector at {}: {}",
+                url, e
+            ))
+        })?;
+
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        status => Err(Error::Call(format!(
+            "The OTLP collector at {} replied '{}'",
+            url, status
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_round_trips() {
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+        let context = TraceContext::parse(traceparent).unwrap_or_else(|e| unreachable!("{:?}", e));
+
+        assert_eq!(context.traceparent(), traceparent);
+        assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id(), "00f067aa0ba902b7");
+
+        let child = Span::start("child", Some(context));
+        assert_eq!(child.context().trace_id(), context.trace_id());
+        assert_ne!(child.context().span_id(), context.span_id());
+        assert_eq!(child.parent_span_id, Some(context.span_id));
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert!(TraceContext::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scope_sets_the_current_context() {
+        assert!(current().is_none());
+
+        let span = Span::start("outer", None);
+        let context = span.context();
+
+        let inner = scope(context, async { current() }).await;
+
+        assert_eq!(inner, Some(context));
+        assert!(current().is_none());
+    }
+
+    #[test]
+    fn test_file_exporter_appends_one_request_per_line() {
+        let path = std::env::temp_dir().join(format!("op-otlp-{}.jsonl", Uuid::new_v4()));
+
+        let span = |name: &str| {
+            json!({
+                "traceId": "4bf92f3577b34da6a3ce929d0e0e4736",
+                "spanId": "00f067aa0ba902b7",
+                "name": name,
+            })
+        };
+
+        for name in ["first", "second"] {
+            append_line(&path, &export_request("op-test", vec![span(name)]))
+                .unwrap_or_else(|e| unreachable!("{:?}", e));
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap_or_else(|e| unreachable!("{:?}", e));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(contents.ends_with('\n'));
+
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap_or_else(|e| unreachable!("{:?}", e)))
+            .collect();
+
+        let expected = |name: &str| {
+            json!({
+                "resourceSpans": [{
+                    "resource": {
+                        "attributes": [{
+                            "key": "service.name",
+                            "value": { "stringValue": "op-test" },
+                        }],
+                    },
+                    "scopeSpans": [{
+                        "scope": {
+                            "name": "templemeads",
+                            "version": env!("CARGO_PKG_VERSION"),
+                        },
+                        "spans": [span(name)],
+                    }],
+                }],
+            })
+        };
+
+        assert_eq!(lines, vec![expected("first"), expected("second")]);
+    }
+
+    #[test]
+    fn test_collector_url() {
+        for (endpoint, url) in [
+            ("http://localhost", "http://localhost:4318/v1/traces"),
+            ("http://localhost:4318", "http://localhost:4318/v1/traces"),
+            (
+                "http://[::1]:9000/otlp/",
+                "http://[::1]:9000/otlp/v1/traces",
+            ),
+            (
+                "https://otel.example.com",
+                "https://otel.example.com/v1/traces",
+            ),
+        ] {
+            assert_eq!(
+                collector_url(endpoint)
+                    .unwrap_or_else(|e| unreachable!("{:?}", e))
+                    .as_str(),
+                url
+            );
+        }
+
+        assert!(collector_url("ftp://localhost:4318").is_err());
+        assert!(collector_url("localhost:4318").is_err());
+    }
+}